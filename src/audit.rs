@@ -0,0 +1,199 @@
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::database::queries;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::tagger::{self, id3_handler, types::{AudioMetadata, TaggerConfig}};
+
+/// A file modified this long after hvtag tagged it was edited by something else.
+const EDIT_GRACE_SECS: i64 = 60;
+
+/// Findings for one work; a work can be in several categories at once.
+#[derive(Debug, Default)]
+struct WorkAudit {
+    files: usize,
+    untagged: usize,
+    stale: usize,
+    edited: usize,
+    /// Tag fields that differ from the database, over all stale/edited files
+    fields: Vec<&'static str>,
+}
+
+impl WorkAudit {
+    fn labels(&self) -> Vec<&'static str> {
+        let mut labels = Vec::new();
+        if self.untagged > 0 {
+            labels.push(if self.untagged == self.files { "untagged" } else { "partially tagged" });
+        }
+        if self.stale > 0 {
+            labels.push("stale");
+        }
+        if self.edited > 0 {
+            labels.push("manually edited");
+        }
+        labels
+    }
+}
+
+/// Work-level tag fields whose value in the file differs from what the database would write.
+/// Per-file fields (title, track number) are not compared. Multi-value fields are compared in
+/// their joined form, so every artist frame style reads back the same.
+fn diff_tags(expected: &AudioMetadata, actual: &AudioMetadata, separator: &str) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if expected.album != actual.album {
+        fields.push("album");
+    }
+    if expected.album_artist != actual.album_artist {
+        fields.push("album artist");
+    }
+    if expected.artists.join(separator) != actual.artists.join(separator) {
+        fields.push("artist");
+    }
+    if expected.genre.join(separator) != actual.genre.join(separator) {
+        fields.push("genre");
+    }
+    fields
+}
+
+/// `--audit`: read back the tags of every MP3 of tagged works and compare them with what the
+/// database says should be there. Works are reported as untagged/partially tagged (files without
+/// tags), stale (files unchanged since tagging but the database moved on, e.g. a CV or circle
+/// rename: run --full-retag --all) or manually edited (files rewritten by another tool after tagging).
+/// Read-only: nothing is written to the files or the database.
+pub fn run_audit_workflow(conn: &Connection, config: &TaggerConfig) -> Result<(), HvtError> {
+    let works = queries::get_tagged_works_with_paths(conn)?;
+    if works.is_empty() {
+        info!("No tagged works in database");
+        return Ok(());
+    }
+
+    info!("=== AUDIT: {} work(s) ===", works.len());
+
+    let pb = crate::create_progress_bar(works.len() as u64);
+    let mut findings: Vec<(RJCode, WorkAudit)> = Vec::new();
+    let mut missing_folders = 0usize;
+
+    for (rjcode, folder_path) in &works {
+        pb.set_message(format!("Auditing {}", rjcode));
+        pb.inc(1);
+
+        let folder = Path::new(folder_path);
+        if !folder.is_dir() {
+            warn!("Folder not found for {}: {}", rjcode, folder_path.display());
+            missing_folders += 1;
+            continue;
+        }
+
+        let expected = tagger::fetch_metadata_from_db(conn, rjcode, config)?;
+        let audit = audit_work(conn, folder, &expected, config)?;
+        if !audit.labels().is_empty() {
+            pb.println(format!("{} ✗ ({})", rjcode, audit.labels().join(", ")));
+            findings.push((rjcode.clone(), audit));
+        }
+    }
+
+    pb.finish_and_clear();
+
+    info!("\n=== AUDIT COMPLETE ===");
+    info!(
+        "Audited: {} work(s) | With problems: {} | Missing folders: {}",
+        works.len() - missing_folders, findings.len(), missing_folders
+    );
+    for (rjcode, audit) in &findings {
+        let mut line = format!("{} [{}] {} file(s)", rjcode, audit.labels().join(", "), audit.files);
+        if audit.untagged > 0 {
+            line.push_str(&format!(", {} untagged", audit.untagged));
+        }
+        if audit.stale + audit.edited > 0 {
+            line.push_str(&format!(", {} differ ({})", audit.stale + audit.edited, audit.fields.join(", ")));
+        }
+        warn!("{}", line);
+    }
+
+    Ok(())
+}
+
+fn audit_work(
+    conn: &Connection,
+    folder: &Path,
+    expected: &AudioMetadata,
+    config: &TaggerConfig,
+) -> Result<WorkAudit, HvtError> {
+    let mut audit = WorkAudit::default();
+
+    for file in tagger::list_audio_files(folder)? {
+        let is_mp3 = file.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("mp3"));
+        if !is_mp3 {
+            continue;
+        }
+        audit.files += 1;
+
+        let actual = match id3_handler::read_id3_tags(&file, &config.tag_separator)? {
+            Some(actual) if !actual.album.is_empty() => actual,
+            _ => {
+                audit.untagged += 1;
+                continue;
+            }
+        };
+
+        let fields = diff_tags(expected, &actual, &config.tag_separator);
+        if fields.is_empty() {
+            continue;
+        }
+        for field in fields {
+            if !audit.fields.contains(&field) {
+                audit.fields.push(field);
+            }
+        }
+
+        // Modified after hvtag last wrote it -> someone else edited the tags
+        let tag_time = queries::get_file_tag_time(conn, &file)?;
+        let modified = std::fs::metadata(&file)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64);
+        match (tag_time, modified) {
+            (Some(tagged), Some(modified)) if modified > tagged + EDIT_GRACE_SECS => audit.edited += 1,
+            _ => audit.stale += 1,
+        }
+    }
+
+    Ok(audit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(artists: &[&str], genre: &[&str]) -> AudioMetadata {
+        AudioMetadata {
+            title: "Track".to_string(),
+            artists: artists.iter().map(|s| s.to_string()).collect(),
+            album: "Work".to_string(),
+            album_artist: "Circle".to_string(),
+            track_number: None,
+            disc: None,
+            genre: genre.iter().map(|s| s.to_string()).collect(),
+            date: None,
+            grouping: None,
+            comment: None,
+            mood: vec![],
+            category_frames: vec![],
+            source_frames: vec![],
+            rating: None,
+        }
+    }
+
+    #[test]
+    fn test_diff_tags() {
+        let expected = metadata(&["A", "B"], &["tag1", "tag2"]);
+        // A joined genre read back as a single value is the same tag
+        assert!(diff_tags(&expected, &metadata(&["A", "B"], &["tag1; tag2"]), "; ").is_empty());
+        assert_eq!(diff_tags(&expected, &metadata(&["A"], &["tag1; tag2"]), "; "), vec!["artist"]);
+    }
+}
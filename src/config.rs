@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use tracing::info;
 use crate::errors::HvtError;
+use crate::tagger::track_parser::TrackParsingPreference;
 
 // ========== VPN Configuration ==========
 
@@ -46,6 +48,55 @@ impl Default for VpnConfig {
 
 // ========== Tagger Configuration ==========
 
+/// Which name of a voice actor is written to the artist tag
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CvNameStyle {
+    /// Japanese name (as credited on DLsite)
+    #[default]
+    Jp,
+    /// English name, falling back to the Japanese one
+    En,
+    /// "Japanese (English)" when an English name is known
+    JpEn,
+}
+
+/// Which work title populates the TITLE/ALBUM tags
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleLanguage {
+    /// Japanese title (DLsite API work_name)
+    #[default]
+    Jp,
+    /// Title of the en_US locale page, falling back to the Japanese one
+    En,
+}
+
+/// How multiple voice actors are written to the artist frames
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtistFrameStyle {
+    /// One TPE1 string joined with the tag separator
+    #[default]
+    Joined,
+    /// ID3v2.4 multiple values: TPE1 values separated by a null byte (Navidrome, foobar2000)
+    MultiValue,
+    /// TPE1 joined with the tag separator for display, plus a null-separated TXXX:ARTISTS
+    /// (MusicBee, Picard)
+    TxxxArtists,
+}
+
+/// ID3v2 version written to MP3 files
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Default)]
+pub enum Id3Version {
+    #[default]
+    #[serde(rename = "2.4")]
+    V24,
+    /// For car stereos and old players that only read ID3v2.3
+    #[serde(rename = "2.3")]
+    V23,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TaggerConfig {
     /// Use null byte separator (\0) for tags instead of custom separator
@@ -55,6 +106,90 @@ pub struct TaggerConfig {
     /// Custom separator to use when use_null_separator is false
     #[serde(default = "default_custom_separator")]
     pub custom_separator: String,
+
+    /// Voice actor name written to the artist tag: "jp", "en" or "jp_en" (custom CV names win)
+    #[serde(default)]
+    pub cv_name_style: CvNameStyle,
+
+    /// Work title written to the title/album tags: "jp" or "en"
+    #[serde(default)]
+    pub title_language: TitleLanguage,
+
+    /// Write the series (original work of a translation) into the grouping (TIT1) tag
+    #[serde(default)]
+    pub write_series_tag: bool,
+
+    /// Write the DLSite work description into the comment (COMM) tag
+    #[serde(default)]
+    pub write_description_comment: bool,
+
+    /// Write an info.nfo (metadata + description) into each work folder when tagging
+    #[serde(default)]
+    pub write_nfo: bool,
+
+    /// Also write the legacy `.tagged` marker file into tagged work folders (the tagged state is
+    /// kept in the database; for scripts that look for the marker)
+    #[serde(default)]
+    pub write_tagged_markers: bool,
+
+    /// Write the RJ code, DLSite URL and circle code as TXXX frames (DLSITE_ID, DLSITE_URL, CIRCLE_CODE)
+    #[serde(default = "default_write_source_frames")]
+    pub write_source_frames: bool,
+
+    /// Write the DLSite star rating into a POPM (popularimeter) frame
+    #[serde(default)]
+    pub write_rating: bool,
+
+    /// Write the DLSite age rating (All Ages, R15, R18) into a TXXX:RATING frame
+    #[serde(default)]
+    pub write_age_rating: bool,
+
+    /// Write an iTunes explicit-content flag (TXXX:ITUNESADVISORY, 1 for R18 works)
+    #[serde(default)]
+    pub write_explicit_flag: bool,
+
+    /// How multiple CVs are written: "joined", "multi_value" or "txxx_artists"
+    #[serde(default)]
+    pub artist_frame_style: ArtistFrameStyle,
+
+    /// ID3v2 version written: "2.4" or "2.3"
+    #[serde(default)]
+    pub id3_version: Id3Version,
+
+    /// Measure loudness (EBU R128, with ffmpeg) after conversion and write ReplayGain track and
+    /// album gain tags
+    #[serde(default)]
+    pub replaygain: bool,
+
+    /// Remove an existing ID3v1 tag from MP3 files when tagging
+    #[serde(default)]
+    pub strip_id3v1: bool,
+
+    /// Move audio files found in subfolders of a work up to its root before tagging
+    #[serde(default = "default_normalize_folders")]
+    pub normalize_folders: bool,
+
+    /// When normalizing, move the other files of those subfolders (PDFs, images, scripts) into
+    /// an extras/ subfolder
+    #[serde(default)]
+    pub collect_extras: bool,
+
+    /// Split a single-file work with a cue sheet, or an audiobook with chapters, into one MP3 per
+    /// track
+    #[serde(default)]
+    pub split_cue: bool,
+
+    /// Bitrate in kbps of the MP3 files made from FLAC/WAV/OGG/M4A (conversion and cue splits)
+    #[serde(default = "default_target_bitrate")]
+    pub target_bitrate: u32,
+
+    /// DLSite work types (e.g. "MOV") or option codes whose works are never tagged
+    #[serde(default)]
+    pub skip_work_types: Vec<String>,
+
+    /// Files of a work tagged at the same time (tag writes and checksums); 1 tags them one by one
+    #[serde(default = "default_io_parallelism")]
+    pub io_parallelism: usize,
 }
 
 fn default_use_null_separator() -> bool {
@@ -65,11 +200,47 @@ fn default_custom_separator() -> String {
     "; ".to_string()
 }
 
+fn default_write_source_frames() -> bool {
+    true
+}
+
+fn default_normalize_folders() -> bool {
+    true
+}
+
+fn default_target_bitrate() -> u32 {
+    320
+}
+
+fn default_io_parallelism() -> usize {
+    4
+}
+
 impl Default for TaggerConfig {
     fn default() -> Self {
         Self {
             use_null_separator: false,
             custom_separator: "; ".to_string(),
+            cv_name_style: CvNameStyle::default(),
+            title_language: TitleLanguage::default(),
+            write_series_tag: false,
+            write_description_comment: false,
+            write_nfo: false,
+            write_tagged_markers: false,
+            write_source_frames: default_write_source_frames(),
+            write_rating: false,
+            write_age_rating: false,
+            write_explicit_flag: false,
+            artist_frame_style: ArtistFrameStyle::default(),
+            id3_version: Id3Version::default(),
+            replaygain: false,
+            strip_id3v1: false,
+            normalize_folders: default_normalize_folders(),
+            collect_extras: false,
+            split_cue: false,
+            target_bitrate: default_target_bitrate(),
+            skip_work_types: Vec::new(),
+            io_parallelism: default_io_parallelism(),
         }
     }
 }
@@ -87,13 +258,107 @@ impl TaggerConfig {
 
 // ========== Import Configuration ==========
 
-#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ImportConfig {
     /// Source directory where new works are dropped for import
     pub source_path: Option<String>,
 
     /// Target library directory where works are moved after processing
     pub library_path: Option<String>,
+
+    /// Rename files/folders whose Shift-JIS names were extracted as mojibake to their decoded
+    /// name before import (when false they are only reported)
+    #[serde(default)]
+    pub fix_mojibake_filenames: bool,
+
+    /// Place works under `library_path/{circle}/` instead of directly in `library_path`. Applied
+    /// when works are moved in after tagging, and to library works refreshed by --retag/--full-retag
+    #[serde(default)]
+    pub organize_by_circle: bool,
+
+    /// Library folder of imported works, relative to `library_path`, with `{rjcode}`, `{title}`,
+    /// `{circle}`, `{circle_code}`, `{date}` and `{year}` placeholders (`/` for subfolders).
+    /// Replaces `organize_by_circle` when set
+    #[serde(default)]
+    pub folder_template: Option<String>,
+
+    /// Longest folder name, in characters, of the work and circle folders --full moves works into
+    /// (0 = no limit). Titles are shortened to fit, keeping the RJ code; names never exceed 255
+    /// bytes anyway
+    #[serde(default = "default_max_folder_name_length")]
+    pub max_folder_name_length: usize,
+
+    /// Write kana in romaji and drop accents in those folder names (kanji are kept)
+    #[serde(default)]
+    pub transliterate_folder_names: bool,
+
+    /// Look for works in subfolders of the scanned roots (`Circle/RJxxxx/`, `2023/RJxxxx/`)
+    /// instead of only their direct children
+    #[serde(default)]
+    pub recursive: bool,
+
+    /// With `recursive`: how many folder levels are searched (1 = direct children only)
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+
+    /// Glob patterns of folders never registered, matched against the folder name and its path
+    /// under the scanned root (`_incomplete`, `backup/`, `RJ01234567`). Added to the patterns of
+    /// a `.hvtagignore` file at the root
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+fn default_max_folder_name_length() -> usize {
+    120
+}
+
+fn default_max_depth() -> usize {
+    3
+}
+
+impl Default for ImportConfig {
+    fn default() -> Self {
+        Self {
+            source_path: None,
+            library_path: None,
+            fix_mojibake_filenames: false,
+            organize_by_circle: false,
+            folder_template: None,
+            max_folder_name_length: default_max_folder_name_length(),
+            transliterate_folder_names: false,
+            recursive: false,
+            max_depth: default_max_depth(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl ImportConfig {
+    /// Folder levels searched for works under a scanned root
+    pub fn scan_depth(&self) -> usize {
+        if self.recursive { self.max_depth.max(1) } else { 1 }
+    }
+}
+
+// ========== Library Configuration ==========
+
+/// A named collection (`[library.<name>]`) with its own database and scan roots, selected with
+/// `--library <name>`
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct LibraryConfig {
+    /// Database file; defaults to `libraries/<name>/data.db3` in the data directory
+    pub db_path: Option<String>,
+
+    /// Replaces `[import] source_path`
+    pub source_path: Option<String>,
+
+    /// Replaces `[import] library_path`
+    pub library_path: Option<String>,
+}
+
+/// Library names become folder names: letters, digits, '-' and '_' only
+pub(crate) fn is_valid_library_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
 }
 
 // ========== Web UI Configuration ==========
@@ -135,6 +400,544 @@ impl Default for UiConfig {
     }
 }
 
+// ========== Integrity Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct IntegrityConfig {
+    /// Write a `checksums.sha256` manifest into each work folder after tagging (checked by --fsck)
+    #[serde(default = "default_write_checksum_manifest")]
+    pub write_checksum_manifest: bool,
+
+    /// --fsck skips works whose files were all checked less than this many days ago (0 = always)
+    #[serde(default = "default_fsck_interval_days")]
+    pub fsck_interval_days: u32,
+
+    /// After tagging, flag empty or near-silent audio files (likely broken downloads), queue the
+    /// work for review and leave it unmarked until they are replaced (requires FFmpeg; off by
+    /// default, as it decodes every file)
+    #[serde(default = "default_detect_silent_audio")]
+    pub detect_silent_audio: bool,
+}
+
+fn default_write_checksum_manifest() -> bool {
+    false
+}
+
+fn default_fsck_interval_days() -> u32 {
+    30
+}
+
+fn default_detect_silent_audio() -> bool {
+    false
+}
+
+impl Default for IntegrityConfig {
+    fn default() -> Self {
+        Self {
+            write_checksum_manifest: default_write_checksum_manifest(),
+            fsck_interval_days: default_fsck_interval_days(),
+            detect_silent_audio: default_detect_silent_audio(),
+        }
+    }
+}
+
+// ========== Video Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct VideoConfig {
+    /// Accept RJ/VJ folders containing only video files (no audio) as works during import
+    #[serde(default = "default_video_only_is_valid")]
+    pub video_only_is_valid: bool,
+
+    /// Also write work metadata (title/album/artists/genre/date) into MP4/M4V bonus videos
+    #[serde(default = "default_tag_video_files")]
+    pub tag_video_files: bool,
+}
+
+fn default_video_only_is_valid() -> bool {
+    false
+}
+
+fn default_tag_video_files() -> bool {
+    false
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        Self {
+            video_only_is_valid: default_video_only_is_valid(),
+            tag_video_files: default_tag_video_files(),
+        }
+    }
+}
+
+// ========== Images Configuration ==========
+
+/// Which images of a work's DLSite page are saved into its folder
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize, Default, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageMode {
+    /// Only the main cover, as folder.jpeg
+    #[default]
+    Cover,
+    /// The cover plus the sample gallery, into a scans/ subfolder
+    Full,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ImagesConfig {
+    /// Images saved with each work (`--images` overrides it for one run)
+    #[serde(default)]
+    pub mode: ImageMode,
+
+    /// Covers are resized to this width and height; 0 keeps DLSite's original size
+    #[serde(default = "default_cover_size")]
+    pub cover_size: u32,
+
+    /// Covers downloaded at the same time by --full and --refresh-covers
+    #[serde(default = "default_cover_parallelism")]
+    pub cover_parallelism: usize,
+
+    /// Retries of a cover download after a network error, a 429 or a 5xx
+    #[serde(default = "default_cover_retries")]
+    pub cover_retries: u32,
+
+    /// A cover URL that failed this many times is not tried again without --force (0: always
+    /// tried)
+    #[serde(default = "default_cover_max_failures")]
+    pub cover_max_failures: u32,
+
+    /// Nor before this many hours after its last failure
+    #[serde(default = "default_cover_failure_cooldown_hours")]
+    pub cover_failure_cooldown_hours: u64,
+}
+
+fn default_cover_size() -> u32 {
+    500
+}
+
+fn default_cover_parallelism() -> usize {
+    4
+}
+
+fn default_cover_retries() -> u32 {
+    2
+}
+
+fn default_cover_max_failures() -> u32 {
+    3
+}
+
+fn default_cover_failure_cooldown_hours() -> u64 {
+    24
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        Self {
+            mode: ImageMode::default(),
+            cover_size: default_cover_size(),
+            cover_parallelism: default_cover_parallelism(),
+            cover_retries: default_cover_retries(),
+            cover_max_failures: default_cover_max_failures(),
+            cover_failure_cooldown_hours: default_cover_failure_cooldown_hours(),
+        }
+    }
+}
+
+impl ImagesConfig {
+    /// Target size of downloaded covers, `None` to keep the original
+    pub fn cover_target_size(&self) -> Option<(u32, u32)> {
+        (self.cover_size > 0).then_some((self.cover_size, self.cover_size))
+    }
+}
+
+// ========== Library Import Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LibraryImportConfig {
+    /// --import-library: maximum number of works fetched and tagged per day
+    #[serde(default = "default_works_per_day")]
+    pub works_per_day: u32,
+
+    /// Pause between two DLSite fetches, to stay polite on long runs
+    #[serde(default = "default_delay_between_works_secs")]
+    pub delay_between_works_secs: u64,
+
+    /// When the daily budget is spent, wait for the next day and continue instead of exiting
+    #[serde(default = "default_keep_running")]
+    pub keep_running: bool,
+}
+
+fn default_works_per_day() -> u32 {
+    200
+}
+
+fn default_delay_between_works_secs() -> u64 {
+    5
+}
+
+fn default_keep_running() -> bool {
+    false
+}
+
+impl Default for LibraryImportConfig {
+    fn default() -> Self {
+        Self {
+            works_per_day: default_works_per_day(),
+            delay_between_works_secs: default_delay_between_works_secs(),
+            keep_running: default_keep_running(),
+        }
+    }
+}
+
+// ========== Backup Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BackupConfig {
+    /// Back up the database and record a health snapshot before bulk runs
+    #[serde(default = "default_backup_enabled")]
+    pub enabled: bool,
+
+    /// Only runs touching at least this many works trigger a backup
+    #[serde(default = "default_backup_min_works")]
+    pub min_works: usize,
+
+    /// Number of backups kept in the backups folder (oldest deleted first)
+    #[serde(default = "default_keep_backups")]
+    pub keep_backups: usize,
+}
+
+fn default_backup_enabled() -> bool {
+    true
+}
+
+fn default_backup_min_works() -> usize {
+    20
+}
+
+fn default_keep_backups() -> usize {
+    10
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_backup_enabled(),
+            min_works: default_backup_min_works(),
+            keep_backups: default_keep_backups(),
+        }
+    }
+}
+
+// ========== HTTP Client Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HttpConfig {
+    /// Timeout of a whole request, body included (seconds)
+    #[serde(default = "default_http_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Ask for gzip/brotli compressed responses
+    #[serde(default = "default_http_compression")]
+    pub compression: bool,
+
+    /// Use HTTP/2 when the server offers it; false sticks to HTTP/1.1
+    #[serde(default = "default_http2")]
+    pub http2: bool,
+
+    /// Idle connections kept open per host for the next requests
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+
+    /// Requests sent at the same time to one host (0: no limit)
+    #[serde(default = "default_per_host_concurrency")]
+    pub per_host_concurrency: usize,
+}
+
+fn default_http_timeout_secs() -> u64 {
+    30
+}
+
+fn default_http_compression() -> bool {
+    true
+}
+
+fn default_http2() -> bool {
+    true
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    8
+}
+
+fn default_per_host_concurrency() -> usize {
+    4
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: default_http_timeout_secs(),
+            compression: default_http_compression(),
+            http2: default_http2(),
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            per_host_concurrency: default_per_host_concurrency(),
+        }
+    }
+}
+
+// ========== HTTP Cache Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HttpCacheConfig {
+    /// How long DLSite API responses and work pages are reused before being fetched again
+    /// (0 disables the cache)
+    #[serde(default = "default_http_cache_ttl_hours")]
+    pub ttl_hours: u64,
+
+    /// Never access the network: metadata only from the database and the cache, whatever the
+    /// age of the cached pages (`--offline`)
+    #[serde(default)]
+    pub offline: bool,
+}
+
+fn default_http_cache_ttl_hours() -> u64 {
+    24
+}
+
+impl Default for HttpCacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_hours: default_http_cache_ttl_hours(),
+            offline: false,
+        }
+    }
+}
+
+// ========== Metadata Sources Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MetadataConfig {
+    /// Sites the metadata of a work is fetched from, in order of preference: a field missing
+    /// from one is taken from the next ("dlsite", "asmr_one")
+    #[serde(default = "default_metadata_sources")]
+    pub sources: Vec<String>,
+
+    /// Base URL of the asmr.one API
+    #[serde(default = "default_asmr_one_api")]
+    pub asmr_one_api: String,
+
+    /// DLSite sections an RJ work is looked up under, in order, until one has it
+    #[serde(default = "default_dlsite_sections")]
+    pub dlsite_sections: Vec<String>,
+
+    /// How often a request answered with an anti-bot challenge is retried before the work is
+    /// recorded as a "dlsite_challenge" error
+    #[serde(default = "default_challenge_retries")]
+    pub challenge_retries: u32,
+
+    /// Pause before the first retry of a challenged request, doubled at each retry (seconds)
+    #[serde(default = "default_challenge_backoff_secs")]
+    pub challenge_backoff_secs: u64,
+
+    /// Cookie jar exported from a browser (Netscape cookies.txt) whose dlsite.com cookies are
+    /// sent with every DLSite request, to reuse a passed challenge
+    #[serde(default)]
+    pub cookies_file: Option<String>,
+}
+
+fn default_challenge_retries() -> u32 {
+    3
+}
+
+fn default_challenge_backoff_secs() -> u64 {
+    60
+}
+
+fn default_metadata_sources() -> Vec<String> {
+    vec!["dlsite".to_string()]
+}
+
+fn default_dlsite_sections() -> Vec<String> {
+    crate::dlsite::sections::DEFAULT_RJ_SECTIONS.iter().map(|s| s.to_string()).collect()
+}
+
+fn default_asmr_one_api() -> String {
+    "https://api.asmr.one/api".to_string()
+}
+
+impl Default for MetadataConfig {
+    fn default() -> Self {
+        Self {
+            sources: default_metadata_sources(),
+            asmr_one_api: default_asmr_one_api(),
+            dlsite_sections: default_dlsite_sections(),
+            challenge_retries: default_challenge_retries(),
+            challenge_backoff_secs: default_challenge_backoff_secs(),
+            cookies_file: None,
+        }
+    }
+}
+
+// ========== Playlists Configuration ==========
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PlaylistsConfig {
+    /// Folder where `--playlist` writes its playlists (`<library_path>/playlists` when unset)
+    #[serde(default)]
+    pub output_path: Option<String>,
+}
+
+// ========== Run Reports Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RunReportsConfig {
+    /// Write a JSON summary of every run that touched works to the `runs` data folder
+    #[serde(default = "default_run_reports_enabled")]
+    pub enabled: bool,
+
+    /// Number of reports kept (oldest deleted first)
+    #[serde(default = "default_keep_run_reports")]
+    pub keep: usize,
+}
+
+fn default_run_reports_enabled() -> bool {
+    true
+}
+
+fn default_keep_run_reports() -> usize {
+    100
+}
+
+impl Default for RunReportsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_run_reports_enabled(),
+            keep: default_keep_run_reports(),
+        }
+    }
+}
+
+// ========== Logging Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LoggingConfig {
+    /// Also write the log to files, one per day (see `logging::RotatingFile`)
+    #[serde(default = "default_logging_enabled")]
+    pub enabled: bool,
+
+    /// Level of the log files (`EnvFilter` directive), independent of `RUST_LOG`
+    #[serde(default = "default_logging_level")]
+    pub level: String,
+
+    /// Folder of the log files (the `logs` data folder when unset)
+    #[serde(default)]
+    pub path: Option<String>,
+
+    /// A new file is started when the day's file reaches this size
+    #[serde(default = "default_logging_max_size_mb")]
+    pub max_size_mb: u64,
+
+    /// Number of log files kept (oldest deleted first)
+    #[serde(default = "default_logging_max_files")]
+    pub max_files: usize,
+}
+
+fn default_logging_enabled() -> bool {
+    true
+}
+
+fn default_logging_level() -> String {
+    "info".to_string()
+}
+
+fn default_logging_max_size_mb() -> u64 {
+    10
+}
+
+fn default_logging_max_files() -> usize {
+    30
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_logging_enabled(),
+            level: default_logging_level(),
+            path: None,
+            max_size_mb: default_logging_max_size_mb(),
+            max_files: default_logging_max_files(),
+        }
+    }
+}
+
+// ========== Track Parsing Configuration ==========
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TrackParsingConfig {
+    /// Strategies tried in order on works without a saved preference, before the interactive
+    /// prompt (see `TrackParsingPreference::from_spec` for the syntax)
+    #[serde(default)]
+    pub strategies: Vec<String>,
+
+    /// Ask interactively when no strategy numbers the files cleanly
+    #[serde(default = "default_track_parsing_prompt")]
+    pub prompt: bool,
+
+    /// Use the titles of a bundled tracklist file without asking
+    #[serde(default)]
+    pub accept_tracklist: bool,
+
+    /// Keep the track numbers already present in the files' tags instead of parsing filenames
+    #[serde(default = "default_keep_existing_numbers")]
+    pub keep_existing_numbers: bool,
+
+    /// Silence split of single-file works: level (dB) below which audio counts as silence
+    #[serde(default = "default_silence_threshold_db")]
+    pub silence_threshold_db: f64,
+
+    /// Silence split: shortest pause (seconds) that starts a new track
+    #[serde(default = "default_silence_min_duration")]
+    pub silence_min_duration: f64,
+}
+
+fn default_track_parsing_prompt() -> bool {
+    true
+}
+
+fn default_keep_existing_numbers() -> bool {
+    true
+}
+
+fn default_silence_threshold_db() -> f64 {
+    -50.0
+}
+
+fn default_silence_min_duration() -> f64 {
+    2.0
+}
+
+impl Default for TrackParsingConfig {
+    fn default() -> Self {
+        Self {
+            strategies: Vec::new(),
+            prompt: default_track_parsing_prompt(),
+            accept_tracklist: false,
+            keep_existing_numbers: default_keep_existing_numbers(),
+            silence_threshold_db: default_silence_threshold_db(),
+            silence_min_duration: default_silence_min_duration(),
+        }
+    }
+}
+
+impl TrackParsingConfig {
+    /// The configured strategies, parsed
+    pub fn presets(&self) -> Result<Vec<TrackParsingPreference>, HvtError> {
+        self.strategies.iter().map(|spec| TrackParsingPreference::from_spec(spec)).collect()
+    }
+}
+
 // ========== Root Configuration ==========
 
 /// Root configuration structure
@@ -149,8 +952,47 @@ pub struct Config {
     #[serde(default)]
     pub import: ImportConfig,
 
+    #[serde(default, rename = "library")]
+    pub libraries: BTreeMap<String, LibraryConfig>,
+
     #[serde(default)]
     pub ui: UiConfig,
+
+    #[serde(default)]
+    pub integrity: IntegrityConfig,
+
+    #[serde(default)]
+    pub video: VideoConfig,
+
+    #[serde(default)]
+    pub images: ImagesConfig,
+
+    #[serde(default)]
+    pub library_import: LibraryImportConfig,
+
+    #[serde(default)]
+    pub backup: BackupConfig,
+
+    #[serde(default)]
+    pub http: HttpConfig,
+
+    #[serde(default)]
+    pub http_cache: HttpCacheConfig,
+
+    #[serde(default)]
+    pub metadata: MetadataConfig,
+
+    #[serde(default)]
+    pub playlists: PlaylistsConfig,
+
+    #[serde(default)]
+    pub run_reports: RunReportsConfig,
+
+    #[serde(default)]
+    pub logging: LoggingConfig,
+
+    #[serde(default)]
+    pub track_parsing: TrackParsingConfig,
 }
 
 impl Default for Config {
@@ -159,13 +1001,26 @@ impl Default for Config {
             vpn: VpnConfig::default(),
             tagger: TaggerConfig::default(),
             import: ImportConfig::default(),
+            libraries: BTreeMap::new(),
             ui: UiConfig::default(),
+            integrity: IntegrityConfig::default(),
+            video: VideoConfig::default(),
+            images: ImagesConfig::default(),
+            library_import: LibraryImportConfig::default(),
+            backup: BackupConfig::default(),
+            http: HttpConfig::default(),
+            http_cache: HttpCacheConfig::default(),
+            metadata: MetadataConfig::default(),
+            playlists: PlaylistsConfig::default(),
+            run_reports: RunReportsConfig::default(),
+            logging: LoggingConfig::default(),
+            track_parsing: TrackParsingConfig::default(),
         }
     }
 }
 
 impl Config {
-    /// Load configuration from ~/.hvtag/config.toml
+    /// Load configuration from the platform config directory (see `paths::config_file`)
     /// Creates a default config file if it doesn't exist
     pub fn load() -> Result<Self, HvtError> {
         let config_path = Self::get_config_path()?;
@@ -186,6 +1041,43 @@ impl Config {
         Ok(config)
     }
 
+    /// Like `load`, but falls back to the defaults without writing a config file (`--read-only`)
+    pub fn load_without_creating() -> Result<Self, HvtError> {
+        if !Self::get_config_path()?.exists() {
+            return Ok(Self::default());
+        }
+        Self::load()
+    }
+
+    /// Switches to a named library (`--library`): its scan roots replace the `[import]` ones.
+    /// Returns the path of its database.
+    pub fn use_library(&mut self, name: &str) -> Result<String, HvtError> {
+        let Some(library) = self.libraries.get(name).cloned() else {
+            let known: Vec<&str> = self.libraries.keys().map(String::as_str).collect();
+            return Err(HvtError::Generic(format!(
+                "Unknown library '{}' (configured: {})",
+                name,
+                if known.is_empty() { "none".to_string() } else { known.join(", ") }
+            )));
+        };
+        if !is_valid_library_name(name) {
+            return Err(HvtError::Generic(format!(
+                "Invalid library name '{}': use letters, digits, '-' and '_' only", name
+            )));
+        }
+
+        if library.source_path.is_some() {
+            self.import.source_path = library.source_path;
+        }
+        if library.library_path.is_some() {
+            self.import.library_path = library.library_path;
+        }
+        match library.db_path {
+            Some(db_path) => Ok(db_path),
+            None => Ok(crate::paths::library_database_file(name)?.display().to_string()),
+        }
+    }
+
     /// Create a default configuration file
     fn create_default_config(config_path: &PathBuf) -> Result<(), HvtError> {
         let default_config = Self::get_default_config_content();
@@ -222,6 +1114,46 @@ impl Config {
 # Library directory: where works are moved after processing
 # library_path = "{library_example}"
 
+# Rename files/folders with garbled Shift-JIS names (extracted from old archives) to their
+# decoded Japanese name before import. When false, they are only reported.
+fix_mojibake_filenames = false
+
+# Organize the library by circle: works go to library_path/<circle>/<RJcode> (circle display
+# name, custom preferences included). Works refreshed with --retag/--full-retag are moved into
+# their circle folder too.
+organize_by_circle = false
+
+# Rename work folders when --full moves them into the library, relative to library_path:
+# {{rjcode}}, {{title}}, {{circle}}, {{circle_code}}, {{date}} (YYYY-MM-DD) and {{year}} are replaced,
+# "/" creates subfolders. The last folder name must keep {{rjcode}}. Replaces organize_by_circle.
+# folder_template = "{{circle}}/{{rjcode}} {{title}}"
+
+# Longest folder name (in characters) of the work and circle folders --full moves works into;
+# longer titles are shortened, keeping the RJ code. Keeps deep NAS paths under Windows limits.
+# 0 = no limit.
+max_folder_name_length = 120
+
+# Write kana in romaji (ボイス -> boisu) and drop accents in those folder names. Kanji are kept.
+transliterate_folder_names = false
+
+# Also look for works in subfolders (Circle/RJxxxx, 2023/RJxxxx...), down to max_depth levels
+# (1 = direct children only). Folders named RJ/VJ are never searched further.
+recursive = false
+max_depth = 3
+
+# Folders never registered: glob patterns matched against the folder name and its path under
+# the scanned folder. A .hvtagignore file (one pattern per line) at the root of a scanned folder
+# adds to this list.
+exclude = []   # e.g. ["_incomplete", "backup/", "RJ01234567"]
+
+# Named libraries, selected with --library <name>: each has its own database and scan roots
+# (replacing source_path/library_path above), so one install can manage several collections.
+# db_path defaults to libraries/<name>/data.db3 next to the default database.
+# [library.nas]
+# db_path = "/mnt/nas/hvtag/data.db3"
+# source_path = "/mnt/nas/downloads"
+# library_path = "/mnt/nas/asmr"
+
 [vpn]
 # Enable VPN functionality for metadata fetching from DLsite
 # Set to true if you need to access DLsite from a restricted region
@@ -245,6 +1177,94 @@ use_null_separator = false
 # Common separators: "; " (default), " / ", ", ", " | "
 custom_separator = "; "
 
+# Voice actor names in the artist tag: "jp" (default), "en" (English name when DLsite has one)
+# or "jp_en" ("Japanese (English)"). A custom name set in --manage-cvs always wins.
+# Run --full-retag --all after changing it.
+cv_name_style = "jp"
+
+# Work title in the title/album tags: "jp" (default) or "en" (title of the English DLsite page,
+# which is the Japanese one for untranslated works). Run --full-retag --all after changing it.
+title_language = "jp"
+
+# Write the series into the grouping (TIT1) tag so players group a work with its translations:
+# the title of the original work when it is in the library, else its RJ code.
+# Run --full-retag --all after changing it.
+write_series_tag = false
+
+# Write the DLsite work description into the comment tag of every file
+write_description_comment = false
+
+# Write an info.nfo (title, circle, CVs, release date, tags, DLsite link and description)
+# into each work folder when tagging
+write_nfo = false
+
+# Which works are tagged is kept in the database. Also write the old empty .tagged file into
+# each tagged work folder, for scripts relying on it (--write-markers for one run)
+write_tagged_markers = false
+
+# Write the RJ code, DLsite URL and circle code as custom TXXX tags (DLSITE_ID, DLSITE_URL,
+# CIRCLE_CODE) so files can be traced back to their work after renaming
+write_source_frames = true
+
+# Write the DLsite star rating (0-5) into the POPM rating frame, scaled to 1-255, so players can
+# sort works by their DLsite score. Run --full-retag --all after enabling it.
+write_rating = false
+
+# Write the DLsite age rating ("All Ages", "R15" or "R18") into a custom TXXX:RATING tag, so
+# mixed libraries can be filtered by it. Run --full-retag --all after enabling it.
+write_age_rating = false
+
+# Write the iTunes explicit-content flag (TXXX:ITUNESADVISORY): 1 for R18 works, 0 otherwise.
+# Apple Music, MusicBee and Navidrome show it as an "explicit" badge.
+write_explicit_flag = false
+
+# How multiple voice actors are written to the artist tag:
+#   "joined"       - one string joined with the separator above (default)
+#   "multi_value"  - ID3v2.4 null-separated values (Navidrome, foobar2000)
+#   "txxx_artists" - joined artist tag for display + null-separated TXXX:ARTISTS (MusicBee, Picard)
+# Run --full-retag --all after changing it.
+artist_frame_style = "joined"
+
+# ID3v2 version written to MP3 files: "2.4" (default) or "2.3" for car stereos and old players.
+# 2.3 has no multi-value frames ("multi_value" above is written as "joined").
+id3_version = "2.4"
+
+# Remove existing ID3v1 tags (old players may show them instead of the ID3v2 tags)
+strip_id3v1 = false
+
+# Move audio files found in subfolders (CD1/, mp3/...) up to the work folder before tagging.
+# Every move is logged, so "hvtag --normalize RJxxxx --undo" restores the original layout.
+# --no-normalize turns it off for one run: files are then tagged in place, with the disc number
+# taken from their subfolder name (CD1/, Disc 2/...).
+normalize_folders = true
+
+# When normalizing, also move the other files of those subfolders (PDFs, images, scripts...)
+# into an extras/ subfolder, keeping their subfolder path (extras/CD1/scans/...). Videos stay put.
+collect_extras = false
+
+# Works shipped as one long MP3/FLAC with a .cue sheet: split the file into one MP3 per track
+# with ffmpeg (the original is deleted once split, like --convert does), each tagged with its
+# number and title. Off, the single MP3 gets the tracks as ID3 chapters instead.
+# Also applies to .m4b/.m4a audiobooks with chapters (converted to MP3 with --convert when not split).
+split_cue = false
+
+# Bitrate (kbps) of the MP3 files made by --convert, by conversion while tagging and by cue splits
+target_bitrate = 320
+
+# Works never tagged, by DLSite work type (SOU voice/ASMR, MOV video, MUS music, ICG images...)
+# or option code (AIG AI-generated...): they are still registered and their metadata fetched.
+# --list --work-type MOV shows which works a code matches.
+skip_work_types = []
+
+# Measure each work's loudness with ffmpeg (EBU R128) and write ReplayGain track and album gain
+# tags (REPLAYGAIN_TRACK_GAIN, REPLAYGAIN_ALBUM_GAIN...), so players level works recorded at
+# very different volumes. Adds a full decode of every file to tagging.
+replaygain = false
+
+# Files of a work tagged at the same time. Tag writes on a NAS mostly wait on the network, so a
+# few at once go faster; 1 tags the files one by one.
+io_parallelism = 4
+
 [ui]
 # Bind address for the --ui web server. Defaults to loopback-only (127.0.0.1) for safety.
 # To reach it from your phone over Tailscale/VPN, set this to your Tailscale IP
@@ -259,23 +1279,208 @@ port = 8787
 
 # Number of works shown per page in the works list.
 page_size = 50
+
+[integrity]
+# Write a checksums.sha256 manifest (sha256sum format) into each work folder after tagging.
+# --fsck re-verifies folders against their manifest to detect silent corruption on aging drives.
+write_checksum_manifest = false
+
+# --fsck skips works that were fully checked less than this many days ago (0 = check everything),
+# so running it regularly (e.g. from cron) spreads the work over time.
+fsck_interval_days = 30
+
+# After tagging, flag empty / zero-duration / near-silent audio files (likely broken downloads).
+# Decodes every audio file of the work, so it is off by default. Flagged files are listed by
+# --stats and the work is queued for --review; it is not marked as tagged until they are replaced.
+detect_silent_audio = false
+
+[video]
+# Some works ship bonus videos (mp4, mkv...). They are never flattened, converted or ID3-tagged.
+# Accept RJ/VJ folders that contain only video files (no audio) as works during import
+video_only_is_valid = false
+
+# Also write work metadata (title/album/artists/genre/date) into MP4/M4V videos (requires FFmpeg,
+# stream copy - no re-encoding)
+tag_video_files = false
+
+[images]
+# "cover": only the main cover (folder.jpeg). "full": also the DLsite sample gallery, saved into a
+# scans/ subfolder of each work (useful for media centers). --images <mode> overrides it for one run.
+mode = "cover"
+
+# Covers (folder.jpeg) are resized to cover_size x cover_size pixels; 0 keeps DLsite's original size.
+# --refresh-covers always downloads the original size.
+cover_size = 500
+
+# Covers downloaded at the same time by --full and --refresh-covers, and how often a download is
+# retried after a network error, a 429 or a 5xx
+cover_parallelism = 4
+cover_retries = 2
+
+# A cover URL that failed (404, broken image...) is not tried again for cover_failure_cooldown_hours,
+# and never again after cover_max_failures failures (0: always tried). --force tries them anyway.
+cover_max_failures = 3
+cover_failure_cooldown_hours = 24
+
+[library_import]
+# --import-library <root>: first import of an existing collection, in daily batches.
+# The queue is kept in the database: re-run the command to continue where it stopped.
+# Maximum number of works fetched and tagged per day
+works_per_day = 200
+
+# Pause between two DLsite fetches (seconds)
+delay_between_works_secs = 5
+
+# When the daily budget is spent, wait for the next day and continue instead of exiting
+keep_running = false
+
+[backup]
+# Before --full, --full-retag and --import-library batches touching at least min_works works,
+# copy the database to backups/data-YYYYMMDD-HHMMSS.db3 (next to data.db3) and record
+# before/after library counts in the health_snapshots table
+enabled = true
+min_works = 20
+
+# Number of backups to keep (oldest deleted first)
+keep_backups = 10
+
+[http]
+# One HTTP client is shared by every DLSite, asmr.one and cover request of a run, so
+# connections are reused from one work to the next.
+timeout_secs = 30
+# gzip/brotli compressed responses
+compression = true
+# HTTP/2 when the server offers it (false: HTTP/1.1 only)
+http2 = true
+pool_max_idle_per_host = 8
+# Requests sent at the same time to one host (0: no limit)
+per_host_concurrency = 4
+
+[http_cache]
+# DLSite API responses and work pages are kept in the cache folder and reused for this many hours,
+# so a run interrupted halfway doesn't fetch the same works again (0 disables the cache).
+# --refresh, --retag and --full-retag always fetch again.
+ttl_hours = 24
+# Never access the network, like --offline
+offline = false
+
+[metadata]
+# Where work metadata comes from, in order of preference. A field one source lacks (CVs, tags,
+# release date...) is filled from the next one. "dlsite" is the DLSite API and work pages,
+# "asmr_one" the community JSON API of asmr.one. A work removed from DLSite is still reported
+# as removed.
+sources = ["dlsite"]
+# sources = ["dlsite", "asmr_one"]
+asmr_one_api = "https://api.asmr.one/api"
+
+# DLSite sections an RJ work is looked up under, in order: all-ages, otome and BL works only
+# exist under home, girls or bl, and would otherwise be reported as removed. VJ works are looked
+# up under pro, girls-pro and bl-pro.
+dlsite_sections = ["maniax", "home", "girls", "bl"]
+
+# When DLSite answers with a Cloudflare/robot check instead of a page, the request is retried
+# after challenge_backoff_secs seconds (doubled at each retry) up to challenge_retries times,
+# then the work is recorded with a "dlsite_challenge" error. A cookie jar exported from a
+# browser that passed the check (Netscape cookies.txt) can be sent along with every request.
+challenge_retries = 3
+challenge_backoff_secs = 60
+# cookies_file = "/path/to/cookies.txt"
+
+[playlists]
+# Folder where --playlist writes its .m3u8 playlists, one subfolder per grouping (by_circle, by_cv...).
+# Defaults to <library_path>/playlists.
+# output_path = "{library_example}/playlists"
+
+[run_reports]
+# After each run that touched works, write runs/YYYYMMDD-HHMMSS.json (next to data.db3): the command,
+# duration, every work with its outcome, error and time taken, bytes downloaded and converted
+enabled = true
+
+# Number of reports to keep (oldest deleted first)
+keep = 100
+
+[logging]
+# Besides the console, write the log to logs/hvtag-YYYY-MM-DD.log (next to data.db3), to look into
+# an overnight run afterwards. A new file is started each day (UTC) and when max_size_mb is reached.
+enabled = true
+
+# Level of the log files: "error", "warn", "info", "debug", "trace", or per module like
+# "info,hvtag::dlsite=debug". RUST_LOG only sets the console's level.
+level = "info"
+
+# Folder of the log files (defaults to the logs folder next to data.db3)
+# path = "/var/log/hvtag"
+
+max_size_mb = 10
+
+# Number of log files to keep (oldest deleted first)
+max_files = 30
+
+[track_parsing]
+# Track numbering strategies tried in order on works without a saved preference, before asking.
+# The first one that numbers every file without duplicates is used. Available:
+#   "asian_fullwidth", "asian_brackets" (【01】), "asian_kanji_episode" (第01話), "first_number",
+#   "custom_delimiter:<delimiter>" (e.g. "custom_delimiter:_"), "strip_prefix:<regex>" (e.g. "strip_prefix:s.*?_"),
+#   "custom_regex:<regex>" (one capture group for the track number, e.g. "custom_regex:_t(\\d+)$")
+# --parsing-strategy <strategy> replaces this list for one run and never asks.
+strategies = []
+
+# Ask interactively when neither the strategies above nor automatic detection number the files,
+# and before using a bundled tracklist's titles. When false (or with --non-interactive), such
+# works are tagged with the best guess and listed under "Needs attention" in --stats.
+prompt = true
+
+# Use the titles of a bundled tracklist file (tracklist.txt, トラックリスト.txt...) without asking
+accept_tracklist = false
+
+# Keep the track numbers already written in the files' tags (when most files have one) instead of
+# parsing filenames again. --review turns this off for the work being reviewed.
+keep_existing_numbers = true
+
+# Works shipped as one long audio file without a cue sheet: the prompt offers to split the file
+# where it goes silent (needs ffmpeg). Audio below silence_threshold_db (dB) for at least
+# silence_min_duration seconds is a pause between two tracks.
+silence_threshold_db = -50.0
+silence_min_duration = 2.0
 "#)
     }
 
     /// Get the path to the configuration file
     fn get_config_path() -> Result<PathBuf, HvtError> {
-        let home = dirs::home_dir()
-            .ok_or_else(|| HvtError::Generic("Could not determine home directory".to_string()))?;
+        crate::paths::config_file()
+    }
 
-        let config_dir = home.join(".hvtag");
+}
 
-        // Create directory if it doesn't exist
-        if !config_dir.exists() {
-            std::fs::create_dir_all(&config_dir)
-                .map_err(|e| HvtError::Generic(format!("Failed to create config directory: {}", e)))?;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        Ok(config_dir.join("config.toml"))
+    #[test]
+    fn test_use_library_replaces_scan_roots() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [import]
+            source_path = "/downloads"
+            library_path = "/library"
+
+            [library.nas]
+            db_path = "/mnt/nas/data.db3"
+            library_path = "/mnt/nas/asmr"
+            "#,
+        ).unwrap();
+
+        assert_eq!(config.use_library("nas").unwrap(), "/mnt/nas/data.db3");
+        assert_eq!(config.import.source_path.as_deref(), Some("/downloads"));
+        assert_eq!(config.import.library_path.as_deref(), Some("/mnt/nas/asmr"));
+        assert!(config.use_library("local").is_err());
     }
 
+    #[test]
+    fn test_is_valid_library_name() {
+        assert!(is_valid_library_name("nas"));
+        assert!(is_valid_library_name("local_2"));
+        assert!(!is_valid_library_name(""));
+        assert!(!is_valid_library_name("../nas"));
+    }
 }
@@ -0,0 +1,157 @@
+use std::path::Path;
+
+use toml::Value;
+
+use crate::config::{self, Config};
+use crate::errors::HvtError;
+use crate::folders::ScanOptions;
+
+/// Keys of `raw` (the file as written) that the parsed configuration doesn't know, as dotted
+/// paths (`import.sourcepath`). `known` is the parsed configuration serialized back: serde
+/// ignores unknown keys, so they are the ones missing from it.
+fn unknown_keys(raw: &Value, known: &Value, prefix: &str) -> Vec<String> {
+    let (Value::Table(raw), Value::Table(known)) = (raw, known) else {
+        return Vec::new();
+    };
+    let mut unknown = Vec::new();
+    for (key, value) in raw {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match known.get(key) {
+            Some(known_value) => unknown.extend(unknown_keys(value, known_value, &path)),
+            None => unknown.push(path),
+        }
+    }
+    unknown
+}
+
+struct Report {
+    errors: usize,
+    warnings: usize,
+}
+
+impl Report {
+    fn ok(&self, message: &str) {
+        println!("  ✓ {}", message);
+    }
+
+    fn warning(&mut self, message: &str) {
+        println!("  ! {}", message);
+        self.warnings += 1;
+    }
+
+    fn error(&mut self, message: &str) {
+        println!("  ✗ {}", message);
+        self.errors += 1;
+    }
+
+    /// A folder the config points to: missing is a warning (e.g. an unmounted NAS share)
+    fn folder(&mut self, key: &str, path: Option<&str>) {
+        match path {
+            None => {}
+            Some(path) if Path::new(path).is_dir() => self.ok(&format!("{} = {}", key, path)),
+            Some(path) => self.warning(&format!("{} = {}: folder not found", key, path)),
+        }
+    }
+}
+
+/// `--check-config`: validates config.toml (syntax and values), lists keys hvtag doesn't know
+/// (typos, options of another version), checks the folders and files it references, then prints
+/// the effective configuration (defaults filled in, `--library` applied). Fails when the
+/// configuration has errors, so it can guard scripts.
+pub fn run_config_check_workflow(library: Option<&str>) -> Result<(), HvtError> {
+    let config_path = crate::paths::config_file()?;
+    println!("=== {} ===", config_path.display());
+    let mut report = Report { errors: 0, warnings: 0 };
+
+    let mut config = if config_path.exists() {
+        let contents = std::fs::read_to_string(&config_path)?;
+        let raw: Value = toml::from_str(&contents)
+            .map_err(|e| HvtError::Parse(format!("config.toml is not valid TOML: {}", e)))?;
+        let config: Config = toml::from_str(&contents)
+            .map_err(|e| HvtError::Parse(format!("config.toml is invalid: {}", e)))?;
+        report.ok("Syntax and types");
+
+        let known = Value::try_from(&config).map_err(|e| HvtError::Parse(e.to_string()))?;
+        let unknown = unknown_keys(&raw, &known, "");
+        if unknown.is_empty() {
+            report.ok("No unknown key");
+        }
+        for key in &unknown {
+            report.warning(&format!("Unknown key '{}' (ignored)", key));
+        }
+        config
+    } else {
+        report.warning("No config file, the defaults are used");
+        Config::default()
+    };
+
+    // Values parsed lazily by the commands
+    match config.track_parsing.presets() {
+        Ok(_) => report.ok("track_parsing.strategies"),
+        Err(e) => report.error(&format!("track_parsing.strategies: {}", e)),
+    }
+    match ScanOptions::from_config(&config) {
+        Ok(_) => report.ok("import.exclude"),
+        Err(e) => report.error(&format!("import.exclude: {}", e)),
+    }
+    match tracing_subscriber::EnvFilter::try_new(&config.logging.level) {
+        Ok(_) => report.ok("logging.level"),
+        Err(e) => report.error(&format!("logging.level = {}: {}", config.logging.level, e)),
+    }
+
+    // Referenced files and folders
+    if config.vpn.enabled {
+        match &config.vpn.wireguard {
+            Some(wireguard) if Path::new(&wireguard.config_path).is_file() => {
+                report.ok(&format!("vpn.wireguard.config_path = {}", wireguard.config_path))
+            }
+            Some(wireguard) => report.error(&format!(
+                "vpn.wireguard.config_path = {}: file not found", wireguard.config_path
+            )),
+            None => report.error("vpn.enabled = true but [vpn.wireguard] is missing"),
+        }
+    }
+    report.folder("import.source_path", config.import.source_path.as_deref());
+    report.folder("import.library_path", config.import.library_path.as_deref());
+    for (name, library) in &config.libraries {
+        if !config::is_valid_library_name(name) {
+            report.error(&format!("[library.{}]: use letters, digits, '-' and '_' only", name));
+        }
+        report.folder(&format!("library.{}.source_path", name), library.source_path.as_deref());
+        report.folder(&format!("library.{}.library_path", name), library.library_path.as_deref());
+        if let Some(db_path) = &library.db_path {
+            if !Path::new(db_path).is_file() {
+                report.warning(&format!("library.{}.db_path = {}: not created yet", name, db_path));
+            }
+        }
+    }
+
+    if let Some(name) = library {
+        config.use_library(name)?;
+    }
+    let effective = toml::to_string_pretty(&config).map_err(|e| HvtError::Parse(e.to_string()))?;
+    println!("\n=== Effective configuration{} ===", library.map(|n| format!(" (library {})", n)).unwrap_or_default());
+    println!("{}", effective);
+
+    println!("{} error(s), {} warning(s)", report.errors, report.warnings);
+    if report.errors > 0 {
+        return Err(HvtError::Generic(format!("config.toml has {} error(s)", report.errors)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_keys() {
+        let raw: Value = toml::from_str(
+            "[import]\nsource_path = \"/in\"\nsourcepath = \"/typo\"\n[library.nas]\ndb_path = \"/nas.db3\"\n[colors]\nx = 1\n",
+        )
+        .unwrap();
+        let config: Config = toml::from_str(&toml::to_string(&raw).unwrap()).unwrap();
+        let known = Value::try_from(&config).unwrap();
+        assert_eq!(unknown_keys(&raw, &known, ""), ["colors", "import.sourcepath"]);
+    }
+}
@@ -0,0 +1,303 @@
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::database::db_loader::open_db;
+use crate::folders::{self, types::RJCode, ScanOptions};
+use crate::refresh::RefreshField;
+use crate::run_stats::{WorkObserver, WorkOutcome};
+use crate::runner::{Operation, Runner};
+
+#[cfg(windows)]
+const DEFAULT_PIPE_NAME: &str = r"\\.\pipe\hvtag";
+
+fn notify(events: &broadcast::Sender<String>, method: &str, params: Value) {
+    let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+    // No receiver just means no client is connected
+    let _ = events.send(message.to_string());
+}
+
+/// Progress sink of the operations: one `progress` notification per work handled
+fn progress_observer(events: broadcast::Sender<String>) -> WorkObserver {
+    Box::new(move |rjcode: &RJCode, operation: &str, outcome: WorkOutcome, error: Option<&str>| {
+        notify(
+            &events,
+            "progress",
+            json!({ "rjcode": rjcode.as_str(), "operation": operation, "outcome": outcome.as_str(), "error": error }),
+        );
+    })
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+struct ControlState {
+    runner: Arc<Runner>,
+    /// Notifications sent to every connected client
+    events: broadcast::Sender<String>,
+    current: Mutex<Option<&'static str>>,
+}
+
+impl ControlState {
+    /// Queues the operation on the runner; the receiver gets its outcome once it is done.
+    fn start(self: &Arc<Self>, operation: Operation) -> oneshot::Receiver<Result<(), String>> {
+        let (done, result) = oneshot::channel();
+        let name = operation.name();
+        let state = Arc::clone(self);
+        let started = move || {
+            *state.current.lock().expect("current mutex poisoned") = Some(name);
+            notify(&state.events, "started", json!({ "operation": name }));
+        };
+        let state = Arc::clone(self);
+        let finished = move |outcome: Result<(), String>| {
+            *state.current.lock().expect("current mutex poisoned") = None;
+            notify(
+                &state.events,
+                "finished",
+                json!({ "operation": name, "ok": outcome.is_ok(), "error": outcome.as_ref().err() }),
+            );
+            let _ = done.send(outcome);
+        };
+        self.runner.spawn(operation, Some(progress_observer(self.events.clone())), started, finished);
+        result
+    }
+
+    /// Folders waiting in the import folder, without processing them
+    fn scan(&self) -> Result<Value, String> {
+        let config = self.runner.config();
+        let source_path = config.import.source_path.as_deref()
+            .ok_or("import.source_path is not configured in config.toml")?;
+        let options = ScanOptions::from_config(config).map_err(|e| e.to_string())?;
+        let found = folders::get_list_of_folders(source_path, &options).map_err(|e| e.to_string())?;
+        let conn = open_db(self.runner.db_path()).map_err(|e| e.to_string())?;
+        Ok(Value::Array(
+            found.iter()
+                .map(|f| {
+                    let is_tagged = crate::database::queries::is_work_tagged(&conn, &f.rjcode).unwrap_or(false);
+                    json!({ "rjcode": f.rjcode.as_str(), "path": f.path.to_string_lossy(), "is_tagged": is_tagged })
+                })
+                .collect(),
+        ))
+    }
+}
+
+fn parse_operation(method: &str, params: &Value) -> Result<Option<Operation>, String> {
+    Ok(Some(match method {
+        "import" => Operation::Import,
+        "retag" => {
+            let rjcode = params.get("rjcode").and_then(Value::as_str).ok_or("missing param: rjcode")?;
+            Operation::Retag(RJCode::new(rjcode.to_uppercase()).map_err(|e| e.to_string())?.to_string())
+        }
+        "full_retag" => Operation::FullRetag {
+            all: params.get("all").and_then(Value::as_bool).unwrap_or(false),
+        },
+        "refresh" => {
+            let older_than_days = match params.get("older_than") {
+                Some(Value::String(age)) => Some(crate::refresh::parse_age_days(age)?),
+                Some(value) => value.as_u64().map(|days| days as u32),
+                None => None,
+            };
+            let fields = match params.get("fields") {
+                Some(Value::Array(fields)) => fields
+                    .iter()
+                    .map(|f| {
+                        let name = f.as_str().unwrap_or_default();
+                        <RefreshField as clap::ValueEnum>::from_str(name, true)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                _ => Vec::new(),
+            };
+            Operation::Refresh { older_than_days, fields }
+        }
+        _ => return Ok(None),
+    }))
+}
+
+/// Answers one request; operations answer once they are done
+async fn handle_request(state: &Arc<ControlState>, request: Request) -> Value {
+    let result: Result<Value, (i64, String)> = match request.method.as_str() {
+        "ping" => Ok(json!("pong")),
+        "status" => Ok(json!({
+            "running": *state.current.lock().expect("current mutex poisoned"),
+        })),
+        "scan" => state.scan().map_err(|e| (-32000, e)),
+        method => match parse_operation(method, &request.params) {
+            Ok(Some(operation)) => match state.start(operation).await {
+                Ok(Ok(())) => Ok(json!({ "ok": true })),
+                Ok(Err(e)) => Err((-32000, e)),
+                Err(_) => Err((-32000, "operation thread stopped".to_string())),
+            },
+            Ok(None) => Err((-32601, format!("unknown method: {}", method))),
+            Err(e) => Err((-32602, e)),
+        },
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": request.id, "result": result }),
+        Err((code, message)) => {
+            json!({ "jsonrpc": "2.0", "id": request.id, "error": { "code": code, "message": message } })
+        }
+    }
+}
+
+/// Serves one client: newline-delimited JSON-RPC requests in, responses and notifications out.
+/// Requests of a client run in order; another client can ask for `status` meanwhile.
+async fn serve_client<S>(state: Arc<ControlState>, stream: S)
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let (out, mut outgoing) = mpsc::unbounded_channel::<String>();
+
+    // Writer: this client's responses and every progress notification
+    let mut events = state.events.subscribe();
+    let writer_task = tokio::spawn(async move {
+        loop {
+            let line = tokio::select! {
+                line = outgoing.recv() => match line {
+                    Some(line) => line,
+                    None => break,
+                },
+                event = events.recv() => match event {
+                    Ok(line) => line,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        debug!("Control client lagging, {} notification(s) dropped", skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            };
+            if writer.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle_request(&state, request).await,
+            Err(e) => json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": e.to_string() } }),
+        };
+        if out.send(response.to_string()).is_err() {
+            break;
+        }
+    }
+    drop(out);
+    writer_task.abort();
+}
+
+/// `--control-socket [path]`: serves a JSON-RPC 2.0 interface (one JSON object per line) on a
+/// Unix socket (`hvtag.sock` in the data folder by default) or a Windows named pipe
+/// (`\\.\pipe\hvtag`), for GUI frontends and scripts. Methods: `ping`, `status`, `scan`,
+/// `import`, `retag` (`rjcode`), `full_retag` (`all`), `refresh` (`older_than`, `fields`). Operations
+/// answer when they are done; meanwhile every client receives `started`, `progress` (one per
+/// work) and `finished` notifications. Operations never prompt, like `--non-interactive`.
+pub async fn run_control_socket(
+    db_path: Option<String>,
+    config: &Config,
+    socket_path: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (events, _) = broadcast::channel(256);
+    let state = Arc::new(ControlState {
+        runner: Arc::new(Runner::new(db_path, config)),
+        events,
+        current: Mutex::new(None),
+    });
+
+    serve(state, socket_path).await
+}
+
+#[cfg(unix)]
+async fn serve(state: Arc<ControlState>, socket_path: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    use std::path::PathBuf;
+
+    let path = match socket_path {
+        Some(path) => PathBuf::from(path),
+        None => crate::paths::control_socket()?,
+    };
+    if path.exists() {
+        // Another server still answering keeps its socket; nobody answering means it was left
+        // behind by a run that was killed
+        if tokio::net::UnixStream::connect(&path).await.is_ok() {
+            return Err(format!("A control socket is already running on {}", path.display()).into());
+        }
+        std::fs::remove_file(&path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(&path)?;
+    info!("hvtag control socket listening on {}", path.display());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve_client(Arc::clone(&state), stream));
+                }
+                Err(e) => warn!("Control socket accept failed: {}", e),
+            },
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    let _ = std::fs::remove_file(&path);
+    info!("Control socket closed");
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn serve(state: Arc<ControlState>, socket_path: Option<String>) -> Result<(), Box<dyn std::error::Error>> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let name = socket_path.unwrap_or_else(|| DEFAULT_PIPE_NAME.to_string());
+    let mut server = ServerOptions::new().first_pipe_instance(true).create(&name)?;
+    info!("hvtag control pipe listening on {}", name);
+
+    loop {
+        tokio::select! {
+            connected = server.connect() => {
+                connected?;
+                // A new instance waits for the next client while this one is served
+                let client = std::mem::replace(&mut server, ServerOptions::new().create(&name)?);
+                tokio::spawn(serve_client(Arc::clone(&state), client));
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+    info!("Control pipe closed");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_operation() {
+        assert!(matches!(parse_operation("import", &Value::Null), Ok(Some(Operation::Import))));
+        assert!(matches!(
+            parse_operation("retag", &json!({ "rjcode": "rj01000001" })),
+            Ok(Some(Operation::Retag(code))) if code == "RJ01000001"
+        ));
+        assert!(parse_operation("retag", &json!({})).is_err());
+        match parse_operation("refresh", &json!({ "older_than": "2w", "fields": ["stars", "release-date"] })) {
+            Ok(Some(Operation::Refresh { older_than_days, fields })) => {
+                assert_eq!(older_than_days, Some(14));
+                assert_eq!(fields, vec![RefreshField::Stars, RefreshField::ReleaseDate]);
+            }
+            _ => panic!("refresh not parsed"),
+        }
+        assert!(matches!(parse_operation("format_disk", &Value::Null), Ok(None)));
+    }
+}
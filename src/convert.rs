@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use rusqlite::Connection;
+use tracing::{debug, info, warn};
+
+use crate::database::conversion::{self, STATUS_CONVERSION_FAILED, STATUS_CONVERTED, STATUS_CONVERTING};
+use crate::database::queries;
+use crate::errors::HvtError;
+use crate::run_stats::{RunStats, WorkOutcome};
+use crate::tagger::converter;
+use crate::tagger::types::AudioFormat;
+
+/// `--convert`: convert-only pipeline. Converts every FLAC/WAV/OGG/M4A file of the library's works to
+/// MP3 in place (at `[tagger] target_bitrate` kbps), without fetching metadata or tagging anything.
+///
+/// 1. Scans every active work folder and registers non-MP3 audio in `file_processing` as
+///    `pending_conversion`
+/// 2. Converts each pending file, tracking `converting` → `converted` / `conversion_failed`
+///
+/// Resumable: an interrupted run leaves rows in `converting`, which the next run picks up again
+/// (finishing a conversion whose temp file was already complete), and failed files are retried.
+pub async fn run_convert_workflow(conn: &Connection, bitrate: u32, stats: &RunStats) -> Result<(), HvtError> {
+    if !converter::is_ffmpeg_available() {
+        return Err(HvtError::AudioConversion(
+            "ffmpeg not found in PATH (required for --convert).".to_string()
+        ));
+    }
+
+    info!("=== CONVERT ===");
+
+    // 1. Register non-MP3 audio found on disk
+    info!("\n--- Scanning library for non-MP3 audio ---");
+    let mut registered = 0usize;
+    for (rjcode, folder_path) in queries::get_all_works_with_paths(conn)? {
+        let entries = match std::fs::read_dir(&folder_path) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("Skipping {}: cannot read {} ({})", rjcode, folder_path.display(), e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let file_path = entry.path();
+            if !file_path.is_file() || !needs_conversion(&file_path) {
+                continue;
+            }
+
+            let file_name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let file_size = entry.metadata().map(|m| m.len() as i64).unwrap_or(0);
+            conversion::register_file_for_conversion(
+                conn, &rjcode, &file_path, file_name, extension, file_size,
+            )?;
+            registered += 1;
+        }
+    }
+    debug!("{} non-MP3 file(s) found on disk", registered);
+
+    // 2. Convert everything still pending
+    let works = conversion::get_works_needing_conversion(conn)?;
+    if works.is_empty() {
+        info!("No works need conversion");
+        return Ok(());
+    }
+
+    info!("\n--- Converting ({} work(s)) ---", works.len());
+    let pb = crate::create_progress_bar(works.len() as u64);
+    let mut converted = 0usize;
+    let mut failed = 0usize;
+
+    for (rjcode, _) in &works {
+        pb.set_message(format!("Converting {}", rjcode));
+        let mut work_failed = 0usize;
+
+        for file_path in conversion::get_files_needing_conversion(conn, rjcode)? {
+            conversion::set_conversion_status(conn, &file_path, STATUS_CONVERTING, None)?;
+
+            match convert_file(&file_path, bitrate, stats).await {
+                Ok(_) => {
+                    conversion::set_conversion_status(conn, &file_path, STATUS_CONVERTED, None)?;
+                    converted += 1;
+                }
+                Err(e) => {
+                    warn!("Failed to convert {}: {}", file_path.display(), e);
+                    conversion::set_conversion_status(
+                        conn, &file_path, STATUS_CONVERSION_FAILED, Some(&e.to_string()),
+                    )?;
+                    work_failed += 1;
+                }
+            }
+        }
+
+        if work_failed == 0 {
+            pb.println(format!("{} ✓", rjcode));
+            stats.record_work(conn, rjcode, "convert", WorkOutcome::Processed, None);
+        } else {
+            pb.println(format!("{} ✗ ({} file(s) failed)", rjcode, work_failed));
+            let error = format!("{} file(s) failed to convert", work_failed);
+            stats.record_work(conn, rjcode, "convert", WorkOutcome::Failed, Some(&error));
+            failed += work_failed;
+        }
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+
+    info!("=== CONVERT COMPLETE: {} file(s) converted, {} failed ===", converted, failed);
+    Ok(())
+}
+
+fn needs_conversion(file_path: &Path) -> bool {
+    let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    AudioFormat::from_extension(extension).needs_conversion()
+}
+
+/// Converts one registered file, first recovering from an interrupted previous attempt:
+/// `convert_to_mp3_in_place` only deletes the original once ffmpeg has succeeded, so a missing
+/// original next to its `.mp3.tmp` means the conversion itself completed and only the final
+/// rename is left to do.
+async fn convert_file(file_path: &Path, bitrate: u32, stats: &RunStats) -> Result<(), HvtError> {
+    if !file_path.exists() {
+        let temp_output = file_path.with_extension("mp3.tmp");
+        let final_path = file_path.with_extension("mp3");
+        if temp_output.exists() {
+            std::fs::rename(&temp_output, &final_path)?;
+            debug!("Recovered interrupted conversion: {}", final_path.display());
+            return Ok(());
+        }
+        if final_path.exists() {
+            return Ok(());
+        }
+        return Err(HvtError::AudioConversion(format!("File not found: {}", file_path.display())));
+    }
+
+    converter::convert_to_mp3_in_place(file_path, bitrate, stats).await
+}
@@ -0,0 +1,241 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::database::queries;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::run_stats::{RunStats, WorkOutcome};
+
+/// Suffix of a file being copied, renamed once complete so an interrupted copy is never taken
+/// for a finished one
+const PART_SUFFIX: &str = ".hvtag-part";
+
+/// Space left free on the destination by the preflight check
+const SPACE_MARGIN_BYTES: u64 = 64 * 1024 * 1024;
+
+/// One file of a work to copy, with the path it gets under the destination
+struct PendingFile {
+    source: PathBuf,
+    target: PathBuf,
+    size: u64,
+}
+
+/// Files under `dir`, recursively, sorted
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<(), HvtError> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_files(&path, out)?;
+        } else if !path.to_string_lossy().ends_with(PART_SUFFIX) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Folder of a work under the destination: its path relative to `library_path` (circle folders
+/// kept), or only its folder name for works outside the library
+fn destination_folder(dest: &Path, folder: &Path, library_path: Option<&Path>) -> PathBuf {
+    match library_path.and_then(|lib| folder.strip_prefix(lib).ok()) {
+        Some(relative) if !relative.as_os_str().is_empty() => dest.join(relative),
+        _ => dest.join(folder.file_name().unwrap_or(folder.as_os_str())),
+    }
+}
+
+/// Whether `target` is a finished copy of `source`: same size, and written after `source` was
+/// last modified (a file re-tagged since keeps its size thanks to the ID3 padding)
+fn is_copied(source: &std::fs::Metadata, target: &Path) -> bool {
+    let Ok(target) = std::fs::metadata(target) else {
+        return false;
+    };
+    let newer = match (source.modified(), target.modified()) {
+        (Ok(source), Ok(target)) => target >= source,
+        _ => true,
+    };
+    target.len() == source.len() && newer
+}
+
+/// Files of a work still to copy: missing at the destination, or changed since they were copied
+fn pending_files(folder: &Path, target_folder: &Path) -> Result<Vec<PendingFile>, HvtError> {
+    let mut files = Vec::new();
+    collect_files(folder, &mut files)?;
+    let mut pending = Vec::new();
+    for source in files {
+        let Ok(relative) = source.strip_prefix(folder) else {
+            continue;
+        };
+        let target = target_folder.join(relative);
+        let metadata = std::fs::metadata(&source)?;
+        if !is_copied(&metadata, &target) {
+            pending.push(PendingFile { source, target, size: metadata.len() });
+        }
+    }
+    Ok(pending)
+}
+
+/// Free space (bytes) of the filesystem holding `path`, from `df` (`PowerShell` on Windows).
+/// `None` when it can't be told.
+fn available_space(path: &Path) -> Option<u64> {
+    if cfg!(target_os = "windows") {
+        let root = path.components().next()?.as_os_str().to_string_lossy().trim_end_matches(':').to_string();
+        let output = std::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", &format!("(Get-PSDrive '{}').Free", root)])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    } else {
+        let output = std::process::Command::new("df").arg("-Pk").arg(path).output().ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let kib: u64 = stdout.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+        Some(kib * 1024)
+    }
+}
+
+/// Copies a file through a `.hvtag-part` file renamed once complete
+fn copy_file(file: &PendingFile) -> Result<(), HvtError> {
+    if let Some(parent) = file.target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut part = file.target.clone().into_os_string();
+    part.push(PART_SUFFIX);
+    let part = PathBuf::from(part);
+    std::fs::copy(&file.source, &part)?;
+    std::fs::rename(&part, &file.target)?;
+    Ok(())
+}
+
+fn format_size(bytes: u64) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= 1024.0 * MIB {
+        format!("{:.1} GiB", bytes as f64 / (1024.0 * MIB))
+    } else {
+        format!("{:.1} MiB", bytes as f64 / MIB)
+    }
+}
+
+/// `--copy <dest>`: copies every tagged work (or only `works`) to `dest`, leaving the library
+/// untouched, e.g. to sync a phone or DAP folder. Works keep their path relative to
+/// `library_path`. Files already copied and unchanged since are skipped, so an interrupted
+/// copy resumes where it stopped; the run stops before copying anything when the
+/// destination lacks the space.
+pub fn run_copy_workflow(conn: &Connection, app_config: &Config, dest: &str, works: &[String], stats: &RunStats) -> Result<(), HvtError> {
+    let selected: Vec<RJCode> = works.iter()
+        .map(|w| RJCode::new(w.to_uppercase()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let targets: Vec<(RJCode, PathBuf)> = queries::get_tagged_works_with_paths(conn)?
+        .into_iter()
+        .filter(|(rjcode, _)| selected.is_empty() || selected.contains(rjcode))
+        .collect();
+    if targets.is_empty() {
+        info!("No tagged work to copy");
+        return Ok(());
+    }
+
+    let dest = Path::new(dest);
+    std::fs::create_dir_all(dest)?;
+    let library_path = app_config.import.library_path.as_deref().map(Path::new);
+
+    // Preflight: what is left to copy, and whether it fits
+    let mut plan: Vec<(RJCode, Vec<PendingFile>)> = Vec::new();
+    let mut up_to_date = 0usize;
+    for (rjcode, folder) in &targets {
+        if !folder.is_dir() {
+            warn!("{} not found on disk, skipping", folder.display());
+            stats.record_work(conn, rjcode, "copy", WorkOutcome::Failed, Some("folder missing"));
+            continue;
+        }
+        let pending = pending_files(folder, &destination_folder(dest, folder, library_path))?;
+        if pending.is_empty() {
+            up_to_date += 1;
+            stats.record_work(conn, rjcode, "copy", WorkOutcome::Skipped, Some("already copied"));
+        } else {
+            plan.push((rjcode.clone(), pending));
+        }
+    }
+    let total_bytes: u64 = plan.iter().flat_map(|(_, files)| files).map(|f| f.size).sum();
+    let total_files: usize = plan.iter().map(|(_, files)| files.len()).sum();
+    info!(
+        "=== COPY TO {}: {} work(s) to copy ({} file(s), {}), {} already copied ===",
+        dest.display(), plan.len(), total_files, format_size(total_bytes), up_to_date
+    );
+    if plan.is_empty() {
+        return Ok(());
+    }
+    match available_space(dest) {
+        Some(free) if free < total_bytes + SPACE_MARGIN_BYTES => {
+            return Err(HvtError::Generic(format!(
+                "Not enough space on {}: {} needed, {} free",
+                dest.display(), format_size(total_bytes), format_size(free)
+            )));
+        }
+        Some(_) => {}
+        None => warn!("Could not check the free space of {}, copying anyway", dest.display()),
+    }
+
+    // Copy, one progress step per file
+    let pb = crate::create_progress_bar(total_files as u64);
+    let (mut copied, mut failed) = (0usize, 0usize);
+    for (rjcode, files) in &plan {
+        let mut error = None;
+        for (i, file) in files.iter().enumerate() {
+            let name = file.source.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            pb.set_message(format!("{} {}", rjcode, name));
+            if let Err(e) = copy_file(file) {
+                error = Some(e.to_string());
+                pb.inc((files.len() - i) as u64);
+                break;
+            }
+            stats.add_file_written();
+            pb.inc(1);
+        }
+        match error {
+            None => {
+                copied += 1;
+                pb.println(format!("{} ✓", rjcode));
+                stats.record_work(conn, rjcode, "copy", WorkOutcome::Processed, None);
+            }
+            Some(e) => {
+                failed += 1;
+                pb.println(format!("{} ✗ ({})", rjcode, e));
+                stats.record_work(conn, rjcode, "copy", WorkOutcome::Failed, Some(&e));
+            }
+        }
+    }
+    pb.finish_and_clear();
+
+    info!("Copied: {} | Already copied: {} | Failed: {}", copied, up_to_date, failed);
+    if failed > 0 {
+        info!("Run the same --copy again to resume");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_destination_folder() {
+        let dest = Path::new("/media/dap/ASMR");
+        let library = Path::new("/library");
+        assert_eq!(
+            destination_folder(dest, Path::new("/library/サークル/RJ01234567"), Some(library)),
+            PathBuf::from("/media/dap/ASMR/サークル/RJ01234567")
+        );
+        assert_eq!(
+            destination_folder(dest, Path::new("/elsewhere/RJ01234567"), Some(library)),
+            PathBuf::from("/media/dap/ASMR/RJ01234567")
+        );
+        assert_eq!(
+            destination_folder(dest, Path::new("/elsewhere/RJ01234567"), None),
+            PathBuf::from("/media/dap/ASMR/RJ01234567")
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures_util::{stream, StreamExt};
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::database::actor::DbHandle;
+use crate::database::queries;
+use crate::dlsite::MetadataClient;
+use crate::folders::types::RJCode;
+use crate::run_stats::{RunStats, WorkOutcome};
+use crate::tagger::cover_art::{self, CoverSettings};
+
+/// `--refresh-covers`: re-downloads, at DLSite's original size, every folder.jpeg narrower than
+/// `min_width` pixels (e.g. the 500x500 covers of older runs). Like `--full-retag`, covers are
+/// only cached while the VPN is up and copied into the work folders once it is down. A cover is
+/// only replaced when the new one is wider.
+pub async fn run_refresh_covers_workflow(
+    db: &Connection,
+    app_config: &Config,
+    min_width: u32,
+    stats: &Arc<RunStats>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if app_config.http_cache.offline {
+        return Err("--refresh-covers downloads covers and cannot run with --offline".into());
+    }
+    let small_covers: Vec<(RJCode, PathBuf, u32)> = queries::get_all_works_with_paths(db)?
+        .into_iter()
+        .filter_map(|(rjcode, folder_path)| {
+            let (width, _) = cover_art::cover_dimensions(&folder_path)?;
+            (width < min_width).then_some((rjcode, folder_path, width))
+        })
+        .collect();
+    if small_covers.is_empty() {
+        info!("No cover narrower than {} px", min_width);
+        return Ok(());
+    }
+
+    info!("=== REFRESH COVERS: {} cover(s) narrower than {} px ===", small_covers.len(), min_width);
+
+    // ===== VPN PHASE: cache the original-size covers =====
+    let db_thread = DbHandle::open_beside(db)?;
+    let vpn_manager = crate::connect_vpn_if_enabled(app_config)?;
+    let client = MetadataClient::from_config(app_config, stats)?;
+    let pb = crate::create_progress_bar(small_covers.len() as u64);
+    pb.set_message("Covers");
+    // Original size: these covers are replaced because they are too small
+    let cover_settings = CoverSettings::from_config(&app_config.images).original_size();
+    let downloads = small_covers.iter().map(|(rjcode, _, _)| {
+        let (pb, client, db_thread, cover_settings) = (&pb, &client, db_thread.as_ref(), &cover_settings);
+        async move {
+            let cached = match cover_art::download_cover_with_fallback(db, db_thread, rjcode, client, cover_settings).await {
+                Ok(_) => true,
+                Err(e) => {
+                    warn!("Failed to download cover for {}: {}", rjcode, e);
+                    pb.println(format!("{} ✗", rjcode));
+                    false
+                }
+            };
+            pb.inc(1);
+            cached
+        }
+    });
+    let cached: Vec<bool> = stream::iter(downloads).buffered(app_config.images.cover_parallelism.max(1)).collect().await;
+    pb.finish_and_clear();
+    crate::disconnect_vpn(vpn_manager)?;
+
+    // ===== POST-VPN PHASE: replace the covers =====
+    let mut replaced = 0usize;
+    let mut not_larger = 0usize;
+    let mut failed = 0usize;
+    for ((rjcode, folder_path, old_width), was_cached) in small_covers.iter().zip(cached) {
+        if !was_cached {
+            stats.record_work(db, rjcode, "refresh-covers", WorkOutcome::Failed, Some("no working cover source"));
+            failed += 1;
+            continue;
+        }
+
+        match cover_art::cached_cover_dimensions(rjcode.as_str()) {
+            Some((new_width, _)) if new_width > *old_width => {
+                if let Err(e) = cover_art::copy_cover_from_cache(rjcode.as_str(), folder_path, stats) {
+                    warn!("Failed to replace cover of {}: {}", rjcode, e);
+                    stats.record_work(db, rjcode, "refresh-covers", WorkOutcome::Failed, Some(&e.to_string()));
+                    failed += 1;
+                    continue;
+                }
+                info!("{}: {} px -> {} px", rjcode, old_width, new_width);
+                stats.record_work(db, rjcode, "refresh-covers", WorkOutcome::Processed, None);
+                replaced += 1;
+            }
+            _ => {
+                cover_art::discard_cached_cover(rjcode.as_str());
+                stats.record_work(db, rjcode, "refresh-covers", WorkOutcome::Skipped, None);
+                not_larger += 1;
+            }
+        }
+    }
+
+    info!(
+        "=== REFRESH COVERS COMPLETE: {} replaced, {} without a larger cover on DLSite, {} failed ===",
+        replaced, not_larger, failed
+    );
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use dialoguer::{Select, Confirm, theme::ColorfulTheme};
+use rusqlite::Connection;
+use crate::errors::HvtError;
+use crate::database::custom_cvs;
+
+pub fn run_interactive_cv_manager(conn: &Connection) -> Result<(), HvtError> {
+    loop {
+        // Main menu
+        let options = vec![
+            "Review duplicate CV candidates",
+            "Merge two CVs manually",
+            "View merged aliases",
+            "Unmerge an alias",
+            "Exit"
+        ];
+
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("CV Manager - Main Menu")
+            .items(&options)
+            .default(0)
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+        match selection {
+            0 => review_duplicate_candidates(conn)?,
+            1 => merge_manually(conn)?,
+            2 => view_aliases(conn)?,
+            3 => unmerge_alias(conn)?,
+            4 => {
+                println!("Exiting CV manager...");
+                break;
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(())
+}
+
+/// Walks through groups of CV names that look like variants of one another (same name once
+/// spaces/separators/kana are folded) and lets the user pick the name to keep for each group.
+fn review_duplicate_candidates(conn: &Connection) -> Result<(), HvtError> {
+    let groups = custom_cvs::find_duplicate_cv_groups(conn)?;
+
+    if groups.is_empty() {
+        println!("\nNo duplicate candidates found.");
+        return Ok(());
+    }
+
+    println!("\n{} group(s) of possible duplicates found.", groups.len());
+
+    for (index, group) in groups.iter().enumerate() {
+        println!("\n=== Group {}/{} ===", index + 1, groups.len());
+        for (name, count) in group {
+            println!("  {} ({} work(s))", name, count);
+        }
+
+        let mut options: Vec<String> = group.iter()
+            .map(|(name, count)| format!("Same person, keep '{}' ({} work(s))", name, count))
+            .collect();
+        options.push("Different people - skip".to_string());
+        options.push("Stop reviewing".to_string());
+
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Merge these names?")
+            .items(&options)
+            .default(group.len())
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+        if selection == group.len() {
+            continue;
+        }
+        if selection == group.len() + 1 {
+            break;
+        }
+
+        let canonical = &group[selection].0;
+        let mut files_marked = 0;
+        for (name, _) in group.iter().filter(|(name, _)| name != canonical) {
+            files_marked += custom_cvs::merge_cv_alias(conn, name, canonical)?;
+        }
+        println!("✓ Merged into '{}'", canonical);
+        print_files_marked(files_marked);
+    }
+
+    Ok(())
+}
+
+fn merge_manually(conn: &Connection) -> Result<(), HvtError> {
+    let cvs = custom_cvs::list_all_cvs_with_counts(conn, custom_cvs::DEFAULT_CV_SORT)?;
+
+    if cvs.len() < 2 {
+        println!("\nNot enough CVs in database to merge.");
+        return Ok(());
+    }
+
+    let cv_displays: Vec<String> = cvs.iter()
+        .map(|(_id, name_jp, _name_en, custom_name, count)| match custom_name {
+            Some(custom) => format!("{} [custom: {}] ({} work(s))", name_jp, custom, count),
+            None => format!("{} ({} work(s))", name_jp, count),
+        })
+        .collect();
+
+    let alias_index = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select the name variant to merge (it will be credited under the other name)")
+        .items(&cv_displays)
+        .default(0)
+        .interact()
+        .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+    let canonical_index = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select the CV to merge it into")
+        .items(&cv_displays)
+        .default(0)
+        .interact()
+        .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+    if alias_index == canonical_index {
+        println!("Cannot merge a CV into itself. Cancelled.");
+        return Ok(());
+    }
+
+    let alias = &cvs[alias_index].1;
+    let canonical = &cvs[canonical_index].1;
+
+    let confirm = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Credit all {} work(s) of '{}' to '{}'?", cvs[alias_index].4, alias, canonical))
+        .default(true)
+        .interact()
+        .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))?;
+
+    if !confirm {
+        println!("Cancelled.");
+        return Ok(());
+    }
+
+    let files_marked = custom_cvs::merge_cv_alias(conn, alias, canonical)?;
+    println!("\n✓ '{}' merged into '{}'", alias, canonical);
+    print_files_marked(files_marked);
+
+    Ok(())
+}
+
+fn view_aliases(conn: &Connection) -> Result<(), HvtError> {
+    let aliases = custom_cvs::list_cv_aliases(conn)?;
+
+    if aliases.is_empty() {
+        println!("\nNo merged aliases.");
+        return Ok(());
+    }
+
+    println!("\n=== Merged CV aliases ===");
+    for (alias, canonical) in &aliases {
+        println!("  {} → {}", alias, canonical);
+    }
+    println!("\nTotal: {} alias(es)", aliases.len());
+    println!();
+
+    Ok(())
+}
+
+fn unmerge_alias(conn: &Connection) -> Result<(), HvtError> {
+    let aliases = custom_cvs::list_cv_aliases(conn)?;
+
+    if aliases.is_empty() {
+        println!("\nNo merged aliases.");
+        return Ok(());
+    }
+
+    let displays: Vec<String> = aliases.iter()
+        .map(|(alias, canonical)| format!("{} → {}", alias, canonical))
+        .collect();
+
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Select an alias to unmerge")
+        .items(&displays)
+        .default(0)
+        .interact()
+        .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+    let (alias, _) = &aliases[selection];
+    let files_marked = custom_cvs::remove_cv_alias(conn, alias)?;
+    println!("\n✓ '{}' is credited under its own name again", alias);
+    print_files_marked(files_marked);
+
+    Ok(())
+}
+
+fn print_files_marked(files_marked: usize) {
+    if files_marked > 0 {
+        println!("✓ {} file(s) marked for re-tagging", files_marked);
+        println!("  Run --tag to apply changes to all affected works");
+    }
+}
@@ -1,22 +1,43 @@
 use rusqlite::Connection;
 
-use crate::{database::{sql::{init_db, init_table}, tables::*}, errors::HvtError};
+use crate::{database::tables::*, errors::HvtError};
 
 pub mod db_loader;
 pub mod migration;
 pub mod queries;
-pub mod sql;
 pub mod tables;
 pub mod custom_tags;
 pub mod custom_circles;
 pub mod custom_cvs;
 pub mod web_queries;
+pub mod integrity;
+pub mod conversion;
+pub mod search;
+pub mod library_import;
+pub mod backup;
+pub mod tag_history;
+pub mod review_queue;
+pub mod tag_rules;
+pub mod tag_categories;
+pub mod work_overrides;
+pub mod work_groups;
+pub mod normalization_log;
+pub mod sales_history;
+pub mod ranking_history;
+pub mod cover_failures;
+pub mod actor;
+pub mod db_path;
+
+/// DDL only: table names and columns are the constants of `tables`, never values
+fn init_table(name: &str, cols: &str) -> String {
+    format!("CREATE TABLE IF NOT EXISTS {name} ({cols})")
+}
 
 pub fn init(conn: &Connection) -> Result<(), HvtError> {
     // Ensure foreign keys are enabled (additional safety check)
     conn.execute("PRAGMA foreign_keys = ON", [])?;
 
-    conn.execute(&init_db(), [])?;
+    conn.execute("CREATE TABLE IF NOT EXISTS db_init AS SELECT datetime() AS init_dte", [])?;
     conn.execute(&init_table(DB_FOLDERS_NAME, DB_FOLDERS_COLS), [])?;
     conn.execute(&init_table(DB_DLSITE_SCAN_NAME, DB_DLSITE_SCAN_COLS), [])?;
     conn.execute(&init_table(DB_DLSITE_TAG_NAME, DB_DLSITE_TAG_COLS), [])?;
@@ -26,11 +47,16 @@ pub fn init(conn: &Connection) -> Result<(), HvtError> {
     conn.execute(&init_table(DB_RELEASE_DATE_NAME, DB_RELEASE_DATE_COLS), [])?;
     conn.execute(&init_table(DB_RATING_NAME, DB_RATING_COLS), [])?;
     conn.execute(&init_table(DB_STARS_NAME, DB_STARS_COLS), [])?;
+    conn.execute(&init_table(DB_REVIEWS_NAME, DB_REVIEWS_COLS), [])?;
+    conn.execute(&init_table(DB_WORK_TYPES_NAME, DB_WORK_TYPES_COLS), [])?;
     conn.execute(&init_table(DB_WORKS_NAME, DB_WORKS_COLS), [])?;
     conn.execute(&init_table(DB_CVS_NAME, DB_CVS_COLS), [])?;
     conn.execute(&init_table(DB_LKP_WORK_CVS_NAME, DB_LKP_WORK_CVS_COLS), [])?;
     conn.execute(&init_table(DB_DLSITE_ERRORS_NAME, DB_DLSITE_ERRORS_COLS), [])?;
     conn.execute(&init_table(DB_DLSITE_COVERS_LINK_NAME, DB_DLSITE_COVERS_LINK_COLS), [])?;
+    conn.execute(&init_table(DB_DESCRIPTION_NAME, DB_DESCRIPTION_COLS), [])?;
+    conn.execute(&init_table(DB_WORK_RELATIONS_NAME, DB_WORK_RELATIONS_COLS), [])?;
+    conn.execute(&init_table(DB_WORK_IMAGES_NAME, DB_WORK_IMAGES_COLS), [])?;
 
     // New tables for enhanced tracking and historization
     conn.execute(&init_table(DB_FILE_PROCESSING_NAME, DB_FILE_PROCESSING_COLS), [])?;
@@ -53,6 +79,47 @@ pub fn init(conn: &Connection) -> Result<(), HvtError> {
     conn.execute(DB_FILE_PROCESSING_INDEX_FLD_ID, [])?;
     conn.execute(DB_FILE_PROCESSING_INDEX_TAG_DATE, [])?;
 
+    // Audio integrity check results (--verify)
+    conn.execute(&init_table(DB_FILE_INTEGRITY_NAME, DB_FILE_INTEGRITY_COLS), [])?;
+    conn.execute(DB_FILE_INTEGRITY_INDEX_FLD_ID, [])?;
+
+    // Initial library import queue (--import-library)
+    conn.execute(&init_table(DB_LIBRARY_IMPORT_QUEUE_NAME, DB_LIBRARY_IMPORT_QUEUE_COLS), [])?;
+
+    // Health snapshots + DB backups around bulk runs
+    conn.execute(&init_table(DB_HEALTH_SNAPSHOTS_NAME, DB_HEALTH_SNAPSHOTS_COLS), [])?;
+
+    // Decisions deferred by unattended runs (--non-interactive)
+    conn.execute(&init_table(DB_REVIEW_QUEUE_NAME, DB_REVIEW_QUEUE_COLS), [])?;
+
+    // Regex/priority/max-count tag filtering rules (--manage-tags)
+    conn.execute(&init_table(DB_TAG_RULES_NAME, DB_TAG_RULES_COLS), [])?;
+
+    // Tag categories written to their own frames (--manage-tags)
+    conn.execute(&init_table(DB_TAG_CATEGORIES_NAME, DB_TAG_CATEGORIES_COLS), [])?;
+    conn.execute(&init_table(DB_LKP_TAG_CATEGORY_NAME, DB_LKP_TAG_CATEGORY_COLS), [])?;
+
+    // Per-work metadata overrides (--manage-works)
+    conn.execute(&init_table(DB_WORK_OVERRIDES_NAME, DB_WORK_OVERRIDES_COLS), [])?;
+
+    // Works split across folders, tagged as one album (--manage-works)
+    conn.execute(&init_table(DB_WORK_GROUPS_NAME, DB_WORK_GROUPS_COLS), [])?;
+    conn.execute(&init_table(DB_LKP_WORK_GROUP_NAME, DB_LKP_WORK_GROUP_COLS), [])?;
+
+    // Files moved by the folder normalization (--normalize --undo)
+    conn.execute(&init_table(DB_NORMALIZATION_LOG_NAME, DB_NORMALIZATION_LOG_COLS), [])?;
+
+    // Price and popularity of works over time (--stats --work)
+    conn.execute(&init_table(DB_SALES_HISTORY_NAME, DB_SALES_HISTORY_COLS), [])?;
+    conn.execute(DB_SALES_HISTORY_INDEX, [])?;
+    conn.execute(&init_table(DB_RANKING_HISTORY_NAME, DB_RANKING_HISTORY_COLS), [])?;
+
+    // Cover URLs that keep failing (skipped by cover downloads)
+    conn.execute(&init_table(DB_COVER_FAILURES_NAME, DB_COVER_FAILURES_COLS), [])?;
+
+    // Full-text search index (--search)
+    conn.execute(&format!("CREATE VIRTUAL TABLE IF NOT EXISTS {DB_WORKS_FTS_NAME} USING fts5({DB_WORKS_FTS_COLS})"), [])?;
+
     // Run migrations to add new columns to existing tables
     migration::migrate_schema(conn)?;
 
@@ -0,0 +1,115 @@
+use std::sync::mpsc;
+use std::thread;
+
+use rusqlite::Connection;
+use tokio::sync::oneshot;
+use tracing::debug;
+
+use crate::database::db_loader;
+use crate::errors::HvtError;
+
+type Job = Box<dyn FnOnce(&Connection) + Send>;
+
+/// Sends statements to the database thread; clones share it. The thread stops once every handle
+/// is dropped.
+///
+/// A second connection, owned by a dedicated thread that runs its statements one after the other,
+/// for the writes made away from the workflow's own connection: the files recorded by the blocking
+/// tagging jobs (`TaggedFile::record_on`) and the cover failures of the concurrent cover downloads
+/// (`cover_art::record_cover_failure`). Nothing else goes through it: metadata fetches, processing
+/// history and moves still use the workflow's connection on the main task, held across awaits.
+/// Workflows open it with `open_beside` and pass it down; without it (in-memory database) those
+/// two writes fall back to the workflow's connection.
+#[derive(Clone)]
+pub struct DbHandle {
+    jobs: mpsc::Sender<Job>,
+}
+
+impl DbHandle {
+    /// Opens a second connection to the database `conn` is opened on and starts its thread.
+    /// `None` for an in-memory database.
+    pub fn open_beside(conn: &Connection) -> Result<Option<Self>, HvtError> {
+        let Some(path) = conn.path().filter(|p| !p.is_empty()) else {
+            return Ok(None);
+        };
+        let db = db_loader::open_db(Some(path))?;
+        Ok(Some(Self::spawn(db)))
+    }
+
+    /// Moves `conn` to a dedicated thread
+    pub fn spawn(conn: Connection) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            for job in queue {
+                job(&conn);
+            }
+            debug!("Database thread stopped");
+        });
+        DbHandle { jobs }
+    }
+
+    fn submit<T, F>(&self, f: F) -> Result<oneshot::Receiver<Result<T, HvtError>>, HvtError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, HvtError> + Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        self.jobs
+            .send(Box::new(move |conn| {
+                let _ = reply.send(f(conn));
+            }))
+            .map_err(|_| HvtError::Generic("the database thread has stopped".to_string()))?;
+        Ok(result)
+    }
+
+    /// Runs `f` on the database thread, waiting for it without blocking the runtime
+    pub async fn call<T, F>(&self, f: F) -> Result<T, HvtError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, HvtError> + Send + 'static,
+    {
+        self.submit(f)?
+            .await
+            .map_err(|_| HvtError::Generic("the database thread dropped a statement".to_string()))?
+    }
+
+    /// Runs `f` on the database thread from a blocking thread (never from async code)
+    pub fn call_blocking<T, F>(&self, f: F) -> Result<T, HvtError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> Result<T, HvtError> + Send + 'static,
+    {
+        self.submit(f)?
+            .blocking_recv()
+            .map_err(|_| HvtError::Generic("the database thread dropped a statement".to_string()))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::{stream, StreamExt};
+
+    #[test]
+    fn test_statements_run_on_the_database_thread() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE t (n INTEGER)", []).unwrap();
+        let db = DbHandle::spawn(conn);
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let writes = (1..=3).map(|n| {
+            let db = db.clone();
+            async move { db.call(move |conn| Ok(conn.execute("INSERT INTO t VALUES (?1)", [n])?)).await }
+        });
+        let inserted: usize = runtime.block_on(stream::iter(writes).buffered(3).collect::<Vec<_>>()).into_iter().map(|r| r.unwrap()).sum();
+        assert_eq!(inserted, 3);
+
+        let total = std::thread::spawn(move || {
+            db.call_blocking(|conn| Ok(conn.query_row("SELECT SUM(n) FROM t", [], |row| row.get::<_, i64>(0))?))
+        })
+        .join()
+        .unwrap()
+        .unwrap();
+        assert_eq!(total, 6);
+    }
+}
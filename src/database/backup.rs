@@ -0,0 +1,144 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use rusqlite::{params, Connection};
+
+use crate::database::{tables::*, web_queries};
+use crate::errors::HvtError;
+
+/// Backups are written next to the database, in this subfolder.
+const BACKUP_DIR_NAME: &str = "backups";
+const BACKUP_FILE_PREFIX: &str = "data-";
+const BACKUP_FILE_EXT: &str = ".db3";
+
+/// Summary counts of the library, recorded before and after bulk runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HealthSnapshot {
+    pub active_works: i64,
+    pub tagged_works: i64,
+    pub circles: i64,
+    pub cvs: i64,
+    pub tags: i64,
+    pub unresolved_errors: i64,
+}
+
+impl HealthSnapshot {
+    /// Labelled counts, in display order.
+    pub fn fields(&self) -> [(&'static str, i64); 6] {
+        [
+            ("works", self.active_works),
+            ("tagged", self.tagged_works),
+            ("circles", self.circles),
+            ("CVs", self.cvs),
+            ("tags", self.tags),
+            ("unresolved errors", self.unresolved_errors),
+        ]
+    }
+}
+
+/// Counts the current state of the library.
+pub fn take_health_snapshot(conn: &Connection) -> Result<HealthSnapshot, HvtError> {
+    let count = |sql: String| -> Result<i64, HvtError> { Ok(conn.query_row(&sql, [], |row| row.get(0))?) };
+
+    Ok(HealthSnapshot {
+        active_works: web_queries::count_all_active_works(conn)?,
+        tagged_works: web_queries::count_tagged_works(conn)?,
+        circles: count(format!("SELECT COUNT(*) FROM {DB_CIRCLE_NAME}"))?,
+        cvs: count(format!("SELECT COUNT(*) FROM {DB_CVS_NAME}"))?,
+        tags: count(format!("SELECT COUNT(*) FROM {DB_DLSITE_TAG_NAME}"))?,
+        unresolved_errors: count(format!(
+            "SELECT COUNT(*) FROM {DB_DLSITE_ERRORS_NAME} WHERE COALESCE(is_resolved, 0) = 0"
+        ))?,
+    })
+}
+
+/// Stores a snapshot row. `phase` is 'before' or 'after'.
+pub fn record_health_snapshot(
+    conn: &Connection,
+    run_name: &str,
+    phase: &str,
+    works_affected: usize,
+    snapshot: &HealthSnapshot,
+    backup_path: Option<&Path>,
+) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {DB_HEALTH_SNAPSHOTS_NAME}
+             (run_name, phase, works_affected, active_works, tagged_works, circles, cvs, tags,
+              unresolved_errors, backup_path)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+        ),
+        params![
+            run_name,
+            phase,
+            works_affected as i64,
+            snapshot.active_works,
+            snapshot.tagged_works,
+            snapshot.circles,
+            snapshot.cvs,
+            snapshot.tags,
+            snapshot.unresolved_errors,
+            backup_path.map(|p| p.display().to_string()),
+        ],
+    )?;
+    Ok(())
+}
+
+/// Folder holding the backups of the database `conn` is opened on.
+fn backup_dir(conn: &Connection) -> Result<PathBuf, HvtError> {
+    let db_path = conn
+        .path()
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| HvtError::Generic("In-memory database cannot be backed up".to_string()))?;
+    let parent = Path::new(db_path).parent().unwrap_or_else(|| Path::new("."));
+    Ok(parent.join(BACKUP_DIR_NAME))
+}
+
+/// Writes a consistent copy of the database to `backups/data-YYYYMMDD-HHMMSS.db3` (UTC)
+/// with `VACUUM INTO`, and returns its path.
+pub fn backup_database(conn: &Connection) -> Result<PathBuf, HvtError> {
+    let dir = backup_dir(conn)?;
+    if !dir.exists() {
+        fs::create_dir_all(&dir).map_err(|_| HvtError::PathCreationFailed(dir.display().to_string()))?;
+    }
+
+    let timestamp = crate::timestamp::file_stamp(crate::timestamp::now());
+    let mut path = dir.join(format!("{BACKUP_FILE_PREFIX}{timestamp}{BACKUP_FILE_EXT}"));
+    // Two bulk runs within the same second: keep both backups
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{BACKUP_FILE_PREFIX}{timestamp}-{n}{BACKUP_FILE_EXT}"));
+        n += 1;
+    }
+
+    conn.execute("VACUUM INTO ?1", params![path.display().to_string()])?;
+    Ok(path)
+}
+
+/// Deletes the oldest backups so that at most `keep` remain. Returns how many were deleted.
+pub fn prune_backups(conn: &Connection, keep: usize) -> Result<usize, HvtError> {
+    let dir = backup_dir(conn)?;
+    if !dir.exists() {
+        return Ok(0);
+    }
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(BACKUP_FILE_PREFIX) && n.ends_with(BACKUP_FILE_EXT))
+        })
+        .collect();
+    if backups.len() <= keep {
+        return Ok(0);
+    }
+
+    // Timestamped names sort chronologically
+    backups.sort();
+    let excess = backups.len() - keep;
+    for old in &backups[..excess] {
+        fs::remove_file(old)?;
+    }
+    Ok(excess)
+}
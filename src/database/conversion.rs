@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection};
+
+use crate::database::db_path::{DbPath, DbPathRef};
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+// `file_processing.processing_status` values used by the convert-only pipeline (`--convert`).
+// A row stuck in `converting` means a previous run was interrupted mid-file; it is picked up
+// again like `pending_conversion`. Failed rows are retried on every run.
+pub const STATUS_PENDING_CONVERSION: &str = "pending_conversion";
+pub const STATUS_CONVERTING: &str = "converting";
+pub const STATUS_CONVERTED: &str = "converted";
+pub const STATUS_CONVERSION_FAILED: &str = "conversion_failed";
+
+/// Register a non-MP3 audio file found on disk as needing conversion. A row already marked
+/// `converted` for the same path is reset, since the file being back on disk means it was
+/// re-added after the previous conversion; any other existing row is left as-is.
+pub fn register_file_for_conversion(
+    conn: &Connection,
+    rjcode: &RJCode,
+    file_path: &Path,
+    file_name: &str,
+    extension: &str,
+    file_size: i64,
+) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {DB_FILE_PROCESSING_NAME}
+             (fld_id, file_path, file_name, file_extension, file_size_bytes, is_converted, processing_status)
+             VALUES (
+                 (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1),
+                 ?2, ?3, ?4, ?5, 0, ?6
+             )
+             ON CONFLICT(file_path) DO UPDATE SET
+                 is_converted = 0,
+                 convert_date = NULL,
+                 conversion_error = NULL,
+                 processing_status = excluded.processing_status
+             WHERE processing_status = '{STATUS_CONVERTED}'"
+        ),
+        params![rjcode, DbPathRef(file_path), file_name, extension, file_size, STATUS_PENDING_CONVERSION],
+    )?;
+    Ok(())
+}
+
+/// Get active works that still have non-MP3 audio waiting for conversion (pending, interrupted
+/// or previously failed), with their registered paths.
+pub fn get_works_needing_conversion(conn: &Connection) -> Result<Vec<(RJCode, PathBuf)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT DISTINCT f.rjcode, f.path
+         FROM {DB_FOLDERS_NAME} f
+         INNER JOIN {DB_FILE_PROCESSING_NAME} fp ON fp.fld_id = f.fld_id
+         WHERE f.active = 1
+           AND fp.processing_status IN (?1, ?2, ?3)
+         ORDER BY f.rjcode"
+    ))?;
+    let rows = stmt.query_map(
+        params![STATUS_PENDING_CONVERSION, STATUS_CONVERTING, STATUS_CONVERSION_FAILED],
+        |row| Ok((row.get(0)?, row.get::<_, DbPath>(1)?.0)),
+    )?;
+    let works: Vec<(RJCode, PathBuf)> = rows.collect::<Result<Vec<_>, _>>()?;
+    Ok(works)
+}
+
+/// Get the file paths of a work still waiting for conversion.
+pub fn get_files_needing_conversion(conn: &Connection, rjcode: &RJCode) -> Result<Vec<PathBuf>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT file_path FROM {DB_FILE_PROCESSING_NAME}
+         WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)
+           AND processing_status IN (?2, ?3, ?4)
+         ORDER BY file_path"
+    ))?;
+    let rows = stmt.query_map(
+        params![rjcode, STATUS_PENDING_CONVERSION, STATUS_CONVERTING, STATUS_CONVERSION_FAILED],
+        |row| row.get::<_, DbPath>(0).map(PathBuf::from),
+    )?;
+    let files: Vec<PathBuf> = rows.collect::<Result<Vec<_>, _>>()?;
+    Ok(files)
+}
+
+/// Update the conversion status of a file. `converted` also sets `is_converted`/`convert_date`;
+/// `error` is stored in `conversion_error` (cleared when `None`).
+pub fn set_conversion_status(
+    conn: &Connection,
+    file_path: &Path,
+    status: &str,
+    error: Option<&str>,
+) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "UPDATE {DB_FILE_PROCESSING_NAME}
+             SET processing_status = ?2,
+                 conversion_error = ?3,
+                 is_converted = CASE WHEN ?2 = '{STATUS_CONVERTED}' THEN 1 ELSE is_converted END,
+                 convert_date = CASE WHEN ?2 = '{STATUS_CONVERTED}' THEN datetime('now') ELSE convert_date END,
+                 last_processed = datetime('now')
+             WHERE file_path = ?1"
+        ),
+        params![DbPathRef(file_path), status, error],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// Records a failed download of a cover URL, counting the failures of the same URL
+pub fn record_failure(conn: &Connection, rjcode: &RJCode, url: &str, error: &str) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {DB_COVER_FAILURES_NAME} (url, rjcode, last_error) VALUES (?1, ?2, ?3)
+             ON CONFLICT(url) DO UPDATE SET failures = failures + 1, last_error = ?3,
+                 last_failure = datetime('now')"
+        ),
+        params![url, rjcode.as_str(), error],
+    )?;
+    Ok(())
+}
+
+/// Forgets the failures of a URL that was downloaded
+pub fn clear(conn: &Connection, url: &str) -> Result<(), HvtError> {
+    conn.execute(&format!("DELETE FROM {DB_COVER_FAILURES_NAME} WHERE url = ?1"), params![url])?;
+    Ok(())
+}
+
+/// Whether a URL is not worth trying: it failed `max_failures` times (0: never given up on), or
+/// less than `cooldown_hours` ago
+pub fn is_skipped(conn: &Connection, url: &str, max_failures: u32, cooldown_hours: u64) -> Result<bool, HvtError> {
+    let skipped = conn
+        .query_row(
+            &format!(
+                "SELECT (?2 > 0 AND failures >= ?2)
+                     OR last_failure > datetime('now', '-' || ?3 || ' hours')
+                 FROM {DB_COVER_FAILURES_NAME} WHERE url = ?1"
+            ),
+            params![url, max_failures, cooldown_hours as i64],
+            |row| row.get::<_, bool>(0),
+        )
+        .optional()?;
+    Ok(skipped.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skipped_after_failures() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        let url = "https://img.dlsite.jp/modpub/images2/work/doujin/RJ01235000/RJ01234567_img_main.jpg";
+
+        assert!(!is_skipped(&conn, url, 3, 0).unwrap());
+        record_failure(&conn, &work, url, "HTTP 404").unwrap();
+        // Within the cooldown, then not anymore
+        assert!(is_skipped(&conn, url, 3, 24).unwrap());
+        assert!(!is_skipped(&conn, url, 3, 0).unwrap());
+        record_failure(&conn, &work, url, "HTTP 404").unwrap();
+        record_failure(&conn, &work, url, "HTTP 404").unwrap();
+        assert!(is_skipped(&conn, url, 3, 0).unwrap());
+        assert!(!is_skipped(&conn, url, 0, 0).unwrap());
+
+        clear(&conn, url).unwrap();
+        assert!(!is_skipped(&conn, url, 3, 24).unwrap());
+    }
+}
@@ -1,5 +1,9 @@
+use std::collections::BTreeMap;
+
 use rusqlite::{params, Connection};
+use unicode_normalization::UnicodeNormalization;
 
+use crate::config::CvNameStyle;
 use crate::database::tables::*;
 use crate::errors::HvtError;
 use crate::folders::types::RJCode;
@@ -69,14 +73,16 @@ pub fn remove_custom_cv_mapping(conn: &Connection, cv_name_jp: &str) -> Result<(
     Ok(())
 }
 
-/// Get merged CVs for a work (DLSite cvs + global custom rename applied), deduped.
+/// Get merged CVs for a work (DLSite cvs + aliases resolved to their canonical CV + global
+/// custom rename applied), deduped.
 /// This is the function the tagger calls instead of reading `cvs.name_jp` raw, so a rename
 /// actually reaches the ID3 `artist` tag, not just the web UI display.
 pub fn get_merged_cvs_for_work(conn: &Connection, work: &RJCode) -> Result<Vec<String>, HvtError> {
     let mut stmt = conn.prepare(&format!(
-        "SELECT COALESCE(ccvm.custom_name, cv.name_jp) AS final_name
+        "SELECT COALESCE(ccvm.custom_name, canon.name_jp) AS final_name
          FROM {DB_CVS_NAME} cv
-         LEFT JOIN {DB_CUSTOM_CV_MAPPINGS_NAME} ccvm ON ccvm.cv_id = cv.cv_id
+         JOIN {DB_CVS_NAME} canon ON canon.cv_id = COALESCE(cv.alias_of, cv.cv_id)
+         LEFT JOIN {DB_CUSTOM_CV_MAPPINGS_NAME} ccvm ON ccvm.cv_id = canon.cv_id
          WHERE cv.cv_id IN (
              SELECT cv_id FROM {DB_LKP_WORK_CVS_NAME} WHERE fld_id = (
                  SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1
@@ -96,15 +102,63 @@ pub fn get_merged_cvs_for_work(conn: &Connection, work: &RJCode) -> Result<Vec<S
     Ok(cvs)
 }
 
-/// Mark all works featuring a specific CV for re-tagging.
+/// CV names for a work's artist tag: like `get_merged_cvs_for_work`, but CVs without a custom
+/// name are written in the configured `style` (English name when DLsite provided one).
+pub fn get_cv_artists_for_work(
+    conn: &Connection,
+    work: &RJCode,
+    style: CvNameStyle,
+) -> Result<Vec<String>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT ccvm.custom_name, canon.name_jp,
+                COALESCE(NULLIF(canon.name_en, ''), NULLIF(cv.name_en, '')) AS name_en
+         FROM {DB_CVS_NAME} cv
+         JOIN {DB_CVS_NAME} canon ON canon.cv_id = COALESCE(cv.alias_of, cv.cv_id)
+         LEFT JOIN {DB_CUSTOM_CV_MAPPINGS_NAME} ccvm ON ccvm.cv_id = canon.cv_id
+         WHERE cv.cv_id IN (
+             SELECT cv_id FROM {DB_LKP_WORK_CVS_NAME} WHERE fld_id = (
+                 SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1
+             )
+         )"
+    ))?;
+
+    let mut cvs: Vec<String> = stmt
+        .query_map(params![work.as_str()], |row| {
+            let custom_name: Option<String> = row.get(0)?;
+            let name_jp: String = row.get(1)?;
+            let name_en: Option<String> = row.get(2)?;
+            Ok(custom_name.unwrap_or_else(|| cv_display_name(style, &name_jp, name_en.as_deref())))
+        })?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    cvs.sort();
+    cvs.dedup();
+
+    Ok(cvs)
+}
+
+/// Formats a CV name according to the `[tagger] cv_name_style` preference.
+pub fn cv_display_name(style: CvNameStyle, name_jp: &str, name_en: Option<&str>) -> String {
+    let name_en = name_en.map(str::trim).filter(|en| !en.is_empty() && *en != name_jp);
+    match (style, name_en) {
+        (CvNameStyle::En, Some(en)) => en.to_string(),
+        (CvNameStyle::JpEn, Some(en)) => format!("{} ({})", name_jp, en),
+        _ => name_jp.to_string(),
+    }
+}
+
+/// Mark all works featuring a specific CV (or one of its aliases) for re-tagging.
 pub fn mark_works_for_retagging(conn: &Connection, cv_name_jp: &str) -> Result<usize, HvtError> {
     let rows_affected = conn.execute(
         &format!(
             "UPDATE {DB_FILE_PROCESSING_NAME}
              SET tag_date = NULL, is_tagged = 0
              WHERE fld_id IN (
-                 SELECT fld_id FROM {DB_LKP_WORK_CVS_NAME} WHERE cv_id = (
-                     SELECT cv_id FROM {DB_CVS_NAME} WHERE name_jp = ?1
+                 SELECT fld_id FROM {DB_LKP_WORK_CVS_NAME} WHERE cv_id IN (
+                     SELECT cv_id FROM {DB_CVS_NAME}
+                     WHERE name_jp = ?1
+                        OR alias_of = (SELECT cv_id FROM {DB_CVS_NAME} WHERE name_jp = ?1)
                  )
              )"
         ),
@@ -138,7 +192,9 @@ pub fn should_retag_work_for_cv(conn: &Connection, work: &RJCode) -> Result<bool
                 "SELECT COUNT(*)
                  FROM {DB_CUSTOM_CV_MAPPINGS_NAME} ccvm
                  WHERE ccvm.cv_id IN (
-                     SELECT cv_id FROM {DB_LKP_WORK_CVS_NAME} WHERE fld_id = (
+                     SELECT COALESCE(cv.alias_of, cv.cv_id) FROM {DB_CVS_NAME} cv
+                     JOIN {DB_LKP_WORK_CVS_NAME} lwcv ON lwcv.cv_id = cv.cv_id
+                     WHERE lwcv.fld_id = (
                          SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1
                      )
                  )
@@ -151,3 +207,149 @@ pub fn should_retag_work_for_cv(conn: &Connection, work: &RJCode) -> Result<bool
 
     Ok(has_newer_mapping > 0)
 }
+
+/// Key under which CV name variants are considered the same person: NFKC, lowercase, spaces and
+/// name separators (・, ., -, _) removed, katakana folded to hiragana. Only used to *suggest*
+/// merges — a kana-only variant can still be a different person, the user decides.
+pub fn cv_dedup_key(name: &str) -> String {
+    name.nfkc()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace() && !matches!(c, '・' | '･' | '.' | '-' | '_'))
+        .map(|c| match c {
+            // Katakana ァ..ヶ → hiragana ぁ..ゖ
+            '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Canonical (non-alias) CVs whose names share the same `cv_dedup_key`, as groups of
+/// `(name_jp, work_count)`, most used name first. Groups are candidates for `merge_cv_alias`.
+pub fn find_duplicate_cv_groups(conn: &Connection) -> Result<Vec<Vec<(String, i64)>>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT cv.name_jp, COUNT(lwcv.fld_id) AS work_count
+         FROM {DB_CVS_NAME} cv
+         LEFT JOIN {DB_LKP_WORK_CVS_NAME} lwcv ON lwcv.cv_id = cv.cv_id
+         WHERE cv.alias_of IS NULL
+         GROUP BY cv.cv_id"
+    ))?;
+    let rows: Vec<(String, i64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut groups: BTreeMap<String, Vec<(String, i64)>> = BTreeMap::new();
+    for (name, count) in rows {
+        groups.entry(cv_dedup_key(&name)).or_default().push((name, count));
+    }
+
+    Ok(groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .map(|mut group| {
+            group.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            group
+        })
+        .collect())
+}
+
+/// Merges the CV `alias_name_jp` into `canonical_name_jp`: works crediting the alias get the
+/// canonical CV's (possibly custom-renamed) name as artist. Aliases of the merged CV follow it to
+/// the new canonical CV. Affected works are marked for re-tagging; returns the files marked.
+pub fn merge_cv_alias(conn: &Connection, alias_name_jp: &str, canonical_name_jp: &str) -> Result<usize, HvtError> {
+    let (alias_id, _) = get_cv_id_and_alias(conn, alias_name_jp)?;
+    let (canonical_id, canonical_alias_of) = get_cv_id_and_alias(conn, canonical_name_jp)?;
+    let root_id = canonical_alias_of.unwrap_or(canonical_id);
+
+    if root_id == alias_id {
+        return Err(HvtError::Generic(format!(
+            "'{}' is already an alias of '{}'", canonical_name_jp, alias_name_jp
+        )));
+    }
+
+    conn.execute(
+        &format!("UPDATE {DB_CVS_NAME} SET alias_of = ?1 WHERE cv_id = ?2 OR alias_of = ?2"),
+        params![root_id, alias_id],
+    )?;
+
+    mark_cv_group_for_retagging(conn, root_id)
+}
+
+/// Detaches an alias from its canonical CV (it is credited under its own name again). Affected
+/// works are marked for re-tagging; returns the files marked.
+pub fn remove_cv_alias(conn: &Connection, alias_name_jp: &str) -> Result<usize, HvtError> {
+    let (alias_id, alias_of) = get_cv_id_and_alias(conn, alias_name_jp)?;
+    if alias_of.is_none() {
+        return Ok(0);
+    }
+
+    conn.execute(
+        &format!("UPDATE {DB_CVS_NAME} SET alias_of = NULL WHERE cv_id = ?1"),
+        params![alias_id],
+    )?;
+
+    mark_cv_group_for_retagging(conn, alias_id)
+}
+
+/// All merged aliases as `(alias_name_jp, canonical_name_jp)`, grouped by canonical CV.
+pub fn list_cv_aliases(conn: &Connection) -> Result<Vec<(String, String)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT cv.name_jp, canon.name_jp
+         FROM {DB_CVS_NAME} cv
+         JOIN {DB_CVS_NAME} canon ON canon.cv_id = cv.alias_of
+         ORDER BY canon.name_jp COLLATE NOCASE, cv.name_jp COLLATE NOCASE"
+    ))?;
+    let aliases = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(aliases)
+}
+
+fn get_cv_id_and_alias(conn: &Connection, name_jp: &str) -> Result<(i64, Option<i64>), HvtError> {
+    Ok(conn.query_row(
+        &format!("SELECT cv_id, alias_of FROM {DB_CVS_NAME} WHERE name_jp = ?1"),
+        params![name_jp],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?)
+}
+
+/// Marks every work crediting `cv_id` or one of its aliases for re-tagging.
+fn mark_cv_group_for_retagging(conn: &Connection, cv_id: i64) -> Result<usize, HvtError> {
+    let rows_affected = conn.execute(
+        &format!(
+            "UPDATE {DB_FILE_PROCESSING_NAME}
+             SET tag_date = NULL, is_tagged = 0
+             WHERE fld_id IN (
+                 SELECT fld_id FROM {DB_LKP_WORK_CVS_NAME} WHERE cv_id IN (
+                     SELECT cv_id FROM {DB_CVS_NAME} WHERE cv_id = ?1 OR alias_of = ?1
+                 )
+             )"
+        ),
+        params![cv_id],
+    )?;
+    Ok(rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cv_dedup_key_folds_spacing_and_kana() {
+        assert_eq!(cv_dedup_key("山田 花子"), cv_dedup_key("山田花子"));
+        assert_eq!(cv_dedup_key("山田　花子"), cv_dedup_key("山田花子"));
+        assert_eq!(cv_dedup_key("カノン"), cv_dedup_key("かのん"));
+        assert_eq!(cv_dedup_key("Mia Hanabi"), cv_dedup_key("mia・hanabi"));
+        assert_ne!(cv_dedup_key("山田花子"), cv_dedup_key("山田太郎"));
+    }
+
+    #[test]
+    fn test_cv_display_name_styles() {
+        assert_eq!(cv_display_name(CvNameStyle::Jp, "花守ゆみり", Some("Yumiri Hanamori")), "花守ゆみり");
+        assert_eq!(cv_display_name(CvNameStyle::En, "花守ゆみり", Some("Yumiri Hanamori")), "Yumiri Hanamori");
+        assert_eq!(cv_display_name(CvNameStyle::JpEn, "花守ゆみり", Some("Yumiri Hanamori")), "花守ゆみり (Yumiri Hanamori)");
+        // No (or no distinct) English name: always the Japanese one
+        assert_eq!(cv_display_name(CvNameStyle::En, "花守ゆみり", None), "花守ゆみり");
+        assert_eq!(cv_display_name(CvNameStyle::JpEn, "花守ゆみり", Some("")), "花守ゆみり");
+        assert_eq!(cv_display_name(CvNameStyle::JpEn, "Mia", Some("Mia")), "Mia");
+    }
+}
@@ -1,7 +1,8 @@
-use rusqlite::{Connection, params};
+use rusqlite::{Connection, params, params_from_iter};
 use crate::errors::HvtError;
 use crate::folders::types::RJCode;
 use crate::database::tables::*;
+use crate::database::tag_rules::{self, TagRules};
 
 /// List all DLSite tags used in the database (alphabetically sorted)
 /// Returns Vec<(tag_id, tag_name, custom_name_if_mapped, is_ignored)>
@@ -188,16 +189,26 @@ pub fn get_dlsite_tags_for_work(
 }
 
 /// Get merged tags for a work (DLSite tags with global custom mappings applied)
-/// Filters out tags marked as ignored
+/// Filters out tags marked as ignored, then applies the tag rules (see `tag_rules::TagRules::apply`)
 pub fn get_merged_tags_for_work(
     conn: &Connection,
     work: &RJCode,
 ) -> Result<Vec<String>, HvtError> {
-    // Get all tags with their custom mappings if they exist
+    let rules = tag_rules::load_tag_rules(conn)?;
+    get_merged_tags_for_work_with_rules(conn, work, &rules)
+}
+
+/// `get_merged_tags_for_work` with rules already loaded (loops over many works)
+pub fn get_merged_tags_for_work_with_rules(
+    conn: &Connection,
+    work: &RJCode,
+    rules: &TagRules,
+) -> Result<Vec<String>, HvtError> {
+    // Get all tags with their custom mappings if they exist, else the name in the tag language
     // Filter out tags where is_ignored = 1
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare_cached(
         &format!(
-            "SELECT COALESCE(ctm.custom_tag_name, dt.tag_name) as final_tag_name
+            "SELECT dt.tag_name, COALESCE(ctm.custom_tag_name, {}) as final_tag_name
              FROM {DB_DLSITE_TAG_NAME} dt
              LEFT JOIN {DB_CUSTOM_TAG_MAPPINGS_NAME} ctm ON dt.tag_id = ctm.dlsite_tag_id
              WHERE dt.tag_id IN (
@@ -205,20 +216,18 @@ pub fn get_merged_tags_for_work(
                      SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1
                  )
              )
-             AND COALESCE(ctm.is_ignored, 0) = 0"
+             AND COALESCE(ctm.is_ignored, 0) = 0",
+            rules.language.name_expr("dt")
         )
     )?;
 
-    let mut tags: Vec<String> = stmt
-        .query_map(params![work.as_str()], |row| row.get(0))?
+    let tags: Vec<(String, String)> = stmt
+        .query_map(params![work.as_str()], |row| Ok((row.get(0)?, row.get(1)?)))?
         .filter_map(|r| r.ok())
         .collect();
 
-    // Deduplicate tags in case multiple DLSite tags are renamed to the same custom name
-    tags.sort();
-    tags.dedup();
-
-    Ok(tags)
+    // Sorted and deduplicated in case multiple DLSite tags are renamed to the same custom name
+    Ok(rules.apply(tags))
 }
 
 /// Get the modification date of a custom tag mapping
@@ -294,6 +303,31 @@ pub fn mark_works_for_retagging(
     Ok(rows_affected)
 }
 
+/// `mark_works_for_retagging` for many tags in one statement (bulk mapping changes)
+pub fn mark_works_for_retagging_many(
+    conn: &Connection,
+    dlsite_tag_names: &[String],
+) -> Result<usize, HvtError> {
+    if dlsite_tag_names.is_empty() {
+        return Ok(0);
+    }
+    let placeholders = vec!["?"; dlsite_tag_names.len()].join(", ");
+    let rows_affected = conn.execute(
+        &format!(
+            "UPDATE {DB_FILE_PROCESSING_NAME}
+             SET tag_date = NULL, is_tagged = 0
+             WHERE fld_id IN (
+                 SELECT fld_id FROM {DB_LKP_WORK_TAG_NAME} WHERE tag_id IN (
+                     SELECT tag_id FROM {DB_DLSITE_TAG_NAME} WHERE tag_name IN ({placeholders})
+                 )
+             )"
+        ),
+        params_from_iter(dlsite_tag_names),
+    )?;
+
+    Ok(rows_affected)
+}
+
 /// Check if any tags used by this work have been modified since last tagging
 pub fn should_retag_work(conn: &Connection, work: &RJCode) -> Result<bool, HvtError> {
     // Get the last tag date for files in this work
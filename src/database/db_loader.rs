@@ -1,31 +1,17 @@
-use std::{fs, path::PathBuf};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
+use tracing::{debug, warn};
 
 use crate::errors::HvtError;
+use crate::paths;
 
 pub fn get_default_db_path() -> Result<String, HvtError> {
-    // Use platform-appropriate data directory
-    let data_dir = if cfg!(target_os = "windows") {
-        // On Windows, use AppData\Local
-        dirs::data_local_dir()
-            .ok_or_else(|| HvtError::Generic("Could not determine local data directory".to_string()))?
-            .join("hvtag")
-    } else {
-        // On Linux/macOS, use ~/.hvtag
-        dirs::home_dir()
-            .ok_or_else(|| HvtError::Generic("Could not determine home directory".to_string()))?
-            .join(".hvtag")
-    };
-
-    if !data_dir.exists() {
-        fs::create_dir_all(&data_dir)
-            .map_err(|_| HvtError::PathCreationFailed(data_dir.display().to_string()))?;
-    }
-
-    let db_path = data_dir.join("data.db3");
+    // Platform-appropriate data directory, see paths::database_file
+    let db_path = paths::database_file()?;
     db_path.to_str()
-        .ok_or_else(|| HvtError::PathCreationFailed(data_dir.display().to_string()))
+        .ok_or_else(|| HvtError::PathCreationFailed(db_path.display().to_string()))
         .map(|s| s.to_string())
 }
 
@@ -34,10 +20,152 @@ pub fn open_db(custom_path: Option<&str>) -> Result<Connection, HvtError> {
         Some(p) => p.to_string(),
         None => get_default_db_path()?
     };
-    let conn = Connection::open(path)?;
+    let conn = Connection::open(&path)?;
+
+    // Other connections (the web UI, see database::actor) wait for their turn rather than
+    // failing with "database is locked", the journal mode change below included
+    conn.busy_timeout(Duration::from_secs(10))?;
 
     // CRITICAL: Enable foreign keys (SQLite disables them by default)
     conn.execute("PRAGMA foreign_keys = ON", [])?;
 
+    // Room for every statement of the per-work metadata writes (see queries' prepare_cached)
+    conn.set_prepared_statement_cache_capacity(64);
+
+    // WAL: readers (the web UI, the database thread of database::actor) no longer wait for a
+    // writer, and commits are cheaper on slow disks. NORMAL is safe with WAL: a power loss can
+    // only lose the last commits, never corrupt the file. SQLite doesn't support WAL on network
+    // filesystems (a database on a NAS share), which keep the default rollback journal.
+    if is_on_network_filesystem(Path::new(&path)) {
+        debug!("{} is on a network filesystem, WAL not enabled", path);
+    } else {
+        let journal_mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        if !journal_mode.eq_ignore_ascii_case("wal") {
+            debug!("WAL not available for this database, journal mode stays {}", journal_mode);
+        }
+        conn.execute_batch("PRAGMA synchronous = NORMAL")?;
+    }
+
     Ok(conn)
 }
+
+/// Filesystem types of network mounts, as listed in `/proc/self/mounts`
+#[cfg(target_os = "linux")]
+const NETWORK_FILESYSTEMS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "afs", "ceph", "glusterfs", "fuse.sshfs", "fuse.rclone", "davfs",
+];
+
+/// Whether `path` is on a network share: the filesystem of its longest matching mount point on
+/// Linux, a UNC path (`\\server\share`) on Windows. Unknown elsewhere, taken as local.
+fn is_on_network_filesystem(path: &Path) -> bool {
+    let dir = path.parent().unwrap_or(path);
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    #[cfg(target_os = "linux")]
+    {
+        let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+            return false;
+        };
+        mount_filesystem(&mounts, &dir).is_some_and(|fs_type| NETWORK_FILESYSTEMS.contains(&fs_type))
+    }
+    #[cfg(windows)]
+    {
+        let dir = dir.to_string_lossy();
+        // canonicalize gives `\\?\UNC\server\share`
+        dir.starts_with(r"\\?\UNC\") || (dir.starts_with(r"\\") && !dir.starts_with(r"\\?\"))
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        let _ = dir;
+        false
+    }
+}
+
+/// Filesystem type of the mount point of `/proc/self/mounts` holding `dir` (spaces in mount
+/// points are written `\040`)
+#[cfg(target_os = "linux")]
+fn mount_filesystem<'a>(mounts: &'a str, dir: &Path) -> Option<&'a str> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(' ');
+            let mount_point = fields.nth(1)?.replace("\\040", " ");
+            let fs_type = fields.next()?;
+            dir.starts_with(&mount_point).then_some((mount_point.len(), fs_type))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, fs_type)| fs_type)
+}
+
+/// Opens the database without write access (`--read-only`): any statement that would modify it
+/// fails. The schema is not initialized or migrated, so the file is left exactly as it is.
+pub fn open_db_read_only(custom_path: Option<&str>) -> Result<Connection, HvtError> {
+    let path = match custom_path {
+        Some(p) => p.to_string(),
+        None => get_default_db_path()?
+    };
+    open_read_only(Path::new(&path))
+}
+
+/// Opens a database file read-only, another library's included. A WAL database needs its `-shm`
+/// file, which can't be created on a read-only mount: it is then opened as immutable, without
+/// the changes still in its `-wal` file.
+pub fn open_read_only(path: &Path) -> Result<Connection, HvtError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    // The `-shm` file is only needed, and missing, once the database is read
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get::<_, i64>(0)) {
+        Ok(_) => Ok(conn),
+        Err(e) => {
+            debug!("{} can't be read as is ({}), opened as immutable", path.display(), e);
+            let wal = PathBuf::from(format!("{}-wal", path.display()));
+            if std::fs::metadata(&wal).is_ok_and(|m| m.len() > 0) {
+                warn!(
+                    "{} can't be opened for reading its -wal file: the changes of the last runs on this database are not shown",
+                    path.display()
+                );
+            }
+            open_immutable(path)
+        }
+    }
+}
+
+/// Opens `path` as a file that no one writes to: no lock, no `-shm` or `-wal` file
+fn open_immutable(path: &Path) -> Result<Connection, HvtError> {
+    let path = path.to_string_lossy().replace('\\', "/").replace('%', "%25").replace('?', "%3f").replace('#', "%23");
+    Ok(Connection::open_with_flags(
+        format!("file:{}?immutable=1", path),
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_only_opens_of_a_wal_database() {
+        let dir = std::env::temp_dir().join(format!("hvtag-test-wal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data #1.db3");
+        let writer = Connection::open(&path).unwrap();
+        writer.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0)).unwrap();
+        writer.execute_batch("CREATE TABLE t (n INTEGER); INSERT INTO t VALUES (1);").unwrap();
+        drop(writer);
+
+        let count = |conn: &Connection| conn.query_row("SELECT COUNT(*) FROM t", [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(count(&open_read_only(&path).unwrap()), 1);
+        // What a read-only mount without a -shm file gets
+        let immutable = open_immutable(&path).unwrap();
+        assert_eq!(count(&immutable), 1);
+        assert!(immutable.execute("INSERT INTO t VALUES (2)", []).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_mount_filesystem() {
+        let mounts = "/dev/sda1 / ext4 rw 0 0\n//nas/music /mnt/nas cifs rw 0 0\n/dev/sdb1 /mnt/nas\\040usb ext4 rw 0 0\n";
+        assert_eq!(mount_filesystem(mounts, Path::new("/mnt/nas/hvtag")), Some("cifs"));
+        assert_eq!(mount_filesystem(mounts, Path::new("/mnt/nas usb")), Some("ext4"));
+        assert_eq!(mount_filesystem(mounts, Path::new("/home/user")), Some("ext4"));
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use rusqlite::ToSql;
+
+/// A filesystem path read from the database (`folders.path`, `file_processing.file_path`...).
+/// Valid UTF-8 paths are TEXT, as always; a name that is not (e.g. Shift-JIS bytes of a zip
+/// extracted on Linux) is stored as its raw bytes in a BLOB instead of being replaced by U+FFFD,
+/// so the path read back still opens the folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbPath(pub PathBuf);
+
+/// A filesystem path given to a query, stored as `DbPath` describes
+pub struct DbPathRef<'a>(pub &'a Path);
+
+impl ToSql for DbPathRef<'_> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        if let Some(text) = self.0.to_str() {
+            return Ok(ToSqlOutput::from(text));
+        }
+        Ok(ToSqlOutput::from(os_bytes(self.0)))
+    }
+}
+
+impl FromSql for DbPath {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            // TEXT may hold invalid UTF-8 too, e.g. after `substr`/`||` on a BLOB path
+            ValueRef::Text(bytes) | ValueRef::Blob(bytes) => Ok(DbPath(path_from_bytes(bytes))),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+impl From<DbPath> for PathBuf {
+    fn from(path: DbPath) -> Self {
+        path.0
+    }
+}
+
+#[cfg(unix)]
+fn os_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    use std::os::unix::ffi::OsStrExt;
+    PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+// Windows names are UTF-16 and only fail UTF-8 with unpaired surrogates: stored as WTF-8
+#[cfg(not(unix))]
+fn os_bytes(path: &Path) -> Vec<u8> {
+    path.as_os_str().as_encoded_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => PathBuf::from(text),
+        // SAFETY: only bytes written by `os_bytes` (from `as_encoded_bytes`) are not UTF-8
+        Err(_) => PathBuf::from(unsafe { std::ffi::OsStr::from_encoded_bytes_unchecked(bytes) }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path_round_trip() {
+        use std::os::unix::ffi::OsStrExt;
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE t (path TEXT)", []).unwrap();
+
+        // "音声" in Shift-JIS
+        let garbled = Path::new("/library").join(std::ffi::OsStr::from_bytes(b"RJ01000001 \x89\xb9\x90\xba"));
+        let plain = Path::new("/library/RJ01000002 音声");
+        for path in [&garbled, plain] {
+            conn.execute("INSERT INTO t VALUES (?1)", [DbPathRef(path)]).unwrap();
+        }
+
+        let paths: Vec<PathBuf> = conn
+            .prepare("SELECT path FROM t ORDER BY rowid").unwrap()
+            .query_map([], |row| row.get::<_, DbPath>(0).map(PathBuf::from)).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(paths, [garbled.clone(), plain.to_path_buf()]);
+        let found: i64 = conn
+            .query_row("SELECT COUNT(*) FROM t WHERE path = ?1", [DbPathRef(&garbled)], |row| row.get(0))
+            .unwrap();
+        assert_eq!(found, 1);
+    }
+}
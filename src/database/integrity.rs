@@ -0,0 +1,118 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection};
+
+use crate::database::db_path::{DbPath, DbPathRef};
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// Outcome of a single file's integrity check, stored as `file_integrity.status`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntegrityStatus {
+    /// ffmpeg decoded the whole file without reporting anything
+    Ok,
+    /// ffmpeg reported decode errors (truncated stream, bad frames, broken header...)
+    Corrupt,
+    /// The check itself could not be run on this file (ffmpeg failed to start, path not UTF-8...)
+    Error,
+}
+
+impl IntegrityStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IntegrityStatus::Ok => "ok",
+            IntegrityStatus::Corrupt => "corrupt",
+            IntegrityStatus::Error => "error",
+        }
+    }
+}
+
+/// `error_details` prefix of files flagged by the silence check done after tagging, so a later
+/// clean check only clears its own flags (not decode errors found by --verify).
+pub const SILENCE_CHECK_PREFIX: &str = "silence check: ";
+
+/// Record (or overwrite) the latest integrity check result for a file.
+pub fn record_file_integrity(
+    conn: &Connection,
+    rjcode: &RJCode,
+    file_path: &Path,
+    status: IntegrityStatus,
+    error_details: Option<&str>,
+) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO {DB_FILE_INTEGRITY_NAME}
+             (file_path, fld_id, status, error_details, checked_at)
+             VALUES (
+                 ?1,
+                 (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?2),
+                 ?3, ?4, datetime('now')
+             )"
+        ),
+        params![DbPathRef(file_path), rjcode, status.as_str(), error_details],
+    )?;
+    Ok(())
+}
+
+/// Get the checksums recorded at tagging time for a work's files.
+/// Returns Vec<(file_path, sha256)>, only for files that have a checksum.
+pub fn get_recorded_checksums(
+    conn: &Connection,
+    rjcode: &RJCode,
+) -> Result<Vec<(PathBuf, String)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT file_path, checksum FROM {DB_FILE_PROCESSING_NAME}
+         WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)
+           AND checksum IS NOT NULL
+         ORDER BY file_path"
+    ))?;
+    let rows = stmt.query_map(params![rjcode], |row| Ok((row.get::<_, DbPath>(0)?.0, row.get(1)?)))?;
+    let checksums: Vec<(PathBuf, String)> = rows.collect::<Result<Vec<_>, _>>()?;
+    Ok(checksums)
+}
+
+/// Days elapsed since a work's most recent integrity check (any file), or `None` if it was
+/// never checked. Used by `--fsck` to skip recently verified works.
+pub fn days_since_last_check(conn: &Connection, rjcode: &RJCode) -> Result<Option<f64>, HvtError> {
+    let days: Option<f64> = conn.query_row(
+        &format!(
+            "SELECT julianday('now') - julianday(MAX(checked_at)) FROM {DB_FILE_INTEGRITY_NAME}
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![rjcode],
+        |row| row.get(0),
+    )?;
+    Ok(days)
+}
+
+/// Removes a silence flag left on a file by an earlier run (e.g. once it has been re-downloaded).
+pub fn clear_silence_flag(conn: &Connection, file_path: &Path) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "DELETE FROM {DB_FILE_INTEGRITY_NAME}
+             WHERE file_path = ?1 AND status = 'corrupt' AND error_details LIKE ?2 || '%'"
+        ),
+        params![DbPathRef(file_path), SILENCE_CHECK_PREFIX],
+    )?;
+    Ok(())
+}
+
+/// (rjcode, file_path, status, error_details)
+pub type FlaggedFile = (RJCode, String, String, Option<String>);
+
+/// Files of active works whose latest check found a problem — the list of tracks to re-download.
+pub fn get_flagged_files(conn: &Connection) -> Result<Vec<FlaggedFile>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.rjcode, i.file_path, i.status, i.error_details
+         FROM {DB_FILE_INTEGRITY_NAME} i
+         JOIN {DB_FOLDERS_NAME} f ON f.fld_id = i.fld_id AND f.active = 1
+         WHERE i.status != 'ok'
+         ORDER BY f.rjcode, i.file_path"
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        let file_path = row.get::<_, DbPath>(1)?.0.display().to_string();
+        Ok((row.get(0)?, file_path, row.get(2)?, row.get(3)?))
+    })?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
@@ -0,0 +1,93 @@
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection};
+
+use crate::database::db_path::DbPath;
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// A failed work is retried on later runs until it has failed this many times.
+pub const MAX_IMPORT_ATTEMPTS: i64 = 3;
+
+/// Adds a registered work to the import queue (no-op if it is already queued, whatever its status).
+pub fn enqueue_work(conn: &Connection, rjcode: &RJCode) -> Result<usize, HvtError> {
+    let rows = conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO {DB_LIBRARY_IMPORT_QUEUE_NAME} (fld_id)
+             SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+        ),
+        params![rjcode],
+    )?;
+    Ok(rows)
+}
+
+/// Next works to process, in queue order: pending works first, then failed ones that still
+/// have attempts left. Returns `(rjcode, path)`.
+pub fn next_queued_works(conn: &Connection, limit: i64) -> Result<Vec<(RJCode, PathBuf)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.rjcode, f.path
+         FROM {DB_LIBRARY_IMPORT_QUEUE_NAME} q
+         JOIN {DB_FOLDERS_NAME} f ON f.fld_id = q.fld_id
+         WHERE f.active = 1
+           AND (q.status = 'pending' OR (q.status = 'failed' AND q.attempts < ?1))
+         ORDER BY q.status = 'failed', q.fld_id
+         LIMIT ?2"
+    ))?;
+    let rows = stmt.query_map(params![MAX_IMPORT_ATTEMPTS, limit], |row| Ok((row.get(0)?, row.get::<_, DbPath>(1)?.0)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Records the outcome of processing a queued work (`error` is `None` on success).
+pub fn mark_work_processed(conn: &Connection, rjcode: &RJCode, error: Option<&str>) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "UPDATE {DB_LIBRARY_IMPORT_QUEUE_NAME}
+             SET status = CASE WHEN ?2 IS NULL THEN 'done' ELSE 'failed' END,
+                 attempts = attempts + 1,
+                 error_details = ?2,
+                 processed_at = datetime('now')
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![rjcode, error],
+    )?;
+    Ok(())
+}
+
+/// Number of works processed (successfully or not) today, counted against the daily budget.
+/// Days are UTC days, like every timestamp in the database.
+pub fn count_processed_today(conn: &Connection) -> Result<i64, HvtError> {
+    Ok(conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM {DB_LIBRARY_IMPORT_QUEUE_NAME}
+             WHERE date(processed_at) = date('now')"
+        ),
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+/// Queue totals: `(remaining, done, failed for good)`.
+pub fn queue_counts(conn: &Connection) -> Result<(i64, i64, i64), HvtError> {
+    Ok(conn.query_row(
+        &format!(
+            "SELECT
+                COALESCE(SUM(status = 'pending' OR (status = 'failed' AND attempts < ?1)), 0),
+                COALESCE(SUM(status = 'done'), 0),
+                COALESCE(SUM(status = 'failed' AND attempts >= ?1), 0)
+             FROM {DB_LIBRARY_IMPORT_QUEUE_NAME}"
+        ),
+        params![MAX_IMPORT_ATTEMPTS],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?)
+}
+
+/// Seconds left until the next UTC day starts (when the daily budget resets).
+pub fn seconds_until_next_day(conn: &Connection) -> Result<u64, HvtError> {
+    let seconds: i64 = conn.query_row(
+        "SELECT CAST(strftime('%s', date('now', '+1 day')) AS INTEGER) - CAST(strftime('%s', 'now') AS INTEGER)",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(seconds.max(0) as u64)
+}
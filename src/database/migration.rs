@@ -1,4 +1,7 @@
-use rusqlite::Connection;
+use rusqlite::{params, Connection};
+use crate::database::db_path::{DbPath, DbPathRef};
+use crate::database::init_table;
+use crate::database::tables::{DB_TAG_RULES_COLS, DB_TAG_RULES_NAME};
 use crate::errors::HvtError;
 
 /// Migrates the database schema to add new columns to existing tables
@@ -7,6 +10,19 @@ pub fn migrate_schema(conn: &Connection) -> Result<(), HvtError> {
     migrate_folders_table(conn)?;
     migrate_dlsite_errors_table(conn)?;
     migrate_track_parsing_prefs_table(conn)?;
+    migrate_file_processing_checksum(conn)?;
+    migrate_cvs_alias(conn)?;
+    migrate_works_name_en(conn)?;
+    migrate_metadata_history_file_name(conn)?;
+    migrate_track_parsing_custom_regex(conn)?;
+    migrate_dlsite_covers_source(conn)?;
+    migrate_unescape_tag_names(conn)?;
+    migrate_folders_missing_since(conn)?;
+    migrate_dlsite_tag_name_jp(conn)?;
+    migrate_tag_rules_language(conn)?;
+    migrate_import_tagged_markers(conn)?;
+    migrate_file_processing_tag_config_hash(conn)?;
+    migrate_dlsite_scan_section(conn)?;
     Ok(())
 }
 
@@ -102,6 +118,254 @@ fn migrate_track_parsing_prefs_table(conn: &Connection) -> Result<(), HvtError>
     Ok(())
 }
 
+/// Adds checksum columns to file_processing (SHA-256 of each file, recorded after tagging)
+fn migrate_file_processing_checksum(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT checksum FROM file_processing LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute(
+            "ALTER TABLE file_processing ADD COLUMN checksum TEXT",
+            [],
+        )?;
+        conn.execute(
+            "ALTER TABLE file_processing ADD COLUMN checksum_date TEXT",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Adds alias_of column to cvs (name variant merged into another CV, see custom_cvs::merge_cv_alias)
+fn migrate_cvs_alias(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT alias_of FROM cvs LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute(
+            "ALTER TABLE cvs ADD COLUMN alias_of INTEGER REFERENCES cvs(cv_id)",
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Adds the English title (en_US locale) of works
+fn migrate_works_name_en(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT name_en FROM works LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute("ALTER TABLE works ADD COLUMN name_en TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Adds the genre name of the ja_JP page next to the en_US one (the `tag_name` key)
+fn migrate_dlsite_tag_name_jp(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT tag_name_jp FROM dlsite_tag LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute("ALTER TABLE dlsite_tag ADD COLUMN tag_name_jp TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Rebuilds tag_rules with the 'language' rule type allowed by its CHECK constraint
+fn migrate_tag_rules_language(conn: &Connection) -> Result<(), HvtError> {
+    let sql: String = conn.query_row(
+        &format!("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = '{DB_TAG_RULES_NAME}'"),
+        [],
+        |row| row.get(0),
+    )?;
+
+    if !sql.contains("'language'") {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(&format!("ALTER TABLE {DB_TAG_RULES_NAME} RENAME TO {DB_TAG_RULES_NAME}_old"), [])?;
+        tx.execute(&init_table(DB_TAG_RULES_NAME, DB_TAG_RULES_COLS), [])?;
+        tx.execute(
+            &format!(
+                "INSERT INTO {DB_TAG_RULES_NAME} (rule_id, rule_type, value, position, created_at)
+                 SELECT rule_id, rule_type, value, position, created_at FROM {DB_TAG_RULES_NAME}_old"
+            ),
+            [],
+        )?;
+        tx.execute(&format!("DROP TABLE {DB_TAG_RULES_NAME}_old"), [])?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Adds the file a metadata_history row is about (tag snapshots are per file)
+fn migrate_metadata_history_file_name(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT file_name FROM metadata_history LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute("ALTER TABLE metadata_history ADD COLUMN file_name TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Adds the user regex of the "custom_regex" track parsing strategy
+fn migrate_track_parsing_custom_regex(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT custom_regex FROM track_parsing_preferences LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute("ALTER TABLE track_parsing_preferences ADD COLUMN custom_regex TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Adds the source of the downloaded cover (and the URL it came from) to the dlsite_covers table
+fn migrate_dlsite_covers_source(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT source FROM dlsite_covers LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute("ALTER TABLE dlsite_covers ADD COLUMN source TEXT", [])?;
+        conn.execute("ALTER TABLE dlsite_covers ADD COLUMN source_link TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Adds when a folder was first found missing from disk (see --prune)
+fn migrate_folders_missing_since(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT missing_since FROM folders LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute("ALTER TABLE folders ADD COLUMN missing_since TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Tag names used to be stored with their quotes doubled (escaping left over from string-built
+/// SQL). When the unescaped name already exists the doubled row is left as is: works drop it on
+/// their next fetch.
+fn migrate_unescape_tag_names(conn: &Connection) -> Result<(), HvtError> {
+    conn.execute(
+        "UPDATE OR IGNORE dlsite_tag SET tag_name = REPLACE(tag_name, '''''', '''')
+         WHERE tag_name LIKE '%''''%'",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Tagged works used to be told by a `.tagged` file in their folder, now by `file_processing`
+/// alone. Adds `file_processing.tag_source` ('marker' for the rows imported here) and, once,
+/// records the audio files of each work whose folder has a marker but no file recorded as
+/// tagged. The markers are left in place (the tagger clears them when it next tags the work).
+fn migrate_import_tagged_markers(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT tag_source FROM file_processing LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute("ALTER TABLE file_processing ADD COLUMN tag_source TEXT", [])?;
+        import_tagged_markers(conn)?;
+    }
+
+    Ok(())
+}
+
+/// Adds `file_processing.tag_config_hash`: hash of the tagging settings a file was tagged with
+/// (see `TaggerConfig::tag_config_hash`), NULL for files tagged before
+fn migrate_file_processing_tag_config_hash(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT tag_config_hash FROM file_processing LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute("ALTER TABLE file_processing ADD COLUMN tag_config_hash TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Adds `dlsite_scan.section`: the DLSite section a work was found under, NULL for works
+/// fetched before (their section is looked up again on the next fetch)
+fn migrate_dlsite_scan_section(conn: &Connection) -> Result<(), HvtError> {
+    let needs_migration = conn
+        .prepare("SELECT section FROM dlsite_scan LIMIT 1")
+        .is_err();
+
+    if needs_migration {
+        conn.execute("ALTER TABLE dlsite_scan ADD COLUMN section TEXT", [])?;
+    }
+
+    Ok(())
+}
+
+/// Records as tagged the audio files of works with a `.tagged` marker and no tagged file in
+/// `file_processing`. Returns how many works were imported.
+fn import_tagged_markers(conn: &Connection) -> Result<usize, HvtError> {
+    let mut stmt = conn.prepare(
+        "SELECT f.fld_id, f.path FROM folders f
+         WHERE f.active = 1
+           AND NOT EXISTS (SELECT 1 FROM file_processing fp WHERE fp.fld_id = f.fld_id AND fp.is_tagged = 1)",
+    )?;
+    let works = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, DbPath>(1)?.0)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut imported = 0;
+    for (fld_id, folder) in works {
+        let folder = folder.as_path();
+        let Ok(marker) = std::fs::metadata(folder.join(crate::tagger::TAGGED_MARKER_NAME)) else {
+            continue;
+        };
+        // The marker was written right after tagging
+        let tagged_at = marker
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64);
+        let Ok(files) = crate::tagger::list_audio_files(folder) else {
+            continue;
+        };
+        for file in files {
+            let size = std::fs::metadata(&file).map(|m| m.len() as i64).ok();
+            conn.execute(
+                "INSERT OR IGNORE INTO file_processing
+                 (fld_id, file_path, file_name, file_extension, file_size_bytes, is_tagged, tag_date,
+                  processing_status, tag_source)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 1, COALESCE(datetime(?6, 'unixepoch'), datetime('now')),
+                         'completed', 'marker')",
+                params![
+                    fld_id,
+                    DbPathRef(&file),
+                    file.file_name().and_then(|n| n.to_str()).unwrap_or(""),
+                    file.extension().and_then(|e| e.to_str()).unwrap_or(""),
+                    size,
+                    tagged_at,
+                ],
+            )?;
+        }
+        imported += 1;
+    }
+    Ok(imported)
+}
+
 /// Placeholder for future database migrations
 /// Currently not needed as the database can be reset at will during development
 ///
@@ -116,3 +380,35 @@ pub fn migrate_add_constraints(_conn: &Connection) -> Result<(), HvtError> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::folders::types::RJCode;
+
+    #[test]
+    fn test_tagged_markers_are_imported() {
+        let root = std::env::temp_dir().join(format!("hvtag-test-markers-{}", std::process::id()));
+        let (marked, unmarked) = (root.join("RJ01000001"), root.join("RJ01000002"));
+        for folder in [&marked, &unmarked] {
+            std::fs::create_dir_all(folder).unwrap();
+            std::fs::write(folder.join("01.mp3"), b"audio").unwrap();
+        }
+        std::fs::write(marked.join(crate::tagger::TAGGED_MARKER_NAME), "").unwrap();
+
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        let works = [RJCode::new("RJ01000001".to_string()).unwrap(), RJCode::new("RJ01000002".to_string()).unwrap()];
+        for (work, folder) in works.iter().zip([&marked, &unmarked]) {
+            crate::database::queries::insert_folder_record(&conn, work, folder, true).unwrap();
+        }
+
+        assert_eq!(import_tagged_markers(&conn).unwrap(), 1);
+        assert!(crate::database::queries::is_work_tagged(&conn, &works[0]).unwrap());
+        assert!(!crate::database::queries::is_work_tagged(&conn, &works[1]).unwrap());
+        // Already recorded: not imported twice
+        assert_eq!(import_tagged_markers(&conn).unwrap(), 0);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,62 @@
+use rusqlite::{params, Connection};
+
+use crate::database::tables::*;
+use crate::errors::HvtError;
+
+/// Logs the moves of one normalization: (original path, new path), relative to the work folder
+pub fn record_moves(conn: &Connection, rjcode: &str, moves: &[(String, String)]) -> Result<(), HvtError> {
+    let tx = conn.unchecked_transaction()?;
+    for (original_path, new_path) in moves {
+        tx.execute(
+            &format!("INSERT INTO {DB_NORMALIZATION_LOG_NAME} (rjcode, original_path, new_path) VALUES (?1, ?2, ?3)"),
+            params![rjcode, original_path, new_path],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Moves of a work not undone yet: Vec<(log_id, original_path, new_path)>, latest first so
+/// they are undone in reverse order
+pub fn get_pending_moves(conn: &Connection, rjcode: &str) -> Result<Vec<(i64, String, String)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT log_id, original_path, new_path FROM {DB_NORMALIZATION_LOG_NAME}
+         WHERE rjcode = ?1 AND undone_at IS NULL
+         ORDER BY log_id DESC"
+    ))?;
+    let moves = stmt
+        .query_map(params![rjcode], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(moves)
+}
+
+pub fn mark_undone(conn: &Connection, log_id: i64) -> Result<(), HvtError> {
+    conn.execute(
+        &format!("UPDATE {DB_NORMALIZATION_LOG_NAME} SET undone_at = datetime('now') WHERE log_id = ?1"),
+        params![log_id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pending_moves_latest_first() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        let moves = [
+            ("CD1/01.mp3".to_string(), "01.mp3".to_string()),
+            ("CD2/01.mp3".to_string(), "01_1.mp3".to_string()),
+        ];
+        record_moves(&conn, "RJ01234567", &moves).unwrap();
+
+        let pending = get_pending_moves(&conn, "RJ01234567").unwrap();
+        assert_eq!(pending.iter().map(|(_, o, _)| o.as_str()).collect::<Vec<_>>(), ["CD2/01.mp3", "CD1/01.mp3"]);
+        assert!(get_pending_moves(&conn, "RJ07654321").unwrap().is_empty());
+
+        mark_undone(&conn, pending[0].0).unwrap();
+        assert_eq!(get_pending_moves(&conn, "RJ01234567").unwrap(), [pending[1].clone()]);
+    }
+}
@@ -1,36 +1,72 @@
-use rusqlite::{Connection, params};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OptionalExtension, params};
+use crate::database::db_path::{DbPath, DbPathRef};
 use crate::folders::types::{ManagedFolder, RGCode, RJCode};
 use crate::database::tables::*;
 use crate::errors::HvtError;
 use crate::tagger::track_parser::TrackParsingPreference;
+use crate::tagger::types::ReviewCounts;
 
 /// Insert a managed folder into the database
 pub fn insert_managed_folder(
     conn: &Connection,
     mf: &ManagedFolder,
 ) -> Result<usize, HvtError> {
-    let rows = conn.execute(
+    let rows = conn.prepare_cached(
         &format!(
            "WITH mx AS (SELECT COALESCE(MAX(fld_id), 0) AS m FROM {DB_FOLDERS_NAME}) 
             INSERT OR IGNORE INTO {DB_FOLDERS_NAME} (fld_id, rjcode, path, last_scan, active)
             SELECT mx.m + 1, ?1, ?2, datetime(), ?3
             FROM mx"),
-        params![&mf.rjcode, &mf.path, true],
+    )?.execute(params![&mf.rjcode, DbPathRef(&mf.path), true])?;
+    Ok(rows)
+}
+
+/// Insert a folder row from a bare RJ code and path, without scanning the folder on disk — used
+/// by `--import-data` for works merged in from another machine (`active` is false when the path
+/// doesn't exist locally).
+pub fn insert_folder_record(
+    conn: &Connection,
+    rjcode: &RJCode,
+    path: &Path,
+    active: bool,
+) -> Result<usize, HvtError> {
+    let rows = conn.execute(
+        &format!(
+           "WITH mx AS (SELECT COALESCE(MAX(fld_id), 0) AS m FROM {DB_FOLDERS_NAME})
+            INSERT OR IGNORE INTO {DB_FOLDERS_NAME} (fld_id, rjcode, path, last_scan, active)
+            SELECT mx.m + 1, ?1, ?2, datetime(), ?3
+            FROM mx"),
+        params![rjcode, DbPathRef(path), active],
     )?;
     Ok(rows)
 }
 
-/// Insert an error for a work
+/// Insert an error for a work. The same unresolved error seen again is counted as a retry of
+/// the existing entry (`retry_count`, `error_timestamp` of the last occurrence).
 pub fn insert_error(
     conn: &Connection,
     work: &RJCode,
     error: &str,
     error_category: Option<&str>,
 ) -> Result<usize, HvtError> {
+    let retried = conn.execute(
+        &format!(
+            "UPDATE {DB_DLSITE_ERRORS_NAME}
+             SET retry_count = COALESCE(retry_count, 0) + 1, error_timestamp = CURRENT_TIMESTAMP
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?3)
+               AND error_type = ?1 AND error_category IS ?2 AND COALESCE(is_resolved, 0) = 0"
+        ),
+        params![error, error_category, work],
+    )?;
+    if retried > 0 {
+        return Ok(retried);
+    }
     let rows = conn.execute(
         &format!(
-            "INSERT INTO {DB_DLSITE_ERRORS_NAME} (fld_id, error_type, error_category, error_timestamp)
-             SELECT fld_id, ?1, ?2, CURRENT_TIMESTAMP
+            "INSERT INTO {DB_DLSITE_ERRORS_NAME} (fld_id, error_type, error_category, error_timestamp, retry_count, is_resolved)
+             SELECT fld_id, ?1, ?2, CURRENT_TIMESTAMP, 0, 0
              FROM {DB_FOLDERS_NAME}
              WHERE rjcode = ?3"
         ),
@@ -39,19 +75,84 @@ pub fn insert_error(
     Ok(rows)
 }
 
+/// A `dlsite_errors` entry, for `--errors`.
+#[derive(Debug, Clone)]
+pub struct WorkError {
+    pub rjcode: String,
+    pub error_type: Option<String>,
+    pub error_category: Option<String>,
+    pub error_details: Option<String>,
+    pub error_timestamp: Option<String>,
+    pub retry_count: i64,
+    pub resolved_date: Option<String>,
+    pub is_resolved: bool,
+}
+
+/// Errors of active works, grouped by work (RJ code order), most recent first within a work.
+/// `category` filters on `error_category`; `unresolved_only` hides resolved entries.
+pub fn get_errors(conn: &Connection, category: Option<&str>, unresolved_only: bool) -> Result<Vec<WorkError>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.rjcode, e.error_type, e.error_category, e.error_details, e.error_timestamp,
+                COALESCE(e.retry_count, 0), e.resolved_date, COALESCE(e.is_resolved, 0)
+         FROM {DB_DLSITE_ERRORS_NAME} e
+         JOIN {DB_FOLDERS_NAME} f ON f.fld_id = e.fld_id AND f.active = 1
+         WHERE (?1 IS NULL OR e.error_category = ?1)
+           AND (?2 = 0 OR COALESCE(e.is_resolved, 0) = 0)
+         ORDER BY f.rjcode, e.error_timestamp DESC"
+    ))?;
+    let rows = stmt.query_map(params![category, unresolved_only], |row| {
+        Ok(WorkError {
+            rjcode: row.get(0)?,
+            error_type: row.get(1)?,
+            error_category: row.get(2)?,
+            error_details: row.get(3)?,
+            error_timestamp: row.get(4)?,
+            retry_count: row.get(5)?,
+            resolved_date: row.get(6)?,
+            is_resolved: row.get(7)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Marks every unresolved error of a work resolved. Returns how many were.
+pub fn resolve_errors(conn: &Connection, work: &RJCode) -> Result<usize, HvtError> {
+    let rows = conn.execute(
+        &format!(
+            "UPDATE {DB_DLSITE_ERRORS_NAME} SET is_resolved = 1, resolved_date = CURRENT_TIMESTAMP
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)
+               AND COALESCE(is_resolved, 0) = 0"
+        ),
+        params![work],
+    )?;
+    Ok(rows)
+}
+
 /// Insert a tag
 pub fn insert_tag(
     conn: &Connection,
     tag: &str,
     tag_id: usize,
 ) -> Result<usize, HvtError> {
-    let rows = conn.execute(
+    let rows = conn.prepare_cached(
         &format!("INSERT OR IGNORE INTO {DB_DLSITE_TAG_NAME} (tag_id, tag_name) VALUES (?1, ?2)"),
-        params![tag_id, tag],
-    )?;
+    )?.execute(params![tag_id, tag])?;
     Ok(rows)
 }
 
+/// Stores the ja_JP page name of a genre (`tag_name` is the en_US one). A name identical in both
+/// locales isn't a translation and is not stored.
+pub fn set_tag_name_jp(
+    conn: &Connection,
+    tag: &str,
+    tag_jp: &str,
+) -> Result<(), HvtError> {
+    conn.prepare_cached(
+        &format!("UPDATE {DB_DLSITE_TAG_NAME} SET tag_name_jp = NULLIF(?2, tag_name) WHERE tag_name = ?1"),
+    )?.execute(params![tag, tag_jp])?;
+    Ok(())
+}
+
 /// Check if a circle already exists in the database
 pub fn circle_exists(
     conn: &Connection,
@@ -85,28 +186,32 @@ pub fn insert_circle(
 /// triggering `INSERT OR REPLACE`'s delete-then-insert conflict path (which cascades and
 /// deletes every other work's lkp_work_cvs row for that actor). Returns the cv_id: the
 /// existing row's id if `name_jp` already exists, otherwise the id assigned by SQLite's
-/// native `INTEGER PRIMARY KEY` autoincrement.
+/// native `INTEGER PRIMARY KEY` autoincrement. A non-empty `en_name` fills in the English
+/// name of an existing row that doesn't have one yet.
 pub fn insert_cv(
     conn: &Connection,
     jp_name: &str,
     en_name: &str,
 ) -> Result<i64, HvtError> {
     let existing: Option<i64> = conn
-        .query_row(
-            &format!("SELECT cv_id FROM {DB_CVS_NAME} WHERE name_jp = ?1"),
-            params![jp_name],
-            |row| row.get(0),
-        )
+        .prepare_cached(&format!("SELECT cv_id FROM {DB_CVS_NAME} WHERE name_jp = ?1"))?
+        .query_row(params![jp_name], |row| row.get(0))
         .ok();
 
     if let Some(cv_id) = existing {
+        if !en_name.is_empty() {
+            conn.prepare_cached(
+                &format!(
+                    "UPDATE {DB_CVS_NAME} SET name_en = ?2
+                     WHERE cv_id = ?1 AND (name_en IS NULL OR name_en = '')"
+                ),
+            )?.execute(params![cv_id, en_name])?;
+        }
         return Ok(cv_id);
     }
 
-    conn.execute(
-        &format!("INSERT INTO {DB_CVS_NAME} (name_jp, name_en) VALUES (?1, ?2)"),
-        params![jp_name, en_name],
-    )?;
+    conn.prepare_cached(&format!("INSERT INTO {DB_CVS_NAME} (name_jp, name_en) VALUES (?1, ?2)"))?
+        .execute(params![jp_name, en_name])?;
     Ok(conn.last_insert_rowid())
 }
 
@@ -131,7 +236,7 @@ pub fn remove_previous_data_of_work(
              SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1
          )"
     );
-    let rows = conn.execute(&sql, params![work])?;
+    let rows = conn.prepare_cached(&sql)?.execute(params![work])?;
     Ok(rows)
 }
 
@@ -153,6 +258,21 @@ pub fn assign_release_date_to_work(
     Ok(rows)
 }
 
+/// Circle (RG) code of a work, if its circle was collected
+pub fn get_circle_code_for_work(conn: &Connection, work: &RJCode) -> Result<Option<String>, HvtError> {
+    let rgcode = conn.query_row(
+        &format!(
+            "SELECT c.rgcode
+             FROM {DB_LKP_WORK_CIRCLE_NAME} lwc
+             JOIN {DB_CIRCLE_NAME} c ON c.cir_id = lwc.cir_id
+             WHERE lwc.fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![work],
+        |row| row.get(0),
+    ).optional()?;
+    Ok(rgcode)
+}
+
 /// Assign circle to a work
 pub fn assign_circle_to_work(
     conn: &Connection,
@@ -220,6 +340,89 @@ pub fn assign_rating_to_work(
 }
 
 /// Assign stars rating to a work
+pub fn assign_reviews_to_work(
+    conn: &Connection,
+    work: &RJCode,
+    reviews: &ReviewCounts,
+) -> Result<usize, HvtError> {
+    let [r1, r2, r3, r4, r5] = reviews.distribution;
+    let rows = conn.execute(
+        &format!(
+            "INSERT INTO {DB_REVIEWS_NAME} (fld_id, rate_count, review_count, rate_1, rate_2, rate_3, rate_4, rate_5)
+             SELECT fld_id, ?1, ?2, ?3, ?4, ?5, ?6, ?7
+             FROM {DB_FOLDERS_NAME}
+             WHERE rjcode = ?8"
+        ),
+        params![reviews.rate_count, reviews.review_count, r1, r2, r3, r4, r5, work],
+    )?;
+    Ok(rows)
+}
+
+/// Ratings and reviews of a work, `None` if never fetched
+pub fn get_review_counts(conn: &Connection, work: &RJCode) -> Result<Option<ReviewCounts>, HvtError> {
+    let reviews = conn.query_row(
+        &format!(
+            "SELECT r.rate_count, r.review_count, r.rate_1, r.rate_2, r.rate_3, r.rate_4, r.rate_5
+             FROM {DB_REVIEWS_NAME} r
+             JOIN {DB_FOLDERS_NAME} f ON f.fld_id = r.fld_id
+             WHERE f.rjcode = ?1"
+        ),
+        params![work],
+        |row| Ok(ReviewCounts {
+            rate_count: row.get::<_, Option<u32>>(0)?.unwrap_or(0),
+            review_count: row.get::<_, Option<u32>>(1)?.unwrap_or(0),
+            distribution: [
+                row.get::<_, Option<u32>>(2)?.unwrap_or(0),
+                row.get::<_, Option<u32>>(3)?.unwrap_or(0),
+                row.get::<_, Option<u32>>(4)?.unwrap_or(0),
+                row.get::<_, Option<u32>>(5)?.unwrap_or(0),
+                row.get::<_, Option<u32>>(6)?.unwrap_or(0),
+            ],
+        }),
+    ).optional()?;
+    Ok(reviews)
+}
+
+pub fn assign_work_type_to_work(
+    conn: &Connection,
+    work: &RJCode,
+    work_type: Option<&str>,
+    options: &[String],
+    custom_genres: &[String],
+) -> Result<usize, HvtError> {
+    let rows = conn.execute(
+        &format!(
+            "INSERT INTO {DB_WORK_TYPES_NAME} (fld_id, work_type, options, custom_genres)
+             SELECT fld_id, ?1, ?2, ?3
+             FROM {DB_FOLDERS_NAME}
+             WHERE rjcode = ?4"
+        ),
+        params![work_type, options.join("#"), custom_genres.join("#"), work],
+    )?;
+    Ok(rows)
+}
+
+/// (work type, option codes, circle-defined genres) of a work
+pub type WorkTypeInfo = (Option<String>, Vec<String>, Vec<String>);
+
+/// Work type, option codes and circle-defined genres of a work, `None` if never fetched
+pub fn get_work_type(conn: &Connection, work: &RJCode) -> Result<Option<WorkTypeInfo>, HvtError> {
+    let split = |list: Option<String>| -> Vec<String> {
+        list.unwrap_or_default().split('#').filter(|s| !s.is_empty()).map(String::from).collect()
+    };
+    let row = conn.query_row(
+        &format!(
+            "SELECT t.work_type, t.options, t.custom_genres
+             FROM {DB_WORK_TYPES_NAME} t
+             JOIN {DB_FOLDERS_NAME} f ON f.fld_id = t.fld_id
+             WHERE f.rjcode = ?1"
+        ),
+        params![work],
+        |row| Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, Option<String>>(2)?)),
+    ).optional()?;
+    Ok(row.map(|(work_type, options, custom_genres)| (work_type, split(options), split(custom_genres))))
+}
+
 pub fn assign_stars_to_work(
     conn: &Connection,
     work: &RJCode,
@@ -237,6 +440,131 @@ pub fn assign_stars_to_work(
     Ok(rows)
 }
 
+/// Assign description (synopsis text from the product page) to a work
+pub fn assign_description_to_work(
+    conn: &Connection,
+    work: &RJCode,
+    description: &str,
+) -> Result<usize, HvtError> {
+    let rows = conn.execute(
+        &format!(
+            "INSERT INTO {DB_DESCRIPTION_NAME} (fld_id, description)
+             SELECT fld_id, ?1
+             FROM {DB_FOLDERS_NAME}
+             WHERE rjcode = ?2"
+        ),
+        params![description, work],
+    )?;
+    Ok(rows)
+}
+
+/// Assign translation/series relations to a work: (relation, related_rjcode) pairs
+pub fn assign_relations_to_work(
+    conn: &Connection,
+    work: &RJCode,
+    relations: &[(String, String)],
+) -> Result<usize, HvtError> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "INSERT OR IGNORE INTO {DB_WORK_RELATIONS_NAME} (fld_id, related_rjcode, relation)
+         SELECT fld_id, ?2, ?3
+         FROM {DB_FOLDERS_NAME}
+         WHERE rjcode = ?1"
+    ))?;
+    let mut rows = 0;
+    for (relation, related_rjcode) in relations {
+        rows += stmt.execute(params![work, related_rjcode, relation])?;
+    }
+    Ok(rows)
+}
+
+/// Assign the sample image URLs of a work, in page order. A position whose URL is unchanged keeps
+/// its saved file; positions past the end of the new list are dropped.
+pub fn assign_sample_images_to_work(
+    conn: &Connection,
+    work: &RJCode,
+    urls: &[String],
+) -> Result<(), HvtError> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "INSERT INTO {DB_WORK_IMAGES_NAME} (fld_id, position, image_url)
+         SELECT fld_id, ?2, ?3 FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1
+         ON CONFLICT (fld_id, position) DO UPDATE SET
+             file_name = CASE WHEN image_url = excluded.image_url THEN file_name END,
+             image_url = excluded.image_url"
+    ))?;
+    for (position, url) in urls.iter().enumerate() {
+        stmt.execute(params![work, position as i64 + 1, url])?;
+    }
+    conn.execute(
+        &format!(
+            "DELETE FROM {DB_WORK_IMAGES_NAME}
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1) AND position > ?2"
+        ),
+        params![work, urls.len() as i64],
+    )?;
+    Ok(())
+}
+
+/// Sample images of a work. Returns Vec<(position, image_url, file_name)>, by position.
+pub fn get_sample_images(conn: &Connection, work: &RJCode) -> Result<Vec<(i64, String, Option<String>)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT position, image_url, file_name FROM {DB_WORK_IMAGES_NAME}
+         WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)
+         ORDER BY position"
+    ))?;
+    let rows = stmt.query_map(params![work], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Records the file a sample image was saved to in the work's scans/ folder
+pub fn set_sample_image_file(conn: &Connection, work: &RJCode, position: i64, file_name: &str) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "UPDATE {DB_WORK_IMAGES_NAME} SET file_name = ?3
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1) AND position = ?2"
+        ),
+        params![work, position, file_name],
+    )?;
+    Ok(())
+}
+
+/// Series a work belongs to, as the RJ code of its root: the original work (or the parent
+/// translation) it derives from, or the work itself when it has translations. `None` for
+/// standalone works.
+pub fn get_series_root(conn: &Connection, work: &RJCode) -> Result<Option<String>, HvtError> {
+    let root: Option<String> = conn.query_row(
+        &format!(
+            "SELECT COALESCE(
+                 (SELECT related_rjcode FROM {DB_WORK_RELATIONS_NAME} WHERE fld_id = f.fld_id AND relation = 'original'),
+                 (SELECT related_rjcode FROM {DB_WORK_RELATIONS_NAME} WHERE fld_id = f.fld_id AND relation = 'parent'),
+                 CASE WHEN EXISTS (
+                     SELECT 1 FROM {DB_WORK_RELATIONS_NAME} WHERE fld_id = f.fld_id AND relation = 'child'
+                 ) THEN f.rjcode END
+             )
+             FROM {DB_FOLDERS_NAME} f
+             WHERE f.rjcode = ?1"
+        ),
+        params![work],
+        |row| row.get(0),
+    ).optional()?.flatten();
+    Ok(root)
+}
+
+/// Description of a work, if one was collected
+pub fn get_work_description(conn: &Connection, work: &RJCode) -> Result<Option<String>, HvtError> {
+    let description = conn
+        .query_row(
+            &format!(
+                "SELECT description FROM {DB_DESCRIPTION_NAME}
+                 WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+            ),
+            params![work],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .ok()
+        .flatten();
+    Ok(description)
+}
+
 /// Assign cover link to a work
 pub fn assign_cover_link_to_work(
     conn: &Connection,
@@ -255,6 +583,33 @@ pub fn assign_cover_link_to_work(
     Ok(rows)
 }
 
+/// Records where the cover of a work was actually downloaded from (see `cover_art::CoverSource`)
+pub fn set_cover_source(
+    conn: &Connection,
+    work: &RJCode,
+    source: &str,
+    link: &str,
+) -> Result<(), HvtError> {
+    let updated = conn.execute(
+        &format!(
+            "UPDATE {DB_DLSITE_COVERS_LINK_NAME} SET source = ?2, source_link = ?3
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![work, source, link],
+    )?;
+    // Works fetched before cover links were stored have no row yet
+    if updated == 0 {
+        conn.execute(
+            &format!(
+                "INSERT INTO {DB_DLSITE_COVERS_LINK_NAME} (fld_id, link, source, source_link)
+                 SELECT fld_id, NULL, ?2, ?3 FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+            ),
+            params![work, source, link],
+        )?;
+    }
+    Ok(())
+}
+
 /// Assign CVs to a work
 pub fn assign_cvs_to_work(
     conn: &Connection,
@@ -285,41 +640,69 @@ pub fn assign_cvs_to_work(
     Ok(rows)
 }
 
-/// Insert or update work name in the works table
+/// Insert or update work name in the works table. `work_name_en` is the title shown on the
+/// en_US locale page; `None` keeps the stored one.
 pub fn insert_work_name(
     conn: &Connection,
     work: &RJCode,
     work_name: &str,
+    work_name_en: Option<&str>,
 ) -> Result<usize, HvtError> {
+    let updated = conn.execute(
+        &format!(
+            "UPDATE {DB_WORKS_NAME} SET name = ?2, name_en = COALESCE(?3, name_en)
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![work, work_name, work_name_en],
+    )?;
+    if updated > 0 {
+        return Ok(updated);
+    }
+
     let rows = conn.execute(
         &format!(
-            "INSERT OR REPLACE INTO {DB_WORKS_NAME} (fld_id, name)
-             SELECT fld_id, ?2
+            "INSERT INTO {DB_WORKS_NAME} (fld_id, name, name_en)
+             SELECT fld_id, ?2, ?3
              FROM {DB_FOLDERS_NAME}
              WHERE rjcode = ?1"
         ),
-        params![work, work_name],
+        params![work, work_name, work_name_en],
     )?;
     Ok(rows)
 }
 
-/// Set work scan date
+/// Set work scan date, with the DLSite section the work was found under
 pub fn set_work_scan_date(
     conn: &Connection,
     work: &RJCode,
+    section: Option<&str>,
 ) -> Result<usize, HvtError> {
     let rows = conn.execute(
         &format!(
-            "INSERT OR REPLACE INTO {DB_DLSITE_SCAN_NAME} (fld_id, last_scan)
-             SELECT fld_id, datetime()
+            "INSERT OR REPLACE INTO {DB_DLSITE_SCAN_NAME} (fld_id, last_scan, section)
+             SELECT fld_id, datetime(), ?2
              FROM {DB_FOLDERS_NAME}
              WHERE rjcode = ?1"
         ),
-        params![work],
+        params![work, section],
     )?;
     Ok(rows)
 }
 
+/// DLSite section the work was last found under, if it was fetched from DLSite
+pub fn get_dlsite_section(conn: &Connection, work: &RJCode) -> Result<Option<String>, HvtError> {
+    let section = conn.query_row(
+        &format!(
+            "SELECT section FROM {DB_DLSITE_SCAN_NAME}
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1) AND section IS NOT NULL
+             ORDER BY last_scan DESC, rowid DESC LIMIT 1"
+        ),
+        params![work],
+        |row| row.get(0),
+    ).optional()?;
+    Ok(section)
+}
+
 /// Get maximum ID from a table
 pub fn get_max_id(
     conn: &Connection,
@@ -333,23 +716,96 @@ pub fn get_max_id(
 
 /// Get all active works with their registered paths — used by `--full-retag` to enumerate
 /// every work in the library.
-pub fn get_all_works_with_paths(conn: &Connection) -> Result<Vec<(RJCode, String)>, HvtError> {
+pub fn get_all_works_with_paths(conn: &Connection) -> Result<Vec<(RJCode, PathBuf)>, HvtError> {
     let mut stmt = conn.prepare(&format!(
         "SELECT rjcode, path FROM {DB_FOLDERS_NAME} WHERE active = 1"
     ))?;
-    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
-    let works: Vec<(RJCode, String)> = rows.collect::<Result<Vec<_>, _>>()?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, DbPath>(1)?.0)))?;
+    let works: Vec<(RJCode, PathBuf)> = rows.collect::<Result<Vec<_>, _>>()?;
+    Ok(works)
+}
+
+/// Get active works still to tag, with their registered paths — used by `--full-retag` without
+/// `--all`: works with no file recorded as tagged, and those marked for re-tagging (a file set
+/// back to untagged by `--refresh`, `--untag` or a tag/circle/CV change).
+pub fn get_pending_works_with_paths(conn: &Connection) -> Result<Vec<(RJCode, PathBuf)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.rjcode, f.path FROM {DB_FOLDERS_NAME} f
+         WHERE f.active = 1
+           AND (
+               NOT EXISTS (
+                   SELECT 1 FROM {DB_FILE_PROCESSING_NAME} fp
+                   WHERE fp.fld_id = f.fld_id AND fp.is_tagged = 1
+               )
+               OR EXISTS (
+                   SELECT 1 FROM {DB_FILE_PROCESSING_NAME} fp
+                   WHERE fp.fld_id = f.fld_id AND fp.is_tagged = 0
+               )
+           )
+         ORDER BY f.rjcode"
+    ))?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, DbPath>(1)?.0)))?;
+    let works: Vec<(RJCode, PathBuf)> = rows.collect::<Result<Vec<_>, _>>()?;
+    Ok(works)
+}
+
+/// Whether a work is tagged: files of it are recorded as tagged in `file_processing` and none is
+/// marked for re-tagging
+pub fn is_work_tagged(conn: &Connection, rjcode: &RJCode) -> Result<bool, HvtError> {
+    let tagged = conn.query_row(
+        &format!(
+            "SELECT COALESCE(MIN(fp.is_tagged), 0) FROM {DB_FILE_PROCESSING_NAME} fp
+             JOIN {DB_FOLDERS_NAME} f ON f.fld_id = fp.fld_id
+             WHERE f.rjcode = ?1"
+        ),
+        params![rjcode],
+        |row| row.get::<_, bool>(0),
+    )?;
+    Ok(tagged)
+}
+
+/// Get active works that have at least one file recorded as tagged in `file_processing`, with
+/// their registered paths — used by `--verify` to only check works the tagger has touched.
+pub fn get_tagged_works_with_paths(conn: &Connection) -> Result<Vec<(RJCode, PathBuf)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.rjcode, f.path FROM {DB_FOLDERS_NAME} f
+         WHERE f.active = 1
+           AND EXISTS (
+               SELECT 1 FROM {DB_FILE_PROCESSING_NAME} fp
+               WHERE fp.fld_id = f.fld_id AND fp.is_tagged = 1
+           )
+         ORDER BY f.rjcode"
+    ))?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get::<_, DbPath>(1)?.0)))?;
+    let works: Vec<(RJCode, PathBuf)> = rows.collect::<Result<Vec<_>, _>>()?;
     Ok(works)
 }
 
+/// Unix time at which a file was last tagged by hvtag, from `file_processing` (None when the
+/// tagger never recorded it)
+pub fn get_file_tag_time(conn: &Connection, file_path: &Path) -> Result<Option<i64>, HvtError> {
+    let tag_time = conn
+        .query_row(
+            &format!(
+                "SELECT CAST(strftime('%s', tag_date) AS INTEGER) FROM {DB_FILE_PROCESSING_NAME}
+                 WHERE file_path = ?1 AND is_tagged = 1"
+            ),
+            params![DbPathRef(file_path)],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    Ok(tag_time)
+}
+
 /// Get the registered folder path for a specific work, if it exists in the database.
 /// Used by `--retag <rjcode>` to resolve the real library path rather than assuming cwd.
-pub fn get_work_path(conn: &Connection, rjcode: &RJCode) -> Result<Option<String>, HvtError> {
-    let path: Option<String> = conn
+pub fn get_work_path(conn: &Connection, rjcode: &RJCode) -> Result<Option<PathBuf>, HvtError> {
+    let path: Option<PathBuf> = conn
         .query_row(
             &format!("SELECT path FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"),
             params![rjcode],
-            |row| row.get(0),
+            |row| row.get::<_, DbPath>(0).map(PathBuf::from),
         )
         .ok();
     Ok(path)
@@ -371,8 +827,9 @@ pub fn rjcode_exists(conn: &Connection, rjcode: &RJCode) -> Result<bool, HvtErro
 /// Permanently removes a work from the database (no filesystem changes) — for works whose folder
 /// is already gone from disk, where the trash feature's file-move step doesn't apply. Unlike
 /// `deactivate_and_relocate_work` (the reversible trash path), this is NOT reversible: every
-/// child row is gone for good. `file_processing` has no `ON DELETE CASCADE` on `fld_id` (see
-/// `tables.rs`), so it must be deleted explicitly first; everything else under `folders.fld_id`
+/// child row is gone for good. `file_processing` and `processing_history` have no
+/// `ON DELETE CASCADE` on `fld_id` (see `tables.rs`), so they must be deleted explicitly first;
+/// everything else under `folders.fld_id`
 /// (works, lkp_work_tag/circle/cvs, rating, stars, release_date, dlsite_covers, dlsite_scan,
 /// track_parsing_prefs) cascades from the final `folders` delete.
 pub fn delete_work_permanently(conn: &Connection, rjcode: &RJCode) -> Result<(), HvtError> {
@@ -382,6 +839,18 @@ pub fn delete_work_permanently(conn: &Connection, rjcode: &RJCode) -> Result<(),
         ),
         params![rjcode],
     )?;
+    conn.execute(
+        &format!(
+            "DELETE FROM {DB_PROCESSING_HISTORY_NAME} WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![rjcode],
+    )?;
+    conn.execute(
+        &format!(
+            "DELETE FROM {DB_METADATA_HISTORY_NAME} WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![rjcode],
+    )?;
     conn.execute(
         &format!("DELETE FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"),
         params![rjcode],
@@ -389,16 +858,193 @@ pub fn delete_work_permanently(conn: &Connection, rjcode: &RJCode) -> Result<(),
     Ok(())
 }
 
+/// Points a work at the folder it was moved to outside hvtag (found again by a rescan) and logs the
+/// move in `metadata_history`.
+pub fn record_folder_move(conn: &Connection, rjcode: &RJCode, old_path: &Path, new_path: &Path) -> Result<(), HvtError> {
+    conn.execute(
+        &format!("UPDATE {DB_FOLDERS_NAME} SET path = ?2, missing_since = NULL WHERE rjcode = ?1"),
+        params![rjcode, DbPathRef(new_path)],
+    )?;
+    conn.execute(
+        &format!(
+            "INSERT INTO {DB_METADATA_HISTORY_NAME} (fld_id, metadata_type, old_value, new_value, change_reason, source)
+             SELECT fld_id, 'folder_path', ?2, ?3, 'folder moved', 'rescan'
+             FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+        ),
+        params![rjcode, DbPathRef(old_path), DbPathRef(new_path)],
+    )?;
+    Ok(())
+}
+
+/// Records in `file_processing` that hvtag moved a work folder from `old_path` to `new_path`:
+/// each file under the old folder gets its new location as `move_destination`. The paths are
+/// matched here rather than in SQL, where a BLOB path (see `DbPath`) never equals a TEXT one.
+pub fn record_files_moved(conn: &Connection, rjcode: &RJCode, old_path: &Path, new_path: &Path) -> Result<usize, HvtError> {
+    let files: Vec<(i64, PathBuf)> = conn
+        .prepare(&format!(
+            "SELECT file_id, file_path FROM {DB_FILE_PROCESSING_NAME}
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ))?
+        .query_map(params![rjcode], |row| Ok((row.get(0)?, row.get::<_, DbPath>(1)?.0)))?
+        .collect::<Result<_, _>>()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "UPDATE {DB_FILE_PROCESSING_NAME}
+         SET is_moved = 1, move_date = datetime('now'), move_destination = ?2
+         WHERE file_id = ?1"
+    ))?;
+    let mut rows = 0;
+    for (file_id, file_path) in files {
+        if let Ok(relative) = file_path.strip_prefix(old_path) {
+            rows += stmt.execute(params![file_id, DbPathRef(&new_path.join(relative))])?;
+        }
+    }
+    Ok(rows)
+}
+
+/// Active works already fetched from DLSite, optionally only those last fetched more than
+/// `older_than_days` ago (`--refresh`), least recently fetched first.
+pub fn get_works_scanned_before(conn: &Connection, older_than_days: Option<u32>) -> Result<Vec<RJCode>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.rjcode FROM {DB_FOLDERS_NAME} f
+         JOIN {DB_DLSITE_SCAN_NAME} s ON s.fld_id = f.fld_id
+         WHERE f.active = 1
+           AND (?1 IS NULL OR s.last_scan < datetime('now', '-' || ?1 || ' days'))
+         ORDER BY s.last_scan, f.rjcode"
+    ))?;
+    let rows = stmt.query_map(params![older_than_days], |row| row.get(0))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Whether the work's DLSite metadata was collected at least once.
+pub fn has_collected_metadata(conn: &Connection, rjcode: &RJCode) -> Result<bool, HvtError> {
+    let exists = conn.query_row(
+        &format!(
+            "SELECT EXISTS(SELECT 1 FROM {DB_DLSITE_SCAN_NAME}
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1))"
+        ),
+        params![rjcode],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
+
+/// Makes the next `--full-retag` re-tag a work (its metadata changed, see `--refresh`).
+pub fn mark_work_for_retagging(conn: &Connection, rjcode: &RJCode) -> Result<usize, HvtError> {
+    let rows = conn.execute(
+        &format!(
+            "UPDATE {DB_FILE_PROCESSING_NAME} SET tag_date = NULL, is_tagged = 0
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![rjcode],
+    )?;
+    Ok(rows)
+}
+
+/// Makes the next `--full-retag` re-tag the works with files tagged under other tagging settings
+/// than `config_hash` (e.g. another tag separator). Files tagged before the hash was recorded
+/// are assumed to match it. Returns how many works were marked.
+pub fn mark_works_with_other_tag_config(conn: &Connection, config_hash: &str) -> Result<usize, HvtError> {
+    conn.execute(
+        &format!("UPDATE {DB_FILE_PROCESSING_NAME} SET tag_config_hash = ?1 WHERE is_tagged = 1 AND tag_config_hash IS NULL"),
+        params![config_hash],
+    )?;
+    let works: usize = conn.query_row(
+        &format!(
+            "SELECT COUNT(DISTINCT fld_id) FROM {DB_FILE_PROCESSING_NAME}
+             WHERE is_tagged = 1 AND tag_config_hash <> ?1"
+        ),
+        params![config_hash],
+        |row| row.get(0),
+    )?;
+    if works > 0 {
+        conn.execute(
+            &format!(
+                "UPDATE {DB_FILE_PROCESSING_NAME} SET tag_date = NULL, is_tagged = 0
+                 WHERE fld_id IN (SELECT fld_id FROM {DB_FILE_PROCESSING_NAME}
+                                  WHERE is_tagged = 1 AND tag_config_hash <> ?1)"
+            ),
+            params![config_hash],
+        )?;
+    }
+    Ok(works)
+}
+
+/// Hides a work from every command (`--remove` without `--purge`); its rows are kept, so setting
+/// `active` back to 1 restores it.
+pub fn deactivate_work(conn: &Connection, rjcode: &RJCode) -> Result<(), HvtError> {
+    conn.execute(
+        &format!("UPDATE {DB_FOLDERS_NAME} SET active = 0 WHERE rjcode = ?1"),
+        params![rjcode],
+    )?;
+    Ok(())
+}
+
+/// Records whether a work's folder is missing from disk (`--prune`). Returns for how many days it
+/// has been missing, 0 when it is there.
+pub fn set_folder_missing(conn: &Connection, rjcode: &RJCode, missing: bool) -> Result<f64, HvtError> {
+    if !missing {
+        conn.execute(
+            &format!("UPDATE {DB_FOLDERS_NAME} SET missing_since = NULL WHERE rjcode = ?1 AND missing_since IS NOT NULL"),
+            params![rjcode],
+        )?;
+        return Ok(0.0);
+    }
+    conn.execute(
+        &format!("UPDATE {DB_FOLDERS_NAME} SET missing_since = COALESCE(missing_since, datetime()) WHERE rjcode = ?1"),
+        params![rjcode],
+    )?;
+    let days: f64 = conn.query_row(
+        &format!("SELECT julianday('now') - julianday(missing_since) FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"),
+        params![rjcode],
+        |row| row.get(0),
+    )?;
+    Ok(days)
+}
+
+/// Marks every file of a work as no longer tagged (after `--untag`), so the next run re-tags it.
+pub fn reset_work_tagged_files(conn: &Connection, rjcode: &RJCode) -> Result<usize, HvtError> {
+    let rows = conn.execute(
+        &format!(
+            "UPDATE {DB_FILE_PROCESSING_NAME} SET is_tagged = 0
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![rjcode],
+    )?;
+    Ok(rows)
+}
+
+/// Appends an event to `processing_history` (e.g. one per work and command, see `run_stats`).
+pub fn insert_processing_event(
+    conn: &Connection,
+    rjcode: &RJCode,
+    operation_type: &str,
+    stage: &str,
+    status: &str,
+    error_message: Option<&str>,
+) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {DB_PROCESSING_HISTORY_NAME}
+             (fld_id, operation_type, stage, status, error_message, completed_at)
+             SELECT fld_id, ?2, ?3, ?4, ?5, datetime('now')
+             FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+        ),
+        params![rjcode, operation_type, stage, status, error_message],
+    )?;
+    Ok(())
+}
+
 /// Get all unscanned works with their paths from the database
-pub fn get_unscanned_works_with_paths(conn: &Connection) -> Result<Vec<(RJCode, String)>, HvtError> {
+pub fn get_unscanned_works_with_paths(conn: &Connection) -> Result<Vec<(RJCode, PathBuf)>, HvtError> {
     let mut stmt = conn.prepare(&format!(
         "SELECT rjcode, path FROM {DB_FOLDERS_NAME}
          WHERE fld_id NOT IN (SELECT fld_id FROM {DB_WORKS_NAME})"
     ))?;
     let rows = stmt.query_map([], |row| {
-        Ok((row.get(0)?, row.get(1)?))
+        Ok((row.get(0)?, row.get::<_, DbPath>(1)?.0))
     })?;
-    let works: Vec<(RJCode, String)> = rows.collect::<Result<Vec<_>, _>>()?;
+    let works: Vec<(RJCode, PathBuf)> = rows.collect::<Result<Vec<_>, _>>()?;
     Ok(works)
 }
 
@@ -430,7 +1076,7 @@ pub fn get_track_parsing_preference(
     let result = conn.query_row(
         &format!(
             "SELECT strategy_name, custom_delimiter, use_asian_conversion, asian_format_type,
-                    strip_prefix_pattern
+                    strip_prefix_pattern, custom_regex
              FROM {DB_TRACK_PARSING_PREFS_NAME}
              WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
         ),
@@ -442,6 +1088,7 @@ pub fn get_track_parsing_preference(
                 use_asian_conversion: row.get::<_, i64>(2)? != 0,
                 asian_format_type: row.get(3)?,
                 strip_prefix_pattern: row.get(4)?,
+                custom_regex: row.get(5)?,
             })
         },
     );
@@ -463,10 +1110,10 @@ pub fn save_track_parsing_preference(
         &format!(
             "INSERT OR REPLACE INTO {DB_TRACK_PARSING_PREFS_NAME}
              (fld_id, strategy_name, custom_delimiter, use_asian_conversion, asian_format_type,
-              strip_prefix_pattern, last_used)
+              strip_prefix_pattern, custom_regex, last_used)
              VALUES (
                  (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1),
-                 ?2, ?3, ?4, ?5, ?6, datetime('now')
+                 ?2, ?3, ?4, ?5, ?6, ?7, datetime('now')
              )"
         ),
         params![
@@ -476,6 +1123,7 @@ pub fn save_track_parsing_preference(
             preference.use_asian_conversion,
             &preference.asian_format_type,
             &preference.strip_prefix_pattern,
+            &preference.custom_regex,
         ],
     )?;
 
@@ -486,7 +1134,7 @@ pub fn save_track_parsing_preference(
 pub fn update_folder_path(
     conn: &Connection,
     rjcode: &RJCode,
-    new_path: &str,
+    new_path: &Path,
 ) -> Result<usize, HvtError> {
     let rows = conn.execute(
         &format!(
@@ -494,7 +1142,7 @@ pub fn update_folder_path(
              SET path = ?1
              WHERE rjcode = ?2"
         ),
-        params![new_path, rjcode],
+        params![DbPathRef(new_path), rjcode],
     )?;
     Ok(rows)
 }
@@ -528,4 +1176,196 @@ mod tests {
     fn test_normalize_cv_name_trims_whitespace() {
         assert_eq!(normalize_cv_name("  Nodoka Nishiura  "), "Nodoka Nishiura");
     }
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_quoted_values_are_stored_verbatim() {
+        let conn = test_db();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        let path = "/library/it's \"here\"'); DROP TABLE folders; --";
+        insert_folder_record(&conn, &work, Path::new(path), true).unwrap();
+        insert_work_name(&conn, &work, "お姉さん's 'ASMR'", None).unwrap();
+        insert_error(&conn, &work, "can't parse", None).unwrap();
+
+        assert_eq!(get_work_path(&conn, &work).unwrap().as_deref(), Some(Path::new(path)));
+        let name: String = conn
+            .query_row(&format!("SELECT name FROM {DB_WORKS_NAME}"), [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(name, "お姉さん's 'ASMR'");
+    }
+
+    #[test]
+    fn test_dlsite_section_is_kept_with_the_scan() {
+        let conn = test_db();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        insert_folder_record(&conn, &work, Path::new("/library/RJ01234567"), true).unwrap();
+        assert_eq!(get_dlsite_section(&conn, &work).unwrap(), None);
+
+        set_work_scan_date(&conn, &work, Some("home")).unwrap();
+        assert_eq!(get_dlsite_section(&conn, &work).unwrap().as_deref(), Some("home"));
+        // A fetch from other sources only keeps the section already known
+        set_work_scan_date(&conn, &work, None).unwrap();
+        assert_eq!(get_dlsite_section(&conn, &work).unwrap().as_deref(), Some("home"));
+        set_work_scan_date(&conn, &work, Some("girls")).unwrap();
+        assert_eq!(get_dlsite_section(&conn, &work).unwrap().as_deref(), Some("girls"));
+    }
+
+    #[test]
+    fn test_tags_and_cvs_with_quotes_are_assigned() {
+        let conn = test_db();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        insert_folder_record(&conn, &work, Path::new("/library/RJ01234567"), true).unwrap();
+        insert_tag(&conn, "Girl's Love", 1).unwrap();
+        insert_cv(&conn, "O'Hara", "").unwrap();
+
+        let tags = vec!["Girl's Love".to_string()];
+        assert_eq!(assign_tags_to_work(&conn, &work, &tags).unwrap(), 1);
+        assert_eq!(assign_cvs_to_work(&conn, &work, &["O'Hara".to_string()]).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_init_unescapes_doubled_quotes_in_tag_names() {
+        let conn = test_db();
+        insert_tag(&conn, "Girl''s Love", 1).unwrap();
+        // Already stored unescaped: the doubled row is left alone
+        insert_tag(&conn, "Boy's Love", 2).unwrap();
+        insert_tag(&conn, "Boy''s Love", 3).unwrap();
+        crate::database::init(&conn).unwrap();
+
+        let names: Vec<String> = conn
+            .prepare(&format!("SELECT tag_name FROM {DB_DLSITE_TAG_NAME} ORDER BY tag_id"))
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(names, ["Girl's Love", "Boy's Love", "Boy''s Love"]);
+    }
+
+    #[test]
+    fn test_repeated_errors_are_counted_as_retries() {
+        let conn = test_db();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        insert_folder_record(&conn, &work, Path::new("/library/RJ01234567"), true).unwrap();
+        insert_error(&conn, &work, "removed work", Some("dlsite_removed")).unwrap();
+        insert_error(&conn, &work, "removed work", Some("dlsite_removed")).unwrap();
+        insert_error(&conn, &work, "timeout", None).unwrap();
+
+        let errors = get_errors(&conn, Some("dlsite_removed"), true).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].retry_count, 1);
+
+        assert_eq!(resolve_errors(&conn, &work).unwrap(), 2);
+        assert!(get_errors(&conn, None, true).unwrap().is_empty());
+        // Once resolved, the same error is a new entry
+        insert_error(&conn, &work, "removed work", Some("dlsite_removed")).unwrap();
+        assert_eq!(get_errors(&conn, None, false).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_work_type_round_trip() {
+        let conn = test_db();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        insert_folder_record(&conn, &work, Path::new("/library/RJ01234567"), true).unwrap();
+        assert_eq!(get_work_type(&conn, &work).unwrap(), None);
+
+        let options = vec!["JPN".to_string(), "AIG".to_string()];
+        assign_work_type_to_work(&conn, &work, Some("MOV"), &options, &[]).unwrap();
+        let (work_type, stored_options, custom_genres) = get_work_type(&conn, &work).unwrap().unwrap();
+        assert_eq!(work_type.as_deref(), Some("MOV"));
+        assert_eq!(stored_options, options);
+        assert!(custom_genres.is_empty());
+    }
+
+    #[test]
+    fn test_pending_works() {
+        let conn = test_db();
+        let tagged = RJCode::new("RJ01000001".to_string()).unwrap();
+        let retag = RJCode::new("RJ01000002".to_string()).unwrap();
+        let new = RJCode::new("RJ01000003".to_string()).unwrap();
+        for work in [&tagged, &retag, &new] {
+            insert_folder_record(&conn, work, Path::new(&format!("/library/{}", work)), true).unwrap();
+        }
+        for (work, file) in [(&tagged, "01.mp3"), (&retag, "01.mp3"), (&retag, "02.mp3")] {
+            conn.execute(
+                &format!(
+                    "INSERT INTO {DB_FILE_PROCESSING_NAME} (fld_id, file_path, file_name, is_tagged)
+                     SELECT fld_id, path || '/' || ?2, ?2, 1 FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+                ),
+                params![work, file],
+            )
+            .unwrap();
+        }
+        mark_work_for_retagging(&conn, &retag).unwrap();
+
+        let pending: Vec<RJCode> = get_pending_works_with_paths(&conn).unwrap().into_iter().map(|(w, _)| w).collect();
+        assert_eq!(pending, [retag, new]);
+    }
+
+    #[test]
+    fn test_works_tagged_with_other_settings_are_marked() {
+        let conn = test_db();
+        let old = RJCode::new("RJ01000001".to_string()).unwrap();
+        let current = RJCode::new("RJ01000002".to_string()).unwrap();
+        for work in [&old, &current] {
+            insert_folder_record(&conn, work, Path::new(&format!("/library/{}", work)), true).unwrap();
+            conn.execute(
+                &format!(
+                    "INSERT INTO {DB_FILE_PROCESSING_NAME} (fld_id, file_path, file_name, is_tagged)
+                     SELECT fld_id, path || '/01.mp3', '01.mp3', 1 FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+                ),
+                params![work],
+            )
+            .unwrap();
+        }
+        // Files tagged before the hash was recorded are taken as tagged with the current settings
+        assert_eq!(mark_works_with_other_tag_config(&conn, "aaaa").unwrap(), 0);
+        assert!(is_work_tagged(&conn, &old).unwrap());
+
+        conn.execute(
+            &format!("UPDATE {DB_FILE_PROCESSING_NAME} SET tag_config_hash = 'bbbb' WHERE file_path LIKE '%RJ01000002%'"),
+            [],
+        )
+        .unwrap();
+        assert_eq!(mark_works_with_other_tag_config(&conn, "bbbb").unwrap(), 1);
+        assert!(!is_work_tagged(&conn, &old).unwrap());
+        assert!(is_work_tagged(&conn, &current).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_moved_files_with_non_utf8_names_are_recorded() {
+        use std::os::unix::ffi::OsStrExt;
+        let conn = test_db();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        let (old, new) = (Path::new("/import/RJ01234567"), Path::new("/library/RJ01234567"));
+        insert_folder_record(&conn, &work, old, true).unwrap();
+        // "音声" in Shift-JIS, and a sibling folder sharing the prefix
+        let garbled = old.join(std::ffi::OsStr::from_bytes(b"\x89\xb9\x90\xba.mp3"));
+        for file in [garbled.as_path(), &old.join("02.mp3"), Path::new("/import/RJ01234567 (2)/01.mp3")] {
+            conn.execute(
+                &format!(
+                    "INSERT INTO {DB_FILE_PROCESSING_NAME} (fld_id, file_path, file_name)
+                     SELECT fld_id, ?2, 'x' FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+                ),
+                params![work, DbPathRef(file)],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(record_files_moved(&conn, &work, old, new).unwrap(), 2);
+        let destination: DbPath = conn
+            .query_row(
+                &format!("SELECT move_destination FROM {DB_FILE_PROCESSING_NAME} WHERE file_path = ?1"),
+                [DbPathRef(&garbled)],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(destination.0, new.join(garbled.file_name().unwrap()));
+    }
 }
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection};
+
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// A ranking reached by a work: (term, category, rank, rank_date)
+pub type Ranking = (String, String, i64, String);
+
+/// Records the rankings listed by a fetch; those already known (same term, category and date)
+/// are ignored
+pub fn record_rankings(conn: &Connection, rjcode: &RJCode, rankings: &[(String, String, u32, String)]) -> Result<(), HvtError> {
+    for (term, category, rank, rank_date) in rankings {
+        conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO {DB_RANKING_HISTORY_NAME} (rjcode, term, category, rank, rank_date)
+                 VALUES (?1, ?2, ?3, ?4, ?5)"
+            ),
+            params![rjcode.as_str(), term, category, rank, rank_date],
+        )?;
+    }
+    Ok(())
+}
+
+/// Every ranking of a work, oldest first
+pub fn get_history(conn: &Connection, rjcode: &RJCode) -> Result<Vec<Ranking>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT term, category, rank, rank_date FROM {DB_RANKING_HISTORY_NAME}
+         WHERE rjcode = ?1
+         ORDER BY rank_date, term, category"
+    ))?;
+    let rankings = stmt
+        .query_map(params![rjcode.as_str()], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rankings)
+}
+
+/// Best (lowest) rank each ranked work ever reached, any term or category, keyed by RJ code.
+/// On a tie the latest ranking is kept.
+pub fn get_best_rankings(conn: &Connection) -> Result<HashMap<String, Ranking>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT rjcode, term, category, rank, rank_date FROM {DB_RANKING_HISTORY_NAME}
+         ORDER BY rank DESC, rank_date"
+    ))?;
+    // Rows come worst first, so the best ranking of each work is inserted last
+    let best = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, (row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))))?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_best_rankings() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        let ranking = |term: &str, rank: u32, date: &str| (term.to_string(), "voice".to_string(), rank, date.to_string());
+        record_rankings(&conn, &work, &[ranking("day", 12, "2026-01-01"), ranking("week", 30, "2026-01-07")]).unwrap();
+        // Fetched again: the known rankings are not duplicated
+        record_rankings(&conn, &work, &[ranking("day", 12, "2026-01-01"), ranking("day", 3, "2026-01-02")]).unwrap();
+
+        assert_eq!(get_history(&conn, &work).unwrap().len(), 3);
+        let best = get_best_rankings(&conn).unwrap();
+        assert_eq!(best["RJ01234567"], ("day".to_string(), "voice".to_string(), 3, "2026-01-02".to_string()));
+    }
+}
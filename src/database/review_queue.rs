@@ -0,0 +1,89 @@
+use rusqlite::{params, Connection};
+
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// Why a work was put in the review queue, stored as `review_queue.reason`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReviewReason {
+    /// Track numbers could not be parsed reliably (failures or duplicates)
+    TrackNumbering,
+    /// A bundled tracklist file was found but its titles were not confirmed
+    Tracklist,
+    /// The circle profile page gave no name, the circle is tagged by its RG code
+    MissingCircleName,
+    /// Fetching the work's metadata from DLSite failed
+    ScrapeFailed,
+    /// A source folder could not be renamed to its RJ code without a prompt (target taken)
+    RenameConflict,
+    /// Empty or silent audio files were found after tagging (`[integrity] detect_silent_audio`)
+    DamagedAudio,
+}
+
+impl ReviewReason {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "track_numbering" => Some(ReviewReason::TrackNumbering),
+            "tracklist" => Some(ReviewReason::Tracklist),
+            "missing_circle_name" => Some(ReviewReason::MissingCircleName),
+            "scrape_failed" => Some(ReviewReason::ScrapeFailed),
+            "rename_conflict" => Some(ReviewReason::RenameConflict),
+            "damaged_audio" => Some(ReviewReason::DamagedAudio),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReviewReason::TrackNumbering => "track_numbering",
+            ReviewReason::Tracklist => "tracklist",
+            ReviewReason::MissingCircleName => "missing_circle_name",
+            ReviewReason::ScrapeFailed => "scrape_failed",
+            ReviewReason::RenameConflict => "rename_conflict",
+            ReviewReason::DamagedAudio => "damaged_audio",
+        }
+    }
+}
+
+/// Queues (or refreshes) a work for review.
+pub fn add_review_item(
+    conn: &Connection,
+    rjcode: &RJCode,
+    reason: ReviewReason,
+    details: &str,
+) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO {DB_REVIEW_QUEUE_NAME} (fld_id, reason, details, queued_at)
+             SELECT fld_id, ?2, ?3, datetime('now') FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+        ),
+        params![rjcode, reason.as_str(), details],
+    )?;
+    Ok(())
+}
+
+/// Removes a work's review item once the decision has been made.
+pub fn resolve_review_item(conn: &Connection, rjcode: &RJCode, reason: ReviewReason) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "DELETE FROM {DB_REVIEW_QUEUE_NAME}
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1) AND reason = ?2"
+        ),
+        params![rjcode, reason.as_str()],
+    )?;
+    Ok(())
+}
+
+/// Open review items, oldest first. Returns Vec<(rjcode, reason, details)>.
+pub fn get_review_items(conn: &Connection) -> Result<Vec<(String, String, Option<String>)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.rjcode, r.reason, r.details
+         FROM {DB_REVIEW_QUEUE_NAME} r
+         JOIN {DB_FOLDERS_NAME} f ON f.fld_id = r.fld_id
+         WHERE f.active = 1
+         ORDER BY r.queued_at, f.rjcode"
+    ))?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
@@ -0,0 +1,56 @@
+use rusqlite::{params, Connection};
+
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// One fetch of a work's sales figures: (fetched_at, price, dl_count, wishlist_count)
+pub type SalesSample = (String, Option<i64>, Option<i64>, Option<i64>);
+
+/// Records the price, download and wishlist counts returned by a fetch
+pub fn record_sample(
+    conn: &Connection,
+    rjcode: &RJCode,
+    price: Option<u32>,
+    dl_count: Option<u64>,
+    wishlist_count: Option<u64>,
+) -> Result<(), HvtError> {
+    conn.execute(
+        &format!("INSERT INTO {DB_SALES_HISTORY_NAME} (rjcode, price, dl_count, wishlist_count) VALUES (?1, ?2, ?3, ?4)"),
+        params![rjcode.as_str(), price, dl_count.map(|c| c as i64), wishlist_count.map(|c| c as i64)],
+    )?;
+    Ok(())
+}
+
+/// Every recorded fetch of a work, oldest first
+pub fn get_history(conn: &Connection, rjcode: &RJCode) -> Result<Vec<SalesSample>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT fetched_at, price, dl_count, wishlist_count FROM {DB_SALES_HISTORY_NAME}
+         WHERE rjcode = ?1
+         ORDER BY sample_id"
+    ))?;
+    let samples = stmt
+        .query_map(params![rjcode.as_str()], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_oldest_first() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        record_sample(&conn, &work, Some(1320), Some(100), Some(40)).unwrap();
+        record_sample(&conn, &work, Some(990), Some(250), None).unwrap();
+
+        let history = get_history(&conn, &work).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!((history[0].1, history[0].2), (Some(1320), Some(100)));
+        assert_eq!((history[1].1, history[1].2, history[1].3), (Some(990), Some(250), None));
+        assert!(get_history(&conn, &RJCode::new("RJ07654321".to_string()).unwrap()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use rusqlite::{Connection, ToSql, MAIN_DB};
+
+use crate::database::db_path::DbPath;
+use crate::database::tables::*;
+use crate::errors::HvtError;
+
+/// Trigram tokenizer: a term needs at least this many characters to be looked up in the index.
+const MIN_INDEXED_TERM_CHARS: usize = 3;
+
+/// One `--search` result.
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub rjcode: String,
+    pub name: String,
+    pub path: String,
+    pub tagged_files: i64,
+    pub total_files: i64,
+}
+
+/// Repopulates `works_fts` from the current state of the library: one row per active work with
+/// its title (JP and EN), circle (codes, EN/JP names, custom name), CVs, tags (DLSite and custom
+/// names) and description. The table is recreated, so indexes created by an older version pick up new columns.
+/// On a read-only connection (`--read-only`) the index is built in the `temp` schema instead,
+/// where it shadows the stored one for the rest of the session.
+pub fn rebuild_search_index(conn: &Connection) -> Result<(), HvtError> {
+    let index = if conn.is_readonly(MAIN_DB)? {
+        format!("temp.{DB_WORKS_FTS_NAME}")
+    } else {
+        DB_WORKS_FTS_NAME.to_string()
+    };
+
+    conn.execute(&format!("DROP TABLE IF EXISTS {index}"), [])?;
+    conn.execute(&format!("CREATE VIRTUAL TABLE {index} USING fts5({DB_WORKS_FTS_COLS})"), [])?;
+    conn.execute(
+        &format!(
+            "INSERT INTO {index} (rjcode, name, circle, cvs, tags, description)
+             SELECT f.rjcode,
+                COALESCE((SELECT w.name || ' ' || COALESCE(w.name_en, '') FROM {DB_WORKS_NAME} w WHERE w.fld_id = f.fld_id LIMIT 1), ''),
+                COALESCE((
+                    SELECT group_concat(c.rgcode || ' ' || COALESCE(c.name_en, '') || ' ' || COALESCE(c.name_jp, '') || ' ' || COALESCE(ccm.custom_name, ''), ' ')
+                    FROM {DB_LKP_WORK_CIRCLE_NAME} lwc
+                    JOIN {DB_CIRCLE_NAME} c ON c.cir_id = lwc.cir_id
+                    LEFT JOIN {DB_CUSTOM_CIRCLE_MAPPINGS_NAME} ccm ON ccm.cir_id = c.cir_id
+                    WHERE lwc.fld_id = f.fld_id
+                ), ''),
+                COALESCE((
+                    SELECT group_concat(cv.name_jp || ' ' || COALESCE(cv.name_en, '') || ' ' || COALESCE(ccvm.custom_name, ''), ' ')
+                    FROM {DB_LKP_WORK_CVS_NAME} lwcv
+                    JOIN {DB_CVS_NAME} cv ON cv.cv_id = lwcv.cv_id
+                    LEFT JOIN {DB_CUSTOM_CV_MAPPINGS_NAME} ccvm ON ccvm.cv_id = cv.cv_id
+                    WHERE lwcv.fld_id = f.fld_id
+                ), ''),
+                COALESCE((
+                    SELECT group_concat(dt.tag_name || ' ' || COALESCE(dt.tag_name_jp, '') || ' ' || COALESCE(ctm.custom_tag_name, ''), ' ')
+                    FROM {DB_LKP_WORK_TAG_NAME} lwt
+                    JOIN {DB_DLSITE_TAG_NAME} dt ON dt.tag_id = lwt.tag_id
+                    LEFT JOIN {DB_CUSTOM_TAG_MAPPINGS_NAME} ctm ON ctm.dlsite_tag_id = dt.tag_id
+                    WHERE lwt.fld_id = f.fld_id
+                ), ''),
+                COALESCE((SELECT d.description FROM {DB_DESCRIPTION_NAME} d WHERE d.fld_id = f.fld_id LIMIT 1), '')
+             FROM {DB_FOLDERS_NAME} f
+             WHERE f.active = 1"
+        ),
+        [],
+    )?;
+    Ok(())
+}
+
+/// Searches active works: every whitespace-separated term must match somewhere in the RJ code,
+/// title, circle, CVs, tags or description. Terms of 3+ characters go through the FTS index (results ranked
+/// by relevance); shorter ones (common in Japanese: 耳かき is fine, 睡眠 isn't) fall back to a
+/// substring scan of the indexed text. Call `rebuild_search_index` first.
+pub fn search_works(conn: &Connection, query: &str) -> Result<Vec<SearchHit>, HvtError> {
+    let (indexed, short): (Vec<&str>, Vec<&str>) = query
+        .split_whitespace()
+        .partition(|term| term.chars().count() >= MIN_INDEXED_TERM_CHARS);
+
+    if indexed.is_empty() && short.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut conditions = Vec::new();
+    let mut values: Vec<String> = Vec::new();
+
+    if !indexed.is_empty() {
+        // Each term as a quoted FTS5 string (no operator parsing); juxtaposition means AND
+        let match_expr = indexed.iter()
+            .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        values.push(match_expr);
+        conditions.push(format!("{DB_WORKS_FTS_NAME} MATCH ?{}", values.len()));
+    }
+
+    for term in &short {
+        values.push(term.to_string());
+        conditions.push(format!(
+            "(s.rjcode || ' ' || s.name || ' ' || s.circle || ' ' || s.cvs || ' ' || s.tags || ' ' || s.description) LIKE '%' || ?{} || '%'",
+            values.len()
+        ));
+    }
+
+    let order_by = if indexed.is_empty() { "f.rjcode" } else { "s.rank, f.rjcode" };
+
+    let sql = format!(
+        "SELECT f.rjcode, s.name, f.path,
+            (SELECT COUNT(*) FROM {DB_FILE_PROCESSING_NAME} fp WHERE fp.fld_id = f.fld_id AND fp.is_tagged = 1),
+            (SELECT COUNT(*) FROM {DB_FILE_PROCESSING_NAME} fp WHERE fp.fld_id = f.fld_id)
+         FROM {DB_WORKS_FTS_NAME} s
+         JOIN {DB_FOLDERS_NAME} f ON f.rjcode = s.rjcode
+         WHERE {conditions}
+         ORDER BY {order_by}",
+        conditions = conditions.join(" AND "),
+    );
+
+    let params: Vec<&dyn ToSql> = values.iter().map(|v| v as &dyn ToSql).collect();
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params.as_slice(), |row| {
+        Ok(SearchHit {
+            rjcode: row.get(0)?,
+            name: row.get(1)?,
+            path: row.get::<_, Option<DbPath>>(2)?.map(|p| p.0.display().to_string()).unwrap_or_default(),
+            tagged_files: row.get(3)?,
+            total_files: row.get(4)?,
+        })
+    })?;
+
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
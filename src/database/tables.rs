@@ -1,13 +1,16 @@
 pub const DB_FOLDERS_NAME: &str = "folders";
 pub const DB_FOLDERS_COLS: &str = "fld_id INTEGER PRIMARY KEY, rjcode TEXT NOT NULL UNIQUE, path TEXT, last_scan TEXT, active BOOLEAN";
 
+// section: DLSite section the work was found under (maniax, home, girls...), NULL when DLSite
+// is not a metadata source
 pub const DB_DLSITE_SCAN_NAME: &str = "dlsite_scan";
 pub const DB_DLSITE_SCAN_COLS: &str = "fld_id INTEGER NOT NULL, \
     last_scan TEXT, \
+    section TEXT, \
     FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
 
 pub const DB_DLSITE_TAG_NAME: &str = "dlsite_tag";
-pub const DB_DLSITE_TAG_COLS: &str = "tag_id INTEGER PRIMARY KEY, tag_name TEXT NOT NULL UNIQUE";
+pub const DB_DLSITE_TAG_COLS: &str = "tag_id INTEGER PRIMARY KEY, tag_name TEXT NOT NULL UNIQUE, tag_name_jp TEXT";
 
 pub const DB_CIRCLE_NAME: &str = "circles";
 pub const DB_CIRCLE_COLS: &str = "cir_id INTEGER PRIMARY KEY, rgcode TEXT NOT NULL UNIQUE, name_en TEXT, name_jp TEXT";
@@ -41,12 +44,35 @@ pub const DB_STARS_COLS: &str = "fld_id INTEGER NOT NULL, \
     stars REAL, \
     FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
 
+// Number of DLSite ratings and written reviews of a work, and its ratings per star (1 to 5)
+pub const DB_REVIEWS_NAME: &str = "reviews";
+pub const DB_REVIEWS_COLS: &str = "fld_id INTEGER NOT NULL, \
+    rate_count INTEGER, \
+    review_count INTEGER, \
+    rate_1 INTEGER, \
+    rate_2 INTEGER, \
+    rate_3 INTEGER, \
+    rate_4 INTEGER, \
+    rate_5 INTEGER, \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
+
+// DLSite work type (SOU, MOV, MUS...), option codes and circle-defined genres of a work, the
+// lists '#'-separated as in the API ([tagger] skip_work_types, --work-type)
+pub const DB_WORK_TYPES_NAME: &str = "work_types";
+pub const DB_WORK_TYPES_COLS: &str = "fld_id INTEGER NOT NULL, \
+    work_type TEXT, \
+    options TEXT, \
+    custom_genres TEXT, \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
+
 pub const DB_WORKS_NAME: &str = "works";
 pub const DB_WORKS_COLS: &str = "fld_id INTEGER NOT NULL, \
     name TEXT, \
     img_link TEXT, \
     FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
 
+// alias_of: cv_id of the canonical CV when this row is a name variant merged into it (added by
+// migration, see custom_cvs::merge_cv_alias). Always points at a canonical row, never an alias.
 pub const DB_CVS_NAME: &str = "cvs";
 pub const DB_CVS_COLS: &str = "cv_id INTEGER PRIMARY KEY, name_jp TEXT NOT NULL UNIQUE, name_en TEXT";
 
@@ -68,6 +94,31 @@ pub const DB_DLSITE_ERRORS_COLS: &str = "fld_id INTEGER NOT NULL, \
     resolved_date TEXT, \
     FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
 
+pub const DB_DESCRIPTION_NAME: &str = "descriptions";
+pub const DB_DESCRIPTION_COLS: &str = "fld_id INTEGER NOT NULL, \
+    description TEXT, \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
+
+// Translation/series relationships from the DLSite API's translation_info. related_rjcode may be a
+// work that is not in the library. relation: 'original' (the untranslated work), 'parent' (the
+// translation this one derives from), 'child' (a translation of this work)
+pub const DB_WORK_RELATIONS_NAME: &str = "work_relations";
+pub const DB_WORK_RELATIONS_COLS: &str = "fld_id INTEGER NOT NULL, \
+    related_rjcode TEXT NOT NULL, \
+    relation TEXT NOT NULL CHECK(relation IN ('original', 'parent', 'child')), \
+    PRIMARY KEY (fld_id, related_rjcode, relation), \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
+
+// Sample/gallery images of a work's DLSite page. file_name is set once the image is saved in the
+// work's scans/ folder (--images full), and cleared when DLSite changes the image at that position
+pub const DB_WORK_IMAGES_NAME: &str = "work_images";
+pub const DB_WORK_IMAGES_COLS: &str = "fld_id INTEGER NOT NULL, \
+    position INTEGER NOT NULL, \
+    image_url TEXT NOT NULL, \
+    file_name TEXT, \
+    PRIMARY KEY (fld_id, position), \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
+
 pub const DB_DLSITE_COVERS_LINK_NAME: &str = "dlsite_covers";
 pub const DB_DLSITE_COVERS_LINK_COLS: &str = "fld_id INTEGER NOT NULL, \
     link TEXT, \
@@ -173,3 +224,158 @@ pub const DB_TRACK_PARSING_PREFS_COLS: &str = "pref_id INTEGER PRIMARY KEY AUTOI
 
 pub const DB_TRACK_PARSING_PREFS_INDEX: &str =
     "CREATE INDEX IF NOT EXISTS idx_track_parsing_fld_id ON track_parsing_preferences(fld_id)";
+
+// Audio integrity check results (--verify). One row per file, overwritten by each new check.
+// status: 'ok', 'corrupt' (ffmpeg reported decode errors), 'error' (ffmpeg could not be run)
+pub const DB_FILE_INTEGRITY_NAME: &str = "file_integrity";
+pub const DB_FILE_INTEGRITY_COLS: &str = "file_path TEXT PRIMARY KEY, \
+    fld_id INTEGER NOT NULL, \
+    status TEXT NOT NULL CHECK(status IN ('ok', 'corrupt', 'error')), \
+    error_details TEXT, \
+    checked_at TEXT DEFAULT (datetime('now')), \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
+
+pub const DB_FILE_INTEGRITY_INDEX_FLD_ID: &str =
+    "CREATE INDEX IF NOT EXISTS idx_file_integrity_fld_id ON file_integrity(fld_id)";
+
+// Full-text search index over works (--search). Derived data only: rebuilt from the tables
+// above before each search. Trigram tokenizer since Japanese titles have no word boundaries
+// (side effect: terms shorter than 3 characters can't use the index, see database::search).
+pub const DB_WORKS_FTS_NAME: &str = "works_fts";
+pub const DB_WORKS_FTS_COLS: &str = "rjcode, name, circle, cvs, tags, description, tokenize = 'trigram'";
+
+// Initial library import queue (--import-library). One row per work; processed in fld_id order,
+// a few hundred per day, so the position survives restarts.
+// status: 'pending', 'done', 'failed' (retried until MAX_IMPORT_ATTEMPTS, after pending works)
+pub const DB_LIBRARY_IMPORT_QUEUE_NAME: &str = "library_import_queue";
+pub const DB_LIBRARY_IMPORT_QUEUE_COLS: &str = "fld_id INTEGER PRIMARY KEY, \
+    status TEXT NOT NULL DEFAULT 'pending' CHECK(status IN ('pending', 'done', 'failed')), \
+    attempts INTEGER DEFAULT 0, \
+    error_details TEXT, \
+    queued_at TEXT DEFAULT (datetime('now')), \
+    processed_at TEXT, \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
+
+// Library health snapshots taken around bulk runs (see database::backup). A 'before' row is
+// written with the path of the DB backup taken just before the run, an 'after' row once it ends.
+pub const DB_HEALTH_SNAPSHOTS_NAME: &str = "health_snapshots";
+pub const DB_HEALTH_SNAPSHOTS_COLS: &str = "snapshot_id INTEGER PRIMARY KEY, \
+    run_name TEXT NOT NULL, \
+    phase TEXT NOT NULL CHECK(phase IN ('before', 'after')), \
+    taken_at TEXT DEFAULT (datetime('now')), \
+    works_affected INTEGER, \
+    active_works INTEGER, \
+    tagged_works INTEGER, \
+    circles INTEGER, \
+    cvs INTEGER, \
+    tags INTEGER, \
+    unresolved_errors INTEGER, \
+    backup_path TEXT";
+
+// Works that need a human decision which an unattended run (--non-interactive / [track_parsing]
+// prompt = false) could not ask for. One row per work and reason, removed once resolved.
+// reason: 'track_numbering' (uncertain track numbers), 'tracklist' (unconfirmed tracklist titles)
+pub const DB_REVIEW_QUEUE_NAME: &str = "review_queue";
+pub const DB_REVIEW_QUEUE_COLS: &str = "review_id INTEGER PRIMARY KEY AUTOINCREMENT, \
+    fld_id INTEGER NOT NULL, \
+    reason TEXT NOT NULL, \
+    details TEXT, \
+    queued_at TEXT DEFAULT (datetime('now')), \
+    UNIQUE (fld_id, reason), \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
+
+// Rule-based tag filtering (--manage-tags), applied after the per-tag mappings:
+// rule_type 'ignore_pattern' (value = regex), 'priority' (value = regex, ordered by position),
+// 'max_tags' (value = number, one row at most), 'language' (value = 'jp', one row at most)
+pub const DB_TAG_RULES_NAME: &str = "tag_rules";
+pub const DB_TAG_RULES_COLS: &str = "rule_id INTEGER PRIMARY KEY AUTOINCREMENT, \
+    rule_type TEXT NOT NULL CHECK(rule_type IN ('ignore_pattern', 'priority', 'max_tags', 'language')), \
+    value TEXT NOT NULL, \
+    position INTEGER NOT NULL DEFAULT 0, \
+    created_at TEXT DEFAULT (datetime('now'))";
+
+// Tag categories (--manage-tags): groups of DLSite tags written to their own frame instead of
+// the genre. frame: 'genre', 'mood' (TMOO), 'txxx' (TXXX named after the category)
+pub const DB_TAG_CATEGORIES_NAME: &str = "tag_categories";
+pub const DB_TAG_CATEGORIES_COLS: &str = "category_id INTEGER PRIMARY KEY AUTOINCREMENT, \
+    name TEXT NOT NULL UNIQUE, \
+    frame TEXT NOT NULL CHECK(frame IN ('genre', 'mood', 'txxx')), \
+    created_at TEXT DEFAULT (datetime('now')), \
+    modified_at TEXT DEFAULT (datetime('now'))";
+
+// One category at most per DLSite tag
+pub const DB_LKP_TAG_CATEGORY_NAME: &str = "lkp_tag_category";
+pub const DB_LKP_TAG_CATEGORY_COLS: &str = "tag_id INTEGER PRIMARY KEY, \
+    category_id INTEGER NOT NULL, \
+    FOREIGN KEY (tag_id) REFERENCES dlsite_tag(tag_id) ON DELETE CASCADE, \
+    FOREIGN KEY (category_id) REFERENCES tag_categories(category_id) ON DELETE CASCADE";
+
+// Per-work metadata overrides (--manage-works), applied over the merged values when tagging.
+// field: 'title', 'circle', 'cvs', 'tags' (lists one value per line), 'release_date'
+pub const DB_WORK_OVERRIDES_NAME: &str = "work_overrides";
+pub const DB_WORK_OVERRIDES_COLS: &str = "fld_id INTEGER NOT NULL, \
+    field TEXT NOT NULL CHECK(field IN ('title', 'circle', 'cvs', 'tags', 'release_date')), \
+    value TEXT NOT NULL, \
+    modified_at TEXT DEFAULT (datetime('now')), \
+    PRIMARY KEY (fld_id, field), \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE";
+
+// Work groups (--manage-works): works split into one RJ folder per disc, tagged as one album
+// named after the group
+pub const DB_WORK_GROUPS_NAME: &str = "work_groups";
+pub const DB_WORK_GROUPS_COLS: &str = "group_id INTEGER PRIMARY KEY AUTOINCREMENT, \
+    name TEXT NOT NULL UNIQUE, \
+    created_at TEXT DEFAULT (datetime('now'))";
+
+// One group at most per work; disc_number runs from 1 to the group size without gaps
+pub const DB_LKP_WORK_GROUP_NAME: &str = "lkp_work_group";
+pub const DB_LKP_WORK_GROUP_COLS: &str = "fld_id INTEGER PRIMARY KEY, \
+    group_id INTEGER NOT NULL, \
+    disc_number INTEGER NOT NULL, \
+    FOREIGN KEY (fld_id) REFERENCES folders(fld_id) ON DELETE CASCADE, \
+    FOREIGN KEY (group_id) REFERENCES work_groups(group_id) ON DELETE CASCADE";
+
+// Audio files moved up to the work folder root by the folder normalization, so --normalize
+// --undo can put them back. Paths are relative to the work folder, which may move since;
+// keyed by RJ code as source folders are normalized before being registered
+pub const DB_NORMALIZATION_LOG_NAME: &str = "normalization_log";
+pub const DB_NORMALIZATION_LOG_COLS: &str = "log_id INTEGER PRIMARY KEY AUTOINCREMENT, \
+    rjcode TEXT NOT NULL, \
+    original_path TEXT NOT NULL, \
+    new_path TEXT NOT NULL, \
+    moved_at TEXT DEFAULT (datetime('now')), \
+    undone_at TEXT";
+
+// Price, download and wishlist counts of a work, one row per DLSite fetch (--stats --work).
+// Keyed by RJ code so the history outlives a removed folder
+pub const DB_SALES_HISTORY_NAME: &str = "sales_history";
+pub const DB_SALES_HISTORY_COLS: &str = "sample_id INTEGER PRIMARY KEY AUTOINCREMENT, \
+    rjcode TEXT NOT NULL, \
+    price INTEGER, \
+    dl_count INTEGER, \
+    wishlist_count INTEGER, \
+    fetched_at TEXT DEFAULT (datetime('now'))";
+
+pub const DB_SALES_HISTORY_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS idx_sales_history_rjcode ON sales_history(rjcode)";
+
+// DLSite rankings reached by a work (term: 'day', 'week', 'month', 'year', 'total'; category:
+// 'all', 'voice'...), as listed by the API at each fetch. A ranking is kept once per date.
+pub const DB_RANKING_HISTORY_NAME: &str = "ranking_history";
+pub const DB_RANKING_HISTORY_COLS: &str = "ranking_id INTEGER PRIMARY KEY AUTOINCREMENT, \
+    rjcode TEXT NOT NULL, \
+    term TEXT NOT NULL, \
+    category TEXT NOT NULL, \
+    rank INTEGER NOT NULL, \
+    rank_date TEXT NOT NULL, \
+    UNIQUE (rjcode, term, category, rank_date)";
+
+// Cover URLs whose download failed (404, undecodable image...), so they are not tried again
+// on every run: skipped after [images] cover_max_failures failures, or for
+// cover_failure_cooldown_hours after the last one (--force tries them anyway)
+pub const DB_COVER_FAILURES_NAME: &str = "cover_failures";
+pub const DB_COVER_FAILURES_COLS: &str = "url TEXT PRIMARY KEY, \
+    rjcode TEXT NOT NULL, \
+    failures INTEGER NOT NULL DEFAULT 1, \
+    last_error TEXT, \
+    last_failure TEXT DEFAULT (datetime('now'))";
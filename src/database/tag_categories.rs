@@ -0,0 +1,205 @@
+use std::collections::HashMap;
+
+use rusqlite::{params, Connection};
+
+use crate::database::tables::*;
+use crate::database::tag_rules;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// Where the tags of a category are written, stored as `tag_categories.frame`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CategoryFrame {
+    /// The genre frame (TCON), with the uncategorized tags
+    Genre,
+    /// The mood frame (TMOO, TXXX:MOOD in ID3v2.3)
+    Mood,
+    /// A TXXX frame named after the category in upper case (e.g. TXXX:STYLE)
+    Txxx,
+}
+
+impl CategoryFrame {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "genre" => Some(CategoryFrame::Genre),
+            "mood" => Some(CategoryFrame::Mood),
+            "txxx" => Some(CategoryFrame::Txxx),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CategoryFrame::Genre => "genre",
+            CategoryFrame::Mood => "mood",
+            CategoryFrame::Txxx => "txxx",
+        }
+    }
+}
+
+/// A work's merged tags split by the frame they are written to.
+#[derive(Debug, Default, PartialEq)]
+pub struct CategorizedTags {
+    pub genre: Vec<String>,
+    pub mood: Vec<String>,
+    /// (TXXX description, tags), by category name
+    pub txxx: Vec<(String, Vec<String>)>,
+}
+
+/// TXXX description of a `Txxx` category
+pub fn txxx_description(category: &str) -> String {
+    category.trim().to_uppercase().replace(' ', "_")
+}
+
+/// Splits merged tags (sorted) with `categories`: final tag name -> (category, frame).
+/// Uncategorized tags stay in the genre.
+fn partition(tags: Vec<String>, categories: &HashMap<String, (String, CategoryFrame)>) -> CategorizedTags {
+    let mut split = CategorizedTags::default();
+    for tag in tags {
+        match categories.get(&tag) {
+            None | Some((_, CategoryFrame::Genre)) => split.genre.push(tag),
+            Some((_, CategoryFrame::Mood)) => split.mood.push(tag),
+            Some((category, CategoryFrame::Txxx)) => {
+                let description = txxx_description(category);
+                match split.txxx.iter_mut().find(|(d, _)| *d == description) {
+                    Some((_, values)) => values.push(tag),
+                    None => split.txxx.push((description, vec![tag])),
+                }
+            }
+        }
+    }
+    split.txxx.sort();
+    split
+}
+
+/// Splits a work's merged tags (`custom_tags::get_merged_tags_for_work`) by category. A tag
+/// renamed onto the same name as another follows the category of either.
+pub fn categorize_tags_for_work(conn: &Connection, work: &RJCode, tags: Vec<String>) -> Result<CategorizedTags, HvtError> {
+    let name_expr = tag_rules::load_tag_language(conn)?.name_expr("dt");
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT COALESCE(ctm.custom_tag_name, {name_expr}), c.name, c.frame
+         FROM {DB_LKP_WORK_TAG_NAME} lwt
+         JOIN {DB_DLSITE_TAG_NAME} dt ON dt.tag_id = lwt.tag_id
+         JOIN {DB_LKP_TAG_CATEGORY_NAME} ltc ON ltc.tag_id = dt.tag_id
+         JOIN {DB_TAG_CATEGORIES_NAME} c ON c.category_id = ltc.category_id
+         LEFT JOIN {DB_CUSTOM_TAG_MAPPINGS_NAME} ctm ON ctm.dlsite_tag_id = dt.tag_id
+         WHERE lwt.fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+    ))?;
+    let categories: HashMap<String, (String, CategoryFrame)> = stmt
+        .query_map(params![work], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .filter_map(|r| r.ok())
+        .filter_map(|(tag, category, frame)| CategoryFrame::from_str(&frame).map(|f| (tag, (category, f))))
+        .collect();
+    Ok(partition(tags, &categories))
+}
+
+/// Categories with their tag count: Vec<(category_id, name, frame, tag_count)>, by name
+pub fn list_categories(conn: &Connection) -> Result<Vec<(i64, String, CategoryFrame, i64)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT c.category_id, c.name, c.frame, COUNT(ltc.tag_id)
+         FROM {DB_TAG_CATEGORIES_NAME} c
+         LEFT JOIN {DB_LKP_TAG_CATEGORY_NAME} ltc ON ltc.category_id = c.category_id
+         GROUP BY c.category_id
+         ORDER BY c.name COLLATE NOCASE"
+    ))?;
+    let categories = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, i64>(3)?)))?
+        .filter_map(|r| r.ok())
+        .filter_map(|(id, name, frame, count)| CategoryFrame::from_str(&frame).map(|f| (id, name, f, count)))
+        .collect();
+    Ok(categories)
+}
+
+/// Creates a category, or changes the frame of an existing one
+pub fn upsert_category(conn: &Connection, name: &str, frame: CategoryFrame) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "INSERT INTO {DB_TAG_CATEGORIES_NAME} (name, frame) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET frame = excluded.frame, modified_at = datetime('now')"
+        ),
+        params![name, frame.as_str()],
+    )?;
+    Ok(())
+}
+
+/// Deletes a category; its tags go back to the genre
+pub fn delete_category(conn: &Connection, category_id: i64) -> Result<(), HvtError> {
+    conn.execute(
+        &format!("DELETE FROM {DB_LKP_TAG_CATEGORY_NAME} WHERE category_id = ?1"),
+        params![category_id],
+    )?;
+    conn.execute(
+        &format!("DELETE FROM {DB_TAG_CATEGORIES_NAME} WHERE category_id = ?1"),
+        params![category_id],
+    )?;
+    Ok(())
+}
+
+/// DLSite tag names of a category, alphabetically
+pub fn get_category_tags(conn: &Connection, category_id: i64) -> Result<Vec<String>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT dt.tag_name FROM {DB_LKP_TAG_CATEGORY_NAME} ltc
+         JOIN {DB_DLSITE_TAG_NAME} dt ON dt.tag_id = ltc.tag_id
+         WHERE ltc.category_id = ?1
+         ORDER BY dt.tag_name"
+    ))?;
+    let tags = stmt.query_map(params![category_id], |row| row.get(0))?.filter_map(|r| r.ok()).collect();
+    Ok(tags)
+}
+
+/// Puts a DLSite tag in a category (a tag belongs to one category at most), or back in the
+/// genre with `None`
+pub fn set_tag_category(conn: &Connection, dlsite_tag_name: &str, category_id: Option<i64>) -> Result<(), HvtError> {
+    match category_id {
+        Some(category_id) => conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {DB_LKP_TAG_CATEGORY_NAME} (tag_id, category_id)
+                 SELECT tag_id, ?2 FROM {DB_DLSITE_TAG_NAME} WHERE tag_name = ?1"
+            ),
+            params![dlsite_tag_name, category_id],
+        )?,
+        None => conn.execute(
+            &format!(
+                "DELETE FROM {DB_LKP_TAG_CATEGORY_NAME}
+                 WHERE tag_id = (SELECT tag_id FROM {DB_DLSITE_TAG_NAME} WHERE tag_name = ?1)"
+            ),
+            params![dlsite_tag_name],
+        )?,
+    };
+    Ok(())
+}
+
+/// TXXX descriptions of every category, so a tag rewrite clears the frames of categories whose
+/// frame changed
+pub fn all_txxx_descriptions(conn: &Connection) -> Result<Vec<String>, HvtError> {
+    let mut stmt = conn.prepare(&format!("SELECT name FROM {DB_TAG_CATEGORIES_NAME}"))?;
+    let names: Vec<String> = stmt.query_map([], |row| row.get(0))?.filter_map(|r| r.ok()).collect();
+    Ok(names.iter().map(|n| txxx_description(n)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition() {
+        let categories: HashMap<String, (String, CategoryFrame)> = [
+            ("耳かき", ("play", CategoryFrame::Txxx)),
+            ("癒し", ("mood", CategoryFrame::Mood)),
+            ("お姉さん", ("voice type", CategoryFrame::Txxx)),
+            ("バイノーラル", ("format", CategoryFrame::Genre)),
+        ]
+        .into_iter()
+        .map(|(tag, (category, frame))| (tag.to_string(), (category.to_string(), frame)))
+        .collect();
+        let tags = ["asmr", "お姉さん", "バイノーラル", "癒し", "耳かき"].map(String::from).to_vec();
+
+        let split = partition(tags, &categories);
+        assert_eq!(split.genre, ["asmr", "バイノーラル"]);
+        assert_eq!(split.mood, ["癒し"]);
+        assert_eq!(
+            split.txxx,
+            [("PLAY".to_string(), vec!["耳かき".to_string()]), ("VOICE_TYPE".to_string(), vec!["お姉さん".to_string()])]
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use rusqlite::{params, Connection};
+
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// `metadata_history.metadata_type` of the per-file tag snapshots taken before hvtag first tags a
+/// file. `old_value` holds the file's ID3v2 tag (without pictures, which hvtag never touches) as
+/// hex, or NULL when the file had no tag.
+pub const TAG_SNAPSHOT_TYPE: &str = "id3_snapshot";
+
+/// Stores the pre-hvtag tag of a file, unless one is already recorded: only the state from before
+/// the first tagging is kept, later re-tags don't overwrite it.
+pub fn record_tag_snapshot(
+    conn: &Connection,
+    fld_id: i64,
+    file_name: &str,
+    raw_tag: Option<&[u8]>,
+) -> Result<bool, HvtError> {
+    let rows = conn.execute(
+        &format!(
+            "INSERT INTO {DB_METADATA_HISTORY_NAME}
+             (fld_id, metadata_type, file_name, old_value, change_reason, source)
+             SELECT ?1, '{TAG_SNAPSHOT_TYPE}', ?2, ?3, 'before tagging', 'hvtag'
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM {DB_METADATA_HISTORY_NAME}
+                 WHERE fld_id = ?1 AND metadata_type = '{TAG_SNAPSHOT_TYPE}' AND file_name = ?2
+             )"
+        ),
+        params![fld_id, file_name, raw_tag.map(to_hex)],
+    )?;
+    Ok(rows > 0)
+}
+
+/// (file_name, raw tag or None if the file had no tag)
+pub type TagSnapshot = (String, Option<Vec<u8>>);
+
+/// Tag snapshots of a work's files.
+pub fn get_tag_snapshots(conn: &Connection, rjcode: &RJCode) -> Result<Vec<TagSnapshot>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT file_name, old_value FROM {DB_METADATA_HISTORY_NAME}
+         WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)
+           AND metadata_type = '{TAG_SNAPSHOT_TYPE}'"
+    ))?;
+    let rows = stmt.query_map(params![rjcode], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+
+    let mut snapshots = Vec::new();
+    for row in rows {
+        let (file_name, hex) = row?;
+        let raw_tag = match hex {
+            Some(hex) => Some(from_hex(&hex).ok_or_else(|| {
+                HvtError::Parse(format!("Corrupt tag snapshot for {}", file_name))
+            })?),
+            None => None,
+        };
+        snapshots.push((file_name, raw_tag));
+    }
+    Ok(snapshots)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let bytes = [0x49, 0x44, 0x33, 0x00, 0xff];
+        assert_eq!(to_hex(&bytes), "49443300ff");
+        assert_eq!(from_hex("49443300ff").as_deref(), Some(&bytes[..]));
+        assert_eq!(from_hex("4"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+}
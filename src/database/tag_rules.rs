@@ -0,0 +1,249 @@
+use regex::Regex;
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::warn;
+
+use crate::database::tables::*;
+use crate::errors::HvtError;
+
+/// Kind of a `tag_rules` row, stored as `rule_type`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TagRuleType {
+    /// Tags whose DLSite or custom name matches the regex are dropped
+    IgnorePattern,
+    /// Tags matching the regex survive the max-tags cut first, in rule order
+    Priority,
+    /// At most this many tags per work (the value is the number)
+    MaxTags,
+    /// Language of the DLSite genre names (the value is "en" or "jp")
+    Language,
+}
+
+impl TagRuleType {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "ignore_pattern" => Some(TagRuleType::IgnorePattern),
+            "priority" => Some(TagRuleType::Priority),
+            "max_tags" => Some(TagRuleType::MaxTags),
+            "language" => Some(TagRuleType::Language),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagRuleType::IgnorePattern => "ignore_pattern",
+            TagRuleType::Priority => "priority",
+            TagRuleType::MaxTags => "max_tags",
+            TagRuleType::Language => "language",
+        }
+    }
+}
+
+/// Which scraped name of a DLSite genre is displayed when it has no custom mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TagLanguage {
+    /// The en_US page name (`tag_name`)
+    #[default]
+    En,
+    /// The ja_JP page name (`tag_name_jp`), falling back to the en_US one
+    Jp,
+}
+
+impl TagLanguage {
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "en" => Some(TagLanguage::En),
+            "jp" => Some(TagLanguage::Jp),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TagLanguage::En => "en",
+            TagLanguage::Jp => "jp",
+        }
+    }
+
+    /// SQL expression of the displayed DLSite name, for a `dlsite_tag` aliased `alias`
+    pub fn name_expr(&self, alias: &str) -> String {
+        match self {
+            TagLanguage::En => format!("{alias}.tag_name"),
+            TagLanguage::Jp => format!("COALESCE(NULLIF({alias}.tag_name_jp, ''), {alias}.tag_name)"),
+        }
+    }
+}
+
+/// The rules applied on top of the per-tag mappings when a work's tags are merged.
+#[derive(Debug, Default)]
+pub struct TagRules {
+    pub ignore: Vec<Regex>,
+    pub priority: Vec<Regex>,
+    pub max_tags: Option<usize>,
+    pub language: TagLanguage,
+}
+
+impl TagRules {
+    /// `tags` are `(dlsite_name, final_name)` pairs. Returns the final names kept, sorted and
+    /// deduplicated: ignored ones removed, then, above `max_tags`, the tags matching the earliest
+    /// priority rule are kept first (alphabetically among equals).
+    pub fn apply(&self, tags: Vec<(String, String)>) -> Vec<String> {
+        let mut kept: Vec<String> = tags
+            .into_iter()
+            .filter(|(dlsite_name, final_name)| {
+                !self.ignore.iter().any(|re| re.is_match(dlsite_name) || re.is_match(final_name))
+            })
+            .map(|(_, final_name)| final_name)
+            .collect();
+        kept.sort();
+        kept.dedup();
+
+        if let Some(max_tags) = self.max_tags {
+            if kept.len() > max_tags {
+                let rank = |tag: &str| self.priority.iter().position(|re| re.is_match(tag)).unwrap_or(usize::MAX);
+                let mut ranked = kept;
+                ranked.sort_by(|a, b| (rank(a), a).cmp(&(rank(b), b)));
+                ranked.truncate(max_tags);
+                ranked.sort();
+                kept = ranked;
+            }
+        }
+        kept
+    }
+}
+
+/// Rules as stored: Vec<(rule_id, rule_type, value)>, ignore patterns first, priority rules in
+/// their order.
+pub fn list_tag_rules(conn: &Connection) -> Result<Vec<(i64, TagRuleType, String)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT rule_id, rule_type, value FROM {DB_TAG_RULES_NAME} ORDER BY rule_type, position, rule_id"
+    ))?;
+    let rules = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+        .filter_map(|r| r.ok())
+        .filter_map(|(id, rule_type, value)| TagRuleType::from_str(&rule_type).map(|t| (id, t, value)))
+        .collect();
+    Ok(rules)
+}
+
+/// The rules ready to apply. A pattern that no longer compiles is skipped with a warning.
+pub fn load_tag_rules(conn: &Connection) -> Result<TagRules, HvtError> {
+    let mut rules = TagRules::default();
+    for (_, rule_type, value) in list_tag_rules(conn)? {
+        match rule_type {
+            TagRuleType::IgnorePattern | TagRuleType::Priority => match Regex::new(&value) {
+                Ok(re) if rule_type == TagRuleType::IgnorePattern => rules.ignore.push(re),
+                Ok(re) => rules.priority.push(re),
+                Err(e) => warn!("Skipping invalid tag rule pattern '{}': {}", value, e),
+            },
+            TagRuleType::MaxTags => rules.max_tags = value.parse().ok().filter(|n| *n > 0),
+            TagRuleType::Language => rules.language = TagLanguage::from_str(&value).unwrap_or_default(),
+        }
+    }
+    Ok(rules)
+}
+
+/// Adds an ignore or priority pattern (priority rules go last). The regex is checked first.
+pub fn add_pattern_rule(conn: &Connection, rule_type: TagRuleType, pattern: &str) -> Result<(), HvtError> {
+    Regex::new(pattern).map_err(|e| HvtError::Parse(format!("Invalid pattern '{}': {}", pattern, e)))?;
+    conn.execute(
+        &format!(
+            "INSERT INTO {DB_TAG_RULES_NAME} (rule_type, value, position)
+             SELECT ?1, ?2, COALESCE(MAX(position), 0) + 1 FROM {DB_TAG_RULES_NAME} WHERE rule_type = ?1"
+        ),
+        params![rule_type.as_str(), pattern],
+    )?;
+    Ok(())
+}
+
+/// Sets the max-tags limit; `None` removes it.
+pub fn set_max_tags(conn: &Connection, max_tags: Option<usize>) -> Result<(), HvtError> {
+    conn.execute(
+        &format!("DELETE FROM {DB_TAG_RULES_NAME} WHERE rule_type = ?1"),
+        params![TagRuleType::MaxTags.as_str()],
+    )?;
+    if let Some(max_tags) = max_tags {
+        conn.execute(
+            &format!("INSERT INTO {DB_TAG_RULES_NAME} (rule_type, value, position) VALUES (?1, ?2, 0)"),
+            params![TagRuleType::MaxTags.as_str(), max_tags.to_string()],
+        )?;
+    }
+    Ok(())
+}
+
+/// The tag language alone (SQL queries that don't need the patterns)
+pub fn load_tag_language(conn: &Connection) -> Result<TagLanguage, HvtError> {
+    let value: Option<String> = conn
+        .query_row(
+            &format!("SELECT value FROM {DB_TAG_RULES_NAME} WHERE rule_type = ?1"),
+            params![TagRuleType::Language.as_str()],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value.and_then(|v| TagLanguage::from_str(&v)).unwrap_or_default())
+}
+
+/// Sets the tag language; English, the default, is stored as no rule.
+pub fn set_tag_language(conn: &Connection, language: TagLanguage) -> Result<(), HvtError> {
+    conn.execute(
+        &format!("DELETE FROM {DB_TAG_RULES_NAME} WHERE rule_type = ?1"),
+        params![TagRuleType::Language.as_str()],
+    )?;
+    if language != TagLanguage::En {
+        conn.execute(
+            &format!("INSERT INTO {DB_TAG_RULES_NAME} (rule_type, value, position) VALUES (?1, ?2, 0)"),
+            params![TagRuleType::Language.as_str(), language.as_str()],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn remove_tag_rule(conn: &Connection, rule_id: i64) -> Result<(), HvtError> {
+    conn.execute(&format!("DELETE FROM {DB_TAG_RULES_NAME} WHERE rule_id = ?1"), params![rule_id])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(name: &str) -> (String, String) {
+        (name.to_string(), name.to_string())
+    }
+
+    #[test]
+    fn test_apply_tag_rules() {
+        let rules = TagRules {
+            ignore: vec![Regex::new("キャンペーン").unwrap()],
+            priority: vec![Regex::new("^asmr$").unwrap(), Regex::new("バイノーラル").unwrap()],
+            max_tags: Some(2),
+            language: TagLanguage::En,
+        };
+        let tags = vec![
+            pair("癒し"),
+            pair("バイノーラル/ダミヘ"),
+            ("30%オフキャンペーン".to_string(), "sale".to_string()),
+            pair("asmr"),
+            pair("耳かき"),
+        ];
+        assert_eq!(rules.apply(tags), ["asmr", "バイノーラル/ダミヘ"]);
+
+        // Without a limit only the ignore patterns apply
+        let rules = TagRules { max_tags: None, ..rules };
+        assert_eq!(rules.apply(vec![pair("耳かき"), pair("癒し"), pair("キャンペーン中")]), ["癒し", "耳かき"]);
+    }
+
+    #[test]
+    fn test_tag_language_rule() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        set_tag_language(&conn, TagLanguage::Jp).unwrap();
+        set_max_tags(&conn, Some(5)).unwrap();
+        assert_eq!(load_tag_language(&conn).unwrap(), TagLanguage::Jp);
+        assert_eq!(load_tag_rules(&conn).unwrap().language, TagLanguage::Jp);
+
+        set_tag_language(&conn, TagLanguage::En).unwrap();
+        assert_eq!(load_tag_language(&conn).unwrap(), TagLanguage::En);
+        assert_eq!(list_tag_rules(&conn).unwrap().len(), 1);
+    }
+}
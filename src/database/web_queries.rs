@@ -1,14 +1,19 @@
+use std::path::{Path, PathBuf};
+
 use rusqlite::{params, Connection};
 
 use crate::database::custom_circles;
+use crate::database::db_path::{DbPath, DbPathRef};
 use crate::database::custom_cvs;
 use crate::database::custom_tags;
+use crate::database::queries;
 use crate::database::tables::*;
+use crate::database::tag_rules;
 use crate::errors::HvtError;
 use crate::folders::types::RJCode;
 
 /// One row in the works list (used by both the full-page load and the htmx search partial).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct WorkSummary {
     pub rjcode: String,
     pub name: String,
@@ -29,13 +34,16 @@ pub struct WorkDetail {
     pub rating: Option<String>,
     pub stars: Option<f32>,
     pub release_date: Option<String>,
+    pub description: Option<String>,
 }
 
 /// Filters for the works list: `q` is a free-text substring match (existing behavior); `tag`/
 /// `circle`/`cv` are optional *exact* matches, composable with `q` and with each other, used for
 /// click-through navigation (e.g. clicking a tag chip filters to exactly the works that have it).
 /// - `tag`: exact merged/display tag name — same semantics as `custom_tags::get_merged_tags_for_work`
-///   (custom rename applied, ignored tags excluded).
+///   (custom rename applied, ignored tags excluded). The regex `tag_rules` are not applied in
+///   SQL: a tag cut by a rule still matches. Both the EN and JP genre names match, whatever the
+///   tag language.
 /// - `circle`: exact `circles.rgcode` — the stable key (display names can collide under custom prefs).
 /// - `cv`: exact merged/display CV name — same semantics as `custom_cvs::get_merged_cvs_for_work`.
 pub struct WorkFilter<'a> {
@@ -102,7 +110,8 @@ const FILTER_WHERE: &str = "
             SELECT lwt.fld_id FROM lkp_work_tag lwt
             JOIN dlsite_tag dt ON dt.tag_id = lwt.tag_id
             LEFT JOIN custom_tag_mappings ctm ON ctm.dlsite_tag_id = dt.tag_id
-            WHERE dt.tag_name LIKE '%' || ?1 || '%' OR ctm.custom_tag_name LIKE '%' || ?1 || '%'
+            WHERE dt.tag_name LIKE '%' || ?1 || '%' OR dt.tag_name_jp LIKE '%' || ?1 || '%'
+               OR ctm.custom_tag_name LIKE '%' || ?1 || '%'
         )
     )
     AND (?2 IS NULL OR c.rgcode = ?2)
@@ -112,13 +121,14 @@ const FILTER_WHERE: &str = "
         LEFT JOIN custom_tag_mappings ctm3 ON ctm3.dlsite_tag_id = dt3.tag_id
         WHERE lwt3.fld_id = f.fld_id
           AND COALESCE(ctm3.is_ignored, 0) = 0
-          AND COALESCE(ctm3.custom_tag_name, dt3.tag_name) = ?3
+          AND ?3 IN (COALESCE(ctm3.custom_tag_name, dt3.tag_name), COALESCE(ctm3.custom_tag_name, dt3.tag_name_jp))
     ))
     AND (?4 IS NULL OR EXISTS (
         SELECT 1 FROM lkp_work_cvs lwcv4
         JOIN cvs cv4 ON cv4.cv_id = lwcv4.cv_id
-        LEFT JOIN custom_cv_mappings ccvm4 ON ccvm4.cv_id = cv4.cv_id
-        WHERE lwcv4.fld_id = f.fld_id AND COALESCE(ccvm4.custom_name, cv4.name_jp) = ?4
+        JOIN cvs canon4 ON canon4.cv_id = COALESCE(cv4.alias_of, cv4.cv_id)
+        LEFT JOIN custom_cv_mappings ccvm4 ON ccvm4.cv_id = canon4.cv_id
+        WHERE lwcv4.fld_id = f.fld_id AND COALESCE(ccvm4.custom_name, canon4.name_jp) = ?4
     ))
 ";
 
@@ -209,7 +219,10 @@ pub fn get_work_detail(conn: &Connection, rjcode: &RJCode) -> Result<Option<Work
                  WHERE f.rjcode = ?1"
             ),
             params![rjcode],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<String>>(2)?.unwrap_or_default())),
+            |row| {
+                let folder_path = row.get::<_, Option<DbPath>>(2)?.map(|p| p.0.display().to_string());
+                Ok((row.get(0)?, row.get(1)?, folder_path.unwrap_or_default()))
+            },
         )
         .map(Some)
         .or_else(|e| match e {
@@ -258,6 +271,8 @@ pub fn get_work_detail(conn: &Connection, rjcode: &RJCode) -> Result<Option<Work
         )
         .ok();
 
+    let description = queries::get_work_description(conn, rjcode)?;
+
     let tags = custom_tags::get_merged_tags_for_work(conn, rjcode)?;
     let circle_name = custom_circles::get_merged_circle_name_for_work(conn, rjcode)?;
     let cvs = custom_cvs::get_merged_cvs_for_work(conn, rjcode)?;
@@ -273,16 +288,17 @@ pub fn get_work_detail(conn: &Connection, rjcode: &RJCode) -> Result<Option<Work
         rating,
         stars,
         release_date,
+        description,
     }))
 }
 
 /// The work's folder path, used to locate `folder.jpeg` for cover serving.
-pub fn get_folder_path(conn: &Connection, rjcode: &str) -> Result<Option<String>, HvtError> {
-    let path: Option<String> = conn
+pub fn get_folder_path(conn: &Connection, rjcode: &str) -> Result<Option<PathBuf>, HvtError> {
+    let path: Option<PathBuf> = conn
         .query_row(
             &format!("SELECT path FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"),
             params![rjcode],
-            |row| row.get(0),
+            |row| row.get::<_, DbPath>(0).map(PathBuf::from),
         )
         .ok();
     Ok(path)
@@ -339,8 +355,9 @@ pub fn count_all_active_works(conn: &Connection) -> Result<i64, HvtError> {
 /// Top `limit` tags by active-work count, grouped by merged/display name (two DLSite tags
 /// custom-renamed to the same display name count together), excluding ignored tags.
 pub fn top_tags_by_count(conn: &Connection, limit: i64) -> Result<Vec<(String, i64)>, HvtError> {
+    let name_expr = tag_rules::load_tag_language(conn)?.name_expr("dt");
     let sql = format!(
-        "SELECT COALESCE(ctm.custom_tag_name, dt.tag_name) AS display_name,
+        "SELECT COALESCE(ctm.custom_tag_name, {name_expr}) AS display_name,
                 COUNT(DISTINCT lwt.fld_id) AS work_count
          FROM {DB_LKP_WORK_TAG_NAME} lwt
          JOIN {DB_DLSITE_TAG_NAME} dt ON dt.tag_id = lwt.tag_id
@@ -376,17 +393,18 @@ pub fn top_circles_by_count(conn: &Connection, limit: i64) -> Result<Vec<(String
     Ok(rows.collect::<Result<Vec<_>, _>>()?)
 }
 
-/// Top `limit` voice actors by active-work count, grouped by merged/display name (a custom
-/// rename in `custom_cv_mappings` collapses near-duplicate DLSite entries into one count).
+/// Top `limit` voice actors by active-work count, grouped by merged/display name (aliases and
+/// custom renames in `custom_cv_mappings` collapse near-duplicate DLSite entries into one count).
 /// Returns `(display_name, work_count)`.
 pub fn top_cvs_by_count(conn: &Connection, limit: i64) -> Result<Vec<(String, i64)>, HvtError> {
     let sql = format!(
-        "SELECT COALESCE(ccvm.custom_name, cv.name_jp) AS display_name,
+        "SELECT COALESCE(ccvm.custom_name, canon.name_jp) AS display_name,
                 COUNT(DISTINCT lwcv.fld_id) AS work_count
          FROM {DB_LKP_WORK_CVS_NAME} lwcv
          JOIN {DB_CVS_NAME} cv ON cv.cv_id = lwcv.cv_id
+         JOIN {DB_CVS_NAME} canon ON canon.cv_id = COALESCE(cv.alias_of, cv.cv_id)
          JOIN {DB_FOLDERS_NAME} f ON f.fld_id = lwcv.fld_id AND f.active = 1
-         LEFT JOIN {DB_CUSTOM_CV_MAPPINGS_NAME} ccvm ON ccvm.cv_id = cv.cv_id
+         LEFT JOIN {DB_CUSTOM_CV_MAPPINGS_NAME} ccvm ON ccvm.cv_id = canon.cv_id
          GROUP BY display_name
          ORDER BY work_count DESC, display_name COLLATE NOCASE ASC
          LIMIT ?1"
@@ -396,6 +414,82 @@ pub fn top_cvs_by_count(conn: &Connection, limit: i64) -> Result<Vec<(String, i6
     Ok(rows.collect::<Result<Vec<_>, _>>()?)
 }
 
+/// Number of active works with at least one file tagged by hvtag (recorded in file_processing).
+pub fn count_tagged_works(conn: &Connection) -> Result<i64, HvtError> {
+    Ok(conn.query_row(
+        &format!(
+            "SELECT COUNT(DISTINCT f.fld_id)
+             FROM {DB_FOLDERS_NAME} f
+             JOIN {DB_FILE_PROCESSING_NAME} fp ON fp.fld_id = f.fld_id AND fp.is_tagged = 1
+             WHERE f.active = 1"
+        ),
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+/// Audio files of active works recorded at tagging time, grouped by extension (lowercased).
+/// Returns `(extension, file_count, total_bytes)`, largest total first.
+pub fn audio_format_distribution(conn: &Connection) -> Result<Vec<(String, i64, i64)>, HvtError> {
+    let sql = format!(
+        "SELECT LOWER(COALESCE(NULLIF(fp.file_extension, ''), '?')) AS ext,
+                COUNT(*),
+                COALESCE(SUM(fp.file_size_bytes), 0) AS total_bytes
+         FROM {DB_FILE_PROCESSING_NAME} fp
+         JOIN {DB_FOLDERS_NAME} f ON f.fld_id = fp.fld_id AND f.active = 1
+         GROUP BY ext
+         ORDER BY total_bytes DESC"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Unresolved DLSite fetch errors of active works, grouped by error type. Returns
+/// `(error_type, count)`, most frequent first.
+pub fn unresolved_error_counts(conn: &Connection) -> Result<Vec<(String, i64)>, HvtError> {
+    let sql = format!(
+        "SELECT COALESCE(e.error_type, 'unknown') AS error_type, COUNT(*) AS error_count
+         FROM {DB_DLSITE_ERRORS_NAME} e
+         JOIN {DB_FOLDERS_NAME} f ON f.fld_id = e.fld_id AND f.active = 1
+         WHERE COALESCE(e.is_resolved, 0) = 0
+         GROUP BY error_type
+         ORDER BY error_count DESC"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// An unresolved DLSite fetch error of an active work, for the API's error list.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct UnresolvedError {
+    pub rjcode: String,
+    pub error_type: Option<String>,
+    pub error_category: Option<String>,
+    pub error_timestamp: Option<String>,
+}
+
+pub fn list_unresolved_errors(conn: &Connection) -> Result<Vec<UnresolvedError>, HvtError> {
+    let sql = format!(
+        "SELECT f.rjcode, e.error_type, e.error_category, e.error_timestamp
+         FROM {DB_DLSITE_ERRORS_NAME} e
+         JOIN {DB_FOLDERS_NAME} f ON f.fld_id = e.fld_id AND f.active = 1
+         WHERE COALESCE(e.is_resolved, 0) = 0
+         ORDER BY e.error_timestamp DESC, f.rjcode"
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map([], |row| {
+        Ok(UnresolvedError {
+            rjcode: row.get(0)?,
+            error_type: row.get(1)?,
+            error_category: row.get(2)?,
+            error_timestamp: row.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
 /// Resolves a CV's numeric id to its name_jp — mutation routes take an id (not a name) to
 /// avoid URL-encoding arbitrary Japanese text, then bridge back to the `&str`-based
 /// `custom_cvs` functions via this lookup. Same rationale as `get_tag_name_by_id`.
@@ -415,10 +509,10 @@ pub fn get_cv_name_by_id(conn: &Connection, cv_id: i64) -> Result<Option<String>
 /// files-untouched inconsistency if the move fails. Deliberately touches only `folders`; every
 /// child row (tags/circle/cv/rating/stars/release_date) is left intact so the work stays fully
 /// restorable by moving the folder back and flipping `active` to 1 by hand.
-pub fn deactivate_and_relocate_work(conn: &Connection, rjcode: &RJCode, new_path: &str) -> Result<(), HvtError> {
+pub fn deactivate_and_relocate_work(conn: &Connection, rjcode: &RJCode, new_path: &Path) -> Result<(), HvtError> {
     conn.execute(
         &format!("UPDATE {DB_FOLDERS_NAME} SET active = 0, path = ?1 WHERE rjcode = ?2"),
-        params![new_path, rjcode.as_str()],
+        params![DbPathRef(new_path), rjcode.as_str()],
     )?;
     Ok(())
 }
@@ -0,0 +1,177 @@
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// The group of a work: its album name and its place among the group's discs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkGroupMembership {
+    pub group_id: i64,
+    pub name: String,
+    pub disc_number: u32,
+    pub total_discs: u32,
+}
+
+pub fn get_work_group(conn: &Connection, work: &RJCode) -> Result<Option<WorkGroupMembership>, HvtError> {
+    let membership = conn.query_row(
+        &format!(
+            "SELECT g.group_id, g.name, lwg.disc_number,
+                    (SELECT COUNT(*) FROM {DB_LKP_WORK_GROUP_NAME} WHERE group_id = g.group_id)
+             FROM {DB_LKP_WORK_GROUP_NAME} lwg
+             JOIN {DB_WORK_GROUPS_NAME} g ON g.group_id = lwg.group_id
+             WHERE lwg.fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![work],
+        |row| Ok(WorkGroupMembership {
+            group_id: row.get(0)?,
+            name: row.get(1)?,
+            disc_number: row.get(2)?,
+            total_discs: row.get(3)?,
+        }),
+    ).optional()?;
+    Ok(membership)
+}
+
+/// Groups with their work count: Vec<(group_id, name, works)>, by name
+pub fn list_groups(conn: &Connection) -> Result<Vec<(i64, String, i64)>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT g.group_id, g.name, COUNT(lwg.fld_id)
+         FROM {DB_WORK_GROUPS_NAME} g
+         LEFT JOIN {DB_LKP_WORK_GROUP_NAME} lwg ON lwg.group_id = g.group_id
+         GROUP BY g.group_id
+         ORDER BY g.name COLLATE NOCASE"
+    ))?;
+    let groups = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(groups)
+}
+
+/// RJ codes of a group, by disc number
+pub fn get_group_works(conn: &Connection, group_id: i64) -> Result<Vec<String>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT f.rjcode FROM {DB_LKP_WORK_GROUP_NAME} lwg
+         JOIN {DB_FOLDERS_NAME} f ON f.fld_id = lwg.fld_id
+         WHERE lwg.group_id = ?1
+         ORDER BY lwg.disc_number, f.rjcode"
+    ))?;
+    let works = stmt.query_map(params![group_id], |row| row.get(0))?.filter_map(|r| r.ok()).collect();
+    Ok(works)
+}
+
+/// Creates an empty group named `name` (the album name of its works)
+pub fn create_group(conn: &Connection, name: &str) -> Result<i64, HvtError> {
+    conn.execute(
+        &format!("INSERT INTO {DB_WORK_GROUPS_NAME} (name) VALUES (?1)"),
+        params![name],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn rename_group(conn: &Connection, group_id: i64, name: &str) -> Result<(), HvtError> {
+    conn.execute(
+        &format!("UPDATE {DB_WORK_GROUPS_NAME} SET name = ?2 WHERE group_id = ?1"),
+        params![group_id, name],
+    )?;
+    Ok(())
+}
+
+/// Rewrites the disc numbers of a group as 1..n in the order of `works`
+fn renumber(conn: &Connection, works: &[String]) -> Result<(), HvtError> {
+    for (i, rjcode) in works.iter().enumerate() {
+        conn.execute(
+            &format!(
+                "UPDATE {DB_LKP_WORK_GROUP_NAME} SET disc_number = ?2
+                 WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+            ),
+            params![rjcode, i as u32 + 1],
+        )?;
+    }
+    Ok(())
+}
+
+/// Adds a work to a group as its last disc, leaving its previous group if any
+pub fn add_work_to_group(conn: &Connection, work: &RJCode, group_id: i64) -> Result<(), HvtError> {
+    remove_work_from_group(conn, work)?;
+    conn.execute(
+        &format!(
+            "INSERT INTO {DB_LKP_WORK_GROUP_NAME} (fld_id, group_id, disc_number)
+             SELECT fld_id, ?2,
+                    (SELECT COALESCE(MAX(disc_number), 0) + 1 FROM {DB_LKP_WORK_GROUP_NAME} WHERE group_id = ?2)
+             FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+        ),
+        params![work, group_id],
+    )?;
+    Ok(())
+}
+
+/// Moves a work to another disc number of its group, shifting the discs in between
+pub fn set_disc_number(conn: &Connection, work: &RJCode, disc_number: u32) -> Result<(), HvtError> {
+    let Some(membership) = get_work_group(conn, work)? else {
+        return Ok(());
+    };
+    let mut works = get_group_works(conn, membership.group_id)?;
+    works.retain(|w| w != work.as_str());
+    let index = (disc_number.max(1) as usize - 1).min(works.len());
+    works.insert(index, work.to_string());
+    renumber(conn, &works)
+}
+
+/// Takes a work out of its group; the discs after it move up, and an emptied group is deleted
+pub fn remove_work_from_group(conn: &Connection, work: &RJCode) -> Result<(), HvtError> {
+    let Some(membership) = get_work_group(conn, work)? else {
+        return Ok(());
+    };
+    conn.execute(
+        &format!(
+            "DELETE FROM {DB_LKP_WORK_GROUP_NAME}
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+        ),
+        params![work],
+    )?;
+    let works = get_group_works(conn, membership.group_id)?;
+    if works.is_empty() {
+        conn.execute(
+            &format!("DELETE FROM {DB_WORK_GROUPS_NAME} WHERE group_id = ?1"),
+            params![membership.group_id],
+        )?;
+    }
+    renumber(conn, &works)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_work_group_disc_numbers() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        let works: Vec<RJCode> = ["RJ01000001", "RJ01000002", "RJ01000003"].iter()
+            .map(|w| RJCode::new(w.to_string()).unwrap())
+            .collect();
+        for work in &works {
+            crate::database::queries::insert_folder_record(&conn, work, std::path::Path::new(&format!("/library/{}", work)), true).unwrap();
+        }
+
+        let group_id = create_group(&conn, "Album").unwrap();
+        for work in &works {
+            add_work_to_group(&conn, work, group_id).unwrap();
+        }
+        let disc = |work: &RJCode| get_work_group(&conn, work).unwrap().map(|m| (m.disc_number, m.total_discs));
+        assert_eq!(disc(&works[2]), Some((3, 3)));
+
+        set_disc_number(&conn, &works[2], 1).unwrap();
+        assert_eq!(get_group_works(&conn, group_id).unwrap(), ["RJ01000003", "RJ01000001", "RJ01000002"]);
+
+        remove_work_from_group(&conn, &works[0]).unwrap();
+        assert_eq!(disc(&works[0]), None);
+        assert_eq!(disc(&works[1]), Some((2, 2)));
+
+        remove_work_from_group(&conn, &works[1]).unwrap();
+        remove_work_from_group(&conn, &works[2]).unwrap();
+        assert!(list_groups(&conn).unwrap().is_empty());
+    }
+}
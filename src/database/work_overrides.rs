@@ -0,0 +1,139 @@
+use rusqlite::{params, Connection};
+
+use crate::database::tables::*;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// A work value that can be overridden, stored as `work_overrides.field`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverrideField {
+    Title,
+    Circle,
+    Cvs,
+    Tags,
+    ReleaseDate,
+}
+
+impl OverrideField {
+    pub const ALL: [OverrideField; 5] = [
+        OverrideField::Title,
+        OverrideField::Circle,
+        OverrideField::Cvs,
+        OverrideField::Tags,
+        OverrideField::ReleaseDate,
+    ];
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "title" => Some(OverrideField::Title),
+            "circle" => Some(OverrideField::Circle),
+            "cvs" => Some(OverrideField::Cvs),
+            "tags" => Some(OverrideField::Tags),
+            "release_date" => Some(OverrideField::ReleaseDate),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OverrideField::Title => "title",
+            OverrideField::Circle => "circle",
+            OverrideField::Cvs => "cvs",
+            OverrideField::Tags => "tags",
+            OverrideField::ReleaseDate => "release_date",
+        }
+    }
+
+    /// CVs and tags hold several values, one per line in the database
+    pub fn is_list(&self) -> bool {
+        matches!(self, OverrideField::Cvs | OverrideField::Tags)
+    }
+}
+
+/// The overrides of one work: `None` keeps the merged value (DLSite data with the global
+/// mappings applied).
+#[derive(Debug, Default, PartialEq)]
+pub struct WorkOverrides {
+    pub title: Option<String>,
+    pub circle: Option<String>,
+    pub cvs: Option<Vec<String>>,
+    pub tags: Option<Vec<String>>,
+    pub release_date: Option<String>,
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value.lines().map(str::trim).filter(|v| !v.is_empty()).map(String::from).collect()
+}
+
+pub fn get_work_overrides(conn: &Connection, work: &RJCode) -> Result<WorkOverrides, HvtError> {
+    let mut stmt = conn.prepare_cached(&format!(
+        "SELECT field, value FROM {DB_WORK_OVERRIDES_NAME}
+         WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)"
+    ))?;
+    let rows: Vec<(String, String)> = stmt
+        .query_map(params![work], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    let mut overrides = WorkOverrides::default();
+    for (field, value) in rows {
+        match OverrideField::from_str(&field) {
+            Some(OverrideField::Title) => overrides.title = Some(value),
+            Some(OverrideField::Circle) => overrides.circle = Some(value),
+            Some(OverrideField::Cvs) => overrides.cvs = Some(split_list(&value)),
+            Some(OverrideField::Tags) => overrides.tags = Some(split_list(&value)),
+            Some(OverrideField::ReleaseDate) => overrides.release_date = Some(value),
+            None => {}
+        }
+    }
+    Ok(overrides)
+}
+
+/// Sets an override; lists (`OverrideField::is_list`) are given one value per element.
+pub fn set_work_override(conn: &Connection, work: &RJCode, field: OverrideField, values: &[String]) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "INSERT OR REPLACE INTO {DB_WORK_OVERRIDES_NAME} (fld_id, field, value, modified_at)
+             SELECT fld_id, ?2, ?3, datetime('now') FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"
+        ),
+        params![work, field.as_str(), values.join("\n")],
+    )?;
+    Ok(())
+}
+
+/// Back to the merged value
+pub fn clear_work_override(conn: &Connection, work: &RJCode, field: OverrideField) -> Result<(), HvtError> {
+    conn.execute(
+        &format!(
+            "DELETE FROM {DB_WORK_OVERRIDES_NAME}
+             WHERE fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1) AND field = ?2"
+        ),
+        params![work, field.as_str()],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_work_overrides_roundtrip() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        let work = RJCode::new("RJ01234567".to_string()).unwrap();
+        crate::database::queries::insert_folder_record(&conn, &work, std::path::Path::new("/library/RJ01234567"), true).unwrap();
+
+        let tags = ["耳かき".to_string(), "癒し".to_string()];
+        set_work_override(&conn, &work, OverrideField::Tags, &tags).unwrap();
+        set_work_override(&conn, &work, OverrideField::Title, &["Custom title".to_string()]).unwrap();
+        let overrides = get_work_overrides(&conn, &work).unwrap();
+        assert_eq!(overrides.tags.as_deref(), Some(&tags[..]));
+        assert_eq!(overrides.title.as_deref(), Some("Custom title"));
+        assert_eq!(overrides.cvs, None);
+
+        clear_work_override(&conn, &work, OverrideField::Tags).unwrap();
+        clear_work_override(&conn, &work, OverrideField::Title).unwrap();
+        assert_eq!(get_work_overrides(&conn, &work).unwrap(), WorkOverrides::default());
+    }
+}
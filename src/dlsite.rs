@@ -1,12 +1,19 @@
 use rusqlite::Connection;
 use tracing::{debug, warn};
 
-use crate::{database::{queries, tables::*}, dlsite::scrapper::DlSiteProductScrapResult, errors::HvtError, folders::types::RJCode, tagger::types::WorkDetails};
+use crate::{database::{custom_circles, queries, ranking_history, sales_history, review_queue::ReviewReason, tables::*}, errors::HvtError, folders::types::RJCode, tagger};
 
 pub mod api;
+pub mod challenge;
+pub mod client;
+pub mod http_cache;
 pub mod scrapper;
+pub mod sections;
+pub mod sources;
 pub mod types;
 
+pub use client::MetadataClient;
+
 #[derive(Default, Clone)]
 pub struct DataSelection {
     pub tags: bool,
@@ -15,40 +22,82 @@ pub struct DataSelection {
     pub rating: bool,
     pub cvs: bool,
     pub stars: bool,
-    pub cover_link: bool
-}
-
-pub async fn assign_data_to_work(
-    conn: &Connection,
-    work: RJCode,
-    data_selection: DataSelection,
-) -> Result<(), HvtError> {
-    assign_data_to_work_with_client(conn, work, data_selection, None).await
+    pub cover_link: bool,
+    pub description: bool,
+    pub relations: bool,
+    pub sample_images: bool,
 }
 
 pub async fn assign_data_to_work_with_client(
     conn: &Connection,
     work: RJCode,
     data_selection: DataSelection,
-    client: Option<&reqwest::Client>,
+    client: &MetadataClient,
 ) -> Result<(), HvtError> {
-    let wd = WorkDetails::build_from_rjcode_with_client(work.as_str().to_string(), client).await
-        .map_err(|x: Box<dyn std::error::Error>| HvtError::Http(x.to_string()))?;
-    let sr = DlSiteProductScrapResult::build_from_rjcode_with_client(work.as_str().to_string(), client).await;
+    // --offline: without a cached copy the work keeps what the database already has
+    if client.cache.is_offline()
+        && sources::uses_dlsite(client.metadata())
+        && !(http_cache::contains(&http_cache::api_key(work.as_str()))
+            && http_cache::contains(&http_cache::page_key(work.as_str(), "en_US")))
+    {
+        if queries::has_collected_metadata(conn, &work)? {
+            debug!("{} not in the HTTP cache, keeping its metadata from the database", work);
+            return Ok(());
+        }
+        client.cache.record_missing_offline(work.as_str());
+        return Err(HvtError::Offline(format!("no cached DLSite data for {}", work)));
+    }
 
-    if sr.genre.is_empty() {
-        return Err(HvtError::RemovedWork(work));
+    // The section found by an earlier fetch is tried first, and known on an HTTP cache hit
+    if let Some(section) = queries::get_dlsite_section(conn, &work)? {
+        client.sections.remember(&work, &section);
     }
 
-    // Insert work name (always do this regardless of data_selection)
-    queries::insert_work_name(conn, &work, &wd.name)?;
+    // Every source of [metadata], merged: fields missing from one are taken from the next
+    let md = sources::fetch_metadata(&work, client).await?;
+
+    // Names of a circle seen for the first time, scraped before any write so the database is
+    // never left half-updated while waiting on DLSite
+    let new_circle_names = match &md.maker_code {
+        Some(maker_code) if data_selection.circle && !queries::circle_exists(conn, maker_code)? => {
+            debug!("Circle {} not in database, scraping names...", maker_code);
+            // Scrape circle names from circle profile page title
+            let scraped = if sources::uses_dlsite(client.metadata()) {
+                scrapper::scrape_circle_profile(maker_code.as_str(), &client.sections.section_of(&work), client).await
+            } else {
+                Err(HvtError::Generic("DLSite is not a metadata source".to_string()))
+            };
+            Some(match scraped {
+                Ok((en, jp)) => (en, jp),
+                Err(e) => {
+                    warn!("Failed to scrape circle profile for {}: {}. Using fallback.", maker_code, e);
+                    (md.circle_name_en.clone().unwrap_or_default(), md.circle_name_jp.clone().unwrap_or_default())
+                }
+            })
+        }
+        _ => None,
+    };
+
+    // All writes of the work in one transaction: faster on slow disks, and a failure leaves
+    // the previous data in place instead of a partial update
+    let tx = conn.unchecked_transaction()?;
+    let conn: &Connection = &tx;
+
+    // Insert work name, JP + EN (always do this regardless of data_selection)
+    queries::insert_work_name(conn, &work, md.name.as_deref().unwrap_or_default(), md.name_en.as_deref())?;
+
+    // WORK TYPE (always too: [tagger] skip_work_types relies on it)
+    if md.work_type.is_some() || !md.options.is_empty() || !md.custom_genres.is_empty() {
+        queries::remove_previous_data_of_work(conn, DB_WORK_TYPES_NAME, &work)?;
+        queries::assign_work_type_to_work(conn, &work, md.work_type.as_deref(), &md.options, &md.custom_genres)?;
+    }
 
     // TAGS
     if data_selection.tags {
-        debug!("assign tags: {:?}", &sr.genre);
+        debug!("assign tags: {:?}", &md.tags);
 
         // Convert all tags to lowercase
-        let tags_lowercase: Vec<String> = sr.genre.iter()
+        let tags_lowercase: Vec<String> = md.tags.iter()
             .map(|tag| tag.to_lowercase())
             .collect();
 
@@ -59,6 +108,11 @@ pub async fn assign_data_to_work_with_client(
             max_tag_id += queries::insert_tag(conn, tag, max_tag_id + 1)?;
         }
 
+        // Japanese genre names (ja_JP page), shown when the tag language rule is Japanese
+        for (tag, tag_jp) in tags_lowercase.iter().zip(&md.tags_jp) {
+            queries::set_tag_name_jp(conn, tag, &tag_jp.to_lowercase())?;
+        }
+
         // remove existing tags if exists and assign new tags
         queries::remove_previous_data_of_work(conn, DB_LKP_WORK_TAG_NAME, &work)?;
         queries::assign_tags_to_work(conn, &work, &tags_lowercase)?;
@@ -66,67 +120,63 @@ pub async fn assign_data_to_work_with_client(
 
     // RELEASE DATE
     if data_selection.release_date {
-        debug!("assign date: {:?}", &wd.release_date);
+        debug!("assign date: {:?}", &md.release_date);
         queries::remove_previous_data_of_work(conn, DB_RELEASE_DATE_NAME, &work)?;
-        queries::assign_release_date_to_work(conn, &work, &wd.release_date)?;
+        queries::assign_release_date_to_work(conn, &work, md.release_date.as_deref().unwrap_or_default())?;
     }
 
     // CIRCLE
-    if data_selection.circle {
-        debug!("assign circle: {:?}", &wd.maker_code);
-
-        // Check if circle already exists in database
-        let circle_exists = queries::circle_exists(conn, &wd.maker_code)?;
+    if let Some(maker_code) = md.maker_code.as_ref().filter(|_| data_selection.circle) {
+        debug!("assign circle: {:?}", maker_code);
 
-        if !circle_exists {
-            debug!("Circle {} not in database, scraping names...", &wd.maker_code);
+        if let Some((circle_name_en, circle_name_jp)) = &new_circle_names {
             let max_cir_id = queries::get_max_id(conn, "cir_id", DB_CIRCLE_NAME)?;
 
-            // Scrape circle names from circle profile page title
-            let (circle_name_en, circle_name_jp) = match scrapper::scrape_circle_profile(
-                wd.maker_code.as_str(),
-                work.site_section(),
-                client,
-            ).await {
-                Ok((en, jp)) => (en, jp),
-                Err(e) => {
-                    warn!("Failed to scrape circle profile for {}: {}. Using fallback.", wd.maker_code, e);
-                    (String::new(), String::new())
-                }
-            };
-
             // Insert circle with BOTH names (EN, JP)
-            queries::insert_circle(conn, &wd.maker_code, &circle_name_en, &circle_name_jp, max_cir_id + 1)?;
+            queries::insert_circle(conn, maker_code, circle_name_en, circle_name_jp, max_cir_id + 1)?;
         } else {
-            debug!("Circle {} already in database, skipping scrape", &wd.maker_code);
+            debug!("Circle {} already in database, skipping scrape", maker_code);
         }
 
         // Remove previous assignment before creating new one
         queries::remove_previous_data_of_work(conn, DB_LKP_WORK_CIRCLE_NAME, &work)?;
 
         // Assign circle to work
-        queries::assign_circle_to_work(conn, &work, &wd.maker_code)?;
+        queries::assign_circle_to_work(conn, &work, maker_code)?;
+
+        // Nameless circle (profile scrape failed) without a name set in --manage-circles
+        let (_, _, name_en, name_jp) = custom_circles::get_circle_info(conn, maker_code.as_str())?;
+        let nameless = name_en.is_empty() && name_jp.is_empty()
+            && custom_circles::get_circle_preference(conn, maker_code.as_str())?.is_none();
+        let pending = nameless.then(|| format!("no name found for circle {}", maker_code));
+        tagger::update_review_queue(conn, &work, ReviewReason::MissingCircleName, pending.as_deref());
     }
 
     // RATING
-    if data_selection.rating {
-        debug!("assign rating: {}", &wd.age_category);
+    if let Some(age_category) = md.age_category.as_ref().filter(|_| data_selection.rating) {
+        debug!("assign rating: {}", age_category);
         queries::remove_previous_data_of_work(conn, DB_RATING_NAME, &work)?;
-        queries::assign_rating_to_work(conn, &work, &wd.age_category.to_string())?;
+        queries::assign_rating_to_work(conn, &work, &age_category.to_string())?;
     }
 
     // CVS
     if data_selection.cvs {
-        debug!("assign cvs: {:?}", &sr.cvs);
+        debug!("assign cvs: {:?} (en: {:?})", &md.cvs, &md.cvs_en);
 
         // Normalize before both insert and assign so the two agree on the exact string used
         // for the name_jp lookup/join (see queries::normalize_cv_name).
-        let normalized_cvs: Vec<String> = sr.cvs.iter()
+        let normalized_cvs: Vec<String> = md.cvs.iter()
             .map(|cv| queries::normalize_cv_name(cv))
             .collect();
 
-        for cv in &normalized_cvs {
-            queries::insert_cv(conn, cv, "")?;
+        // English names are only known when the en_US page lists the same CVs; a name that is
+        // identical in both locales isn't a translation and is not stored as name_en.
+        for (i, cv) in normalized_cvs.iter().enumerate() {
+            let cv_en = md.cvs_en.get(i)
+                .map(|en| queries::normalize_cv_name(en))
+                .filter(|en| en != cv)
+                .unwrap_or_default();
+            queries::insert_cv(conn, cv, &cv_en)?;
         }
 
         queries::remove_previous_data_of_work(conn, DB_LKP_WORK_CVS_NAME, &work)?;
@@ -136,15 +186,49 @@ pub async fn assign_data_to_work_with_client(
     // COVER LINK
     if data_selection.cover_link {
         queries::remove_previous_data_of_work(conn, DB_DLSITE_COVERS_LINK_NAME, &work)?;
-        queries::assign_cover_link_to_work(conn, &work, &wd.image_link)?;
+        queries::assign_cover_link_to_work(conn, &work, md.image_link.as_deref().unwrap_or_default())?;
     }
 
     // STARS
-    if data_selection.stars {
+    if let Some(rate) = md.rate.filter(|_| data_selection.stars) {
         queries::remove_previous_data_of_work(conn, DB_STARS_NAME, &work)?;
-        queries::assign_stars_to_work(conn, &work, wd.rate)?;
+        queries::assign_stars_to_work(conn, &work, rate)?;
+    }
+
+    // RATING AND REVIEW COUNTS (with the stars they explain)
+    if let Some(reviews) = md.reviews.as_ref().filter(|_| data_selection.stars) {
+        queries::remove_previous_data_of_work(conn, DB_REVIEWS_NAME, &work)?;
+        queries::assign_reviews_to_work(conn, &work, reviews)?;
+    }
+
+    // DESCRIPTION (kept as-is when the page has none, e.g. scraping hiccup)
+    if data_selection.description {
+        if let Some(description) = &md.description {
+            queries::remove_previous_data_of_work(conn, DB_DESCRIPTION_NAME, &work)?;
+            queries::assign_description_to_work(conn, &work, description)?;
+        }
+    }
+
+    // RELATIONS (translation_info: original/parent/child works)
+    if data_selection.relations {
+        debug!("assign relations: {:?}", &md.related_works);
+        queries::remove_previous_data_of_work(conn, DB_WORK_RELATIONS_NAME, &work)?;
+        queries::assign_relations_to_work(conn, &work, &md.related_works)?;
+    }
+
+    // SAMPLE IMAGES (URLs only, the files are downloaded with --images full)
+    if data_selection.sample_images {
+        debug!("assign sample images: {} image(s)", md.sample_images.len());
+        queries::assign_sample_images_to_work(conn, &work, &md.sample_images)?;
+    }
+
+    // SALES (price and popularity, one sample per fetch)
+    if md.price.is_some() || md.dl_count.is_some() || md.wishlist_count.is_some() {
+        sales_history::record_sample(conn, &work, md.price, md.dl_count, md.wishlist_count)?;
     }
+    ranking_history::record_rankings(conn, &work, &md.rankings)?;
 
-    queries::set_work_scan_date(conn, &work)?;
+    queries::set_work_scan_date(conn, &work, client.sections.found(&work).as_deref())?;
+    tx.commit()?;
     Ok(())
 }
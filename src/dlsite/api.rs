@@ -1,42 +1,71 @@
-use std::error::Error;
 use tracing::debug;
 
-use crate::{folders::types::{RGCode, RJCode}, tagger::types::{AgeCategory, WorkDetails}};
+use crate::{dlsite::{challenge, http_cache, MetadataClient}, errors::HvtError, folders::types::{RGCode, RJCode}, tagger::types::{translation_relations, AgeCategory, ReviewCounts, WorkDetails}};
 
 impl WorkDetails {
-    pub async fn build_from_rjcode(rjcode: String) -> Result<Self, Box<dyn Error>> {
-        Self::build_from_rjcode_with_client(rjcode, None).await
-    }
-
+    /// Queries the DLSite product API. Fails with `HvtError::RemovedWork` only when every
+    /// section answered that it doesn't know the work; a timeout, a 403/429/5xx or an
+    /// unreadable answer is an `HvtError::Http`, so a transient failure isn't taken for a
+    /// removed work, and a robot check still served after the configured retries is an
+    /// `HvtError::Challenge`.
     pub async fn build_from_rjcode_with_client(
         rjcode: String,
-        client: Option<&reqwest::Client>,
-    ) -> Result<Self, Box<dyn Error>> {
+        client: &MetadataClient,
+    ) -> Result<Self, HvtError> {
         let code = RJCode::from_string_unchecked(rjcode.clone());
-        let section = code.site_section();
-        let url = format!("https://www.dlsite.com/{section}/product/info/ajax?product_id={rjcode}");
-        debug!("Querying DLSite API: {url}");
 
-        let resp = if let Some(client) = client {
-            client.get(&url).send().await?.text().await?
-        } else {
-            reqwest::get(&url).await?.text().await?
+        let cache_key = http_cache::api_key(&rjcode);
+        let cached = client.cache.get(&cache_key);
+        let map = match &cached {
+            Some(body) => parse_api_response(body)?,
+            None if client.cache.is_offline() => {
+                return Err(HvtError::Offline(format!("no cached DLSite API response for {rjcode}")));
+            }
+            None => {
+                // A work outside its section gets an empty answer (or a 404): try the next one
+                let mut found = None;
+                for section in client.sections.candidates(&code) {
+                    let url = format!("https://www.dlsite.com/{section}/product/info/ajax?product_id={rjcode}");
+                    debug!("Querying DLSite API: {url}");
+                    let (status, body) = challenge::send_with_backoff(client, &format!("the DLSite API of {rjcode}"), || {
+                        let request = client.http.get(&url);
+                        match client.challenge.browser_cookies() {
+                            Some(cookies) => request.header("Cookie", cookies),
+                            None => request,
+                        }
+                    }).await?;
+                    if status == reqwest::StatusCode::NOT_FOUND {
+                        debug!("{rjcode} not found under /{section}/");
+                        continue;
+                    }
+                    if !status.is_success() {
+                        return Err(HvtError::Http(format!("DLSite API answered {status} for {rjcode}")));
+                    }
+                    // An unknown work gets an empty answer ([] or {})
+                    let map = match serde_json::from_str::<serde_json::Value>(&body) {
+                        Ok(serde_json::Value::Array(a)) if a.is_empty() => None,
+                        _ => Some(parse_api_response(&body)?),
+                    };
+                    match map {
+                        Some(map) if !map.is_empty() => {
+                            client.sections.remember(&code, &section);
+                            client.cache.put(&cache_key, &body);
+                            found = Some(map);
+                            break;
+                        }
+                        _ => debug!("{rjcode} not found under /{section}/"),
+                    }
+                }
+                found.ok_or_else(|| HvtError::RemovedWork(code.clone()))?
+            }
         };
 
-        // Parse as generic Value to avoid type mismatches with variable DLSite API fields.
-        // DLSite also migrated old 6-digit codes (e.g. RJ584634) to 8-digit format (e.g. RJ01584634)
-        // by adding "01" prefix — the API may return the old key when queried with the new one.
-        let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str::<serde_json::Value>(&resp)?
-            .as_object()
-            .cloned()
-            .ok_or("DLSite API response is not a JSON object")?;
-
         let work = if let Some(v) = map.get(&rjcode) {
             v.clone()
         } else if map.len() == 1 {
             map.into_values().next().unwrap()
         } else {
-            return Err(format!("DLSite API returned unexpected response for {rjcode}").into());
+            return Err(HvtError::Parse(format!("DLSite API returned unexpected response for {rjcode}")));
         };
 
         let maker_id = work["maker_id"].as_str().unwrap_or("").to_string();
@@ -46,6 +75,46 @@ impl WorkDetails {
         let work_image = work["work_image"].as_str().unwrap_or("").to_string();
         let release_date = work["regist_date"].as_str().unwrap_or("").to_string();
 
+        let translation_info = &work["translation_info"];
+        let child_worknos: Vec<String> = translation_info["child_worknos"]
+            .as_array()
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default();
+        let related_works = translation_relations(
+            &rjcode,
+            translation_info["original_workno"].as_str(),
+            translation_info["parent_workno"].as_str(),
+            &child_worknos,
+        );
+
+        // Counts are numbers or numeric strings depending on the work
+        let count = |value: &serde_json::Value| value.as_u64().or_else(|| value.as_str().and_then(|s| s.parse().ok()));
+        let price = work["price"].as_u64().map(|p| p as u32);
+        let dl_count = count(&work["dl_count"]);
+        let wishlist_count = count(&work["wishlist_count"]);
+        let rankings = work["rank"].as_array().into_iter().flatten()
+            .filter_map(|entry| Some((
+                entry["term"].as_str()?.to_string(),
+                entry["category"].as_str()?.to_string(),
+                entry["rank"].as_u64()? as u32,
+                entry["rank_date"].as_str()?.to_string(),
+            )))
+            .collect();
+
+        let work_type = work["work_type"].as_str().filter(|t| !t.is_empty()).map(String::from);
+        // "JPN#SND#REV", or already a list depending on the endpoint
+        let options: Vec<String> = match &work["options"] {
+            serde_json::Value::String(s) => s.split('#').filter(|o| !o.is_empty()).map(String::from).collect(),
+            serde_json::Value::Array(a) => a.iter().filter_map(|v| v.as_str().map(String::from)).collect(),
+            _ => Vec::new(),
+        };
+        // Plain names or objects with a name
+        let custom_genres: Vec<String> = work["custom_genres"].as_array().into_iter().flatten()
+            .filter_map(|g| g.as_str().or_else(|| g["name_en"].as_str()).or_else(|| g["name"].as_str()))
+            .filter(|g| !g.is_empty())
+            .map(String::from)
+            .collect();
+
         let image_link = if work_image.starts_with("//") {
             format!("https:{work_image}")
         } else {
@@ -60,6 +129,26 @@ impl WorkDetails {
             name,
             image_link,
             release_date,
+            related_works,
+            price,
+            dl_count,
+            wishlist_count,
+            rankings,
+            reviews: ReviewCounts::from_json(&work),
+            work_type,
+            options,
+            custom_genres,
         })
     }
 }
+
+/// Parses an API response as a generic Value to avoid type mismatches with variable DLSite API
+/// fields. DLSite also migrated old 6-digit codes (e.g. RJ584634) to 8-digit format (e.g.
+/// RJ01584634) by adding "01" prefix — the API may return the old key when queried with the new one.
+fn parse_api_response(body: &str) -> Result<serde_json::Map<String, serde_json::Value>, HvtError> {
+    serde_json::from_str::<serde_json::Value>(body)
+        .map_err(|e| HvtError::Http(format!("DLSite API response is not JSON (access restricted?): {e}")))?
+        .as_object()
+        .cloned()
+        .ok_or_else(|| HvtError::Parse("DLSite API response is not a JSON object".to_string()))
+}
@@ -0,0 +1,150 @@
+use std::path::Path;
+use std::time::Duration;
+
+use reqwest::{RequestBuilder, StatusCode};
+use tracing::{debug, warn};
+
+use crate::config::MetadataConfig;
+use crate::dlsite::MetadataClient;
+use crate::errors::HvtError;
+
+/// Anti-bot settings of `[metadata]`
+#[derive(Debug, Clone)]
+pub struct ChallengeSettings {
+    retries: u32,
+    backoff: Duration,
+    /// `name=value; ...` of the dlsite.com cookies of the browser cookie jar
+    cookies: Option<String>,
+}
+
+/// Markers of Cloudflare/robot-check pages, looked up in the first bytes of a body
+const CHALLENGE_MARKERS: [&str; 8] = [
+    "cf-chl",
+    "challenge-platform",
+    "cf_chl_opt",
+    "<title>Just a moment",
+    "Attention Required! | Cloudflare",
+    "g-recaptcha",
+    "h-captcha",
+    "captcha-form",
+];
+
+impl ChallengeSettings {
+    /// How often a challenged request is retried, the first pause (doubled at each retry) and
+    /// the browser cookie jar (Netscape `cookies.txt`) sent to DLSite, if any
+    pub fn from_config(metadata: &MetadataConfig) -> Result<Self, HvtError> {
+        let cookies = match metadata.cookies_file.as_deref() {
+            Some(path) => {
+                let content = std::fs::read_to_string(Path::new(path)).map_err(|e| {
+                    HvtError::Generic(format!("cannot read the cookie jar {} ([metadata] cookies_file): {}", path, e))
+                })?;
+                let header = cookie_header(&content);
+                if header.is_none() {
+                    warn!("No dlsite.com cookie in {}", path);
+                }
+                header
+            }
+            None => None,
+        };
+        Ok(ChallengeSettings {
+            retries: metadata.challenge_retries,
+            backoff: Duration::from_secs(metadata.challenge_backoff_secs),
+            cookies,
+        })
+    }
+
+    /// The `Cookie` header of a request, `locale=...` and the browser cookies if configured
+    pub fn cookies_with_locale(&self, locale: &str) -> String {
+        match &self.cookies {
+            Some(cookies) => format!("locale={}; {}", locale, cookies),
+            None => format!("locale={}", locale),
+        }
+    }
+
+    /// The browser cookies alone, for requests without a locale
+    pub fn browser_cookies(&self) -> Option<&str> {
+        self.cookies.as_deref()
+    }
+}
+
+/// Whether a response is an anti-bot challenge (Cloudflare "Just a moment", captcha) rather
+/// than the page asked for
+pub fn is_challenge(status: StatusCode, body: &str) -> bool {
+    let head = body.get(..body.len().min(20_000)).unwrap_or(body);
+    let marked = CHALLENGE_MARKERS.iter().any(|marker| head.contains(marker));
+    marked && (status.is_success() || matches!(status.as_u16(), 403 | 429 | 503))
+}
+
+/// Sends the request made by `request`, waiting and retrying while DLSite answers with a
+/// challenge (`[metadata] challenge_retries`, pauses of `challenge_backoff_secs` doubled each
+/// time). Returns the status and body of the first real answer, or `HvtError::Challenge`.
+pub async fn send_with_backoff<F>(client: &MetadataClient, what: &str, request: F) -> Result<(StatusCode, String), HvtError>
+where
+    F: Fn() -> RequestBuilder,
+{
+    let settings = &client.challenge;
+    let mut attempt = 0;
+    loop {
+        let resp = client.http.send(request()).await
+            .map_err(|e| HvtError::Http(format!("HTTP request for {} failed: {}", what, e)))?;
+        let status = resp.status();
+        let body = resp.text().await
+            .map_err(|e| HvtError::Http(format!("Failed to read the response for {}: {}", what, e)))?;
+        client.stats.add_bytes_downloaded(body.len());
+
+        if !is_challenge(status, &body) {
+            return Ok((status, body));
+        }
+        if attempt >= settings.retries {
+            return Err(HvtError::Challenge(format!(
+                "DLSite served a robot check for {} ({} retries); set [metadata] cookies_file to a cookie jar exported from a browser that passed it",
+                what, settings.retries
+            )));
+        }
+        let pause = settings.backoff * 2u32.pow(attempt);
+        warn!("DLSite served a robot check for {}, retrying in {}s", what, pause.as_secs());
+        tokio::time::sleep(pause).await;
+        attempt += 1;
+        debug!("Retry {} for {}", attempt, what);
+    }
+}
+
+/// `name=value; ...` of the dlsite.com cookies of a Netscape cookie jar (`cookies.txt`, as
+/// exported by browser extensions: domain, subdomains flag, path, secure, expiry, name, value)
+fn cookie_header(content: &str) -> Option<String> {
+    let cookies: Vec<String> = content.lines()
+        // curl marks HttpOnly cookies with a "#HttpOnly_" prefix on the domain
+        .map(|line| line.strip_prefix("#HttpOnly_").unwrap_or(line))
+        .filter(|line| !line.starts_with('#') && !line.trim().is_empty())
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [domain, _, _, _, _, name, value] = fields.as_slice() else {
+                return None;
+            };
+            let domain = domain.trim_start_matches('.');
+            (domain == "dlsite.com" || domain.ends_with(".dlsite.com")).then(|| format!("{}={}", name, value.trim_end()))
+        })
+        .collect();
+    (!cookies.is_empty()).then(|| cookies.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_challenge_detection_and_cookie_jar() {
+        let cloudflare = "<html><head><title>Just a moment...</title></head><body><script src=\"/cdn-cgi/challenge-platform/h/b/orchestrate\"></script></body></html>";
+        assert!(is_challenge(StatusCode::FORBIDDEN, cloudflare));
+        assert!(is_challenge(StatusCode::OK, cloudflare));
+        assert!(!is_challenge(StatusCode::OK, r#"<html><h1 id="work_name">作品</h1></html>"#));
+        assert!(!is_challenge(StatusCode::NOT_FOUND, cloudflare));
+
+        let jar = "# Netscape HTTP Cookie File\n\
+            .dlsite.com\tTRUE\t/\tTRUE\t1999999999\tcf_clearance\tabc123\n\
+            #HttpOnly_www.dlsite.com\tFALSE\t/\tTRUE\t1999999999\t__DLsite_SID\txyz\n\
+            .example.org\tTRUE\t/\tFALSE\t1999999999\tother\tnope\n";
+        assert_eq!(cookie_header(jar).as_deref(), Some("cf_clearance=abc123; __DLsite_SID=xyz"));
+        assert_eq!(cookie_header("# empty\n"), None);
+    }
+}
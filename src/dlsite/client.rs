@@ -0,0 +1,46 @@
+use std::sync::Arc;
+
+use crate::config::{Config, MetadataConfig};
+use crate::dlsite::challenge::ChallengeSettings;
+use crate::dlsite::http_cache::HttpCache;
+use crate::dlsite::sections::Sections;
+use crate::errors::HvtError;
+use crate::http::HttpClient;
+use crate::run_stats::RunStats;
+
+/// What fetching a work needs: the HTTP client, the `[metadata]` settings and the stats of the
+/// run. Built by each workflow once the VPN is up and passed to every fetch.
+pub struct MetadataClient {
+    pub http: HttpClient,
+    pub challenge: ChallengeSettings,
+    pub sections: Sections,
+    pub cache: HttpCache,
+    /// Counts the bytes fetched
+    pub stats: Arc<RunStats>,
+    metadata: MetadataConfig,
+}
+
+impl MetadataClient {
+    /// Fails when the `[metadata] cookies_file` can't be read
+    pub fn from_config(config: &Config, stats: &Arc<RunStats>) -> Result<Self, HvtError> {
+        Ok(MetadataClient {
+            http: HttpClient::new(&config.http)?,
+            challenge: ChallengeSettings::from_config(&config.metadata)?,
+            sections: Sections::new(&config.metadata.dlsite_sections),
+            cache: HttpCache::from_config(&config.http_cache),
+            stats: Arc::clone(stats),
+            metadata: config.metadata.clone(),
+        })
+    }
+
+    /// A client for refreshes: works are fetched again even when the HTTP cache has them
+    pub fn refetching(mut self) -> Self {
+        self.cache.write_only();
+        self
+    }
+
+    /// The `[metadata]` settings (sources and their order)
+    pub fn metadata(&self) -> &MetadataConfig {
+        &self.metadata
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tracing::{debug, warn};
+
+use crate::config::HttpCacheConfig;
+
+/// The `[http_cache]` of a workflow's `MetadataClient`: DLSite API responses and work pages kept
+/// in the cache folder and reused while younger than `ttl_hours`, or whatever their age with
+/// `--offline`, when no request is sent at all.
+#[derive(Debug)]
+pub struct HttpCache {
+    /// Lifetime of a cached response, 0 when the cache is off
+    ttl_secs: u64,
+    offline: bool,
+    /// Whether fresh entries are served; refreshes only store what they fetch (`write_only`)
+    reuse: bool,
+    /// Works lacking data because of `--offline`, listed when the client is dropped
+    missing_offline: Mutex<Vec<String>>,
+}
+
+impl HttpCache {
+    pub fn from_config(config: &HttpCacheConfig) -> Self {
+        HttpCache {
+            ttl_secs: config.ttl_hours * 3600,
+            offline: config.offline,
+            reuse: true,
+            missing_offline: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Cached entries are not served (still stored for later runs), except offline where they
+    /// are all there is
+    pub fn write_only(&mut self) {
+        self.reuse = false;
+    }
+
+    /// `--offline`: no network access at all
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Records a work that couldn't be fetched because of `--offline` and has no data yet
+    pub fn record_missing_offline(&self, rjcode: &str) {
+        if let Ok(mut missing) = self.missing_offline.lock() {
+            if !missing.iter().any(|r| r == rjcode) {
+                missing.push(rjcode.to_string());
+            }
+        }
+    }
+
+    /// The cached body stored under `key`, unless missing or older than the TTL (any age offline)
+    pub fn get(&self, key: &str) -> Option<String> {
+        if !self.offline && (self.ttl_secs == 0 || !self.reuse) {
+            return None;
+        }
+        let path = entry_path(key)?;
+        if !self.offline {
+            let age = fs::metadata(&path).ok()?.modified().ok()?.elapsed().ok()?;
+            if age.as_secs() >= self.ttl_secs {
+                return None;
+            }
+        }
+        let body = fs::read_to_string(&path).ok()?;
+        debug!("HTTP cache hit: {}", key);
+        Some(body)
+    }
+
+    /// Stores a successful response. A failed write is only logged: the next run fetches it again.
+    pub fn put(&self, key: &str, body: &str) {
+        if self.ttl_secs == 0 {
+            return;
+        }
+        if let Some(path) = entry_path(key) {
+            if let Err(e) = fs::write(&path, body) {
+                debug!("Failed to write HTTP cache entry {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Lists, at the end of an `--offline` workflow, the works skipped for lack of cached data
+impl Drop for HttpCache {
+    fn drop(&mut self) {
+        let Ok(missing) = self.missing_offline.lock() else {
+            return;
+        };
+        if missing.is_empty() {
+            return;
+        }
+        warn!(
+            "Offline: {} work(s) have no metadata yet, run again without --offline to fetch them: {}",
+            missing.len(),
+            missing.join(", ")
+        );
+    }
+}
+
+/// Deletes the entries older than `[http_cache] ttl_hours`, unless offline where they are all
+/// that is left
+pub fn remove_expired(config: &HttpCacheConfig) {
+    if config.ttl_hours == 0 || config.offline {
+        return;
+    }
+    let ttl = Duration::from_secs(config.ttl_hours * 3600);
+    let Ok(dir) = crate::paths::http_cache_dir() else {
+        return;
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_none_or(|age| age >= ttl);
+        if expired {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Cache file of a DLSite API response
+pub fn api_key(rjcode: &str) -> String {
+    format!("api_{}.json", rjcode)
+}
+
+/// Cache file of a work page in one locale (`en_US`, `ja_JP`)
+pub fn page_key(rjcode: &str, locale: &str) -> String {
+    format!("work_{}_{}.html", rjcode, locale)
+}
+
+fn entry_path(key: &str) -> Option<PathBuf> {
+    crate::paths::http_cache_dir().ok().map(|dir| dir.join(key))
+}
+
+/// Whether an entry is stored under `key`, whatever its age
+pub fn contains(key: &str) -> bool {
+    entry_path(key).is_some_and(|path| path.exists())
+}
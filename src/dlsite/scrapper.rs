@@ -1,15 +1,20 @@
 use reqwest::Url;
 use scraper::{ElementRef, Html, Selector};
-use tracing::warn;
-use crate::{errors::HvtError, folders::types::RJCode};
+use tracing::{debug, warn};
+use crate::{dlsite::{challenge, http_cache, sections, MetadataClient}, errors::HvtError, folders::types::RJCode};
 
 #[derive(Debug)]
 pub struct DlSiteProductScrapResult {
     pub genre: Vec<String>,
+    pub genre_jp: Vec<String>,            // Genres of the ja_JP page, same order as `genre` (empty if unknown)
     pub cvs: Vec<String>,
+    pub cvs_en: Vec<String>,              // English CV names, same order as `cvs` (empty if unknown)
     pub circle_name: Option<String>,      // Backward compat (JP if avail, else EN)
     pub circle_name_en: Option<String>,   // English circle name
     pub circle_name_jp: Option<String>,   // Japanese circle name
+    pub description: Option<String>,      // Work description/synopsis (plain text)
+    pub work_name_en: Option<String>,     // Title on the en_US locale page (JP title if untranslated)
+    pub sample_images: Vec<String>,       // Sample/gallery image URLs, in page order (cover excluded)
 }
 
 fn extract_td_after_th(html: &str, th_text: &str) -> Result<Option<String>, HvtError> {
@@ -68,76 +73,146 @@ fn extract_cv_from_staff_block(html: &str) -> Result<Vec<String>, HvtError> {
     Ok(vec![])
 }
 
-impl DlSiteProductScrapResult {
-    pub async fn build_from_rjcode(rjcode: String) -> DlSiteProductScrapResult {
-        Self::build_from_rjcode_with_client(rjcode, None).await
+/// Extracts the work description as plain text: the `itemprop="description"` block (falling back
+/// to the `.work_parts_area` sections it is made of), one line per text node, blank lines dropped.
+fn extract_description(html: &str) -> Result<Option<String>, HvtError> {
+    let document = Html::parse_document(html);
+
+    for selector in ["[itemprop=\"description\"]", ".work_parts_area"] {
+        let selector = Selector::parse(selector)
+            .map_err(|e| HvtError::Parse(format!("Failed to parse description selector: {:?}", e)))?;
+
+        let lines: Vec<&str> = document.select(&selector)
+            .flat_map(|container| container.text())
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        if !lines.is_empty() {
+            return Ok(Some(lines.join("\n")));
+        }
     }
 
-    pub async fn build_from_rjcode_with_client(
-        rjcode: String,
-        client: Option<&reqwest::Client>,
-    ) -> DlSiteProductScrapResult {
-        // Internal function that handles errors - converts them to default values
-        match Self::build_from_rjcode_impl(rjcode, client).await {
-            Ok(result) => result,
-            Err(e) => {
-                warn!("Failed to scrape DLSite data: {}", e);
-                // Return empty result on error (will be detected as RemovedWork)
-                DlSiteProductScrapResult {
-                    genre: vec![],
-                    cvs: vec![String::from("<unknown>")],
-                    circle_name: None,
-                    circle_name_en: None,
-                    circle_name_jp: None,
-                }
-            }
+    Ok(None)
+}
+
+/// Extracts the sample gallery of a product page: the `data-src` of each `.product-slider-data`
+/// entry, made absolute. The first entry is the main cover, already saved as folder.jpeg.
+fn extract_sample_images(html: &str) -> Result<Vec<String>, HvtError> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse(".product-slider-data > div[data-src]")
+        .map_err(|e| HvtError::Parse(format!("Failed to parse product-slider-data selector: {:?}", e)))?;
+
+    let mut images: Vec<String> = Vec::new();
+    for elem in document.select(&selector) {
+        let Some(src) = elem.value().attr("data-src").map(str::trim).filter(|s| !s.is_empty()) else {
+            continue;
+        };
+        let url = if src.starts_with("//") { format!("https:{src}") } else { src.to_string() };
+        if !images.contains(&url) {
+            images.push(url);
+        }
+    }
+    Ok(images.into_iter().skip(1).collect())
+}
+
+/// Extracts the genres (`.main_genre` links) of a product page, in the page's locale.
+fn extract_genres(html: &str) -> Result<Vec<String>, HvtError> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse(".main_genre")
+        .map_err(|e| HvtError::Parse(format!("Failed to parse main_genre selector: {:?}", e)))?;
+
+    let mut genre = vec![];
+    if let Some(elem) = document.select(&selector).next() {
+        let content = elem.text().filter(|x| !x.contains("\n")).collect::<Vec<_>>();
+        for c in content {
+            genre.push(c.to_string());
         }
     }
+    Ok(genre)
+}
+
+/// Extracts the work title (`#work_name` heading) of a product page.
+fn extract_work_name(html: &str) -> Result<Option<String>, HvtError> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("#work_name")
+        .map_err(|e| HvtError::Parse(format!("Failed to parse work_name selector: {:?}", e)))?;
+
+    Ok(document.select(&selector)
+        .next()
+        .map(|elem| elem.text().collect::<Vec<_>>().join("").trim().to_string())
+        .filter(|name| !name.is_empty()))
+}
+
+/// Whether a page is a work page (it has the `#work_name` title)
+fn is_work_page(html: &str) -> bool {
+    extract_work_name(html).is_ok_and(|name| name.is_some())
+}
 
-    async fn build_from_rjcode_impl(
+impl DlSiteProductScrapResult {
+    /// Scrapes the work page. Fails with `HvtError::RemovedWork` only when no section has the
+    /// page (404); a timeout, a 403/429/5xx or a captcha page is an `HvtError::Http`, so a
+    /// transient failure isn't taken for a removed work.
+    pub async fn build_from_rjcode_with_client(
         rjcode: String,
-        client: Option<&reqwest::Client>,
+        client: &MetadataClient,
     ) -> Result<DlSiteProductScrapResult, HvtError> {
         let code = RJCode::from_string_unchecked(rjcode.clone());
-        let section = code.site_section();
-        let url_str = format!("https://www.dlsite.com/{section}/work/=/product_id/{rjcode}.html");
-        let url = url_str.parse::<Url>()
-            .map_err(|e| HvtError::Http(format!("Invalid URL: {}", e)))?;
 
-        let default_client = reqwest::Client::new();
-        let http_client = client.unwrap_or(&default_client);
-
-        let resp = http_client
-            .get(url)
-            .header("Cookie", "locale=en_US")
-            .header("Accept-Language", "en-US")
-            .send()
-            .await
-            .map_err(|e| HvtError::Http(format!("HTTP request failed: {}", e)))?;
-
-        let html = resp.text().await
-            .map_err(|e| HvtError::Http(format!("Failed to get response text: {}", e)))?;
-
-        let document = Html::parse_document(&html);
-        let selector = Selector::parse(".main_genre")
-            .map_err(|e| HvtError::Parse(format!("Failed to parse main_genre selector: {:?}", e)))?;
-
-        let mut genre = vec![];
-        if let Some(elem) = document.select(&selector).next() {
-            let content = elem.text().filter(|x| !x.contains("\n")).collect::<Vec<_>>();
-            for c in content {
-                genre.push(c.replace("'", "''").to_string());
+        // The page only exists under the work's section: try them in order (see sections)
+        let mut page = None;
+        for section in client.sections.candidates(&code) {
+            let url_str = format!("https://www.dlsite.com/{section}/work/=/product_id/{rjcode}.html");
+            let url = url_str.parse::<Url>()
+                .map_err(|e| HvtError::Http(format!("Invalid URL: {}", e)))?;
+            if let Some(html) = fetch_work_page(client, url, &rjcode, "en_US").await? {
+                client.sections.remember(&code, &section);
+                page = Some((url_str, html));
+                break;
             }
+            debug!("{} has no page under /{}/", rjcode, section);
         }
-
-        // Extract CVs - Try English FIRST (since we're using en_US locale), then Japanese as fallback
-        let mut cvs = vec![];
-        if let Some(elem) = extract_td_after_th(&html, "Voice Actor")? {
-            cvs = elem.split(" / ").map(|x| x.trim().to_string()).collect();
-        }
+        let Some((url_str, html)) = page else {
+            return Err(HvtError::RemovedWork(code));
+        };
+
+        let genre = extract_genres(&html)?;
+
+        // The ja_JP page gives the Japanese CV names (they are the cvs.name_jp key) and genres;
+        // the en_US page's "Voice Actor" row gives the English names when it lists the same CVs
+        let cvs_en_page = extract_td_after_th(&html, "Voice Actor")?
+            .map(|elem| split_cv_names(&elem))
+            .unwrap_or_default();
+        let (cvs_jp_page, genre_jp_page) = match fetch_work_page(client, &url_str, &rjcode, "ja_JP").await {
+            Ok(Some(jp_html)) => (
+                extract_td_after_th(&jp_html, "声優")?.map(|elem| split_cv_names(&elem)).unwrap_or_default(),
+                extract_genres(&jp_html)?,
+            ),
+            Ok(None) => {
+                warn!("No Japanese page for {}", rjcode);
+                (vec![], vec![])
+            }
+            // Kept as a fetch failure rather than stored without Japanese names and genres
+            Err(e @ HvtError::Challenge(_)) => return Err(e),
+            Err(e) => {
+                warn!("Failed to fetch the Japanese page of {}: {}", rjcode, e);
+                (vec![], vec![])
+            }
+        };
+        // Genres are paired by position, so only a list of the same length is usable
+        let genre_jp = if genre_jp_page.len() == genre.len() { genre_jp_page } else { vec![] };
+
+        let mut cvs_en = vec![];
+        let mut cvs = if cvs_jp_page.is_empty() {
+            cvs_en_page
+        } else {
+            if cvs_en_page.len() == cvs_jp_page.len() {
+                cvs_en = cvs_en_page;
+            }
+            cvs_jp_page
+        };
         if cvs.is_empty() {
             if let Some(elem) = extract_td_after_th(&html, "声優")? {
-                cvs = elem.split(" / ").map(|x| x.trim().to_string()).collect();
+                cvs = split_cv_names(&elem);
             }
         }
         if cvs.is_empty() {
@@ -152,19 +227,70 @@ impl DlSiteProductScrapResult {
         let circle_name_en = extract_td_after_th(&html, "Circle")?.map(|s| s.trim().to_string());
         let circle_name_jp = extract_td_after_th(&html, "サークル名")?.map(|s| s.trim().to_string());
 
+        let description = extract_description(&html)?;
+        let work_name_en = extract_work_name(&html)?;
+        let sample_images = extract_sample_images(&html)?;
+
         // For backward compatibility, set circle_name to EN if available, else JP (since we're in EN locale)
         let circle_name = circle_name_en.clone().or(circle_name_jp.clone());
 
         Ok(DlSiteProductScrapResult {
             genre,
+            genre_jp,
             cvs,
+            cvs_en,
             circle_name,        // JP prioritaire (backward compat)
             circle_name_en,     // English name
             circle_name_jp,     // Japanese name
+            description,
+            work_name_en,
+            sample_images,
         })
     }
 }
 
+fn split_cv_names(row: &str) -> Vec<String> {
+    row.split(" / ").map(|x| x.trim().to_string()).filter(|x| !x.is_empty()).collect()
+}
+
+/// The work page in `locale` (`en_US`, `ja_JP`), from the HTTP cache when it is fresh. Only
+/// successful responses are cached; a robot check is retried (see `challenge`).
+async fn fetch_work_page<U: reqwest::IntoUrl + Clone>(
+    client: &MetadataClient,
+    url: U,
+    rjcode: &str,
+    locale: &str,
+) -> Result<Option<String>, HvtError> {
+    let cache_key = http_cache::page_key(rjcode, locale);
+    if let Some(html) = client.cache.get(&cache_key) {
+        return Ok(Some(html));
+    }
+    if client.cache.is_offline() {
+        return Err(HvtError::Offline(format!("no cached {} page for {}", locale, rjcode)));
+    }
+
+    let (status, html) = challenge::send_with_backoff(client, &format!("the {} page of {}", locale, rjcode), || {
+        client.http
+            .get(url.clone())
+            .header("Cookie", client.challenge.cookies_with_locale(locale))
+            .header("Accept-Language", locale.replace('_', "-"))
+    }).await?;
+    // Not under this section: the caller tries the next one
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !status.is_success() {
+        return Err(HvtError::Http(format!("DLSite answered {} for the {} page of {}", status, locale, rjcode)));
+    }
+
+    // A captcha or access restriction page comes with a 200 too: not cached, and not a 404
+    if !is_work_page(&html) {
+        return Err(HvtError::Http(format!("DLSite served a page without the work for {} ({}), captcha or access restriction?", rjcode, locale)));
+    }
+    client.cache.put(&cache_key, &html);
+    Ok(Some(html))
+}
+
 /// Parse circle name from page title
 /// Title format: "Circle Name（カタカナ） Circle Profile | ..."
 /// Extracts only the name before the katakana pronunciation
@@ -189,35 +315,31 @@ fn parse_circle_name_from_title(title: &str) -> String {
 /// Scrape circle names from circle profile page TITLE.
 /// Makes 2 requests with different locales to get both EN and JP names.
 ///
-/// `section` should be `"maniax"` (RJ works) or `"pro"` (VJ works).
+/// `section` is the section of one of the circle's works (see `Sections::section_of`).
 /// Returns (name_en, name_jp)
 pub async fn scrape_circle_profile(
     rgcode: &str,
     section: &str,
-    client: Option<&reqwest::Client>,
+    client: &MetadataClient,
 ) -> Result<(String, String), HvtError> {
-    let subpath = if section == "pro" { "maker/profile" } else { "circle/profile" };
+    let subpath = if sections::is_commercial(section) { "maker/profile" } else { "circle/profile" };
+    if client.cache.is_offline() {
+        return Err(HvtError::Offline(format!("circle profile of {} not fetched", rgcode)));
+    }
     let url_str = format!("https://www.dlsite.com/{section}/{subpath}/=/maker_id/{rgcode}.html");
     let url = url_str.parse::<Url>()
         .map_err(|e| HvtError::Http(format!("Invalid URL: {}", e)))?;
 
-    let default_client = reqwest::Client::new();
-    let http_client = client.unwrap_or(&default_client);
-
     let title_selector = Selector::parse("title")
         .map_err(|e| HvtError::Parse(format!("Failed to parse title selector: {:?}", e)))?;
 
     // Request 1: Get EN name with locale=en_US
-    let resp_en = http_client
-        .get(url.clone())
-        .header("Cookie", "locale=en_US")
-        .header("Accept-Language", "en-US")
-        .send()
-        .await
-        .map_err(|e| HvtError::Http(format!("HTTP request failed (EN): {}", e)))?;
-
-    let html_en = resp_en.text().await
-        .map_err(|e| HvtError::Http(format!("Failed to get response text (EN): {}", e)))?;
+    let (_, html_en) = challenge::send_with_backoff(client, &format!("the circle profile of {} (EN)", rgcode), || {
+        client.http
+            .get(url.clone())
+            .header("Cookie", client.challenge.cookies_with_locale("en_US"))
+            .header("Accept-Language", "en-US")
+    }).await?;
 
     let document_en = Html::parse_document(&html_en);
     let name_en = if let Some(title_elem) = document_en.select(&title_selector).next() {
@@ -228,16 +350,12 @@ pub async fn scrape_circle_profile(
     };
 
     // Request 2: Get JP name with locale=ja_JP
-    let resp_jp = http_client
-        .get(url)
-        .header("Cookie", "locale=ja_JP")
-        .header("Accept-Language", "ja-JP")
-        .send()
-        .await
-        .map_err(|e| HvtError::Http(format!("HTTP request failed (JP): {}", e)))?;
-
-    let html_jp = resp_jp.text().await
-        .map_err(|e| HvtError::Http(format!("Failed to get response text (JP): {}", e)))?;
+    let (_, html_jp) = challenge::send_with_backoff(client, &format!("the circle profile of {} (JP)", rgcode), || {
+        client.http
+            .get(url.clone())
+            .header("Cookie", client.challenge.cookies_with_locale("ja_JP"))
+            .header("Accept-Language", "ja-JP")
+    }).await?;
 
     let document_jp = Html::parse_document(&html_jp);
     let name_jp = if let Some(title_elem) = document_jp.select(&title_selector).next() {
@@ -254,6 +372,31 @@ pub async fn scrape_circle_profile(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_captcha_page_is_not_a_work_page() {
+        assert!(is_work_page(r#"<html><body><h1 id="work_name">作品名</h1></body></html>"#));
+        assert!(!is_work_page(r#"<html><body><form id="captcha-form">Please verify you are human</form></body></html>"#));
+    }
+
+    #[test]
+    fn test_extract_sample_images_skips_cover() {
+        let html = r#"<html><body>
+            <div class="product-slider-data">
+                <div data-src="//img.dlsite.jp/modpub/images2/work/doujin/RJ01000000/RJ01000000_img_main.jpg"></div>
+                <div data-src="//img.dlsite.jp/modpub/images2/work/doujin/RJ01000000/RJ01000000_img_smp1.jpg"></div>
+                <div data-src="https://img.dlsite.jp/modpub/images2/work/doujin/RJ01000000/RJ01000000_img_smp2.png"></div>
+                <div data-src="//img.dlsite.jp/modpub/images2/work/doujin/RJ01000000/RJ01000000_img_smp1.jpg"></div>
+            </div>
+        </body></html>"#;
+
+        let images = extract_sample_images(html).unwrap();
+        assert_eq!(images, vec![
+            "https://img.dlsite.jp/modpub/images2/work/doujin/RJ01000000/RJ01000000_img_smp1.jpg".to_string(),
+            "https://img.dlsite.jp/modpub/images2/work/doujin/RJ01000000/RJ01000000_img_smp2.png".to_string(),
+        ]);
+        assert!(extract_sample_images("<html></html>").unwrap().is_empty());
+    }
+
     /// Mirrors the real structure found on RJ197417's page: no structured Voice Actor row,
     /// CV credited only in the free-text [Staff] block inside .work_parts_area.
     #[test]
@@ -312,6 +455,41 @@ mod tests {
         assert!(cvs.is_empty());
     }
 
+    #[test]
+    fn test_extract_description_prefers_itemprop_block() {
+        let html = r#"<html><body>
+            <div class="work_parts_container" itemprop="description">
+                <div class="work_parts_area"><p>あらすじ<br />
+                耳かきで癒される作品です。</p></div>
+            </div>
+            <div class="work_parts_area"><p>Unrelated block</p></div>
+        </body></html>"#;
+
+        let description = extract_description(html).unwrap();
+        assert_eq!(description.as_deref(), Some("あらすじ\n耳かきで癒される作品です。"));
+    }
+
+    #[test]
+    fn test_extract_genres() {
+        let html = r#"<div class="main_genre"><a href="/g1">癒し</a><a href="/g2">耳かき</a>
+</div>"#;
+        assert_eq!(extract_genres(html).unwrap(), ["癒し", "耳かき"]);
+        assert!(extract_genres("<div></div>").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_extract_work_name() {
+        let html = r#"<html><body>
+            <h1 itemprop="name" id="work_name">
+                Binaural Ear Cleaning for Sleep
+            </h1>
+        </body></html>"#;
+
+        let name = extract_work_name(html).unwrap();
+        assert_eq!(name.as_deref(), Some("Binaural Ear Cleaning for Sleep"));
+        assert_eq!(extract_work_name("<html><body></body></html>").unwrap(), None);
+    }
+
     #[test]
     fn test_extract_cv_from_staff_block_no_container_present() {
         let html = r#"<html><body><p>No work_parts_area div at all.</p></body></html>"#;
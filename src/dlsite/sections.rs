@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::folders::types::RJCode;
+
+// DLSite files works under site sections ("floors"): most RJ works are under /maniax/, but
+// all-ages, otome and BL works only exist under /home/, /girls/ or /bl/, and the API and work
+// page answer 404 for them elsewhere. Sections are tried in order ([metadata] dlsite_sections)
+// and the one that answered is remembered, for the rest of the fetch in `Sections` and in
+// `dlsite_scan` for the next runs and the tagger (see `queries::get_dlsite_section`).
+
+/// Sections of RJ works tried when `[metadata] dlsite_sections` is not set
+pub const DEFAULT_RJ_SECTIONS: [&str; 4] = ["maniax", "home", "girls", "bl"];
+
+/// Sections of VJ (commercial) works
+const VJ_SECTIONS: [&str; 3] = ["pro", "girls-pro", "bl-pro"];
+
+/// The sections tried for works and the ones they were found under, for one `MetadataClient`
+#[derive(Debug)]
+pub struct Sections {
+    /// Sections tried for RJ works, in order (`[metadata] dlsite_sections`)
+    rj_sections: Vec<String>,
+    found: Mutex<HashMap<String, String>>,
+}
+
+impl Sections {
+    pub fn new(rj_sections: &[String]) -> Self {
+        Sections { rj_sections: rj_sections.to_vec(), found: Mutex::new(HashMap::new()) }
+    }
+
+    /// The sections to try for a work, the one it was last found under first
+    pub fn candidates(&self, code: &RJCode) -> Vec<String> {
+        let mut sections: Vec<String> = if code.as_str().starts_with("VJ") {
+            VJ_SECTIONS.iter().map(|s| s.to_string()).collect()
+        } else if self.rj_sections.is_empty() {
+            DEFAULT_RJ_SECTIONS.iter().map(|s| s.to_string()).collect()
+        } else {
+            self.rj_sections.clone()
+        };
+        if let Some(found) = self.found(code) {
+            sections.retain(|s| *s != found);
+            sections.insert(0, found);
+        }
+        sections
+    }
+
+    /// The section a work was found under, if it was looked up or remembered
+    pub fn found(&self, code: &RJCode) -> Option<String> {
+        self.found.lock().ok()?.get(code.as_str()).cloned()
+    }
+
+    /// Section of a work: the one it was found under, else the first to try
+    pub fn section_of(&self, code: &RJCode) -> String {
+        self.found(code).unwrap_or_else(|| self.candidates(code).remove(0))
+    }
+
+    /// Remembers the section that answered for a work
+    pub fn remember(&self, code: &RJCode, section: &str) {
+        if let Ok(mut found) = self.found.lock() {
+            found.insert(code.as_str().to_string(), section.to_string());
+        }
+    }
+}
+
+/// Section of a work whose section is unknown: the first default one
+pub fn default_section(code: &RJCode) -> &'static str {
+    if code.as_str().starts_with("VJ") {
+        VJ_SECTIONS[0]
+    } else {
+        DEFAULT_RJ_SECTIONS[0]
+    }
+}
+
+/// Whether circles of a section have a `maker/profile` page (commercial brands) rather than a
+/// `circle/profile` one
+pub fn is_commercial(section: &str) -> bool {
+    section.ends_with("pro")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_found_section_is_tried_first() {
+        let sections = Sections::new(&[]);
+        let work = RJCode::new("RJ01999999".to_string()).unwrap();
+        assert_eq!(sections.candidates(&work)[0], "maniax");
+        sections.remember(&work, "girls");
+        assert_eq!(sections.candidates(&work), ["girls", "maniax", "home", "bl"]);
+        assert_eq!(sections.section_of(&work), "girls");
+        assert_eq!(sections.candidates(&RJCode::new("VJ01000000".to_string()).unwrap())[0], "pro");
+        // Another client starts with nothing found
+        assert_eq!(Sections::new(&["home".to_string()]).section_of(&work), "home");
+    }
+}
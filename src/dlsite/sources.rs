@@ -0,0 +1,365 @@
+use std::future::Future;
+use std::pin::Pin;
+
+use tracing::{debug, warn};
+
+use crate::{
+    config::MetadataConfig,
+    dlsite::{scrapper::DlSiteProductScrapResult, MetadataClient},
+    errors::HvtError,
+    folders::types::{RGCode, RJCode},
+    tagger::types::{AgeCategory, ReviewCounts, WorkDetails},
+};
+
+/// Names accepted in `[metadata] sources`
+pub const SOURCE_NAMES: [&str; 2] = ["dlsite", "asmr_one"];
+
+pub type SourceFuture<'a> = Pin<Box<dyn Future<Output = Result<SourceMetadata, HvtError>> + 'a>>;
+
+/// A site or API that knows the metadata of works
+pub trait MetadataSource {
+    /// Name of the source in `[metadata] sources`
+    fn name(&self) -> &'static str;
+
+    /// Fetches what the source knows of `rjcode`. `HvtError::RemovedWork` tells the work is gone
+    /// from the source.
+    fn fetch<'a>(&'a self, rjcode: &'a RJCode, client: &'a MetadataClient) -> SourceFuture<'a>;
+}
+
+/// Metadata of a work as one source knows it; a field a source doesn't provide stays
+/// `None`/empty and is filled by the next source (see `fill_missing`)
+#[derive(Debug, Default)]
+pub struct SourceMetadata {
+    pub name: Option<String>,
+    pub name_en: Option<String>,
+    pub maker_code: Option<RGCode>,
+    pub circle_name_en: Option<String>,
+    pub circle_name_jp: Option<String>,
+    pub age_category: Option<AgeCategory>,
+    pub rate: Option<f32>,
+    pub image_link: Option<String>,
+    pub release_date: Option<String>,
+    pub tags: Vec<String>,
+    /// Japanese names of `tags`, same order (empty if unknown)
+    pub tags_jp: Vec<String>,
+    pub cvs: Vec<String>,
+    /// English names of `cvs`, same order (empty if unknown)
+    pub cvs_en: Vec<String>,
+    pub description: Option<String>,
+    /// (relation, workno) pairs, see `translation_relations`
+    pub related_works: Vec<(String, String)>,
+    pub sample_images: Vec<String>,
+    /// Price (yen), download and wishlist counts at the time of the fetch
+    pub price: Option<u32>,
+    pub dl_count: Option<u64>,
+    pub wishlist_count: Option<u64>,
+    /// Rankings reached: (term, category, rank, rank_date)
+    pub rankings: Vec<(String, String, u32, String)>,
+    pub reviews: Option<ReviewCounts>,
+    /// Work type, option codes and circle-defined genres (DLSite only)
+    pub work_type: Option<String>,
+    pub options: Vec<String>,
+    pub custom_genres: Vec<String>,
+}
+
+impl SourceMetadata {
+    /// Takes from `other` the fields this one lacks. Paired lists (tags and their Japanese
+    /// names, CVs and their English names) are taken together so they stay aligned.
+    pub fn fill_missing(&mut self, other: SourceMetadata) {
+        fn fill<T>(field: &mut Option<T>, value: Option<T>) {
+            if field.is_none() {
+                *field = value;
+            }
+        }
+        fill(&mut self.name, other.name);
+        fill(&mut self.name_en, other.name_en);
+        fill(&mut self.maker_code, other.maker_code);
+        fill(&mut self.circle_name_en, other.circle_name_en);
+        fill(&mut self.circle_name_jp, other.circle_name_jp);
+        fill(&mut self.age_category, other.age_category);
+        fill(&mut self.rate, other.rate);
+        fill(&mut self.image_link, other.image_link);
+        fill(&mut self.release_date, other.release_date);
+        fill(&mut self.description, other.description);
+        fill(&mut self.price, other.price);
+        fill(&mut self.dl_count, other.dl_count);
+        fill(&mut self.wishlist_count, other.wishlist_count);
+        fill(&mut self.reviews, other.reviews);
+        fill(&mut self.work_type, other.work_type);
+        if self.options.is_empty() {
+            self.options = other.options;
+        }
+        if self.custom_genres.is_empty() {
+            self.custom_genres = other.custom_genres;
+        }
+        if self.tags.is_empty() {
+            self.tags = other.tags;
+            self.tags_jp = other.tags_jp;
+        }
+        if self.cvs.is_empty() {
+            self.cvs = other.cvs;
+            self.cvs_en = other.cvs_en;
+        }
+        if self.related_works.is_empty() {
+            self.related_works = other.related_works;
+        }
+        if self.sample_images.is_empty() {
+            self.sample_images = other.sample_images;
+        }
+        if self.rankings.is_empty() {
+            self.rankings = other.rankings;
+        }
+    }
+}
+
+/// Checks the sources of `[metadata]`: fails on an unknown source name or an empty list
+pub fn check_sources(config: &MetadataConfig) -> Result<(), HvtError> {
+    if let Some(unknown) = config.sources.iter().find(|s| !SOURCE_NAMES.contains(&s.as_str())) {
+        return Err(HvtError::Parse(format!(
+            "unknown metadata source '{}' in [metadata] sources (expected one of: {})",
+            unknown,
+            SOURCE_NAMES.join(", ")
+        )));
+    }
+    if config.sources.is_empty() {
+        return Err(HvtError::Parse("[metadata] sources is empty".to_string()));
+    }
+    Ok(())
+}
+
+/// The configured sources, in order of preference
+pub fn configured_sources(config: &MetadataConfig) -> Vec<Box<dyn MetadataSource>> {
+    config.sources.iter()
+        .filter_map(|name| -> Option<Box<dyn MetadataSource>> {
+            match name.as_str() {
+                "dlsite" => Some(Box::new(DlSiteSource)),
+                "asmr_one" => Some(Box::new(AsmrOneSource { api_url: config.asmr_one_api.clone() })),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Whether DLSite is one of the configured sources
+pub fn uses_dlsite(config: &MetadataConfig) -> bool {
+    config.sources.iter().any(|s| s == "dlsite")
+}
+
+/// Fetches `rjcode` from every configured source and merges the results, earlier sources
+/// winning. A failing source is skipped, except a work removed from DLSite which stays an
+/// error (it is reported as removed rather than tagged from another site). Fails with the
+/// first error when no source knows the work.
+pub async fn fetch_metadata(rjcode: &RJCode, client: &MetadataClient) -> Result<SourceMetadata, HvtError> {
+    let mut merged: Option<SourceMetadata> = None;
+    let mut first_error = None;
+
+    for source in configured_sources(client.metadata()) {
+        match source.fetch(rjcode, client).await {
+            Ok(metadata) => match merged.as_mut() {
+                Some(merged) => merged.fill_missing(metadata),
+                None => merged = Some(metadata),
+            },
+            Err(e @ HvtError::RemovedWork(_)) => return Err(e),
+            Err(e) => {
+                debug!("Metadata source {} failed for {}: {}", source.name(), rjcode, e);
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    match (merged, first_error) {
+        (Some(merged), first_error) => {
+            if let Some(e) = first_error {
+                warn!("Some metadata of {} could not be fetched: {}", rjcode, e);
+            }
+            Ok(merged)
+        }
+        (None, Some(e)) => Err(e),
+        (None, None) => Err(HvtError::Generic("no metadata source configured".to_string())),
+    }
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+// ========== DLSite ==========
+
+/// The DLSite product API (names, rating, dates, relations) and work pages (genres, CVs,
+/// description, sample images)
+pub struct DlSiteSource;
+
+impl MetadataSource for DlSiteSource {
+    fn name(&self) -> &'static str {
+        "dlsite"
+    }
+
+    fn fetch<'a>(&'a self, rjcode: &'a RJCode, client: &'a MetadataClient) -> SourceFuture<'a> {
+        Box::pin(async move {
+            let wd = WorkDetails::build_from_rjcode_with_client(rjcode.as_str().to_string(), client).await?;
+            let sr = DlSiteProductScrapResult::build_from_rjcode_with_client(rjcode.as_str().to_string(), client).await?;
+
+            Ok(SourceMetadata {
+                name: non_empty(wd.name),
+                name_en: sr.work_name_en,
+                maker_code: (!wd.maker_code.as_str().is_empty()).then_some(wd.maker_code),
+                circle_name_en: sr.circle_name_en,
+                circle_name_jp: sr.circle_name_jp.or(sr.circle_name),
+                age_category: Some(wd.age_category),
+                rate: Some(wd.rate),
+                image_link: non_empty(wd.image_link),
+                release_date: non_empty(wd.release_date),
+                tags: sr.genre,
+                tags_jp: sr.genre_jp,
+                cvs: sr.cvs,
+                cvs_en: sr.cvs_en,
+                description: sr.description,
+                related_works: wd.related_works,
+                sample_images: sr.sample_images,
+                price: wd.price,
+                dl_count: wd.dl_count,
+                wishlist_count: wd.wishlist_count,
+                rankings: wd.rankings,
+                reviews: wd.reviews,
+                work_type: wd.work_type,
+                options: wd.options,
+                custom_genres: wd.custom_genres,
+            })
+        })
+    }
+}
+
+// ========== asmr.one ==========
+
+/// The community JSON API of asmr.one (`<api>/workInfo/<number>`): title, circle, CVs, tags
+/// with their Japanese names, release date, rating and cover
+pub struct AsmrOneSource {
+    pub api_url: String,
+}
+
+/// Cache file of an asmr.one API response
+fn asmr_one_key(rjcode: &str) -> String {
+    format!("asmr_one_{}.json", rjcode)
+}
+
+impl MetadataSource for AsmrOneSource {
+    fn name(&self) -> &'static str {
+        "asmr_one"
+    }
+
+    fn fetch<'a>(&'a self, rjcode: &'a RJCode, client: &'a MetadataClient) -> SourceFuture<'a> {
+        Box::pin(async move {
+            // The API knows works by the number of their code, without the leading zeros
+            let number: u64 = rjcode.as_str().trim_start_matches(|c: char| c.is_ascii_alphabetic()).parse()
+                .map_err(|_| HvtError::Parse(format!("{} has no work number", rjcode)))?;
+            let url = format!("{}/workInfo/{}", self.api_url.trim_end_matches('/'), number);
+
+            let cache_key = asmr_one_key(rjcode.as_str());
+            let cached = client.cache.get(&cache_key);
+            let body = match &cached {
+                Some(body) => body.clone(),
+                None if client.cache.is_offline() => {
+                    return Err(HvtError::Offline(format!("no cached asmr.one response for {}", rjcode)));
+                }
+                None => {
+                    debug!("Querying asmr.one API: {url}");
+                    let response = client.http.send(client.http.get(&url)).await.map_err(|e| HvtError::Http(e.to_string()))?;
+                    if !response.status().is_success() {
+                        return Err(HvtError::Http(format!("asmr.one returned {} for {}", response.status(), rjcode)));
+                    }
+                    let body = response.text().await.map_err(|e| HvtError::Http(e.to_string()))?;
+                    client.stats.add_bytes_downloaded(body.len());
+                    body
+                }
+            };
+
+            let metadata = parse_asmr_one_work(&body)?;
+            if cached.is_none() {
+                client.cache.put(&cache_key, &body);
+            }
+            Ok(metadata)
+        })
+    }
+}
+
+/// Parses an asmr.one `workInfo` response
+fn parse_asmr_one_work(body: &str) -> Result<SourceMetadata, HvtError> {
+    let work: serde_json::Value = serde_json::from_str(body)
+        .map_err(|e| HvtError::Parse(format!("asmr.one response: {}", e)))?;
+    if !work.is_object() || work.get("title").is_none() {
+        return Err(HvtError::Parse("asmr.one response is not a work".to_string()));
+    }
+
+    let text = |value: &serde_json::Value| value.as_str().map(str::trim).filter(|s| !s.is_empty()).map(String::from);
+
+    let circle = &work["circle"];
+    let maker_code = text(&circle["source_id"])
+        .filter(|id| id.starts_with("RG"))
+        .or_else(|| work["circle_id"].as_u64().map(|id| format!("RG{:05}", id)))
+        .map(RGCode::new);
+
+    let mut tags = Vec::new();
+    let mut tags_jp = Vec::new();
+    for tag in work["tags"].as_array().into_iter().flatten() {
+        let Some(name) = text(&tag["i18n"]["en-us"]["name"]).or_else(|| text(&tag["name"])) else {
+            continue;
+        };
+        tags.push(name);
+        tags_jp.push(text(&tag["i18n"]["ja-jp"]["name"]).unwrap_or_default());
+    }
+    // Japanese names are only usable when every tag has one (same order as `tags`)
+    if tags_jp.iter().any(String::is_empty) {
+        tags_jp.clear();
+    }
+
+    Ok(SourceMetadata {
+        name: text(&work["title"]),
+        circle_name_jp: text(&circle["name"]).or_else(|| text(&work["name"])),
+        maker_code,
+        // `nsfw` only tells adult works apart from the others
+        age_category: work["nsfw"].as_bool().filter(|nsfw| *nsfw).map(|_| AgeCategory::R18),
+        rate: work["rate_average_2dp"].as_f64().map(|r| r as f32),
+        image_link: text(&work["mainCoverUrl"]),
+        release_date: text(&work["release"]),
+        tags,
+        tags_jp,
+        price: work["price"].as_u64().map(|p| p as u32),
+        dl_count: work["dl_count"].as_u64(),
+        reviews: ReviewCounts::from_json(&work),
+        cvs: work["vas"].as_array().into_iter().flatten().filter_map(|va| text(&va["name"])).collect(),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asmr_one_fills_missing_fields() {
+        let body = r#"{"id":1234567,"title":"作品","circle_id":12345,"name":"サークル","nsfw":true,
+            "release":"2021-03-04","rate_average_2dp":4.56,"mainCoverUrl":"https://example.org/cover.jpg",
+            "circle":{"id":12345,"name":"サークル","source_id":"RG12345"},
+            "vas":[{"id":"a","name":"声優A"},{"id":"b","name":"声優B"}],
+            "tags":[{"id":1,"name":"耳かき","i18n":{"en-us":{"name":"Ear Cleaning"},"ja-jp":{"name":"耳かき"}}}]}"#;
+        let asmr_one = parse_asmr_one_work(body).unwrap();
+        assert_eq!(asmr_one.maker_code.as_ref().map(RGCode::as_str), Some("RG12345"));
+        assert_eq!(asmr_one.tags, ["Ear Cleaning"]);
+        assert_eq!(asmr_one.tags_jp, ["耳かき"]);
+        assert_eq!(asmr_one.cvs, ["声優A", "声優B"]);
+
+        let mut dlsite = SourceMetadata {
+            name: Some("DLSite title".to_string()),
+            tags: vec!["binaural".to_string()],
+            ..Default::default()
+        };
+        dlsite.fill_missing(asmr_one);
+        assert_eq!(dlsite.name.as_deref(), Some("DLSite title"));
+        assert_eq!(dlsite.tags, ["binaural"]);
+        assert!(dlsite.tags_jp.is_empty());
+        assert_eq!(dlsite.cvs.len(), 2);
+        assert_eq!(dlsite.release_date.as_deref(), Some("2021-03-04"));
+
+        assert!(parse_asmr_one_work(r#"{"error":"not found"}"#).is_err());
+    }
+}
@@ -18,6 +18,12 @@ pub enum HvtError {
     #[error("Work {0} removed from DLSite")]
     RemovedWork(RJCode),
 
+    #[error("DLSite anti-bot challenge: {0}")]
+    Challenge(String),
+
+    #[error("Offline: {0}")]
+    Offline(String),
+
     #[error("Folder reading error: {0}")]
     FolderReading(String),
 
@@ -0,0 +1,314 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::database::{custom_circles, custom_tags};
+use crate::database::tables::*;
+use crate::database::{queries, web_queries};
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::run_stats::RunStats;
+use crate::tagger::{self, cover_art, nfo, types::{AgeCategory, TaggerConfig}};
+
+/// Output format for `--export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// DLSite age rating kept by `--filter-rating` in `--list` and `--export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum RatingFilter {
+    AllAges,
+    R15,
+    R18,
+}
+
+impl RatingFilter {
+    /// Whether a stored rating (`rating.rating`) is this one
+    pub fn matches(&self, rating: Option<&str>) -> bool {
+        let category = match self {
+            RatingFilter::AllAges => AgeCategory::AllAge,
+            RatingFilter::R15 => AgeCategory::R15,
+            RatingFilter::R18 => AgeCategory::R18,
+        };
+        rating == Some(category.to_string().as_str())
+    }
+}
+
+/// One exported work. `circle`/`tags`/`cvs` are the merged display values (custom mappings
+/// applied, exactly what the tagger writes); `dlsite_tags`/`dlsite_cvs` are the raw DLSite
+/// values (as are the raw circle names), kept in the JSON export so it can be re-imported with
+/// `--import-data` without baking custom mappings in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportedWork {
+    pub rjcode: String,
+    pub title: String,
+    pub circle: String,
+    pub circle_code: Option<String>,
+    #[serde(default)]
+    pub circle_name_en: Option<String>,
+    #[serde(default)]
+    pub circle_name_jp: Option<String>,
+    pub tags: Vec<String>,
+    pub cvs: Vec<String>,
+    pub release_date: Option<String>,
+    pub rating: Option<String>,
+    pub stars: Option<f32>,
+    pub path: String,
+    #[serde(default)]
+    pub dlsite_tags: Vec<String>,
+    #[serde(default)]
+    pub dlsite_cvs: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Number of DLSite ratings and written reviews
+    #[serde(default)]
+    pub rate_count: Option<u32>,
+    #[serde(default)]
+    pub review_count: Option<u32>,
+    /// DLSite work type (SOU, MOV...), option codes and circle-defined genres
+    #[serde(default)]
+    pub work_type: Option<String>,
+    #[serde(default)]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub custom_genres: Vec<String>,
+}
+
+impl ExportedWork {
+    /// Whether the work has this DLSite work type or option code (`--work-type`, case-insensitive)
+    pub fn has_work_type(&self, code: &str) -> bool {
+        self.work_type.iter().chain(&self.options).any(|c| c.eq_ignore_ascii_case(code))
+    }
+}
+
+/// `--export <json|csv>`: dump every active work (or only `works`, if given) with merged
+/// metadata to `output` (defaults to `hvtag_export.<format>` in the current directory),
+/// keeping only the works with the `rating` age rating and the `work_type` type or option code
+/// if given.
+pub fn run_export_workflow(
+    conn: &Connection,
+    format: ExportFormat,
+    works: &[String],
+    rating: Option<RatingFilter>,
+    work_type: Option<&str>,
+    output: Option<&str>,
+) -> Result<(), HvtError> {
+    let rjcodes: Vec<RJCode> = if works.is_empty() {
+        queries::get_all_works_with_paths(conn)?
+            .into_iter()
+            .map(|(rjcode, _)| rjcode)
+            .collect()
+    } else {
+        works.iter()
+            .map(|w| RJCode::new(w.to_uppercase()))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut exported = Vec::with_capacity(rjcodes.len());
+    for rjcode in &rjcodes {
+        match build_exported_work(conn, rjcode)? {
+            Some(work) if rating.is_some_and(|r| !r.matches(work.rating.as_deref())) => {}
+            Some(work) if work_type.is_some_and(|t| !work.has_work_type(t)) => {}
+            Some(work) => exported.push(work),
+            None => warn!("{} not found in the database, skipping", rjcode),
+        }
+    }
+
+    let content = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&exported)
+            .map_err(|e| HvtError::Parse(format!("Failed to serialize export: {}", e)))?,
+        ExportFormat::Csv => to_csv(&exported),
+    };
+
+    let default_output = format!("hvtag_export.{}", format.extension());
+    let output_path = Path::new(output.unwrap_or(&default_output));
+    std::fs::write(output_path, content)?;
+
+    info!("Exported {} work(s) to {}", exported.len(), output_path.display());
+    Ok(())
+}
+
+/// `--export-nfo`: writes an `album.nfo` into the folder of every active work (or only `works`)
+/// so Jellyfin and Kodi show its circle, CVs, tags, release date, description and cover. With
+/// `organize_by_circle`, the circle folders also get an `artist.nfo`. Values are the merged ones
+/// the tagger writes.
+pub fn run_export_nfo_workflow(conn: &Connection, app_config: &Config, works: &[String], stats: &RunStats) -> Result<(), HvtError> {
+    let selected: Vec<RJCode> = works.iter()
+        .map(|w| RJCode::new(w.to_uppercase()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let targets: Vec<(RJCode, std::path::PathBuf)> = queries::get_all_works_with_paths(conn)?
+        .into_iter()
+        .filter(|(rjcode, _)| selected.is_empty() || selected.contains(rjcode))
+        .collect();
+
+    let tagger_config = TaggerConfig {
+        tag_separator: app_config.tagger.get_separator(),
+        cv_name_style: app_config.tagger.cv_name_style,
+        title_language: app_config.tagger.title_language,
+        ..Default::default()
+    };
+    // Circle folders are the library's direct subfolders
+    let library_path = app_config.import.library_path.as_deref()
+        .filter(|_| app_config.import.organize_by_circle)
+        .map(Path::new);
+
+    let mut written = 0usize;
+    let mut circle_folders: HashSet<std::path::PathBuf> = HashSet::new();
+    for (rjcode, folder_path) in &targets {
+        if !folder_path.is_dir() {
+            warn!("{} not found on disk, skipping", folder_path.display());
+            continue;
+        }
+        let metadata = tagger::fetch_metadata_from_db(conn, rjcode, &tagger_config)?;
+        let description = queries::get_work_description(conn, rjcode)?;
+        let section = queries::get_dlsite_section(conn, rjcode)?;
+        let cover = cover_art::has_cover_art(folder_path).then_some("folder.jpeg");
+        if let Err(e) = nfo::write_album_nfo(folder_path, rjcode, section.as_deref(), &metadata, description.as_deref(), cover, stats) {
+            warn!("Failed to write {} for {}: {}", nfo::ALBUM_NFO_FILE_NAME, rjcode, e);
+            continue;
+        }
+        written += 1;
+
+        let circle_folder = folder_path.parent()
+            .filter(|parent| library_path.is_some_and(|lib| parent.starts_with(lib) && *parent != lib));
+        if let Some(circle_folder) = circle_folder {
+            if circle_folders.insert(circle_folder.to_path_buf()) {
+                let rgcode = queries::get_circle_code_for_work(conn, rjcode)?;
+                if let Err(e) = nfo::write_artist_nfo(circle_folder, &metadata.album_artist, rgcode.as_deref(), stats) {
+                    warn!("Failed to write {} in {}: {}", nfo::ARTIST_NFO_FILE_NAME, circle_folder.display(), e);
+                }
+            }
+        }
+    }
+
+    info!(
+        "Wrote {} for {} work(s) and {} for {} circle folder(s)",
+        nfo::ALBUM_NFO_FILE_NAME, written, nfo::ARTIST_NFO_FILE_NAME, circle_folders.len()
+    );
+    Ok(())
+}
+
+/// Builds the export record of a single work, or `None` if it isn't in the database. Also used
+/// by `--import-data` to read works out of another hvtag database.
+pub fn build_exported_work(conn: &Connection, rjcode: &RJCode) -> Result<Option<ExportedWork>, HvtError> {
+    let Some(detail) = web_queries::get_work_detail(conn, rjcode)? else {
+        return Ok(None);
+    };
+
+    let (circle_name_en, circle_name_jp) = match &detail.circle_rgcode {
+        Some(rgcode) => {
+            let (_, _, name_en, name_jp) = custom_circles::get_circle_info(conn, rgcode)?;
+            (Some(name_en), Some(name_jp))
+        }
+        None => (None, None),
+    };
+    let reviews = queries::get_review_counts(conn, rjcode)?;
+    let (work_type, options, custom_genres) = queries::get_work_type(conn, rjcode)?.unwrap_or_default();
+
+    Ok(Some(ExportedWork {
+        rjcode: detail.rjcode,
+        title: detail.name,
+        circle: detail.circle_name,
+        circle_code: detail.circle_rgcode,
+        circle_name_en,
+        circle_name_jp,
+        tags: detail.tags,
+        cvs: detail.cvs,
+        release_date: detail.release_date,
+        rating: detail.rating,
+        stars: detail.stars,
+        path: detail.folder_path,
+        dlsite_tags: custom_tags::get_dlsite_tags_for_work(conn, rjcode)?,
+        dlsite_cvs: get_dlsite_cvs_for_work(conn, rjcode)?,
+        description: detail.description,
+        rate_count: reviews.map(|r| r.rate_count),
+        review_count: reviews.map(|r| r.review_count),
+        work_type,
+        options,
+        custom_genres,
+    }))
+}
+
+/// Raw DLSite CV names (`cvs.name_jp`) for a work, without custom renames applied.
+fn get_dlsite_cvs_for_work(conn: &Connection, rjcode: &RJCode) -> Result<Vec<String>, HvtError> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT cv.name_jp FROM {DB_CVS_NAME} cv
+         JOIN {DB_LKP_WORK_CVS_NAME} lwcv ON lwcv.cv_id = cv.cv_id
+         WHERE lwcv.fld_id = (SELECT fld_id FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1)
+         ORDER BY cv.name_jp"
+    ))?;
+    let cvs: Vec<String> = stmt
+        .query_map(params![rjcode], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(cvs)
+}
+
+/// Spreadsheet-friendly CSV: one row per work, list columns joined with "; ". Raw DLSite
+/// columns and the (multi-line) description are left out (JSON only).
+fn to_csv(works: &[ExportedWork]) -> String {
+    let mut out = String::from("rjcode,title,circle,circle_code,tags,cvs,release_date,rating,stars,path\n");
+    for w in works {
+        let fields = [
+            w.rjcode.clone(),
+            w.title.clone(),
+            w.circle.clone(),
+            w.circle_code.clone().unwrap_or_default(),
+            w.tags.join("; "),
+            w.cvs.join("; "),
+            w.release_date.clone().unwrap_or_default(),
+            w.rating.clone().unwrap_or_default(),
+            w.stars.map(|s| s.to_string()).unwrap_or_default(),
+            w.path.clone(),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&line.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+/// Quotes a CSV field (RFC 4180) when it contains a comma, quote or line break.
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("癒しボイス"), "癒しボイス");
+        assert_eq!(csv_field("Tag A; Tag B"), "Tag A; Tag B");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_rating_filter_matches_stored_rating() {
+        assert!(RatingFilter::AllAges.matches(Some("All Ages")));
+        assert!(RatingFilter::R18.matches(Some("R18")));
+        assert!(!RatingFilter::R18.matches(Some("R15")));
+        assert!(!RatingFilter::AllAges.matches(None));
+    }
+}
@@ -0,0 +1,69 @@
+use rusqlite::Connection;
+use tracing::info;
+
+use crate::database::queries::{self, WorkError};
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// `--errors [--category <category>] [--unresolved]`: lists the DLSite fetch errors recorded in
+/// `dlsite_errors`, grouped per work, with when they last happened, how many times they were hit
+/// again and whether they were resolved. Scoped to active (non-trashed) works.
+pub fn run_errors_workflow(conn: &Connection, category: Option<&str>, unresolved_only: bool) -> Result<(), HvtError> {
+    let errors = queries::get_errors(conn, category, unresolved_only)?;
+    if errors.is_empty() {
+        println!("No {}error recorded", if unresolved_only { "unresolved " } else { "" });
+        return Ok(());
+    }
+
+    let mut works = 0;
+    let mut current: Option<&str> = None;
+    for error in &errors {
+        if current != Some(error.rjcode.as_str()) {
+            let count = errors.iter().filter(|e| e.rjcode == error.rjcode).count();
+            println!("\n{} ({} error(s))", error.rjcode, count);
+            current = Some(error.rjcode.as_str());
+            works += 1;
+        }
+        println!("  {}", format_error(error));
+    }
+
+    let unresolved = errors.iter().filter(|e| !e.is_resolved).count();
+    println!("\n{} error(s) on {} work(s), {} unresolved", errors.len(), works, unresolved);
+    Ok(())
+}
+
+fn format_error(error: &WorkError) -> String {
+    let mut line = format!(
+        "{}  {}",
+        error.error_timestamp.as_deref().unwrap_or("(no date)"),
+        error.error_type.as_deref().unwrap_or("unknown"),
+    );
+    if let Some(category) = &error.error_category {
+        line.push_str(&format!(" [{}]", category));
+    }
+    if error.retry_count > 0 {
+        line.push_str(&format!("  retries: {}", error.retry_count));
+    }
+    if error.is_resolved {
+        line.push_str("  resolved");
+        if let Some(date) = &error.resolved_date {
+            line.push_str(&format!(" {}", date));
+        }
+    }
+    if let Some(details) = error.error_details.as_deref().and_then(|d| d.lines().next()) {
+        line.push_str(&format!("  ({})", details));
+    }
+    line
+}
+
+/// `--resolve-errors <rjcode>`: marks every unresolved error of the work resolved, e.g. once the
+/// work was re-fetched by hand or its removal from DLSite acknowledged.
+pub fn run_resolve_errors_workflow(conn: &Connection, rjcode: &str) -> Result<(), HvtError> {
+    let rjcode = RJCode::new(rjcode.to_uppercase())?;
+    if !queries::rjcode_exists(conn, &rjcode)? {
+        return Err(HvtError::Generic(format!("{} not found in the database", rjcode)));
+    }
+    let resolved = queries::resolve_errors(conn, &rjcode)?;
+    info!("{}: {} error(s) marked resolved", rjcode, resolved);
+    Ok(())
+}
@@ -1,46 +1,467 @@
+use glob::Pattern;
 use rusqlite::Connection;
+use tracing::{debug, info, warn};
 
-use crate::{database::queries, errors::HvtError, folders::types::ManagedFolder};
+use crate::{config::Config, database::{custom_circles, queries}, errors::HvtError, folders::types::{ManagedFolder, RJCode}, sanitize::{self, NameRules}};
 use std::fs;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub mod types;
 
+/// Dossiers inspectés en parallèle : sur un partage réseau le coût est la latence de chaque
+/// `read_dir`, pas le CPU
+const SCAN_THREADS: usize = 8;
+
+/// Fichier de motifs d'exclusion lu à la racine de chaque dossier parcouru
+pub const IGNORE_FILE_NAME: &str = ".hvtagignore";
+
+/// Réglages du parcours des dossiers (`[import]` et `[video]`)
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Accepte aussi les dossiers RJ/VJ ne contenant que des vidéos
+    pub accept_video_only: bool,
+    /// Nombre de niveaux parcourus (1 = enfants directs uniquement)
+    pub max_depth: usize,
+    /// Motifs glob des dossiers ignorés (`[import] exclude`)
+    pub exclude: Vec<Pattern>,
+}
+
+impl ScanOptions {
+    pub fn from_config(config: &Config) -> Result<Self, HvtError> {
+        Ok(ScanOptions {
+            accept_video_only: config.video.video_only_is_valid,
+            max_depth: config.import.scan_depth(),
+            exclude: parse_exclude_patterns(config.import.exclude.iter().map(String::as_str))?,
+        })
+    }
+}
+
+/// Compile des motifs d'exclusion : une ligne vide ou commençant par `#` est ignorée, un `/`
+/// final est retiré (seuls des dossiers sont comparés)
+fn parse_exclude_patterns<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Vec<Pattern>, HvtError> {
+    lines
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let line = line.trim_end_matches('/');
+            Pattern::new(line).map_err(|e| HvtError::Parse(format!("Invalid exclude pattern '{}': {}", line, e)))
+        })
+        .collect()
+}
+
+/// Un dossier est exclu si un motif correspond à son nom ou à son chemin relatif à la racine
+/// parcourue (`Cercle/RJ01234567`)
+fn is_excluded(exclude: &[Pattern], name: &str, relative_path: &str) -> bool {
+    exclude.iter().any(|p| p.matches(name) || p.matches(relative_path))
+}
+
 /// Renvoie la liste des dossier dans le path indiqué
-pub fn get_list_of_folders(base_path: &str) -> Result<Vec<ManagedFolder>, HvtError> {
-    let mut directories = Vec::new();
+/// Les dossiers RJ/VJ et les dossiers cachés (`.trash`...) ne sont jamais parcourus, les dossiers
+/// exclus (config ou `.hvtagignore` de la racine) sont ignorés avec tout leur contenu
+pub fn get_list_of_folders(base_path: &str, options: &ScanOptions) -> Result<Vec<ManagedFolder>, HvtError> {
+    let root = Path::new(base_path);
+    let exclude = exclude_patterns_for_root(root, options)?;
+
+    let mut res = Vec::new();
+    collect_folders(root, root, options, &exclude, options.max_depth, &mut res)?;
+    Ok(res)
+}
+
+/// Enfants directs de `base_path` exclus du parcours, que la préparation du dossier source
+/// (renommage, aplatissement) doit aussi laisser intacts
+pub fn excluded_folders(base_path: &str, options: &ScanOptions) -> Result<Vec<PathBuf>, HvtError> {
+    let root = Path::new(base_path);
+    let exclude = exclude_patterns_for_root(root, options)?;
+    if exclude.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut res = Vec::new();
+    for entry in fs::read_dir(root)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.path().is_dir() && is_excluded(&exclude, &name, &name) {
+            res.push(entry.path());
+        }
+    }
+    Ok(res)
+}
+
+/// Motifs de la config complétés par ceux du `.hvtagignore` de la racine
+fn exclude_patterns_for_root(root: &Path, options: &ScanOptions) -> Result<Vec<Pattern>, HvtError> {
+    let mut exclude = options.exclude.clone();
+    if let Ok(content) = fs::read_to_string(root.join(IGNORE_FILE_NAME)) {
+        exclude.extend(parse_exclude_patterns(content.lines())?);
+    }
+    Ok(exclude)
+}
 
-    let entries = fs::read_dir(base_path)
-        .map_err(|_| HvtError::FolderReading(base_path.to_string()))?;
+fn collect_folders(
+    root: &Path,
+    dir: &Path,
+    options: &ScanOptions,
+    exclude: &[Pattern],
+    depth_left: usize,
+    found: &mut Vec<ManagedFolder>,
+) -> Result<(), HvtError> {
+    let entries = fs::read_dir(dir)
+        .map_err(|_| HvtError::FolderReading(dir.display().to_string()))?;
 
+    let mut subdirs = Vec::new();
     for entry in entries {
         let entry = entry
             .map_err(|_| HvtError::FolderReading("<unknown>".to_string()))?;
         let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
 
-        if path.is_dir() {
-            directories.push(
-                ManagedFolder::new(
-                    path
-                    .to_string_lossy()
-                    .to_string()
-                )
-            );
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative_path = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        if is_excluded(exclude, &name, &relative_path) {
+            debug!("Excluded from scan: {}", path.display());
+            continue;
         }
+        subdirs.push(path);
     }
 
-    let res = directories
-        .into_iter()
-        .filter(|x| x.is_valid)
-        .collect();
-    Ok(res)
+    for folder in inspect_folders(&subdirs) {
+        if folder.is_valid || (options.accept_video_only && folder.is_video_only_work()) {
+            found.push(folder);
+        } else if depth_left > 1 && is_container_folder(Path::new(&folder.path)) {
+            collect_folders(root, Path::new(&folder.path), options, exclude, depth_left - 1, found)?;
+        }
+    }
+    Ok(())
+}
+
+/// `ManagedFolder::new` sur chaque dossier, réparti sur `SCAN_THREADS` threads. L'ordre des
+/// dossiers est conservé
+fn inspect_folders(paths: &[PathBuf]) -> Vec<ManagedFolder> {
+    let next = AtomicUsize::new(0);
+    let mut inspected: Vec<(usize, ManagedFolder)> = std::thread::scope(|s| {
+        let workers: Vec<_> = (0..SCAN_THREADS.min(paths.len()))
+            .map(|_| s.spawn(|| {
+                let mut done = Vec::new();
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(path) = paths.get(i) else { break };
+                    done.push((i, ManagedFolder::new(path.clone())));
+                }
+                done
+            }))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("folder inspection thread panicked"))
+            .collect()
+    });
+    inspected.sort_by_key(|(i, _)| *i);
+    inspected.into_iter().map(|(_, folder)| folder).collect()
+}
+
+/// Dossier de rangement pouvant contenir des œuvres (cercle, année...) : ni une œuvre, ni caché
+fn is_container_folder(path: &Path) -> bool {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    !name.is_empty() && !name.starts_with('.') && !name.starts_with("RJ") && !name.starts_with("VJ")
 }
 
 /// Enregistre les dossiers dans la db
+/// Un code RJ déjà connu trouvé ailleurs que son ancien chemin (dossier déplacé à la main) met
+/// à jour le chemin, sauf si l'ancien dossier existe encore (doublon, laissé tel quel)
 pub fn register_folders(conn: &Connection, folder_list: Vec<ManagedFolder>) -> Result<(), HvtError> {
+    // Une seule transaction pour tout le lot : un commit par dossier est très lent sur un
+    // partage réseau, et une erreur ne laisse pas un enregistrement partiel
+    let tx = conn.unchecked_transaction()?;
     for fld in &folder_list {
-        queries::insert_managed_folder(conn, fld)?;
+        if queries::insert_managed_folder(&tx, fld)? > 0 {
+            continue;
+        }
+        let Some(old_path) = queries::get_work_path(&tx, &fld.rjcode)? else {
+            continue;
+        };
+        if old_path == fld.path {
+            continue;
+        }
+        if !old_path.as_os_str().is_empty() && old_path.exists() {
+            warn!("{} found in {} but {} still exists, keeping the registered path",
+                  fld.rjcode, fld.path.display(), old_path.display());
+            continue;
+        }
+        queries::record_folder_move(&tx, &fld.rjcode, &old_path, &fld.path)?;
+        info!("{} moved: {} -> {}", fld.rjcode, old_path.display(), fld.path.display());
     }
+    tx.commit()?;
 
     Ok(())
 }
 
+
+/// Dossier parent d'une œuvre dans la bibliothèque : `library_path` lui-même, ou
+/// `library_path/{cercle}` si `organize_by_circle` est activé (nom d'affichage du cercle,
+/// préférences personnalisées comprises, mis en forme selon `rules`)
+pub fn library_parent_dir(
+    conn: &Connection,
+    library_path: &Path,
+    rjcode: &RJCode,
+    organize_by_circle: bool,
+    rules: NameRules,
+) -> Result<PathBuf, HvtError> {
+    if !organize_by_circle {
+        return Ok(library_path.to_path_buf());
+    }
+    let circle_name = custom_circles::get_merged_circle_name_for_work(conn, rjcode)?;
+    Ok(library_path.join(rules.apply(&circle_name)))
+}
+
+/// Chemin relatif d'une œuvre dans la bibliothèque d'après `[import] folder_template` : chaque
+/// `{clé}` de `values` est remplacée par sa valeur nettoyée, `/` sépare les sous-dossiers et les
+/// segments restés vides sont ignorés. Un nom de dossier plus long que `rules.max_len` caractères
+/// raccourcit d'abord sa plus longue valeur (le titre, en général), pour garder le `{rjcode}`
+pub fn render_folder_template(template: &str, values: &[(&str, &str)], rules: NameRules) -> PathBuf {
+    let values: Vec<(&str, String)> = values
+        .iter()
+        .map(|(key, value)| (*key, if value.trim().is_empty() { String::new() } else { rules.clean(value) }))
+        .collect();
+    template
+        .split('/')
+        .map(|segment| render_template_segment(segment, &values, rules.max_len))
+        .filter(|segment| !segment.trim().is_empty())
+        .map(|segment| rules.apply(&segment))
+        .collect()
+}
+
+/// Nom du dossier d'une œuvre mis en forme selon `rules` (voir `NameRules::clean`) puis raccourci
+/// à `rules.max_len` caractères en gardant son code RJ : le texte qui l'entoure est coupé, celui
+/// d'après en premier. Un nom qui n'est pas de l'UTF-8 est gardé tel quel.
+pub fn work_folder_name(name: &OsStr, rjcode: &RJCode, rules: NameRules) -> OsString {
+    let Some(name) = name.to_str() else {
+        return name.to_os_string();
+    };
+    let cleaned = rules.clean(name);
+    let name = cleaned.as_str();
+    let max_name_len = rules.max_len;
+    let short = sanitize::truncate(name, max_name_len);
+    let rjcode = rjcode.as_str();
+    let Some(at) = name.find(rjcode).filter(|_| short != name && !short.contains(rjcode)) else {
+        return short.into();
+    };
+    let (before, after) = (&name[..at], &name[at + rjcode.len()..]);
+    let mut budget = short.chars().count().saturating_sub(rjcode.len());
+    loop {
+        let mut kept_before = before.chars().take(budget).collect::<String>().trim_end().to_string();
+        if !kept_before.is_empty() && before.ends_with(char::is_whitespace) {
+            kept_before.push(' ');
+        }
+        let kept_after: String = after.chars().take(budget.saturating_sub(kept_before.chars().count())).collect();
+        let candidate = format!("{}{}{}", kept_before, rjcode, kept_after.trim_end_matches(|c: char| c == '.' || c.is_whitespace()));
+        // Les caractères gardés peuvent peser plus d'octets que ceux de `short`
+        if budget == 0 || sanitize::truncate(&candidate, max_name_len) == candidate {
+            return candidate.into();
+        }
+        budget -= 1;
+    }
+}
+
+fn render_template_segment(segment: &str, values: &[(&str, String)], max_name_len: usize) -> String {
+    let render = |values: &[(&str, String)]| {
+        values.iter().fold(segment.to_string(), |rendered, (key, value)| rendered.replace(&format!("{{{}}}", key), value))
+    };
+    let rendered = render(values);
+    let overflow = rendered.chars().count() - sanitize::truncate(&rendered, max_name_len).chars().count();
+    if overflow == 0 {
+        return rendered;
+    }
+    let mut values = values.to_vec();
+    let longest = values
+        .iter_mut()
+        .filter(|(key, _)| segment.contains(&format!("{{{}}}", key)))
+        .max_by_key(|(_, value)| value.chars().count());
+    if let Some((_, value)) = longest {
+        let keep = value.chars().count().saturating_sub(overflow).max(1);
+        *value = sanitize::truncate(value, keep);
+    }
+    render(&values)
+}
+
+/// Vérifie qu'un modèle garde `{rjcode}` dans le nom du dossier de l'œuvre (son dernier
+/// segment), sans quoi elle ne serait plus reconnue au prochain parcours
+pub fn validate_folder_template(template: &str) -> Result<(), HvtError> {
+    let last_segment = template.rsplit('/').find(|s| !s.trim().is_empty()).unwrap_or("");
+    if last_segment.contains("{rjcode}") {
+        Ok(())
+    } else {
+        Err(HvtError::Generic(format!(
+            "folder_template \"{}\" must contain {{rjcode}} in its last folder name", template
+        )))
+    }
+}
+
+/// Chemin étendu Windows d'un chemin absolu (`\\?\C:\...`, `\\?\UNC\serveur\partage\...`), pour
+/// les programmes externes (ffmpeg) au-delà de MAX_PATH (260 caractères) : std::fs l'ajoute déjà
+/// de lui-même. Inchangé hors de Windows, ou pour un chemin relatif
+pub fn long_path(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        if let Some(extended) = path.to_str().and_then(extended_length_path) {
+            return PathBuf::from(extended);
+        }
+    }
+    path.to_path_buf()
+}
+
+fn extended_length_path(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") || path.starts_with(r"\\.\") {
+        return None;
+    }
+    let path = path.replace('/', "\\");
+    let (prefix, rest, root_parts) = if let Some(unc) = path.strip_prefix(r"\\") {
+        (r"\\?\UNC\", unc, 2)
+    } else if path.as_bytes().first().is_some_and(u8::is_ascii_alphabetic) && path[1..].starts_with(":\\") {
+        (r"\\?\", path.as_str(), 1)
+    } else {
+        return None;
+    };
+    // Windows ne normalise plus un chemin `\\?\` : `.` et `..` sont résolus ici
+    let mut parts: Vec<&str> = Vec::new();
+    for part in rest.split('\\') {
+        match part {
+            "" | "." => {}
+            ".." if parts.len() > root_parts => {
+                parts.pop();
+            }
+            ".." => {}
+            _ => parts.push(part),
+        }
+    }
+    let separator = if parts.len() <= root_parts { "\\" } else { "" };
+    Some(format!("{}{}{}", prefix, parts.join("\\"), separator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extended_length_path() {
+        assert_eq!(extended_length_path(r"C:\Library/サークル\.\RJ01234567"), Some(r"\\?\C:\Library\サークル\RJ01234567".to_string()));
+        assert_eq!(extended_length_path(r"\\nas\music\a\..\RJ01234567"), Some(r"\\?\UNC\nas\music\RJ01234567".to_string()));
+        assert_eq!(extended_length_path(r"C:\.."), Some(r"\\?\C:\".to_string()));
+        assert_eq!(extended_length_path(r"\\?\C:\Library"), None);
+        assert_eq!(extended_length_path(r"Library\RJ01234567"), None);
+    }
+
+    #[test]
+    fn test_render_folder_template() {
+        let values = [("rjcode", "RJ01234567"), ("title", "作品/第1巻"), ("circle", "サークル"), ("circle_code", ""), ("year", "2023")];
+        assert_eq!(
+            render_folder_template("{circle}/{year}/{rjcode} {title}", &values, NameRules::default()),
+            PathBuf::from("サークル/2023/RJ01234567 作品_第1巻")
+        );
+        // An empty value drops its folder level and the spaces around it
+        assert_eq!(render_folder_template("{circle_code}/{rjcode} {circle_code}", &values, NameRules::default()), PathBuf::from("RJ01234567"));
+        // A name too long shortens the title, keeping the RJ code
+        assert_eq!(
+            render_folder_template("{title} [{rjcode}]", &[("rjcode", "RJ01234567"), ("title", "とても長い作品のタイトル")], NameRules { max_len: 18, transliterate: false }),
+            PathBuf::from("とても長い [RJ01234567]")
+        );
+        let romaji = NameRules { max_len: 0, transliterate: true };
+        assert_eq!(render_folder_template("{circle}/{rjcode} {title}", &values, romaji), PathBuf::from("sakuru/RJ01234567 作品_第1巻"));
+        assert!(validate_folder_template("{circle}/{rjcode} {title}").is_ok());
+        assert!(validate_folder_template("{rjcode}/{title}").is_err());
+    }
+
+    #[test]
+    fn test_work_folder_name() {
+        let rjcode = RJCode::new("RJ01234567".to_string()).unwrap();
+        let shorten = |name: &str, max_len| work_folder_name(OsStr::new(name), &rjcode, NameRules { max_len, transliterate: false });
+        assert_eq!(shorten("RJ01234567 とても長い作品のタイトル", 15), "RJ01234567 とても長");
+        // The RJ code at the end is kept, the title before it is cut
+        assert_eq!(shorten("[サークル] とても長い作品 RJ01234567", 16), "[サークル RJ01234567");
+        assert_eq!(shorten("RJ01234567", 5), "RJ01234567");
+        assert_eq!(shorten("RJ01234567 カ\u{3099}イト\u{200B}", 0), "RJ01234567 ガイト");
+        let romaji = NameRules { max_len: 0, transliterate: true };
+        assert_eq!(work_folder_name(OsStr::new("RJ01234567 ボイス"), &rjcode, romaji), "RJ01234567 boisu");
+    }
+
+    #[test]
+    fn test_is_container_folder() {
+        assert!(is_container_folder(Path::new("/library/サークル名")));
+        assert!(is_container_folder(Path::new("/library/2023")));
+        assert!(!is_container_folder(Path::new("/library/RJ01234567")));
+        assert!(!is_container_folder(Path::new("/library/.trash")));
+    }
+
+    #[test]
+    fn test_exclude_patterns() {
+        let exclude = parse_exclude_patterns(
+            ["# comment", "", "_incomplete", "backup/", "RJ0123*", "Circle/RJ09999999"].into_iter(),
+        )
+        .unwrap();
+        assert_eq!(exclude.len(), 4);
+        assert!(is_excluded(&exclude, "_incomplete", "_incomplete"));
+        assert!(is_excluded(&exclude, "backup", "2023/backup"));
+        assert!(is_excluded(&exclude, "RJ01234567", "RJ01234567"));
+        assert!(is_excluded(&exclude, "RJ09999999", "Circle/RJ09999999"));
+        assert!(!is_excluded(&exclude, "RJ09999999", "Other/RJ09999999"));
+        assert!(!is_excluded(&exclude, "RJ07654321", "RJ07654321"));
+    }
+
+    #[test]
+    fn test_inspect_folders_keeps_order() {
+        let root = std::env::temp_dir().join(format!("hvtag-test-inspect-{}", std::process::id()));
+        let paths: Vec<PathBuf> = (0..20).map(|i| root.join(format!("RJ{:08}", i))).collect();
+        for path in &paths {
+            fs::create_dir_all(path).unwrap();
+        }
+
+        let inspected = inspect_folders(&paths);
+        let _ = fs::remove_dir_all(&root);
+        let inspected_paths: Vec<PathBuf> = inspected.into_iter().map(|f| f.path).collect();
+        assert_eq!(inspected_paths, paths);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_inspect_folders_with_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+        let root = std::env::temp_dir().join(format!("hvtag-test-non-utf8-{}", std::process::id()));
+        // "音声" in Shift-JIS, in the folder name and in a track name
+        let work = root.join(std::ffi::OsStr::from_bytes(b"RJ01234567 \x89\xb9\x90\xba"));
+        fs::create_dir_all(&work).unwrap();
+        fs::write(work.join(std::ffi::OsStr::from_bytes(b"\x89\xb9\x90\xba.mp3")), b"").unwrap();
+
+        let inspected = inspect_folders(std::slice::from_ref(&work));
+        let _ = fs::remove_dir_all(&root);
+        assert!(inspected[0].is_valid);
+        assert_eq!(inspected[0].path, work);
+        assert_eq!(inspected[0].files.len(), 1);
+    }
+
+    #[test]
+    fn test_register_folders_follows_moved_folder() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        let root = std::env::temp_dir().join(format!("hvtag-test-move-{}", std::process::id()));
+        let old_dir = root.join("old").join("RJ01234567");
+        let new_dir = root.join("new").join("RJ01234567");
+
+        fs::create_dir_all(&old_dir).unwrap();
+        register_folders(&conn, vec![ManagedFolder::new(old_dir.clone())]).unwrap();
+        fs::create_dir_all(new_dir.parent().unwrap()).unwrap();
+        fs::rename(&old_dir, &new_dir).unwrap();
+        register_folders(&conn, vec![ManagedFolder::new(new_dir.clone())]).unwrap();
+        let _ = fs::remove_dir_all(&root);
+
+        let rjcode = RJCode::new("RJ01234567".to_string()).unwrap();
+        assert_eq!(queries::get_work_path(&conn, &rjcode).unwrap(), Some(new_dir.clone()));
+        let (old_value, new_value): (String, String) = conn
+            .query_row(
+                "SELECT old_value, new_value FROM metadata_history WHERE metadata_type = 'folder_path'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((old_value, new_value), (old_dir.to_string_lossy().to_string(), new_dir.to_string_lossy().to_string()));
+    }
+}
@@ -1,6 +1,7 @@
-use std::{fmt::Display, fs::{read_dir, DirEntry}, path::Path};
-use tracing::{warn, error};
+use std::{ffi::OsString, fmt::Display, fs::{read_dir, DirEntry}, path::PathBuf};
+use tracing::error;
 use crate::errors::HvtError;
+use crate::tagger::types::{is_audio_extension, is_video_extension};
 
 // Newtype pattern for RJCode with validation
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -19,9 +20,11 @@ impl RJCode {
         &self.0
     }
 
-    /// Returns the DLsite site section for this code ("maniax" for RJ, "pro" for VJ).
-    pub fn site_section(&self) -> &'static str {
-        if self.0.starts_with("VJ") { "pro" } else { "maniax" }
+    /// Product page of the work on DLsite, under `section` (where it was found, see
+    /// `queries::get_dlsite_section`), else "maniax" for RJ, "pro" for VJ.
+    pub fn dlsite_url(&self, section: Option<&str>) -> String {
+        let section = section.unwrap_or_else(|| crate::dlsite::sections::default_section(self));
+        format!("https://www.dlsite.com/{}/work/=/product_id/{}.html", section, self.0)
     }
 
     pub(crate) fn from_string_unchecked(s: String) -> Self {
@@ -81,46 +84,46 @@ impl rusqlite::types::FromSql for RGCode {
 
 #[derive(Debug, Clone)]
 pub struct ManagedFile {
-    filename: String,
+    /// File name as stored on disk, not necessarily UTF-8
+    filename: OsString,
     extension: String,
-    path: String,
+    path: PathBuf,
 }
 
 impl ManagedFile {
-    pub fn from_direntry(e: DirEntry) -> Result<Self, HvtError> {
-        let filename = e.file_name()
-            .into_string()
-            .map_err(|_| HvtError::Parse("Invalid filename encoding".to_string()))?;
-
-        let extension = filename
-            .split('.')
-            .last()
-            .unwrap_or("unknown")
-            .to_string();
-
-        Ok(ManagedFile {
-            filename,
+    pub fn from_direntry(e: DirEntry) -> Self {
+        let path = e.path();
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        ManagedFile {
+            filename: e.file_name(),
             extension,
-            path: e.path().display().to_string()
-        })
+            path,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub struct ManagedFolder {
     pub is_valid: bool,
-    pub is_tagged: bool,
     pub has_cover: bool,
+    /// Whether the folder (or a direct subdirectory) contains video files (bonus mp4s etc.)
+    pub has_video_files: bool,
     pub rjcode: RJCode,
-    pub path: String,
+    pub path: PathBuf,
     pub files: Vec<ManagedFile>,
 }
 
 impl ManagedFolder {
-    pub fn new(path: String) -> Self {
-        let p = Path::new(&path);
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let p = path.as_path();
         let mut files = vec![];
         let mut has_audio_files = false;
+        let mut has_video_files = false;
 
         // Scan immediate directory for files
         match read_dir(p) {
@@ -129,25 +132,27 @@ impl ManagedFolder {
                     if let Ok(en) = e {
                         let entry_path = en.path();
                         if entry_path.is_file() {
-                            match ManagedFile::from_direntry(en) {
-                                Ok(file) => {
-                                    // Check if it's an audio file
-                                    if matches!(file.extension.as_str(), "mp3" | "flac" | "wav" | "ogg") {
-                                        has_audio_files = true;
-                                    }
-                                    files.push(file);
-                                }
-                                Err(e) => warn!("Could not process file: {}", e),
+                            let file = ManagedFile::from_direntry(en);
+                            // Check if it's an audio file
+                            if is_audio_extension(&file.extension) {
+                                has_audio_files = true;
+                            }
+                            if is_video_extension(&file.extension) {
+                                has_video_files = true;
                             }
+                            files.push(file);
                         } else if entry_path.is_dir() {
                             // Check subdirectories for audio files
                             if let Ok(sub_entries) = read_dir(&entry_path) {
                                 for sub_e in sub_entries.flatten() {
                                     if sub_e.path().is_file() {
                                         if let Some(ext) = sub_e.path().extension() {
-                                            if matches!(ext.to_str().unwrap_or(""), "mp3" | "flac" | "wav" | "ogg") {
+                                            if is_audio_extension(ext.to_str().unwrap_or("")) {
                                                 has_audio_files = true;
                                             }
+                                            if is_video_extension(ext.to_str().unwrap_or("")) {
+                                                has_video_files = true;
+                                            }
                                         }
                                     }
                                 }
@@ -163,19 +168,18 @@ impl ManagedFolder {
                     is_valid: false,
                     path: path.clone(),
                     files: vec![],
-                    is_tagged: false,
                     has_cover: false,
+                    has_video_files: false,
                     rjcode: RJCode::from_string_unchecked(String::new()),
                 };
             }
         };
 
-        let is_tagged = files.iter().any(|x| x.extension == "tagged");
         let has_cover = files.iter().any(|x| x.filename == "folder.jpeg");
 
+        // The RJ code part of the name is ASCII, whatever the encoding of the rest
         let rjcode_str = p.file_name()
-            .and_then(|n| n.to_str())
-            .map(|s| s.to_string())
+            .map(|n| n.to_string_lossy().to_string())
             .unwrap_or_else(|| String::from(""));
 
         // Folder is valid if it has RJ/VJ prefix and contains audio files (even in subdirectories)
@@ -183,11 +187,19 @@ impl ManagedFolder {
 
         ManagedFolder {
             is_valid,
-            path: path.to_string(),
+            path: path.clone(),
             files,
-            is_tagged,
             has_cover,
+            has_video_files,
             rjcode: RJCode::from_string_unchecked(rjcode_str),
         }
     }
+
+    /// Whether this folder is a video-only work: RJ/VJ-prefixed name and video files but no
+    /// audio. Only accepted as a work when `[video] video_only_is_valid` is enabled.
+    pub fn is_video_only_work(&self) -> bool {
+        !self.is_valid
+            && self.has_video_files
+            && (self.rjcode.as_str().starts_with("RJ") || self.rjcode.as_str().starts_with("VJ"))
+    }
 }
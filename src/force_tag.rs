@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::database::actor::DbHandle;
+use crate::database::queries;
+use crate::dlsite::MetadataClient;
+use crate::folders::types::{ManagedFolder, RJCode};
+use crate::run_stats::{RunStats, WorkOutcome};
+use crate::tagger::{self, types::TaggerConfig};
+
+/// `--force-tag [RJCODE...] [--circle X] [--all]`: re-tags library works from what the database
+/// has, without fetching DLSite (e.g. after changing the tag separator). The tagged state of the
+/// selected works is cleared first (`is_tagged`, `tag_date`), so a work that fails stays pending
+/// for `--full-retag`. Files are not converted and covers are not downloaded.
+pub async fn run_force_tag_workflow(
+    db: &Connection,
+    app_config: &Config,
+    rjcodes: &[String],
+    circle: Option<&str>,
+    all: bool,
+    stats: &Arc<RunStats>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if rjcodes.is_empty() && circle.is_none() && !all {
+        return Err("--force-tag needs the works to re-tag: RJ codes, --circle <name> or --all".into());
+    }
+    let wanted = rjcodes
+        .iter()
+        .map(|code| RJCode::new(code.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut works = Vec::new();
+    for (rjcode, path) in queries::get_all_works_with_paths(db)? {
+        if !all && !wanted.is_empty() && !wanted.contains(&rjcode) {
+            continue;
+        }
+        if let Some(circle) = circle {
+            if !crate::work_matches_circle(db, &rjcode, circle)? {
+                continue;
+            }
+        }
+        works.push((rjcode, path));
+    }
+    for code in &wanted {
+        if !works.iter().any(|(rjcode, _)| rjcode == code) {
+            warn!("{} is not in the library, skipped", code);
+        }
+    }
+    if works.is_empty() {
+        info!("No work to re-tag");
+        return Ok(());
+    }
+
+    info!("=== FORCE TAG: {} work(s) ===", works.len());
+    let before = crate::backup_before_bulk_run(db, app_config, "force-tag", works.len())?;
+    let tagger_config = TaggerConfig {
+        convert_to_mp3: false,
+        download_cover: false,
+        ..crate::library_tagger_config(app_config, true)?
+    };
+
+    // Covers are not downloaded, but the tagger takes a client
+    let client = MetadataClient::from_config(app_config, stats)?;
+    let db_thread = DbHandle::open_beside(db)?;
+
+    let pb = crate::create_progress_bar(works.len() as u64);
+    let mut tagged = 0usize;
+    let mut failed = 0usize;
+    for (rjcode, path) in &works {
+        pb.set_message(format!("Tagging {}", rjcode));
+        queries::mark_work_for_retagging(db, rjcode)?;
+
+        let folder = ManagedFolder::new(path.clone());
+        match tagger::process_work_folder(db, &folder, &tagger_config, &client, db_thread.as_ref()).await {
+            Ok(()) => {
+                pb.println(format!("{} ✓", rjcode));
+                stats.record_work(db, rjcode, "force-tag", WorkOutcome::Processed, None);
+                tagged += 1;
+            }
+            Err(e) => {
+                warn!("Failed to re-tag {}: {}", rjcode, e);
+                pb.println(format!("{} ✗", rjcode));
+                stats.record_work(db, rjcode, "force-tag", WorkOutcome::Failed, Some(&e.to_string()));
+                failed += 1;
+            }
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    crate::snapshot_after_bulk_run(db, "force-tag", works.len(), before)?;
+    info!("=== FORCE TAG COMPLETE: {} re-tagged | Failed: {} ===", tagged, failed);
+    Ok(())
+}
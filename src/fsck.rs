@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use rusqlite::Connection;
+use tracing::{debug, info, warn};
+
+use crate::database::integrity::{self, IntegrityStatus};
+use crate::database::queries;
+use crate::errors::HvtError;
+use crate::tagger::checksum;
+
+/// `--fsck`: re-verify every library work that has a `checksums.sha256` manifest against it,
+/// reporting files that changed (silent corruption on aging drives) or disappeared. Works checked
+/// less than `interval_days` ago are skipped so the command can run regularly (e.g. from cron)
+/// without re-reading the whole library each time. Results go to `file_integrity`, like --verify.
+pub fn run_fsck_workflow(conn: &Connection, interval_days: u32) -> Result<(), HvtError> {
+    let works = queries::get_all_works_with_paths(conn)?;
+    if works.is_empty() {
+        info!("No works in database");
+        return Ok(());
+    }
+
+    info!("=== FSCK: {} work(s) ===", works.len());
+
+    let pb = crate::create_progress_bar(works.len() as u64);
+    let mut verified_works = 0usize;
+    let mut skipped_recent = 0usize;
+    let mut without_manifest = 0usize;
+    let mut checked_files = 0usize;
+    let mut problems: Vec<String> = Vec::new();
+
+    for (rjcode, folder_path) in &works {
+        pb.set_message(format!("Checking {}", rjcode));
+        pb.inc(1);
+
+        if interval_days > 0 {
+            if let Some(days) = integrity::days_since_last_check(conn, rjcode)? {
+                if days < interval_days as f64 {
+                    debug!("Skipping {}: checked {:.1} day(s) ago", rjcode, days);
+                    skipped_recent += 1;
+                    continue;
+                }
+            }
+        }
+
+        let folder = Path::new(folder_path);
+        let entries = match checksum::read_manifest(folder) {
+            Ok(Some(entries)) => entries,
+            Ok(None) => {
+                without_manifest += 1;
+                continue;
+            }
+            Err(e) => {
+                warn!("Cannot read manifest for {} ({}): {}", rjcode, folder_path.display(), e);
+                pb.println(format!("{} ✗ (manifest unreadable)", rjcode));
+                problems.push(format!("{}: manifest unreadable ({})", rjcode, e));
+                continue;
+            }
+        };
+
+        let mut work_ok = true;
+        for (expected, file_name) in entries {
+            let file = folder.join(&file_name);
+            let (status, details) = if !file.exists() {
+                (IntegrityStatus::Error, Some("file missing".to_string()))
+            } else {
+                match checksum::sha256_file(&file) {
+                    Ok(actual) if actual == expected => (IntegrityStatus::Ok, None),
+                    Ok(actual) => (
+                        IntegrityStatus::Corrupt,
+                        Some(format!("checksum mismatch (manifest {}, now {})", expected, actual)),
+                    ),
+                    Err(e) => (IntegrityStatus::Error, Some(e.to_string())),
+                }
+            };
+
+            integrity::record_file_integrity(
+                conn,
+                rjcode,
+                &file,
+                status,
+                details.as_deref(),
+            )?;
+            checked_files += 1;
+
+            if let Some(details) = details {
+                work_ok = false;
+                problems.push(format!("[{}] {} {}: {}", status.as_str(), rjcode, file_name, details));
+            }
+        }
+
+        verified_works += 1;
+        pb.println(format!("{} {}", rjcode, if work_ok { "✓" } else { "✗" }));
+    }
+
+    pb.finish_and_clear();
+
+    info!("\n=== FSCK COMPLETE ===");
+    info!(
+        "Verified: {} work(s), {} file(s) | Skipped (recent): {} | No manifest: {} | Problems: {}",
+        verified_works, checked_files, skipped_recent, without_manifest, problems.len()
+    );
+    for problem in &problems {
+        warn!("{}", problem);
+    }
+
+    Ok(())
+}
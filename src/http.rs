@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::{Client, IntoUrl, RequestBuilder, Response};
+use tokio::sync::Semaphore;
+
+use crate::config::HttpConfig;
+use crate::errors::HvtError;
+
+/// HTTP client of a workflow (`[http]` settings): DLSite API, work page, circle profile and
+/// cover requests reuse its pooled connections instead of opening new ones for each work.
+/// Built once the VPN is up, so its connections go through the right route. Clones share the
+/// pool and the per-host limits.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client,
+    per_host_concurrency: usize,
+    // Requests in flight per host, limited to `per_host_concurrency`
+    hosts: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl HttpClient {
+    pub fn new(config: &HttpConfig) -> Result<Self, HvtError> {
+        let mut builder = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .cookie_store(true)
+            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .gzip(config.compression)
+            .brotli(config.compression);
+        if !config.http2 {
+            builder = builder.http1_only();
+        }
+        let client = builder.build().map_err(|e| HvtError::Http(format!("Failed to build the HTTP client: {}", e)))?;
+        Ok(HttpClient {
+            client,
+            per_host_concurrency: config.per_host_concurrency,
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Sends a request, waiting first while `per_host_concurrency` requests to the same host are
+    /// waiting for their response headers
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let request = request.build()?;
+        let semaphore = request.url().host_str().and_then(|host| self.host_semaphore(host));
+        let _permit = match &semaphore {
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
+        };
+        self.client.execute(request).await
+    }
+
+    fn host_semaphore(&self, host: &str) -> Option<Arc<Semaphore>> {
+        if self.per_host_concurrency == 0 {
+            return None;
+        }
+        let mut hosts = self.hosts.lock().ok()?;
+        let semaphore = hosts
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.per_host_concurrency)));
+        Some(Arc::clone(semaphore))
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::database::actor::DbHandle;
+use crate::database::library_import;
+use crate::folders::{get_list_of_folders, register_folders, types::RJCode, ScanOptions};
+use crate::run_stats::{RunStats, WorkOutcome};
+use crate::tagger::converter;
+
+/// `--import-library <root>`: first import of an existing collection, in place (folders are not
+/// moved). Every RJ/VJ folder under `root` is registered and queued; each run then fetches
+/// metadata and tags at most `works_per_day` works (minus those already processed today), and
+/// the queue position is kept in the database so the next run continues where this one stopped.
+/// With `keep_running`, waits for the next day instead of exiting until the queue is empty.
+pub async fn run_library_import_workflow(
+    db: &Connection,
+    root: &str,
+    app_config: &Config,
+    stats: &Arc<RunStats>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !converter::is_ffmpeg_available() {
+        return Err("ffmpeg not found in PATH (required for automatic FLAC/WAV/OGG conversion).".into());
+    }
+
+    let settings = &app_config.library_import;
+    info!("=== LIBRARY IMPORT: {} ===", root);
+
+    // Scan + queue (cheap, done on every run so folders added since are picked up)
+    let folders = get_list_of_folders(root, &ScanOptions::from_config(app_config)?)?;
+    register_folders(db, folders.clone())?;
+    let mut queued = 0;
+    for folder in &folders {
+        queued += library_import::enqueue_work(db, &folder.rjcode)?;
+    }
+    info!("Found {} folder(s), {} newly queued", folders.len(), queued);
+
+    loop {
+        let (remaining, done, failed) = library_import::queue_counts(db)?;
+        info!("Queue: {} remaining, {} done, {} failed", remaining, done, failed);
+        if remaining == 0 {
+            info!("=== LIBRARY IMPORT COMPLETE ===");
+            return Ok(());
+        }
+
+        let budget = settings.works_per_day as i64 - library_import::count_processed_today(db)?;
+        if budget <= 0 {
+            if !settings.keep_running {
+                info!("Daily budget of {} work(s) spent, run again tomorrow to continue", settings.works_per_day);
+                return Ok(());
+            }
+            let wait = library_import::seconds_until_next_day(db)? + 60;
+            info!("Daily budget of {} work(s) spent, waiting {} minute(s) for the next batch", settings.works_per_day, wait / 60);
+            tokio::time::sleep(Duration::from_secs(wait)).await;
+            continue;
+        }
+
+        let batch = library_import::next_queued_works(db, budget)?;
+        run_batch(db, batch, app_config, stats).await?;
+    }
+}
+
+/// Processes one batch like `--full-retag` does: fetch every work's metadata while the VPN is
+/// up, then tag the folders in place once it is down.
+async fn run_batch(
+    db: &Connection,
+    batch: Vec<(RJCode, PathBuf)>,
+    app_config: &Config,
+    stats: &Arc<RunStats>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let delay = Duration::from_secs(app_config.library_import.delay_between_works_secs);
+    let works_affected = batch.len();
+    let before = crate::backup_before_bulk_run(db, app_config, "import-library", works_affected)?;
+
+    // ===== VPN PHASE: fetch metadata + cache covers =====
+    let db_thread = DbHandle::open_beside(db)?;
+    let vpn_manager = crate::connect_vpn_if_enabled(app_config)?;
+    let client = crate::dlsite::MetadataClient::from_config(app_config, stats)?;
+
+    info!("\n--- Fetching metadata ({} work(s)) ---", batch.len());
+    let pb = crate::create_progress_bar(batch.len() as u64);
+    let mut fetched = Vec::with_capacity(batch.len());
+
+    for (index, (rjcode, folder_path)) in batch.into_iter().enumerate() {
+        if index > 0 {
+            tokio::time::sleep(delay).await;
+        }
+        pb.set_message(format!("Fetching {}", rjcode));
+        match crate::refresh_metadata_and_cache_cover(db, db_thread.as_ref(), &rjcode, &client, &app_config.images).await {
+            Ok(_) => {
+                pb.println(format!("{} ✓", rjcode));
+                fetched.push((rjcode, folder_path));
+            }
+            Err(e) => {
+                warn!("Failed to fetch metadata for {}: {}", rjcode, e);
+                pb.println(format!("{} ✗", rjcode));
+                let error = format!("metadata fetch failed: {}", e);
+                library_import::mark_work_processed(db, &rjcode, Some(&error))?;
+                stats.record_work(db, &rjcode, "import-library", WorkOutcome::Failed, Some(&error));
+            }
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    crate::disconnect_vpn(vpn_manager)?;
+    // Missing covers are downloaded without the VPN, by a new client
+    let client = crate::dlsite::MetadataClient::from_config(app_config, stats)?;
+
+    // ===== POST-VPN PHASE: apply covers + tag in place =====
+    info!("\n--- Tagging files ({} work(s)) ---", fetched.len());
+    let pb = crate::create_progress_bar(fetched.len() as u64);
+    let mut success = 0usize;
+    let mut failed = 0usize;
+
+    for (rjcode, folder_path) in fetched {
+        pb.set_message(format!("Tagging {}", rjcode));
+        match crate::apply_cover_and_tag(db, db_thread.as_ref(), &client, &rjcode, folder_path, app_config, true).await {
+            Ok(_) => {
+                library_import::mark_work_processed(db, &rjcode, None)?;
+                stats.record_work(db, &rjcode, "import-library", WorkOutcome::Processed, None);
+                pb.println(format!("{} ✓", rjcode));
+                success += 1;
+            }
+            Err(e) => {
+                warn!("Failed to tag {}: {}", rjcode, e);
+                let error = format!("tagging failed: {}", e);
+                library_import::mark_work_processed(db, &rjcode, Some(&error))?;
+                stats.record_work(db, &rjcode, "import-library", WorkOutcome::Failed, Some(&error));
+                pb.println(format!("{} ✗", rjcode));
+                failed += 1;
+            }
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+
+    info!("Batch done: {} tagged, {} failed", success, failed);
+    crate::snapshot_after_bulk_run(db, "import-library", works_affected, before)?;
+    Ok(())
+}
@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use dialoguer::console::{pad_str, Alignment};
+use rusqlite::Connection;
+use tracing::info;
+
+use crate::database::{queries, ranking_history};
+use crate::errors::HvtError;
+use crate::export::{self, ExportedWork, RatingFilter};
+use crate::tagger::cover_art;
+
+const CIRCLE_COLUMN_WIDTH: usize = 24;
+
+/// Filters of `--list`. Text filters are case-insensitive substring matches; all given filters
+/// must match.
+#[derive(Debug, Default)]
+pub struct ListFilter {
+    /// Circle display name, EN/JP name or RG code
+    pub circle: Option<String>,
+    /// CV display name or raw DLSite name
+    pub cv: Option<String>,
+    /// Tag display name or raw DLSite name
+    pub tag: Option<String>,
+    /// Only works whose files were never tagged
+    pub untagged: bool,
+    /// Only works without a folder.jpeg
+    pub missing_cover: bool,
+    /// Only works released strictly after this date (YYYY-MM-DD)
+    pub released_after: Option<String>,
+    /// Only works rated strictly above this DLSite star score
+    pub stars_over: Option<f32>,
+    /// Only works with this DLSite age rating
+    pub rating: Option<RatingFilter>,
+    /// Only works with at least this many DLSite ratings
+    pub min_ratings: Option<u32>,
+    /// Only works with at least this many written DLSite reviews
+    pub min_reviews: Option<u32>,
+    /// Only works with this DLSite work type or option code (SOU, MOV, AIG...)
+    pub work_type: Option<String>,
+}
+
+/// Order of the works printed by `--list`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListSort {
+    /// By RJ code
+    #[default]
+    Rjcode,
+    /// By best DLSite ranking reached, unranked works last
+    Popularity,
+}
+
+/// `--list`: prints the works of the library matching `filter`, as a table or as JSON (same
+/// records as `--export json`).
+pub fn run_list_workflow(conn: &Connection, filter: &ListFilter, sort: ListSort, json: bool) -> Result<(), HvtError> {
+    let tagged: HashSet<String> = queries::get_tagged_works_with_paths(conn)?
+        .into_iter()
+        .map(|(rjcode, _)| rjcode.as_str().to_string())
+        .collect();
+
+    let mut works = Vec::new();
+    for (rjcode, _) in queries::get_all_works_with_paths(conn)? {
+        let Some(work) = export::build_exported_work(conn, &rjcode)? else {
+            continue;
+        };
+        if matches_filter(&work, filter, tagged.contains(&work.rjcode)) {
+            works.push(work);
+        }
+    }
+    works.sort_by(|a, b| a.rjcode.cmp(&b.rjcode));
+    let best_rankings = match sort {
+        ListSort::Rjcode => None,
+        ListSort::Popularity => {
+            let best = ranking_history::get_best_rankings(conn)?;
+            works.sort_by_key(|work| best.get(&work.rjcode).map_or(i64::MAX, |(_, _, rank, _)| *rank));
+            Some(best)
+        }
+    };
+
+    if json {
+        let content = serde_json::to_string_pretty(&works)
+            .map_err(|e| HvtError::Parse(format!("Failed to serialize works: {}", e)))?;
+        println!("{}", content);
+        return Ok(());
+    }
+
+    if works.is_empty() {
+        info!("No work matches the given filters");
+        return Ok(());
+    }
+
+    // Best ranking column when sorted by popularity, e.g. "#3 day/voice"
+    let rank_header = if best_rankings.is_some() { format!("{:<20}  ", "BEST RANK") } else { String::new() };
+    println!("{:<10}  {:<10}  {:>5}  {}{}  TITLE", "RJCODE", "RELEASED", "STARS", rank_header, pad_str("CIRCLE", CIRCLE_COLUMN_WIDTH, Alignment::Left, None));
+    for work in &works {
+        let released = work.release_date.as_deref().map(release_day).unwrap_or("");
+        let stars = work.stars.map(|s| format!("{:.2}", s)).unwrap_or_default();
+        let circle = pad_str(&work.circle, CIRCLE_COLUMN_WIDTH, Alignment::Left, Some("…"));
+        let rank = match &best_rankings {
+            Some(best) => {
+                let rank = best.get(&work.rjcode)
+                    .map(|(term, category, rank, _)| format!("#{} {}/{}", rank, term, category))
+                    .unwrap_or_default();
+                format!("{:<20}  ", rank)
+            }
+            None => String::new(),
+        };
+        println!("{:<10}  {:<10}  {:>5}  {}{}  {}", work.rjcode, released, stars, rank, circle, work.title);
+    }
+
+    info!("{} work(s)", works.len());
+    Ok(())
+}
+
+fn matches_filter(work: &ExportedWork, filter: &ListFilter, is_tagged: bool) -> bool {
+    if let Some(circle) = &filter.circle {
+        let names = [
+            Some(&work.circle),
+            work.circle_code.as_ref(),
+            work.circle_name_en.as_ref(),
+            work.circle_name_jp.as_ref(),
+        ];
+        if !names.into_iter().flatten().any(|name| contains_ignore_case(name, circle)) {
+            return false;
+        }
+    }
+
+    if let Some(cv) = &filter.cv {
+        if !work.cvs.iter().chain(&work.dlsite_cvs).any(|name| contains_ignore_case(name, cv)) {
+            return false;
+        }
+    }
+
+    if let Some(tag) = &filter.tag {
+        if !work.tags.iter().chain(&work.dlsite_tags).any(|name| contains_ignore_case(name, tag)) {
+            return false;
+        }
+    }
+
+    if filter.untagged && is_tagged {
+        return false;
+    }
+
+    if filter.missing_cover && cover_art::has_cover_art(Path::new(&work.path)) {
+        return false;
+    }
+
+    if let Some(after) = &filter.released_after {
+        match work.release_date.as_deref() {
+            Some(date) if release_day(date) > after.as_str() => {}
+            _ => return false,
+        }
+    }
+
+    if let Some(min_stars) = filter.stars_over {
+        match work.stars {
+            Some(stars) if stars > min_stars => {}
+            _ => return false,
+        }
+    }
+
+    if filter.rating.is_some_and(|rating| !rating.matches(work.rating.as_deref())) {
+        return false;
+    }
+
+    if filter.min_ratings.is_some_and(|min| work.rate_count.unwrap_or(0) < min) {
+        return false;
+    }
+
+    if filter.min_reviews.is_some_and(|min| work.review_count.unwrap_or(0) < min) {
+        return false;
+    }
+
+    if filter.work_type.as_ref().is_some_and(|code| !work.has_work_type(code)) {
+        return false;
+    }
+
+    true
+}
+
+/// DLSite release dates are stored as "YYYY-MM-DD HH:MM:SS": keep the day only.
+fn release_day(date: &str) -> &str {
+    date.get(..10).unwrap_or(date)
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+/// clap value parser for `--released-after`: accepts YYYY-MM-DD only, so the string comparison
+/// against stored dates stays meaningful.
+pub fn parse_day(value: &str) -> Result<String, String> {
+    let valid = value.len() == 10
+        && value.char_indices().all(|(i, c)| if i == 4 || i == 7 { c == '-' } else { c.is_ascii_digit() });
+    if valid {
+        Ok(value.to_string())
+    } else {
+        Err(format!("expected a date as YYYY-MM-DD, got \"{}\"", value))
+    }
+}
@@ -0,0 +1,168 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use crate::config::LoggingConfig;
+use crate::errors::HvtError;
+
+const LOG_FILE_PREFIX: &str = "hvtag-";
+const LOG_FILE_EXT: &str = ".log";
+
+/// Date of the current log file (UTC, like the log timestamps)
+fn today() -> String {
+    crate::timestamp::date(crate::timestamp::now())
+}
+
+/// `hvtag-YYYY-MM-DD.log`, then `hvtag-YYYY-MM-DD.1.log`... once the size limit is reached
+fn log_file_name(date: &str, index: u32) -> String {
+    if index == 0 {
+        format!("{LOG_FILE_PREFIX}{date}{LOG_FILE_EXT}")
+    } else {
+        format!("{LOG_FILE_PREFIX}{date}.{index}{LOG_FILE_EXT}")
+    }
+}
+
+struct LogFile {
+    file: File,
+    date: String,
+    index: u32,
+    written: u64,
+}
+
+/// Log files of `[logging]`: one per day, a new one when `max_size_mb` is reached, the oldest
+/// deleted beyond `max_files`.
+pub struct RotatingFile {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    current: Mutex<Option<LogFile>>,
+}
+
+impl RotatingFile {
+    pub fn new(dir: PathBuf, max_size_mb: u64, max_files: usize) -> Result<Self, HvtError> {
+        fs::create_dir_all(&dir).map_err(|_| HvtError::PathCreationFailed(dir.display().to_string()))?;
+        let rotating = Self {
+            dir,
+            max_bytes: max_size_mb.max(1) * 1024 * 1024,
+            max_files: max_files.max(1),
+            current: Mutex::new(None),
+        };
+        // Fail at startup rather than on the first event
+        let opened = rotating.open(&today(), None)?;
+        *rotating.current.lock().expect("log file mutex poisoned") = Some(opened);
+        Ok(rotating)
+    }
+
+    /// Opens the day's last file (appending), or the next one when it is full or `after` is
+    /// given (the index of the file that just filled up).
+    fn open(&self, date: &str, after: Option<u32>) -> io::Result<LogFile> {
+        let mut index = after.map_or(0, |i| i + 1);
+        loop {
+            let path = self.dir.join(log_file_name(date, index));
+            let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            let next_exists = self.dir.join(log_file_name(date, index + 1)).exists();
+            if size < self.max_bytes && !next_exists {
+                let file = OpenOptions::new().create(true).append(true).open(&path)?;
+                self.prune();
+                return Ok(LogFile { file, date: date.to_string(), index, written: size });
+            }
+            index += 1;
+        }
+    }
+
+    /// Deletes the oldest log files beyond `max_files`. Errors are ignored: logging goes on.
+    fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut logs: Vec<(SystemTime, PathBuf)> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with(LOG_FILE_PREFIX) && n.ends_with(LOG_FILE_EXT))
+            })
+            .map(|p| (fs::metadata(&p).and_then(|m| m.modified()).unwrap_or(UNIX_EPOCH), p))
+            .collect();
+        if logs.len() <= self.max_files {
+            return;
+        }
+        logs.sort();
+        for (_, old) in &logs[..logs.len() - self.max_files] {
+            let _ = fs::remove_file(old);
+        }
+    }
+}
+
+/// One event's output, holding the file for the whole event
+pub struct RotatingWriter<'a> {
+    rotating: &'a RotatingFile,
+    current: MutexGuard<'a, Option<LogFile>>,
+}
+
+impl Write for RotatingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let date = today();
+        let rotate = match self.current.as_ref() {
+            Some(log) if log.date != date => Some(None),
+            Some(log) if log.written > 0 && log.written + buf.len() as u64 > self.rotating.max_bytes => Some(Some(log.index)),
+            Some(_) => None,
+            None => Some(None),
+        };
+        if let Some(after) = rotate {
+            *self.current = Some(self.rotating.open(&date, after)?);
+        }
+        let log = self.current.as_mut().expect("log file opened above");
+        let written = log.file.write(buf)?;
+        log.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.current.as_mut() {
+            Some(log) => log.file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingFile {
+    type Writer = RotatingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RotatingWriter {
+            rotating: self,
+            // A panic while logging must not silence the rest of the run
+            current: self.current.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+/// The `[logging]` file output, in addition to the console: `None` when disabled. Its level is
+/// `[logging] level` (an `EnvFilter` directive like "debug" or "info,hvtag=debug"), independent
+/// of `RUST_LOG`.
+pub fn file_layer(config: &LoggingConfig) -> Result<Option<Box<dyn Layer<Registry> + Send + Sync>>, HvtError> {
+    if !config.enabled {
+        return Ok(None);
+    }
+    let dir = match &config.path {
+        Some(path) => Path::new(path).to_path_buf(),
+        None => crate::paths::logs_dir()?,
+    };
+    let filter = EnvFilter::try_new(&config.level)
+        .map_err(|e| HvtError::Parse(format!("Invalid [logging] level '{}': {}", config.level, e)))?;
+    let writer = RotatingFile::new(dir, config.max_size_mb, config.max_files)?;
+    Ok(Some(
+        tracing_subscriber::fmt::layer()
+            .with_ansi(false)
+            .with_writer(writer)
+            .with_filter(filter)
+            .boxed(),
+    ))
+}
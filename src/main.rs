@@ -1,16 +1,22 @@
 
 use clap::Parser;
+use futures_util::{stream, StreamExt};
 use tracing::{info, warn, error, debug};
+use tracing_subscriber::prelude::*;
 use indicatif::{ProgressBar, ProgressStyle, ProgressDrawTarget};
 
-use std::path::Path;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use crate::{
-    database::{db_loader::open_db, init, queries},
-    dlsite::{assign_data_to_work_with_client, DataSelection},
-    folders::{get_list_of_folders, register_folders, types::{ManagedFolder, RJCode}},
-    tagger::{cover_art, converter, folder_normalizer, process_work_folder, types::TaggerConfig},
+    database::{actor::DbHandle, backup, db_loader::{open_db, open_db_read_only}, init, queries, review_queue::ReviewReason},
+    dlsite::{assign_data_to_work_with_client, DataSelection, MetadataClient},
+    folders::{excluded_folders, get_list_of_folders, library_parent_dir, register_folders, types::{ManagedFolder, RJCode}, ScanOptions},
+    sanitize::NameRules,
+    tagger::{cover_art::{self, CoverSettings}, converter, folder_normalizer, process_work_folder, types::TaggerConfig, update_review_queue},
     vpn::WireGuardManager,
-    config::{Config, VpnProvider},
+    config::{Config, ImageMode, ImagesConfig, VpnProvider},
+    run_stats::{RunStats, WorkOutcome},
 };
 
 mod errors;
@@ -20,9 +26,43 @@ mod folders;
 mod database;
 mod tag_manager;
 mod circle_manager;
+mod cv_manager;
 mod vpn;
 mod config;
 mod web;
+mod verify;
+mod convert;
+mod covers;
+mod fsck;
+mod audit;
+mod untag;
+mod rename_preview;
+mod export;
+mod merge;
+mod search;
+mod list;
+mod stats;
+mod library_import;
+mod review;
+mod remove;
+mod prune;
+mod refresh;
+mod force_tag;
+mod playlist;
+mod config_check;
+mod logging;
+mod fetch_errors;
+mod tag_translations;
+mod tag_mappings;
+mod work_manager;
+mod control;
+mod run_stats;
+mod runner;
+mod paths;
+mod http;
+mod copy;
+mod sanitize;
+mod timestamp;
 
 #[derive(Parser, Debug)]
 struct PrgmArgs {
@@ -30,27 +70,191 @@ struct PrgmArgs {
     #[arg(long)]
     full: bool,
 
+    /// With --full: only move works whose files were tagged into the library; the others stay
+    /// in the import folder
+    #[arg(long, requires = "full")]
+    only_tagged: bool,
+
+    /// With --full: only move works whose circle name (EN/JP/custom) or RG code contains this
+    /// text into the library; the others stay in the import folder
+    #[arg(long, requires = "full", value_name = "CIRCLE")]
+    only_circle: Option<String>,
+
+    /// With --full: library folder of the moved works for this run, replacing [import]
+    /// folder_template (e.g. "{circle}/{rjcode} {title}")
+    #[arg(long, requires = "full")]
+    folder_template: Option<String>,
+
     /// Refresh an existing work already in the library (re-collect metadata/CVs/cover, re-tag files)
     #[arg(long)]
     retag: Option<String>,
 
-    /// Refresh EVERY work already registered in the library (same as --retag, looped over all of them)
+    /// Remove the ID3 tags hvtag wrote to a work's MP3 files (see --restore)
+    #[arg(long)]
+    untag: Option<String>,
+
+    /// With --untag: restore the tags the files had before hvtag first tagged them instead
+    #[arg(long, requires = "untag")]
+    restore: bool,
+
+    /// Remove a work from the library: deactivate it (hidden from every command, restorable)
+    /// or delete it from the database with --purge. The folder is kept unless --trash or
+    /// --delete-files is given
+    #[arg(long, group = "removal")]
+    remove: Option<String>,
+
+    /// With --remove or --prune: delete every row of the work instead of deactivating it
+    #[arg(long, requires = "removal")]
+    purge: bool,
+
+    /// With --remove: move the work folder to a .trash folder next to it
+    #[arg(long, requires = "remove")]
+    trash: bool,
+
+    /// With --remove: delete the work folder from disk (asks first unless --non-interactive)
+    #[arg(long, requires = "remove", conflicts_with = "trash")]
+    delete_files: bool,
+
+    /// Deactivate (or delete with --purge) the works whose folder no longer exists on disk,
+    /// after confirmation
+    #[arg(long, group = "removal")]
+    prune: bool,
+
+    /// With --prune: only prune folders missing for at least this many days (shares that are
+    /// temporarily unmounted)
+    #[arg(long, requires = "prune", default_value_t = 0)]
+    offline_grace: u32,
+
+    /// Track numbering strategy for this run, replacing [track_parsing] strategies and never
+    /// prompting: asian_fullwidth, asian_brackets, asian_kanji_episode, first_number,
+    /// custom_delimiter:<delimiter> or strip_prefix:<regex>
+    #[arg(long)]
+    parsing_strategy: Option<String>,
+
+    /// Images saved with each work for this run, replacing [images] mode: cover (folder.jpeg
+    /// only) or full (also the DLSite sample gallery, into a scans/ subfolder)
+    #[arg(long, value_enum)]
+    images: Option<ImageMode>,
+
+    /// Try cover URLs that kept failing again, like [images] cover_max_failures = 0 and
+    /// cover_failure_cooldown_hours = 0
+    #[arg(long)]
+    force: bool,
+
+    /// Also write the legacy .tagged marker into tagged work folders, like [tagger]
+    /// write_tagged_markers = true
     #[arg(long)]
+    write_markers: bool,
+
+    /// Look for works in subfolders of the scanned folders (Circle/RJxxxx, 2023/RJxxxx), like
+    /// [import] recursive = true
+    #[arg(long)]
+    recursive: bool,
+
+    /// Re-fetch metadata of works already collected (ratings, stars and tags change on DLSite);
+    /// works whose tags would change are marked for re-tagging
+    #[arg(long)]
+    refresh: bool,
+
+    /// With --refresh: only works last fetched longer ago than this (90d, 12w)
+    #[arg(long, requires = "refresh", value_parser = refresh::parse_age_days)]
+    older_than: Option<u32>,
+
+    /// With --refresh: fields to re-fetch, comma-separated (default: all)
+    #[arg(long, requires = "refresh", value_enum, value_delimiter = ',')]
+    fields: Vec<refresh::RefreshField>,
+
+    /// Folder levels searched with --recursive, replacing [import] max_depth (1 = direct
+    /// children only)
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Refresh the works of the library still to tag: never tagged, or marked for re-tagging
+    /// (same as --retag, looped over them)
+    #[arg(long, group = "retagging")]
     full_retag: bool,
 
+    /// Re-tag library works from the database, without fetching DLSite (e.g. after changing the
+    /// tag separator): clears their tagged state, then tags them again. The works are given by
+    /// RJ code, --circle or --all
+    #[arg(long, num_args = 0.., value_name = "RJCODE", groups = ["retagging", "circle_selection"])]
+    force_tag: Option<Vec<String>>,
+
+    /// With --full-retag or --force-tag: EVERY work registered in the library
+    #[arg(long, requires = "retagging")]
+    all: bool,
+
     /// One-shot test: run the full process on a folder in the import directory,
     /// without moving it or touching the database
     #[arg(long)]
     tag: Option<String>,
 
+    /// Don't move audio files from subfolders up to the work folder for this run, replacing
+    /// [tagger] normalize_folders
+    #[arg(long)]
+    no_normalize: bool,
+
+    /// Also move the non-audio files of a work's subfolders into extras/ when normalizing, for
+    /// this run, replacing [tagger] collect_extras
+    #[arg(long)]
+    collect_extras: bool,
+
+    /// Split single-file works with a cue sheet, and .m4b/.m4a audiobooks by chapter, into one MP3
+    /// per track for this run, replacing
+    /// [tagger] split_cue
+    #[arg(long)]
+    split_cue: bool,
+
+    /// Flatten a work's folder now (audio files from subfolders moved up to its root), or with
+    /// --undo put back the files hvtag moved there
+    #[arg(long, value_name = "RJCODE")]
+    normalize: Option<String>,
+
+    /// With --normalize: restore the layout the work had before it was normalized
+    #[arg(long, requires = "normalize")]
+    undo: bool,
+
+    /// With --normalize: only print the moves, without touching any file
+    #[arg(long, requires = "normalize")]
+    dry_run: bool,
+
     /// Interactive tag management
     #[arg(long)]
     manage_tags: bool,
 
+    /// Apply a JP → EN tag translation pack as custom tag mappings: a CSV (tag_jp,tag_en) or
+    /// JSON file, or "bundled" for the common DLSite genres. Existing mappings are kept
+    #[arg(long, value_name = "FILE")]
+    import_tag_translations: Option<String>,
+
+    /// Write the tags still in Japanese without a mapping to a CSV (or .json) file to translate
+    /// and re-import with --import-tag-translations (defaults to hvtag_untranslated_tags.csv)
+    #[arg(long, value_name = "FILE")]
+    export_untranslated_tags: Option<Option<String>>,
+
+    /// Apply tag renames and ignores in bulk from a CSV file (dlsite_tag,action,custom_name with
+    /// action rename, ignore or remove), marking the affected works for re-tagging
+    #[arg(long, value_name = "FILE")]
+    apply_tag_mappings: Option<String>,
+
+    /// Write every tag rename and ignore to a CSV file readable by --apply-tag-mappings
+    /// (defaults to hvtag_tag_mappings.csv)
+    #[arg(long, value_name = "FILE")]
+    export_tag_mappings: Option<Option<String>>,
+
+    /// Interactive work management: search a work, view its metadata and override its title,
+    /// circle, CVs, tags or release date for that work only
+    #[arg(long)]
+    manage_works: bool,
+
     /// Interactive circle management
     #[arg(long)]
     manage_circles: bool,
 
+    /// Interactive CV management (merge name variants of the same voice actor)
+    #[arg(long)]
+    manage_cvs: bool,
+
     /// Launch local web UI server (browse/search library, edit tag & circle mappings)
     #[arg(long)]
     ui: bool,
@@ -59,22 +263,273 @@ struct PrgmArgs {
     /// Accepts a bare host (keeps the configured port) or a full "host:port" (e.g. "0.0.0.0:8787").
     #[arg(long)]
     ui_bind: Option<String>,
+
+    /// Serve a JSON-RPC control interface (scan, import, retag, refresh, progress events) for
+    /// GUI frontends and scripts on a Unix socket (hvtag.sock in the data folder) or a Windows
+    /// named pipe (\\.\pipe\hvtag), or on PATH
+    #[arg(long, value_name = "PATH")]
+    control_socket: Option<Option<String>>,
+
+    /// Decode every audio file of tagged works with ffmpeg to detect truncated/corrupt audio,
+    /// record results in the database and print a summary report
+    #[arg(long)]
+    verify: bool,
+
+    /// With --verify: compare files against the SHA-256 checksums recorded when they were tagged
+    /// (detects bit-rot/silent modification) instead of decoding them with ffmpeg
+    #[arg(long, requires = "verify")]
+    checksums: bool,
+
+    /// Convert-only pipeline: convert FLAC/WAV/OGG/M4A files of every library work to MP3 in place
+    /// (no metadata fetch, no tagging). Resumable: re-run to pick up interrupted/failed files
+    #[arg(long)]
+    convert: bool,
+
+    /// Re-verify work folders against their checksums.sha256 manifest (see [integrity] in
+    /// config.toml) to detect silent corruption; recently checked works are skipped
+    #[arg(long)]
+    fsck: bool,
+
+    /// Re-download, at DLSite's original size, the folder.jpeg covers narrower than --min-width
+    #[arg(long)]
+    refresh_covers: bool,
+
+    /// With --refresh-covers: width in pixels under which a cover is re-downloaded
+    #[arg(long, requires = "refresh_covers", default_value_t = 600)]
+    min_width: u32,
+
+    /// Read back the tags of every tagged work's MP3 files and compare them with the database:
+    /// lists untagged/partially tagged, stale (database changed since) and manually edited works
+    #[arg(long)]
+    audit: bool,
+
+    /// Export works (merged circle names, tags, CVs, release dates, ratings) to a JSON or CSV file
+    #[arg(long, value_enum, group = "exporting")]
+    export: Option<export::ExportFormat>,
+
+    /// Write album.nfo files (title, circle, CVs, tags, release date, description, cover) into
+    /// the work folders, and artist.nfo into circle folders, for Jellyfin and Kodi
+    #[arg(long, group = "exporting")]
+    export_nfo: bool,
+
+    /// Write .m3u8 playlists (relative paths) grouped by work, circle, cv or tag into
+    /// [playlists] output_path
+    #[arg(long, value_enum)]
+    playlist: Option<playlist::PlaylistGrouping>,
+
+    /// Copy every tagged work into this folder (e.g. a phone or DAP), keeping the library as is.
+    /// Checks the free space first and resumes an interrupted copy
+    #[arg(long, group = "exporting", value_name = "DEST")]
+    copy: Option<String>,
+
+    /// With --export, --export-nfo or --copy: only export these works (RJ codes)
+    #[arg(long, num_args = 1.., requires = "exporting")]
+    works: Vec<String>,
+
+    /// With --export: output file (defaults to hvtag_export.<format> in the current directory)
+    #[arg(long, requires = "export")]
+    output: Option<String>,
+
+    /// Merge another machine's data into the local database: a --export json file or another
+    /// hvtag data.db3. Reconciles works by RJ code, keeps local values on conflict and reports them
+    #[arg(long)]
+    import_data: Option<String>,
+
+    /// Full-text search over work titles, circles, CVs and tags; prints matching RJ codes with
+    /// their path and tagging status (e.g. --search "耳かき バイノーラル")
+    #[arg(long)]
+    search: Option<String>,
+
+    /// List works of the library, optionally filtered (--circle, --cv, --with-tag, --untagged,
+    /// --missing-cover, --released-after, --stars-over, --filter-rating), as a table or JSON (--json)
+    #[arg(long, group = "circle_selection")]
+    list: bool,
+
+    /// With --list or --force-tag: circle name (EN/JP/custom) or RG code contains this text
+    #[arg(long, requires = "circle_selection")]
+    circle: Option<String>,
+
+    /// With --list: one of the CVs contains this text
+    #[arg(long, requires = "list")]
+    cv: Option<String>,
+
+    /// With --list: one of the tags (custom or DLSite name) contains this text
+    #[arg(long, requires = "list")]
+    with_tag: Option<String>,
+
+    /// With --list: only works whose files were never tagged
+    #[arg(long, requires = "list")]
+    untagged: bool,
+
+    /// With --list: only works without a folder.jpeg cover
+    #[arg(long, requires = "list")]
+    missing_cover: bool,
+
+    /// With --list: only works released after this date (YYYY-MM-DD)
+    #[arg(long, requires = "list", value_parser = list::parse_day)]
+    released_after: Option<String>,
+
+    /// With --list: only works with a DLSite star score above this value
+    #[arg(long, requires = "list")]
+    stars_over: Option<f32>,
+
+    /// With --list: only works rated at least this many times on DLSite
+    #[arg(long, requires = "list")]
+    min_ratings: Option<u32>,
+
+    /// With --list: only works with at least this many written DLSite reviews
+    #[arg(long, requires = "list")]
+    min_reviews: Option<u32>,
+
+    /// With --list or --export: only works with this DLSite work type or option code
+    /// (SOU voice, MOV video, MUS music, AIG AI-generated...)
+    #[arg(long)]
+    work_type: Option<String>,
+
+    /// With --list or --export: only works with this DLSite age rating
+    #[arg(long, value_enum)]
+    filter_rating: Option<export::RatingFilter>,
+
+    /// With --list: order of the works (popularity: best DLSite ranking reached first)
+    #[arg(long, requires = "list", value_enum, default_value_t = list::ListSort::Rjcode)]
+    sort: list::ListSort,
+
+    /// With --list: print JSON (same records as --export json) instead of a table
+    #[arg(long, requires = "list")]
+    json: bool,
+
+    /// Print library statistics: work counts, tagged/untagged, works per circle, top CVs, tag
+    /// frequency, best DLSite rankings, audio size and formats, DLSite fetch errors
+    #[arg(long)]
+    stats: bool,
+
+    /// With --stats: price, download and wishlist counts of this work at each DLSite fetch
+    #[arg(long, requires = "stats", value_name = "RJCODE")]
+    work: Option<String>,
+
+    /// List DLSite fetch errors grouped per work, with their last occurrence, retry count and
+    /// resolution (filter with --category and --unresolved)
+    #[arg(long)]
+    errors: bool,
+
+    /// With --errors: only errors of this category (e.g. dlsite_removed, dlsite_challenge)
+    #[arg(long, requires = "errors")]
+    category: Option<String>,
+
+    /// With --errors: hide resolved errors
+    #[arg(long, requires = "errors")]
+    unresolved: bool,
+
+    /// Mark every unresolved fetch error of a work resolved
+    #[arg(long, value_name = "RJCODE")]
+    resolve_errors: Option<String>,
+
+    /// First import of an existing collection, in place: queue every RJ/VJ folder under this
+    /// root and fetch/tag them in daily batches (see [library_import] in config.toml). Re-run to
+    /// resume; the queue position is kept in the database
+    #[arg(long)]
+    import_library: Option<String>,
+
+    /// Use this database file instead of the default data.db3 (e.g. another machine's library)
+    #[arg(long)]
+    db: Option<String>,
+
+    /// Work on a library configured under [library.<name>] in config.toml: its database and
+    /// scan roots replace the default ones
+    #[arg(long, conflicts_with = "db")]
+    library: Option<String>,
+
+    /// Never prompt (cron, CI, ssh without a terminal): uncertain track numbering and unconfirmed
+    /// tracklists are left to configured defaults and queued for review (see --review), and --full
+    /// applies the source folder renames that don't conflict, queueing the others for review
+    #[arg(long)]
+    non_interactive: bool,
+
+    /// Walk the review queue one work at a time (uncertain track numbering, unconfirmed
+    /// tracklists, nameless circles, failed DLSite fetches) and settle each item
+    #[arg(long)]
+    review: bool,
+
+    /// Never access the network: metadata comes from the database and the HTTP cache (whatever
+    /// its age), VPN and downloads are skipped, and the works with no data yet are listed at the end
+    #[arg(long)]
+    offline: bool,
+
+    /// Validate config.toml (syntax, values, unknown keys, referenced files and folders) and
+    /// print the effective configuration
+    #[arg(long)]
+    check_config: bool,
+
+    /// Open the database read-only and refuse anything that writes: only --stats, --errors,
+    /// --list, --search, --export, --export-untranslated-tags and --ui (with its editing actions
+    /// disabled) are available
+    #[arg(long)]
+    read_only: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize tracing subscriber
-    tracing_subscriber::fmt()
-        .with_ansi(false)
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"))
+    // Initialize tracing subscriber: console (RUST_LOG, info by default) and the [logging] files.
+    // A broken config is reported by the command itself once logging is up.
+    let logging_config = Config::load_without_creating().map(|c| c.logging).unwrap_or_default();
+    let (file_layer, file_layer_error) = match logging::file_layer(&logging_config) {
+        Ok(layer) => (layer, None),
+        Err(e) => (None, Some(e)),
+    };
+    tracing_subscriber::registry()
+        .with(file_layer)
+        .with(
+            tracing_subscriber::fmt::layer().with_ansi(false).with_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"))
+            )
         )
         .init();
+    if let Some(e) = file_layer_error {
+        warn!("Log files disabled: {}", e);
+    }
 
     let args = PrgmArgs::parse();
-    let db = open_db(None)?;
-    init(&db)?;
+
+    // Every command ends with a summary footer (elapsed time, works, downloads, files written)
+    let stats = Arc::new(RunStats::new(std::env::args().skip(1).collect::<Vec<_>>().join(" ")));
+    let result = run_command(args, &stats).await;
+    stats.print_footer(result.is_ok());
+    stats.write_report(result.as_ref().err().map(|e| e.to_string()));
+    result
+}
+
+async fn run_command(args: PrgmArgs, stats: &Arc<RunStats>) -> Result<(), Box<dyn std::error::Error>> {
+    // --check-config: before anything loads the config (it reports what would make loading fail)
+    if args.check_config {
+        config_check::run_config_check_workflow(args.library.as_deref())?;
+        return Ok(());
+    }
+
+    // --library <name>: the named library's database (its scan roots are applied with the config below)
+    let db_path = match &args.library {
+        Some(name) => Some(Config::load_without_creating()?.use_library(name)?),
+        None => args.db.clone(),
+    };
+
+    // --read-only: no schema init/migration, and only commands that don't write to the library
+    let db = if args.read_only {
+        if !(args.stats || args.errors || args.list || args.search.is_some() || args.export.is_some()
+            || args.export_untranslated_tags.is_some() || args.ui)
+        {
+            return Err("--read-only only supports --stats, --errors, --list, --search, --export, --export-untranslated-tags and --ui".into());
+        }
+        open_db_read_only(db_path.as_deref())?
+    } else {
+        let db = open_db(db_path.as_deref())?;
+        init(&db)?;
+        db
+    };
+
+    // The managers and the review are interactive by nature
+    if args.non_interactive && (args.manage_tags || args.manage_circles || args.manage_cvs || args.manage_works || args.review) {
+        return Err("--manage-tags, --manage-circles, --manage-cvs, --manage-works and --review cannot run with --non-interactive".into());
+    }
 
     // Handle tag management (early exit if specified)
     if args.manage_tags {
@@ -82,46 +537,305 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
 
+    // Handle tag translation packs (early exit if specified, no config needed)
+    if let Some(source) = args.import_tag_translations {
+        tag_translations::run_import_translations_workflow(&db, &source)?;
+        return Ok(());
+    }
+    if let Some(output) = args.export_untranslated_tags {
+        tag_translations::run_export_untranslated_workflow(&db, output.as_deref())?;
+        return Ok(());
+    }
+
+    // Handle bulk tag mappings (early exit if specified, no config needed)
+    if let Some(source) = args.apply_tag_mappings {
+        tag_mappings::run_apply_mappings_workflow(&db, &source)?;
+        return Ok(());
+    }
+    if let Some(output) = args.export_tag_mappings {
+        tag_mappings::run_export_mappings_workflow(&db, output.as_deref())?;
+        return Ok(());
+    }
+
     // Handle circle management (early exit if specified)
     if args.manage_circles {
         circle_manager::run_interactive_circle_manager(&db)?;
         return Ok(());
     }
 
+    // Handle CV management (early exit if specified)
+    if args.manage_cvs {
+        cv_manager::run_interactive_cv_manager(&db)?;
+        return Ok(());
+    }
+
+    // Handle untag/restore (early exit if specified, no config needed)
+    if let Some(rjcode) = args.untag {
+        untag::run_untag_workflow(&db, &rjcode, args.restore, stats)?;
+        return Ok(());
+    }
+
+    // Handle work removal (early exit if specified, no config needed)
+    if let Some(rjcode) = args.remove {
+        let folder_removal = if args.trash {
+            remove::FolderRemoval::Trash
+        } else if args.delete_files {
+            remove::FolderRemoval::Delete
+        } else {
+            remove::FolderRemoval::Keep
+        };
+        remove::run_remove_workflow(&db, &rjcode, args.purge, folder_removal, !args.non_interactive, stats)?;
+        return Ok(());
+    }
+
+    // Handle stale folder pruning (early exit if specified, no config needed)
+    if args.prune {
+        prune::run_prune_workflow(&db, args.offline_grace, args.purge, !args.non_interactive, stats)?;
+        return Ok(());
+    }
+
+    // Handle integrity check (early exit if specified, no config needed)
+    if args.verify {
+        verify::run_verify_workflow(&db, args.checksums)?;
+        return Ok(());
+    }
+
+    // Handle convert-only pipeline (early exit if specified, only needs the target bitrate)
+    if args.convert {
+        let target_bitrate = Config::load_without_creating()?.tagger.target_bitrate;
+        convert::run_convert_workflow(&db, target_bitrate, stats).await?;
+        return Ok(());
+    }
+
+    // Handle export (early exit if specified, no config needed)
+    if let Some(format) = args.export {
+        export::run_export_workflow(&db, format, &args.works, args.filter_rating, args.work_type.as_deref(), args.output.as_deref())?;
+        return Ok(());
+    }
+
+    // Handle data import/merge (early exit if specified, no config needed)
+    if let Some(source) = args.import_data {
+        merge::run_merge_workflow(&db, &source)?;
+        return Ok(());
+    }
+
+    // Handle library search (early exit if specified, no config needed)
+    if let Some(query) = args.search {
+        search::run_search_workflow(&db, &query)?;
+        return Ok(());
+    }
+
+    // Handle library listing (early exit if specified, no config needed)
+    if args.list {
+        let filter = list::ListFilter {
+            circle: args.circle,
+            cv: args.cv,
+            tag: args.with_tag,
+            untagged: args.untagged,
+            missing_cover: args.missing_cover,
+            released_after: args.released_after,
+            stars_over: args.stars_over,
+            rating: args.filter_rating,
+            min_ratings: args.min_ratings,
+            min_reviews: args.min_reviews,
+            work_type: args.work_type,
+        };
+        list::run_list_workflow(&db, &filter, args.sort, args.json)?;
+        return Ok(());
+    }
+
+    // Handle statistics report (early exit if specified, no config needed)
+    if args.stats {
+        match &args.work {
+            Some(rjcode) => stats::run_work_stats_workflow(&db, &RJCode::new(rjcode.clone())?)?,
+            None => stats::run_stats_workflow(&db)?,
+        }
+        return Ok(());
+    }
+
+    // Handle fetch error listing/resolution (early exit if specified, no config needed)
+    if args.errors {
+        fetch_errors::run_errors_workflow(&db, args.category.as_deref(), args.unresolved)?;
+        return Ok(());
+    }
+    if let Some(rjcode) = args.resolve_errors {
+        fetch_errors::run_resolve_errors_workflow(&db, &rjcode)?;
+        return Ok(());
+    }
+
     // Load configuration
-    let app_config = Config::load()?;
+    let mut app_config = if args.read_only { Config::load_without_creating()? } else { Config::load()? };
+    if let Some(strategy) = args.parsing_strategy {
+        app_config.track_parsing.strategies = vec![strategy];
+        app_config.track_parsing.prompt = false;
+    }
+    if args.non_interactive {
+        app_config.track_parsing.prompt = false;
+    }
+    if let Some(mode) = args.images {
+        app_config.images.mode = mode;
+    }
+    if args.recursive {
+        app_config.import.recursive = true;
+    }
+    if let Some(depth) = args.max_depth {
+        app_config.import.max_depth = depth;
+    }
+    if args.no_normalize {
+        app_config.tagger.normalize_folders = false;
+    }
+    if args.collect_extras {
+        app_config.tagger.collect_extras = true;
+    }
+    if args.split_cue {
+        app_config.tagger.split_cue = true;
+    }
+    if let Some(template) = args.folder_template {
+        app_config.import.folder_template = Some(template);
+    }
+    if let Some(name) = &args.library {
+        app_config.use_library(name)?;
+    }
+    if args.write_markers {
+        app_config.tagger.write_tagged_markers = true;
+    }
+    if args.offline {
+        app_config.vpn.enabled = false;
+        app_config.http_cache.offline = true;
+    }
+    // Cover URLs that kept failing are neither skip-listed nor waited for
+    if args.force {
+        app_config.images.cover_max_failures = 0;
+        app_config.images.cover_failure_cooldown_hours = 0;
+    }
+    dlsite::http_cache::remove_expired(&app_config.http_cache);
+    dlsite::sources::check_sources(&app_config.metadata)?;
+    // A missing cookie jar fails here rather than in the middle of a workflow
+    dlsite::challenge::ChallengeSettings::from_config(&app_config.metadata)?;
+    stats.configure_report(&app_config.run_reports);
+    // Fail before any work is touched on a bad strategy spec
+    app_config.track_parsing.presets()?;
+
+    // --fsck: periodic manifest re-verification (needs config for the check interval)
+    if args.fsck {
+        fsck::run_fsck_workflow(&db, app_config.integrity.fsck_interval_days)?;
+        return Ok(());
+    }
+
+    // --refresh: re-fetch metadata of collected works (needs config for the VPN)
+    if args.refresh {
+        refresh::run_refresh_workflow(&db, &app_config, args.older_than, &args.fields, stats).await?;
+        return Ok(());
+    }
+
+    // --export-nfo: Jellyfin/Kodi metadata files (needs config for the tag values and circle folders)
+    if args.export_nfo {
+        export::run_export_nfo_workflow(&db, &app_config, &args.works, stats)?;
+        return Ok(());
+    }
+
+    // --normalize: flatten or restore a work's folder (needs config for collect_extras)
+    if let Some(rjcode) = args.normalize {
+        folder_normalizer::run_normalize_workflow(&db, &rjcode, args.undo, args.dry_run, app_config.tagger.collect_extras)?;
+        return Ok(());
+    }
+
+    // --copy: tagged works to another folder (needs config for the library layout)
+    if let Some(dest) = args.copy {
+        copy::run_copy_workflow(&db, &app_config, &dest, &args.works, stats)?;
+        return Ok(());
+    }
+
+    // --playlist: m3u8 playlists (needs config for the output folder and tag values)
+    if let Some(grouping) = args.playlist {
+        playlist::run_playlist_workflow(&db, &app_config, grouping, stats)?;
+        return Ok(());
+    }
+
+    // --refresh-covers: replace low-resolution covers (needs config for the VPN)
+    if args.refresh_covers {
+        covers::run_refresh_covers_workflow(&db, &app_config, args.min_width, stats).await?;
+        return Ok(());
+    }
+
+    // --audit: compare file tags with the database (needs config for the expected tag values)
+    if args.audit {
+        let tagger_config = TaggerConfig {
+            tag_separator: app_config.tagger.get_separator(),
+            cv_name_style: app_config.tagger.cv_name_style,
+            title_language: app_config.tagger.title_language,
+            ..Default::default()
+        };
+        audit::run_audit_workflow(&db, &tagger_config)?;
+        return Ok(());
+    }
+
+    // --manage-works: per-work overrides (needs config to show the values as they are tagged)
+    if args.manage_works {
+        work_manager::run_interactive_work_manager(&db, &app_config)?;
+        return Ok(());
+    }
+
+    // --review: settle the works queued for review (needs config to re-tag them)
+    if args.review {
+        review::run_review_workflow(&db, &app_config, stats).await?;
+        return Ok(());
+    }
 
     // --ui: Launch local web UI server (exclusive; needs config for bind address/port)
     if args.ui {
-        web::run_ui_workflow(db, &app_config, args.ui_bind).await?;
+        web::run_ui_workflow(db, db_path, &app_config, args.ui_bind, args.read_only).await?;
+        return Ok(());
+    }
+
+    // --control-socket: JSON-RPC server driving the workflows (exclusive, like --ui)
+    if let Some(socket_path) = args.control_socket {
+        drop(db);
+        control::run_control_socket(db_path, &app_config, socket_path).await?;
+        return Ok(());
+    }
+
+    // --import-library <root>: throttled, resumable first import of an existing collection
+    if let Some(root) = args.import_library {
+        library_import::run_library_import_workflow(&db, &root, &app_config, stats).await?;
         return Ok(());
     }
 
     // --retag <rjcode>: refresh an existing work already registered in the library
     if let Some(rjcode) = args.retag {
-        run_retag_workflow(&db, &rjcode, &app_config).await?;
+        run_retag_workflow(&db, &rjcode, &app_config, stats).await?;
+        return Ok(());
+    }
+
+    // --force-tag: re-tag the selected works from the database
+    if let Some(rjcodes) = &args.force_tag {
+        force_tag::run_force_tag_workflow(&db, &app_config, rjcodes, args.circle.as_deref(), args.all, stats).await?;
         return Ok(());
     }
 
-    // --full-retag: refresh every work registered in the library
+    // --full-retag: refresh the works still to tag (every work with --all)
     if args.full_retag {
-        run_full_retag_workflow(&db, &app_config).await?;
+        run_full_retag_workflow(&db, &app_config, args.all, stats).await?;
         return Ok(());
     }
 
     // --tag <folder>: one-shot test-tag a folder from the import directory, no DB/move
     if let Some(folder_name) = args.tag {
-        run_tag_test_workflow(&db, &folder_name, &app_config).await?;
+        run_tag_test_workflow(&db, &folder_name, &app_config, stats).await?;
         return Ok(());
     }
 
     // --full: import workflow (new works from source directory)
     if args.full {
-        run_import_workflow(&db, &app_config).await?;
+        let move_filter = MoveFilter {
+            only_tagged: args.only_tagged,
+            only_circle: args.only_circle,
+        };
+        run_import_workflow(&db, &app_config, &move_filter, args.non_interactive, stats).await?;
         return Ok(());
     }
 
-    info!("No action specified. Use --full to import new works, --retag <rjcode> to refresh an existing work, --tag <folder> to test-tag a folder without importing it, or --ui to browse the library.");
+    info!("No action specified. Use --full to import new works, --retag <rjcode> to refresh an existing work, --tag <folder> to test-tag a folder without importing it, --verify to check audio files for corruption, or --ui to browse the library.");
     Ok(())
 }
 
@@ -155,14 +869,69 @@ fn disconnect_vpn(manager: Option<WireGuardManager>) -> Result<(), Box<dyn std::
     Ok(())
 }
 
+/// Before a run touching `works_affected` works: if that reaches `[backup] min_works`, copies the
+/// database to the backups folder (keeping the last `keep_backups`) and records a 'before'
+/// health snapshot. Returns the snapshot to hand to `snapshot_after_bulk_run`.
+fn backup_before_bulk_run(
+    db: &rusqlite::Connection,
+    app_config: &Config,
+    run_name: &str,
+    works_affected: usize,
+) -> Result<Option<backup::HealthSnapshot>, Box<dyn std::error::Error>> {
+    let settings = &app_config.backup;
+    if !settings.enabled || works_affected < settings.min_works {
+        return Ok(None);
+    }
+
+    let backup_path = backup::backup_database(db)?;
+    info!("Database backed up to {}", backup_path.display());
+    let pruned = backup::prune_backups(db, settings.keep_backups)?;
+    if pruned > 0 {
+        info!("Deleted {} old backup(s)", pruned);
+    }
+
+    let snapshot = backup::take_health_snapshot(db)?;
+    backup::record_health_snapshot(db, run_name, "before", works_affected, &snapshot, Some(&backup_path))?;
+    Ok(Some(snapshot))
+}
+
+/// Records the 'after' health snapshot of a run that was backed up, and logs what changed.
+fn snapshot_after_bulk_run(
+    db: &rusqlite::Connection,
+    run_name: &str,
+    works_affected: usize,
+    before: Option<backup::HealthSnapshot>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(before) = before else {
+        return Ok(());
+    };
+
+    let after = backup::take_health_snapshot(db)?;
+    backup::record_health_snapshot(db, run_name, "after", works_affected, &after, None)?;
+
+    let changes: Vec<String> = before.fields().iter().zip(after.fields().iter())
+        .filter(|((_, b), (_, a))| a != b)
+        .map(|((label, b), (_, a))| format!("{} {} → {} ({:+})", label, b, a, a - b))
+        .collect();
+    if changes.is_empty() {
+        info!("Library health: no change in counts");
+    } else {
+        info!("Library health: {}", changes.join(", "));
+    }
+    Ok(())
+}
+
 /// Phase 1 of a refresh (needs VPN/DLSite access): re-collects tags/CVs/circle/rating/
-/// release_date and caches a fresh cover to `~/.hvtag/covers_cache/`. Only the database and the
-/// cover cache are touched here — no changes to the actual work folder — so this is safe to run
-/// entirely while the VPN is up, mirroring `--full`'s pre-VPN-disconnect collect phase.
+/// release_date and caches a fresh cover to the cover cache (plus the sample gallery
+/// with `ImageMode::Full`). Only the database and the cover cache are touched here — no changes
+/// to the actual work folder — so this is safe to run entirely while the VPN is up, mirroring
+/// `--full`'s pre-VPN-disconnect collect phase.
 async fn refresh_metadata_and_cache_cover(
     db: &rusqlite::Connection,
+    db_thread: Option<&DbHandle>,
     rjcode: &RJCode,
-    http_client: &reqwest::Client,
+    client: &MetadataClient,
+    images: &ImagesConfig,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let data_selection = DataSelection {
         tags: true,
@@ -172,48 +941,152 @@ async fn refresh_metadata_and_cache_cover(
         cvs: true,
         stars: true,
         cover_link: true,
+        description: true,
+        relations: true,
+        sample_images: true,
     };
-    assign_data_to_work_with_client(db, rjcode.clone(), data_selection, Some(http_client)).await?;
+    let result = assign_data_to_work_with_client(db, rjcode.clone(), data_selection, client).await;
+    let failure = match &result {
+        Err(errors::HvtError::RemovedWork(_) | errors::HvtError::Offline(_)) | Ok(_) => None,
+        Err(e) => Some(e.to_string()),
+    };
+    update_review_queue(db, rjcode, ReviewReason::ScrapeFailed, failure.as_deref());
+    result?;
 
-    if let Ok(Some(cover_url)) = queries::get_cover_link(db, rjcode) {
-        if let Err(e) = cover_art::download_cover_to_cache(&cover_url, &rjcode.to_string(), Some((500, 500))).await {
-            warn!("Failed to cache fresh cover for {}: {}", rjcode, e);
+    if let Err(e) = cover_art::download_cover_with_fallback(db, db_thread, rjcode, client, &CoverSettings::from_config(images)).await {
+        warn!("Failed to cache fresh cover for {}: {}", rjcode, e);
+    }
+    if images.mode == ImageMode::Full {
+        if let Ok(Some(folder_path)) = queries::get_work_path(db, rjcode) {
+            cache_sample_images(db, rjcode, &folder_path, client).await;
         }
     }
     Ok(())
 }
 
+/// `--images full`, network phase: caches the sample images of a work that are not saved in its
+/// scans/ folder yet. Failures are only logged, like covers.
+async fn cache_sample_images(db: &rusqlite::Connection, rjcode: &RJCode, folder_path: &Path, client: &MetadataClient) {
+    let scans_dir = folder_path.join(cover_art::SCANS_DIR_NAME);
+    let missing: Vec<(i64, String)> = queries::get_sample_images(db, rjcode)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, _, file_name)| !file_name.as_ref().is_some_and(|f| scans_dir.join(f).exists()))
+        .map(|(position, url, _)| (position, url))
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+    if let Err(e) = cover_art::download_sample_images_to_cache(&rjcode.to_string(), &missing, client).await {
+        warn!("Failed to cache sample images for {}: {}", rjcode, e);
+    }
+}
+
+/// `--images full`, file phase: moves the cached sample images into the work's scans/ folder and
+/// records their file names in `work_images`.
+fn apply_sample_images(db: &rusqlite::Connection, rjcode: &RJCode, folder_path: &Path, stats: &RunStats) {
+    let copied = match cover_art::copy_sample_images_from_cache(&rjcode.to_string(), folder_path, stats) {
+        Ok(copied) => copied,
+        Err(e) => {
+            warn!("Failed to copy sample images for {}: {}", rjcode, e);
+            return;
+        }
+    };
+    for (position, url, _) in queries::get_sample_images(db, rjcode).unwrap_or_default() {
+        let file_name = cover_art::sample_image_file_name(position, &url);
+        if copied.contains(&file_name) {
+            if let Err(e) = queries::set_sample_image_file(db, rjcode, position, &file_name) {
+                warn!("Failed to record sample image {} of {}: {}", file_name, rjcode, e);
+            }
+        }
+    }
+}
+
 /// Phase 2 of a refresh (no network needed): applies the cached cover (forcing it to replace any
 /// existing one) and re-tags the actual audio files (auto-converting FLAC/WAV/OGG to MP3 first).
 /// Must only run after the VPN has been disconnected — this is what touches the real files, which
 /// may live on a network share that's only reachable once the VPN tunnel is torn back down.
+/// `client` is built once after the disconnect and shared by the works of the batch.
 async fn apply_cover_and_tag(
     db: &rusqlite::Connection,
+    db_thread: Option<&DbHandle>,
+    client: &MetadataClient,
     rjcode: &RJCode,
-    folder_path: String,
+    folder_path: PathBuf,
     app_config: &Config,
-    write_tagged_marker: bool,
+    persistent: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let folder_path_obj = Path::new(&folder_path);
+    let folder_path_obj = folder_path.as_path();
     let cover_path = folder_path_obj.join("folder.jpeg");
-    if cover_path.exists() {
+    // Offline no fresh cover was cached, the current one stays
+    if cover_path.exists() && !client.cache.is_offline() {
         std::fs::remove_file(&cover_path)?;
     }
-    if let Err(e) = cover_art::copy_cover_from_cache(&rjcode.to_string(), folder_path_obj) {
+    if let Err(e) = cover_art::copy_cover_from_cache(&rjcode.to_string(), folder_path_obj, &client.stats) {
         debug!("No fresh cached cover applied for {}: {}", rjcode, e);
     }
+    if persistent && app_config.images.mode == ImageMode::Full {
+        apply_sample_images(db, rjcode, folder_path_obj, &client.stats);
+    }
 
     let folder = ManagedFolder::new(folder_path);
-    let tagger_config = TaggerConfig {
+    let tagger_config = library_tagger_config(app_config, persistent)?;
+    process_work_folder(db, &folder, &tagger_config, client, db_thread).await?;
+    Ok(())
+}
+
+/// Run by the tagging workflows (`--full`, `--full-retag`, `--refresh`): works tagged with other
+/// `[tagger]` settings (e.g. another separator) are marked for re-tagging, left for --full-retag.
+fn mark_works_with_other_tagger_settings(db: &rusqlite::Connection, app_config: &Config) -> Result<(), errors::HvtError> {
+    let config_hash = library_tagger_config(app_config, true)?.tag_config_hash();
+    let marked = queries::mark_works_with_other_tag_config(db, &config_hash)?;
+    if marked > 0 {
+        info!("{} work(s) were tagged with other [tagger] settings and are marked for re-tagging: run --full-retag", marked);
+    }
+    Ok(())
+}
+
+/// Tagging settings of library works (`--retag`, `--full-retag`, `--force-tag`...) from the
+/// config: files converted to MP3, missing covers downloaded, every work re-tagged.
+/// `persistent` is false for the one-shot `--tag` test run, which leaves no file behind.
+fn library_tagger_config(app_config: &Config, persistent: bool) -> Result<TaggerConfig, errors::HvtError> {
+    Ok(TaggerConfig {
         tag_separator: app_config.tagger.get_separator(),
         convert_to_mp3: true,
-        target_bitrate: 320,
+        target_bitrate: app_config.tagger.target_bitrate,
         download_cover: true,
+        cover_settings: CoverSettings::from_config(&app_config.images).original_size(),
         force_retag: true,
-        write_tagged_marker,
-    };
-    process_work_folder(db, &folder, &tagger_config).await?;
-    Ok(())
+        // A one-shot test run shouldn't leave files behind in the folder
+        write_tagged_marker: persistent && app_config.tagger.write_tagged_markers,
+        write_checksum_manifest: persistent && app_config.integrity.write_checksum_manifest,
+        tag_video_files: app_config.video.tag_video_files,
+        detect_silent_audio: app_config.integrity.detect_silent_audio,
+        cv_name_style: app_config.tagger.cv_name_style,
+        title_language: app_config.tagger.title_language,
+        write_series_tag: app_config.tagger.write_series_tag,
+        write_description_comment: app_config.tagger.write_description_comment,
+        write_nfo: app_config.tagger.write_nfo,
+        write_source_frames: app_config.tagger.write_source_frames,
+        write_rating: app_config.tagger.write_rating,
+        write_age_rating: app_config.tagger.write_age_rating,
+        write_explicit_flag: app_config.tagger.write_explicit_flag,
+        artist_frame_style: app_config.tagger.artist_frame_style,
+        id3_version: app_config.tagger.id3_version,
+        strip_id3v1: app_config.tagger.strip_id3v1,
+        normalize_folders: app_config.tagger.normalize_folders,
+        collect_extras: app_config.tagger.collect_extras,
+        split_cue: app_config.tagger.split_cue,
+        skip_work_types: app_config.tagger.skip_work_types.clone(),
+        replaygain: app_config.tagger.replaygain,
+        io_parallelism: app_config.tagger.io_parallelism,
+        parsing_presets: app_config.track_parsing.presets()?,
+        prompt_track_parsing: app_config.track_parsing.prompt,
+        silence_threshold_db: app_config.track_parsing.silence_threshold_db,
+        silence_min_duration: app_config.track_parsing.silence_min_duration,
+        accept_tracklist: app_config.track_parsing.accept_tracklist,
+        keep_existing_track_numbers: app_config.track_parsing.keep_existing_numbers,
+    })
 }
 
 /// `--retag <rjcode>`: refresh a single work already registered in the library.
@@ -221,6 +1094,7 @@ async fn run_retag_workflow(
     db: &rusqlite::Connection,
     rjcode: &str,
     app_config: &Config,
+    stats: &Arc<RunStats>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let rjcode = RJCode::new(rjcode.to_string())?;
     let folder_path = queries::get_work_path(db, &rjcode)?
@@ -235,50 +1109,74 @@ async fn run_retag_workflow(
 
     info!("=== RETAG {} ===", rjcode);
 
+    let db_thread = DbHandle::open_beside(db)?;
     let vpn_manager = connect_vpn_if_enabled(app_config)?;
-    let http_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
+    let client = MetadataClient::from_config(app_config, stats)?.refetching();
 
-    let metadata_result = refresh_metadata_and_cache_cover(db, &rjcode, &http_client).await;
+    let metadata_result = refresh_metadata_and_cache_cover(db, db_thread.as_ref(), &rjcode, &client, &app_config.images).await;
 
     disconnect_vpn(vpn_manager)?;
-    metadata_result?;
+    // Missing covers are downloaded without the VPN, by a new client
+    let client = MetadataClient::from_config(app_config, stats)?;
+    if let Err(e) = metadata_result {
+        stats.record_work(db, &rjcode, "retag", WorkOutcome::Failed, Some(&e.to_string()));
+        return Err(e);
+    }
 
-    apply_cover_and_tag(db, &rjcode, folder_path, app_config, true).await?;
+    if let Err(e) = apply_cover_and_tag(db, db_thread.as_ref(), &client, &rjcode, folder_path.clone(), app_config, true).await {
+        stats.record_work(db, &rjcode, "retag", WorkOutcome::Failed, Some(&e.to_string()));
+        return Err(e);
+    }
+    stats.record_work(db, &rjcode, "retag", WorkOutcome::Processed, None);
+    slot_into_circle_folder(db, &rjcode, &folder_path, app_config)?;
 
     info!("=== RETAG COMPLETE: {} ===", rjcode);
     Ok(())
 }
 
-/// `--full-retag`: refresh EVERY work already registered in the library — same per-work refresh
-/// as `--retag`, looped over the whole database. Connects the VPN once for the entire batch
+/// `--full-retag`: refresh the works still to tag (never tagged or marked for re-tagging, from
+/// the database alone), or EVERY work already registered in the library with `--all` — same
+/// per-work refresh as `--retag`, looped over them. Connects the VPN once for the entire batch
 /// rather than once per work (reconnecting per work would be needlessly slow for hundreds of
 /// works). Continues past individual failures (e.g. a work whose folder no longer exists on
 /// disk) so one bad work doesn't abort the whole batch; failures are reported in the summary.
 async fn run_full_retag_workflow(
     db: &rusqlite::Connection,
     app_config: &Config,
+    all: bool,
+    stats: &Arc<RunStats>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if !converter::is_ffmpeg_available() {
         return Err("ffmpeg not found in PATH (required for automatic FLAC/WAV/OGG conversion).".into());
     }
 
-    let works = queries::get_all_works_with_paths(db)?;
+    mark_works_with_other_tagger_settings(db, app_config)?;
+
+    // The database says which works are pending: no folder of the library is looked at
+    let works = if all {
+        queries::get_all_works_with_paths(db)?
+    } else {
+        queries::get_pending_works_with_paths(db)?
+    };
     if works.is_empty() {
-        info!("No works in database");
+        if all {
+            info!("No works in database");
+        } else {
+            info!("No work to tag: every work is tagged and none is marked for re-tagging (--all refreshes them all)");
+        }
         return Ok(());
     }
 
     info!("=== FULL RETAG: {} work(s) ===", works.len());
+    let works_affected = works.len();
+    let before = backup_before_bulk_run(db, app_config, "full-retag", works_affected)?;
+    let db_thread = DbHandle::open_beside(db)?;
 
     // ===== VPN PHASE: refresh DB metadata + cache fresh covers for every work =====
     // Only the database and the cover cache are touched here, exactly like `--full`'s collect
     // phase — the VPN is torn down before any of the actual work folders are touched below.
     let vpn_manager = connect_vpn_if_enabled(app_config)?;
-    let http_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
+    let client = MetadataClient::from_config(app_config, stats)?.refetching();
 
     info!("\n--- Fetching metadata ({} work(s)) ---", works.len());
     let pb = create_progress_bar(works.len() as u64);
@@ -286,7 +1184,7 @@ async fn run_full_retag_workflow(
 
     for (rjcode, _) in &works {
         pb.set_message(format!("Fetching {}", rjcode));
-        match refresh_metadata_and_cache_cover(db, rjcode, &http_client).await {
+        match refresh_metadata_and_cache_cover(db, db_thread.as_ref(), rjcode, &client, &app_config.images).await {
             Ok(_) => {
                 pb.println(format!("{} ✓", rjcode));
                 metadata_ok.push(true);
@@ -302,6 +1200,8 @@ async fn run_full_retag_workflow(
     pb.finish_and_clear();
 
     disconnect_vpn(vpn_manager)?;
+    // Missing covers are downloaded without the VPN, by a new client
+    let client = MetadataClient::from_config(app_config, stats)?;
 
     // ===== POST-VPN PHASE: apply cached covers + re-tag files, VPN is down =====
     info!("\n--- Tagging files ({} work(s)) ---", works.len());
@@ -315,19 +1215,25 @@ async fn run_full_retag_workflow(
         if !was_ok {
             // Metadata refresh already failed for this work; skip tagging and count it once.
             pb.println(format!("{} ✗ (metadata fetch failed)", rjcode));
+            stats.record_work(db, &rjcode, "full-retag", WorkOutcome::Failed, Some("metadata fetch failed"));
             failed += 1;
             pb.inc(1);
             continue;
         }
 
-        match apply_cover_and_tag(db, &rjcode, folder_path, app_config, true).await {
+        match apply_cover_and_tag(db, db_thread.as_ref(), &client, &rjcode, folder_path.clone(), app_config, true).await {
             Ok(_) => {
+                if let Err(e) = slot_into_circle_folder(db, &rjcode, &folder_path, app_config) {
+                    warn!("Tagged {} but failed to move it into its circle folder: {}", rjcode, e);
+                }
                 pb.println(format!("{} ✓", rjcode));
+                stats.record_work(db, &rjcode, "full-retag", WorkOutcome::Processed, None);
                 success += 1;
             }
             Err(e) => {
                 warn!("Failed to tag {}: {}", rjcode, e);
                 pb.println(format!("{} ✗", rjcode));
+                stats.record_work(db, &rjcode, "full-retag", WorkOutcome::Failed, Some(&e.to_string()));
                 failed += 1;
             }
         }
@@ -338,6 +1244,7 @@ async fn run_full_retag_workflow(
     pb.finish_and_clear();
 
     info!("=== FULL RETAG COMPLETE: {} succeeded, {} failed ===", success, failed);
+    snapshot_after_bulk_run(db, "full-retag", works_affected, before)?;
     Ok(())
 }
 
@@ -351,6 +1258,7 @@ async fn run_tag_test_workflow(
     db: &rusqlite::Connection,
     folder_name: &str,
     app_config: &Config,
+    stats: &Arc<RunStats>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let source_path = app_config.import.source_path.as_ref()
         .ok_or("import.source_path is not configured in config.toml")?;
@@ -359,7 +1267,7 @@ async fn run_tag_test_workflow(
         return Err(format!("Folder not found in import directory: {}", folder_path.display()).into());
     }
 
-    let folder = ManagedFolder::new(folder_path.to_string_lossy().to_string());
+    let folder = ManagedFolder::new(folder_path);
     if !folder.is_valid {
         return Err(format!(
             "'{}' is not a valid work folder (needs an RJ/VJ-prefixed name and audio files)",
@@ -382,7 +1290,7 @@ async fn run_tag_test_workflow(
 
     register_folders(db, vec![folder.clone()])?;
 
-    let result = run_tag_test_inner(db, &folder, app_config).await;
+    let result = run_tag_test_inner(db, &folder, app_config, stats).await;
 
     // Cleanup regardless of success/failure. Shared reference rows (dlsite_tag/circles/cvs
     // themselves) are correctly left untouched — only this fld_id's lkp_* rows disappear.
@@ -400,18 +1308,21 @@ async fn run_tag_test_inner(
     db: &rusqlite::Connection,
     folder: &ManagedFolder,
     app_config: &Config,
+    stats: &Arc<RunStats>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let db_thread = DbHandle::open_beside(db)?;
     let vpn_manager = connect_vpn_if_enabled(app_config)?;
-    let http_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()?;
+    let client = MetadataClient::from_config(app_config, stats)?;
 
-    let metadata_result = refresh_metadata_and_cache_cover(db, &folder.rjcode, &http_client).await;
+    // A test run leaves no gallery behind, see apply_cover_and_tag
+    let images = ImagesConfig { mode: ImageMode::Cover, ..app_config.images.clone() };
+    let metadata_result = refresh_metadata_and_cache_cover(db, db_thread.as_ref(), &folder.rjcode, &client, &images).await;
 
     disconnect_vpn(vpn_manager)?;
     metadata_result?;
 
-    apply_cover_and_tag(db, &folder.rjcode, folder.path.clone(), app_config, false).await?;
+    let client = MetadataClient::from_config(app_config, stats)?;
+    apply_cover_and_tag(db, db_thread.as_ref(), &client, &folder.rjcode, folder.path.clone(), app_config, false).await?;
     Ok(())
 }
 
@@ -428,6 +1339,55 @@ fn create_progress_bar(len: u64) -> ProgressBar {
     pb
 }
 
+/// With `organize_by_circle`, moves a library work that isn't under its circle folder (new work
+/// tagged before the option was enabled, or circle display name changed by the refresh) into
+/// `library_path/{circle}/`, and updates its path in the database. Works outside `library_path`
+/// are left alone. An emptied previous circle folder is removed.
+fn slot_into_circle_folder(
+    db: &rusqlite::Connection,
+    rjcode: &RJCode,
+    source: &Path,
+    app_config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(library_path) = app_config.import.library_path.as_deref() else {
+        return Ok(());
+    };
+    if !app_config.import.organize_by_circle || app_config.import.folder_template.is_some() {
+        return Ok(());
+    }
+
+    let library_path = Path::new(library_path);
+    if !source.starts_with(library_path) {
+        return Ok(());
+    }
+    let folder_name = source.file_name()
+        .ok_or_else(|| format!("Invalid path: {}", source.display()))?;
+
+    let parent = library_parent_dir(db, library_path, rjcode, true, NameRules::from_config(&app_config.import))?;
+    let target = parent.join(folder_name);
+    if target == source {
+        return Ok(());
+    }
+    if target.exists() {
+        warn!("Cannot move {} into {}: target already exists", rjcode, target.display());
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(&parent)?;
+    move_folder_cross_drive(source, &target)?;
+    queries::update_folder_path(db, rjcode, &target)?;
+    queries::record_files_moved(db, rjcode, source, &target)?;
+    info!("Moved {} into {}", rjcode, parent.display());
+
+    // Drop the previous circle folder if this was its last work
+    if let Some(old_parent) = source.parent() {
+        if old_parent != library_path {
+            let _ = std::fs::remove_dir(old_parent);
+        }
+    }
+    Ok(())
+}
+
 /// Move folder with cross-drive support (copy + delete fallback)
 fn move_folder_cross_drive(source: &Path, target: &Path) -> Result<(), errors::HvtError> {
     // Try rename first (fast, works on same drive)
@@ -481,10 +1441,94 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<(), errors::HvtError> {
     Ok(())
 }
 
-/// Import workflow: scan source -> process -> move to library
+/// Works left in the import folder by the move step of `--full`
+#[derive(Debug, Default)]
+struct MoveFilter {
+    /// `--only-tagged`: works without any tagged file
+    only_tagged: bool,
+    /// `--only-circle`: works whose circle display name, EN/JP name or RG code doesn't contain it
+    only_circle: Option<String>,
+}
+
+impl MoveFilter {
+    /// Why a work stays in the import folder, if it does
+    fn skip_reason(&self, db: &rusqlite::Connection, rjcode: &RJCode, tagged: &HashSet<String>) -> Result<Option<&'static str>, errors::HvtError> {
+        if self.only_tagged && !tagged.contains(rjcode.as_str()) {
+            return Ok(Some("not tagged"));
+        }
+        if let Some(circle) = &self.only_circle {
+            if !work_matches_circle(db, rjcode, circle)? {
+                return Ok(Some("other circle"));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Whether the circle name of a work (custom, EN or JP) or its RG code contains `circle`,
+/// case-insensitively
+fn work_matches_circle(db: &rusqlite::Connection, rjcode: &RJCode, circle: &str) -> Result<bool, errors::HvtError> {
+    let circle = circle.to_lowercase();
+    let mut names = vec![database::custom_circles::get_merged_circle_name_for_work(db, rjcode)?];
+    if let Some(rgcode) = queries::get_circle_code_for_work(db, rjcode)? {
+        let (_, _, name_en, name_jp) = database::custom_circles::get_circle_info(db, &rgcode)?;
+        names.extend([rgcode, name_en, name_jp]);
+    }
+    Ok(names.iter().any(|name| name.to_lowercase().contains(&circle)))
+}
+
+/// Library folder of a work from `[import] folder_template`, with the values the tagger writes
+fn work_folder_from_template(
+    db: &rusqlite::Connection,
+    rjcode: &RJCode,
+    template: &str,
+    app_config: &Config,
+) -> Result<std::path::PathBuf, errors::HvtError> {
+    let tagger_config = TaggerConfig {
+        tag_separator: app_config.tagger.get_separator(),
+        cv_name_style: app_config.tagger.cv_name_style,
+        title_language: app_config.tagger.title_language,
+        ..Default::default()
+    };
+    let metadata = tagger::fetch_metadata_from_db(db, rjcode, &tagger_config)?;
+    let circle_code = queries::get_circle_code_for_work(db, rjcode)?.unwrap_or_default();
+    let date = metadata.date.as_deref().and_then(|d| d.get(..10)).unwrap_or("");
+    let values = [
+        ("rjcode", rjcode.as_str()),
+        ("title", metadata.title.as_str()),
+        ("circle", metadata.album_artist.as_str()),
+        ("circle_code", circle_code.as_str()),
+        ("date", date),
+        ("year", date.get(..4).unwrap_or("")),
+    ];
+    Ok(folders::render_folder_template(template, &values, NameRules::from_config(&app_config.import)))
+}
+
+/// A source folder left unrenamed by `--non-interactive`, queued for review under the RJ code
+/// it would have been renamed to (only possible once that work is in the database)
+fn queue_rename_conflict(db: &rusqlite::Connection, old: &Path, new: &Path, reason: &str) -> Result<(), errors::HvtError> {
+    let details = format!("{} not renamed to {}: {}", old.display(), new.display(), reason);
+    let rjcode = new.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(|name| RJCode::new(name.to_string()).ok());
+    match rjcode {
+        Some(rjcode) if queries::get_work_path(db, &rjcode)?.is_some() => {
+            warn!("{}, queued for review", details);
+            update_review_queue(db, &rjcode, ReviewReason::RenameConflict, Some(&details));
+        }
+        _ => warn!("{}; rename it by hand", details),
+    }
+    Ok(())
+}
+
+/// Import workflow: scan source -> process -> move to library. With `non_interactive`, source
+/// folder renames are not previewed: those that conflict are left alone and queued for review.
 async fn run_import_workflow(
     db: &rusqlite::Connection,
     app_config: &Config,
+    move_filter: &MoveFilter,
+    non_interactive: bool,
+    stats: &Arc<RunStats>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Validate config
     let source_path = app_config.import.source_path.as_ref()
@@ -496,14 +1540,50 @@ async fn run_import_workflow(
             "Please configure import.library_path in config.toml".to_string()
         ))?;
 
+    if let Some(template) = &app_config.import.folder_template {
+        folders::validate_folder_template(template)?;
+    }
+
+    mark_works_with_other_tagger_settings(db, app_config)?;
+
     info!("=== IMPORT WORKFLOW ===");
     info!("Source: {}", source_path);
     info!("Library: {}", library_path);
 
     // ========== PRE-VPN PHASE ==========
-    // 1. Prepare source folders: rename non-RJ roots and flatten audio files
+    // 1. Prepare source folders: rename non-RJ roots and flatten audio files.
+    //    Renames are previewed first; folders whose rename is rejected are left untouched.
     info!("\n--- Preparing source folders ---");
-    match folder_normalizer::prepare_source_directory(source_path) {
+    match folder_normalizer::repair_mojibake_names(source_path, app_config.import.fix_mojibake_filenames) {
+        Ok(0) => {}
+        Ok(n) if app_config.import.fix_mojibake_filenames => info!("Repaired {} garbled Shift-JIS name(s)", n),
+        Ok(n) => warn!("Found {} garbled Shift-JIS name(s); set fix_mojibake_filenames = true under [import] to rename them", n),
+        Err(e) => warn!("Garbled name detection encountered an error: {}", e),
+    }
+    // Folders excluded from the scan ([import] exclude, .hvtagignore) are not prepared either
+    let scan_options = ScanOptions::from_config(app_config)?;
+    let ignored = excluded_folders(source_path, &scan_options)?;
+    let planned_renames: Vec<_> = folder_normalizer::plan_source_renames(source_path)?
+        .into_iter()
+        .filter(|(old, _)| !ignored.contains(old))
+        .collect();
+    let mut excluded: Vec<std::path::PathBuf> = if planned_renames.is_empty() {
+        Vec::new()
+    } else if non_interactive {
+        let (_, conflicts) = rename_preview::split_conflicts(planned_renames);
+        for ((old, new), reason) in &conflicts {
+            queue_rename_conflict(db, old, new, reason)?;
+        }
+        conflicts.into_iter().map(|((old, _), _)| old).collect()
+    } else {
+        let approved = rename_preview::review_renames(planned_renames.clone())?;
+        planned_renames.into_iter()
+            .filter(|rename| !approved.contains(rename))
+            .map(|(old, _)| old)
+            .collect()
+    };
+    excluded.extend(ignored);
+    match folder_normalizer::prepare_source_directory(db, source_path, &excluded, app_config.tagger.normalize_folders, app_config.tagger.collect_extras) {
         Ok(0) => debug!("All source folders already normalized"),
         Ok(n) => info!("Prepared {} folder(s)", n),
         Err(e) => warn!("Folder preparation encountered an error: {}", e),
@@ -511,7 +1591,7 @@ async fn run_import_workflow(
 
     // 2. Scan source directory
     info!("\n--- Scanning source directory ---");
-    let source_folders = get_list_of_folders(source_path)?;
+    let source_folders = get_list_of_folders(source_path, &scan_options)?;
 
     if source_folders.is_empty() {
         info!("No valid RJ folders found in source directory");
@@ -536,6 +1616,7 @@ async fn run_import_workflow(
 
         if target_path.exists() {
             warn!("{} already exists in library, skipping", folder.rjcode);
+            stats.record_work(db, &folder.rjcode, "full", WorkOutcome::Skipped, Some("already in library"));
         } else {
             folders_to_process.push(folder);
         }
@@ -547,14 +1628,13 @@ async fn run_import_workflow(
     }
 
     info!("{} folder(s) to process", folders_to_process.len());
+    let before = backup_before_bulk_run(db, app_config, "full", folders_to_process.len())?;
 
     // Register folders in DB now (with source path) so that --collect and --tag can resolve
     // fld_id during this same run. The path will be updated to the library path after the move.
     info!("\n--- Registering folders in database ---");
-    for folder in &folders_to_process {
-        if let Err(e) = register_folders(db, vec![folder.clone()]) {
-            warn!("Failed to register {} in DB: {}", folder.rjcode, e);
-        }
+    if let Err(e) = register_folders(db, folders_to_process.clone()) {
+        warn!("Failed to register folders in DB: {}", e);
     }
 
     // ========== VPN PHASE ==========
@@ -583,12 +1663,8 @@ async fn run_import_workflow(
         }
     }
 
-    // Create HTTP client
-    let http_client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .cookie_store(true)
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
-        .build()?;
+    let db_thread = DbHandle::open_beside(db)?;
+    let client = MetadataClient::from_config(app_config, stats)?;
 
     // Collect metadata (--full always does this)
     {
@@ -601,6 +1677,9 @@ async fn run_import_workflow(
             cvs: true,
             stars: true,
             cover_link: true,
+            description: true,
+            relations: true,
+            sample_images: true,
         };
 
         let pb = create_progress_bar(folders_to_process.len() as u64);
@@ -609,15 +1688,26 @@ async fn run_import_workflow(
             pb.set_message(format!("Fetching {}", folder.rjcode));
 
             let result_msg = match assign_data_to_work_with_client(
-                db, folder.rjcode.clone(), data_selection.clone(), Some(&http_client)
+                db, folder.rjcode.clone(), data_selection.clone(), &client
             ).await {
-                Ok(_) => format!("{} ✓", folder.rjcode),
+                Ok(_) => {
+                    update_review_queue(db, &folder.rjcode, ReviewReason::ScrapeFailed, None);
+                    format!("{} ✓", folder.rjcode)
+                }
                 Err(errors::HvtError::RemovedWork(rjcode)) => {
                     queries::insert_error(db, &rjcode, "removed work", Some("dlsite_removed"))?;
                     format!("{} (removed)", folder.rjcode)
                 }
+                Err(errors::HvtError::Offline(_)) => format!("{} (not cached)", folder.rjcode),
+                Err(e @ errors::HvtError::Challenge(_)) => {
+                    error!("Error fetching {}: {}", folder.rjcode, e);
+                    queries::insert_error(db, &folder.rjcode, &e.to_string(), Some("dlsite_challenge"))?;
+                    update_review_queue(db, &folder.rjcode, ReviewReason::ScrapeFailed, Some(&e.to_string()));
+                    format!("{} (robot check)", folder.rjcode)
+                }
                 Err(e) => {
                     error!("Error fetching {}: {}", folder.rjcode, e);
+                    update_review_queue(db, &folder.rjcode, ReviewReason::ScrapeFailed, Some(&e.to_string()));
                     format!("{} ✗", folder.rjcode)
                 }
             };
@@ -629,8 +1719,10 @@ async fn run_import_workflow(
         pb.finish_and_clear();
     }
 
-    // Download covers (--full always does this)
-    {
+    // Download covers (--full always does this, unless offline)
+    if app_config.http_cache.offline {
+        info!("\n--- Offline: cover and sample image downloads skipped ---");
+    } else {
         info!("\n--- Downloading covers ---");
 
         // Filter folders that need covers (don't have folder.jpeg yet)
@@ -643,30 +1735,39 @@ async fn run_import_workflow(
         } else {
             info!("{} folder(s) need covers", folders_needing_covers.len());
             let pb = create_progress_bar(folders_needing_covers.len() as u64);
-
-            for folder in &folders_needing_covers {
-                pb.set_message(format!("Cover {}", folder.rjcode));
-
-                // Get cover URL from database
-                if let Ok(Some(cover_url)) = queries::get_cover_link(db, &folder.rjcode) {
-                    match cover_art::download_cover_to_cache(&cover_url, &folder.rjcode.to_string(), Some((500, 500))).await {
-                        Ok(_) => pb.println(&format!("{} cover ✓", folder.rjcode)),
+            pb.set_message("Covers");
+
+            let cover_settings = CoverSettings::from_config(&app_config.images);
+            let downloads = folders_needing_covers.iter().map(|folder| {
+                let (pb, client, db_thread, cover_settings) = (&pb, &client, db_thread.as_ref(), &cover_settings);
+                async move {
+                    match cover_art::download_cover_with_fallback(db, db_thread, &folder.rjcode, client, cover_settings).await {
+                        Ok(_) => pb.println(format!("{} cover ✓", folder.rjcode)),
                         Err(e) => {
                             warn!("Failed to download cover for {}: {}", folder.rjcode, e);
-                            pb.println(&format!("{} cover ✗", folder.rjcode));
+                            pb.println(format!("{} cover ✗", folder.rjcode));
                         }
                     }
+                    pb.inc(1);
                 }
-
-                pb.inc(1);
-            }
+            });
+            stream::iter(downloads).buffered(app_config.images.cover_parallelism.max(1)).collect::<Vec<_>>().await;
 
             pb.finish_and_clear();
         }
+
+        if app_config.images.mode == ImageMode::Full {
+            info!("\n--- Downloading sample images ---");
+            for folder in &folders_to_process {
+                cache_sample_images(db, &folder.rjcode, &folder.path, &client).await;
+            }
+        }
     }
 
-    // Disconnect VPN before filesystem operations
+    // Disconnect VPN before filesystem operations; covers still missing are then downloaded
+    // without it, by a new client (the connections of the first one went through the VPN)
     drop(vpn_manager);
+    let client = MetadataClient::from_config(app_config, stats)?;
 
     // ========== POST-VPN PHASE ==========
 
@@ -676,13 +1777,17 @@ async fn run_import_workflow(
         for folder in &folders_to_process {
             let folder_path = Path::new(&folder.path);
 
+            if app_config.images.mode == ImageMode::Full {
+                apply_sample_images(db, &folder.rjcode, folder_path, stats);
+            }
+
             // Skip if folder already has a cover
             if cover_art::has_cover_art(folder_path) {
                 debug!("Skipping {}: already has cover", folder.rjcode);
                 continue;
             }
 
-            if let Err(e) = cover_art::copy_cover_from_cache(&folder.rjcode.to_string(), folder_path) {
+            if let Err(e) = cover_art::copy_cover_from_cache(&folder.rjcode.to_string(), folder_path, stats) {
                 debug!("No cached cover for {}: {}", folder.rjcode, e);
             }
         }
@@ -694,10 +1799,38 @@ async fn run_import_workflow(
         let tagger_config = TaggerConfig {
             tag_separator: app_config.tagger.get_separator(),
             convert_to_mp3: false,
-            target_bitrate: 320,
+            target_bitrate: app_config.tagger.target_bitrate,
             download_cover: true,
+            cover_settings: CoverSettings::from_config(&app_config.images).original_size(),
             force_retag: false,
-            write_tagged_marker: true,
+            write_tagged_marker: app_config.tagger.write_tagged_markers,
+            write_checksum_manifest: app_config.integrity.write_checksum_manifest,
+            tag_video_files: app_config.video.tag_video_files,
+            detect_silent_audio: app_config.integrity.detect_silent_audio,
+            cv_name_style: app_config.tagger.cv_name_style,
+            title_language: app_config.tagger.title_language,
+            write_series_tag: app_config.tagger.write_series_tag,
+            write_description_comment: app_config.tagger.write_description_comment,
+            write_nfo: app_config.tagger.write_nfo,
+            write_source_frames: app_config.tagger.write_source_frames,
+            write_rating: app_config.tagger.write_rating,
+            write_age_rating: app_config.tagger.write_age_rating,
+            write_explicit_flag: app_config.tagger.write_explicit_flag,
+            artist_frame_style: app_config.tagger.artist_frame_style,
+            id3_version: app_config.tagger.id3_version,
+            strip_id3v1: app_config.tagger.strip_id3v1,
+            normalize_folders: app_config.tagger.normalize_folders,
+            collect_extras: app_config.tagger.collect_extras,
+            split_cue: app_config.tagger.split_cue,
+            skip_work_types: app_config.tagger.skip_work_types.clone(),
+            replaygain: app_config.tagger.replaygain,
+            io_parallelism: app_config.tagger.io_parallelism,
+            parsing_presets: app_config.track_parsing.presets()?,
+            prompt_track_parsing: app_config.track_parsing.prompt,
+            silence_threshold_db: app_config.track_parsing.silence_threshold_db,
+            silence_min_duration: app_config.track_parsing.silence_min_duration,
+            accept_tracklist: app_config.track_parsing.accept_tracklist,
+            keep_existing_track_numbers: app_config.track_parsing.keep_existing_numbers,
         };
 
         let pb = create_progress_bar(folders_to_process.len() as u64);
@@ -705,7 +1838,7 @@ async fn run_import_workflow(
         for folder in &folders_to_process {
             pb.set_message(format!("Tagging {}", folder.rjcode));
 
-            let result_msg = match process_work_folder(db, folder, &tagger_config).await {
+            let result_msg = match process_work_folder(db, folder, &tagger_config, &client, db_thread.as_ref()).await {
                 Ok(_) => format!("{} tagged ✓", folder.rjcode),
                 Err(e) => {
                     warn!("Failed to tag {}: {}", folder.rjcode, e);
@@ -725,31 +1858,74 @@ async fn run_import_workflow(
     let pb = create_progress_bar(folders_to_process.len() as u64);
     let mut success_count = 0;
     let mut fail_count = 0;
+    let mut kept_count = 0;
+    let tagged: HashSet<String> = queries::get_tagged_works_with_paths(db)?
+        .into_iter()
+        .map(|(rjcode, _)| rjcode.as_str().to_string())
+        .collect();
+    let name_rules = NameRules::from_config(&app_config.import);
 
     for folder in &folders_to_process {
         pb.set_message(format!("Moving {}", folder.rjcode));
 
-        let source = Path::new(&folder.path);
+        if let Some(reason) = move_filter.skip_reason(db, &folder.rjcode, &tagged)? {
+            pb.println(format!("{} kept in the import folder ({})", folder.rjcode, reason));
+            stats.record_work(db, &folder.rjcode, "full", WorkOutcome::Skipped, Some(reason));
+            kept_count += 1;
+            pb.inc(1);
+            continue;
+        }
+
+        let source = folder.path.as_path();
         let folder_name = source.file_name()
-            .ok_or_else(|| format!("Invalid path: {}", folder.path))?;
-        let target = library_path_obj.join(folder_name);
+            .ok_or_else(|| format!("Invalid path: {}", folder.path.display()))?;
+
+        // With organize_by_circle or a folder template, the circle and title are only known now
+        // that metadata was fetched
+        let target = match app_config.import.folder_template.as_deref() {
+            Some(template) => library_path_obj.join(work_folder_from_template(db, &folder.rjcode, template, app_config)?),
+            None => library_parent_dir(db, library_path_obj, &folder.rjcode, app_config.import.organize_by_circle, name_rules)?
+                .join(folders::work_folder_name(folder_name, &folder.rjcode, name_rules)),
+        };
+        let parent = target.parent().unwrap_or(library_path_obj).to_path_buf();
+        if target.exists() {
+            warn!("{} already exists in library, skipping", target.display());
+            pb.println(format!("{} ✗ (already in library)", folder.rjcode));
+            stats.record_work(db, &folder.rjcode, "full", WorkOutcome::Failed, Some("already in library"));
+            fail_count += 1;
+            pb.inc(1);
+            continue;
+        }
+        if let Err(e) = std::fs::create_dir_all(&parent) {
+            warn!("Failed to create {}: {}", parent.display(), e);
+            pb.println(format!("{} ✗", folder.rjcode));
+            stats.record_work(db, &folder.rjcode, "full", WorkOutcome::Failed, Some(&e.to_string()));
+            fail_count += 1;
+            pb.inc(1);
+            continue;
+        }
 
         match move_folder_cross_drive(source, &target) {
             Ok(_) => {
                 // Update path to final library location (folder was already registered earlier)
-                let target_path_str = target.to_string_lossy().to_string();
-                if let Err(e) = queries::update_folder_path(db, &folder.rjcode, &target_path_str) {
+                if let Err(e) = queries::update_folder_path(db, &folder.rjcode, &target) {
                     warn!("Moved {} but failed to update path in DB: {}", folder.rjcode, e);
                     pb.println(&format!("{} ⚠ (DB path error)", folder.rjcode));
+                    stats.record_work(db, &folder.rjcode, "full", WorkOutcome::Failed, Some(&e.to_string()));
                     fail_count += 1;
                 } else {
+                    if let Err(e) = queries::record_files_moved(db, &folder.rjcode, &folder.path, &target) {
+                        warn!("Failed to record the move of {}'s files: {}", folder.rjcode, e);
+                    }
                     pb.println(&format!("{} ✓", folder.rjcode));
+                    stats.record_work(db, &folder.rjcode, "full", WorkOutcome::Processed, None);
                     success_count += 1;
                 }
             }
             Err(e) => {
                 warn!("Failed to move {}: {}", folder.rjcode, e);
                 pb.println(&format!("{} ✗", folder.rjcode));
+                stats.record_work(db, &folder.rjcode, "full", WorkOutcome::Failed, Some(&e.to_string()));
                 fail_count += 1;
             }
         }
@@ -760,7 +1936,9 @@ async fn run_import_workflow(
     pb.finish_and_clear();
 
     info!("\n=== IMPORT COMPLETE ===");
-    info!("Imported: {} | Failed: {}", success_count, fail_count);
+    info!("Imported: {} | Kept in import folder: {} | Failed: {}", success_count, kept_count, fail_count);
+
+    snapshot_after_bulk_run(db, "full", success_count + fail_count, before)?;
 
     Ok(())
 }
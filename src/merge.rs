@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::database::custom_circles::{self, CirclePreferenceType};
+use crate::database::{custom_cvs, custom_tags, db_loader, queries, tables::*};
+use crate::errors::HvtError;
+use crate::export::{self, ExportedWork};
+use crate::folders::types::{RGCode, RJCode};
+
+/// Counters and conflict list for the final `--import-data` report.
+#[derive(Default)]
+struct MergeReport {
+    added_works: usize,
+    updated_works: usize,
+    added_mappings: usize,
+    conflicts: Vec<String>,
+}
+
+/// `--import-data <file|db>`: merge another machine's dataset into the local database —
+/// either a `--export json` file or another hvtag `data.db3` (opened read-only).
+///
+/// Works are reconciled by RJ code: unknown works are added (inactive if their path doesn't
+/// exist on this machine), known works only get their *missing* fields filled in. Local data
+/// always wins — every field where both sides disagree is reported as a conflict and left as-is.
+/// Custom tag/circle/CV mappings (database source only; JSON exports don't carry them) are
+/// merged the same way: added when missing locally, reported when they differ. The import runs
+/// in one transaction: when it fails, nothing is merged.
+pub fn run_merge_workflow(conn: &Connection, source: &str) -> Result<(), HvtError> {
+    let source_path = Path::new(source);
+    if !source_path.is_file() {
+        return Err(HvtError::Generic(format!("Import source not found: {}", source)));
+    }
+
+    let is_json = source_path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+
+    info!("=== IMPORT DATA: {} ===", source_path.display());
+    let mut report = MergeReport::default();
+
+    let tx = conn.unchecked_transaction()?;
+    if is_json {
+        let content = std::fs::read_to_string(source_path)?;
+        let works: Vec<ExportedWork> = serde_json::from_str(&content)
+            .map_err(|e| HvtError::Parse(format!("Invalid export file: {}", e)))?;
+        merge_works(&tx, &works, &mut report)?;
+    } else {
+        let src = db_loader::open_read_only(source_path)?;
+        let mut works = Vec::new();
+        for (rjcode, _) in queries::get_all_works_with_paths(&src)? {
+            if let Some(work) = export::build_exported_work(&src, &rjcode)? {
+                works.push(work);
+            }
+        }
+        merge_works(&tx, &works, &mut report)?;
+        merge_mappings(&tx, &src, &mut report)?;
+    }
+    tx.commit()?;
+
+    info!("\n=== IMPORT DATA COMPLETE ===");
+    info!(
+        "Works added: {} | Works updated: {} | Mappings added: {} | Conflicts: {}",
+        report.added_works, report.updated_works, report.added_mappings, report.conflicts.len()
+    );
+    for conflict in &report.conflicts {
+        warn!("Conflict (kept local): {}", conflict);
+    }
+
+    Ok(())
+}
+
+fn merge_works(conn: &Connection, works: &[ExportedWork], report: &mut MergeReport) -> Result<(), HvtError> {
+    for incoming in works {
+        let rjcode = match RJCode::new(incoming.rjcode.clone()) {
+            Ok(rjcode) => rjcode,
+            Err(e) => {
+                warn!("Skipping invalid work {}: {}", incoming.rjcode, e);
+                continue;
+            }
+        };
+
+        let local = export::build_exported_work(conn, &rjcode)?;
+        if local.is_none() {
+            let active = Path::new(&incoming.path).exists();
+            queries::insert_folder_record(conn, &rjcode, Path::new(&incoming.path), active)?;
+        }
+
+        let changed = merge_work_fields(conn, &rjcode, local.as_ref(), incoming, report)?;
+        if local.is_none() {
+            report.added_works += 1;
+        } else if changed {
+            report.updated_works += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Fills every field the local work is missing from `incoming`, reporting differing ones.
+/// Returns whether anything was written.
+fn merge_work_fields(
+    conn: &Connection,
+    rjcode: &RJCode,
+    local: Option<&ExportedWork>,
+    incoming: &ExportedWork,
+    report: &mut MergeReport,
+) -> Result<bool, HvtError> {
+    let mut changed = false;
+    let mut conflict = |field: &str, local_value: String, incoming_value: String| {
+        report.conflicts.push(format!(
+            "{} {}: local '{}', imported '{}'",
+            rjcode, field, local_value, incoming_value
+        ));
+    };
+
+    // TITLE (get_work_detail falls back to the RJ code when no name was collected)
+    let local_title = local.map(|l| l.title.as_str()).filter(|t| *t != rjcode.as_str());
+    match local_title {
+        None if incoming.title != rjcode.as_str() => {
+            queries::insert_work_name(conn, rjcode, &incoming.title, None)?;
+            changed = true;
+        }
+        Some(title) if title != incoming.title && incoming.title != rjcode.as_str() => {
+            conflict("title", title.to_string(), incoming.title.clone());
+        }
+        _ => {}
+    }
+
+    // RELEASE DATE
+    match (local.and_then(|l| l.release_date.as_deref()), incoming.release_date.as_deref()) {
+        (None, Some(date)) => {
+            queries::assign_release_date_to_work(conn, rjcode, date)?;
+            changed = true;
+        }
+        (Some(l), Some(i)) if l != i => conflict("release date", l.to_string(), i.to_string()),
+        _ => {}
+    }
+
+    // RATING (age category)
+    match (local.and_then(|l| l.rating.as_deref()), incoming.rating.as_deref()) {
+        (None, Some(rating)) => {
+            queries::assign_rating_to_work(conn, rjcode, rating)?;
+            changed = true;
+        }
+        (Some(l), Some(i)) if l != i => conflict("rating", l.to_string(), i.to_string()),
+        _ => {}
+    }
+
+    // STARS (DLSite score moves over time: only filled in, never reported)
+    if local.and_then(|l| l.stars).is_none() {
+        if let Some(stars) = incoming.stars {
+            queries::assign_stars_to_work(conn, rjcode, stars)?;
+            changed = true;
+        }
+    }
+
+    // DESCRIPTION (DLSite edits it over time: only filled in, never reported)
+    if local.and_then(|l| l.description.as_deref()).is_none() {
+        if let Some(description) = &incoming.description {
+            queries::assign_description_to_work(conn, rjcode, description)?;
+            changed = true;
+        }
+    }
+
+    // CIRCLE
+    match (local.and_then(|l| l.circle_code.as_deref()), incoming.circle_code.as_deref()) {
+        (None, Some(code)) => {
+            let rgcode = RGCode::new(code.to_string());
+            if !queries::circle_exists(conn, &rgcode)? {
+                let max_cir_id = queries::get_max_id(conn, "cir_id", DB_CIRCLE_NAME)?;
+                // JSON exports made before raw circle names were exported only carry the merged
+                // display name: use it as the JP name rather than leaving the circle nameless.
+                let name_en = incoming.circle_name_en.clone().unwrap_or_default();
+                let name_jp = incoming.circle_name_jp.clone().unwrap_or_else(|| incoming.circle.clone());
+                queries::insert_circle(conn, &rgcode, &name_en, &name_jp, max_cir_id + 1)?;
+            }
+            queries::assign_circle_to_work(conn, rjcode, &rgcode)?;
+            changed = true;
+        }
+        (Some(l), Some(i)) if l != i => conflict("circle", l.to_string(), i.to_string()),
+        _ => {}
+    }
+
+    // TAGS (raw DLSite tags, so local custom mappings apply on top)
+    let local_tags = local.map(|l| sorted(&l.dlsite_tags)).unwrap_or_default();
+    let incoming_tags = sorted(&incoming.dlsite_tags);
+    if local_tags.is_empty() && !incoming_tags.is_empty() {
+        let mut max_tag_id = queries::get_max_id(conn, "tag_id", DB_DLSITE_TAG_NAME)?;
+        for tag in &incoming_tags {
+            max_tag_id += queries::insert_tag(conn, tag, max_tag_id + 1)?;
+        }
+        queries::assign_tags_to_work(conn, rjcode, &incoming_tags)?;
+        changed = true;
+    } else if !incoming_tags.is_empty() && local_tags != incoming_tags {
+        conflict("tags", local_tags.join(", "), incoming_tags.join(", "));
+    }
+
+    // CVS (raw DLSite names)
+    let local_cvs = local.map(|l| normalized_cvs(&l.dlsite_cvs)).unwrap_or_default();
+    let incoming_cvs = normalized_cvs(&incoming.dlsite_cvs);
+    if local_cvs.is_empty() && !incoming_cvs.is_empty() {
+        for cv in &incoming_cvs {
+            queries::insert_cv(conn, cv, "")?;
+        }
+        queries::assign_cvs_to_work(conn, rjcode, &incoming_cvs)?;
+        changed = true;
+    } else if !incoming_cvs.is_empty() && local_cvs != incoming_cvs {
+        conflict("CVs", local_cvs.join(", "), incoming_cvs.join(", "));
+    }
+
+    Ok(changed)
+}
+
+/// Merges custom tag/circle/CV mappings from another hvtag database. Mappings whose tag/circle/CV
+/// doesn't exist locally (even after merging works) are skipped.
+fn merge_mappings(conn: &Connection, src: &Connection, report: &mut MergeReport) -> Result<(), HvtError> {
+    // Tag mappings: tag_name -> (custom_name, is_ignored)
+    let local_tags: HashMap<String, (Option<String>, bool)> = custom_tags::get_all_custom_mappings(conn)?
+        .into_iter()
+        .map(|(tag, custom, ignored)| (tag, (custom, ignored)))
+        .collect();
+    for (tag, custom, ignored) in custom_tags::get_all_custom_mappings(src)? {
+        match local_tags.get(&tag) {
+            Some(local) if *local == (custom.clone(), ignored) => {}
+            Some(local) => report.conflicts.push(format!(
+                "tag mapping '{}': local {}, imported {}",
+                tag, describe_tag_mapping(&local.0, local.1), describe_tag_mapping(&custom, ignored)
+            )),
+            None => {
+                let result = match (&custom, ignored) {
+                    (_, true) => custom_tags::ignore_tag(conn, &tag),
+                    (Some(name), false) => custom_tags::add_custom_tag_mapping(conn, &tag, name),
+                    (None, false) => continue,
+                };
+                match result {
+                    Ok(_) => report.added_mappings += 1,
+                    Err(HvtError::Database(rusqlite::Error::QueryReturnedNoRows)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    // Circle preferences: rgcode -> (preference_type, custom_name)
+    let local_circles: HashMap<String, (String, Option<String>)> = custom_circles::get_all_custom_circle_preferences(conn)?
+        .into_iter()
+        .map(|(rgcode, _, _, pref, custom)| (rgcode, (pref, custom)))
+        .collect();
+    for (rgcode, _, _, pref, custom) in custom_circles::get_all_custom_circle_preferences(src)? {
+        match local_circles.get(&rgcode) {
+            Some(local) if *local == (pref.clone(), custom.clone()) => {}
+            Some(local) => report.conflicts.push(format!(
+                "circle preference {}: local {} {:?}, imported {} {:?}",
+                rgcode, local.0, local.1, pref, custom
+            )),
+            None => {
+                let Some(pref_type) = CirclePreferenceType::from_str(&pref) else {
+                    continue;
+                };
+                match custom_circles::set_circle_preference(conn, &rgcode, pref_type, custom.as_deref()) {
+                    Ok(_) => report.added_mappings += 1,
+                    Err(HvtError::Database(rusqlite::Error::QueryReturnedNoRows)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    // CV renames: name_jp -> custom_name
+    let local_cvs: HashMap<String, String> = custom_cvs::list_all_cvs_with_counts(conn, custom_cvs::DEFAULT_CV_SORT)?
+        .into_iter()
+        .filter_map(|(_, name_jp, _, custom, _)| custom.map(|c| (name_jp, c)))
+        .collect();
+    for (_, name_jp, _, custom, _) in custom_cvs::list_all_cvs_with_counts(src, custom_cvs::DEFAULT_CV_SORT)? {
+        let Some(custom) = custom else {
+            continue;
+        };
+        match local_cvs.get(&name_jp) {
+            Some(local) if *local == custom => {}
+            Some(local) => report.conflicts.push(format!(
+                "CV rename '{}': local '{}', imported '{}'",
+                name_jp, local, custom
+            )),
+            None => match custom_cvs::add_custom_cv_mapping(conn, &name_jp, &custom) {
+                Ok(_) => report.added_mappings += 1,
+                Err(HvtError::Database(rusqlite::Error::QueryReturnedNoRows)) => {}
+                Err(e) => return Err(e),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn describe_tag_mapping(custom: &Option<String>, ignored: bool) -> String {
+    match (custom, ignored) {
+        (_, true) => "ignored".to_string(),
+        (Some(name), false) => format!("renamed to '{}'", name),
+        (None, false) => "unmapped".to_string(),
+    }
+}
+
+fn sorted(values: &[String]) -> Vec<String> {
+    let mut values = values.to_vec();
+    values.sort();
+    values
+}
+
+/// CV names normalized like fetched ones (full-width parentheses folded) and sorted, so both sides
+/// compare equal whatever the source database kept
+fn normalized_cvs(names: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = names.iter().map(|cv| queries::normalize_cv_name(cv)).collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_json_export_with_conflicting_and_missing_fields() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::database::init(&conn).unwrap();
+        let local = RJCode::new("RJ01000001".to_string()).unwrap();
+        queries::insert_folder_record(&conn, &local, Path::new("/library/RJ01000001"), true).unwrap();
+        queries::insert_work_name(&conn, &local, "Local title", None).unwrap();
+        queries::assign_release_date_to_work(&conn, &local, "2020-01-01").unwrap();
+
+        let works: Vec<ExportedWork> = serde_json::from_value(json!([
+            {
+                "rjcode": "RJ01000001", "title": "Imported title", "circle": "", "tags": [], "cvs": [],
+                "release_date": "2021-02-02", "stars": 4.5, "path": "/elsewhere/RJ01000001",
+                "dlsite_tags": ["ASMR", "Binaural"]
+            },
+            {
+                "rjcode": "RJ01000002", "title": "New work", "circle": "", "tags": [], "cvs": [],
+                "path": "/elsewhere/RJ01000002"
+            }
+        ]))
+        .unwrap();
+        let mut report = MergeReport::default();
+        merge_works(&conn, &works, &mut report).unwrap();
+
+        assert_eq!((report.added_works, report.updated_works), (1, 1));
+        assert_eq!(report.conflicts.len(), 2, "{:?}", report.conflicts);
+
+        // Local values win, missing ones are filled in
+        let merged = export::build_exported_work(&conn, &local).unwrap().unwrap();
+        assert_eq!(merged.title, "Local title");
+        assert_eq!(merged.release_date.as_deref(), Some("2020-01-01"));
+        assert_eq!(merged.stars, Some(4.5));
+        assert_eq!(sorted(&merged.dlsite_tags), vec!["ASMR", "Binaural"]);
+
+        // An unknown work is added, inactive when its folder isn't on this machine
+        let added = RJCode::new("RJ01000002".to_string()).unwrap();
+        assert_eq!(export::build_exported_work(&conn, &added).unwrap().unwrap().title, "New work");
+        let active: bool = conn
+            .query_row(&format!("SELECT active FROM {DB_FOLDERS_NAME} WHERE rjcode = ?1"), [added.as_str()], |row| row.get(0))
+            .unwrap();
+        assert!(!active);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use directories::{BaseDirs, ProjectDirs};
+use tracing::{info, warn};
+
+use crate::errors::HvtError;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+const DB_FILE_NAME: &str = "data.db3";
+const COVER_CACHE_DIR_NAME: &str = "covers_cache";
+const HTTP_CACHE_DIR_NAME: &str = "http_cache";
+const RUNS_DIR_NAME: &str = "runs";
+const LOGS_DIR_NAME: &str = "logs";
+#[cfg(unix)]
+const CONTROL_SOCKET_FILE_NAME: &str = "hvtag.sock";
+/// Next to the database, see `database::backup`
+const BACKUP_DIR_NAME: &str = "backups";
+
+/// Platform locations of hvtag's files:
+/// - Linux: `~/.config/hvtag`, `~/.local/share/hvtag`, `~/.cache/hvtag` (XDG variables respected)
+/// - macOS: `~/Library/Application Support/hvtag`, `~/Library/Caches/hvtag`
+/// - Windows: `%APPDATA%\hvtag\config`, `%LOCALAPPDATA%\hvtag\data`, `%LOCALAPPDATA%\hvtag\cache`
+fn project_dirs() -> Result<ProjectDirs, HvtError> {
+    ProjectDirs::from("", "", "hvtag")
+        .ok_or_else(|| HvtError::Generic("Could not determine home directory".to_string()))
+}
+
+/// Where every file lived before platform locations were used: `~/.hvtag` (the database was
+/// already in `%LOCALAPPDATA%\hvtag` on Windows).
+fn legacy_dir() -> Option<PathBuf> {
+    BaseDirs::new().map(|dirs| dirs.home_dir().join(".hvtag"))
+}
+
+fn legacy_db_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        BaseDirs::new().map(|dirs| dirs.data_local_dir().join("hvtag"))
+    } else {
+        legacy_dir()
+    }
+}
+
+fn ensure_dir(dir: &Path) -> Result<(), HvtError> {
+    if !dir.exists() {
+        fs::create_dir_all(dir).map_err(|_| HvtError::PathCreationFailed(dir.display().to_string()))?;
+    }
+    Ok(())
+}
+
+/// Moves a file or a flat folder from its legacy location when the new one doesn't exist yet.
+/// Falls back to copy + delete across drives. A failed move is only logged: the new location is
+/// then used from scratch and the legacy file is left in place.
+fn migrate_legacy(legacy: &Path, new: &Path) {
+    if new.exists() || !legacy.exists() {
+        return;
+    }
+    let moved = fs::rename(legacy, new).or_else(|_| {
+        if legacy.is_dir() {
+            fs::create_dir_all(new)?;
+            for entry in fs::read_dir(legacy)?.filter_map(|e| e.ok()) {
+                fs::copy(entry.path(), new.join(entry.file_name()))?;
+            }
+            fs::remove_dir_all(legacy)
+        } else {
+            fs::copy(legacy, new)?;
+            fs::remove_file(legacy)
+        }
+    });
+    match moved {
+        Ok(()) => info!("Moved {} to {}", legacy.display(), new.display()),
+        Err(e) => warn!("Failed to move {} to {}: {}", legacy.display(), new.display(), e),
+    }
+}
+
+/// The configuration file, moved from `~/.hvtag/config.toml` on first use.
+pub fn config_file() -> Result<PathBuf, HvtError> {
+    let dir = project_dirs()?.config_dir().to_path_buf();
+    ensure_dir(&dir)?;
+    let path = dir.join(CONFIG_FILE_NAME);
+    if let Some(legacy) = legacy_dir() {
+        migrate_legacy(&legacy.join(CONFIG_FILE_NAME), &path);
+    }
+    Ok(path)
+}
+
+/// The default database file, moved (with its backups) from the legacy folder on first use.
+pub fn database_file() -> Result<PathBuf, HvtError> {
+    let dir = project_dirs()?.data_local_dir().to_path_buf();
+    ensure_dir(&dir)?;
+    let path = dir.join(DB_FILE_NAME);
+    if let Some(legacy) = legacy_db_dir() {
+        if !path.exists() && legacy.join(DB_FILE_NAME).exists() {
+            migrate_legacy(&legacy.join(BACKUP_DIR_NAME), &dir.join(BACKUP_DIR_NAME));
+        }
+        migrate_legacy(&legacy.join(DB_FILE_NAME), &path);
+    }
+    Ok(path)
+}
+
+/// Default database of a named library (`[library.<name>]` without `db_path`), in its own folder
+/// so that its backups are kept apart from the other libraries'.
+pub fn library_database_file(name: &str) -> Result<PathBuf, HvtError> {
+    let dir = project_dirs()?.data_local_dir().join("libraries").join(name);
+    ensure_dir(&dir)?;
+    Ok(dir.join(DB_FILE_NAME))
+}
+
+/// The cover cache folder, moved from `~/.hvtag/covers_cache` on first use.
+pub fn cover_cache_dir() -> Result<PathBuf, HvtError> {
+    let dir = project_dirs()?.cache_dir().join(COVER_CACHE_DIR_NAME);
+    if let Some(legacy) = legacy_dir() {
+        if let Some(parent) = dir.parent() {
+            ensure_dir(parent)?;
+        }
+        migrate_legacy(&legacy.join(COVER_CACHE_DIR_NAME), &dir);
+    }
+    ensure_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Cached DLSite responses (see `dlsite::http_cache`).
+pub fn http_cache_dir() -> Result<PathBuf, HvtError> {
+    let dir = project_dirs()?.cache_dir().join(HTTP_CACHE_DIR_NAME);
+    ensure_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Run reports (see `RunStats::write_report`), next to the database.
+pub fn runs_dir() -> Result<PathBuf, HvtError> {
+    let dir = project_dirs()?.data_local_dir().join(RUNS_DIR_NAME);
+    ensure_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Default `[logging]` folder, next to the database.
+pub fn logs_dir() -> Result<PathBuf, HvtError> {
+    let dir = project_dirs()?.data_local_dir().join(LOGS_DIR_NAME);
+    ensure_dir(&dir)?;
+    Ok(dir)
+}
+
+/// Default `--control-socket` path, next to the database.
+#[cfg(unix)]
+pub fn control_socket() -> Result<PathBuf, HvtError> {
+    let dir = project_dirs()?.data_local_dir().to_path_buf();
+    ensure_dir(&dir)?;
+    Ok(dir.join(CONTROL_SOCKET_FILE_NAME))
+}
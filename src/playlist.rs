@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+use clap::ValueEnum;
+use id3::TagLike;
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::database::queries;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::run_stats::RunStats;
+use crate::sanitize;
+use crate::tagger::{self, types::{AudioMetadata, TaggerConfig}};
+
+/// How `--playlist` groups works into playlists.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum PlaylistGrouping {
+    /// One playlist per work
+    Work,
+    Circle,
+    Cv,
+    Tag,
+}
+
+impl PlaylistGrouping {
+    /// Subfolder of the output folder holding this grouping's playlists
+    fn folder_name(&self) -> &'static str {
+        match self {
+            PlaylistGrouping::Work => "by_work",
+            PlaylistGrouping::Circle => "by_circle",
+            PlaylistGrouping::Cv => "by_cv",
+            PlaylistGrouping::Tag => "by_tag",
+        }
+    }
+}
+
+struct Track {
+    path: PathBuf,
+    title: String,
+    number: Option<u32>,
+}
+
+struct PlaylistWork {
+    rjcode: RJCode,
+    metadata: AudioMetadata,
+    tracks: Vec<Track>,
+}
+
+/// Audio files of a work in track order (ID3 track number, then file name), with the ID3 title
+/// or the file name.
+fn read_tracks(folder_path: &Path) -> Result<Vec<Track>, HvtError> {
+    let mut tracks: Vec<Track> = tagger::list_audio_files(folder_path)?
+        .into_iter()
+        .map(|path| {
+            let tag = id3::Tag::read_from_path(&path).ok();
+            let title = tag.as_ref()
+                .and_then(|t| t.title().map(str::to_string))
+                .unwrap_or_else(|| path.file_stem().unwrap_or_default().to_string_lossy().to_string());
+            let number = tag.as_ref().and_then(|t| t.track());
+            Track { path, title, number }
+        })
+        .collect();
+    tracks.sort_by(|a, b| (a.number.unwrap_or(u32::MAX), &a.path).cmp(&(b.number.unwrap_or(u32::MAX), &b.path)));
+    Ok(tracks)
+}
+
+/// `target` relative to the `base` directory (`../Circle/RJ01000001/01.mp3`). `None` when no
+/// relative path exists (different Windows drives).
+fn relative_path(base: &Path, target: &Path) -> Option<PathBuf> {
+    let base: Vec<Component> = base.components().collect();
+    let target: Vec<Component> = target.components().collect();
+    if base.first() != target.first() {
+        return None;
+    }
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &target[common..] {
+        relative.push(component.as_os_str());
+    }
+    Some(relative)
+}
+
+/// Extended M3U content, UTF-8 (`.m3u8`), with paths relative to `playlist_dir`
+fn build_playlist(playlist_dir: &Path, works: &[&PlaylistWork]) -> String {
+    let mut content = String::from("#EXTM3U\n");
+    for work in works {
+        for track in &work.tracks {
+            let path = relative_path(playlist_dir, &track.path).unwrap_or_else(|| track.path.clone());
+            content.push_str(&format!("#EXTINF:-1,{} - {}\n", work.metadata.album_artist, track.title));
+            content.push_str(&format!("{}\n", path.display()));
+        }
+    }
+    content
+}
+
+/// `--playlist <work|circle|cv|tag>`: writes one `.m3u8` playlist per work, circle, CV or tag
+/// into `<output>/by_<grouping>/` (`[playlists] output_path`, `<library_path>/playlists` by
+/// default), replacing the playlists of a previous run. Works are ordered by release date,
+/// tracks by track number; paths are relative to the playlist so the library can be mounted
+/// elsewhere. Circle, CV and tag names are the merged ones the tagger writes.
+pub fn run_playlist_workflow(conn: &Connection, app_config: &Config, grouping: PlaylistGrouping, stats: &RunStats) -> Result<(), HvtError> {
+    let output_path = match (&app_config.playlists.output_path, &app_config.import.library_path) {
+        (Some(output_path), _) => PathBuf::from(output_path),
+        (None, Some(library_path)) => Path::new(library_path).join("playlists"),
+        (None, None) => {
+            return Err(HvtError::Generic(
+                "set [playlists] output_path or [import] library_path in config.toml".to_string(),
+            ))
+        }
+    };
+    let playlist_dir = output_path.join(grouping.folder_name());
+
+    let tagger_config = TaggerConfig {
+        tag_separator: app_config.tagger.get_separator(),
+        cv_name_style: app_config.tagger.cv_name_style,
+        title_language: app_config.tagger.title_language,
+        ..Default::default()
+    };
+
+    let mut works: Vec<PlaylistWork> = Vec::new();
+    for (rjcode, folder_path) in queries::get_all_works_with_paths(conn)? {
+        if !folder_path.is_dir() {
+            warn!("{} not found on disk, skipping", folder_path.display());
+            continue;
+        }
+        let tracks = read_tracks(&folder_path)?;
+        if tracks.is_empty() {
+            continue;
+        }
+        let metadata = tagger::fetch_metadata_from_db(conn, &rjcode, &tagger_config)?;
+        works.push(PlaylistWork { rjcode, metadata, tracks });
+    }
+    works.sort_by(|a, b| (&a.metadata.date, a.rjcode.as_str()).cmp(&(&b.metadata.date, b.rjcode.as_str())));
+
+    let mut playlists: BTreeMap<String, Vec<&PlaylistWork>> = BTreeMap::new();
+    for work in &works {
+        let names = match grouping {
+            PlaylistGrouping::Work => vec![format!("{} {}", work.rjcode, work.metadata.album)],
+            PlaylistGrouping::Circle => vec![work.metadata.album_artist.clone()],
+            PlaylistGrouping::Cv => work.metadata.artists.clone(),
+            PlaylistGrouping::Tag => work.metadata.all_tags(),
+        };
+        for name in names {
+            playlists.entry(sanitize::folder_name(&name)).or_default().push(work);
+        }
+    }
+
+    std::fs::create_dir_all(&playlist_dir)
+        .map_err(|_| HvtError::PathCreationFailed(playlist_dir.display().to_string()))?;
+    for entry in std::fs::read_dir(&playlist_dir)?.flatten() {
+        if entry.path().extension().is_some_and(|e| e == "m3u8") {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    for (name, works) in &playlists {
+        std::fs::write(playlist_dir.join(format!("{}.m3u8", name)), build_playlist(&playlist_dir, works))?;
+        stats.add_file_written();
+    }
+
+    info!("Wrote {} playlist(s) to {}", playlists.len(), playlist_dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(Path::new("/library/playlists/by_cv"), Path::new("/library/Circle/RJ01000001/01.mp3")),
+            Some(PathBuf::from("../../Circle/RJ01000001/01.mp3"))
+        );
+        assert_eq!(
+            relative_path(Path::new("/library"), Path::new("/library/RJ01000001/01.mp3")),
+            Some(PathBuf::from("RJ01000001/01.mp3"))
+        );
+    }
+}
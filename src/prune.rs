@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::database::queries;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::run_stats::{RunStats, WorkOutcome};
+use crate::tagger::cover_art;
+
+/// `--prune`: finds active works whose folder no longer exists on disk and deactivates them (or
+/// deletes them with `purge`, like `--remove`) after confirmation. The first time a folder is
+/// found missing is recorded in `folders.missing_since`; it is only pruned once it has been
+/// missing for `offline_grace_days`, so a share that is temporarily unmounted doesn't empty the
+/// library. A folder found again is no longer counted as missing.
+pub fn run_prune_workflow(
+    conn: &Connection,
+    offline_grace_days: u32,
+    purge: bool,
+    confirm: bool,
+    stats: &RunStats,
+) -> Result<(), HvtError> {
+    info!("=== PRUNE{} ===", if purge { " (purge)" } else { "" });
+
+    let mut stale: Vec<(RJCode, PathBuf)> = Vec::new();
+    let mut in_grace = 0usize;
+    for (rjcode, path) in queries::get_all_works_with_paths(conn)? {
+        if !path.as_os_str().is_empty() && path.exists() {
+            queries::set_folder_missing(conn, &rjcode, false)?;
+            continue;
+        }
+        let days_missing = queries::set_folder_missing(conn, &rjcode, true)?;
+        if days_missing < offline_grace_days as f64 {
+            in_grace += 1;
+            continue;
+        }
+        stale.push((rjcode, path));
+    }
+
+    if in_grace > 0 {
+        info!("{} missing folder(s) kept until they have been missing for {} day(s)", in_grace, offline_grace_days);
+    }
+    if stale.is_empty() {
+        info!("No stale folder to prune");
+        return Ok(());
+    }
+
+    println!("\nFolders no longer on disk:");
+    for (rjcode, path) in &stale {
+        println!("  {}  {}", rjcode, path.display());
+    }
+
+    if confirm {
+        let prompt = format!(
+            "{} these {} work(s)?",
+            if purge { "Delete" } else { "Deactivate" },
+            stale.len()
+        );
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .default(false)
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))?;
+        if !confirmed {
+            info!("Cancelled, nothing was pruned");
+            return Ok(());
+        }
+    }
+
+    let mut failed = 0usize;
+    for (rjcode, _) in &stale {
+        let result = if purge {
+            // Counted for the footer; the history row is deleted with the work right after
+            stats.record_work(conn, rjcode, "prune", WorkOutcome::Processed, None);
+            queries::delete_work_permanently(conn, rjcode).map(|()| {
+                cover_art::discard_cached_cover(rjcode.as_str());
+                cover_art::discard_cached_sample_images(rjcode.as_str());
+            })
+        } else {
+            queries::deactivate_work(conn, rjcode)
+                .map(|()| stats.record_work(conn, rjcode, "prune", WorkOutcome::Processed, None))
+        };
+        if let Err(e) = result {
+            warn!("Failed to prune {}: {}", rjcode, e);
+            failed += 1;
+        }
+    }
+
+    info!(
+        "=== PRUNE COMPLETE: {} work(s) {} | Failed: {} ===",
+        stale.len() - failed,
+        if purge { "deleted" } else { "deactivated" },
+        failed
+    );
+    Ok(())
+}
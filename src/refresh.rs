@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::database::queries;
+use crate::database::review_queue::ReviewReason;
+use crate::dlsite::{assign_data_to_work_with_client, DataSelection, MetadataClient};
+use crate::errors::HvtError;
+use crate::run_stats::{RunStats, WorkOutcome};
+use crate::tagger;
+
+/// Metadata re-fetched by `--refresh` (`--fields`).
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum RefreshField {
+    Tags,
+    Circle,
+    Cvs,
+    ReleaseDate,
+    Rating,
+    Stars,
+    Description,
+    Relations,
+}
+
+/// Only the selected fields are replaced; the title is always updated with them.
+fn data_selection(fields: &[RefreshField]) -> DataSelection {
+    DataSelection {
+        tags: fields.contains(&RefreshField::Tags),
+        release_date: fields.contains(&RefreshField::ReleaseDate),
+        circle: fields.contains(&RefreshField::Circle),
+        rating: fields.contains(&RefreshField::Rating),
+        cvs: fields.contains(&RefreshField::Cvs),
+        stars: fields.contains(&RefreshField::Stars),
+        cover_link: false,
+        description: fields.contains(&RefreshField::Description),
+        relations: fields.contains(&RefreshField::Relations),
+        sample_images: false,
+    }
+}
+
+/// `--older-than` value: a number of days, optionally suffixed with `d`, or weeks with `w`
+/// (`90d`, `12w`, `30`).
+pub fn parse_age_days(value: &str) -> Result<u32, String> {
+    let value = value.trim();
+    let (number, factor) = match value.strip_suffix('w') {
+        Some(weeks) => (weeks, 7),
+        None => (value.strip_suffix('d').unwrap_or(value), 1),
+    };
+    number
+        .parse::<u32>()
+        .map(|n| n * factor)
+        .map_err(|_| format!("invalid age '{}', expected e.g. 90d or 12w", value))
+}
+
+/// `--refresh`: re-fetches the selected fields of works already collected (ratings, stars and
+/// tags drift on DLSite), optionally only those last fetched more than `older_than_days` ago.
+/// When what would be written to the files changes, the work is marked for re-tagging: the
+/// next `--tag` picks it up.
+pub async fn run_refresh_workflow(
+    db: &Connection,
+    app_config: &Config,
+    older_than_days: Option<u32>,
+    fields: &[RefreshField],
+    stats: &Arc<RunStats>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    crate::mark_works_with_other_tagger_settings(db, app_config)?;
+
+    let works = queries::get_works_scanned_before(db, older_than_days)?;
+    if works.is_empty() {
+        info!("No work to refresh");
+        return Ok(());
+    }
+    let fields = if fields.is_empty() { RefreshField::value_variants() } else { fields };
+
+    info!("=== REFRESH: {} work(s) ===", works.len());
+
+    // Compared before and after the fetch, as the tagger would write it
+    let tagger_config = crate::library_tagger_config(app_config, true)?;
+
+    let vpn_manager = crate::connect_vpn_if_enabled(app_config)?;
+    let client = MetadataClient::from_config(app_config, stats)?.refetching();
+
+    let pb = crate::create_progress_bar(works.len() as u64);
+    let mut changed = 0usize;
+    let mut unchanged = 0usize;
+    let mut failed = 0usize;
+    for rjcode in &works {
+        pb.set_message(format!("Refreshing {}", rjcode));
+        let before = tagger::fetch_metadata_from_db(db, rjcode, &tagger_config)?;
+
+        let result = assign_data_to_work_with_client(db, rjcode.clone(), data_selection(fields), &client).await;
+        let failure = match &result {
+            Err(HvtError::RemovedWork(_) | HvtError::Offline(_)) | Ok(_) => None,
+            Err(e) => Some(e.to_string()),
+        };
+        tagger::update_review_queue(db, rjcode, ReviewReason::ScrapeFailed, failure.as_deref());
+
+        match result {
+            Ok(()) => {
+                let after = tagger::fetch_metadata_from_db(db, rjcode, &tagger_config)?;
+                if after != before {
+                    queries::mark_work_for_retagging(db, rjcode)?;
+                    pb.println(format!("{} ✓ changed", rjcode));
+                    changed += 1;
+                } else {
+                    unchanged += 1;
+                }
+                stats.record_work(db, rjcode, "refresh", WorkOutcome::Processed, None);
+            }
+            Err(HvtError::RemovedWork(_)) => {
+                pb.println(format!("{} - removed from DLSite", rjcode));
+                stats.record_work(db, rjcode, "refresh", WorkOutcome::Skipped, Some("removed from DLSite"));
+            }
+            Err(e @ HvtError::Challenge(_)) => {
+                warn!("Failed to refresh {}: {}", rjcode, e);
+                queries::insert_error(db, rjcode, &e.to_string(), Some("dlsite_challenge"))?;
+                pb.println(format!("{} ✗ DLSite robot check", rjcode));
+                stats.record_work(db, rjcode, "refresh", WorkOutcome::Failed, Some(&e.to_string()));
+                failed += 1;
+            }
+            Err(e @ HvtError::Offline(_)) => {
+                pb.println(format!("{} - not cached", rjcode));
+                stats.record_work(db, rjcode, "refresh", WorkOutcome::Skipped, Some(&e.to_string()));
+            }
+            Err(e) => {
+                warn!("Failed to refresh {}: {}", rjcode, e);
+                pb.println(format!("{} ✗", rjcode));
+                stats.record_work(db, rjcode, "refresh", WorkOutcome::Failed, Some(&e.to_string()));
+                failed += 1;
+            }
+        }
+        pb.inc(1);
+    }
+    pb.finish_and_clear();
+    crate::disconnect_vpn(vpn_manager)?;
+
+    info!(
+        "=== REFRESH COMPLETE: {} changed (marked for re-tagging, run --tag), {} unchanged, {} failed ===",
+        changed, unchanged, failed
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_age_days() {
+        assert_eq!(parse_age_days("90d"), Ok(90));
+        assert_eq!(parse_age_days("12w"), Ok(84));
+        assert_eq!(parse_age_days("30"), Ok(30));
+        assert!(parse_age_days("3m").is_err());
+    }
+}
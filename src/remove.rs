@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use dialoguer::{Confirm, theme::ColorfulTheme};
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::database::{queries, web_queries};
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::run_stats::{RunStats, WorkOutcome};
+use crate::tagger::cover_art;
+
+/// What `--remove` does with the work's folder on disk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FolderRemoval {
+    /// Leave the folder where it is
+    Keep,
+    /// Move it to a sibling `.trash/<rjcode>` folder, like the web UI's trash action
+    Trash,
+    /// Delete it with everything inside
+    Delete,
+}
+
+/// `--remove <rjcode>`: takes a work out of the library. By default the work is only deactivated
+/// (`folders.active = 0`, hidden from every command, child rows kept so it can be restored by
+/// hand); with `purge` every row of the work is deleted (see `queries::delete_work_permanently`)
+/// along with its cached cover and sample images. The folder on disk is handled first: if
+/// trashing or deleting it fails, the database is not touched.
+pub fn run_remove_workflow(
+    conn: &Connection,
+    rjcode: &str,
+    purge: bool,
+    folder_removal: FolderRemoval,
+    confirm: bool,
+    stats: &RunStats,
+) -> Result<(), HvtError> {
+    let rjcode = RJCode::new(rjcode.to_string())?;
+    if !queries::rjcode_exists(conn, &rjcode)? {
+        return Err(HvtError::Generic(format!("{} not found in the database", rjcode)));
+    }
+    let folder_path = queries::get_work_path(conn, &rjcode)?.filter(|p| !p.as_os_str().is_empty());
+
+    info!("=== REMOVE {}{} ===", rjcode, if purge { " (purge)" } else { "" });
+
+    if folder_removal == FolderRemoval::Delete && confirm {
+        let prompt = format!(
+            "Delete {} from disk? This cannot be undone",
+            folder_path.as_ref().map_or("the work folder".into(), |p| p.display().to_string())
+        );
+        let confirmed = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .default(false)
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))?;
+        if !confirmed {
+            info!("Cancelled, nothing was removed");
+            return Ok(());
+        }
+    }
+
+    let mut trashed_to = None;
+    match (&folder_path, folder_removal) {
+        (_, FolderRemoval::Keep) => {}
+        (Some(path), _) if !path.exists() => {
+            warn!("{} no longer exists on disk, only the database is updated", path.display());
+        }
+        (Some(path), FolderRemoval::Trash) => {
+            let target = trash_folder(path, &rjcode)?;
+            info!("Moved {} to {}", path.display(), target.display());
+            trashed_to = Some(target);
+        }
+        (Some(path), FolderRemoval::Delete) => {
+            std::fs::remove_dir_all(path)?;
+            info!("Deleted {}", path.display());
+        }
+        (None, _) => warn!("{} has no folder path, only the database is updated", rjcode),
+    }
+
+    if purge {
+        // Counted for the footer; the history row is deleted with the work right after
+        stats.record_work(conn, &rjcode, "remove", WorkOutcome::Processed, None);
+        queries::delete_work_permanently(conn, &rjcode)?;
+        cover_art::discard_cached_cover(rjcode.as_str());
+        cover_art::discard_cached_sample_images(rjcode.as_str());
+        info!("=== REMOVE COMPLETE: {} deleted from the database ===", rjcode);
+    } else {
+        match &trashed_to {
+            Some(target) => web_queries::deactivate_and_relocate_work(conn, &rjcode, target)?,
+            None => queries::deactivate_work(conn, &rjcode)?,
+        }
+        stats.record_work(conn, &rjcode, "remove", WorkOutcome::Processed, None);
+        info!("=== REMOVE COMPLETE: {} deactivated (--remove {} --purge deletes it) ===", rjcode, rjcode);
+    }
+    Ok(())
+}
+
+/// Moves the folder to `<parent>/.trash/<rjcode>` (same volume, so usually a plain rename).
+fn trash_folder(source: &Path, rjcode: &RJCode) -> Result<PathBuf, HvtError> {
+    let parent = source
+        .parent()
+        .ok_or_else(|| HvtError::Generic(format!("{} has no parent directory", source.display())))?;
+    let trash_dir = parent.join(".trash");
+    let target = trash_dir.join(rjcode.as_str());
+    if target.exists() {
+        return Err(HvtError::Generic(format!("{} already exists", target.display())));
+    }
+    std::fs::create_dir_all(&trash_dir)
+        .map_err(|_| HvtError::PathCreationFailed(trash_dir.display().to_string()))?;
+    crate::move_folder_cross_drive(source, &target)?;
+    Ok(target)
+}
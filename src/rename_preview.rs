@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use dialoguer::console::style;
+use dialoguer::{theme::ColorfulTheme, Confirm, MultiSelect};
+
+use crate::errors::HvtError;
+
+/// A folder rename: (current path, new path)
+type Rename = (PathBuf, PathBuf);
+
+/// Number of renames shown per preview page.
+const PAGE_SIZE: usize = 20;
+
+/// Shows a paginated old → new preview of a batch of renames and lets the user exclude
+/// individual entries before anything is applied. Entries that would conflict — target already
+/// exists on disk, or several entries renamed to the same target — are highlighted and
+/// pre-excluded. Returns the renames the user approved (empty if the batch was cancelled).
+pub fn review_renames(renames: Vec<(PathBuf, PathBuf)>) -> Result<Vec<(PathBuf, PathBuf)>, HvtError> {
+    if renames.is_empty() {
+        return Ok(renames);
+    }
+
+    let conflicts = find_conflicts(&renames);
+    let total_pages = renames.len().div_ceil(PAGE_SIZE);
+    let mut approved: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+    println!("\n=== Rename preview: {} folder(s), {} conflict(s) ===", renames.len(), conflicts.len());
+
+    for (page_index, page) in renames.chunks(PAGE_SIZE).enumerate() {
+        let items: Vec<String> = page
+            .iter()
+            .enumerate()
+            .map(|(i, (old, new))| {
+                let line = format!("{}  →  {}", display_name(old), display_name(new));
+                match conflicts.get(&(page_index * PAGE_SIZE + i)) {
+                    Some(reason) => format!("{} {}", style(line).red(), style(format!("⚠ {}", reason)).yellow()),
+                    None => line,
+                }
+            })
+            .collect();
+        let defaults: Vec<bool> = (0..page.len())
+            .map(|i| !conflicts.contains_key(&(page_index * PAGE_SIZE + i)))
+            .collect();
+
+        let selected = MultiSelect::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "Page {}/{} - renames to apply (space: toggle, enter: next)",
+                page_index + 1, total_pages
+            ))
+            .items(&items)
+            .defaults(&defaults)
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+        approved.extend(selected.into_iter().map(|i| page[i].clone()));
+    }
+
+    let excluded = renames.len() - approved.len();
+    let confirm = Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Apply {} rename(s) ({} excluded)?", approved.len(), excluded))
+        .default(true)
+        .interact()
+        .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))?;
+
+    if !confirm {
+        println!("Renames cancelled, no folder will be renamed.");
+        return Ok(Vec::new());
+    }
+
+    Ok(approved)
+}
+
+/// Without a prompt (`--non-interactive`, control socket): the renames that can be applied
+/// cleanly, and the conflicting ones with their reason, which are left untouched.
+pub fn split_conflicts(renames: Vec<Rename>) -> (Vec<Rename>, Vec<(Rename, &'static str)>) {
+    let conflicts = find_conflicts(&renames);
+    let mut approved = Vec::new();
+    let mut rejected = Vec::new();
+    for (i, rename) in renames.into_iter().enumerate() {
+        match conflicts.get(&i) {
+            Some(reason) => rejected.push((rename, *reason)),
+            None => approved.push(rename),
+        }
+    }
+    (approved, rejected)
+}
+
+/// Index → reason for every entry whose rename can't be applied cleanly.
+fn find_conflicts(renames: &[(PathBuf, PathBuf)]) -> HashMap<usize, &'static str> {
+    let mut target_counts: HashMap<&PathBuf, usize> = HashMap::new();
+    for (_, new) in renames {
+        *target_counts.entry(new).or_insert(0) += 1;
+    }
+
+    renames
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (old, new))| {
+            if target_counts[new] > 1 {
+                Some((i, "duplicate target"))
+            } else if new != old && new.exists() {
+                Some((i, "target already exists"))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn display_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_conflicts_keeps_clean_renames() {
+        let renames = vec![
+            (PathBuf::from("/src/[A] RJ01000001"), PathBuf::from("/src/RJ01000001")),
+            (PathBuf::from("/src/RJ01000002 v1"), PathBuf::from("/src/RJ01000002")),
+            (PathBuf::from("/src/RJ01000002 v2"), PathBuf::from("/src/RJ01000002")),
+        ];
+        let (approved, conflicts) = split_conflicts(renames.clone());
+        assert_eq!(approved, renames[..1]);
+        assert_eq!(conflicts, [(renames[1].clone(), "duplicate target"), (renames[2].clone(), "duplicate target")]);
+    }
+}
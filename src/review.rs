@@ -0,0 +1,176 @@
+use std::sync::Arc;
+
+use dialoguer::{Input, Select, theme::ColorfulTheme};
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::database::custom_circles::{self, CirclePreferenceType};
+use crate::database::queries;
+use crate::database::review_queue::{self, ReviewReason};
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::run_stats::RunStats;
+
+/// What the user chose for one review item.
+enum ReviewAction {
+    /// Refresh and re-tag the work with prompts forced on
+    Retag,
+    /// Give the work's circle a custom name
+    NameCircle,
+    Resolve,
+    Skip,
+    Quit,
+}
+
+/// `--review`: walks the review queue (works tagged with a best guess under --non-interactive,
+/// nameless circles, failed DLSite fetches) one item at a time, oldest first.
+pub async fn run_review_workflow(conn: &Connection, app_config: &Config, stats: &Arc<RunStats>) -> Result<(), Box<dyn std::error::Error>> {
+    let items = review_queue::get_review_items(conn)?;
+    if items.is_empty() {
+        info!("Nothing to review");
+        return Ok(());
+    }
+
+    // The work is re-tagged as if it was new: parse filenames again and ask about tracklists
+    let mut retag_config = app_config.clone();
+    retag_config.track_parsing.prompt = true;
+    retag_config.track_parsing.keep_existing_numbers = false;
+    retag_config.track_parsing.accept_tracklist = false;
+
+    let total = items.len();
+    let mut resolved = 0usize;
+    for (i, (rjcode, reason, details)) in items.into_iter().enumerate() {
+        let rjcode = RJCode::new(rjcode)?;
+        let Some(reason) = ReviewReason::from_str(&reason) else {
+            warn!("Unknown review reason '{}' for {}, skipping", reason, rjcode);
+            continue;
+        };
+
+        // Several works of one circle are queued: naming the circle for the first settles the rest
+        if reason == ReviewReason::MissingCircleName && circle_is_named(conn, &rjcode)? {
+            review_queue::resolve_review_item(conn, &rjcode, reason)?;
+            resolved += 1;
+            continue;
+        }
+
+        println!("\n[{}/{}] {} — {}", i + 1, total, rjcode, reason_label(reason));
+        if let Some(details) = &details {
+            println!("  {}", details);
+        }
+        if let Ok(Some(path)) = queries::get_work_path(conn, &rjcode) {
+            println!("  {}", path.display());
+        }
+
+        match prompt_action(reason)? {
+            ReviewAction::Retag => {
+                if let Err(e) = crate::run_retag_workflow(conn, rjcode.as_str(), &retag_config, stats).await {
+                    warn!("Re-tagging {} failed: {}", rjcode, e);
+                    continue;
+                }
+                // The tagger resolves track numbering and tracklist items itself; a successful
+                // refresh settles a failed fetch
+                if reason == ReviewReason::ScrapeFailed {
+                    review_queue::resolve_review_item(conn, &rjcode, reason)?;
+                }
+                if !is_still_queued(conn, &rjcode, reason)? {
+                    resolved += 1;
+                }
+            }
+            ReviewAction::NameCircle => {
+                if name_circle(conn, &rjcode)? {
+                    review_queue::resolve_review_item(conn, &rjcode, reason)?;
+                    resolved += 1;
+                }
+            }
+            ReviewAction::Resolve => {
+                review_queue::resolve_review_item(conn, &rjcode, reason)?;
+                resolved += 1;
+            }
+            ReviewAction::Skip => {}
+            ReviewAction::Quit => break,
+        }
+    }
+
+    let remaining = review_queue::get_review_items(conn)?.len();
+    info!("Review done: {} item(s) resolved, {} left in the queue", resolved, remaining);
+    Ok(())
+}
+
+fn reason_label(reason: ReviewReason) -> &'static str {
+    match reason {
+        ReviewReason::TrackNumbering => "uncertain track numbering",
+        ReviewReason::Tracklist => "unconfirmed tracklist titles",
+        ReviewReason::MissingCircleName => "circle has no name",
+        ReviewReason::ScrapeFailed => "DLSite fetch failed",
+        ReviewReason::RenameConflict => "source folder not renamed (conflict)",
+        ReviewReason::DamagedAudio => "empty or silent audio files",
+    }
+}
+
+fn prompt_action(reason: ReviewReason) -> Result<ReviewAction, HvtError> {
+    let mut options = match reason {
+        ReviewReason::TrackNumbering => vec![("Re-number and re-tag now", ReviewAction::Retag)],
+        ReviewReason::Tracklist => vec![("Review the tracklist and re-tag now", ReviewAction::Retag)],
+        ReviewReason::MissingCircleName => vec![("Set a circle name", ReviewAction::NameCircle)],
+        ReviewReason::ScrapeFailed => vec![("Fetch again and re-tag now", ReviewAction::Retag)],
+        // Settled by hand: rename or remove one of the folders, then mark as resolved
+        ReviewReason::RenameConflict => Vec::new(),
+        // Once the files have been downloaded again, re-tagging checks them again
+        ReviewReason::DamagedAudio => vec![("Check again and re-tag now", ReviewAction::Retag)],
+    };
+    options.push(("Mark as resolved", ReviewAction::Resolve));
+    options.push(("Skip", ReviewAction::Skip));
+    options.push(("Quit", ReviewAction::Quit));
+
+    let labels: Vec<&str> = options.iter().map(|(label, _)| *label).collect();
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Action")
+        .items(&labels)
+        .default(0)
+        .interact()
+        .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+    Ok(options.swap_remove(selection).1)
+}
+
+/// Whether the work's circle got a name since it was queued (new scrape or --manage-circles).
+fn circle_is_named(conn: &Connection, rjcode: &RJCode) -> Result<bool, HvtError> {
+    let Some(rgcode) = queries::get_circle_code_for_work(conn, rjcode)? else {
+        return Ok(false);
+    };
+    let (_, _, name_en, name_jp) = custom_circles::get_circle_info(conn, &rgcode)?;
+    Ok(!name_en.is_empty() || !name_jp.is_empty() || custom_circles::get_circle_preference(conn, &rgcode)?.is_some())
+}
+
+/// Asks for a custom name for the work's circle. Returns false when cancelled.
+fn name_circle(conn: &Connection, rjcode: &RJCode) -> Result<bool, HvtError> {
+    let Some(rgcode) = queries::get_circle_code_for_work(conn, rjcode)? else {
+        println!("No circle is assigned to {}, fetch it again first.", rjcode);
+        return Ok(false);
+    };
+
+    let name: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt(format!("Name for circle {} (empty to cancel)", rgcode))
+        .allow_empty(true)
+        .interact_text()
+        .map_err(|e| HvtError::Parse(format!("Input error: {}", e)))?;
+    if name.trim().is_empty() {
+        println!("Cancelled.");
+        return Ok(false);
+    }
+
+    custom_circles::set_circle_preference(conn, &rgcode, CirclePreferenceType::Custom, Some(name.trim()))?;
+    let files_marked = custom_circles::mark_circle_works_for_retagging(conn, &rgcode)?;
+    println!("✓ Circle {} is now '{}'", rgcode, name.trim());
+    if files_marked > 0 {
+        println!("✓ {} file(s) marked for re-tagging, run --tag to apply", files_marked);
+    }
+    Ok(true)
+}
+
+fn is_still_queued(conn: &Connection, rjcode: &RJCode, reason: ReviewReason) -> Result<bool, HvtError> {
+    Ok(review_queue::get_review_items(conn)?
+        .iter()
+        .any(|(code, r, _)| code == rjcode.as_str() && r == reason.as_str()))
+}
@@ -0,0 +1,285 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rusqlite::Connection;
+use serde::Serialize;
+use tracing::{debug, info, warn};
+
+use crate::config::RunReportsConfig;
+use crate::database::queries;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::timestamp;
+
+/// Called with every work recorded, e.g. to notify the clients of the control socket
+pub type WorkObserver = Box<dyn Fn(&RJCode, &str, WorkOutcome, Option<&str>) + Send + Sync>;
+
+/// Counters and report of one run: a CLI command, or one operation of `--ui`/`--control-socket`.
+/// Created by whoever starts the run and passed to its workflow (the fetches count their bytes
+/// through `MetadataClient::stats`); shared by the blocking tagging jobs of the run.
+pub struct RunStats {
+    command: String,
+    started: Instant,
+    works_processed: AtomicU64,
+    works_skipped: AtomicU64,
+    works_failed: AtomicU64,
+    bytes_downloaded: AtomicU64,
+    files_written: AtomicU64,
+    bytes_converted: AtomicU64,
+    /// `None` unless `configure_report` enabled it
+    report: Mutex<Option<RunReport>>,
+    observer: Option<WorkObserver>,
+}
+
+// Works of the run for the report, and when the previous one ended (the works of a run are
+// handled one after another, so the time since is the work's duration)
+struct RunReport {
+    keep: usize,
+    last_work_end: Instant,
+    works: Vec<WorkResult>,
+}
+
+#[derive(Serialize)]
+struct WorkResult {
+    rjcode: String,
+    operation: String,
+    outcome: &'static str,
+    error: Option<String>,
+    duration_secs: f64,
+}
+
+/// Outcome of one work in a command, stored as `processing_history.status`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorkOutcome {
+    Processed,
+    Skipped,
+    Failed,
+}
+
+impl WorkOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkOutcome::Processed => "success",
+            WorkOutcome::Skipped => "skipped",
+            WorkOutcome::Failed => "failed",
+        }
+    }
+}
+
+impl RunStats {
+    /// Starts a run; `command` is written in its report (the command line, or the operation).
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            started: Instant::now(),
+            works_processed: AtomicU64::new(0),
+            works_skipped: AtomicU64::new(0),
+            works_failed: AtomicU64::new(0),
+            bytes_downloaded: AtomicU64::new(0),
+            files_written: AtomicU64::new(0),
+            bytes_converted: AtomicU64::new(0),
+            report: Mutex::new(None),
+            observer: None,
+        }
+    }
+
+    /// Calls `observer` with every work recorded.
+    pub fn with_observer(mut self, observer: WorkObserver) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Counts a work's outcome for the footer and records it in `processing_history` under
+    /// `operation` (the command, e.g. "full-retag"). A failed history insert is only logged.
+    pub fn record_work(&self, conn: &Connection, rjcode: &RJCode, operation: &str, outcome: WorkOutcome, error: Option<&str>) {
+        let counter = match outcome {
+            WorkOutcome::Processed => &self.works_processed,
+            WorkOutcome::Skipped => &self.works_skipped,
+            WorkOutcome::Failed => &self.works_failed,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        if let Some(observer) = &self.observer {
+            observer(rjcode, operation, outcome, error);
+        }
+        if let Some(report) = self.report.lock().expect("run report mutex poisoned").as_mut() {
+            let now = Instant::now();
+            report.works.push(WorkResult {
+                rjcode: rjcode.to_string(),
+                operation: operation.to_string(),
+                outcome: outcome.as_str(),
+                error: error.map(str::to_string),
+                duration_secs: (now - report.last_work_end).as_secs_f64(),
+            });
+            report.last_work_end = now;
+        }
+
+        if let Err(e) = queries::insert_processing_event(conn, rjcode, operation, "work", outcome.as_str(), error) {
+            debug!("Failed to record processing history for {}: {}", rjcode, e);
+        }
+    }
+
+    /// Adds to the bytes fetched from DLSite (pages, API responses, covers).
+    pub fn add_bytes_downloaded(&self, bytes: usize) {
+        self.bytes_downloaded.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Adds the size of an audio file converted to MP3 (its size before conversion).
+    pub fn add_bytes_converted(&self, bytes: u64) {
+        self.bytes_converted.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Counts a file created or rewritten on disk (tagged/converted audio, cover, tagged video).
+    pub fn add_file_written(&self) {
+        self.files_written.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// One-line summary ending every run, e.g.
+    /// `=== Done in 2m 05s | works: 12 processed, 3 skipped, 1 failed | 4.2 MiB downloaded | 230 file(s) written ===`
+    pub fn print_footer(&self, succeeded: bool) {
+        let processed = self.works_processed.load(Ordering::Relaxed);
+        let skipped = self.works_skipped.load(Ordering::Relaxed);
+        let failed = self.works_failed.load(Ordering::Relaxed);
+
+        let mut parts = vec![format!(
+            "{} in {}",
+            if succeeded { "Done" } else { "Failed" },
+            format_duration(self.started.elapsed())
+        )];
+        if processed + skipped + failed > 0 {
+            parts.push(format!("works: {} processed, {} skipped, {} failed", processed, skipped, failed));
+        }
+        let bytes = self.bytes_downloaded.load(Ordering::Relaxed);
+        if bytes > 0 {
+            parts.push(format!("{} downloaded", crate::stats::format_size(bytes as i64)));
+        }
+        let converted = self.bytes_converted.load(Ordering::Relaxed);
+        if converted > 0 {
+            parts.push(format!("{} converted", crate::stats::format_size(converted as i64)));
+        }
+        let files = self.files_written.load(Ordering::Relaxed);
+        if files > 0 {
+            parts.push(format!("{} file(s) written", files));
+        }
+
+        info!("=== {} ===", parts.join(" | "));
+    }
+
+    /// Starts collecting the works of the run for `write_report` (`[run_reports]`). Commands
+    /// that end before the config is loaded (`--list`, `--search`...) write no report.
+    pub fn configure_report(&self, config: &RunReportsConfig) {
+        if config.enabled {
+            *self.report.lock().expect("run report mutex poisoned") = Some(RunReport {
+                keep: config.keep,
+                last_work_end: Instant::now(),
+                works: Vec::new(),
+            });
+        }
+    }
+
+    /// Writes `runs/YYYYMMDD-HHMMSS.json` in the data folder: the command, start/end time (UTC),
+    /// duration, outcome, counters and every work with its result, so that a long run can be
+    /// reviewed once its progress bars have scrolled away. Only runs that touched works or
+    /// files, or failed, get a report. Errors are only logged.
+    pub fn write_report(&self, error: Option<String>) {
+        let Some(report) = self.report.lock().expect("run report mutex poisoned").take() else {
+            return;
+        };
+        if report.works.is_empty() && self.files_written.load(Ordering::Relaxed) == 0 && error.is_none() {
+            return;
+        }
+        match self.save_report(report, error) {
+            Ok(path) => info!("Run report: {}", path.display()),
+            Err(e) => warn!("Failed to write the run report: {}", e),
+        }
+    }
+
+    fn save_report(&self, report: RunReport, error: Option<String>) -> Result<PathBuf, HvtError> {
+        let elapsed = self.started.elapsed();
+        let now = timestamp::now();
+        let file_stamp = timestamp::file_stamp(now);
+        let count = |outcome: WorkOutcome| report.works.iter().filter(|w| w.outcome == outcome.as_str()).count();
+        let content = RunReportFile {
+            command: self.command.clone(),
+            started_at: timestamp::rfc3339(now - elapsed),
+            finished_at: timestamp::rfc3339(now),
+            duration_secs: elapsed.as_secs_f64(),
+            succeeded: error.is_none(),
+            error,
+            works_processed: count(WorkOutcome::Processed),
+            works_skipped: count(WorkOutcome::Skipped),
+            works_failed: count(WorkOutcome::Failed),
+            bytes_downloaded: self.bytes_downloaded.load(Ordering::Relaxed),
+            bytes_converted: self.bytes_converted.load(Ordering::Relaxed),
+            files_written: self.files_written.load(Ordering::Relaxed),
+            works: &report.works,
+        };
+
+        let dir = crate::paths::runs_dir()?;
+        let mut path = dir.join(format!("{file_stamp}.json"));
+        // Two runs within the same second: keep both reports
+        let mut n = 1;
+        while path.exists() {
+            path = dir.join(format!("{file_stamp}-{n}.json"));
+            n += 1;
+        }
+        let content = serde_json::to_string_pretty(&content)
+            .map_err(|e| HvtError::Parse(format!("Failed to serialize run report: {}", e)))?;
+        std::fs::write(&path, content)?;
+
+        // Timestamped names sort chronologically
+        let mut reports: Vec<PathBuf> = std::fs::read_dir(&dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .collect();
+        if reports.len() > report.keep {
+            reports.sort();
+            for old in &reports[..reports.len() - report.keep] {
+                std::fs::remove_file(old)?;
+            }
+        }
+        Ok(path)
+    }
+}
+
+#[derive(Serialize)]
+struct RunReportFile<'a> {
+    command: String,
+    started_at: String,
+    finished_at: String,
+    duration_secs: f64,
+    succeeded: bool,
+    error: Option<String>,
+    works_processed: usize,
+    works_skipped: usize,
+    works_failed: usize,
+    bytes_downloaded: u64,
+    bytes_converted: u64,
+    files_written: u64,
+    works: &'a [WorkResult],
+}
+
+fn format_duration(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m {:02}s", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{:.1}s", elapsed.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(4200)), "4.2s");
+        assert_eq!(format_duration(Duration::from_secs(125)), "2m 05s");
+        assert_eq!(format_duration(Duration::from_secs(3723)), "1h 02m 03s");
+    }
+}
@@ -0,0 +1,115 @@
+use std::sync::{Arc, Mutex};
+
+use crate::config::Config;
+use crate::database::db_loader::open_db;
+use crate::refresh::RefreshField;
+use crate::run_stats::{RunStats, WorkObserver};
+
+/// A long-running command started by a server (`--ui` jobs, `--control-socket`), run like its
+/// CLI flag
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// `--full`: import new works from the import folder
+    Import,
+    /// `--retag <rjcode>`
+    Retag(String),
+    /// `--full-retag [--all]`
+    FullRetag { all: bool },
+    /// `--refresh [--older-than] [--fields]`
+    Refresh { older_than_days: Option<u32>, fields: Vec<RefreshField> },
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Import => "import",
+            Operation::Retag(_) => "retag",
+            Operation::FullRetag { .. } => "full_retag",
+            Operation::Refresh { .. } => "refresh",
+        }
+    }
+
+    /// Written in the run report
+    fn command(&self) -> String {
+        match self {
+            Operation::Retag(rjcode) => format!("retag {}", rjcode),
+            operation => operation.name().to_string(),
+        }
+    }
+}
+
+/// Runs the operations of a server one at a time, so two runs never fight over the VPN or the
+/// same files. Each runs on its own thread with its own database connection and runtime (the
+/// workflows hold the connection across awaits, so they can't be spawned on the server's
+/// runtime), as a run of its own: its footer and run report only count its works.
+pub struct Runner {
+    db_path: Option<String>,
+    config: Config,
+    /// Held while an operation runs
+    running: Mutex<()>,
+}
+
+impl Runner {
+    /// `config` is the server's; operations never prompt, like `--non-interactive`.
+    pub fn new(db_path: Option<String>, config: &Config) -> Self {
+        let mut config = config.clone();
+        config.track_parsing.prompt = false;
+        Self { db_path, config, running: Mutex::new(()) }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn db_path(&self) -> Option<&str> {
+        self.db_path.as_deref()
+    }
+
+    /// Runs `operation` once the previous ones are done: `started` is called when it starts,
+    /// `finished` with its outcome. `observer` is called with every work of the run.
+    pub fn spawn(
+        self: &Arc<Self>,
+        operation: Operation,
+        observer: Option<WorkObserver>,
+        started: impl FnOnce() + Send + 'static,
+        finished: impl FnOnce(Result<(), String>) + Send + 'static,
+    ) {
+        let runner = Arc::clone(self);
+        std::thread::spawn(move || {
+            let _running = runner.running.lock().expect("runner mutex poisoned");
+            started();
+
+            let mut stats = RunStats::new(operation.command());
+            if let Some(observer) = observer {
+                stats = stats.with_observer(observer);
+            }
+            let stats = Arc::new(stats);
+            stats.configure_report(&runner.config.run_reports);
+            let outcome = runner.execute(&operation, &stats);
+            stats.print_footer(outcome.is_ok());
+            stats.write_report(outcome.as_ref().err().cloned());
+
+            finished(outcome);
+        });
+    }
+
+    fn execute(&self, operation: &Operation, stats: &Arc<RunStats>) -> Result<(), String> {
+        let conn = open_db(self.db_path.as_deref()).map_err(|e| e.to_string())?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let config = &self.config;
+        let result = runtime.block_on(async {
+            match operation {
+                Operation::Import => crate::run_import_workflow(&conn, config, &crate::MoveFilter::default(), true, stats).await,
+                Operation::Retag(rjcode) => crate::run_retag_workflow(&conn, rjcode, config, stats).await,
+                Operation::FullRetag { all } => crate::run_full_retag_workflow(&conn, config, *all, stats).await,
+                Operation::Refresh { older_than_days, fields } => {
+                    crate::refresh::run_refresh_workflow(&conn, config, *older_than_days, fields, stats).await
+                }
+            }
+        });
+        result.map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,226 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::ImportConfig;
+
+/// Longest file or folder name in UTF-8 bytes (ext4, APFS...): about 85 Japanese characters
+const MAX_NAME_BYTES: usize = 255;
+
+/// How folder names are made from metadata when works are organized or moved (`[import]`
+/// `max_folder_name_length` and `transliterate_folder_names`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NameRules {
+    /// Longest name in characters, 0 = no limit (`MAX_NAME_BYTES` always applies)
+    pub max_len: usize,
+    /// Kana written in romaji and accents dropped (`transliterate`)
+    pub transliterate: bool,
+}
+
+impl NameRules {
+    pub fn from_config(import: &ImportConfig) -> Self {
+        NameRules {
+            max_len: import.max_folder_name_length,
+            transliterate: import.transliterate_folder_names,
+        }
+    }
+
+    /// `name` transliterated if enabled, cleaned up by `folder_name`, then shortened
+    pub fn apply(&self, name: &str) -> String {
+        truncate(&self.clean(name), self.max_len)
+    }
+
+    /// `apply` without the length limit, for a value inserted into a longer name
+    pub fn clean(&self, name: &str) -> String {
+        if self.transliterate {
+            folder_name(&transliterate(name))
+        } else {
+            folder_name(name)
+        }
+    }
+}
+
+/// Text as it should be compared and written to disk: NFC (the same name typed on macOS, which
+/// decomposes `が`, and on Windows gives the same bytes), zero-width characters removed
+pub fn normalize(text: &str) -> String {
+    text.nfc().filter(|c| !is_zero_width(*c)).collect()
+}
+
+fn is_zero_width(c: char) -> bool {
+    matches!(c, '\u{200B}' | '\u{200C}' | '\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{180E}')
+}
+
+/// A name usable as a folder or file name on every platform: `normalize`d, characters forbidden
+/// on Windows replaced by `_`, leading spaces and trailing dots/spaces removed, reserved device
+/// names (`CON`, `NUL`, `COM1`...) prefixed with `_`
+pub fn folder_name(name: &str) -> String {
+    let sanitized: String = normalize(name)
+        .chars()
+        .map(|c| if matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') || c.is_control() { '_' } else { c })
+        .collect();
+    let sanitized = trim_name(&sanitized);
+    if sanitized.is_empty() {
+        "_".to_string()
+    } else if is_reserved_name(sanitized) {
+        format!("_{}", sanitized)
+    } else {
+        sanitized.to_string()
+    }
+}
+
+// Windows drops trailing dots and spaces, so `Title.` and `Title` would be the same folder
+fn trim_name(name: &str) -> &str {
+    name.trim_start().trim_end_matches(|c: char| c == '.' || c.is_whitespace())
+}
+
+/// Names Windows reserves for devices, with any extension (`nul.txt`)
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).trim_end().to_ascii_uppercase();
+    matches!(stem.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || (stem.len() == 4
+            && (stem.starts_with("COM") || stem.starts_with("LPT"))
+            && matches!(stem.as_bytes()[3], b'1'..=b'9'))
+}
+
+/// Shortens a name to `max_chars` characters (0 = no limit) and `MAX_NAME_BYTES` bytes without
+/// splitting a character; trailing dots/spaces left by the cut are removed
+pub fn truncate(name: &str, max_chars: usize) -> String {
+    let mut end = 0;
+    for (count, (index, c)) in name.char_indices().enumerate() {
+        if (max_chars > 0 && count >= max_chars) || index + c.len_utf8() > MAX_NAME_BYTES {
+            break;
+        }
+        end = index + c.len_utf8();
+    }
+    if end == name.len() {
+        return name.to_string();
+    }
+    let truncated = trim_name(&name[..end]);
+    if truncated.is_empty() {
+        "_".to_string()
+    } else {
+        truncated.to_string()
+    }
+}
+
+/// Mostly-ASCII spelling of a name: kana in Hepburn romaji (`ボイス` → `boisu`), full-width
+/// letters and digits folded (NFKC), accents dropped (`é` → `e`). Kanji have no reading without a
+/// dictionary and are kept.
+pub fn transliterate(text: &str) -> String {
+    let romaji = kana_to_romaji(&text.nfkc().collect::<String>());
+    romaji.nfkd().filter(|c| !is_combining_mark(*c)).nfc().collect()
+}
+
+/// Romaji of the hiragana U+3041 (`ぁ`) to U+3096 (`ゖ`); `っ` doubles the next consonant
+const ROMAJI: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o",
+    "ka", "ga", "ki", "gi", "ku", "gu", "ke", "ge", "ko", "go",
+    "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo",
+    "ta", "da", "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do",
+    "na", "ni", "nu", "ne", "no",
+    "ha", "ba", "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo", "po",
+    "ma", "mi", "mu", "me", "mo",
+    "ya", "ya", "yu", "yu", "yo", "yo",
+    "ra", "ri", "ru", "re", "ro",
+    "wa", "wa", "i", "e", "o", "n", "vu", "ka", "ke",
+];
+
+/// The hiragana of a kana, katakana included
+fn hiragana(c: char) -> Option<char> {
+    match c {
+        '\u{3041}'..='\u{3096}' => Some(c),
+        '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - 0x60),
+        _ => None,
+    }
+}
+
+fn kana_to_romaji(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    // Romaji of the previous kana, `None` after anything else
+    let mut last: Option<&str> = None;
+    let mut double_next = false;
+    for c in text.chars() {
+        // The long vowel mark is dropped, as in most romanized titles
+        if c == 'ー' && last.is_some() {
+            continue;
+        }
+        let Some(kana) = hiragana(c) else {
+            out.push(c);
+            last = None;
+            double_next = false;
+            continue;
+        };
+        if kana == 'っ' {
+            double_next = true;
+            continue;
+        }
+        let romaji = ROMAJI[(kana as u32 - 0x3041) as usize];
+        match (kana, last) {
+            // きゃ → kya, しゃ → sha, じゃ → ja
+            ('ゃ' | 'ゅ' | 'ょ', Some(previous)) if previous.len() > 1 && previous.ends_with('i') => {
+                out.pop();
+                if out.ends_with("sh") || out.ends_with("ch") || out.ends_with('j') {
+                    out.push_str(&romaji[1..]);
+                } else {
+                    out.push_str(romaji);
+                }
+            }
+            // ファ → fa, ティ → ti, ウィ → wi
+            ('ぁ' | 'ぃ' | 'ぅ' | 'ぇ' | 'ぉ', Some(previous)) if previous.ends_with(['a', 'i', 'u', 'e', 'o']) => {
+                out.pop();
+                if previous.len() == 1 {
+                    out.push('w');
+                }
+                out.push_str(romaji);
+            }
+            _ => {
+                if double_next {
+                    match romaji.chars().next() {
+                        Some('c') => out.push('t'),
+                        Some(first) if !"aiueon".contains(first) => out.push(first),
+                        _ => {}
+                    }
+                }
+                out.push_str(romaji);
+            }
+        }
+        last = Some(romaji);
+        double_next = false;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folder_name() {
+        assert_eq!(folder_name("サークル名"), "サークル名");
+        assert_eq!(folder_name("A/B: C?"), "A_B_ C_");
+        assert_eq!(folder_name(" Circle... "), "Circle");
+        assert_eq!(folder_name("Title. . "), "Title");
+        assert_eq!(folder_name("..."), "_");
+        assert_eq!(folder_name("con"), "_con");
+        assert_eq!(folder_name("LPT1.txt"), "_LPT1.txt");
+        assert_eq!(folder_name("CONSOLE"), "CONSOLE");
+        // Decomposed (macOS) and zero-width characters
+        assert_eq!(folder_name("カ\u{3099}イト\u{200B}"), "ガイト");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("RJ01234567 作品", 0), "RJ01234567 作品");
+        assert_eq!(truncate("RJ01234567 作品. 第1巻", 14), "RJ01234567 作品");
+        // 255 bytes at most: 85 three-byte characters
+        assert_eq!(truncate(&"音".repeat(100), 0).chars().count(), 85);
+    }
+
+    #[test]
+    fn test_transliterate() {
+        assert_eq!(transliterate("ボイスドラマ"), "boisudorama");
+        assert_eq!(transliterate("しょうじょ ちゃっと"), "shoujo chatto");
+        assert_eq!(transliterate("きゃっきゃ"), "kyakkya");
+        assert_eq!(transliterate("ファンティア ウィッチ"), "fantia witchi");
+        assert_eq!(transliterate("Ｃａｆé メイド喫茶"), "Cafe meido喫茶");
+    }
+}
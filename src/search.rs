@@ -0,0 +1,35 @@
+use rusqlite::Connection;
+use tracing::info;
+
+use crate::database::search::{self, SearchHit};
+use crate::errors::HvtError;
+
+/// `--search <query>`: full-text search over work titles, circles, CVs and tags. Prints each
+/// matching RJ code with its title, folder path and tagging status.
+pub fn run_search_workflow(conn: &Connection, query: &str) -> Result<(), HvtError> {
+    search::rebuild_search_index(conn)?;
+    let hits = search::search_works(conn, query)?;
+
+    if hits.is_empty() {
+        info!("No work matches \"{}\"", query);
+        return Ok(());
+    }
+
+    for hit in &hits {
+        println!("{}  [{}]  {}", hit.rjcode, tag_status(hit), hit.name);
+        println!("    {}", hit.path);
+    }
+
+    info!("{} work(s) match \"{}\"", hits.len(), query);
+    Ok(())
+}
+
+fn tag_status(hit: &SearchHit) -> String {
+    if hit.total_files == 0 || hit.tagged_files == 0 {
+        "untagged".to_string()
+    } else if hit.tagged_files < hit.total_files {
+        format!("partially tagged {}/{}", hit.tagged_files, hit.total_files)
+    } else {
+        "tagged".to_string()
+    }
+}
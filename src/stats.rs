@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use rusqlite::Connection;
+
+use crate::database::{integrity, queries, ranking_history, review_queue, sales_history, web_queries};
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+
+/// Rows shown per ranking (circles, CVs, tags)
+const TOP_N: i64 = 20;
+
+/// `--stats`: prints a library report — work counts, tagging status, top circles/CVs/tags, best rankings,
+/// audio size and format distribution, unresolved DLSite fetch errors, damaged audio files and works queued for review. Scoped to active
+/// (non-trashed) works; sizes/formats come from the files recorded when they were tagged.
+pub fn run_stats_workflow(conn: &Connection) -> Result<(), HvtError> {
+    let total_works = web_queries::count_all_active_works(conn)?;
+    let tagged_works = web_queries::count_tagged_works(conn)?;
+
+    println!("=== Library ===");
+    println!("  Works:    {}", total_works);
+    println!("  Tagged:   {}", tagged_works);
+    println!("  Untagged: {}", total_works - tagged_works);
+
+    let circles: Vec<(String, i64)> = web_queries::top_circles_by_count(conn, TOP_N)?
+        .into_iter()
+        .map(|(rgcode, name, count)| (format!("{} ({})", name, rgcode), count))
+        .collect();
+    print_ranking("Works per circle", &circles);
+    print_ranking("Top CVs", &web_queries::top_cvs_by_count(conn, TOP_N)?);
+    print_ranking("Tag frequency", &web_queries::top_tags_by_count(conn, TOP_N)?);
+
+    // Best DLSite ranking each work reached (see --list --sort popularity)
+    let mut ranked: Vec<(String, ranking_history::Ranking)> = ranking_history::get_best_rankings(conn)?.into_iter().collect();
+    ranked.sort_by(|(a_code, a), (b_code, b)| a.2.cmp(&b.2).then_with(|| a_code.cmp(b_code)));
+    println!("\n=== Best DLSite rankings ===");
+    if ranked.is_empty() {
+        println!("  None");
+    }
+    for (rjcode, (term, category, rank, rank_date)) in ranked.iter().take(TOP_N as usize) {
+        println!("  {:>6}  {} ({}/{}, {})", format!("#{}", rank), rjcode, term, category, rank_date);
+    }
+
+    let formats = web_queries::audio_format_distribution(conn)?;
+    let total_files: i64 = formats.iter().map(|(_, count, _)| count).sum();
+    let total_bytes: i64 = formats.iter().map(|(_, _, bytes)| bytes).sum();
+    println!("\n=== Audio ===");
+    println!("  Files: {}  Total size: {}", total_files, format_size(total_bytes));
+    for (extension, count, bytes) in &formats {
+        println!("  {:>6}  {:>6} file(s)  {:>10}", extension, count, format_size(*bytes));
+    }
+
+    let errors = web_queries::unresolved_error_counts(conn)?;
+    println!("\n=== Fetch errors (unresolved) ===");
+    if errors.is_empty() {
+        println!("  None");
+    }
+    for (error_type, count) in &errors {
+        println!("  {:>6}  {}", count, error_type);
+    }
+
+    // Files flagged by --verify/--fsck or by the silence check after tagging: re-download them
+    let flagged = integrity::get_flagged_files(conn)?;
+    println!("\n=== Damaged audio files ({}) ===", flagged.len());
+    if flagged.is_empty() {
+        println!("  None");
+    }
+    for (rjcode, file_path, status, details) in &flagged {
+        let file_name = Path::new(file_path).file_name().and_then(|n| n.to_str()).unwrap_or(file_path);
+        let first_line = details.as_deref().and_then(|d| d.lines().next()).unwrap_or("");
+        println!("  {} [{}] {}: {}", rjcode, status, file_name, first_line);
+    }
+
+    // Decisions an unattended run (--non-interactive) deferred: re-run --retag on these works
+    let review = review_queue::get_review_items(conn)?;
+    println!("\n=== Needs attention ({}) ===", review.len());
+    if review.is_empty() {
+        println!("  None");
+    }
+    for (rjcode, reason, details) in &review {
+        println!("  {} [{}] {}", rjcode, reason, details.as_deref().unwrap_or(""));
+    }
+
+    Ok(())
+}
+
+/// `--stats --work <RJCODE>`: DLSite ratings and rankings of a work, then its price, download and wishlist
+/// counts at each fetch, with the change since the previous one
+pub fn run_work_stats_workflow(conn: &Connection, rjcode: &RJCode) -> Result<(), HvtError> {
+    if let Some(reviews) = queries::get_review_counts(conn, rjcode)? {
+        println!("=== Ratings of {} ===", rjcode);
+        println!("  Ratings: {}  Reviews: {}", reviews.rate_count, reviews.review_count);
+        for (stars, count) in reviews.distribution.iter().enumerate().rev() {
+            println!("  {}  {:>6}", "★".repeat(stars + 1), count);
+        }
+        println!();
+    }
+
+    let rankings = ranking_history::get_history(conn, rjcode)?;
+    println!("=== DLSite rankings of {} ({}) ===", rjcode, rankings.len());
+    if rankings.is_empty() {
+        println!("  None");
+    }
+    for (term, category, rank, rank_date) in &rankings {
+        println!("  {:<19}  {:>6}  {}/{}", rank_date, format!("#{}", rank), term, category);
+    }
+
+    let history = sales_history::get_history(conn, rjcode)?;
+    println!("\n=== Sales history of {} ({} fetch(es)) ===", rjcode, history.len());
+    if history.is_empty() {
+        println!("  None: the history starts at the next --refresh or --retag of the work");
+        return Ok(());
+    }
+
+    println!("  {:<19}  {:>8}  {:>16}  {:>16}", "Fetched", "Price", "Downloads", "Wishlists");
+    let mut previous: Option<&sales_history::SalesSample> = None;
+    for sample in &history {
+        let (fetched_at, price, dl_count, wishlist_count) = sample;
+        let price = price.map(|p| format!("¥{}", p)).unwrap_or_else(|| "-".to_string());
+        println!(
+            "  {:<19}  {:>8}  {:>16}  {:>16}",
+            fetched_at,
+            price,
+            format_count(*dl_count, previous.and_then(|p| p.2)),
+            format_count(*wishlist_count, previous.and_then(|p| p.3)),
+        );
+        previous = Some(sample);
+    }
+    Ok(())
+}
+
+/// "1234 (+56)": a count and its change since the previous fetch
+fn format_count(count: Option<i64>, previous: Option<i64>) -> String {
+    match (count, previous) {
+        (Some(count), Some(previous)) if count != previous => format!("{} ({:+})", count, count - previous),
+        (Some(count), _) => count.to_string(),
+        (None, _) => "-".to_string(),
+    }
+}
+
+fn print_ranking(title: &str, rows: &[(String, i64)]) {
+    println!("\n=== {} ===", title);
+    if rows.is_empty() {
+        println!("  None");
+    }
+    for (label, count) in rows {
+        println!("  {:>6}  {}", count, label);
+    }
+}
+
+/// Human-readable byte count (binary units)
+pub fn format_size(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
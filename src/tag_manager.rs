@@ -1,7 +1,10 @@
-use dialoguer::{Select, Input, Confirm, theme::ColorfulTheme};
+use std::collections::HashMap;
+
+use dialoguer::{Select, Input, Confirm, MultiSelect, theme::ColorfulTheme};
 use rusqlite::Connection;
 use crate::errors::HvtError;
-use crate::database::custom_tags;
+use crate::database::{custom_tags, queries, tag_categories::{self, CategoryFrame}, tag_rules::{self, TagLanguage, TagRuleType}};
+use crate::folders::types::RJCode;
 
 pub fn run_interactive_tag_manager(conn: &Connection) -> Result<(), HvtError> {
     loop {
@@ -14,6 +17,8 @@ pub fn run_interactive_tag_manager(conn: &Connection) -> Result<(), HvtError> {
             "Bulk ignore tags below threshold",
             "View current custom mappings",
             "Remove a custom mapping",
+            "Tag rules (patterns, priority, max tags per work)",
+            "Tag categories (mood, TXXX frames)",
             "Exit"
         ];
 
@@ -32,7 +37,9 @@ pub fn run_interactive_tag_manager(conn: &Connection) -> Result<(), HvtError> {
             4 => bulk_ignore_tags_below_threshold(conn)?,
             5 => view_custom_mappings(conn)?,
             6 => remove_custom_mapping(conn)?,
-            7 => {
+            7 => manage_tag_rules(conn)?,
+            8 => manage_tag_categories(conn)?,
+            9 => {
                 println!("Exiting tag manager...");
                 break;
             }
@@ -556,3 +563,302 @@ fn remove_custom_mapping(conn: &Connection) -> Result<(), HvtError> {
 
     Ok(())
 }
+
+/// Merged tags of every work, to find the works a rule change affects
+fn merged_tags_of_all_works(conn: &Connection) -> Result<HashMap<String, Vec<String>>, HvtError> {
+    let rules = tag_rules::load_tag_rules(conn)?;
+    let mut tags = HashMap::new();
+    for (rjcode, _name) in custom_tags::list_all_works(conn)? {
+        let Ok(work) = RJCode::new(rjcode.clone()) else { continue };
+        tags.insert(rjcode, custom_tags::get_merged_tags_for_work_with_rules(conn, &work, &rules)?);
+    }
+    Ok(tags)
+}
+
+/// Marks the works whose tags differ from `before` for re-tagging
+fn mark_changed_works_for_retagging(conn: &Connection, before: &HashMap<String, Vec<String>>) -> Result<(), HvtError> {
+    let after = merged_tags_of_all_works(conn)?;
+    let mut works_marked = 0;
+    for (rjcode, tags) in &after {
+        if before.get(rjcode) != Some(tags) {
+            if let Ok(work) = RJCode::new(rjcode.clone()) {
+                queries::mark_work_for_retagging(conn, &work)?;
+                works_marked += 1;
+            }
+        }
+    }
+    if works_marked > 0 {
+        println!("✓ {} work(s) marked for re-tagging", works_marked);
+        println!("  Run --tag to apply changes to all affected works");
+    } else {
+        println!("  No work's tags changed");
+    }
+    Ok(())
+}
+
+fn describe_rule(rule_type: TagRuleType, value: &str) -> String {
+    match rule_type {
+        TagRuleType::IgnorePattern => format!("ignore /{}/", value),
+        TagRuleType::Priority => format!("priority /{}/", value),
+        TagRuleType::MaxTags => format!("at most {} tag(s) per work", value),
+        TagRuleType::Language => format!("genre names in {}", if value == "jp" { "Japanese" } else { "English" }),
+    }
+}
+
+/// Rules applied after the per-tag mappings: regex ignore patterns (matched against the DLSite
+/// and custom names), a max-tags-per-work limit, priority patterns deciding which tags
+/// survive the limit (earliest rule first), and the language of the DLSite genre names.
+fn manage_tag_rules(conn: &Connection) -> Result<(), HvtError> {
+    loop {
+        let rules = tag_rules::list_tag_rules(conn)?;
+        println!("\n=== Tag rules ===");
+        if rules.is_empty() {
+            println!("  None");
+        }
+        for (_id, rule_type, value) in &rules {
+            println!("  {}", describe_rule(*rule_type, value));
+        }
+        println!();
+
+        let options = vec![
+            "Add an ignore pattern (regex)",
+            "Add a priority pattern (regex, after the existing ones)",
+            "Set the max tags per work",
+            "Set the genre name language (English / Japanese)",
+            "Remove a rule",
+            "Back",
+        ];
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Tag rules")
+            .items(&options)
+            .default(0)
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+        if selection == options.len() - 1 {
+            break;
+        }
+
+        let before = merged_tags_of_all_works(conn)?;
+        match selection {
+            0 | 1 => {
+                let pattern: String = Input::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Pattern (e.g. キャンペーン, ^(?i)asmr$)")
+                    .interact_text()
+                    .map_err(|e| HvtError::Parse(format!("Input error: {}", e)))?;
+                let pattern = pattern.trim();
+                let re = match regex::Regex::new(pattern) {
+                    Ok(re) => re,
+                    Err(e) => {
+                        println!("Invalid pattern: {}", e);
+                        continue;
+                    }
+                };
+                let matching: Vec<String> = custom_tags::list_all_dlsite_tags(conn)?
+                    .into_iter()
+                    .filter(|(_, name, custom, _)| re.is_match(name) || custom.as_deref().is_some_and(|c| re.is_match(c)))
+                    .map(|(_, name, _, _)| name)
+                    .collect();
+                println!("Matches {} tag(s){}", matching.len(), if matching.is_empty() { "" } else { ":" });
+                for name in matching.iter().take(10) {
+                    println!("  - {}", name);
+                }
+                if matching.len() > 10 {
+                    println!("  ... and {} more", matching.len() - 10);
+                }
+                let rule_type = if selection == 0 { TagRuleType::IgnorePattern } else { TagRuleType::Priority };
+                let confirm = Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!("Add rule '{}'?", describe_rule(rule_type, pattern)))
+                    .default(true)
+                    .interact()
+                    .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))?;
+                if !confirm {
+                    continue;
+                }
+                tag_rules::add_pattern_rule(conn, rule_type, pattern)?;
+            }
+            2 => {
+                let max_tags: usize = Input::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Max tags per work (0 = no limit)")
+                    .interact_text()
+                    .map_err(|e| HvtError::Parse(format!("Input error: {}", e)))?;
+                tag_rules::set_max_tags(conn, (max_tags > 0).then_some(max_tags))?;
+            }
+            3 => {
+                let languages = [TagLanguage::En, TagLanguage::Jp];
+                let current = tag_rules::load_tag_language(conn)?;
+                let language = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Genre names (Japanese falls back to English until a work is fetched again)")
+                    .items(&["English (en_US page)", "Japanese (ja_JP page)"])
+                    .default(languages.iter().position(|l| *l == current).unwrap_or(0))
+                    .interact()
+                    .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+                tag_rules::set_tag_language(conn, languages[language])?;
+            }
+            4 => {
+                if rules.is_empty() {
+                    println!("No rule to remove.");
+                    continue;
+                }
+                let displays: Vec<String> = rules.iter().map(|(_, t, v)| describe_rule(*t, v)).collect();
+                let selection = Select::with_theme(&ColorfulTheme::default())
+                    .with_prompt("Select a rule to remove")
+                    .items(&displays)
+                    .default(0)
+                    .interact()
+                    .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+                tag_rules::remove_tag_rule(conn, rules[selection].0)?;
+            }
+            _ => unreachable!(),
+        }
+        println!("\n✓ Tag rules updated");
+        mark_changed_works_for_retagging(conn, &before)?;
+    }
+    Ok(())
+}
+
+fn describe_frame(name: &str, frame: CategoryFrame) -> String {
+    match frame {
+        CategoryFrame::Genre => "genre".to_string(),
+        CategoryFrame::Mood => "mood".to_string(),
+        CategoryFrame::Txxx => format!("TXXX:{}", tag_categories::txxx_description(name)),
+    }
+}
+
+/// Marks the works using any of these DLSite tags for re-tagging
+fn mark_tags_for_retagging(conn: &Connection, tag_names: &[String]) -> Result<(), HvtError> {
+    let mut files_marked = 0;
+    for tag_name in tag_names {
+        files_marked += custom_tags::mark_works_for_retagging(conn, tag_name)?;
+    }
+    if files_marked > 0 {
+        println!("✓ {} file(s) marked for re-tagging", files_marked);
+        println!("  Run --tag to apply changes to all affected works");
+    }
+    Ok(())
+}
+
+/// Categories group DLSite tags ("situation", "voice", "play"...) and write them to their own
+/// frame instead of the genre: the mood frame or a TXXX frame named after the category.
+fn manage_tag_categories(conn: &Connection) -> Result<(), HvtError> {
+    loop {
+        let categories = tag_categories::list_categories(conn)?;
+        println!("\n=== Tag categories ===");
+        if categories.is_empty() {
+            println!("  None (every tag goes to the genre)");
+        }
+        for (_id, name, frame, tag_count) in &categories {
+            println!("  {} → {} ({} tag(s))", name, describe_frame(name, *frame), tag_count);
+        }
+        println!();
+
+        let options = vec![
+            "View the tags of a category",
+            "Create a category / change its frame",
+            "Choose the tags of a category",
+            "Delete a category (its tags go back to the genre)",
+            "Back",
+        ];
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Tag categories")
+            .items(&options)
+            .default(0)
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+        if selection == options.len() - 1 {
+            break;
+        }
+
+        if selection == 1 {
+            let name: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Category name (e.g. situation, voice, play)")
+                .interact_text()
+                .map_err(|e| HvtError::Parse(format!("Input error: {}", e)))?;
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            let frames = [CategoryFrame::Genre, CategoryFrame::Mood, CategoryFrame::Txxx];
+            let frame_displays: Vec<String> = frames.iter().map(|f| describe_frame(name, *f)).collect();
+            let frame = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Frame written for its tags")
+                .items(&frame_displays)
+                .default(2)
+                .interact()
+                .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+            tag_categories::upsert_category(conn, name, frames[frame])?;
+            println!("\n✓ Category '{}' writes to {}", name, frame_displays[frame]);
+            if let Some((id, ..)) = tag_categories::list_categories(conn)?.into_iter().find(|(_, n, ..)| n == name) {
+                mark_tags_for_retagging(conn, &tag_categories::get_category_tags(conn, id)?)?;
+            }
+            continue;
+        }
+
+        if categories.is_empty() {
+            println!("No category yet.");
+            continue;
+        }
+        let displays: Vec<String> = categories.iter().map(|(_, name, ..)| name.clone()).collect();
+        let chosen = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Category")
+            .items(&displays)
+            .default(0)
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+        let (category_id, name, ..) = &categories[chosen];
+        let current = tag_categories::get_category_tags(conn, *category_id)?;
+
+        match selection {
+            0 => {
+                println!("\n=== {} ===", name);
+                if current.is_empty() {
+                    println!("  None");
+                }
+                for tag in &current {
+                    println!("  {}", tag);
+                }
+            }
+            2 => {
+                let tags = custom_tags::list_all_dlsite_tags(conn)?;
+                let tag_displays: Vec<String> = tags.iter()
+                    .map(|(_, tag_name, custom, _)| match custom {
+                        Some(custom_name) => format!("{} → {}", tag_name, custom_name),
+                        None => tag_name.clone(),
+                    })
+                    .collect();
+                let checked: Vec<bool> = tags.iter().map(|(_, tag_name, _, _)| current.contains(tag_name)).collect();
+                let picked = MultiSelect::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!("Tags of '{}' (space to toggle, enter to save)", name))
+                    .items(&tag_displays)
+                    .defaults(&checked)
+                    .interact()
+                    .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+                let mut changed = Vec::new();
+                for (i, (_, tag_name, _, _)) in tags.iter().enumerate() {
+                    let selected = picked.contains(&i);
+                    if selected != checked[i] {
+                        tag_categories::set_tag_category(conn, tag_name, selected.then_some(*category_id))?;
+                        changed.push(tag_name.clone());
+                    }
+                }
+                println!("\n✓ {} tag(s) changed", changed.len());
+                mark_tags_for_retagging(conn, &changed)?;
+            }
+            3 => {
+                let confirm = Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!("Delete category '{}' ({} tag(s) go back to the genre)?", name, current.len()))
+                    .default(false)
+                    .interact()
+                    .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))?;
+                if confirm {
+                    tag_categories::delete_category(conn, *category_id)?;
+                    println!("\n✓ Category deleted");
+                    mark_tags_for_retagging(conn, &current)?;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    Ok(())
+}
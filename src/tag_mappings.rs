@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::database::custom_tags;
+use crate::errors::HvtError;
+use crate::export::csv_field;
+use crate::tag_translations::parse_csv_line;
+
+/// One row of a mappings file: what happens to a DLSite tag
+#[derive(Debug, Clone, PartialEq)]
+enum MappingAction {
+    Rename(String),
+    Ignore,
+    /// Back to the DLSite name
+    Remove,
+}
+
+/// Rows of a `dlsite_tag,action,custom_name` CSV file (`action`: rename, ignore or remove). The
+/// header row and blank lines are skipped; an invalid row is an error naming its line.
+fn parse_mappings(content: &str) -> Result<Vec<(String, MappingAction)>, HvtError> {
+    let mut mappings = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let fields: Vec<String> = parse_csv_line(line).into_iter().map(|f| f.trim().to_string()).collect();
+        if fields.iter().all(|f| f.is_empty()) || (i == 0 && fields[0] == "dlsite_tag") {
+            continue;
+        }
+        let field = |n: usize| fields.get(n).map(String::as_str).unwrap_or("");
+        let action = match (field(1).to_lowercase().as_str(), field(2)) {
+            ("rename", "") => return Err(HvtError::Parse(format!("Line {}: rename without a custom name", i + 1))),
+            ("rename", custom) => MappingAction::Rename(custom.to_string()),
+            ("ignore", _) => MappingAction::Ignore,
+            ("remove", _) => MappingAction::Remove,
+            (other, _) => return Err(HvtError::Parse(format!(
+                "Line {}: unknown action '{}' (rename, ignore or remove)", i + 1, other
+            ))),
+        };
+        if field(0).is_empty() {
+            return Err(HvtError::Parse(format!("Line {}: missing DLSite tag", i + 1)));
+        }
+        mappings.push((field(0).to_string(), action));
+    }
+    Ok(mappings)
+}
+
+/// `--apply-tag-mappings <file>`: the `--manage-tags` renames and ignores in bulk, from a CSV
+/// file (see `parse_mappings`). The whole file is applied in one transaction, then every work
+/// using a changed tag is marked for re-tagging at once. Tags not in the library are skipped.
+pub fn run_apply_mappings_workflow(conn: &Connection, source: &str) -> Result<(), HvtError> {
+    let path = Path::new(source);
+    if !path.is_file() {
+        return Err(HvtError::Generic(format!("Mappings file not found: {}", source)));
+    }
+    let mappings = parse_mappings(&std::fs::read_to_string(path)?)?;
+    info!("=== APPLY TAG MAPPINGS: {} ({} rows) ===", source, mappings.len());
+
+    let current: HashMap<String, MappingAction> = custom_tags::list_all_dlsite_tags(conn)?
+        .into_iter()
+        .map(|(_, name, custom, ignored)| {
+            let action = match (custom, ignored) {
+                (_, true) => MappingAction::Ignore,
+                (Some(custom), false) => MappingAction::Rename(custom),
+                (None, false) => MappingAction::Remove,
+            };
+            (name, action)
+        })
+        .collect();
+
+    let mut changed = Vec::new();
+    let mut missing = 0;
+    let tx = conn.unchecked_transaction()?;
+    for (tag, action) in &mappings {
+        match current.get(tag) {
+            None => {
+                missing += 1;
+                warn!("Tag '{}' is not in the library, skipped", tag);
+            }
+            Some(existing) if existing == action => {}
+            Some(_) => {
+                match action {
+                    MappingAction::Rename(custom) => custom_tags::add_custom_tag_mapping(&tx, tag, custom)?,
+                    MappingAction::Ignore => custom_tags::ignore_tag(&tx, tag)?,
+                    MappingAction::Remove => custom_tags::remove_custom_tag_mapping(&tx, tag)?,
+                }
+                changed.push(tag.clone());
+            }
+        }
+    }
+    let files_marked = custom_tags::mark_works_for_retagging_many(&tx, &changed)?;
+    tx.commit()?;
+
+    info!(
+        "Mappings changed: {} | Unchanged: {} | Not in library: {}",
+        changed.len(), mappings.len() - changed.len() - missing, missing
+    );
+    if files_marked > 0 {
+        info!("{} file(s) marked for re-tagging, run --tag to apply", files_marked);
+    }
+    Ok(())
+}
+
+/// `--export-tag-mappings [file]`: writes every rename and ignore as a CSV file that
+/// `--apply-tag-mappings` reads back (defaults to hvtag_tag_mappings.csv).
+pub fn run_export_mappings_workflow(conn: &Connection, output: Option<&str>) -> Result<(), HvtError> {
+    let mut mappings = custom_tags::get_all_custom_mappings(conn)?;
+    mappings.sort();
+
+    let mut out = String::from("dlsite_tag,action,custom_name\n");
+    for (tag, custom, ignored) in &mappings {
+        let (action, custom) = match (custom, ignored) {
+            (_, true) => ("ignore", ""),
+            (Some(custom), false) => ("rename", custom.as_str()),
+            (None, false) => continue,
+        };
+        out.push_str(&format!("{},{},{}\n", csv_field(tag), action, csv_field(custom)));
+    }
+
+    let output = output.unwrap_or("hvtag_tag_mappings.csv");
+    std::fs::write(output, out)?;
+    info!("Exported {} tag mapping(s) to {}", mappings.len(), output);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mappings() {
+        let csv = "dlsite_tag,action,custom_name\n耳かき,rename,Ear Cleaning\n\n\"sale, 30%\",Ignore,\nasmr,remove\n";
+        assert_eq!(
+            parse_mappings(csv).unwrap(),
+            [
+                ("耳かき".to_string(), MappingAction::Rename("Ear Cleaning".to_string())),
+                ("sale, 30%".to_string(), MappingAction::Ignore),
+                ("asmr".to_string(), MappingAction::Remove),
+            ]
+        );
+        assert!(parse_mappings("耳かき,rename,\n").is_err());
+        assert!(parse_mappings("耳かき,hide\n").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::database::custom_tags;
+use crate::errors::HvtError;
+use crate::export::csv_field;
+
+/// `--import-tag-translations bundled`: common DLSite genres as named on the English site
+const BUNDLED_EN: &[(&str, &str)] = &[
+    ("耳かき", "Ear Cleaning"),
+    ("耳舐め", "Ear Licking"),
+    ("癒し", "Healing"),
+    ("囁き", "Whispering"),
+    ("ささやき", "Whispering"),
+    ("バイノーラル/ダミヘ", "Binaural/Dummy Head Mic"),
+    ("添い寝", "Sleeping Together"),
+    ("睡眠導入", "Sleep Inducing"),
+    ("マッサージ", "Massage"),
+    ("ラブラブ/あまあま", "Lovey-Dovey/Sweet Love"),
+    ("純愛", "Pure Love"),
+    ("ほのぼの", "Heartwarming"),
+    ("日常/生活", "Daily Life/Living"),
+    ("学園もの", "School/Academy"),
+    ("ファンタジー", "Fantasy"),
+    ("百合", "Yuri"),
+    ("お姉さん", "Onee-san"),
+    ("妹", "Younger Sister"),
+    ("幼なじみ", "Childhood Friend"),
+    ("同級生/同僚", "Classmate/Colleague"),
+    ("先輩/後輩", "Senpai/Kouhai"),
+    ("メイド", "Maid"),
+    ("巫女", "Shrine Maiden"),
+    ("シスター", "Nun"),
+    ("天使/悪魔", "Angel/Devil"),
+    ("ケモミミ", "Kemonomimi"),
+    ("ツンデレ", "Tsundere"),
+    ("ヤンデレ", "Yandere"),
+    ("クーデレ", "Kuudere"),
+    ("主従", "Master and Servant"),
+];
+
+/// A row of a JSON translation file (and of `--export-untranslated-tags` JSON output)
+#[derive(Debug, Serialize, Deserialize)]
+struct TranslationRow {
+    tag_jp: String,
+    #[serde(default)]
+    tag_en: String,
+    #[serde(default, skip_deserializing)]
+    works: i64,
+}
+
+/// JSON translation files: `{"耳かき": "Ear Cleaning"}` or `[{"tag_jp": ..., "tag_en": ...}]`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TranslationFile {
+    Map(HashMap<String, String>),
+    Rows(Vec<TranslationRow>),
+}
+
+/// Splits one CSV line (RFC 4180 quoting, as written by `csv_field`)
+pub(crate) fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// `(tag_jp, tag_en)` pairs of a translation file: JSON, or CSV with `tag_jp,tag_en` as the first
+/// two columns (a header row and further columns are ignored). Rows left untranslated are dropped.
+fn parse_translations(content: &str, is_json: bool) -> Result<Vec<(String, String)>, HvtError> {
+    let pairs: Vec<(String, String)> = if is_json {
+        let file: TranslationFile = serde_json::from_str(content)
+            .map_err(|e| HvtError::Parse(format!("Invalid translation file: {}", e)))?;
+        match file {
+            TranslationFile::Map(map) => map.into_iter().collect(),
+            TranslationFile::Rows(rows) => rows.into_iter().map(|r| (r.tag_jp, r.tag_en)).collect(),
+        }
+    } else {
+        content
+            .lines()
+            .map(parse_csv_line)
+            .filter(|fields| fields.len() >= 2 && fields[0].trim() != "tag_jp")
+            .map(|mut fields| {
+                let tag_en = fields.swap_remove(1);
+                (fields.swap_remove(0), tag_en)
+            })
+            .collect()
+    };
+
+    let mut pairs: Vec<(String, String)> = pairs
+        .into_iter()
+        .map(|(jp, en)| (jp.trim().to_string(), en.trim().to_string()))
+        .filter(|(jp, en)| !jp.is_empty() && !en.is_empty())
+        .collect();
+    pairs.sort();
+    Ok(pairs)
+}
+
+/// `--import-tag-translations <file|bundled>`: applies a JP → EN translation pack as custom tag
+/// mappings (renames). Tags already renamed or ignored keep their mapping, and translations of
+/// tags not in the library are skipped. The affected works are flagged for re-tagging.
+pub fn run_import_translations_workflow(conn: &Connection, source: &str) -> Result<(), HvtError> {
+    let translations = if source == "bundled" {
+        BUNDLED_EN.iter().map(|(jp, en)| (jp.to_string(), en.to_string())).collect()
+    } else {
+        let path = Path::new(source);
+        if !path.is_file() {
+            return Err(HvtError::Generic(format!("Translation file not found: {}", source)));
+        }
+        let is_json = path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        parse_translations(&std::fs::read_to_string(path)?, is_json)?
+    };
+    info!("=== IMPORT TAG TRANSLATIONS: {} ({} entries) ===", source, translations.len());
+
+    let tags: HashMap<String, (Option<String>, bool)> = custom_tags::list_all_dlsite_tags(conn)?
+        .into_iter()
+        .map(|(_, name, custom, ignored)| (name, (custom, ignored)))
+        .collect();
+
+    let (mut applied, mut kept, mut missing, mut files_marked) = (0, 0, 0, 0);
+    let tx = conn.unchecked_transaction()?;
+    for (tag_jp, tag_en) in &translations {
+        match tags.get(tag_jp) {
+            None => missing += 1,
+            Some((Some(custom), _)) if custom == tag_en => {}
+            Some((Some(_), _)) | Some((None, true)) => {
+                kept += 1;
+                warn!("Kept existing mapping of '{}' (translation: '{}')", tag_jp, tag_en);
+            }
+            Some((None, false)) => {
+                custom_tags::add_custom_tag_mapping(&tx, tag_jp, tag_en)?;
+                files_marked += custom_tags::mark_works_for_retagging(&tx, tag_jp)?;
+                applied += 1;
+            }
+        }
+    }
+    tx.commit()?;
+
+    info!(
+        "Translations applied: {} | Kept existing mappings: {} | Not in library: {}",
+        applied, kept, missing
+    );
+    if files_marked > 0 {
+        info!("{} file(s) marked for re-tagging, run --tag to apply", files_marked);
+    }
+    Ok(())
+}
+
+/// `--export-untranslated-tags [file]`: writes the library's tags that still have Japanese names
+/// and no custom mapping, most used first, with an empty `tag_en` column to fill in and re-import
+/// with `--import-tag-translations`. CSV, or JSON when the file ends in `.json`.
+pub fn run_export_untranslated_workflow(conn: &Connection, output: Option<&str>) -> Result<(), HvtError> {
+    let mut rows: Vec<TranslationRow> = custom_tags::list_all_dlsite_tags_with_counts(conn, custom_tags::DEFAULT_TAG_SORT)?
+        .into_iter()
+        .filter(|(_, name, custom, ignored, _)| custom.is_none() && !ignored && !name.is_ascii())
+        .map(|(_, tag_jp, _, _, works)| TranslationRow { tag_jp, tag_en: String::new(), works })
+        .collect();
+    rows.sort_by(|a, b| b.works.cmp(&a.works).then_with(|| a.tag_jp.cmp(&b.tag_jp)));
+
+    let output = output.unwrap_or("hvtag_untranslated_tags.csv");
+    let is_json = Path::new(output).extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    let content = if is_json {
+        serde_json::to_string_pretty(&rows).map_err(|e| HvtError::Parse(format!("JSON serialization failed: {}", e)))?
+    } else {
+        let mut out = String::from("tag_jp,tag_en,works\n");
+        for row in &rows {
+            out.push_str(&format!("{},,{}\n", csv_field(&row.tag_jp), row.works));
+        }
+        out
+    };
+    std::fs::write(output, content)?;
+    info!("Exported {} untranslated tag(s) to {}", rows.len(), output);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_translations() {
+        let csv = "tag_jp,tag_en,works\n耳かき,Ear Cleaning,12\n\"癒し\",\"Healing, soft\"\nお姉さん,,3\n";
+        assert_eq!(
+            parse_translations(csv, false).unwrap(),
+            [
+                ("癒し".to_string(), "Healing, soft".to_string()),
+                ("耳かき".to_string(), "Ear Cleaning".to_string()),
+            ]
+        );
+
+        let map = r#"{"耳かき": "Ear Cleaning", "妹": " "}"#;
+        assert_eq!(parse_translations(map, true).unwrap(), [("耳かき".to_string(), "Ear Cleaning".to_string())]);
+        let rows = r#"[{"tag_jp": "耳かき", "tag_en": "Ear Cleaning", "works": 12}, {"tag_jp": "妹"}]"#;
+        assert_eq!(parse_translations(rows, true).unwrap(), [("耳かき".to_string(), "Ear Cleaning".to_string())]);
+    }
+}
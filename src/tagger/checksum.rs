@@ -0,0 +1,99 @@
+use std::io::Read;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+use crate::errors::HvtError;
+
+/// Computes the SHA-256 of a file as a lowercase hex string, streaming it in chunks so large
+/// WAV/FLAC files are never loaded into memory at once.
+pub fn sha256_file(file_path: &Path) -> Result<String, HvtError> {
+    let mut file = std::fs::File::open(file_path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Name of the per-work checksum manifest, in `sha256sum` format so it can also be checked with
+/// standard tools (`sha256sum -c checksums.sha256` from inside the work folder).
+pub const MANIFEST_FILE_NAME: &str = "checksums.sha256";
+
+/// Hashes every audio file at the root of a work folder and (over)writes its manifest.
+/// Returns the number of files listed.
+pub fn write_manifest(folder_path: &Path) -> Result<usize, HvtError> {
+    let mut content = String::new();
+    let files = super::list_audio_files(folder_path)?;
+
+    for file in &files {
+        let Some(file_name) = file.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        content.push_str(&format!("{}  {}\n", sha256_file(file)?, file_name));
+    }
+
+    std::fs::write(folder_path.join(MANIFEST_FILE_NAME), content)?;
+    Ok(files.len())
+}
+
+/// Reads a work folder's manifest, if it has one.
+/// Returns Vec<(sha256, file_name)>.
+pub fn read_manifest(folder_path: &Path) -> Result<Option<Vec<(String, String)>>, HvtError> {
+    let manifest_path = folder_path.join(MANIFEST_FILE_NAME);
+    if !manifest_path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(manifest_path)?;
+    Ok(Some(parse_manifest(&content)))
+}
+
+/// Parses `sha256sum` output: `<64 hex chars><space><space or '*'><file name>` per line.
+/// Malformed lines are skipped.
+fn parse_manifest(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (hash, rest) = line.split_at_checked(64)?;
+            if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            let name = rest.strip_prefix("  ").or_else(|| rest.strip_prefix(" *"))?;
+            if name.is_empty() {
+                return None;
+            }
+            Some((hash.to_ascii_lowercase(), name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest_text_and_binary_modes() {
+        let hash = "a".repeat(64);
+        let content = format!("{hash}  01 トラック.mp3\n{hash} *02.flac\n");
+        assert_eq!(
+            parse_manifest(&content),
+            vec![
+                (hash.clone(), "01 トラック.mp3".to_string()),
+                (hash, "02.flac".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_manifest_skips_malformed_lines() {
+        let content = format!("not a hash  file.mp3\n{}  \n\n", "b".repeat(64));
+        assert!(parse_manifest(&content).is_empty());
+    }
+}
@@ -2,6 +2,8 @@ use std::path::Path;
 use std::process::Command;
 use tracing::debug;
 use crate::errors::HvtError;
+use crate::folders::long_path;
+use crate::run_stats::RunStats;
 
 /// Converts an audio file to MP3 using ffmpeg
 ///
@@ -20,6 +22,7 @@ pub async fn convert_to_mp3(
     output: &Path,
     bitrate: u32,
 ) -> Result<(), HvtError> {
+    let (input, output) = (long_path(input), long_path(output));
     let input_str = input.to_str()
         .ok_or_else(|| HvtError::AudioConversion("Invalid input path".to_string()))?;
 
@@ -33,6 +36,7 @@ pub async fn convert_to_mp3(
             "-i", input_str,
             "-codec:a", "libmp3lame",
             "-b:a", &bitrate_str,
+            "-f", "mp3",  // Explicit muxer: output may be a ".mp3.tmp" temp file
             "-y",  // Overwrite output file if it exists
             output_str,
         ])
@@ -53,6 +57,7 @@ pub async fn convert_to_mp3(
 /// # Arguments
 /// * `file_path` - Path to the audio file to convert
 /// * `bitrate` - Target bitrate in kbps (e.g., 320)
+/// * `stats` - The run's stats, counting the file written and the bytes converted
 ///
 /// # Returns
 /// Ok(()) if conversion succeeds and original is deleted, Err otherwise
@@ -65,9 +70,11 @@ pub async fn convert_to_mp3(
 pub async fn convert_to_mp3_in_place(
     file_path: &Path,
     bitrate: u32,
+    stats: &RunStats,
 ) -> Result<(), HvtError> {
     // Create temporary output path
     let temp_output = file_path.with_extension("mp3.tmp");
+    let source_size = std::fs::metadata(file_path).map(|m| m.len()).unwrap_or(0);
 
     // Convert to temp file
     convert_to_mp3(file_path, &temp_output, bitrate).await?;
@@ -82,9 +89,116 @@ pub async fn convert_to_mp3_in_place(
         .map_err(|e| HvtError::Io(e))?;
 
     debug!("Converted and replaced: {} -> {}", file_path.display(), final_path.display());
+    stats.add_file_written();
+    stats.add_bytes_converted(source_size);
     Ok(())
 }
 
+/// Extracts the `start`..`end` seconds of an audio file into an MP3 (one track of a cue sheet):
+/// a stream copy for an MP3 source, an encode at `bitrate` kbps otherwise. `end` `None` goes to
+/// the end of the file.
+pub fn extract_mp3_segment(
+    input: &Path,
+    output: &Path,
+    start: f64,
+    end: Option<f64>,
+    bitrate: u32,
+    stats: &RunStats,
+) -> Result<(), HvtError> {
+    let (input, output) = (long_path(input), long_path(output));
+    let input_str = input.to_str()
+        .ok_or_else(|| HvtError::AudioConversion("Invalid input path".to_string()))?;
+    let output_str = output.to_str()
+        .ok_or_else(|| HvtError::AudioConversion("Invalid output path".to_string()))?;
+
+    let mut args: Vec<String> = vec![
+        "-v".into(), "error".into(),
+        "-i".into(), input_str.into(),
+        "-ss".into(), format!("{:.3}", start),
+    ];
+    if let Some(end) = end {
+        args.extend(["-to".into(), format!("{:.3}", end)]);
+    }
+    args.extend(["-map".into(), "0:a".into(), "-map_metadata".into(), "-1".into()]);
+    let is_mp3 = input.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("mp3"));
+    if is_mp3 {
+        args.extend(["-c:a".into(), "copy".into()]);
+    } else {
+        args.extend(["-codec:a".into(), "libmp3lame".into(), "-b:a".into(), format!("{}k", bitrate)]);
+    }
+    args.extend(["-f".into(), "mp3".into(), "-y".into(), output_str.into()]);
+
+    let status = Command::new("ffmpeg")
+        .args(&args)
+        .status()
+        .map_err(|e| HvtError::AudioConversion(format!("Failed to execute ffmpeg: {}", e)))?;
+
+    if !status.success() {
+        let _ = std::fs::remove_file(&output);
+        return Err(HvtError::AudioConversion(
+            format!("ffmpeg exited with status: {}", status)
+        ));
+    }
+    stats.add_file_written();
+    Ok(())
+}
+
+/// Duration of an audio file in seconds, from the `Duration:` line ffmpeg prints about its
+/// input. `None` when ffmpeg can't tell.
+pub fn audio_duration(file_path: &Path) -> Option<f64> {
+    let output = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-i")
+        .arg(long_path(file_path))
+        .output()
+        .ok()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stderr.lines().find_map(|l| l.trim().strip_prefix("Duration:"))?;
+    parse_timestamp(line.split(',').next()?)
+}
+
+/// Chapters of an audio file (`.m4b`/`.m4a` audiobooks), from the `Chapter #` lines ffmpeg
+/// prints about its input: Vec<(start, end, title)>, times in seconds. Empty without chapters.
+pub fn read_chapters(file_path: &Path) -> Result<Vec<(f64, f64, Option<String>)>, HvtError> {
+    let output = Command::new("ffmpeg")
+        .arg("-hide_banner")
+        .arg("-i")
+        .arg(long_path(file_path))
+        .output()
+        .map_err(|e| HvtError::AudioConversion(format!("Failed to execute ffmpeg: {}", e)))?;
+    Ok(parse_chapters(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// "    Chapter #0:1: start 192.000000, end 400.500000" followed by an optional
+/// "        title           : 耳かき" metadata line
+fn parse_chapters(stderr: &str) -> Vec<(f64, f64, Option<String>)> {
+    let mut chapters: Vec<(f64, f64, Option<String>)> = Vec::new();
+    let mut in_chapter = false;
+    for line in stderr.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Chapter #") {
+            in_chapter = false;
+            let times = rest.split_once(": start ").map(|(_, times)| times);
+            let Some((start, end)) = times.and_then(|t| t.split_once(", end ")) else {
+                continue;
+            };
+            if let (Ok(start), Ok(end)) = (start.trim().parse(), end.trim().parse()) {
+                chapters.push((start, end, None));
+                in_chapter = true;
+            }
+        } else if line.starts_with("Stream #") || line.starts_with("Input #") {
+            in_chapter = false;
+        } else if let (true, Some((key, value))) = (in_chapter, line.split_once(':')) {
+            if key.trim() == "title" && !value.trim().is_empty() {
+                if let Some(chapter) = chapters.last_mut() {
+                    chapter.2.get_or_insert_with(|| value.trim().to_string());
+                }
+            }
+        }
+    }
+    chapters
+}
+
 /// Checks if ffmpeg is available in the system PATH
 pub fn is_ffmpeg_available() -> bool {
     Command::new("ffmpeg")
@@ -93,3 +207,357 @@ pub fn is_ffmpeg_available() -> bool {
         .map(|output| output.status.success())
         .unwrap_or(false)
 }
+
+/// Decodes an audio file end-to-end with ffmpeg (discarding the output) to detect truncated or
+/// corrupt audio.
+///
+/// # Returns
+/// * `Ok(None)` - ffmpeg decoded the whole file without reporting anything
+/// * `Ok(Some(details))` - ffmpeg reported decode errors; `details` is its stderr output
+/// * `Err(_)` - ffmpeg could not be run on this file
+pub fn check_audio_integrity(file_path: &Path) -> Result<Option<String>, HvtError> {
+    let file_path = long_path(file_path);
+    let path_str = file_path.to_str()
+        .ok_or_else(|| HvtError::AudioConversion("Invalid input path".to_string()))?;
+
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i", path_str, "-f", "null", "-"])
+        .output()
+        .map_err(|e| HvtError::AudioConversion(format!("Failed to execute ffmpeg: {}", e)))?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if output.status.success() && stderr.is_empty() {
+        return Ok(None);
+    }
+
+    if stderr.is_empty() {
+        Ok(Some(format!("ffmpeg exited with status: {}", output.status)))
+    } else {
+        Ok(Some(stderr))
+    }
+}
+
+/// A track whose loudest sample stays at or under this level is considered silent.
+pub const SILENCE_THRESHOLD_DB: f64 = -70.0;
+
+/// Measures a file with ffmpeg's `volumedetect` filter to catch empty or near-silent tracks
+/// (typically downloads that were cut short or zero-filled), which decode without any error
+/// and so pass `check_audio_integrity`.
+///
+/// # Returns
+/// * `Ok(None)` - the file contains audible audio
+/// * `Ok(Some(reason))` - the file is empty, has zero duration or is silent
+/// * `Err(_)` - ffmpeg could not be run on this file
+pub fn detect_silent_audio(file_path: &Path) -> Result<Option<String>, HvtError> {
+    if std::fs::metadata(file_path)?.len() == 0 {
+        return Ok(Some("empty file (0 bytes)".to_string()));
+    }
+
+    let file_path = long_path(file_path);
+    let path_str = file_path.to_str()
+        .ok_or_else(|| HvtError::AudioConversion("Invalid input path".to_string()))?;
+
+    // volumedetect reports at info level, so the log level can't be lowered to "error" here
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i", path_str, "-af", "volumedetect", "-f", "null", "-"])
+        .output()
+        .map_err(|e| HvtError::AudioConversion(format!("Failed to execute ffmpeg: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(HvtError::AudioConversion(
+            format!("ffmpeg exited with status: {}", output.status)
+        ));
+    }
+
+    let (n_samples, max_volume) = parse_volumedetect(&String::from_utf8_lossy(&output.stderr));
+    Ok(classify_volume(n_samples, max_volume))
+}
+
+/// Silences of an audio file, from ffmpeg's silencedetect filter: Vec<(start, end)> in seconds
+/// of every stretch below `threshold_db` for at least `min_duration` seconds. A silence running
+/// to the end of the file has no `silence_end` and is left out.
+pub fn detect_silences(file_path: &Path, threshold_db: f64, min_duration: f64) -> Result<Vec<(f64, f64)>, HvtError> {
+    let file_path = long_path(file_path);
+    let path_str = file_path.to_str()
+        .ok_or_else(|| HvtError::AudioConversion("Invalid input path".to_string()))?;
+    let filter = format!("silencedetect=noise={}dB:d={}", threshold_db, min_duration);
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i", path_str, "-af", &filter, "-f", "null", "-"])
+        .output()
+        .map_err(|e| HvtError::AudioConversion(format!("Failed to execute ffmpeg: {}", e)))?;
+    if !output.status.success() {
+        return Err(HvtError::AudioConversion(format!("ffmpeg exited with status: {}", output.status)));
+    }
+    Ok(parse_silencedetect(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Pairs the `silence_start: X` and `silence_end: Y | silence_duration: Z` log lines.
+fn parse_silencedetect(output: &str) -> Vec<(f64, f64)> {
+    let mut silences = Vec::new();
+    let mut start: Option<f64> = None;
+    for line in output.lines() {
+        if let Some((_, value)) = line.split_once("silence_start:") {
+            start = value.trim().parse().ok();
+        } else if let Some((_, value)) = line.split_once("silence_end:") {
+            let end = value.split('|').next().and_then(|v| v.trim().parse().ok());
+            if let (Some(start), Some(end)) = (start.take(), end) {
+                silences.push((start, end));
+            }
+        }
+    }
+    silences
+}
+
+/// Where to cut a file at its `silences`: the middle of each one, except a silence at the very
+/// start (the first track starts at 0 anyway).
+pub fn silence_split_points(silences: &[(f64, f64)]) -> Vec<f64> {
+    silences.iter()
+        .filter(|(start, _)| *start > 0.5)
+        .map(|(start, end)| (start + end) / 2.0)
+        .collect()
+}
+
+/// Extracts `n_samples` and `max_volume` (dB) from ffmpeg's volumedetect log lines.
+fn parse_volumedetect(output: &str) -> (Option<u64>, Option<f64>) {
+    let mut n_samples = None;
+    let mut max_volume = None;
+    for line in output.lines() {
+        if let Some((_, value)) = line.split_once("n_samples:") {
+            n_samples = value.trim().parse().ok();
+        } else if let Some((_, value)) = line.split_once("max_volume:") {
+            max_volume = value.trim().trim_end_matches("dB").trim().parse().ok();
+        }
+    }
+    (n_samples, max_volume)
+}
+
+fn classify_volume(n_samples: Option<u64>, max_volume: Option<f64>) -> Option<String> {
+    match (n_samples, max_volume) {
+        (None | Some(0), _) => Some("no audio samples (zero duration)".to_string()),
+        (Some(_), Some(max)) if max <= SILENCE_THRESHOLD_DB => {
+            Some(format!("silent (max volume {:.1} dB)", max))
+        }
+        _ => None,
+    }
+}
+
+/// Loudness of one file measured by ffmpeg's `ebur128` filter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness (LUFS)
+    pub integrated: f64,
+    /// True peak, linear (1.0 = full scale)
+    pub peak: f64,
+    /// Duration in seconds, weighs the file in the album loudness
+    pub duration: f64,
+}
+
+/// Measures a file's integrated loudness and true peak (EBU R128) with ffmpeg, for ReplayGain.
+pub fn measure_loudness(file_path: &Path) -> Result<Loudness, HvtError> {
+    let file_path = long_path(file_path);
+    let path_str = file_path.to_str()
+        .ok_or_else(|| HvtError::AudioConversion("Invalid input path".to_string()))?;
+
+    // Like volumedetect, the ebur128 summary is logged at info level
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostats", "-i", path_str, "-af", "ebur128=peak=true", "-f", "null", "-"])
+        .output()
+        .map_err(|e| HvtError::AudioConversion(format!("Failed to execute ffmpeg: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(HvtError::AudioConversion(
+            format!("ffmpeg exited with status: {}", output.status)
+        ));
+    }
+
+    parse_ebur128(&String::from_utf8_lossy(&output.stderr))
+        .ok_or_else(|| HvtError::AudioConversion("No loudness summary in ffmpeg output".to_string()))
+}
+
+/// Extracts the input duration and the `I:` / `Peak:` values of the ebur128 summary.
+fn parse_ebur128(output: &str) -> Option<Loudness> {
+    let mut duration = None;
+    let mut integrated = None;
+    let mut peak_dbfs = None;
+    // The filter also logs per-frame lines ("... I: -20.1 LUFS ..."): only the summary is read
+    let summary = &output[output.rfind("Summary:")?..];
+    for line in output.lines() {
+        if let Some((_, value)) = line.split_once("Duration:") {
+            duration = value.split(',').next().and_then(parse_timestamp);
+        }
+    }
+    for line in summary.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("I:") {
+            integrated = value.trim().trim_end_matches("LUFS").trim().parse::<f64>().ok();
+        } else if let Some(value) = line.strip_prefix("Peak:") {
+            peak_dbfs = value.trim().trim_end_matches("dBFS").trim().parse::<f64>().ok();
+        }
+    }
+    Some(Loudness {
+        integrated: integrated?,
+        peak: 10f64.powf(peak_dbfs? / 20.0),
+        duration: duration.unwrap_or(0.0),
+    })
+}
+
+/// ReplayGain 2.0 reference level: a track measured at -18 LUFS gets a 0 dB gain.
+pub const REPLAYGAIN_REFERENCE_LUFS: f64 = -18.0;
+
+/// Loudness and peak of a whole work from its files: the duration-weighted energy mean of their
+/// integrated loudness (close to measuring the files back to back) and the highest peak.
+pub fn album_loudness(tracks: &[Loudness]) -> (f64, f64) {
+    let total: f64 = tracks.iter().map(|t| t.duration).sum();
+    let energy: f64 = if total > 0.0 {
+        tracks.iter().map(|t| t.duration * 10f64.powf(t.integrated / 10.0)).sum::<f64>() / total
+    } else {
+        tracks.iter().map(|t| 10f64.powf(t.integrated / 10.0)).sum::<f64>() / tracks.len().max(1) as f64
+    };
+    let peak = tracks.iter().map(|t| t.peak).fold(0.0, f64::max);
+    (10.0 * energy.log10(), peak)
+}
+
+/// "00:03:12.34" -> 192.34
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in value.trim().split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+/// Writes container-level metadata into an MP4/M4V video in place, without re-encoding
+/// (ffmpeg stream copy into a temp file, then replaces the original). Existing metadata that
+/// isn't overridden is kept.
+///
+/// # Arguments
+/// * `file_path` - Path to the video file
+/// * `metadata` - (key, value) pairs using ffmpeg's MP4 metadata keys (title, artist, album...)
+/// * `stats` - The run's stats, counting the file written
+pub fn write_video_metadata(file_path: &Path, metadata: &[(&str, String)], stats: &RunStats) -> Result<(), HvtError> {
+    let file_path = long_path(file_path);
+    let input_str = file_path.to_str()
+        .ok_or_else(|| HvtError::AudioConversion("Invalid input path".to_string()))?;
+
+    let temp_output = file_path.with_extension("tagging.tmp");
+    let temp_str = temp_output.to_str()
+        .ok_or_else(|| HvtError::AudioConversion("Invalid output path".to_string()))?;
+
+    let mut args: Vec<String> = vec![
+        "-v".into(), "error".into(),
+        "-i".into(), input_str.into(),
+        "-map".into(), "0".into(),
+        "-map_metadata".into(), "0".into(),
+        "-c".into(), "copy".into(),
+    ];
+    for (key, value) in metadata {
+        args.push("-metadata".into());
+        args.push(format!("{}={}", key, value));
+    }
+    args.extend(["-f".into(), "mp4".into(), "-y".into(), temp_str.into()]);
+
+    let status = Command::new("ffmpeg")
+        .args(&args)
+        .status()
+        .map_err(|e| HvtError::AudioConversion(format!("Failed to execute ffmpeg: {}", e)))?;
+
+    if !status.success() {
+        let _ = std::fs::remove_file(&temp_output);
+        return Err(HvtError::AudioConversion(
+            format!("ffmpeg exited with status: {}", status)
+        ));
+    }
+
+    std::fs::rename(&temp_output, &file_path)?;
+    debug!("Wrote video metadata: {}", file_path.display());
+    stats.add_file_written();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_silencedetect_output() {
+        let output = "[silencedetect @ 0x1] silence_start: 0\n\
+                      [silencedetect @ 0x1] silence_end: 1.2 | silence_duration: 1.2\n\
+                      [silencedetect @ 0x1] silence_start: 300.5\n\
+                      [silencedetect @ 0x1] silence_end: 303.5 | silence_duration: 3\n\
+                      [silencedetect @ 0x1] silence_start: 1190\n";
+        let silences = parse_silencedetect(output);
+        assert_eq!(silences, vec![(0.0, 1.2), (300.5, 303.5)]);
+        assert_eq!(silence_split_points(&silences), vec![302.0]);
+    }
+
+    #[test]
+    fn parses_chapters_output() {
+        let stderr = "Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'book.m4b':\n\
+                      \x20 Metadata:\n\
+                      \x20   title           : 作品\n\
+                      \x20 Duration: 00:20:00.00, start: 0.000000, bitrate: 128 kb/s\n\
+                      \x20 Chapter #0:0: start 0.000000, end 192.500000\n\
+                      \x20   Metadata:\n\
+                      \x20     title           : 導入\n\
+                      \x20 Chapter #0:1: start 192.500000, end 1200.000000\n\
+                      \x20 Stream #0:0(und): Audio: aac (LC), 44100 Hz, stereo\n\
+                      \x20   Metadata:\n\
+                      \x20     title           : Sound\n";
+        assert_eq!(
+            parse_chapters(stderr),
+            vec![(0.0, 192.5, Some("導入".to_string())), (192.5, 1200.0, None)]
+        );
+    }
+
+    #[test]
+    fn classifies_volumedetect_output() {
+        let silent = "[Parsed_volumedetect_0 @ 0x1] n_samples: 2646000\n\
+                      [Parsed_volumedetect_0 @ 0x1] mean_volume: -91.0 dB\n\
+                      [Parsed_volumedetect_0 @ 0x1] max_volume: -91.0 dB\n";
+        let (n, max) = parse_volumedetect(silent);
+        assert_eq!(n, Some(2646000));
+        assert_eq!(max, Some(-91.0));
+        assert!(classify_volume(n, max).is_some());
+
+        let digital_zero = "n_samples: 44100\nmax_volume: -inf dB\n";
+        let (n, max) = parse_volumedetect(digital_zero);
+        assert!(classify_volume(n, max).is_some());
+
+        assert!(classify_volume(Some(0), None).is_some());
+        assert!(classify_volume(None, None).is_some());
+        assert_eq!(classify_volume(Some(44100), Some(-3.2)), None);
+    }
+
+    #[test]
+    fn parses_ebur128_summary() {
+        let output = "  Duration: 00:03:12.50, start: 0.025057, bitrate: 320 kb/s\n\
+                      [Parsed_ebur128_0 @ 0x1] t: 0.4 TARGET:-23 LUFS M: -30.2 S:-120.7 I: -30.2 LUFS\n\
+                      [Parsed_ebur128_0 @ 0x1] Summary:\n\
+                      \n\
+                      \x20 Integrated loudness:\n\
+                      \x20   I:         -19.5 LUFS\n\
+                      \x20   Threshold: -29.7 LUFS\n\
+                      \n\
+                      \x20 True peak:\n\
+                      \x20   Peak:        0.0 dBFS\n";
+        let loudness = parse_ebur128(output).unwrap();
+        assert_eq!(loudness.integrated, -19.5);
+        assert_eq!(loudness.peak, 1.0);
+        assert_eq!(loudness.duration, 192.5);
+
+        assert_eq!(parse_ebur128("no summary"), None);
+    }
+
+    #[test]
+    fn album_loudness_weighs_tracks_by_duration() {
+        let track = |integrated, peak, duration| Loudness { integrated, peak, duration };
+        let (lufs, peak) = album_loudness(&[track(-20.0, 0.5, 60.0), track(-20.0, 0.9, 30.0)]);
+        assert!((lufs + 20.0).abs() < 1e-9);
+        assert_eq!(peak, 0.9);
+
+        // A short loud track moves the album loudness less than a long one
+        let (short_loud, _) = album_loudness(&[track(-20.0, 1.0, 300.0), track(-10.0, 1.0, 10.0)]);
+        let (long_loud, _) = album_loudness(&[track(-20.0, 1.0, 10.0), track(-10.0, 1.0, 300.0)]);
+        assert!(short_loud < long_loud);
+    }
+}
@@ -1,42 +1,93 @@
 use std::path::{Path, PathBuf};
-use tracing::debug;
+use std::time::Duration;
+use rusqlite::Connection;
+use tracing::{debug, info, warn};
+use crate::config::ImagesConfig;
+use crate::database::actor::DbHandle;
+use crate::database::{cover_failures, queries};
+use crate::dlsite::MetadataClient;
 use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::run_stats::RunStats;
+use crate::tagger::types::WorkDetails;
 use image::ImageFormat;
 
-/// Get the cache directory for covers
-fn get_cache_dir() -> Result<PathBuf, HvtError> {
-    let home = dirs::home_dir()
-        .ok_or_else(|| HvtError::Generic("Could not determine home directory".to_string()))?;
+/// Subfolder of a work holding the sample gallery (`--images full`)
+pub const SCANS_DIR_NAME: &str = "scans";
 
-    let cache_dir = home.join(".hvtag").join("covers_cache");
+/// How covers are downloaded (`[images]`): size, retries and skip-listing of failing URLs
+#[derive(Debug, Clone, Copy)]
+pub struct CoverSettings {
+    /// Target size (width, height) of the cached cover, `None` to keep the original
+    target_size: Option<(u32, u32)>,
+    /// Retries of a download after a network error, a 429 or a 5xx
+    retries: u32,
+    /// A URL that failed this many times is not tried again (0: always tried)
+    max_failures: u32,
+    /// Nor before this many hours after its last failure
+    cooldown_hours: u64,
+}
 
-    // Create cache directory if it doesn't exist
-    if !cache_dir.exists() {
-        std::fs::create_dir_all(&cache_dir)
-            .map_err(|e| HvtError::Generic(format!("Failed to create cache directory: {}", e)))?;
+impl Default for CoverSettings {
+    fn default() -> Self {
+        Self { target_size: None, retries: 2, max_failures: 3, cooldown_hours: 24 }
+    }
+}
+
+impl CoverSettings {
+    /// Covers resized to `[images] cover_size`
+    pub fn from_config(images: &ImagesConfig) -> Self {
+        CoverSettings {
+            target_size: images.cover_target_size(),
+            retries: images.cover_retries,
+            max_failures: images.cover_max_failures,
+            cooldown_hours: images.cover_failure_cooldown_hours,
+        }
     }
 
-    Ok(cache_dir)
+    /// The same settings with covers kept at their original size
+    pub fn original_size(self) -> Self {
+        CoverSettings { target_size: None, ..self }
+    }
+}
+
+/// Get the cache directory for covers
+fn get_cache_dir() -> Result<PathBuf, HvtError> {
+    crate::paths::cover_cache_dir()
 }
 
 /// Downloads cover art from URL and saves it to local cache
 ///
 /// # Arguments
 /// * `url` - The URL of the image to download
+/// * `client` - The run's client (its HTTP client, and the stats counting the bytes)
 /// * `rjcode` - The RJ code of the work (used as cache filename)
-/// * `target_size` - Optional target size (width, height) for resizing. If None, keeps original size.
+/// * `settings` - Target size (resized when set) and retries of the download
 ///
 /// # Returns
 /// Ok(PathBuf) with path to cached cover, Err if download or save fails
 pub async fn download_cover_to_cache(
     url: &str,
+    client: &MetadataClient,
     rjcode: &str,
-    target_size: Option<(u32, u32)>,
+    settings: &CoverSettings,
 ) -> Result<PathBuf, HvtError> {
-    // Download image from URL
-    let response = reqwest::get(url)
-        .await
-        .map_err(|e| HvtError::Http(format!("Failed to download cover art: {}", e)))?;
+    // Download image from URL, again after a transient failure (a 404 is not retried)
+    let retries = settings.retries;
+    let mut attempt = 0;
+    let response = loop {
+        let transient = match client.http.send(client.http.get(url)).await {
+            Ok(response) if !(response.status().is_server_error() || response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS) => break response,
+            Ok(response) => format!("HTTP {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+        if attempt >= retries {
+            return Err(HvtError::Http(format!("Failed to download cover art: {}", transient)));
+        }
+        attempt += 1;
+        debug!("Cover download of {} failed ({}), retry {}/{}", rjcode, transient, attempt, retries);
+        tokio::time::sleep(Duration::from_secs(2 * attempt as u64)).await;
+    };
 
     if !response.status().is_success() {
         return Err(HvtError::Http(format!(
@@ -48,13 +99,14 @@ pub async fn download_cover_to_cache(
     let bytes = response.bytes()
         .await
         .map_err(|e| HvtError::Http(format!("Failed to read cover art bytes: {}", e)))?;
+    client.stats.add_bytes_downloaded(bytes.len());
 
     // Load image
     let img = image::load_from_memory(&bytes)
         .map_err(|e| HvtError::Image(format!("Failed to decode image: {}", e)))?;
 
     // Optionally resize
-    let final_img = if let Some((width, height)) = target_size {
+    let final_img = if let Some((width, height)) = settings.target_size {
         img.resize_exact(width, height, image::imageops::FilterType::Lanczos3)
     } else {
         img
@@ -71,17 +123,128 @@ pub async fn download_cover_to_cache(
     Ok(cache_path)
 }
 
+/// Where a work's cover was downloaded from, stored as `dlsite_covers.source`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoverSource {
+    /// The cover link stored when the work was fetched
+    CoverLink,
+    /// The `work_image` the DLSite API returns now (the stored link may have moved)
+    ApiWorkImage,
+    /// The first image of the sample gallery
+    SampleImage,
+}
+
+impl CoverSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoverSource::CoverLink => "cover_link",
+            CoverSource::ApiWorkImage => "api_work_image",
+            CoverSource::SampleImage => "sample_image",
+        }
+    }
+}
+
+/// Records a failed cover URL through the database thread of the workflow when there is one, so
+/// the covers downloaded at the same time don't wait on each other's writes
+async fn record_cover_failure(
+    conn: &Connection,
+    db_thread: Option<&DbHandle>,
+    rjcode: &RJCode,
+    url: &str,
+    error: String,
+) -> Result<(), HvtError> {
+    match db_thread {
+        Some(db) => {
+            let (rjcode, url) = (rjcode.clone(), url.to_string());
+            db.call(move |conn| cover_failures::record_failure(conn, &rjcode, &url, &error)).await
+        }
+        None => cover_failures::record_failure(conn, rjcode, url, &error),
+    }
+}
+
+/// Caches a work's cover, trying in order the stored cover link, the API's current `work_image`
+/// and the first sample image, and records in `dlsite_covers` which one was used. Errors only
+/// when every source failed. URLs that kept failing are skipped (see `cover_failures`), failures
+/// are recorded through `db_thread` when the workflow has one.
+pub async fn download_cover_with_fallback(
+    conn: &Connection,
+    db_thread: Option<&DbHandle>,
+    rjcode: &RJCode,
+    client: &MetadataClient,
+    settings: &CoverSettings,
+) -> Result<CoverSource, HvtError> {
+    if client.cache.is_offline() {
+        return Err(HvtError::Offline(format!("cover of {} not downloaded", rjcode)));
+    }
+    let mut tried: Vec<String> = Vec::new();
+    let mut skipped = 0;
+    if let Some(section) = queries::get_dlsite_section(conn, rjcode)? {
+        client.sections.remember(rjcode, &section);
+    }
+
+    // Sources are looked up lazily: the API is only queried again when the stored link failed
+    for source in [CoverSource::CoverLink, CoverSource::ApiWorkImage, CoverSource::SampleImage] {
+        let url = match source {
+            CoverSource::CoverLink => queries::get_cover_link(conn, rjcode)?,
+            CoverSource::ApiWorkImage => WorkDetails::build_from_rjcode_with_client(rjcode.to_string(), client)
+                .await
+                .map(|wd| wd.image_link)
+                .map_err(|e| debug!("DLSite API lookup of {} failed: {}", rjcode, e))
+                .ok(),
+            CoverSource::SampleImage => queries::get_sample_images(conn, rjcode)?
+                .into_iter()
+                .next()
+                .map(|(_, url, _)| url),
+        };
+        let Some(url) = url.filter(|u| !u.is_empty() && !tried.contains(u)) else {
+            continue;
+        };
+        if cover_failures::is_skipped(conn, &url, settings.max_failures, settings.cooldown_hours)? {
+            debug!("Cover source {} of {} skipped, it kept failing ({})", source.as_str(), rjcode, url);
+            tried.push(url);
+            skipped += 1;
+            continue;
+        }
+
+        match download_cover_to_cache(&url, client, rjcode.as_str(), settings).await {
+            Ok(_) => {
+                if source != CoverSource::CoverLink {
+                    info!("Cover of {} taken from fallback source {} ({})", rjcode, source.as_str(), url);
+                }
+                queries::set_cover_source(conn, rjcode, source.as_str(), &url)?;
+                cover_failures::clear(conn, &url)?;
+                return Ok(source);
+            }
+            Err(e) => {
+                warn!("Cover source {} failed for {}: {}", source.as_str(), rjcode, e);
+                record_cover_failure(conn, db_thread, rjcode, &url, e.to_string()).await?;
+                tried.push(url);
+            }
+        }
+    }
+
+    if skipped > 0 {
+        return Err(HvtError::Http(format!(
+            "No working cover source for {} ({} skipped after failing before, --force tries them again)",
+            rjcode, skipped
+        )));
+    }
+    Err(HvtError::Http(format!("No working cover source for {}", rjcode)))
+}
+
 /// Copy cover from cache to final folder location
 ///
 /// # Arguments
 /// * `rjcode` - The RJ code of the work
 /// * `folder_path` - The destination folder path
+/// * `stats` - The run's stats, counting the cover written
 ///
 /// # Returns
 /// Ok(()) if successful, Err if copy fails
 pub fn copy_cover_from_cache(
     rjcode: &str,
     folder_path: &Path,
+    stats: &RunStats,
 ) -> Result<(), HvtError> {
     let cache_dir = get_cache_dir()?;
     let cache_path = cache_dir.join(format!("{}.jpeg", rjcode));
@@ -98,6 +261,7 @@ pub fn copy_cover_from_cache(
 
     std::fs::copy(&cache_path, &dest_path)
         .map_err(|e| HvtError::Generic(format!("Failed to copy cover from cache: {}", e)))?;
+    stats.add_file_written();
 
     debug!("Cover copied from cache to: {}", dest_path.display());
 
@@ -107,55 +271,118 @@ pub fn copy_cover_from_cache(
     Ok(())
 }
 
-/// Downloads cover art from URL and saves it as folder.jpeg (LEGACY - direct save)
-///
-/// # Arguments
-/// * `url` - The URL of the image to download
-/// * `folder_path` - The path to the folder where folder.jpeg will be saved
-/// * `target_size` - Optional target size (width, height) for resizing. If None, keeps original size.
-///
-/// # Returns
-/// Ok(()) if successful, Err if download or save fails
-pub async fn download_and_save_cover(
-    url: &str,
-    folder_path: &Path,
-    target_size: Option<(u32, u32)>,
-) -> Result<(), HvtError> {
-    // Download image from URL
-    debug!("Downloading cover from: {}", url);
-    let response = reqwest::get(url)
-        .await
-        .map_err(|e| HvtError::Http(format!("Failed to download cover art: {}", e)))?;
+/// File name of a sample image in scans/: its gallery position and the URL's extension ("03.png")
+pub fn sample_image_file_name(position: i64, url: &str) -> String {
+    let ext = url
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| matches!(ext.as_str(), "jpg" | "jpeg" | "png" | "gif" | "webp"))
+        .unwrap_or_else(|| "jpg".to_string());
+    format!("{:02}.{}", position, ext)
+}
 
-    if !response.status().is_success() {
-        return Err(HvtError::Http(format!(
-            "HTTP {} when downloading cover art",
-            response.status()
-        )));
+/// Cache subfolder holding a work's sample images until they are moved to the work folder
+fn sample_images_cache_dir(rjcode: &str) -> Result<PathBuf, HvtError> {
+    Ok(get_cache_dir()?.join(format!("{}_{}", rjcode, SCANS_DIR_NAME)))
+}
+
+/// Downloads sample images (position, URL) to the cache as-is, without re-encoding. A failed
+/// image is only logged. Returns how many were cached.
+pub async fn download_sample_images_to_cache(
+    rjcode: &str,
+    images: &[(i64, String)],
+    client: &MetadataClient,
+) -> Result<usize, HvtError> {
+    if client.cache.is_offline() {
+        return Err(HvtError::Offline(format!("sample images of {} not downloaded", rjcode)));
+    }
+    let cache_dir = sample_images_cache_dir(rjcode)?;
+    if !cache_dir.exists() {
+        std::fs::create_dir_all(&cache_dir)
+            .map_err(|e| HvtError::Generic(format!("Failed to create cache directory: {}", e)))?;
     }
 
-    let bytes = response.bytes()
-        .await
-        .map_err(|e| HvtError::Http(format!("Failed to read cover art bytes: {}", e)))?;
+    let mut cached = 0;
+    for (position, url) in images {
+        let response = match client.http.send(client.http.get(url)).await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                warn!("HTTP {} when downloading sample image {}", response.status(), url);
+                continue;
+            }
+            Err(e) => {
+                warn!("Failed to download sample image {}: {}", url, e);
+                continue;
+            }
+        };
+        let bytes = response.bytes()
+            .await
+            .map_err(|e| HvtError::Http(format!("Failed to read sample image bytes: {}", e)))?;
+        client.stats.add_bytes_downloaded(bytes.len());
 
-    // Load image
-    let img = image::load_from_memory(&bytes)
-        .map_err(|e| HvtError::Image(format!("Failed to decode image: {}", e)))?;
+        std::fs::write(cache_dir.join(sample_image_file_name(*position, url)), &bytes)
+            .map_err(|e| HvtError::Generic(format!("Failed to save sample image to cache: {}", e)))?;
+        cached += 1;
+    }
 
-    // Optionally resize
-    let final_img = if let Some((width, height)) = target_size {
-        img.resize_exact(width, height, image::imageops::FilterType::Lanczos3)
-    } else {
-        img
-    };
+    debug!("{} sample image(s) cached for {}", cached, rjcode);
+    Ok(cached)
+}
 
-    // Save to folder.jpeg
-    let cover_path = folder_path.join("folder.jpeg");
-    final_img.save_with_format(&cover_path, ImageFormat::Jpeg)
-        .map_err(|e| HvtError::Image(format!("Failed to save cover art: {}", e)))?;
+/// Moves the cached sample images of a work into `folder_path/scans/`. Returns the file names
+/// moved (none when nothing was cached).
+pub fn copy_sample_images_from_cache(rjcode: &str, folder_path: &Path, stats: &RunStats) -> Result<Vec<String>, HvtError> {
+    let cache_dir = sample_images_cache_dir(rjcode)?;
+    if !cache_dir.exists() {
+        return Ok(Vec::new());
+    }
 
-    debug!("Cover art saved to: {}", cover_path.display());
-    Ok(())
+    let scans_dir = folder_path.join(SCANS_DIR_NAME);
+    if !scans_dir.exists() {
+        std::fs::create_dir_all(&scans_dir)
+            .map_err(|_| HvtError::PathCreationFailed(scans_dir.display().to_string()))?;
+    }
+
+    let mut moved = Vec::new();
+    for entry in std::fs::read_dir(&cache_dir)?.filter_map(|e| e.ok()) {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        std::fs::copy(entry.path(), scans_dir.join(&file_name))
+            .map_err(|e| HvtError::Generic(format!("Failed to copy sample image from cache: {}", e)))?;
+        stats.add_file_written();
+        moved.push(file_name);
+    }
+
+    // Clean up cache after successful copy
+    let _ = std::fs::remove_dir_all(&cache_dir);
+
+    debug!("{} sample image(s) copied to: {}", moved.len(), scans_dir.display());
+    Ok(moved)
+}
+
+/// Width and height of the folder.jpeg in `folder_path`, `None` when missing or unreadable
+pub fn cover_dimensions(folder_path: &Path) -> Option<(u32, u32)> {
+    image::image_dimensions(folder_path.join("folder.jpeg")).ok()
+}
+
+/// Width and height of the cached cover of a work, `None` when nothing is cached
+pub fn cached_cover_dimensions(rjcode: &str) -> Option<(u32, u32)> {
+    image::image_dimensions(get_cache_dir().ok()?.join(format!("{}.jpeg", rjcode))).ok()
+}
+
+/// Deletes the cached cover of a work without applying it
+pub fn discard_cached_cover(rjcode: &str) {
+    if let Ok(cache_dir) = get_cache_dir() {
+        let _ = std::fs::remove_file(cache_dir.join(format!("{}.jpeg", rjcode)));
+    }
+}
+
+/// Deletes the cached sample images of a work without applying them
+pub fn discard_cached_sample_images(rjcode: &str) {
+    if let Ok(cache_dir) = sample_images_cache_dir(rjcode) {
+        let _ = std::fs::remove_dir_all(cache_dir);
+    }
 }
 
 /// Checks if folder.jpeg already exists in the given folder
@@ -174,4 +401,11 @@ mod tests {
         // This will return false if the folder doesn't exist or no folder.jpeg
         assert_eq!(has_cover_art(&path), false);
     }
+
+    #[test]
+    fn test_sample_image_file_name() {
+        assert_eq!(sample_image_file_name(3, "https://img.dlsite.jp/x/RJ01000000_img_smp3.PNG"), "03.png");
+        assert_eq!(sample_image_file_name(12, "https://img.dlsite.jp/x/RJ01000000_img_smp12.jpg"), "12.jpg");
+        assert_eq!(sample_image_file_name(1, "https://img.dlsite.jp/x/resize?id=1"), "01.jpg");
+    }
 }
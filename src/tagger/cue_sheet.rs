@@ -0,0 +1,234 @@
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::errors::HvtError;
+use crate::sanitize;
+use crate::tagger::converter;
+use crate::tagger::text_encoding::decode_text;
+use crate::tagger::types::{is_audio_extension, AudioFormat};
+
+/// One `TRACK` of a cue sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    /// Start of the track (`INDEX 01`), in seconds
+    pub start: f64,
+}
+
+/// A parsed cue sheet: the audio files it refers to (`FILE`) and its tracks, in order
+#[derive(Debug, Default, PartialEq)]
+pub struct CueSheet {
+    pub files: Vec<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+impl CueSheet {
+    /// (start, end) of each track in seconds; the last one ends at `duration` (`None`: unknown,
+    /// to the end of the file)
+    pub fn segments(&self, duration: Option<f64>) -> Vec<(f64, Option<f64>)> {
+        self.tracks.iter().enumerate()
+            .map(|(i, track)| (track.start, self.tracks.get(i + 1).map(|next| next.start).or(duration)))
+            .collect()
+    }
+
+    /// Unnamed tracks of `file_name` starting at 0 and at each of `split_points` (seconds), e.g. a
+    /// silence split
+    pub fn from_split_points(file_name: &str, split_points: &[f64]) -> Self {
+        let starts = std::iter::once(0.0).chain(split_points.iter().copied());
+        CueSheet {
+            files: vec![file_name.to_string()],
+            tracks: starts.enumerate()
+                .map(|(i, start)| CueTrack { number: i as u32 + 1, title: None, start })
+                .collect(),
+        }
+    }
+
+    /// File name of a split track: `01 - Title.mp3` (`01.mp3` without a title), so the track
+    /// parser finds the number and the title back
+    pub fn track_file_name(track: &CueTrack) -> String {
+        match &track.title {
+            Some(title) => format!("{:02} - {}.mp3", track.number, sanitize::folder_name(title)),
+            None => format!("{:02}.mp3", track.number),
+        }
+    }
+}
+
+/// Finds a cue sheet at the root of a work folder describing a single audio file that is still
+/// there, with at least two tracks. Returns (cue sheet path, audio file, sheet). A FLAC/WAV file
+/// converted since (`--convert`) is found under its `.mp3` name.
+pub fn find_cue_sheet(folder_path: &Path) -> Option<(PathBuf, PathBuf, CueSheet)> {
+    let mut cue_files: Vec<PathBuf> = std::fs::read_dir(folder_path).ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|path| path.is_file() && path.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("cue")))
+        .collect();
+    cue_files.sort();
+
+    cue_files.into_iter().find_map(|cue_path| {
+        let sheet = read_cue_sheet(&cue_path).ok()?;
+        let [file] = sheet.files.as_slice() else {
+            debug!("Cue sheet {} describes {} files, ignored", cue_path.display(), sheet.files.len());
+            return None;
+        };
+        if sheet.tracks.len() < 2 {
+            return None;
+        }
+        let audio_path = folder_path.join(file);
+        let audio_path = if audio_path.is_file() {
+            audio_path
+        } else if audio_path.with_extension("mp3").is_file() {
+            audio_path.with_extension("mp3")
+        } else {
+            debug!("Audio file of cue sheet {} not found (already split?)", cue_path.display());
+            return None;
+        };
+        Some((cue_path, audio_path, sheet))
+    })
+}
+
+/// Finds the single `.m4a`/`.m4b` audiobook at the root of a work folder, when it has at least
+/// two chapters, and turns them into a sheet like a cue sheet's (`files` holds its name). Needs
+/// ffmpeg to read the chapters.
+pub fn find_chaptered_audio(folder_path: &Path) -> Option<(PathBuf, CueSheet)> {
+    let audio_files: Vec<PathBuf> = std::fs::read_dir(folder_path).ok()?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|path| path.is_file() && path.extension().and_then(|e| e.to_str()).is_some_and(is_audio_extension))
+        .collect();
+    let [audio_path] = audio_files.as_slice() else {
+        return None;
+    };
+    let ext = audio_path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    if AudioFormat::from_extension(ext) != AudioFormat::M4a {
+        return None;
+    }
+
+    let chapters = match converter::read_chapters(audio_path) {
+        Ok(chapters) => chapters,
+        Err(e) => {
+            debug!("Could not read the chapters of {}: {}", audio_path.display(), e);
+            return None;
+        }
+    };
+    if chapters.len() < 2 {
+        return None;
+    }
+    let sheet = CueSheet {
+        files: vec![audio_path.file_name()?.to_string_lossy().to_string()],
+        tracks: chapters.into_iter().enumerate()
+            .map(|(i, (start, _end, title))| CueTrack { number: i as u32 + 1, title, start })
+            .collect(),
+    };
+    Some((audio_path.clone(), sheet))
+}
+
+/// Reads and parses a cue sheet (Shift-JIS cue sheets are common, see `decode_text`).
+pub fn read_cue_sheet(file_path: &Path) -> Result<CueSheet, HvtError> {
+    let bytes = std::fs::read(file_path)?;
+    Ok(parse_cue_sheet(&decode_text(&bytes)))
+}
+
+/// Parses the `FILE`, `TRACK`, `TITLE` and `INDEX 01` commands of a cue sheet; the rest
+/// (`PERFORMER`, `REM`, pregaps...) is ignored, and so are tracks without an `INDEX 01`.
+fn parse_cue_sheet(content: &str) -> CueSheet {
+    let mut sheet = CueSheet::default();
+    // (number, title, start) of the track being read
+    let mut current: Option<(u32, Option<String>, Option<f64>)> = None;
+
+    fn flush(current: &mut Option<(u32, Option<String>, Option<f64>)>, sheet: &mut CueSheet) {
+        if let Some((number, title, Some(start))) = current.take() {
+            sheet.tracks.push(CueTrack { number, title, start });
+        }
+    }
+
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command.to_uppercase().as_str() {
+            "FILE" => {
+                flush(&mut current, &mut sheet);
+                sheet.files.push(cue_file_name(rest));
+            }
+            "TRACK" => {
+                flush(&mut current, &mut sheet);
+                let number = rest.split_whitespace().next().and_then(|n| n.parse().ok());
+                current = number.map(|n| (n, None, None));
+            }
+            "TITLE" => {
+                if let Some((_, title, _)) = current.as_mut() {
+                    let value = unquote(rest);
+                    *title = (!value.is_empty()).then(|| value.to_string());
+                }
+            }
+            "INDEX" => {
+                let mut parts = rest.split_whitespace();
+                if let (Some("01"), Some(time), Some((_, _, start))) = (parts.next(), parts.next(), current.as_mut()) {
+                    *start = parse_cue_time(time);
+                }
+            }
+            _ => {}
+        }
+    }
+    flush(&mut current, &mut sheet);
+    sheet
+}
+
+/// `"name.flac" WAVE` or `name.flac WAVE` -> name.flac
+fn cue_file_name(rest: &str) -> String {
+    if let Some(quoted) = rest.strip_prefix('"') {
+        return quoted.split('"').next().unwrap_or("").to_string();
+    }
+    match rest.rsplit_once(char::is_whitespace) {
+        Some((name, _file_type)) => name.trim().to_string(),
+        None => rest.to_string(),
+    }
+}
+
+fn unquote(value: &str) -> &str {
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value).trim()
+}
+
+/// "03:12:37" (minutes, seconds, frames of 1/75 s) -> 192.49
+fn parse_cue_time(value: &str) -> Option<f64> {
+    let parts: Vec<u32> = value.split(':').map(|p| p.parse().ok()).collect::<Option<_>>()?;
+    let [minutes, seconds, frames] = parts.as_slice() else {
+        return None;
+    };
+    Some(*minutes as f64 * 60.0 + *seconds as f64 + *frames as f64 / 75.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cue_sheet() {
+        let content = "\u{feff}REM GENRE ASMR\n\
+            PERFORMER \"サークル\"\n\
+            TITLE \"作品\"\n\
+            FILE \"本編.flac\" WAVE\n\
+            \x20 TRACK 01 AUDIO\n\
+            \x20   TITLE \"導入\"\n\
+            \x20   INDEX 01 00:00:00\n\
+            \x20 TRACK 02 AUDIO\n\
+            \x20   TITLE \"耳かき\"\n\
+            \x20   INDEX 00 03:10:00\n\
+            \x20   INDEX 01 03:12:37\n\
+            \x20 TRACK 03 AUDIO\n\
+            \x20   INDEX 01 20:00:00\n";
+        let sheet = parse_cue_sheet(content);
+        assert_eq!(sheet.files, ["本編.flac"]);
+        assert_eq!(sheet.tracks.len(), 3);
+        assert_eq!(sheet.tracks[1].title.as_deref(), Some("耳かき"));
+        assert!((sheet.tracks[1].start - 192.4933).abs() < 0.001);
+        assert_eq!(sheet.tracks[2].title, None);
+        assert_eq!(sheet.segments(Some(1500.0))[2], (1200.0, Some(1500.0)));
+        assert_eq!(sheet.segments(None)[0].1, Some(sheet.tracks[1].start));
+        assert_eq!(CueSheet::track_file_name(&sheet.tracks[1]), "02 - 耳かき.mp3");
+        assert_eq!(CueSheet::track_file_name(&sheet.tracks[2]), "03.mp3");
+        assert_eq!(cue_file_name("track.mp3 MP3"), "track.mp3");
+    }
+}
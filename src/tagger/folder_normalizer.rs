@@ -1,8 +1,21 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::fs;
 use regex::Regex;
+use rusqlite::Connection;
 use tracing::{info, debug, warn};
+use crate::database::{normalization_log, queries};
 use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::tagger::text_encoding;
+use crate::tagger::types::{is_audio_extension, is_video_extension};
+
+/// Subfolder that gathers the non-audio files of a work with `collect_extras`
+pub const EXTRAS_DIR: &str = "extras";
+
+/// Subfolders of a work that hvtag manages itself (extras, `scans/` gallery): never flattened
+/// into `extras/`
+const MANAGED_DIRS: &[&str] = &[EXTRAS_DIR, "scans"];
 
 fn rjcode_regex() -> Regex {
     Regex::new(r"((?:RJ|VJ)\d{6,8})").unwrap()
@@ -12,12 +25,16 @@ fn rjcode_regex() -> Regex {
 ///
 /// For each subfolder:
 /// - If its name doesn't start with an RJ/VJ code, searches subdirectory names for one and renames
-/// - Moves all audio files from any subdirectory to the folder root (flatten)
-/// - Removes empty subdirectories
+/// - With `flatten`, moves all audio files from any subdirectory to the folder root (logged in
+///   `normalization_log`) and removes empty subdirectories; with `collect_extras` too, the other
+///   files of those subdirectories go to `extras/` (see `plan_folder_normalization`)
+///
+/// Folders listed in `excluded` (typically renames the user rejected in the preview, see
+/// `plan_source_renames`) are left completely untouched.
 ///
 /// This must run before `get_list_of_folders` so that the scanner finds correctly-named flat folders.
 /// Returns the number of folders that were renamed or had files moved.
-pub fn prepare_source_directory(source_path: &str) -> Result<usize, HvtError> {
+pub fn prepare_source_directory(conn: &Connection, source_path: &str, excluded: &[PathBuf], flatten: bool, collect_extras: bool) -> Result<usize, HvtError> {
     let mut count = 0;
 
     let entries = fs::read_dir(source_path)?;
@@ -26,7 +43,11 @@ pub fn prepare_source_directory(source_path: &str) -> Result<usize, HvtError> {
         if !path.is_dir() {
             continue;
         }
-        match prepare_for_import(&path) {
+        if excluded.contains(&path) {
+            debug!("Skipped (excluded by user): {}", path.display());
+            continue;
+        }
+        match prepare_for_import(conn, &path, flatten, collect_extras) {
             Ok(Some(_)) => count += 1,
             Ok(None) => debug!("Skipped (no RJCode found): {}", path.display()),
             Err(e) => warn!(
@@ -40,36 +61,88 @@ pub fn prepare_source_directory(source_path: &str) -> Result<usize, HvtError> {
     Ok(count)
 }
 
+/// Finds Shift-JIS file and folder names that were extracted as mojibake anywhere under
+/// `source_path` (see `text_encoding::repair_mojibake_name`). With `rename`, they are renamed to
+/// the decoded name (unless the target already exists); otherwise they are only reported.
+/// Returns the number of garbled names found.
+pub fn repair_mojibake_names(source_path: &str, rename: bool) -> Result<usize, HvtError> {
+    let mut count = 0;
+    repair_mojibake_names_in(Path::new(source_path), rename, &mut count)?;
+    Ok(count)
+}
+
+fn repair_mojibake_names_in(dir: &Path, rename: bool, count: &mut usize) -> Result<(), HvtError> {
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        // Children first, so renaming a folder doesn't invalidate the paths below it
+        if path.is_dir() {
+            repair_mojibake_names_in(&path, rename, count)?;
+        }
+
+        let Some(repaired) = text_encoding::repair_mojibake_name(&entry.file_name()) else {
+            continue;
+        };
+        *count += 1;
+
+        if !rename {
+            warn!("Garbled file name: '{}' (probably '{}')", path.display(), repaired);
+            continue;
+        }
+
+        let new_path = path.with_file_name(&repaired);
+        if new_path.exists() {
+            warn!("Cannot rename '{}' → '{}': target already exists", path.display(), repaired);
+            continue;
+        }
+        info!("Renaming garbled '{}' → '{}'", entry.file_name().to_string_lossy(), repaired);
+        fs::rename(&path, &new_path)?;
+    }
+    Ok(())
+}
+
+/// Dry run of the renaming part of `prepare_source_directory`: lists the (current, new) folder
+/// paths it would rename, without touching anything, so they can be previewed first. The target
+/// may already exist — `prepare_for_import` then skips that folder.
+pub fn plan_source_renames(source_path: &str) -> Result<Vec<(PathBuf, PathBuf)>, HvtError> {
+    let mut renames = Vec::new();
+
+    let entries = fs::read_dir(source_path)?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let folder_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if let Some(rjcode) = resolve_rjcode(&path) {
+            if folder_name != rjcode {
+                let new_path = path.with_file_name(&rjcode);
+                renames.push((path, new_path));
+            }
+        }
+    }
+
+    renames.sort();
+    Ok(renames)
+}
+
 /// Prepares a single source folder for import:
 /// 1. If the folder name doesn't start with an RJ/VJ code, searches subdirectory names for one
 ///    and renames the root folder accordingly
-/// 2. Moves all audio files from any subdirectory up to the folder root
+/// 2. With `flatten`, moves all audio files from any subdirectory up to the folder root (logged
+///    in `normalization_log`), and with `collect_extras` their other files into `extras/`
 /// 3. Removes now-empty subdirectories
 ///
 /// Returns the final folder path, or `None` if no RJCode could be found (folder is skipped).
-pub fn prepare_for_import(folder_path: &Path) -> Result<Option<PathBuf>, HvtError> {
+pub fn prepare_for_import(conn: &Connection, folder_path: &Path, flatten: bool, collect_extras: bool) -> Result<Option<PathBuf>, HvtError> {
     let folder_name = folder_path
         .file_name()
         .and_then(|n| n.to_str())
         .unwrap_or("");
 
     // --- Step 1: Resolve the canonical RJCode for this folder ---
-    let rjcode: String = if folder_name.starts_with("RJ") || folder_name.starts_with("VJ") {
-        // Root folder already has the right prefix.
-        // Extract just the bare code in case there's trailing text (e.g. "RJ01234567 - Title").
-        match rjcode_regex().find(folder_name) {
-            Some(m) => m.as_str().to_string(),
-            None => folder_name.to_string(), // Shouldn't happen given the starts_with check
-        }
-    } else {
-        // Root is a freeform title — look for the RJCode in subfolder names (max 5 levels deep)
-        match find_rjcode_in_subtree(folder_path, 5) {
-            Some(code) => code,
-            None => {
-                debug!("No RJCode found in subtree of '{}'", folder_name);
-                return Ok(None);
-            }
-        }
+    let Some(rjcode) = resolve_rjcode(folder_path) else {
+        debug!("No RJCode found in subtree of '{}'", folder_name);
+        return Ok(None);
     };
 
     // --- Step 2: Rename root folder to the bare RJCode if needed ---
@@ -95,48 +168,207 @@ pub fn prepare_for_import(folder_path: &Path) -> Result<Option<PathBuf>, HvtErro
     };
 
     // --- Step 3: Flatten audio files to root ---
-    normalize_folder_structure(&final_path)?;
+    if flatten {
+        let moves = normalize_folder_structure(&final_path, collect_extras)?;
+        normalization_log::record_moves(conn, &rjcode, &moves)?;
+    }
 
     Ok(Some(final_path))
 }
 
-/// Moves all audio files that are inside subdirectories up to `folder_path` root.
-/// Removes empty subdirectories afterwards.
-/// Returns the number of files moved (0 if already flat).
-pub fn normalize_folder_structure(folder_path: &Path) -> Result<usize, HvtError> {
+/// Dry run of `normalize_folder_structure`: the (current, new) paths of the audio files inside
+/// subdirectories of `folder_path`, with name collisions resolved as the moves would. Nothing is
+/// touched.
+///
+/// With `collect_extras`, the other files of those subdirectories (PDFs, images, scripts...) are
+/// planned too, into `extras/` under their subfolder path, so per-disc image folders stay apart
+/// (`CD1/scans/01.jpg` → `extras/CD1/scans/01.jpg`). Videos, root files and the `extras/` and
+/// `scans/` folders are left alone.
+pub fn plan_folder_normalization(folder_path: &Path, collect_extras: bool) -> Result<Vec<(PathBuf, PathBuf)>, HvtError> {
     let mut files_to_move: Vec<PathBuf> = Vec::new();
     collect_audio_in_subdirs(folder_path, folder_path, &mut files_to_move)?;
+    files_to_move.sort();
 
-    if files_to_move.is_empty() {
-        debug!("Already flat: {}", folder_path.display());
-        return Ok(0);
-    }
-
-    for source in &files_to_move {
+    let mut planned: HashSet<PathBuf> = HashSet::new();
+    let mut plan = Vec::with_capacity(files_to_move.len());
+    for source in files_to_move {
         let name = source
             .file_name()
-            .and_then(|n| n.to_str())
             .ok_or_else(|| HvtError::PathCreationFailed(source.display().to_string()))?;
+        let dest = resolve_filename_conflict(&folder_path.join(name), &planned)?;
+        planned.insert(dest.clone());
+        plan.push((source, dest));
+    }
+
+    if collect_extras {
+        let mut extras: Vec<PathBuf> = Vec::new();
+        collect_extras_in_subdirs(folder_path, folder_path, &mut extras)?;
+        extras.sort();
+        for source in extras {
+            let relative = source.strip_prefix(folder_path).unwrap_or(&source);
+            let dest = resolve_filename_conflict(&folder_path.join(EXTRAS_DIR).join(relative), &planned)?;
+            planned.insert(dest.clone());
+            plan.push((source, dest));
+        }
+    }
+    Ok(plan)
+}
 
-        let dest = resolve_filename_conflict(&folder_path.join(name))?;
+/// Moves all audio files that are inside subdirectories up to `folder_path` root, and with
+/// `collect_extras` the other files of those subdirectories into `extras/`.
+/// Removes empty subdirectories afterwards. Video files (bonus mp4s etc.) are left where they
+/// are, so their subdirectories are kept.
+/// Returns the moves made as (original, new) paths relative to `folder_path`, for
+/// `normalization_log` (empty if already flat).
+pub fn normalize_folder_structure(folder_path: &Path, collect_extras: bool) -> Result<Vec<(String, String)>, HvtError> {
+    let plan = plan_folder_normalization(folder_path, collect_extras)?;
+    if plan.is_empty() {
+        debug!("Already flat: {}", folder_path.display());
+        return Ok(Vec::new());
+    }
+
+    let mut moves = Vec::with_capacity(plan.len());
+    for (source, dest) in &plan {
         debug!(
             "Moving {} → {}",
             source.display(),
-            dest.file_name().unwrap().to_string_lossy()
+            relative_path(folder_path, dest)
         );
-        fs::rename(source, &dest)?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(source, dest)?;
+        moves.push((relative_path(folder_path, source), relative_path(folder_path, dest)));
     }
 
     cleanup_empty_subdirs(folder_path)?;
 
-    info!("Normalized: {} file(s) moved to root", files_to_move.len());
-    Ok(files_to_move.len())
+    let extras = moves.iter().filter(|(_, new_path)| Path::new(new_path).starts_with(EXTRAS_DIR)).count();
+    if extras > 0 {
+        info!("Normalized: {} file(s) moved to root, {} to {}/", moves.len() - extras, extras, EXTRAS_DIR);
+    } else {
+        info!("Normalized: {} file(s) moved to root", moves.len());
+    }
+    Ok(moves)
+}
+
+/// Puts files moved by `normalize_folder_structure` back where they were, from the
+/// `normalization_log` rows (log_id, original path, new path) of the work. A file converted to
+/// MP3 since is found under its `.mp3` name and restored as such. With `dry_run`, only logs what
+/// would be moved. Returns the log ids of the restored files.
+pub fn undo_folder_normalization(folder_path: &Path, moves: &[(i64, String, String)], dry_run: bool) -> Result<Vec<i64>, HvtError> {
+    let mut restored = Vec::new();
+    for (log_id, original_path, new_path) in moves {
+        let mut current = folder_path.join(new_path);
+        let mut target = folder_path.join(original_path);
+        if !current.exists() && current.with_extension("mp3").exists() {
+            current.set_extension("mp3");
+            target.set_extension("mp3");
+        }
+        if !current.exists() {
+            warn!("{} no longer exists, cannot restore it", current.display());
+            continue;
+        }
+        if target.exists() {
+            warn!("Cannot move {} back: {} already exists", current.display(), target.display());
+            continue;
+        }
+        info!("{}{} → {}", if dry_run { "Would move " } else { "" }, current.display(), target.display());
+        if !dry_run {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&current, &target)?;
+        }
+        restored.push(*log_id);
+    }
+    // An `extras/` emptied by the undo goes too
+    let extras = folder_path.join(EXTRAS_DIR);
+    if !dry_run && extras.is_dir() {
+        cleanup_empty_subdirs(&extras)?;
+        let _ = fs::remove_dir(&extras); // no-op if non-empty
+    }
+    Ok(restored)
+}
+
+/// `--normalize <rjcode>`: flattens a work's folder now (`collect_extras`: see
+/// `plan_folder_normalization`), logging the moves, or with `undo` puts
+/// back the files logged in `normalization_log`. With `dry_run`, only prints the moves. After an
+/// undo the work is marked for re-tagging; the next tagging flattens it again unless
+/// `normalize_folders` is off (`--no-normalize`).
+pub fn run_normalize_workflow(conn: &Connection, rjcode: &str, undo: bool, dry_run: bool, collect_extras: bool) -> Result<(), HvtError> {
+    let rjcode = RJCode::new(rjcode.to_uppercase())?;
+    let folder_path = queries::get_work_path(conn, &rjcode)?
+        .ok_or_else(|| HvtError::Generic(format!("{} not found in the database", rjcode)))?;
+    let folder = Path::new(&folder_path);
+    if !folder.is_dir() {
+        return Err(HvtError::Generic(format!("{} not found on disk", folder.display())));
+    }
+
+    if undo {
+        let moves = normalization_log::get_pending_moves(conn, rjcode.as_str())?;
+        if moves.is_empty() {
+            info!("{}: no normalization to undo", rjcode);
+            return Ok(());
+        }
+        let restored = undo_folder_normalization(folder, &moves, dry_run)?;
+        if dry_run {
+            info!("{}: {} of {} file(s) would be moved back (dry run)", rjcode, restored.len(), moves.len());
+            return Ok(());
+        }
+        for log_id in &restored {
+            normalization_log::mark_undone(conn, *log_id)?;
+        }
+        queries::mark_work_for_retagging(conn, &rjcode)?;
+        info!("{}: {} of {} file(s) moved back", rjcode, restored.len(), moves.len());
+        info!("The next tagging flattens it again unless normalize_folders is off or --no-normalize is given");
+        return Ok(());
+    }
+
+    if dry_run {
+        let plan = plan_folder_normalization(folder, collect_extras)?;
+        for (source, dest) in &plan {
+            info!("Would move {} → {}", source.display(), dest.display());
+        }
+        info!("{}: {} file(s) would be moved to the root (dry run)", rjcode, plan.len());
+        return Ok(());
+    }
+    let moves = normalize_folder_structure(folder, collect_extras)?;
+    normalization_log::record_moves(conn, rjcode.as_str(), &moves)?;
+    if moves.is_empty() {
+        info!("{}: already flat", rjcode);
+    }
+    Ok(())
+}
+
+fn relative_path(folder_path: &Path, path: &Path) -> String {
+    path.strip_prefix(folder_path).unwrap_or(path).to_string_lossy().to_string()
 }
 
 // ---------------------------------------------------------------------------
 // Internal helpers
 // ---------------------------------------------------------------------------
 
+/// Resolves the canonical RJCode of a source folder: taken from its own name if it already starts
+/// with RJ/VJ (stripping any trailing text, e.g. "RJ01234567 - Title"), otherwise searched for in
+/// subfolder names (max 5 levels deep).
+fn resolve_rjcode(folder_path: &Path) -> Option<String> {
+    let folder_name = folder_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("");
+
+    if folder_name.starts_with("RJ") || folder_name.starts_with("VJ") {
+        match rjcode_regex().find(folder_name) {
+            Some(m) => Some(m.as_str().to_string()),
+            None => Some(folder_name.to_string()), // Shouldn't happen given the starts_with check
+        }
+    } else {
+        // Root is a freeform title — look for the RJCode in subfolder names
+        find_rjcode_in_subtree(folder_path, 5)
+    }
+}
+
 /// Walks `current` recursively and appends audio files that are NOT directly
 /// under `root` (i.e. files that need to be moved up).
 fn collect_audio_in_subdirs(
@@ -151,7 +383,7 @@ fn collect_audio_in_subdirs(
             collect_audio_in_subdirs(&path, root, out)?;
         } else if path.is_file() && path.parent() != Some(root) {
             if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                if matches!(ext.to_lowercase().as_str(), "mp3" | "flac" | "wav" | "ogg") {
+                if is_audio_extension(ext) {
                     out.push(path);
                 }
             }
@@ -160,6 +392,33 @@ fn collect_audio_in_subdirs(
     Ok(())
 }
 
+/// Walks `current` recursively and appends the files that are neither audio nor video, NOT
+/// directly under `root` and outside its `MANAGED_DIRS` (what `collect_extras` moves to
+/// `extras/`).
+fn collect_extras_in_subdirs(
+    current: &Path,
+    root: &Path,
+    out: &mut Vec<PathBuf>,
+) -> Result<(), HvtError> {
+    let entries = fs::read_dir(current)?;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            let managed = current == root
+                && path.file_name().and_then(|n| n.to_str()).is_some_and(|n| MANAGED_DIRS.contains(&n));
+            if !managed {
+                collect_extras_in_subdirs(&path, root, out)?;
+            }
+        } else if path.is_file() && path.parent() != Some(root) {
+            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+            if !is_audio_extension(&ext) && !is_video_extension(&ext) {
+                out.push(path);
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Searches directory names up to `max_depth` levels deep for an RJ/VJ code.
 /// Returns the first code found (breadth-first within each level).
 fn find_rjcode_in_subtree(path: &Path, max_depth: u32) -> Option<String> {
@@ -198,9 +457,10 @@ fn find_rjcode_in_subtree(path: &Path, max_depth: u32) -> Option<String> {
     None
 }
 
-/// Appends a numeric suffix to resolve a filename collision (e.g. `track_1.mp3`).
-fn resolve_filename_conflict(path: &Path) -> Result<PathBuf, HvtError> {
-    if !path.exists() {
+/// Appends a numeric suffix to resolve a filename collision (e.g. `track_1.mp3`) with an existing
+/// file or one of the `planned` moves.
+fn resolve_filename_conflict(path: &Path, planned: &HashSet<PathBuf>) -> Result<PathBuf, HvtError> {
+    if !path.exists() && !planned.contains(path) {
         return Ok(path.to_path_buf());
     }
 
@@ -220,7 +480,7 @@ fn resolve_filename_conflict(path: &Path) -> Result<PathBuf, HvtError> {
             format!("{}_{}.{}", stem, i, ext)
         };
         let candidate_path = parent.join(candidate);
-        if !candidate_path.exists() {
+        if !candidate_path.exists() && !planned.contains(&candidate_path) {
             return Ok(candidate_path);
         }
     }
@@ -243,3 +503,34 @@ fn cleanup_empty_subdirs(folder_path: &Path) -> Result<(), HvtError> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_collects_extras_and_undoes() {
+        let root = std::env::temp_dir().join(format!("hvtag-test-normalize-{}", std::process::id()));
+        let files = ["CD1/01.mp3", "CD1/scans/01.jpg", "CD2/01.mp3", "CD2/script.pdf", "CD2/bonus.mp4", "scans/02.jpg"];
+        for file in files {
+            fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+            fs::write(root.join(file), file).unwrap();
+        }
+
+        let moves = normalize_folder_structure(&root, true).unwrap();
+        let exists = |path: &str| root.join(path).is_file();
+        let all_moved = ["01.mp3", "01_1.mp3", "extras/CD1/scans/01.jpg", "extras/CD2/script.pdf", "CD2/bonus.mp4", "scans/02.jpg"]
+            .iter()
+            .all(|path| exists(path));
+        let log: Vec<(i64, String, String)> = moves.into_iter().rev().enumerate()
+            .map(|(i, (original, new))| (i as i64, original, new))
+            .collect();
+        let restored = undo_folder_normalization(&root, &log, false).unwrap();
+        let all_back = files.iter().all(|path| exists(path)) && !root.join(EXTRAS_DIR).exists();
+        let _ = fs::remove_dir_all(&root);
+
+        assert!(all_moved);
+        assert_eq!(restored.len(), 4);
+        assert!(all_back);
+    }
+}
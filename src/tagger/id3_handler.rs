@@ -1,11 +1,46 @@
 use std::path::Path;
 use id3::TagLike;
+use crate::config::{ArtistFrameStyle, Id3Version};
 use crate::errors::HvtError;
-use crate::tagger::types::AudioMetadata;
+use crate::tagger::types::{AudioMetadata, TaggerConfig};
+
+/// TXXX description of the multi-value artist list (`ArtistFrameStyle::TxxxArtists`)
+const ARTISTS_TXXX: &str = "ARTISTS";
+/// TXXX description of the mood in ID3v2.3, which has no TMOO frame
+const MOOD_TXXX: &str = "MOOD";
+
+/// Email of the POPM rating frame, the one most players read (and write) ratings with
+const POPM_USER: &str = "Windows Media Player 9 Series";
+
+/// POPM rating (1-255, 0 is unknown) of a DLSite star rating (0-5)
+fn popm_rating(stars: f32) -> u8 {
+    (stars.clamp(0.0, 5.0) / 5.0 * 255.0).round().max(1.0) as u8
+}
+
+/// DLSite stars of a POPM rating, to one decimal
+fn stars_from_popm(rating: u8) -> f32 {
+    (f32::from(rating) / 255.0 * 50.0).round() / 10.0
+}
+
+/// Text of the TPE1 frame and, for `TxxxArtists`, of the TXXX:ARTISTS frame.
+fn artist_frames(artists: &[String], separator: &str, style: ArtistFrameStyle) -> (String, Option<String>) {
+    match style {
+        ArtistFrameStyle::Joined => (artists.join(separator), None),
+        ArtistFrameStyle::MultiValue => (artists.join("\0"), None),
+        ArtistFrameStyle::TxxxArtists => (artists.join(separator), Some(artists.join("\0"))),
+    }
+}
 
 /// Writes ID3v2 tags to an MP3 file
 /// Note: Cover art is NOT embedded - it's saved separately as folder.jpeg
-pub fn write_id3_tags(file_path: &Path, metadata: &AudioMetadata, separator: &str) -> Result<(), HvtError> {
+pub fn write_id3_tags(file_path: &Path, metadata: &AudioMetadata, config: &TaggerConfig) -> Result<(), HvtError> {
+    let separator = config.tag_separator.as_str();
+    // ID3v2.3 has no multi-value text frames: fall back to the joined artist string
+    let artist_style = match (config.id3_version, config.artist_frame_style) {
+        (Id3Version::V23, ArtistFrameStyle::MultiValue) => ArtistFrameStyle::Joined,
+        (_, style) => style,
+    };
+
     let mut tag = match id3::Tag::read_from_path(file_path) {
         Ok(t) => t,
         Err(_) => id3::Tag::new(),
@@ -16,10 +51,18 @@ pub fn write_id3_tags(file_path: &Path, metadata: &AudioMetadata, separator: &st
     tag.set_album(&metadata.album);
     tag.set_album_artist(&metadata.album_artist);
 
-    // Set artists (voice actors) - multiple artists separated by configured separator
+    // Set artists (voice actors) - joined with the configured separator or as multiple values.
+    // A TXXX:ARTISTS left by a previous style is dropped so players don't see stale values.
+    tag.remove_extended_text(Some(ARTISTS_TXXX), None);
     if !metadata.artists.is_empty() {
-        let artists_string = metadata.artists.join(separator);
+        let (artists_string, artists_txxx) = artist_frames(&metadata.artists, separator, artist_style);
         tag.set_artist(&artists_string);
+        if let Some(value) = artists_txxx {
+            tag.add_frame(id3::frame::ExtendedText {
+                description: ARTISTS_TXXX.to_string(),
+                value,
+            });
+        }
     }
 
     // Set track number if available
@@ -27,12 +70,62 @@ pub fn write_id3_tags(file_path: &Path, metadata: &AudioMetadata, separator: &st
         tag.set_track(track);
     }
 
-    // Set date if available
-    // Note: id3 crate's set_date_released expects specific format
-    // For now, we skip this if the date string doesn't match expected format
-    if let Some(_date) = &metadata.date {
-        // TODO: Parse date string into id3::Timestamp format
-        // Skipping for now as it requires specific date format parsing
+    // Set disc number, for works split across folders (work groups)
+    if let Some((disc, total)) = metadata.disc {
+        tag.set_disc(disc);
+        tag.set_total_discs(total);
+    }
+
+    // Set release date: TDRC is the ID3v2.4 date frame, TYER is kept for players that only read
+    // the v2.3 year. v2.3 has no TDRC: the day and month go into TDAT (DDMM) instead.
+    if let Some(timestamp) = metadata.date.as_deref().and_then(parse_release_date) {
+        tag.set_year(timestamp.year);
+        match config.id3_version {
+            Id3Version::V24 => {
+                tag.remove("TDAT");
+                tag.set_date_recorded(timestamp);
+            }
+            Id3Version::V23 => {
+                tag.remove_date_recorded();
+                match (timestamp.month, timestamp.day) {
+                    (Some(month), Some(day)) => tag.set_text("TDAT", format!("{:02}{:02}", day, month)),
+                    _ => {
+                        tag.remove("TDAT");
+                    }
+                }
+            }
+        }
+    }
+
+    // Set grouping (series), so players can group the parts/translations of a work
+    if let Some(grouping) = &metadata.grouping {
+        tag.set_text("TIT1", grouping);
+    }
+
+    // Set comment (work description)
+    if let Some(comment) = &metadata.comment {
+        tag.add_frame(id3::frame::Comment {
+            lang: "jpn".to_string(),
+            description: String::new(),
+            text: comment.clone(),
+        });
+    }
+
+    // Set source identifiers, so files can be traced back to their work after renaming
+    for (description, value) in &metadata.source_frames {
+        tag.add_frame(id3::frame::ExtendedText {
+            description: description.clone(),
+            value: value.clone(),
+        });
+    }
+
+    // Set rating (DLSite stars), keeping the POPM frames other players wrote under their own email
+    if let Some(stars) = metadata.rating {
+        tag.add_frame(id3::frame::Popularimeter {
+            user: POPM_USER.to_string(),
+            rating: popm_rating(stars),
+            counter: 0,
+        });
     }
 
     // Set genre (concatenate all genres with configured separator)
@@ -41,10 +134,179 @@ pub fn write_id3_tags(file_path: &Path, metadata: &AudioMetadata, separator: &st
         tag.set_genre(&genre_string);
     }
 
+    // Set the tags of categorized frames (mood, TXXX per category), clearing the ones of tags
+    // that moved elsewhere. ID3v2.3 has no TMOO: the mood goes into TXXX:MOOD.
+    tag.remove("TMOO");
+    tag.remove_extended_text(Some(MOOD_TXXX), None);
+    for (description, values) in &metadata.category_frames {
+        tag.remove_extended_text(Some(description), None);
+        if !values.is_empty() {
+            tag.add_frame(id3::frame::ExtendedText { description: description.clone(), value: values.join(separator) });
+        }
+    }
+    if !metadata.mood.is_empty() {
+        let mood_string = metadata.mood.join(separator);
+        match config.id3_version {
+            Id3Version::V24 => tag.set_text("TMOO", mood_string),
+            Id3Version::V23 => {
+                tag.add_frame(id3::frame::ExtendedText { description: MOOD_TXXX.to_string(), value: mood_string });
+            }
+        }
+    }
+
     // Write tags to file
-    tag.write_to_path(file_path, id3::Version::Id3v24)
+    let version = match config.id3_version {
+        Id3Version::V24 => id3::Version::Id3v24,
+        Id3Version::V23 => id3::Version::Id3v23,
+    };
+    tag.write_to_path(file_path, version)
         .map_err(|e| HvtError::AudioTag(format!("Failed to write ID3 tags: {}", e)))?;
 
+    // Drop a stale ID3v1 tag at the end of the file, which some players read instead of ID3v2
+    if config.strip_id3v1 {
+        id3::v1::Tag::remove_from_path(file_path)
+            .map_err(|e| HvtError::AudioTag(format!("Failed to remove ID3v1 tag: {}", e)))?;
+    }
+
+    Ok(())
+}
+
+/// Writes the ReplayGain frames (TXXX:REPLAYGAIN_TRACK_GAIN, _TRACK_PEAK, _ALBUM_GAIN,
+/// _ALBUM_PEAK), keeping the rest of the tag. Gains are in dB, peaks linear.
+pub fn write_replaygain_tags(
+    file_path: &Path,
+    track: (f64, f64),
+    album: (f64, f64),
+    config: &TaggerConfig,
+) -> Result<(), HvtError> {
+    let mut tag = match id3::Tag::read_from_path(file_path) {
+        Ok(t) => t,
+        Err(_) => id3::Tag::new(),
+    };
+    let frames = [
+        ("REPLAYGAIN_TRACK_GAIN", format!("{:.2} dB", track.0)),
+        ("REPLAYGAIN_TRACK_PEAK", format!("{:.6}", track.1)),
+        ("REPLAYGAIN_ALBUM_GAIN", format!("{:.2} dB", album.0)),
+        ("REPLAYGAIN_ALBUM_PEAK", format!("{:.6}", album.1)),
+    ];
+    for (description, value) in frames {
+        tag.add_frame(id3::frame::ExtendedText { description: description.to_string(), value });
+    }
+
+    let version = match config.id3_version {
+        Id3Version::V24 => id3::Version::Id3v24,
+        Id3Version::V23 => id3::Version::Id3v23,
+    };
+    tag.write_to_path(file_path, version)
+        .map_err(|e| HvtError::AudioTag(format!("Failed to write ReplayGain tags: {}", e)))?;
+    Ok(())
+}
+
+/// Writes ID3 chapters (`CHAP` frames with a `TIT2` title, listed in a top-level `CTOC`) from
+/// (start ms, end ms, title) entries, e.g. the tracks of a cue sheet describing a single-file
+/// work. Chapters already in the tag are replaced; the rest of the tag is kept.
+pub fn write_chapters(
+    file_path: &Path,
+    chapters: &[(u32, u32, String)],
+    config: &TaggerConfig,
+) -> Result<(), HvtError> {
+    let mut tag = match id3::Tag::read_from_path(file_path) {
+        Ok(t) => t,
+        Err(_) => id3::Tag::new(),
+    };
+    tag.remove_all_chapters();
+    tag.remove_all_tables_of_contents();
+
+    let mut elements = Vec::with_capacity(chapters.len());
+    for (i, (start_time, end_time, title)) in chapters.iter().enumerate() {
+        let element_id = format!("chp{}", i + 1);
+        tag.add_frame(id3::frame::Chapter {
+            element_id: element_id.clone(),
+            start_time: *start_time,
+            end_time: *end_time,
+            start_offset: u32::MAX,
+            end_offset: u32::MAX,
+            frames: vec![id3::Frame::text("TIT2", title.as_str())],
+        });
+        elements.push(element_id);
+    }
+    tag.add_frame(id3::frame::TableOfContents {
+        element_id: "toc".to_string(),
+        top_level: true,
+        ordered: true,
+        elements,
+        frames: Vec::new(),
+    });
+
+    let version = match config.id3_version {
+        Id3Version::V24 => id3::Version::Id3v24,
+        Id3Version::V23 => id3::Version::Id3v23,
+    };
+    tag.write_to_path(file_path, version)
+        .map_err(|e| HvtError::AudioTag(format!("Failed to write chapters: {}", e)))?;
+    Ok(())
+}
+
+/// Parses a DLSite date (`regist_date`, e.g. "2023-05-12 16:00:00") into a date-only timestamp.
+/// Partial dates fall back to the year (or year and month); `None` without a plausible year.
+pub fn parse_release_date(date: &str) -> Option<id3::Timestamp> {
+    let mut parts = date
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|p| !p.is_empty());
+
+    let year_str = parts.next()?;
+    if year_str.len() != 4 {
+        return None;
+    }
+    let year: i32 = year_str.parse().ok()?;
+    let month = parts.next().and_then(|m| m.parse::<u8>().ok()).filter(|m| (1..=12).contains(m));
+    let day = month
+        .and(parts.next())
+        .and_then(|d| d.parse::<u8>().ok())
+        .filter(|d| (1..=31).contains(d));
+
+    Some(id3::Timestamp { year, month, day, hour: None, minute: None, second: None })
+}
+
+/// Serializes the file's current ID3v2 tag, pictures excluded, for a tag snapshot.
+/// `None` when the file has no tag.
+pub fn read_raw_tag(file_path: &Path) -> Result<Option<Vec<u8>>, HvtError> {
+    let mut tag = match id3::Tag::read_from_path(file_path) {
+        Ok(t) => t,
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => return Ok(None),
+        Err(e) => return Err(HvtError::AudioTag(format!("Failed to read ID3 tags: {}", e))),
+    };
+    tag.remove_all_pictures();
+
+    let mut raw = Vec::new();
+    tag.write_to(&mut raw, tag.version())
+        .map_err(|e| HvtError::AudioTag(format!("Failed to serialize ID3 tags: {}", e)))?;
+    Ok(Some(raw))
+}
+
+/// Puts back a tag snapshot from `read_raw_tag`, keeping the pictures currently in the file.
+/// `None` removes the ID3v2 tag (the file had none).
+pub fn restore_raw_tag(file_path: &Path, raw_tag: Option<&[u8]>) -> Result<(), HvtError> {
+    let Some(raw_tag) = raw_tag else {
+        return remove_tags(file_path);
+    };
+
+    let mut tag = id3::Tag::read_from2(std::io::Cursor::new(raw_tag))
+        .map_err(|e| HvtError::AudioTag(format!("Failed to parse tag snapshot: {}", e)))?;
+    if let Ok(current) = id3::Tag::read_from_path(file_path) {
+        for picture in current.pictures() {
+            tag.add_frame(picture.clone());
+        }
+    }
+    tag.write_to_path(file_path, tag.version())
+        .map_err(|e| HvtError::AudioTag(format!("Failed to write ID3 tags: {}", e)))?;
+    Ok(())
+}
+
+/// Removes the ID3v2 tag of a file.
+pub fn remove_tags(file_path: &Path) -> Result<(), HvtError> {
+    id3::Tag::remove_from_path(file_path)
+        .map_err(|e| HvtError::AudioTag(format!("Failed to remove ID3 tags: {}", e)))?;
     Ok(())
 }
 
@@ -67,7 +329,11 @@ pub fn read_id3_tags(file_path: &Path, separator: &str) -> Result<Option<AudioMe
     // this used to be hardcoded to ';', which silently misparsed multi-artist tags
     // whenever a non-default separator was configured).
     let artists_str = tag.artist().unwrap_or("");
-    let artists: Vec<String> = if !artists_str.is_empty() {
+    // ID3v2.4 multi-value frames (`ArtistFrameStyle::MultiValue`) are null-separated whatever the
+    // configured separator.
+    let artists: Vec<String> = if artists_str.contains('\0') {
+        artists_str.split('\0').map(|s| s.trim().to_string()).collect()
+    } else if !artists_str.is_empty() {
         artists_str.split(separator).map(|s| s.trim().to_string()).collect()
     } else {
         Vec::new()
@@ -79,9 +345,57 @@ pub fn read_id3_tags(file_path: &Path, separator: &str) -> Result<Option<AudioMe
         album: tag.album().unwrap_or("").to_string(),
         album_artist: tag.album_artist().unwrap_or("").to_string(),
         track_number: tag.track(),
+        disc: tag.disc().zip(tag.total_discs()),
         genre: genres,
-        date: tag.date_released().map(|d| d.to_string()),
+        mood: tag.get("TMOO").and_then(|f| f.content().text())
+            .map(|s| s.split(separator).map(str::to_string).collect())
+            .unwrap_or_default(),
+        category_frames: Vec::new(),
+        date: tag.date_recorded().map(|d| d.to_string()),
+        grouping: tag.get("TIT1").and_then(|f| f.content().text()).map(|s| s.to_string()),
+        comment: tag.comments().next().map(|c| c.text.clone()),
+        source_frames: tag.extended_texts()
+            .map(|t| (t.description.clone(), t.value.clone()))
+            .collect(),
+        rating: tag.frames()
+            .filter_map(|f| f.content().popularimeter())
+            .find(|p| p.user == POPM_USER)
+            .map(|p| stars_from_popm(p.rating)),
     };
 
     Ok(Some(metadata))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_popm_rating() {
+        assert_eq!(popm_rating(5.0), 255);
+        assert_eq!(popm_rating(4.5), 230);
+        assert_eq!(popm_rating(0.0), 1);
+        assert_eq!(stars_from_popm(popm_rating(4.5)), 4.5);
+        assert_eq!(stars_from_popm(popm_rating(3.7)), 3.7);
+    }
+
+    #[test]
+    fn test_parse_release_date() {
+        assert_eq!(parse_release_date("2023-05-12 16:00:00").map(|t| t.to_string()), Some("2023-05-12".to_string()));
+        assert_eq!(parse_release_date("2023年5月").map(|t| t.to_string()), Some("2023-05".to_string()));
+        assert_eq!(parse_release_date("2023-13-01").map(|t| t.to_string()), Some("2023".to_string()));
+        assert_eq!(parse_release_date(""), None);
+        assert_eq!(parse_release_date("23-05-12"), None);
+    }
+
+    #[test]
+    fn test_artist_frames() {
+        let artists = vec!["A".to_string(), "B".to_string()];
+        assert_eq!(artist_frames(&artists, "; ", ArtistFrameStyle::Joined), ("A; B".to_string(), None));
+        assert_eq!(artist_frames(&artists, "; ", ArtistFrameStyle::MultiValue), ("A\0B".to_string(), None));
+        assert_eq!(
+            artist_frames(&artists, "; ", ArtistFrameStyle::TxxxArtists),
+            ("A; B".to_string(), Some("A\0B".to_string()))
+        );
+    }
+}
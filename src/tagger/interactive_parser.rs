@@ -1,7 +1,8 @@
 use dialoguer::{Select, Input, theme::ColorfulTheme};
+use std::path::Path;
 use regex::Regex;
 use crate::errors::HvtError;
-use crate::tagger::track_parser::{TrackParsingPreference, parse_track_number_with_preference, find_duplicate_track_numbers};
+use crate::tagger::track_parser::{TrackParsingPreference, parse_track_number_with_preference, find_duplicate_track_numbers, validate_track_regex};
 
 /// Result of a completed interactive parsing session.
 pub enum ParsingResult {
@@ -12,6 +13,8 @@ pub enum ParsingResult {
     Manual(Vec<Option<u32>>),
     /// User chose to skip — files will be tagged without track numbers.
     Skip,
+    /// Split the single audio file of the work where it goes silent (`converter::detect_silences`).
+    SplitBySilence,
 }
 
 /// Runs the full interactive track-parsing session.
@@ -22,6 +25,7 @@ pub enum ParsingResult {
 pub fn run_interactive_parsing(
     filenames: &[String],
     rjcode: &str,
+    offer_silence_split: bool,
 ) -> Result<ParsingResult, HvtError> {
     println!("\n=== Track Number Parsing ===");
     println!("Work: {}", rjcode);
@@ -35,9 +39,11 @@ pub fn run_interactive_parsing(
     println!("\nAutomatic track number detection failed. Please choose a strategy.\n");
 
     loop {
-        match pick_strategy()? {
+        match pick_strategy(offer_silence_split)? {
             StrategyChoice::Skip => return Ok(ParsingResult::Skip),
 
+            StrategyChoice::SplitBySilence => return Ok(ParsingResult::SplitBySilence),
+
             StrategyChoice::Manual => {
                 let numbers = collect_manual_numbers(filenames)?;
                 return Ok(ParsingResult::Manual(numbers));
@@ -63,24 +69,30 @@ enum StrategyChoice {
     Preference(TrackParsingPreference),
     Manual,
     Skip,
+    SplitBySilence,
 }
 
 // ---------------------------------------------------------------------------
 // Internal helpers
 // ---------------------------------------------------------------------------
 
-/// Shows the strategy selection menu and returns the user's choice.
-fn pick_strategy() -> Result<StrategyChoice, HvtError> {
-    let options = vec![
+/// Shows the strategy selection menu and returns the user's choice. With `offer_silence_split`
+/// (a work with a single audio file), splitting it by silence is offered too.
+fn pick_strategy(offer_silence_split: bool) -> Result<StrategyChoice, HvtError> {
+    let mut options = vec![
         "Asian full-width numbers  (０１２ → 012)",
         "Asian brackets            【01】 ［01］ 〔01〕 （01）",
         "Kanji episode markers     第01話  第01章  第01回",
         "Custom delimiter          (number followed by a pattern)",
         "Strip prefix then first number  (regex, e.g. s.*?_ strips s19_ from s19_01_track)",
+        "Custom regex              (one capture group for the track number, e.g. _t(\\d+)$)",
         "First number in filename  (fallback)",
         "Manual numbering          (enter each track number by hand)",
         "Skip this folder          (no track numbers)",
     ];
+    if offer_silence_split {
+        options.push("Split by silence          (one track per pause in the single audio file)");
+    }
 
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Parsing strategy")
@@ -96,6 +108,7 @@ fn pick_strategy() -> Result<StrategyChoice, HvtError> {
             use_asian_conversion: true,
             asian_format_type: Some("fullwidth".to_string()),
             strip_prefix_pattern: None,
+            custom_regex: None,
         })),
         1 => Ok(StrategyChoice::Preference(TrackParsingPreference {
             strategy_name: "asian_brackets".to_string(),
@@ -103,6 +116,7 @@ fn pick_strategy() -> Result<StrategyChoice, HvtError> {
             use_asian_conversion: true,
             asian_format_type: Some("asian_brackets".to_string()),
             strip_prefix_pattern: None,
+            custom_regex: None,
         })),
         2 => Ok(StrategyChoice::Preference(TrackParsingPreference {
             strategy_name: "asian_kanji_episode".to_string(),
@@ -110,6 +124,7 @@ fn pick_strategy() -> Result<StrategyChoice, HvtError> {
             use_asian_conversion: true,
             asian_format_type: Some("kanji_episode".to_string()),
             strip_prefix_pattern: None,
+            custom_regex: None,
         })),
         3 => {
             let delimiter: String = Input::with_theme(&ColorfulTheme::default())
@@ -122,6 +137,7 @@ fn pick_strategy() -> Result<StrategyChoice, HvtError> {
                 use_asian_conversion: false,
                 asian_format_type: None,
                 strip_prefix_pattern: None,
+                custom_regex: None,
             }))
         }
         4 => {
@@ -145,6 +161,7 @@ fn pick_strategy() -> Result<StrategyChoice, HvtError> {
                     use_asian_conversion: false,
                     asian_format_type: None,
                     strip_prefix_pattern: Some(pattern),
+                    custom_regex: None,
                 })),
                 Err(e) => {
                     println!("Invalid regex: {}. Falling back to first-number strategy.", e);
@@ -154,23 +171,68 @@ fn pick_strategy() -> Result<StrategyChoice, HvtError> {
                         use_asian_conversion: false,
                         asian_format_type: None,
                         strip_prefix_pattern: None,
+                        custom_regex: None,
                     }))
                 }
             }
         }
-        5 => Ok(StrategyChoice::Preference(TrackParsingPreference {
+        5 => {
+            println!("\nRegex matched against the filename (without extension); its capture group");
+            println!("is the track number.");
+            println!("Examples:");
+            println!("  _t(\\d+)$        '07' from 's19_t07'");
+            println!("  ^\\D*(\\d+)       first number after a non-numeric prefix");
+            println!("  Part(\\d+)-      '3' from 'Part3-track'");
+            println!();
+            let pattern: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt("Regex with one capture group")
+                .validate_with(|input: &String| validate_track_regex(input))
+                .interact_text()
+                .map_err(|e| HvtError::Parse(format!("Input error: {}", e)))?;
+            Ok(StrategyChoice::Preference(TrackParsingPreference {
+                strategy_name: "custom_regex".to_string(),
+                custom_delimiter: None,
+                use_asian_conversion: false,
+                asian_format_type: None,
+                strip_prefix_pattern: None,
+                custom_regex: Some(pattern),
+            }))
+        }
+        6 => Ok(StrategyChoice::Preference(TrackParsingPreference {
             strategy_name: "first_number".to_string(),
             custom_delimiter: None,
             use_asian_conversion: false,
             asian_format_type: None,
             strip_prefix_pattern: None,
+            custom_regex: None,
         })),
-        6 => Ok(StrategyChoice::Manual),
-        7 => Ok(StrategyChoice::Skip),
+        7 => Ok(StrategyChoice::Manual),
+        8 => Ok(StrategyChoice::Skip),
+        9 => Ok(StrategyChoice::SplitBySilence),
         _ => unreachable!(),
     }
 }
 
+/// Shows the tracks a silence split would produce, (start, end) in seconds, and asks whether to
+/// split the file.
+pub fn confirm_silence_split(file_name: &str, segments: &[(f64, Option<f64>)]) -> Result<bool, HvtError> {
+    let clock = |seconds: f64| format!("{}:{:02}", seconds as u64 / 60, seconds as u64 % 60);
+    println!("\n=== Split {} by silence: {} track(s) ===", file_name, segments.len());
+    for (i, (start, end)) in segments.iter().enumerate().take(30) {
+        let end = end.map(clock).unwrap_or_else(|| "end".to_string());
+        println!("  {:02}. {} → {}", i + 1, clock(*start), end);
+    }
+    if segments.len() > 30 {
+        println!("  ... and {} more", segments.len() - 30);
+    }
+
+    dialoguer::Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt("Split the file into these tracks?")
+        .default(true)
+        .interact()
+        .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))
+}
+
 /// Prompts the user to enter a track number for each file.
 /// Pressing Enter without a value assigns no track number for that file.
 fn collect_manual_numbers(filenames: &[String]) -> Result<Vec<Option<u32>>, HvtError> {
@@ -251,3 +313,34 @@ fn confirm_strategy(filenames: &[String], track_numbers: &[Option<u32>]) -> Resu
         .interact()
         .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))
 }
+
+/// Shows which title the bundled tracklist file would give each file and asks the user to
+/// confirm. Files whose track number isn't in the tracklist keep their filename-derived title.
+pub fn confirm_tracklist_titles(
+    rjcode: &str,
+    tracklist_path: &Path,
+    previews: &[(String, Option<u32>, Option<String>)],
+) -> Result<bool, HvtError> {
+    println!("\n=== Tracklist found for {} ===", rjcode);
+    println!("File: {}\n", tracklist_path.display());
+
+    for (filename, track, title) in previews.iter().take(15) {
+        let track = track.map(|n| format!("{:>3}", n)).unwrap_or_else(|| " ??".to_string());
+        match title {
+            Some(title) => println!("  [{}] {} → {}", track, filename, title),
+            None        => println!("  [{}] {} (no tracklist entry, keeping filename title)", track, filename),
+        }
+    }
+    if previews.len() > 15 {
+        println!("  ... and {} more", previews.len() - 15);
+    }
+
+    let matched = previews.iter().filter(|(_, _, title)| title.is_some()).count();
+    println!("\nMatched: {}/{}", matched, previews.len());
+
+    dialoguer::Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt("Use tracklist titles?")
+        .default(true)
+        .interact()
+        .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))
+}
@@ -5,26 +5,54 @@ pub mod id3_handler;
 pub mod converter;
 pub mod folder_normalizer;
 pub mod interactive_parser;
+pub mod checksum;
+pub mod tracklist;
+pub mod cue_sheet;
+pub mod text_encoding;
+pub mod nfo;
 
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use futures_util::{stream, StreamExt};
 use rusqlite::Connection;
 use tracing::{info, warn, debug};
+use crate::config::TitleLanguage;
+use crate::database::actor::DbHandle;
+use crate::database::db_path::DbPathRef;
+use crate::database::integrity::{self, IntegrityStatus};
+use crate::database::review_queue::{self, ReviewReason};
+use crate::database::{normalization_log, tag_categories, work_groups, work_overrides};
+use crate::database::tag_history;
 use crate::errors::HvtError;
+use crate::dlsite::MetadataClient;
 use crate::folders::types::{ManagedFolder, RJCode};
-use crate::tagger::types::{AudioMetadata, TaggerConfig, AudioFormat};
+use crate::run_stats::RunStats;
+use crate::tagger::types::{AgeCategory, AudioMetadata, TaggerConfig, AudioFormat, is_taggable_video_extension};
+
+/// Empty file older versions wrote into tagged work folders, now only with `[tagger]
+/// write_tagged_markers` (the database says which works are tagged)
+pub const TAGGED_MARKER_NAME: &str = ".tagged";
 
 /// Main function to process a work folder:
 /// 1. Fetch metadata from database
 /// 2. Download cover art (if enabled)
-/// 3. Tag all audio files
+/// 3. Tag all audio files (on blocking threads, which record them through `db_thread` when
+///    given; without it, once all are tagged)
 /// 4. Convert to MP3 (if enabled)
-/// 5. Mark folder as tagged
+/// 5. Write ReplayGain tags (if enabled)
+/// 6. Flag empty/silent audio files (if enabled)
+/// 7. Record the work as tagged in `file_processing` (and write the checksum manifest, if
+///    enabled), unless files were flagged
 pub async fn process_work_folder(
     conn: &Connection,
     folder: &ManagedFolder,
     config: &TaggerConfig,
+    client: &MetadataClient,
+    db_thread: Option<&DbHandle>,
 ) -> Result<(), HvtError> {
-    info!("Processing folder: {}", folder.path);
+    info!("Processing folder: {}", folder.path.display());
+    let stats = &client.stats;
 
     // Check if re-tagging needed (custom tags OR circle preferences modified)
     let needs_retag_tags = crate::database::custom_tags::should_retag_work(conn, &folder.rjcode).unwrap_or(false);
@@ -32,12 +60,20 @@ pub async fn process_work_folder(
     let needs_retag_cv = crate::database::custom_cvs::should_retag_work_for_cv(conn, &folder.rjcode).unwrap_or(false);
     let needs_retag = needs_retag_tags || needs_retag_circle || needs_retag_cv || config.force_retag;
 
-    // Skip if already tagged and no re-tagging needed
-    if folder.is_tagged && !needs_retag {
+    // Skip if already tagged and no re-tagging needed (the database knows, no folder is read)
+    if crate::database::queries::is_work_tagged(conn, &folder.rjcode)? && !needs_retag {
         debug!("Folder already tagged, skipping (use --force to re-tag)");
         return Ok(());
     }
 
+    // Works of a type never tagged ([tagger] skip_work_types), e.g. videos
+    if !config.skip_work_types.is_empty() {
+        if let Some(code) = skipped_work_type(conn, &folder.rjcode, &config.skip_work_types)? {
+            info!("{} is a {} work, skipped ([tagger] skip_work_types)", folder.rjcode, code);
+            return Ok(());
+        }
+    }
+
     if config.force_retag {
         info!("Force re-tagging: {}", folder.rjcode.as_str());
     }
@@ -52,57 +88,111 @@ pub async fn process_work_folder(
         info!("CV mapping modified, re-tagging work: {}", folder.rjcode.as_str());
     }
 
-    // Step 0: Normalize folder structure (move all audio files to root level)
+    // Step 0: Normalize folder structure (move all audio files to root level), logging the
+    // moves so --normalize --undo can restore the layout
     let folder_path = Path::new(&folder.path);
-    match folder_normalizer::normalize_folder_structure(folder_path) {
-        Ok(count) if count > 0 => info!("Normalized folder structure: {} files moved", count),
-        Ok(_) => {}, // Already normalized
-        Err(e) => warn!("Failed to normalize folder structure: {}", e),
+    if config.normalize_folders {
+        match folder_normalizer::normalize_folder_structure(folder_path, config.collect_extras) {
+            Ok(moves) => normalization_log::record_moves(conn, folder.rjcode.as_str(), &moves)?,
+            Err(e) => warn!("Failed to normalize folder structure: {}", e),
+        }
     }
 
     // Get fld_id for this work
     let fld_id = get_fld_id(conn, &folder.rjcode)?;
 
     // Fetch metadata from database
-    let metadata = fetch_metadata_from_db(conn, &folder.rjcode)?;
+    let metadata = fetch_metadata_from_db(conn, &folder.rjcode, config)?;
 
     // Download cover art if enabled and not already present
     if config.download_cover && !folder.has_cover {
-        if let Some(cover_url) = get_cover_url(conn, &folder.rjcode)? {
-            let folder_path = Path::new(&folder.path);
-            match cover_art::download_and_save_cover(
-                &cover_url,
-                folder_path,
-                None,  // Keep original dimensions from DLSite
-            ).await {
-                Ok(_) => info!("Cover art downloaded successfully"),
-                Err(e) => warn!("Failed to download cover art: {}", e),
-            }
+        let downloaded = cover_art::download_cover_with_fallback(conn, db_thread, &folder.rjcode, client, &config.cover_settings).await
+            .and_then(|_| cover_art::copy_cover_from_cache(folder.rjcode.as_str(), Path::new(&folder.path), stats));
+        match downloaded {
+            Ok(_) => info!("Cover art downloaded successfully"),
+            Err(e) => warn!("Failed to download cover art: {}", e),
         }
     }
 
     // Tag all audio files
-    tag_all_files(conn, fld_id, folder, &metadata, config).await?;
+    tag_all_files(conn, db_thread, fld_id, folder, &metadata, config, stats).await?;
 
-    // Mark folder as tagged by creating .tagged file (skipped for one-shot test runs)
-    if config.write_tagged_marker {
-        create_tagged_marker(&folder.path)?;
+    // Loudness is measured on the converted files, the work being the album
+    if config.replaygain {
+        write_replaygain(folder_path, config);
     }
 
-    info!("Successfully processed folder: {}", folder.path);
+    // Optionally write the same work metadata into bonus videos
+    if config.tag_video_files {
+        tag_video_files(folder_path, &metadata, &config.tag_separator, stats);
+    }
+
+    // Flag empty/silent tracks (likely broken downloads): the work is left unmarked and queued
+    // for review, so it gets processed again once they have been re-downloaded
+    let flagged = if config.detect_silent_audio {
+        let flagged = flag_silent_audio_files(conn, &folder.rjcode, folder_path);
+        let pending = (!flagged.is_empty())
+            .then(|| format!("{} empty or silent audio file(s): {}", flagged.len(), flagged.join(", ")));
+        update_review_queue(conn, &folder.rjcode, ReviewReason::DamagedAudio, pending.as_deref());
+        flagged.len()
+    } else {
+        0
+    };
+    if flagged > 0 {
+        warn!(
+            "{}: {} empty or silent audio file(s), re-download them (not marking the work as tagged)",
+            folder.rjcode, flagged
+        );
+        crate::database::queries::reset_work_tagged_files(conn, &folder.rjcode)?;
+    }
+
+    // The files were recorded as tagged while tagging; the legacy marker is only written on
+    // request, and an old one is cleared otherwise
+    let marker_path = folder_path.join(TAGGED_MARKER_NAME);
+    if config.write_tagged_marker && flagged == 0 {
+        std::fs::write(&marker_path, "")?;
+    } else if marker_path.exists() {
+        std::fs::remove_file(&marker_path)?;
+    }
+
+    // Write the info file (metadata + description) for browsing the folder without a player
+    if config.write_nfo {
+        match crate::database::queries::get_work_description(conn, &folder.rjcode) {
+            Ok(Some(description)) => {
+                let section = crate::database::queries::get_dlsite_section(conn, &folder.rjcode).unwrap_or_default();
+                if let Err(e) = nfo::write_nfo(folder_path, &folder.rjcode, section.as_deref(), &metadata, &description, stats) {
+                    warn!("Failed to write {}: {}", nfo::NFO_FILE_NAME, e);
+                }
+            }
+            Ok(None) => debug!("No description for {}, skipping {}", folder.rjcode, nfo::NFO_FILE_NAME),
+            Err(e) => warn!("Failed to read description of {}: {}", folder.rjcode, e),
+        }
+    }
+
+    // Snapshot the final file hashes so --fsck can later detect silent corruption
+    if config.write_checksum_manifest {
+        match checksum::write_manifest(folder_path) {
+            Ok(count) => debug!("Wrote checksum manifest ({} files)", count),
+            Err(e) => warn!("Failed to write checksum manifest: {}", e),
+        }
+    }
+
+    info!("Successfully processed folder: {}", folder.path.display());
     Ok(())
 }
 
-/// Tags a single audio file based on its format
-pub async fn tag_audio_file(
+/// Tags a single audio file based on its format (blocking file IO)
+pub fn tag_audio_file(
     file_path: &Path,
     metadata: &AudioMetadata,
     format: &AudioFormat,
-    separator: &str,
+    config: &TaggerConfig,
+    stats: &RunStats,
 ) -> Result<(), HvtError> {
     match format {
         AudioFormat::Mp3 => {
-            id3_handler::write_id3_tags(file_path, metadata, separator)?;
+            id3_handler::write_id3_tags(file_path, metadata, config)?;
+            stats.add_file_written();
         }
         AudioFormat::Flac => {
             return Err(HvtError::AudioTag(
@@ -121,98 +211,210 @@ pub async fn tag_audio_file(
 
 // Helper functions
 
-fn fetch_metadata_from_db(conn: &Connection, rjcode: &RJCode) -> Result<AudioMetadata, HvtError> {
+pub(crate) fn fetch_metadata_from_db(conn: &Connection, rjcode: &RJCode, config: &TaggerConfig) -> Result<AudioMetadata, HvtError> {
     // Query database for work metadata (with fallback to RJCode if not collected yet)
-    let work_name: String = conn.query_row(
-        "SELECT name FROM works WHERE fld_id = (SELECT fld_id FROM folders WHERE rjcode = ?1)",
-        rusqlite::params![rjcode],
-        |row| row.get(0),
-    ).unwrap_or_else(|_| {
-        // Fallback: use RJCode as title if metadata not collected yet
-        debug!("No metadata found for {}, using RJCode as title", rjcode);
-        rjcode.to_string()
-    });
+    let name_column = match config.title_language {
+        TitleLanguage::Jp => "name",
+        TitleLanguage::En => "COALESCE(NULLIF(name_en, ''), name)",
+    };
+
+    // Per-work overrides (--manage-works) win over everything below
+    let overrides = work_overrides::get_work_overrides(conn, rjcode)?;
+
+    let work_name: String = match &overrides.title {
+        Some(title) => title.clone(),
+        None => conn.query_row(
+            &format!("SELECT {name_column} FROM works WHERE fld_id = (SELECT fld_id FROM folders WHERE rjcode = ?1)"),
+            rusqlite::params![rjcode],
+            |row| row.get(0),
+        ).unwrap_or_else(|_| {
+            // Fallback: use RJCode as title if metadata not collected yet
+            debug!("No metadata found for {}, using RJCode as title", rjcode);
+            rjcode.to_string()
+        }),
+    };
 
     // Get circle name (with custom preference support)
-    let circle_name = crate::database::custom_circles::get_merged_circle_name_for_work(conn, rjcode)
-        .unwrap_or_else(|_| String::from("Unknown"));
+    let circle_name = match overrides.circle {
+        Some(circle) => circle,
+        None => crate::database::custom_circles::get_merged_circle_name_for_work(conn, rjcode)
+            .unwrap_or_else(|_| String::from("Unknown")),
+    };
 
-    // Get tags (merged: DLSite + custom replacements) - returns empty vec if none
-    let tags = crate::database::custom_tags::get_merged_tags_for_work(conn, rjcode)
-        .unwrap_or_default();
+    // Get tags (merged: DLSite + custom replacements) - returns empty vec if none - split by
+    // category frame. Every TXXX category is listed, empty ones clear stale frames.
+    let tags = match overrides.tags {
+        Some(tags) => tags,
+        None => crate::database::custom_tags::get_merged_tags_for_work(conn, rjcode)
+            .unwrap_or_default(),
+    };
+    let mut tags = tag_categories::categorize_tags_for_work(conn, rjcode, tags)?;
+    for description in tag_categories::all_txxx_descriptions(conn)? {
+        if !tags.txxx.iter().any(|(d, _)| *d == description) {
+            tags.txxx.push((description, Vec::new()));
+        }
+    }
 
-    // Get CVs (voice actors, merged with any custom rename) - will be used as artists
-    let cvs = crate::database::custom_cvs::get_merged_cvs_for_work(conn, rjcode)
-        .unwrap_or_default();
+    // Get CVs (voice actors, merged with any custom rename, in the configured JP/EN style) -
+    // will be used as artists
+    let cvs = match overrides.cvs {
+        Some(cvs) => cvs,
+        None => crate::database::custom_cvs::get_cv_artists_for_work(conn, rjcode, config.cv_name_style)
+            .unwrap_or_default(),
+    };
 
     // Get release date
-    let release_date: Option<String> = conn.query_row(
+    let release_date: Option<String> = overrides.release_date.or_else(|| conn.query_row(
         "SELECT release_date FROM release_date WHERE fld_id = (
             SELECT fld_id FROM folders WHERE rjcode = ?1
         )",
         rusqlite::params![rjcode],
         |row| row.get(0),
-    ).ok();
+    ).ok());
+
+    // Get series (grouping): the root work's title when it is in the library, else its RJ code
+    let grouping = if config.write_series_tag {
+        crate::database::queries::get_series_root(conn, rjcode)
+            .unwrap_or_default()
+            .map(|root| {
+                conn.query_row(
+                    &format!("SELECT {name_column} FROM works WHERE fld_id = (SELECT fld_id FROM folders WHERE rjcode = ?1)"),
+                    rusqlite::params![root],
+                    |row| row.get(0),
+                ).unwrap_or(root)
+            })
+    } else {
+        None
+    };
+
+    // Get description, for the comment frame
+    let comment = if config.write_description_comment {
+        crate::database::queries::get_work_description(conn, rjcode).unwrap_or_default()
+    } else {
+        None
+    };
+
+    // Get source identifiers (TXXX frames)
+    let mut source_frames = Vec::new();
+    if config.write_source_frames {
+        source_frames.push(("DLSITE_ID".to_string(), rjcode.to_string()));
+        let section = crate::database::queries::get_dlsite_section(conn, rjcode)?;
+        source_frames.push(("DLSITE_URL".to_string(), rjcode.dlsite_url(section.as_deref())));
+        if let Ok(Some(rgcode)) = crate::database::queries::get_circle_code_for_work(conn, rjcode) {
+            source_frames.push(("CIRCLE_CODE".to_string(), rgcode));
+        }
+    }
+
+    // Get the age rating ("All Ages", "R15", "R18"; "Other" is an unknown category)
+    if config.write_age_rating || config.write_explicit_flag {
+        let age_rating: Option<String> = conn.query_row(
+            "SELECT rating FROM rating WHERE fld_id = (SELECT fld_id FROM folders WHERE rjcode = ?1)",
+            rusqlite::params![rjcode],
+            |row| row.get(0),
+        ).ok().filter(|rating: &String| *rating != AgeCategory::Other.to_string());
+        if let Some(age_rating) = age_rating {
+            if config.write_explicit_flag {
+                let explicit = if age_rating == AgeCategory::R18.to_string() { "1" } else { "0" };
+                source_frames.push(("ITUNESADVISORY".to_string(), explicit.to_string()));
+            }
+            if config.write_age_rating {
+                source_frames.push(("RATING".to_string(), age_rating));
+            }
+        }
+    }
+
+    // Get DLSite stars, for the POPM frame (0 is a work nobody rated yet)
+    let rating: Option<f32> = if config.write_rating {
+        conn.query_row(
+            "SELECT stars FROM stars WHERE fld_id = (SELECT fld_id FROM folders WHERE rjcode = ?1)",
+            rusqlite::params![rjcode],
+            |row| row.get(0),
+        ).ok().filter(|stars: &f32| *stars > 0.0)
+    } else {
+        None
+    };
+
+    // Get the work group: its works share the group name as album, one disc each
+    let group = work_groups::get_work_group(conn, rjcode)?;
+    let disc = group.as_ref().map(|g| (g.disc_number, g.total_discs));
+    let album = group.map(|g| g.name).unwrap_or_else(|| work_name.clone());
 
     Ok(AudioMetadata {
-        title: work_name.clone(),
+        title: work_name,
         artists: cvs,              // Voice actors as artists
-        album: work_name,
+        album,
         album_artist: circle_name, // Circle as album artist
         track_number: None,        // Will be set per-file
-        genre: tags,
+        disc,
+        genre: tags.genre,
+        mood: tags.mood,
+        category_frames: tags.txxx,
         date: release_date,
+        grouping,
+        comment,
+        source_frames,
+        rating,
     })
 }
 
-fn get_cover_url(conn: &Connection, rjcode: &RJCode) -> Result<Option<String>, HvtError> {
-    let url: Option<String> = conn.query_row(
-        "SELECT link FROM dlsite_covers WHERE fld_id = (
-            SELECT fld_id FROM folders WHERE rjcode = ?1
-        )",
-        rusqlite::params![rjcode],
-        |row| row.get(0),
-    ).ok();
-
-    Ok(url)
-}
-
 async fn tag_all_files(
     conn: &Connection,
+    db_thread: Option<&DbHandle>,
     fld_id: i64,
     folder: &ManagedFolder,
     base_metadata: &AudioMetadata,
     config: &TaggerConfig,
+    stats: &Arc<RunStats>,
 ) -> Result<(), HvtError> {
-    use std::path::PathBuf;
-
     let folder_path = Path::new(&folder.path);
 
-    // STEP 0: Convert non-MP3 files if --convert is enabled
-    if config.convert_to_mp3 {
-        let entries = std::fs::read_dir(folder_path)?;
-        for entry in entries {
-            let entry = entry?;
-            let file_path = entry.path();
+    // Without normalization, the files are tagged where they are, subfolders included
+    let in_place = !config.normalize_folders;
 
-            if !file_path.is_file() {
-                continue;
+    // STEP 0a: Single-file work with a cue sheet, or an audiobook with chapters (.m4b/.m4a):
+    // split it into tracks (`split_cue`), or keep the tracks for ID3 chapters (STEP 7)
+    let ffmpeg_available = converter::is_ffmpeg_available();
+    let single_file = cue_sheet::find_cue_sheet(folder_path)
+        .map(|(cue_path, audio_path, sheet)| (audio_path, sheet, format!("cue sheet {}", cue_path.display())))
+        .or_else(|| {
+            ffmpeg_available.then(|| cue_sheet::find_chaptered_audio(folder_path)).flatten()
+                .map(|(audio_path, sheet)| (audio_path, sheet, "chapters".to_string()))
+        });
+    let mut cue_chapters: Option<(PathBuf, cue_sheet::CueSheet)> = None;
+    if let Some((audio_path, sheet, source)) = single_file {
+        if config.split_cue && ffmpeg_available {
+            info!("Splitting {} into {} track(s) ({})",
+                  audio_path.display(), sheet.tracks.len(), source);
+            match split_cue_sheet(&audio_path, &sheet, config.target_bitrate, stats) {
+                Ok(count) => info!("Split into {} track(s)", count),
+                Err(e) => warn!("Failed to split {}: {}", audio_path.display(), e),
+            }
+        } else {
+            if config.split_cue {
+                warn!("ffmpeg not found in PATH, {} is not split", audio_path.display());
             }
+            debug!("{}: {} chapter(s) from {}", audio_path.display(), sheet.tracks.len(), source);
+            cue_chapters = Some((audio_path, sheet));
+        }
+    }
 
+    // STEP 0b: Convert non-MP3 files if --convert is enabled
+    if config.convert_to_mp3 {
+        for file_path in list_work_files(folder_path, in_place)? {
             let extension = file_path.extension()
                 .and_then(|e| e.to_str())
                 .unwrap_or("");
 
             let format = AudioFormat::from_extension(extension);
 
-            // Convert FLAC, WAV, OGG to MP3
-            if format == AudioFormat::Flac || format == AudioFormat::Wav || format == AudioFormat::Ogg {
+            // Convert FLAC, WAV, OGG, M4A to MP3
+            if format.needs_conversion() {
                 let filename = file_path.file_name()
                     .and_then(|n| n.to_str())
                     .unwrap_or("");
                 info!("Converting to MP3: {}", filename);
 
-                match converter::convert_to_mp3_in_place(&file_path, config.target_bitrate).await {
+                match converter::convert_to_mp3_in_place(&file_path, config.target_bitrate, stats).await {
                     Ok(_) => info!("Converted: {} -> .mp3", filename),
                     Err(e) => warn!("Failed to convert {}: {}", filename, e),
                 }
@@ -221,17 +423,9 @@ async fn tag_all_files(
     }
 
     // STEP 1: Collect all MP3 files
-    let entries = std::fs::read_dir(folder_path)?;
     let mut audio_files: Vec<(PathBuf, String)> = Vec::new();
 
-    for entry in entries {
-        let entry = entry?;
-        let file_path = entry.path();
-
-        if !file_path.is_file() {
-            continue;
-        }
-
+    for file_path in list_work_files(folder_path, in_place)? {
         let filename = file_path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("")
@@ -245,7 +439,7 @@ async fn tag_all_files(
 
         // Only process MP3 files
         if format != AudioFormat::Mp3 {
-            if format == AudioFormat::Flac || format == AudioFormat::Wav || format == AudioFormat::Ogg {
+            if format.needs_conversion() {
                 warn!("Skipping non-MP3 file: {}. Use --convert to convert to MP3 first.", filename);
             }
             continue;
@@ -262,6 +456,9 @@ async fn tag_all_files(
     // STEP 2: Check if files already have track numbers in their ID3 tags
     let existing_tracks: Vec<Option<u32>> = audio_files.iter()
         .map(|(file_path, _)| {
+            if !config.keep_existing_track_numbers {
+                return None;
+            }
             id3_handler::read_id3_tags(file_path, &config.tag_separator)
                 .ok()
                 .flatten()
@@ -287,20 +484,42 @@ async fn tag_all_files(
         .map(|(_, name)| name.clone())
         .collect();
 
-    let mut current_pref = parsing_pref;
+    // Without a saved preference, use the first library-level strategy that numbers every file
+    let mut current_pref = parsing_pref.or_else(|| {
+        let unnumbered: Vec<String> = filenames.iter().zip(existing_tracks.iter())
+            .filter(|(_, existing)| existing.is_none())
+            .map(|(f, _)| f.clone())
+            .collect();
+        let preset = track_parser::first_matching_preset(&unnumbered, &config.parsing_presets)?;
+        debug!("Using track parsing strategy '{}' for {}", preset.strategy_name, folder.rjcode);
+        Some(preset.clone())
+    });
     // Per-file track numbers from manual input (Session-only, not saved to DB).
     let mut manual_numbers: Option<Vec<Option<u32>>> = None;
 
     // Numbers that automatic detection would actually assign this run: only for files that
     // don't already carry a track number (those are left untouched, see STEP 5).
-    let auto_parsed: Vec<Option<u32>> = filenames.iter().zip(existing_tracks.iter())
+    let auto_parsed: Vec<(&Path, Option<u32>)> = audio_files.iter().zip(existing_tracks.iter())
         .filter(|(_, existing)| existing.is_none())
-        .map(|(f, _)| track_parser::parse_track_number_with_preference(f, current_pref.as_ref()))
+        .map(|((path, f), _)| {
+            (path.parent().unwrap_or(folder_path), track_parser::parse_track_number_with_preference(f, current_pref.as_ref()))
+        })
         .collect();
 
-    let failure_count = auto_parsed.iter().filter(|p| p.is_none()).count();
+    let failure_count = auto_parsed.iter().filter(|(_, p)| p.is_none()).count();
     let failure_rate = if auto_parsed.is_empty() { 0.0 } else { failure_count as f32 / auto_parsed.len() as f32 };
-    let duplicate_numbers = track_parser::find_duplicate_track_numbers(&auto_parsed);
+    // Numbers only clash within one folder: a work tagged in place restarts at 1 on each disc
+    let mut duplicate_numbers: Vec<u32> = Vec::new();
+    let parents: BTreeSet<&Path> = auto_parsed.iter().map(|(parent, _)| *parent).collect();
+    for parent in parents {
+        let numbers: Vec<Option<u32>> = auto_parsed.iter()
+            .filter(|(p, _)| *p == parent)
+            .map(|(_, n)| *n)
+            .collect();
+        duplicate_numbers.extend(track_parser::find_duplicate_track_numbers(&numbers));
+    }
+    duplicate_numbers.sort_unstable();
+    duplicate_numbers.dedup();
 
     // Trigger interactive session when:
     // - files don't already have numbers, no saved preference exists yet, and automatic
@@ -312,7 +531,12 @@ async fn tag_all_files(
     let low_confidence = !files_already_numbered && current_pref.is_none() && failure_rate > 0.3;
     let has_duplicates = !duplicate_numbers.is_empty();
 
-    if low_confidence || has_duplicates {
+    if (low_confidence || has_duplicates) && !config.prompt_track_parsing {
+        let details = format!("{}/{} file(s) unparsed, duplicate track number(s) {:?}",
+                              failure_count, filenames.len(), duplicate_numbers);
+        warn!("Track numbering of {} is uncertain ({}), queued for review", folder.rjcode, details);
+        update_review_queue(conn, &folder.rjcode, ReviewReason::TrackNumbering, Some(&details));
+    } else if low_confidence || has_duplicates {
         if has_duplicates {
             info!("Automatic track parsing produced duplicate track number(s) {:?} for {}, requesting user input...",
                   duplicate_numbers, folder.rjcode.as_str());
@@ -321,7 +545,8 @@ async fn tag_all_files(
                   failure_count, filenames.len());
         }
 
-        match interactive_parser::run_interactive_parsing(&filenames, folder.rjcode.as_str()) {
+        let offer_silence_split = audio_files.len() == 1 && converter::is_ffmpeg_available();
+        match interactive_parser::run_interactive_parsing(&filenames, folder.rjcode.as_str(), offer_silence_split) {
             Ok(interactive_parser::ParsingResult::Strategy(pref)) => {
                 crate::database::queries::save_track_parsing_preference(conn, &folder.rjcode, &pref)?;
                 info!("Track parsing preference saved for future use");
@@ -334,20 +559,29 @@ async fn tag_all_files(
             Ok(interactive_parser::ParsingResult::Skip) => {
                 info!("Track numbering skipped for {}", folder.rjcode);
             }
+            Ok(interactive_parser::ParsingResult::SplitBySilence) => {
+                match split_by_silence(&audio_files[0].0, config, stats) {
+                    Ok(true) => {
+                        // Start over on the split tracks
+                        update_review_queue(conn, &folder.rjcode, ReviewReason::TrackNumbering, None);
+                        return Box::pin(tag_all_files(conn, db_thread, fld_id, folder, base_metadata, config, stats)).await;
+                    }
+                    Ok(false) => info!("{} not split, tagged without a track number", filenames[0]),
+                    Err(e) => warn!("Failed to split {} by silence: {}", filenames[0], e),
+                }
+            }
             Err(e) => {
                 warn!("Interactive parsing failed: {}", e);
             }
         }
+        update_review_queue(conn, &folder.rjcode, ReviewReason::TrackNumbering, None);
+    } else {
+        update_review_queue(conn, &folder.rjcode, ReviewReason::TrackNumbering, None);
     }
 
-    // STEP 5: Tag each file
-    for (file_index, (file_path, filename)) in audio_files.iter().enumerate() {
-        let existing_track = if let Ok(Some(existing_metadata)) = id3_handler::read_id3_tags(file_path, &config.tag_separator) {
-            existing_metadata.track_number
-        } else {
-            None
-        };
-
+    // STEP 5: Resolve the track number of each file
+    let mut track_numbers = Vec::with_capacity(audio_files.len());
+    for (file_index, (filename, existing_track)) in filenames.iter().zip(existing_tracks.iter().copied()).enumerate() {
         let track_number = if let Some(ref nums) = manual_numbers {
             // Manual numbers override everything — the user chose each one explicitly
             nums.get(file_index).copied().flatten()
@@ -357,48 +591,542 @@ async fn tag_all_files(
         } else {
             track_parser::parse_track_number_with_preference(filename, current_pref.as_ref())
         };
+        track_numbers.push(track_number);
+    }
 
+    // STEP 6: Use a bundled tracklist file for titles, if present and confirmed
+    let tracklist_titles = load_tracklist_titles(conn, Path::new(&folder.path), &folder.rjcode, &audio_files, &track_numbers, config);
+
+    // Disc numbers from the subfolder names (CD1/, Disc 2/...) when tagging in place, unless
+    // the work is already a disc of a work group
+    let discs: Vec<Option<u32>> = audio_files.iter()
+        .map(|(file_path, _)| if in_place { disc_from_subfolders(folder_path, file_path) } else { None })
+        .collect();
+    let total_discs = discs.iter().flatten().max().copied();
+
+    // STEP 7: Tag the files, `io_parallelism` at a time on blocking threads (writes on a NAS
+    // mostly wait on the network); the database is updated here, in the order of the files
+    let shared_config = Arc::new(config.clone());
+    let db = db_thread.cloned();
+    let mut files = Vec::with_capacity(audio_files.len());
+    let mut jobs = Vec::with_capacity(audio_files.len());
+    for (((file_path, filename), track_number), disc) in audio_files.into_iter().zip(track_numbers).zip(discs) {
         let mut file_metadata = base_metadata.clone();
         file_metadata.track_number = track_number;
-        file_metadata.title = track_parser::extract_track_title(filename);
+        if file_metadata.disc.is_none() {
+            file_metadata.disc = disc.zip(total_discs);
+        }
+        file_metadata.title = track_number
+            .and_then(|n| tracklist_titles.get(&n).cloned())
+            .unwrap_or_else(|| track_parser::extract_track_title(&filename));
 
         debug!("Tagging: {} (track: {:?}, title: {})", filename, track_number, file_metadata.title);
 
-        let format = AudioFormat::Mp3;
-        tag_audio_file(file_path, &file_metadata, &format, &config.tag_separator).await?;
-        record_file_processing(conn, fld_id, file_path)?;
+        let chapters = match &cue_chapters {
+            Some((audio_path, _)) if audio_path.with_extension("mp3") == file_path => cue_chapters.take().map(|(_, sheet)| sheet),
+            _ => None,
+        };
+        let job_config = Arc::clone(&shared_config);
+        let job_path = file_path.clone();
+        let job_db = db.clone();
+        let job_stats = Arc::clone(stats);
+        jobs.push(move || {
+            let tagged = tag_file(&job_path, &file_metadata, chapters.as_ref(), &job_config, &job_stats);
+            match job_db {
+                Some(db) => tagged.record_on(&db, fld_id, &job_path, job_config.tag_config_hash()),
+                None => tagged,
+            }
+        });
+        files.push((file_path, filename));
+    }
+    // Spawned lazily, as `buffered` makes room for them; results come in the order of `files`
+    let results: Vec<_> = stream::iter(jobs.into_iter().map(tokio::task::spawn_blocking))
+        .buffered(config.io_parallelism.max(1))
+        .collect()
+        .await;
+
+    let mut first_error = None;
+    for ((file_path, filename), result) in files.iter().zip(results) {
+        let tagged = result.unwrap_or_else(|e| TaggedFile {
+            original_tag: None,
+            written: Err(HvtError::AudioTag(format!("Tagging {} panicked: {}", filename, e))),
+            size: 0,
+            checksum: None,
+            recorded: None,
+        });
+        match tagged.recorded {
+            Some(recorded) => recorded?,
+            None => {
+                if let Some(original) = &tagged.original_tag {
+                    record_original_tags(conn, fld_id, filename, original);
+                }
+                if tagged.written.is_ok() {
+                    record_file_processing(conn, fld_id, file_path, tagged.size, tagged.checksum, &config.tag_config_hash())?;
+                }
+            }
+        }
+        match tagged.written {
+            Ok(()) => {}
+            Err(e) => {
+                warn!("Failed to tag {}: {}", filename, e);
+                first_error.get_or_insert(e);
+            }
+        }
     }
 
-    Ok(())
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
 }
 
-fn create_tagged_marker(folder_path: &str) -> Result<(), HvtError> {
-    let marker_path = Path::new(folder_path).join(".tagged");
-    std::fs::write(marker_path, "")?;
-    Ok(())
+/// What tagging one file did, on its blocking thread
+struct TaggedFile {
+    /// Raw tag before tagging (`None` when it could not be read)
+    original_tag: Option<Option<Vec<u8>>>,
+    written: Result<(), HvtError>,
+    size: i64,
+    checksum: Option<String>,
+    /// Whether the original tag and the tagged file were recorded through the database thread;
+    /// `None`: left to the caller's connection
+    recorded: Option<Result<(), HvtError>>,
 }
 
-/// Record file processing in database
+impl TaggedFile {
+    /// Records the original tag and the tagged file from the blocking thread, through the
+    /// database thread of the run
+    fn record_on(mut self, db: &DbHandle, fld_id: i64, file_path: &Path, config_hash: String) -> Self {
+        let file_path = file_path.to_path_buf();
+        let original_tag = self.original_tag.take();
+        let written = self.written.is_ok();
+        let (size, checksum) = (self.size, self.checksum.take());
+        self.recorded = Some(db.call_blocking(move |conn| {
+            let file_name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if let Some(original) = &original_tag {
+                record_original_tags(conn, fld_id, file_name, original);
+            }
+            if written {
+                record_file_processing(conn, fld_id, &file_path, size, checksum, &config_hash)?;
+            }
+            Ok(())
+        }));
+        self
+    }
+}
+
+/// Tags one MP3 file (and writes its cue chapters), keeping its tag from before and hashing it
+/// afterwards. Blocking: runs on the blocking pool.
+fn tag_file(
+    file_path: &Path,
+    metadata: &AudioMetadata,
+    chapters: Option<&cue_sheet::CueSheet>,
+    config: &TaggerConfig,
+    stats: &RunStats,
+) -> TaggedFile {
+    let original_tag = match id3_handler::read_raw_tag(file_path) {
+        Ok(raw) => Some(raw),
+        Err(e) => {
+            warn!("Failed to save original tags of {}: {}", file_path.display(), e);
+            None
+        }
+    };
+    let written = tag_audio_file(file_path, metadata, &AudioFormat::Mp3, config, stats);
+    if written.is_ok() {
+        if let Some(sheet) = chapters {
+            write_cue_chapters(file_path, sheet, config);
+        }
+    }
+    let size = std::fs::metadata(file_path).map(|m| m.len() as i64).unwrap_or(0);
+
+    // Hash AFTER tagging, so the stored checksum matches the file as it now sits on disk
+    // (compared later by --verify --checksums to detect bit-rot / silent modification)
+    let checksum = match written.is_ok().then(|| checksum::sha256_file(file_path)) {
+        Some(Ok(hash)) => Some(hash),
+        Some(Err(e)) => {
+            warn!("Failed to compute checksum for {}: {}", file_path.display(), e);
+            None
+        }
+        None => None,
+    };
+
+    TaggedFile { original_tag, written, size, checksum, recorded: None }
+}
+
+/// Splits the audio file of a cue sheet (or an audiobook, by chapter) into one MP3 per track
+/// next to it (named so the track parser finds their number and title, see
+/// `CueSheet::track_file_name`), then deletes it; a cue sheet is kept. When a track fails, the tracks already written are removed and the
+/// original is kept. Returns the number of tracks.
+fn split_cue_sheet(audio_path: &Path, sheet: &cue_sheet::CueSheet, bitrate: u32, stats: &RunStats) -> Result<usize, HvtError> {
+    let folder = audio_path.parent()
+        .ok_or_else(|| HvtError::PathCreationFailed(audio_path.display().to_string()))?;
+    let mut written: Vec<PathBuf> = Vec::new();
+    for (track, (start, end)) in sheet.tracks.iter().zip(sheet.segments(None)) {
+        let output = folder.join(cue_sheet::CueSheet::track_file_name(track));
+        let result = if output.exists() {
+            Err(HvtError::AudioConversion(format!("{} already exists", output.display())))
+        } else {
+            converter::extract_mp3_segment(audio_path, &output, start, end, bitrate, stats)
+        };
+        if let Err(e) = result {
+            for file in &written {
+                let _ = std::fs::remove_file(file);
+            }
+            return Err(e);
+        }
+        written.push(output);
+    }
+    std::fs::remove_file(audio_path)?;
+    Ok(written.len())
+}
+
+/// Detects the silences of a work's single audio file (`[track_parsing] silence_threshold_db` /
+/// `silence_min_duration`) and, once the user confirms the resulting tracks, splits it there
+/// like a cue sheet (`01.mp3`, `02.mp3`...). Returns whether the file was split.
+fn split_by_silence(file_path: &Path, config: &TaggerConfig, stats: &RunStats) -> Result<bool, HvtError> {
+    let file_name = file_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    info!("Detecting silences in {}...", file_name);
+    let silences = converter::detect_silences(file_path, config.silence_threshold_db, config.silence_min_duration)?;
+    let split_points = converter::silence_split_points(&silences);
+    if split_points.is_empty() {
+        info!("No silence of {}s or more below {} dB found", config.silence_min_duration, config.silence_threshold_db);
+        return Ok(false);
+    }
+
+    let sheet = cue_sheet::CueSheet::from_split_points(&file_name, &split_points);
+    if !interactive_parser::confirm_silence_split(&file_name, &sheet.segments(None))? {
+        return Ok(false);
+    }
+    let count = split_cue_sheet(file_path, &sheet, config.target_bitrate, stats)?;
+    info!("Split into {} track(s)", count);
+    Ok(true)
+}
+
+/// Writes the tracks of a cue sheet as ID3 chapters of its single MP3. The last chapter ends
+/// with the file (its duration from ffmpeg when available). Failures are only logged.
+fn write_cue_chapters(file_path: &Path, sheet: &cue_sheet::CueSheet, config: &TaggerConfig) {
+    let duration = converter::is_ffmpeg_available()
+        .then(|| converter::audio_duration(file_path))
+        .flatten();
+    let chapters: Vec<(u32, u32, String)> = sheet.tracks.iter()
+        .zip(sheet.segments(duration))
+        .map(|(track, (start, end))| {
+            let title = track.title.clone().unwrap_or_else(|| format!("{:02}", track.number));
+            let end = end.map(|e| (e * 1000.0) as u32).unwrap_or(u32::MAX);
+            ((start * 1000.0) as u32, end, title)
+        })
+        .collect();
+    match id3_handler::write_chapters(file_path, &chapters, config) {
+        Ok(()) => debug!("Wrote {} chapter(s) to {}", chapters.len(), file_path.display()),
+        Err(e) => warn!("Failed to write chapters to {}: {}", file_path.display(), e),
+    }
+}
+
+/// Files of a work folder: the ones at its root, and with `recursive` those of its subfolders
+/// too (tagging in place, see `TaggerConfig::normalize_folders`).
+fn list_work_files(folder_path: &Path, recursive: bool) -> Result<Vec<PathBuf>, HvtError> {
+    let mut files = Vec::new();
+    let mut subfolders = Vec::new();
+    for entry in std::fs::read_dir(folder_path)? {
+        let path = entry?.path();
+        if path.is_file() {
+            files.push(path);
+        } else if recursive && path.is_dir() {
+            subfolders.push(path);
+        }
+    }
+    subfolders.sort();
+    for subfolder in subfolders {
+        files.extend(list_work_files(&subfolder, true)?);
+    }
+    Ok(files)
+}
+
+/// Disc number of a file tagged in place: the first of its subfolders (from the work folder)
+/// whose name is a disc (`track_parser::parse_disc_number`).
+fn disc_from_subfolders(folder_path: &Path, file_path: &Path) -> Option<u32> {
+    let relative = file_path.strip_prefix(folder_path).ok()?.parent()?;
+    relative.components()
+        .filter_map(|c| c.as_os_str().to_str())
+        .find_map(track_parser::parse_disc_number)
+}
+
+/// Looks for a tracklist text file in the work folder and, if it matches some of the files by
+/// track number, asks the user whether to use its titles. Returns an empty map when there is no
+/// tracklist, nothing matches, or the user declines.
+fn load_tracklist_titles(
+    conn: &Connection,
+    folder_path: &Path,
+    rjcode: &RJCode,
+    audio_files: &[(PathBuf, String)],
+    track_numbers: &[Option<u32>],
+    config: &TaggerConfig,
+) -> BTreeMap<u32, String> {
+    let Some(tracklist_path) = tracklist::find_tracklist_file(folder_path) else {
+        update_review_queue(conn, rjcode, ReviewReason::Tracklist, None);
+        return BTreeMap::new();
+    };
+
+    let titles = match tracklist::read_tracklist(&tracklist_path) {
+        Ok(titles) => titles,
+        Err(e) => {
+            warn!("Could not read tracklist {}: {}", tracklist_path.display(), e);
+            return BTreeMap::new();
+        }
+    };
+
+    let previews: Vec<(String, Option<u32>, Option<String>)> = audio_files.iter()
+        .zip(track_numbers)
+        .map(|((_, filename), track)| (filename.clone(), *track, track.and_then(|n| titles.get(&n).cloned())))
+        .collect();
+    if previews.iter().all(|(_, _, title)| title.is_none()) {
+        debug!("Tracklist {} doesn't match any file of {}", tracklist_path.display(), rjcode);
+        update_review_queue(conn, rjcode, ReviewReason::Tracklist, None);
+        return BTreeMap::new();
+    }
+
+    if config.accept_tracklist {
+        update_review_queue(conn, rjcode, ReviewReason::Tracklist, None);
+        return titles;
+    }
+    if !config.prompt_track_parsing {
+        // Unattended run: keep filename titles until someone confirms the tracklist
+        let details = format!("tracklist {} not confirmed", tracklist_path.display());
+        info!("{}: {}, queued for review", rjcode, details);
+        update_review_queue(conn, rjcode, ReviewReason::Tracklist, Some(&details));
+        return BTreeMap::new();
+    }
+
+    let titles = match interactive_parser::confirm_tracklist_titles(rjcode.as_str(), &tracklist_path, &previews) {
+        Ok(true) => titles,
+        Ok(false) => BTreeMap::new(),
+        Err(e) => {
+            warn!("Tracklist confirmation failed: {}", e);
+            BTreeMap::new()
+        }
+    };
+    update_review_queue(conn, rjcode, ReviewReason::Tracklist, None);
+    titles
+}
+
+/// Queues the work for review under `reason` when `pending` holds details, resolves the item
+/// otherwise. Failures are only logged: the review queue never blocks processing.
+pub(crate) fn update_review_queue(conn: &Connection, rjcode: &RJCode, reason: ReviewReason, pending: Option<&str>) {
+    let result = match pending {
+        Some(details) => review_queue::add_review_item(conn, rjcode, reason, details),
+        None => review_queue::resolve_review_item(conn, rjcode, reason),
+    };
+    if let Err(e) = result {
+        warn!("Failed to update review queue for {}: {}", rjcode, e);
+    }
+}
+
+/// Writes work metadata into every MP4/M4V video of the folder (subdirectories included, since
+/// normalization only flattens audio and leaves videos where they are). Failures are logged per
+/// file and never abort tagging of the work.
+fn tag_video_files(folder_path: &Path, base_metadata: &AudioMetadata, separator: &str, stats: &RunStats) {
+    let mut videos = Vec::new();
+    collect_taggable_videos(folder_path, &mut videos);
+    if videos.is_empty() {
+        return;
+    }
+
+    if !converter::is_ffmpeg_available() {
+        warn!("ffmpeg not found in PATH, skipping tagging of {} video file(s)", videos.len());
+        return;
+    }
+
+    // A null byte can't be passed as a command-line argument: fall back to the default separator
+    let separator = if separator.contains('\0') { "; " } else { separator };
+
+    for video in videos {
+        let filename = video.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let mut metadata = vec![
+            ("title", track_parser::extract_track_title(filename)),
+            ("album", base_metadata.album.clone()),
+            ("album_artist", base_metadata.album_artist.clone()),
+        ];
+        if !base_metadata.artists.is_empty() {
+            metadata.push(("artist", base_metadata.artists.join(separator)));
+        }
+        if !base_metadata.genre.is_empty() {
+            metadata.push(("genre", base_metadata.genre.join(separator)));
+        }
+        if let Some(date) = &base_metadata.date {
+            metadata.push(("date", date.clone()));
+        }
+        if let Some((disc, total)) = base_metadata.disc {
+            metadata.push(("disc", format!("{}/{}", disc, total)));
+        }
+
+        match converter::write_video_metadata(&video, &metadata, stats) {
+            Ok(_) => info!("Tagged video: {}", filename),
+            Err(e) => warn!("Failed to tag video {}: {}", filename, e),
+        }
+    }
+}
+
+fn collect_taggable_videos(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_taggable_videos(&path, out);
+        } else if path.extension().and_then(|e| e.to_str()).is_some_and(is_taggable_video_extension) {
+            out.push(path);
+        }
+    }
+}
+
+/// Measures every MP3 of the folder and writes its ReplayGain track gain, and the work's album
+/// gain. Failures are only logged: players simply don't level the work.
+fn write_replaygain(folder_path: &Path, config: &TaggerConfig) {
+    if !converter::is_ffmpeg_available() {
+        warn!("ffmpeg not found in PATH, skipping ReplayGain");
+        return;
+    }
+
+    let files: Vec<PathBuf> = match list_audio_files(folder_path) {
+        Ok(files) => files
+            .into_iter()
+            .filter(|f| f.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("mp3")))
+            .collect(),
+        Err(e) => {
+            warn!("Failed to list audio files for ReplayGain: {}", e);
+            return;
+        }
+    };
+
+    let mut measured = Vec::with_capacity(files.len());
+    for file in &files {
+        match converter::measure_loudness(file) {
+            Ok(loudness) => measured.push((file, loudness)),
+            Err(e) => warn!("Loudness measurement failed for {}: {}", file.display(), e),
+        }
+    }
+    if measured.is_empty() {
+        return;
+    }
+
+    let loudness: Vec<_> = measured.iter().map(|(_, l)| *l).collect();
+    let (album_lufs, album_peak) = converter::album_loudness(&loudness);
+    let album = (converter::REPLAYGAIN_REFERENCE_LUFS - album_lufs, album_peak);
+    for (file, track) in &measured {
+        let track_gain = (converter::REPLAYGAIN_REFERENCE_LUFS - track.integrated, track.peak);
+        if let Err(e) = id3_handler::write_replaygain_tags(file, track_gain, album, config) {
+            warn!("Failed to write ReplayGain tags to {}: {}", file.display(), e);
+        }
+    }
+    info!("ReplayGain: album gain {:.2} dB ({} file(s))", album.0, measured.len());
+}
+
+/// Runs the silence check on every audio file of the folder and records flagged files in
+/// `file_integrity` (clearing flags of files that now pass). Returns the names of the flagged
+/// files. Failures of the check itself are only logged: they must not block tagging.
+fn flag_silent_audio_files(conn: &Connection, rjcode: &RJCode, folder_path: &Path) -> Vec<String> {
+    if !converter::is_ffmpeg_available() {
+        warn!("ffmpeg not found in PATH, skipping silent audio detection");
+        return Vec::new();
+    }
+
+    let files = match list_audio_files(folder_path) {
+        Ok(files) => files,
+        Err(e) => {
+            warn!("Failed to list audio files for silence check: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut flagged = Vec::new();
+    for file in files {
+        let file_str = file.display().to_string();
+        let recorded = match converter::detect_silent_audio(&file) {
+            Ok(Some(reason)) => {
+                warn!("Damaged audio file {}: {}", file_str, reason);
+                flagged.push(file.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default());
+                integrity::record_file_integrity(
+                    conn,
+                    rjcode,
+                    &file,
+                    IntegrityStatus::Corrupt,
+                    Some(&format!("{}{}", integrity::SILENCE_CHECK_PREFIX, reason)),
+                )
+            }
+            Ok(None) => integrity::clear_silence_flag(conn, &file),
+            Err(e) => {
+                warn!("Silence check failed for {}: {}", file_str, e);
+                Ok(())
+            }
+        };
+        if let Err(e) = recorded {
+            warn!("Failed to record silence check result for {}: {}", file_str, e);
+        }
+    }
+    flagged
+}
+
+/// Keeps the file's tags from before hvtag first tagged it, for `--untag --restore`. Failures are
+/// only logged: they must not block tagging.
+fn record_original_tags(conn: &Connection, fld_id: i64, file_name: &str, raw_tag: &Option<Vec<u8>>) {
+    match tag_history::record_tag_snapshot(conn, fld_id, file_name, raw_tag.as_deref()) {
+        Ok(true) => debug!("Saved original tags of {}", file_name),
+        Ok(false) => {}
+        Err(e) => warn!("Failed to save original tags of {}: {}", file_name, e),
+    }
+}
+
+/// Record file processing in database: size and checksum after tagging, and the hash of the
+/// tagging settings used (`TaggerConfig::tag_config_hash`)
 fn record_file_processing(
     conn: &Connection,
     fld_id: i64,
     file_path: &Path,
+    file_size: i64,
+    checksum: Option<String>,
+    config_hash: &str,
 ) -> Result<(), HvtError> {
     let file_name = file_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
     let extension = file_path.extension().and_then(|e| e.to_str()).unwrap_or("");
-    let file_size = std::fs::metadata(file_path).map(|m| m.len() as i64).unwrap_or(0);
 
     conn.execute(
         "INSERT OR REPLACE INTO file_processing
          (fld_id, file_path, file_name, file_extension, file_size_bytes,
-          is_tagged, tag_date, last_processed, processing_status)
-         VALUES (?1, ?2, ?3, ?4, ?5, 1, datetime('now'), datetime('now'), 'completed')",
-        rusqlite::params![fld_id, file_path.display().to_string(), file_name, extension, file_size],
+          is_tagged, tag_date, last_processed, processing_status, checksum, checksum_date, tag_config_hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, 1, datetime('now'), datetime('now'), 'completed', ?6,
+                 CASE WHEN ?6 IS NULL THEN NULL ELSE datetime('now') END, ?7)",
+        rusqlite::params![
+            fld_id,
+            DbPathRef(file_path),
+            file_name,
+            extension,
+            file_size,
+            checksum,
+            config_hash
+        ],
     )?;
 
     Ok(())
 }
 
+/// List the audio files sitting at the root of a work folder (the tagger flattens works, so
+/// nested audio is not expected here).
+pub fn list_audio_files(folder_path: &Path) -> Result<Vec<PathBuf>, HvtError> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(folder_path)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        if AudioFormat::from_extension(extension) != AudioFormat::Unknown {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
 /// Get fld_id for a work
 fn get_fld_id(conn: &Connection, rjcode: &RJCode) -> Result<i64, HvtError> {
     let fld_id: i64 = conn.query_row(
@@ -409,3 +1137,12 @@ fn get_fld_id(conn: &Connection, rjcode: &RJCode) -> Result<i64, HvtError> {
     Ok(fld_id)
 }
 
+
+/// The work type or option code of a work listed in `skip`, if any (case-insensitive)
+fn skipped_work_type(conn: &Connection, rjcode: &RJCode, skip: &[String]) -> Result<Option<String>, HvtError> {
+    let Some((work_type, options, _)) = crate::database::queries::get_work_type(conn, rjcode)? else {
+        return Ok(None);
+    };
+    Ok(work_type.into_iter().chain(options)
+        .find(|code| skip.iter().any(|s| s.eq_ignore_ascii_case(code))))
+}
@@ -0,0 +1,212 @@
+use std::path::Path;
+
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::run_stats::RunStats;
+use crate::tagger::types::AudioMetadata;
+
+/// Name of the per-work info file (`[tagger] write_nfo`), plain UTF-8 text readable in any viewer.
+pub const NFO_FILE_NAME: &str = "info.nfo";
+
+/// Builds the NFO content: a header with the work's metadata, then the DLSite description.
+fn build_nfo(rjcode: &RJCode, section: Option<&str>, metadata: &AudioMetadata, description: &str) -> String {
+    let mut content = String::new();
+    content.push_str(&format!("Title:    {}\n", metadata.album));
+    content.push_str(&format!("Code:     {}\n", rjcode));
+    content.push_str(&format!("Circle:   {}\n", metadata.album_artist));
+    if !metadata.artists.is_empty() {
+        content.push_str(&format!("CV:       {}\n", metadata.artists.join(", ")));
+    }
+    if let Some(date) = &metadata.date {
+        content.push_str(&format!("Released: {}\n", date));
+    }
+    let tags = metadata.all_tags();
+    if !tags.is_empty() {
+        content.push_str(&format!("Tags:     {}\n", tags.join(", ")));
+    }
+    content.push_str(&format!("URL:      {}\n", rjcode.dlsite_url(section)));
+    content.push('\n');
+    content.push_str(description.trim());
+    content.push('\n');
+    content
+}
+
+/// Album file read by Jellyfin and Kodi from the work folder (`--export-nfo`).
+pub const ALBUM_NFO_FILE_NAME: &str = "album.nfo";
+
+/// Artist file read by Jellyfin and Kodi from the circle folder (`--export-nfo`).
+pub const ARTIST_NFO_FILE_NAME: &str = "artist.nfo";
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn push_element(content: &mut String, name: &str, value: &str) {
+    content.push_str(&format!("    <{name}>{}</{name}>\n", xml_escape(value)));
+}
+
+/// Builds `album.nfo`: the circle is the album artist (and the label), the CVs the artists, the
+/// tags the genres. `cover` is the cover file name when the folder has one.
+fn build_album_nfo(
+    rjcode: &RJCode,
+    section: Option<&str>,
+    metadata: &AudioMetadata,
+    description: Option<&str>,
+    cover: Option<&str>,
+) -> String {
+    let mut content = String::from(XML_HEADER);
+    content.push_str("<album>\n");
+    push_element(&mut content, "title", &metadata.album);
+    push_element(&mut content, "albumartist", &metadata.album_artist);
+    if metadata.artists.is_empty() {
+        push_element(&mut content, "artist", &metadata.album_artist);
+    }
+    for cv in &metadata.artists {
+        push_element(&mut content, "artist", cv);
+    }
+    for tag in &metadata.genre {
+        push_element(&mut content, "genre", tag);
+    }
+    for tag in &metadata.mood {
+        push_element(&mut content, "mood", tag);
+    }
+    for tag in metadata.category_frames.iter().flat_map(|(_, tags)| tags) {
+        push_element(&mut content, "style", tag);
+    }
+    // Stored as DLSite's regist_date ("2020-06-19 00:00:00")
+    if let Some(day) = metadata.date.as_deref().and_then(|d| d.split_whitespace().next()) {
+        let day = day.replace('/', "-");
+        push_element(&mut content, "releasedate", &day);
+        if let Some(year) = day.get(..4) {
+            push_element(&mut content, "year", year);
+        }
+    }
+    push_element(&mut content, "label", &metadata.album_artist);
+    if let Some(description) = description.map(str::trim).filter(|d| !d.is_empty()) {
+        // Kodi shows <review>, Jellyfin <plot>
+        push_element(&mut content, "review", description);
+        push_element(&mut content, "plot", description);
+    }
+    if let Some(cover) = cover {
+        push_element(&mut content, "thumb", cover);
+    }
+    push_element(&mut content, "comment", &rjcode.dlsite_url(section));
+    content.push_str("</album>\n");
+    content
+}
+
+/// Builds `artist.nfo` for a circle; its RG code tells apart circles with the same name.
+fn build_artist_nfo(name: &str, rgcode: Option<&str>) -> String {
+    let mut content = String::from(XML_HEADER);
+    content.push_str("<artist>\n");
+    push_element(&mut content, "name", name);
+    if let Some(rgcode) = rgcode {
+        push_element(&mut content, "disambiguation", rgcode);
+    }
+    content.push_str("</artist>\n");
+    content
+}
+
+/// (Over)writes the work's `album.nfo`. `section` is the DLSite section of the work, for its
+/// URL (see `RJCode::dlsite_url`).
+pub fn write_album_nfo(
+    folder_path: &Path,
+    rjcode: &RJCode,
+    section: Option<&str>,
+    metadata: &AudioMetadata,
+    description: Option<&str>,
+    cover: Option<&str>,
+    stats: &RunStats,
+) -> Result<(), HvtError> {
+    std::fs::write(
+        folder_path.join(ALBUM_NFO_FILE_NAME),
+        build_album_nfo(rjcode, section, metadata, description, cover),
+    )?;
+    stats.add_file_written();
+    Ok(())
+}
+
+/// (Over)writes the circle folder's `artist.nfo`.
+pub fn write_artist_nfo(circle_folder: &Path, name: &str, rgcode: Option<&str>, stats: &RunStats) -> Result<(), HvtError> {
+    std::fs::write(circle_folder.join(ARTIST_NFO_FILE_NAME), build_artist_nfo(name, rgcode))?;
+    stats.add_file_written();
+    Ok(())
+}
+
+/// (Over)writes the work's `info.nfo` next to its audio files (`section` as for `write_album_nfo`).
+pub fn write_nfo(
+    folder_path: &Path,
+    rjcode: &RJCode,
+    section: Option<&str>,
+    metadata: &AudioMetadata,
+    description: &str,
+    stats: &RunStats,
+) -> Result<(), HvtError> {
+    std::fs::write(folder_path.join(NFO_FILE_NAME), build_nfo(rjcode, section, metadata, description))?;
+    stats.add_file_written();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_nfo() {
+        let metadata = AudioMetadata {
+            title: "作品".to_string(),
+            artists: vec!["A".to_string(), "B".to_string()],
+            album: "作品".to_string(),
+            album_artist: "サークル".to_string(),
+            track_number: None,
+            disc: None,
+            genre: vec![],
+            date: None,
+            grouping: None,
+            comment: None,
+            mood: vec![],
+            category_frames: vec![],
+            source_frames: vec![],
+            rating: None,
+        };
+        let nfo = build_nfo(&RJCode::from_string_unchecked("RJ01000001".to_string()), None, &metadata, " 説明 \n");
+        assert!(nfo.starts_with("Title:    作品\nCode:     RJ01000001\nCircle:   サークル\nCV:       A, B\nURL:      https://www.dlsite.com/maniax/"));
+        assert!(!nfo.contains("Tags:"));
+        assert!(nfo.ends_with("\n\n説明\n"));
+    }
+
+    #[test]
+    fn test_build_album_nfo() {
+        let metadata = AudioMetadata {
+            title: "作品".to_string(),
+            artists: vec![],
+            album: "A & B".to_string(),
+            album_artist: "<Circle>".to_string(),
+            track_number: None,
+            disc: None,
+            genre: vec!["ASMR".to_string()],
+            date: Some("2020-06-19 00:00:00".to_string()),
+            grouping: None,
+            comment: None,
+            mood: vec![],
+            category_frames: vec![],
+            source_frames: vec![],
+            rating: None,
+        };
+        let nfo = build_album_nfo(&RJCode::from_string_unchecked("RJ01000001".to_string()), Some("home"), &metadata, None, Some("folder.jpeg"));
+        assert!(nfo.contains("<title>A &amp; B</title>"));
+        // No CV: the circle stands in as artist
+        assert!(nfo.contains("<artist>&lt;Circle&gt;</artist>"));
+        assert!(nfo.contains("<genre>ASMR</genre>"));
+        assert!(nfo.contains("<releasedate>2020-06-19</releasedate>\n    <year>2020</year>"));
+        assert!(nfo.contains("<thumb>folder.jpeg</thumb>"));
+        assert!(nfo.contains("<comment>https://www.dlsite.com/home/work/=/product_id/RJ01000001.html</comment>"));
+        assert!(!nfo.contains("<plot>"));
+    }
+}
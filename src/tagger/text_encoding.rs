@@ -0,0 +1,91 @@
+use std::ffi::OsStr;
+
+/// Decodes a companion text file (tracklist, readme...) that may be UTF-8 (with or without BOM),
+/// UTF-16 (with BOM) or Shift-JIS/cp932 (most Japanese works packaged on Windows).
+pub fn decode_text(bytes: &[u8]) -> String {
+    if let Some((encoding, bom_length)) = encoding_rs::Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return text.into_owned();
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => {
+            let (text, _, _) = encoding_rs::SHIFT_JIS.decode(bytes);
+            text.into_owned()
+        }
+    }
+}
+
+/// Detects a Shift-JIS file name that was extracted as mojibake and returns the repaired name.
+///
+/// Two cases are handled:
+/// - the raw Shift-JIS bytes were kept as-is (not valid UTF-8, typical of unzip on Unix)
+/// - the bytes were read as Windows-1252 by the archiver (`ƒgƒ‰ƒbƒN01.mp3` for `トラック01.mp3`)
+///
+/// Returns `None` when the name looks fine or can't be decoded without errors as Shift-JIS.
+pub fn repair_mojibake_name(name: &OsStr) -> Option<String> {
+    match name.to_str() {
+        Some(name) => repair_cp1252_mojibake(name),
+        None => decode_raw_shift_jis(name),
+    }
+}
+
+fn repair_cp1252_mojibake(name: &str) -> Option<String> {
+    if name.is_ascii() || contains_japanese(name) {
+        return None;
+    }
+
+    let (bytes, _, unmappable) = encoding_rs::WINDOWS_1252.encode(name);
+    if unmappable {
+        return None;
+    }
+    let (decoded, _, had_errors) = encoding_rs::SHIFT_JIS.decode(&bytes);
+    (!had_errors && contains_japanese(&decoded)).then(|| decoded.into_owned())
+}
+
+#[cfg(unix)]
+fn decode_raw_shift_jis(name: &OsStr) -> Option<String> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let (decoded, _, had_errors) = encoding_rs::SHIFT_JIS.decode(name.as_bytes());
+    (!had_errors).then(|| decoded.into_owned())
+}
+
+/// Windows file names are always valid UTF-16: raw Shift-JIS bytes can't end up there.
+#[cfg(not(unix))]
+fn decode_raw_shift_jis(_name: &OsStr) -> Option<String> {
+    None
+}
+
+/// Kana, kanji or full-width forms — the characters a decoded Japanese name should contain.
+fn contains_japanese(text: &str) -> bool {
+    text.chars().any(|c| matches!(c,
+        '\u{3040}'..='\u{30FF}'   // hiragana, katakana
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{FF00}'..='\u{FFEF}' // full-width / half-width forms
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_text_shift_jis_and_utf8() {
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode("01. 耳かき");
+        assert_eq!(decode_text(&sjis), "01. 耳かき");
+        assert_eq!(decode_text("01. 耳かき".as_bytes()), "01. 耳かき");
+        assert_eq!(decode_text(b"\xEF\xBB\xBF01. a"), "01. a");
+    }
+
+    #[test]
+    fn test_repair_mojibake_name() {
+        let (sjis, _, _) = encoding_rs::SHIFT_JIS.encode("トラック01.mp3");
+        let (garbled, _, _) = encoding_rs::WINDOWS_1252.decode(&sjis);
+        assert_eq!(repair_mojibake_name(OsStr::new(garbled.as_ref())).as_deref(), Some("トラック01.mp3"));
+
+        assert_eq!(repair_mojibake_name(OsStr::new("トラック01.mp3")), None);
+        assert_eq!(repair_mojibake_name(OsStr::new("track01.mp3")), None);
+        assert_eq!(repair_mojibake_name(OsStr::new("Café.mp3")), None);
+    }
+}
@@ -1,6 +1,8 @@
 use regex::Regex;
 use unicode_normalization::UnicodeNormalization;
 
+use crate::errors::HvtError;
+
 /// Track parsing preference stored per work in database
 #[derive(Debug, Clone)]
 pub struct TrackParsingPreference {
@@ -11,6 +13,87 @@ pub struct TrackParsingPreference {
     /// Regex pattern to strip from the filename before looking for the first number.
     /// Supports non-greedy quantifiers (e.g. `s.*?_` strips `s19_` from `s19_01_track`).
     pub strip_prefix_pattern: Option<String>,
+    /// User regex whose first capture group is the track number (e.g. `^(\d+)_`).
+    pub custom_regex: Option<String>,
+}
+
+impl TrackParsingPreference {
+    /// Builds a strategy from a config/CLI spec: `asian_fullwidth`, `asian_brackets`,
+    /// `asian_kanji_episode`, `first_number`, `custom_delimiter:<delimiter>`,
+    /// `strip_prefix:<regex>` or `custom_regex:<regex>`.
+    pub fn from_spec(spec: &str) -> Result<Self, HvtError> {
+        let (name, arg) = match spec.split_once(':') {
+            Some((name, arg)) => (name.trim(), Some(arg)),
+            None => (spec.trim(), None),
+        };
+        let mut pref = TrackParsingPreference {
+            strategy_name: name.to_string(),
+            custom_delimiter: None,
+            use_asian_conversion: false,
+            asian_format_type: None,
+            strip_prefix_pattern: None,
+            custom_regex: None,
+        };
+
+        match (name, arg) {
+            ("asian_fullwidth", None) => {
+                pref.use_asian_conversion = true;
+                pref.asian_format_type = Some("fullwidth".to_string());
+            }
+            ("asian_brackets", None) => {
+                pref.use_asian_conversion = true;
+                pref.asian_format_type = Some("asian_brackets".to_string());
+            }
+            ("asian_kanji_episode", None) => {
+                pref.use_asian_conversion = true;
+                pref.asian_format_type = Some("kanji_episode".to_string());
+            }
+            ("first_number", None) => {}
+            ("custom_delimiter", Some(delimiter)) if !delimiter.is_empty() => {
+                pref.custom_delimiter = Some(delimiter.to_string());
+            }
+            ("custom_regex", Some(pattern)) => {
+                validate_track_regex(pattern)
+                    .map_err(|e| HvtError::Parse(format!("Invalid custom_regex '{}': {}", pattern, e)))?;
+                pref.custom_regex = Some(pattern.to_string());
+            }
+            ("strip_prefix", Some(pattern)) => {
+                Regex::new(pattern)
+                    .map_err(|e| HvtError::Parse(format!("Invalid strip_prefix regex '{}': {}", pattern, e)))?;
+                pref.strip_prefix_pattern = Some(pattern.to_string());
+            }
+            _ => {
+                return Err(HvtError::Parse(format!(
+                    "Unknown track parsing strategy '{}' (expected asian_fullwidth, asian_brackets, \
+                     asian_kanji_episode, first_number, custom_delimiter:<delimiter>, strip_prefix:<regex> \
+                     or custom_regex:<regex>)",
+                    spec
+                )))
+            }
+        }
+        Ok(pref)
+    }
+}
+
+/// Checks a user track-number regex: it must compile and have exactly one capture group.
+pub fn validate_track_regex(pattern: &str) -> Result<(), String> {
+    let re = Regex::new(pattern).map_err(|e| e.to_string())?;
+    match re.captures_len() - 1 {
+        1 => Ok(()),
+        n => Err(format!("expected one capture group for the track number, found {}", n)),
+    }
+}
+
+/// First strategy of `presets` that numbers every file on its own (no automatic fallback) without
+/// giving two files the same number.
+pub fn first_matching_preset<'a>(
+    filenames: &[String],
+    presets: &'a [TrackParsingPreference],
+) -> Option<&'a TrackParsingPreference> {
+    presets.iter().find(|pref| {
+        let numbers: Vec<Option<u32>> = filenames.iter().map(|f| try_strategy(f, pref)).collect();
+        numbers.iter().all(|n| n.is_some()) && find_duplicate_track_numbers(&numbers).is_empty()
+    })
 }
 
 /// Converts full-width numbers and characters to ASCII using NFKC normalization
@@ -129,6 +212,11 @@ fn try_strategy(filename: &str, pref: &TrackParsingPreference) -> Option<u32> {
             }
             None
         }
+        "custom_regex" => {
+            let re = Regex::new(pref.custom_regex.as_deref()?).ok()?;
+            let num: u32 = re.captures(name_without_ext)?.get(1)?.as_str().parse().ok()?;
+            (num > 0 && num < 1000).then_some(num)
+        }
         "strip_prefix" => {
             if let Some(ref pattern_str) = pref.strip_prefix_pattern {
                 if let Ok(re) = Regex::new(pattern_str) {
@@ -295,6 +383,21 @@ pub fn find_duplicate_track_numbers(numbers: &[Option<u32>]) -> Vec<u32> {
     dups
 }
 
+/// Disc number of a subfolder name, for works tagged in place: "CD1", "Disc 2", "DISC_03",
+/// "Part.2", "vol1", "その2" or a bare number. Full-width digits are accepted. None for other
+/// names ("mp3", "SE無し").
+pub fn parse_disc_number(folder_name: &str) -> Option<u32> {
+    let name: String = folder_name.nfkc().collect::<String>().to_lowercase();
+    let name = name.trim();
+    let number = if let Ok(num) = name.parse::<u32>() {
+        Some(num)
+    } else {
+        let pattern = Regex::new(r"(?:^|[^a-z])(?:cd|dis[ck]|part|vol|その|ディスク)\.?[\s\-_]*(\d{1,2})(?:\D|$)").ok()?;
+        pattern.captures(name).and_then(|caps| caps[1].parse().ok())
+    };
+    number.filter(|n| (1..100).contains(n))
+}
+
 /// Extracts a clean track title from a filename
 /// Removes: extension, track number prefixes, common separators
 /// Example: "01 - My Track Title.mp3" → "My Track Title"
@@ -419,4 +522,39 @@ mod tests {
         assert_eq!(find_duplicate_track_numbers(&[Some(2), None, Some(1), Some(1), Some(2)]), vec![1, 2]);
         assert_eq!(find_duplicate_track_numbers(&[None, None]), Vec::<u32>::new());
     }
+
+    #[test]
+    fn test_parse_disc_number() {
+        assert_eq!(parse_disc_number("CD1"), Some(1));
+        assert_eq!(parse_disc_number("Disc 2"), Some(2));
+        assert_eq!(parse_disc_number("DISC_03"), Some(3));
+        assert_eq!(parse_disc_number("【Part.2】本編"), Some(2));
+        assert_eq!(parse_disc_number("その２"), Some(2));
+        assert_eq!(parse_disc_number("02"), Some(2));
+        assert_eq!(parse_disc_number("mp3"), None);
+        assert_eq!(parse_disc_number("SE無し"), None);
+        assert_eq!(parse_disc_number("abcd1"), None);
+    }
+
+    #[test]
+    fn test_strategy_spec_and_presets() {
+        assert_eq!(TrackParsingPreference::from_spec("custom_delimiter:_").unwrap().custom_delimiter.as_deref(), Some("_"));
+        assert!(TrackParsingPreference::from_spec("strip_prefix:(").is_err());
+        assert!(TrackParsingPreference::from_spec("custom_delimiter").is_err());
+        assert!(TrackParsingPreference::from_spec("unknown").is_err());
+        assert!(TrackParsingPreference::from_spec("custom_regex:^(\\d+)_(\\d+)").is_err());
+
+        let pref = TrackParsingPreference::from_spec("custom_regex:_t(\\d+)$").unwrap();
+        assert_eq!(try_strategy("s19_t07.mp3", &pref), Some(7));
+        assert_eq!(try_strategy("s19_bonus.mp3", &pref), None);
+
+        let presets = vec![
+            TrackParsingPreference::from_spec("asian_kanji_episode").unwrap(),
+            TrackParsingPreference::from_spec("asian_brackets").unwrap(),
+        ];
+        let files = vec!["【01】はじめに.mp3".to_string(), "【02】本編.mp3".to_string()];
+        assert_eq!(first_matching_preset(&files, &presets).map(|p| p.strategy_name.as_str()), Some("asian_brackets"));
+        let duplicated = vec!["【01】a.mp3".to_string(), "【01】b.mp3".to_string()];
+        assert!(first_matching_preset(&duplicated, &presets).is_none());
+    }
 }
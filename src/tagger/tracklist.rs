@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use tracing::debug;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::errors::HvtError;
+use crate::tagger::text_encoding::decode_text;
+
+/// Max folder depth searched for a tracklist file (they often sit next to the audio in a
+/// subfolder that normalization left behind, or in a "readme"-style folder).
+const MAX_SEARCH_DEPTH: u32 = 3;
+
+/// Finds a bundled tracklist text file (`トラックリスト.txt`, `tracklist.txt`, `曲目.txt`...)
+/// anywhere in the work folder, up to `MAX_SEARCH_DEPTH` levels deep.
+pub fn find_tracklist_file(folder_path: &Path) -> Option<PathBuf> {
+    find_tracklist_file_at_depth(folder_path, MAX_SEARCH_DEPTH)
+}
+
+fn find_tracklist_file_at_depth(dir: &Path, depth: u32) -> Option<PathBuf> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir).ok()?
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+
+    let found = entries.iter().find(|path| path.is_file() && is_tracklist_file_name(path));
+    if found.is_some() {
+        return found.cloned();
+    }
+
+    if depth == 0 {
+        return None;
+    }
+    entries.iter()
+        .filter(|path| path.is_dir())
+        .find_map(|path| find_tracklist_file_at_depth(path, depth - 1))
+}
+
+fn is_tracklist_file_name(path: &Path) -> bool {
+    let is_txt = path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("txt"));
+    let name = path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .nfkc()
+        .collect::<String>()
+        .to_lowercase();
+
+    is_txt && ["トラックリスト", "tracklist", "track list", "track_list", "曲目"]
+        .iter()
+        .any(|keyword| name.contains(keyword))
+}
+
+/// Reads and parses a tracklist file into track number → title.
+pub fn read_tracklist(file_path: &Path) -> Result<BTreeMap<u32, String>, HvtError> {
+    let bytes = std::fs::read(file_path)?;
+    let content = decode_text(&bytes);
+    let titles = parse_tracklist(&content);
+    debug!("Tracklist {}: {} entrie(s)", file_path.display(), titles.len());
+    Ok(titles)
+}
+
+/// Parses tracklist lines such as `01. タイトル`, `トラック1：「タイトル」`, `【02】タイトル`,
+/// `Track03 - Title (12:34)` into track number → title. Lines that don't start with a track
+/// number are ignored; when a number appears twice the first entry wins.
+fn parse_tracklist(content: &str) -> BTreeMap<u32, String> {
+    // NFKC first: full-width digits/punctuation (０１．：) become ASCII
+    let line_regex = Regex::new(
+        r"^\s*(?:(?i:track|tr)\s*|トラック\s*|第\s*)?[\[【(〔]?\s*(\d{1,3})\s*[\]】)〕]?\s*[話章]?[\s.:、_\-–—・]*(\D.*?)\s*$"
+    ).unwrap();
+    let duration_regex = Regex::new(r"\s*[(\[]?\d{1,2}:\d{2}(?::\d{2})?[)\]]?$").unwrap();
+
+    let mut titles = BTreeMap::new();
+    for line in content.lines() {
+        let line: String = line.nfkc().collect();
+        let Some(caps) = line_regex.captures(&line) else {
+            continue;
+        };
+        let Ok(number) = caps[1].parse::<u32>() else {
+            continue;
+        };
+
+        let title = duration_regex.replace(&caps[2], "");
+        let title = title
+            .trim()
+            .trim_start_matches(['「', '『'])
+            .trim_end_matches(['」', '』'])
+            .trim();
+        if !title.is_empty() {
+            titles.entry(number).or_insert_with(|| title.to_string());
+        }
+    }
+    titles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tracklist_common_formats() {
+        let content = "トラックリスト\n\
+            01. 導入\n\
+            トラック２：「耳かき」\n\
+            【03】添い寝 (12:34)\n\
+            Track04 - Ending\n\
+            CV：誰か\n\
+            2024年発売\n";
+        let titles = parse_tracklist(content);
+        assert_eq!(titles.get(&1).map(String::as_str), Some("導入"));
+        assert_eq!(titles.get(&2).map(String::as_str), Some("耳かき"));
+        assert_eq!(titles.get(&3).map(String::as_str), Some("添い寝"));
+        assert_eq!(titles.get(&4).map(String::as_str), Some("Ending"));
+        assert_eq!(titles.len(), 4);
+    }
+}
@@ -1,6 +1,9 @@
 use std::fmt::Display;
 
+use crate::config::{ArtistFrameStyle, CvNameStyle, Id3Version, TitleLanguage};
 use crate::dlsite::types::DlSiteProductIdResult;
+use crate::tagger::cover_art::CoverSettings;
+use crate::tagger::track_parser::TrackParsingPreference;
 
 #[derive(Debug)]
 pub enum AgeCategory {
@@ -37,6 +40,34 @@ impl Display for AgeCategory {
     }
 }
 
+/// DLSite ratings of a work: number of ratings and of written reviews, and how many ratings
+/// gave 1 to 5 stars (`distribution[0]` is 1 star)
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct ReviewCounts {
+    pub rate_count: u32,
+    pub review_count: u32,
+    pub distribution: [u32; 5],
+}
+
+impl ReviewCounts {
+    /// From the `rate_count`, `review_count` and `rate_count_detail` fields of a DLSite or
+    /// asmr.one response; `None` when it has no rating count
+    pub fn from_json(work: &serde_json::Value) -> Option<Self> {
+        let count = |value: &serde_json::Value| value.as_u64().or_else(|| value.as_str().and_then(|s| s.parse().ok()));
+        let mut distribution = [0; 5];
+        for entry in work["rate_count_detail"].as_array().into_iter().flatten() {
+            if let (Some(point @ 1..=5), Some(n)) = (count(&entry["review_point"]), count(&entry["count"])) {
+                distribution[point as usize - 1] = n as u32;
+            }
+        }
+        Some(ReviewCounts {
+            rate_count: count(&work["rate_count"])? as u32,
+            review_count: count(&work["review_count"]).unwrap_or(0) as u32,
+            distribution,
+        })
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct WorkDetails {
     pub rjcode: String,
@@ -46,6 +77,43 @@ pub struct WorkDetails {
     pub name: String,
     pub image_link: String,
     pub release_date: String,
+    /// (relation, workno) pairs from translation_info: 'original', 'parent' or 'child'
+    pub related_works: Vec<(String, String)>,
+    /// Current price (yen), download and wishlist counts, kept in sales_history at each fetch
+    pub price: Option<u32>,
+    pub dl_count: Option<u64>,
+    pub wishlist_count: Option<u64>,
+    /// DLSite rankings reached: (term, category, rank, rank_date)
+    pub rankings: Vec<(String, String, u32, String)>,
+    pub reviews: Option<ReviewCounts>,
+    /// DLSite work type ("SOU" voice/ASMR, "MOV" video, "MUS" music...)
+    pub work_type: Option<String>,
+    /// DLSite option codes of the work ("JPN", "SND", "AIG"...)
+    pub options: Vec<String>,
+    /// Circle-defined genres, shown on DLSite next to the official ones
+    pub custom_genres: Vec<String>,
+}
+
+/// Flattens translation_info into (relation, workno) pairs, dropping references to the work itself
+/// (the API lists an original work as its own original_workno).
+pub fn translation_relations(
+    rjcode: &str,
+    original_workno: Option<&str>,
+    parent_workno: Option<&str>,
+    child_worknos: &[String],
+) -> Vec<(String, String)> {
+    let mut relations = Vec::new();
+    if let Some(original) = original_workno {
+        relations.push(("original".to_string(), original.to_string()));
+    }
+    if let Some(parent) = parent_workno {
+        relations.push(("parent".to_string(), parent.to_string()));
+    }
+    for child in child_worknos {
+        relations.push(("child".to_string(), child.clone()));
+    }
+    relations.retain(|(_, workno)| !workno.is_empty() && !workno.eq_ignore_ascii_case(rjcode));
+    relations
 }
 
 impl WorkDetails {
@@ -65,6 +133,24 @@ impl WorkDetails {
             name: p.work_name,
             image_link,
             release_date: p.regist_date,
+            related_works: translation_relations(
+                rjcode,
+                p.translation_info.original_workno.as_deref(),
+                p.translation_info.parent_workno.as_deref(),
+                &p.translation_info.child_worknos,
+            ),
+            price: Some(p.price),
+            dl_count: Some(p.dl_count as u64),
+            wishlist_count: Some(p.wishlist_count as u64),
+            rankings: p.rank.into_iter().map(|r| (r.term, r.category, r.rank, r.rank_date)).collect(),
+            reviews: Some(ReviewCounts {
+                rate_count: p.rate_count,
+                review_count: p.review_count,
+                distribution: std::array::from_fn(|i| {
+                    p.rate_count_detail.iter().find(|e| e.review_point == i as u32 + 1).map_or(0, |e| e.count)
+                }),
+            }),
+            ..Default::default()
         }
     }
 }
@@ -83,29 +169,147 @@ pub struct Work {
 
 // Audio tagging types for Step 3
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AudioMetadata {
     pub title: String,              // work name
     pub artists: Vec<String>,       // voice actors (CVs) - can be multiple
     pub album: String,              // work name
     pub album_artist: String,       // circle name
     pub track_number: Option<u32>,  // parsed from filename
-    pub genre: Vec<String>,         // dlsite tags
+    pub disc: Option<(u32, u32)>,   // (disc, total discs) of a work group, TPOS
+    pub genre: Vec<String>,         // dlsite tags (uncategorized or in a genre category)
+    pub mood: Vec<String>,          // tags of mood categories, TMOO
+    pub category_frames: Vec<(String, Vec<String>)>, // TXXX (description, tags) of the other categories; empty = frame cleared
     pub date: Option<String>,       // release_date
+    pub grouping: Option<String>,   // series (original work) name, TIT1
+    pub comment: Option<String>,    // work description, COMM
+    pub source_frames: Vec<(String, String)>, // TXXX (description, value): DLSITE_ID, DLSITE_URL, CIRCLE_CODE, RATING, ITUNESADVISORY
+    pub rating: Option<f32>,        // DLSite stars (0-5), POPM
     // Note: Cover art is NOT in AudioMetadata - it's saved separately as folder.jpeg
 }
 
+impl AudioMetadata {
+    /// Every tag of the work, whatever frame its category writes it to
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.genre.iter()
+            .chain(&self.mood)
+            .chain(self.category_frames.iter().flat_map(|(_, values)| values))
+            .cloned()
+            .collect();
+        tags.sort();
+        tags
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TaggerConfig {
     pub convert_to_mp3: bool,
     pub target_bitrate: u32,
     pub download_cover: bool,
+    /// Retries and skip-listing of that cover download (`[images]`); the cover keeps its
+    /// original size
+    pub cover_settings: CoverSettings,
     pub tag_separator: String,
     pub force_retag: bool,
-    /// Whether to write the `.tagged` marker file after processing. Set to `false` for one-shot
-    /// test runs (`--tag <folder>`) so a later real `--full` import on the same folder isn't
-    /// mistakenly skipped because of a marker left behind by the test.
+    /// Whether to also write the legacy `.tagged` marker file after processing (`[tagger]
+    /// write_tagged_markers`); the tagged state itself is kept in `file_processing`. Always
+    /// `false` for one-shot test runs (`--tag <folder>`), which leave nothing behind.
     pub write_tagged_marker: bool,
+    /// Whether to write a `checksums.sha256` manifest into the folder after tagging
+    /// (`[integrity] write_checksum_manifest`), re-verified later by `--fsck`.
+    pub write_checksum_manifest: bool,
+    /// Whether to also write work metadata into MP4/M4V bonus videos (`[video] tag_video_files`).
+    pub tag_video_files: bool,
+    /// Whether to check audio files for empty/silent tracks after tagging (`[integrity]
+    /// detect_silent_audio`); a work with flagged files is not marked as tagged.
+    pub detect_silent_audio: bool,
+    /// Which voice actor name goes into the artist tag (`[tagger] cv_name_style`).
+    pub cv_name_style: CvNameStyle,
+    /// Which work title goes into the title/album tags (`[tagger] title_language`).
+    pub title_language: TitleLanguage,
+    /// Whether to write the series (original/parent work) into the grouping frame
+    /// (`[tagger] write_series_tag`).
+    pub write_series_tag: bool,
+    /// Whether to write the work description into the comment frame
+    /// (`[tagger] write_description_comment`).
+    pub write_description_comment: bool,
+    /// Whether to write an `info.nfo` with the metadata and description into the folder
+    /// (`[tagger] write_nfo`).
+    pub write_nfo: bool,
+    /// Whether to write the RJ code, DLSite URL and circle code as TXXX frames
+    /// (`[tagger] write_source_frames`).
+    pub write_source_frames: bool,
+    /// Whether to write the DLSite star rating into a POPM frame (`[tagger] write_rating`).
+    pub write_rating: bool,
+    /// Whether to write the DLSite age rating into a TXXX:RATING frame (`[tagger] write_age_rating`).
+    pub write_age_rating: bool,
+    /// Whether to write the explicit-content flag into a TXXX:ITUNESADVISORY frame
+    /// (`[tagger] write_explicit_flag`).
+    pub write_explicit_flag: bool,
+    /// How multiple CVs are written to the artist frames (`[tagger] artist_frame_style`).
+    pub artist_frame_style: ArtistFrameStyle,
+    /// ID3v2 version written to MP3 files (`[tagger] id3_version`).
+    pub id3_version: Id3Version,
+    /// Whether to remove existing ID3v1 tags when writing (`[tagger] strip_id3v1`).
+    pub strip_id3v1: bool,
+    /// Whether to move audio files from subfolders up to the work root before tagging
+    /// (`[tagger] normalize_folders`, or `--no-normalize`). Off, files are tagged in place, with
+    /// disc numbers from their subfolder names.
+    pub normalize_folders: bool,
+    /// With `normalize_folders`, also move the non-audio files of those subfolders into
+    /// `extras/` (`[tagger] collect_extras`, or `--collect-extras`).
+    pub collect_extras: bool,
+    /// Split a single-file work described by a cue sheet, or an `.m4b`/`.m4a` audiobook with
+    /// chapters, into one MP3 per track with ffmpeg (`[tagger] split_cue`, or `--split-cue`).
+    /// Otherwise its MP3 gets the tracks as ID3 chapters.
+    pub split_cue: bool,
+    /// DLSite work types or option codes of the works left untagged (`[tagger] skip_work_types`)
+    pub skip_work_types: Vec<String>,
+    /// Whether to measure loudness and write ReplayGain tags after tagging (`[tagger] replaygain`).
+    pub replaygain: bool,
+    /// Files of a work tagged at the same time, on blocking threads (`[tagger] io_parallelism`).
+    pub io_parallelism: usize,
+    /// Track numbering strategies tried in order on works without a saved preference
+    /// (`[track_parsing] strategies`, or `--parsing-strategy`).
+    pub parsing_presets: Vec<TrackParsingPreference>,
+    /// Whether to ask interactively when track numbering fails (`[track_parsing] prompt`).
+    pub prompt_track_parsing: bool,
+    /// Silence split offered for single-file works: level (dB) below which audio counts as
+    /// silence (`[track_parsing] silence_threshold_db`)
+    pub silence_threshold_db: f64,
+    /// Silence split: shortest pause (seconds) that starts a new track
+    /// (`[track_parsing] silence_min_duration`)
+    pub silence_min_duration: f64,
+    /// Whether to use a bundled tracklist's titles without asking (`[track_parsing] accept_tracklist`).
+    pub accept_tracklist: bool,
+    /// Whether track numbers already in the files' tags win over filename parsing
+    /// (`[track_parsing] keep_existing_numbers`).
+    pub keep_existing_track_numbers: bool,
+}
+
+impl TaggerConfig {
+    /// Short hash of the settings that change what is written into the files (separator, name
+    /// styles, optional frames, ID3 version), stored with each tagged file: files tagged with
+    /// other settings are re-tagged
+    pub fn tag_config_hash(&self) -> String {
+        use sha2::{Digest, Sha256};
+        let settings = format!(
+            "{:?}|{:?}|{:?}|{:?}|{}|{}|{}|{}|{}|{}|{:?}|{}",
+            self.tag_separator,
+            self.cv_name_style,
+            self.title_language,
+            self.artist_frame_style,
+            self.write_series_tag,
+            self.write_description_comment,
+            self.write_source_frames,
+            self.write_rating,
+            self.write_age_rating,
+            self.write_explicit_flag,
+            self.id3_version,
+            self.strip_id3v1,
+        );
+        Sha256::digest(settings.as_bytes()).iter().take(8).map(|b| format!("{:02x}", b)).collect()
+    }
 }
 
 impl Default for TaggerConfig {
@@ -115,8 +319,36 @@ impl Default for TaggerConfig {
             target_bitrate: 320,
             tag_separator: "; ".to_string(),
             download_cover: true,
+            cover_settings: CoverSettings::default(),
             force_retag: false,
-            write_tagged_marker: true,
+            write_tagged_marker: false,
+            write_checksum_manifest: false,
+            tag_video_files: false,
+            detect_silent_audio: false,
+            cv_name_style: CvNameStyle::default(),
+            title_language: TitleLanguage::default(),
+            write_series_tag: false,
+            write_description_comment: false,
+            write_nfo: false,
+            write_source_frames: false,
+            write_rating: false,
+            write_age_rating: false,
+            write_explicit_flag: false,
+            artist_frame_style: ArtistFrameStyle::default(),
+            id3_version: Id3Version::default(),
+            strip_id3v1: false,
+            normalize_folders: true,
+            collect_extras: false,
+            split_cue: false,
+            skip_work_types: Vec::new(),
+            replaygain: false,
+            io_parallelism: 4,
+            parsing_presets: Vec::new(),
+            prompt_track_parsing: true,
+            silence_threshold_db: -50.0,
+            silence_min_duration: 2.0,
+            accept_tracklist: false,
+            keep_existing_track_numbers: true,
         }
     }
 }
@@ -127,6 +359,8 @@ pub enum AudioFormat {
     Flac,
     Wav,
     Ogg,
+    /// AAC in an MP4 container (`.m4a`, `.m4b` audiobooks)
+    M4a,
     Unknown,
 }
 
@@ -137,7 +371,63 @@ impl AudioFormat {
             "flac" => AudioFormat::Flac,
             "wav" => AudioFormat::Wav,
             "ogg" => AudioFormat::Ogg,
+            "m4a" | "m4b" => AudioFormat::M4a,
             _ => AudioFormat::Unknown,
         }
     }
+
+    /// Audio that is only tagged once converted to MP3 (`--convert`)
+    pub fn needs_conversion(&self) -> bool {
+        matches!(self, AudioFormat::Flac | AudioFormat::Wav | AudioFormat::Ogg | AudioFormat::M4a)
+    }
+}
+
+/// Whether a file extension is audio hvtag handles (MP3, or a format it converts to MP3)
+pub fn is_audio_extension(ext: &str) -> bool {
+    AudioFormat::from_extension(ext) != AudioFormat::Unknown
+}
+
+/// Video container extensions recognized in work folders (bonus videos shipped with some works).
+/// Never treated as audio: not flattened, converted, verified or ID3-tagged.
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "m4v", "mkv", "webm", "avi", "mov", "wmv"];
+
+pub fn is_video_extension(ext: &str) -> bool {
+    VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str())
+}
+
+/// Video containers whose metadata hvtag can write (`[video] tag_video_files`), via an ffmpeg
+/// stream copy through the MP4 muxer.
+pub fn is_taggable_video_extension(ext: &str) -> bool {
+    matches!(ext.to_lowercase().as_str(), "mp4" | "m4v")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_translation_relations_skips_self() {
+        let children = vec!["RJ01000002".to_string(), "RJ01000001".to_string()];
+        let relations = translation_relations("RJ01000001", Some("RJ01000001"), None, &children);
+        assert_eq!(relations, vec![("child".to_string(), "RJ01000002".to_string())]);
+
+        let relations = translation_relations("RJ01000002", Some("RJ01000001"), Some("RJ01000001"), &[]);
+        assert_eq!(relations.len(), 2);
+    }
+
+    #[test]
+    fn test_review_counts_from_json() {
+        let work = serde_json::json!({
+            "rate_count": 120,
+            "review_count": "3",
+            "rate_count_detail": [
+                {"review_point": 5, "count": 100, "ratio": 83},
+                {"review_point": 4, "count": 20, "ratio": 17},
+                {"review_point": 0, "count": 9, "ratio": 0}
+            ]
+        });
+        let reviews = ReviewCounts::from_json(&work).unwrap();
+        assert_eq!(reviews, ReviewCounts { rate_count: 120, review_count: 3, distribution: [0, 0, 0, 20, 100] });
+        assert_eq!(ReviewCounts::from_json(&serde_json::json!({})), None);
+    }
 }
@@ -0,0 +1,40 @@
+use time::format_description::well_known::Rfc3339;
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+// Dates written outside the database (log files, backups, run reports) are UTC, like the log
+// lines and every date stored in the database.
+
+/// The current time, UTC
+pub fn now() -> OffsetDateTime {
+    OffsetDateTime::now_utc()
+}
+
+/// `YYYY-MM-DD`
+pub fn date(at: OffsetDateTime) -> String {
+    at.format(format_description!("[year]-[month]-[day]")).unwrap_or_default()
+}
+
+/// `YYYYMMDD-HHMMSS`, for file names sorted by date
+pub fn file_stamp(at: OffsetDateTime) -> String {
+    at.format(format_description!("[year][month][day]-[hour][minute][second]")).unwrap_or_default()
+}
+
+/// RFC 3339 to the second (`2026-10-16T09:30:00Z`)
+pub fn rfc3339(at: OffsetDateTime) -> String {
+    at.replace_nanosecond(0).unwrap_or(at).format(&Rfc3339).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn test_formats() {
+        let at = datetime!(2000-02-29 09:05:07.250 UTC);
+        assert_eq!(date(at), "2000-02-29");
+        assert_eq!(file_stamp(at), "20000229-090507");
+        assert_eq!(rfc3339(at), "2000-02-29T09:05:07Z");
+    }
+}
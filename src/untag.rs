@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::database::{queries, tag_history};
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::run_stats::{RunStats, WorkOutcome};
+use crate::tagger::{self, id3_handler};
+
+/// `--untag <rjcode>`: remove the ID3 tags hvtag wrote to a work's MP3 files, or with `restore`
+/// put back the tags the files had before hvtag first tagged them (snapshots in
+/// `metadata_history`). Files tagged before snapshots existed have none and are left as they are
+/// when restoring. The work's files are recorded as untagged (and a legacy `.tagged` marker is
+/// removed) so the next run tags the work again.
+pub fn run_untag_workflow(conn: &Connection, rjcode: &str, restore: bool, stats: &RunStats) -> Result<(), HvtError> {
+    let rjcode = RJCode::new(rjcode.to_string())?;
+    let folder_path = queries::get_work_path(conn, &rjcode)?
+        .ok_or_else(|| HvtError::Generic(format!("{} not found in the database", rjcode)))?;
+    let folder = Path::new(&folder_path);
+
+    info!("=== UNTAG {}{} ===", rjcode, if restore { " (restore original tags)" } else { "" });
+
+    let snapshots: HashMap<String, Option<Vec<u8>>> = if restore {
+        tag_history::get_tag_snapshots(conn, &rjcode)?.into_iter().collect()
+    } else {
+        HashMap::new()
+    };
+
+    let mut changed = 0usize;
+    let mut without_snapshot = 0usize;
+    let mut failed = 0usize;
+    for file in tagger::list_audio_files(folder)? {
+        let is_mp3 = file.extension().and_then(|e| e.to_str()).is_some_and(|e| e.eq_ignore_ascii_case("mp3"));
+        if !is_mp3 {
+            continue;
+        }
+        let file_name = file.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+
+        let result = if restore {
+            match snapshots.get(&file_name) {
+                Some(raw_tag) => id3_handler::restore_raw_tag(&file, raw_tag.as_deref()),
+                None => {
+                    warn!("No original tags recorded for {}, leaving it as is", file_name);
+                    without_snapshot += 1;
+                    continue;
+                }
+            }
+        } else {
+            id3_handler::remove_tags(&file)
+        };
+
+        match result {
+            Ok(()) => {
+                changed += 1;
+                stats.add_file_written();
+            }
+            Err(e) => {
+                warn!("{}: {}", file_name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    let marker = folder.join(crate::tagger::TAGGED_MARKER_NAME);
+    if marker.exists() {
+        std::fs::remove_file(&marker)?;
+    }
+    queries::reset_work_tagged_files(conn, &rjcode)?;
+
+    let outcome = if failed > 0 { WorkOutcome::Failed } else { WorkOutcome::Processed };
+    stats.record_work(conn, &rjcode, "untag", outcome, None);
+
+    info!(
+        "=== UNTAG COMPLETE: {} file(s) {} | No snapshot: {} | Failed: {} ===",
+        changed, if restore { "restored" } else { "untagged" }, without_snapshot, failed
+    );
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+use rusqlite::Connection;
+use tracing::{info, warn};
+
+use crate::database::integrity::{self, IntegrityStatus};
+use crate::database::queries;
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::tagger::{self, checksum, converter};
+
+/// `--verify`: decode every audio file of every tagged work with ffmpeg to detect truncated or
+/// corrupt audio. Each file's result is stored in `file_integrity` (latest check wins) and a
+/// summary listing the problem files is printed at the end. Read-only on the files themselves.
+///
+/// With `--checksums`, files are instead re-hashed and compared against the SHA-256 recorded in
+/// `file_processing` when they were tagged — much faster than decoding, and catches any silent
+/// modification (bit-rot on aging drives / network storage), not just undecodable audio.
+pub fn run_verify_workflow(conn: &Connection, checksums: bool) -> Result<(), HvtError> {
+    if !checksums && !converter::is_ffmpeg_available() {
+        return Err(HvtError::AudioConversion(
+            "ffmpeg not found in PATH (required for --verify).".to_string()
+        ));
+    }
+
+    let works = queries::get_tagged_works_with_paths(conn)?;
+    if works.is_empty() {
+        info!("No tagged works in database");
+        return Ok(());
+    }
+
+    let mode = if checksums { "checksums" } else { "decode" };
+    info!("=== VERIFY ({}): {} work(s) ===", mode, works.len());
+
+    let pb = crate::create_progress_bar(works.len() as u64);
+    let mut checked = 0usize;
+    let mut problems: Vec<(RJCode, PathBuf, IntegrityStatus, String)> = Vec::new();
+    let mut missing_folders = 0usize;
+
+    for (rjcode, folder_path) in &works {
+        pb.set_message(format!("Verifying {}", rjcode));
+
+        let results = if checksums {
+            verify_work_checksums(conn, rjcode)
+        } else {
+            verify_work_decode(Path::new(folder_path))
+        };
+
+        let results = match results {
+            Ok(results) => results,
+            Err(e) => {
+                warn!("Cannot verify {} ({}): {}", rjcode, folder_path.display(), e);
+                pb.println(format!("{} ✗ (unreadable)", rjcode));
+                missing_folders += 1;
+                pb.inc(1);
+                continue;
+            }
+        };
+
+        let mut work_ok = true;
+        for (file, status, details) in results {
+            integrity::record_file_integrity(
+                conn,
+                rjcode,
+                &file,
+                status,
+                details.as_deref(),
+            )?;
+            checked += 1;
+
+            if status != IntegrityStatus::Ok {
+                work_ok = false;
+                problems.push((rjcode.clone(), file, status, details.unwrap_or_default()));
+            }
+        }
+
+        pb.println(format!("{} {}", rjcode, if work_ok { "✓" } else { "✗" }));
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+
+    info!("\n=== VERIFY COMPLETE ===");
+    info!(
+        "Works: {} | Files checked: {} | Problems: {} | Unreadable folders: {}",
+        works.len(), checked, problems.len(), missing_folders
+    );
+
+    for (rjcode, file, status, details) in &problems {
+        let file_name = file.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        // ffmpeg can print one line per bad frame; the first one is enough for the summary,
+        // the full output is kept in file_integrity.error_details.
+        let first_line = details.lines().next().unwrap_or("");
+        warn!("[{}] {} {}: {}", status.as_str(), rjcode, file_name, first_line);
+    }
+
+    Ok(())
+}
+
+type FileCheck = (PathBuf, IntegrityStatus, Option<String>);
+
+/// Decode check: runs every audio file at the root of the work folder through ffmpeg.
+fn verify_work_decode(folder_path: &Path) -> Result<Vec<FileCheck>, HvtError> {
+    let results = tagger::list_audio_files(folder_path)?
+        .into_iter()
+        .map(|file| {
+            let (status, details) = match converter::check_audio_integrity(&file) {
+                Ok(None) => (IntegrityStatus::Ok, None),
+                Ok(Some(details)) => (IntegrityStatus::Corrupt, Some(details)),
+                Err(e) => (IntegrityStatus::Error, Some(e.to_string())),
+            };
+            (file, status, details)
+        })
+        .collect();
+    Ok(results)
+}
+
+/// Checksum check: re-hashes every file that had a checksum recorded at tagging time. A changed
+/// hash is reported as `corrupt`, a file that can no longer be read as `error`.
+fn verify_work_checksums(conn: &Connection, rjcode: &RJCode) -> Result<Vec<FileCheck>, HvtError> {
+    let results = integrity::get_recorded_checksums(conn, rjcode)?
+        .into_iter()
+        .map(|(file, expected)| {
+            let (status, details) = match checksum::sha256_file(&file) {
+                Ok(actual) if actual == expected => (IntegrityStatus::Ok, None),
+                Ok(actual) => (
+                    IntegrityStatus::Corrupt,
+                    Some(format!("checksum mismatch (recorded {}, now {})", expected, actual)),
+                ),
+                Err(e) => (IntegrityStatus::Error, Some(e.to_string())),
+            };
+            (file, status, details)
+        })
+        .collect();
+    Ok(results)
+}
@@ -0,0 +1,87 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::folders::types::RJCode;
+use crate::runner::{Operation, Runner};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+/// A re-tag (DLSite fetch + cover + tagging, like `--retag`) started from the web UI or API.
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: u64,
+    pub rjcode: String,
+    pub status: JobStatus,
+    pub error: Option<String>,
+}
+
+/// Jobs started by the server, run one at a time by its `Runner` (see there).
+pub struct JobQueue {
+    jobs: Mutex<Vec<Job>>,
+    next_id: AtomicU64,
+    runner: Arc<Runner>,
+}
+
+impl JobQueue {
+    /// `config` is the server's; jobs never prompt, like `--non-interactive`.
+    pub fn new(db_path: Option<String>, config: &Config) -> Self {
+        Self {
+            jobs: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(1),
+            runner: Arc::new(Runner::new(db_path, config)),
+        }
+    }
+
+    /// Every job of this server run, most recent first
+    pub fn list(&self) -> Vec<Job> {
+        let jobs = self.jobs.lock().expect("jobs mutex poisoned");
+        jobs.iter().rev().cloned().collect()
+    }
+
+    fn set_status(&self, id: u64, status: JobStatus, error: Option<String>) {
+        let mut jobs = self.jobs.lock().expect("jobs mutex poisoned");
+        if let Some(job) = jobs.iter_mut().find(|j| j.id == id) {
+            job.status = status;
+            job.error = error;
+        }
+    }
+
+    /// Queues a re-tag of `rjcode` and returns its job id.
+    pub fn submit_retag(self: &Arc<Self>, rjcode: RJCode) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.jobs.lock().expect("jobs mutex poisoned").push(Job {
+            id,
+            rjcode: rjcode.to_string(),
+            status: JobStatus::Queued,
+            error: None,
+        });
+
+        let operation = Operation::Retag(rjcode.to_string());
+        let (queue, started_code) = (Arc::clone(self), rjcode.clone());
+        let started = move || {
+            queue.set_status(id, JobStatus::Running, None);
+            info!("Job #{}: re-tagging {}", id, started_code);
+        };
+        let queue = Arc::clone(self);
+        let finished = move |outcome: Result<(), String>| match outcome {
+            Ok(()) => queue.set_status(id, JobStatus::Done, None),
+            Err(e) => {
+                warn!("Job #{} ({}) failed: {}", id, rjcode, e);
+                queue.set_status(id, JobStatus::Failed, Some(e));
+            }
+        };
+        self.runner.spawn(operation, None, started, finished);
+        id
+    }
+}
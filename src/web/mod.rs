@@ -1,4 +1,5 @@
 pub mod error;
+pub mod jobs;
 pub mod routes;
 pub mod state;
 
@@ -8,6 +9,7 @@ use rusqlite::Connection;
 use tracing::{info, warn};
 
 use crate::config::Config;
+use jobs::JobQueue;
 use state::AppState;
 
 /// Launches the local web UI server. Owns the `Connection` for the remainder of the process
@@ -17,14 +19,21 @@ use state::AppState;
 /// `bind_override` lets `--ui-bind` override `config.toml`'s `[ui]` bind address/port for a
 /// single run without editing the file — accepts either a bare host (keeps the configured port)
 /// or a full `host:port` string.
+///
+/// With `read_only` (`--read-only`), the editing/trash/delete routes are not registered at all.
+/// `db_path` is reopened by the background re-tag jobs (see `crate::runner::Runner`).
 pub async fn run_ui_workflow(
     db: Connection,
+    db_path: Option<String>,
     config: &Config,
     bind_override: Option<String>,
+    read_only: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let state = AppState {
         db: Arc::new(Mutex::new(db)),
         page_size: config.ui.page_size,
+        read_only,
+        jobs: Arc::new(JobQueue::new(db_path, config)),
     };
     let app = routes::build_router(state);
 
@@ -47,9 +56,13 @@ pub async fn run_ui_workflow(
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
     info!("hvtag web UI listening on http://{}", addr);
+    if read_only {
+        info!("  Read-only: editing, trash and delete actions are disabled");
+    }
     info!("  Works:   http://{}/works", addr);
     info!("  Tags:    http://{}/tags", addr);
     info!("  Circles: http://{}/circles", addr);
+    info!("  API:     http://{}/api/works", addr);
 
     axum::serve(listener, app)
         .with_graceful_shutdown(shutdown_signal())
@@ -0,0 +1,203 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::database::custom_tags;
+use crate::database::review_queue;
+use crate::database::web_queries::{self, WorkFilter, WorkSort, WorkSummary};
+use crate::export;
+use crate::folders::types::RJCode;
+use crate::web::error::AppResult;
+use crate::web::jobs::Job;
+use crate::web::state::AppState;
+
+// JSON API for scripts and remote control. Same data as the HTML pages, same read-only rules
+// (mutations are not registered with `--read-only`).
+
+#[derive(Deserialize)]
+pub struct WorksParams {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    tag: Option<String>,
+    #[serde(default)]
+    circle: Option<String>,
+    #[serde(default)]
+    cv: Option<String>,
+    #[serde(default)]
+    sort: Option<String>,
+    #[serde(default)]
+    page: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct WorksPage {
+    total: i64,
+    page: i64,
+    page_size: i64,
+    works: Vec<WorkSummary>,
+}
+
+fn invalid_rjcode() -> Response {
+    (StatusCode::NOT_FOUND, "Invalid work code").into_response()
+}
+
+/// GET /api/works?q=&tag=&circle=&cv=&sort=&page= — same filters as the works page
+pub async fn list_works(State(state): State<AppState>, Query(params): Query<WorksParams>) -> AppResult<Json<WorksPage>> {
+    let filter = WorkFilter {
+        q: &params.q,
+        tag: params.tag.as_deref().filter(|s| !s.is_empty()),
+        circle: params.circle.as_deref().filter(|s| !s.is_empty()),
+        cv: params.cv.as_deref().filter(|s| !s.is_empty()),
+    };
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = state.page_size.max(1);
+    let conn = state.db.lock().expect("db mutex poisoned");
+    let works = web_queries::list_work_summaries(
+        &conn,
+        &filter,
+        WorkSort::from_param(params.sort.as_deref()),
+        page_size,
+        (page - 1) * page_size,
+    )?;
+    let total = web_queries::count_work_summaries(&conn, &filter)?;
+    Ok(Json(WorksPage { total, page, page_size, works }))
+}
+
+/// GET /api/works/{rjcode} — the `--export json` record of the work
+pub async fn get_work(State(state): State<AppState>, Path(rjcode): Path<String>) -> AppResult<Response> {
+    let Ok(rjcode) = RJCode::new(rjcode) else {
+        return Ok(invalid_rjcode());
+    };
+    let conn = state.db.lock().expect("db mutex poisoned");
+    Ok(match export::build_exported_work(&conn, &rjcode)? {
+        Some(work) => Json(work).into_response(),
+        None => (StatusCode::NOT_FOUND, "Work not found").into_response(),
+    })
+}
+
+#[derive(Serialize)]
+pub struct ErrorsReport {
+    dlsite_errors: Vec<web_queries::UnresolvedError>,
+    review_queue: Vec<ReviewItem>,
+}
+
+#[derive(Serialize)]
+pub struct ReviewItem {
+    rjcode: String,
+    reason: String,
+    details: Option<String>,
+}
+
+/// GET /api/errors — unresolved DLSite fetch errors and the `--review` queue
+pub async fn list_errors(State(state): State<AppState>) -> AppResult<Json<ErrorsReport>> {
+    let conn = state.db.lock().expect("db mutex poisoned");
+    let review_queue = review_queue::get_review_items(&conn)?
+        .into_iter()
+        .map(|(rjcode, reason, details)| ReviewItem { rjcode, reason, details })
+        .collect();
+    Ok(Json(ErrorsReport {
+        dlsite_errors: web_queries::list_unresolved_errors(&conn)?,
+        review_queue,
+    }))
+}
+
+#[derive(Serialize)]
+pub struct TagMapping {
+    tag_id: i64,
+    tag_name: String,
+    custom_name: Option<String>,
+    is_ignored: bool,
+    work_count: i64,
+}
+
+/// GET /api/tags — every DLSite tag with its custom mapping
+pub async fn list_tags(State(state): State<AppState>) -> AppResult<Json<Vec<TagMapping>>> {
+    let conn = state.db.lock().expect("db mutex poisoned");
+    let tags = custom_tags::list_all_dlsite_tags_with_counts(&conn, "dt.tag_name COLLATE NOCASE ASC")?
+        .into_iter()
+        .map(|(tag_id, tag_name, custom_name, is_ignored, work_count)| TagMapping {
+            tag_id,
+            tag_name,
+            custom_name,
+            is_ignored,
+            work_count,
+        })
+        .collect();
+    Ok(Json(tags))
+}
+
+/// Body of PUT /api/tags/{tag_id}: `{"custom_name": "..."}` renames, `{"ignored": true}`
+/// ignores, `{}` resets to the DLSite name.
+#[derive(Deserialize)]
+pub struct TagMappingUpdate {
+    #[serde(default)]
+    custom_name: Option<String>,
+    #[serde(default)]
+    ignored: bool,
+}
+
+/// PUT /api/tags/{tag_id} — edits a tag mapping; the works using the tag are marked for re-tagging
+pub async fn update_tag(
+    State(state): State<AppState>,
+    Path(tag_id): Path<i64>,
+    Json(update): Json<TagMappingUpdate>,
+) -> AppResult<Response> {
+    let conn = state.db.lock().expect("db mutex poisoned");
+    let Some(tag_name) = web_queries::get_tag_name_by_id(&conn, tag_id)? else {
+        return Ok((StatusCode::NOT_FOUND, "Tag not found").into_response());
+    };
+    let custom_name = update.custom_name.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    if update.ignored {
+        custom_tags::ignore_tag(&conn, &tag_name)?;
+    } else if let Some(custom_name) = custom_name {
+        custom_tags::add_custom_tag_mapping(&conn, &tag_name, custom_name)?;
+    } else {
+        custom_tags::remove_custom_tag_mapping(&conn, &tag_name)?;
+    }
+    custom_tags::mark_works_for_retagging(&conn, &tag_name)?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[derive(Serialize)]
+pub struct JobCreated {
+    job_id: u64,
+}
+
+/// Queues a re-tag if the work is in the library
+fn queue_retag(state: &AppState, rjcode: String) -> AppResult<Result<u64, Response>> {
+    let Ok(rjcode) = RJCode::new(rjcode) else {
+        return Ok(Err(invalid_rjcode()));
+    };
+    {
+        let conn = state.db.lock().expect("db mutex poisoned");
+        if !crate::database::queries::rjcode_exists(&conn, &rjcode)? {
+            return Ok(Err((StatusCode::NOT_FOUND, "Work not found").into_response()));
+        }
+    }
+    Ok(Ok(state.jobs.submit_retag(rjcode)))
+}
+
+/// POST /api/works/{rjcode}/retag — fetches the work from DLSite again and re-tags its files
+/// in the background (`--retag`); follow it with GET /api/jobs
+pub async fn retag_work(State(state): State<AppState>, Path(rjcode): Path<String>) -> AppResult<Response> {
+    Ok(match queue_retag(&state, rjcode)? {
+        Ok(job_id) => (StatusCode::ACCEPTED, Json(JobCreated { job_id })).into_response(),
+        Err(response) => response,
+    })
+}
+
+/// POST /works/{rjcode}/retag — the work page's button, answers with a status line
+pub async fn retag_work_button(State(state): State<AppState>, Path(rjcode): Path<String>) -> AppResult<Response> {
+    Ok(match queue_retag(&state, rjcode)? {
+        Ok(job_id) => Html(format!("Re-tag queued (job #{job_id}), see /api/jobs")).into_response(),
+        Err(response) => response,
+    })
+}
+
+/// GET /api/jobs — jobs of this server run, most recent first
+pub async fn list_jobs(State(state): State<AppState>) -> Json<Vec<Job>> {
+    Json(state.jobs.list())
+}
@@ -97,6 +97,7 @@ struct WorksListTemplate {
 #[template(path = "work_detail.html")]
 struct WorkDetailTemplate {
     work: web_queries::WorkDetail,
+    read_only: bool,
 }
 
 /// Runs the search + pagination query and renders just the results partial (shared by the
@@ -180,7 +181,7 @@ pub async fn work_detail_page(
         return Ok((StatusCode::NOT_FOUND, "Work not found").into_response());
     };
 
-    let html = WorkDetailTemplate { work }.render()?;
+    let html = WorkDetailTemplate { work, read_only: state.read_only }.render()?;
     Ok(Html(html).into_response())
 }
 
@@ -202,11 +203,10 @@ pub async fn trash_work(State(state): State<AppState>, Path(rjcode): Path<String
         let conn = state.db.lock().expect("db mutex poisoned");
         web_queries::get_folder_path(&conn, rjcode.as_str())?
     };
-    let Some(folder_path) = folder_path.filter(|p| !p.is_empty()) else {
+    let Some(source) = folder_path.filter(|p| !p.as_os_str().is_empty()) else {
         return Ok((StatusCode::NOT_FOUND, "Work not found or has no folder path").into_response());
     };
 
-    let source = std::path::PathBuf::from(&folder_path);
     let Some(parent) = source.parent() else {
         return Ok((StatusCode::INTERNAL_SERVER_ERROR, "Folder has no parent directory").into_response());
     };
@@ -222,7 +222,7 @@ pub async fn trash_work(State(state): State<AppState>, Path(rjcode): Path<String
 
     {
         let conn = state.db.lock().expect("db mutex poisoned");
-        web_queries::deactivate_and_relocate_work(&conn, &rjcode, &target.to_string_lossy())?;
+        web_queries::deactivate_and_relocate_work(&conn, &rjcode, &target)?;
     }
 
     Ok((StatusCode::OK, [("HX-Redirect", "/works")]).into_response())
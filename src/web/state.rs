@@ -2,6 +2,8 @@ use std::sync::{Arc, Mutex};
 
 use rusqlite::Connection;
 
+use crate::web::jobs::JobQueue;
+
 /// Shared state for all web UI handlers. `Connection` is `Send` but not `Sync`, and axum
 /// handlers run concurrently across tokio tasks, so it's wrapped in a mutex. Every handler's
 /// DB access is a quick synchronous local SQLite call that never spans an `.await`, so a plain
@@ -11,4 +13,8 @@ use rusqlite::Connection;
 pub struct AppState {
     pub db: Arc<Mutex<Connection>>,
     pub page_size: i64,
+    /// `--read-only`: mutation routes are not registered (see `routes::build_router`).
+    pub read_only: bool,
+    /// Re-tags started from the UI/API, run in the background one at a time.
+    pub jobs: Arc<JobQueue>,
 }
@@ -0,0 +1,330 @@
+use dialoguer::{Select, Input, Confirm, theme::ColorfulTheme};
+use rusqlite::Connection;
+
+use crate::config::Config;
+use crate::database::work_groups::{self, WorkGroupMembership};
+use crate::database::work_overrides::{self, OverrideField, WorkOverrides};
+use crate::database::{queries, search};
+use crate::errors::HvtError;
+use crate::folders::types::RJCode;
+use crate::tagger::{self, id3_handler, types::{AudioMetadata, TaggerConfig}};
+
+/// Lists are edited on one line with this separator
+const LIST_SEPARATOR: &str = "; ";
+
+/// Search results shown at most
+const MAX_HITS: usize = 50;
+
+/// `--manage-works`: pick a work (search by RJ code, title, circle, CV or tag), view its metadata
+/// as it will be tagged, and override its title, circle, CVs, tags or release date. Overrides
+/// only apply to that work, on top of the global mappings, and mark it for re-tagging. Works split
+/// into one folder per disc can also be put in a work group, tagged as one album.
+pub fn run_interactive_work_manager(conn: &Connection, app_config: &Config) -> Result<(), HvtError> {
+    let tagger_config = TaggerConfig {
+        tag_separator: app_config.tagger.get_separator(),
+        cv_name_style: app_config.tagger.cv_name_style,
+        title_language: app_config.tagger.title_language,
+        ..Default::default()
+    };
+    search::rebuild_search_index(conn)?;
+
+    loop {
+        let query: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt("Search a work (RJ code, title, circle, CV, tag; empty to exit)")
+            .allow_empty(true)
+            .interact_text()
+            .map_err(|e| HvtError::Parse(format!("Input error: {}", e)))?;
+        if query.trim().is_empty() {
+            println!("Exiting work manager...");
+            break;
+        }
+
+        let hits = search::search_works(conn, query.trim())?;
+        if hits.is_empty() {
+            println!("No work matches '{}'.", query.trim());
+            continue;
+        }
+        let rjcode = if hits.len() == 1 {
+            hits[0].rjcode.clone()
+        } else {
+            let displays: Vec<String> = hits.iter()
+                .take(MAX_HITS)
+                .map(|hit| format!("{}  {}", hit.rjcode, hit.name))
+                .collect();
+            if hits.len() > MAX_HITS {
+                println!("{} matches, showing the first {}", hits.len(), MAX_HITS);
+            }
+            let selection = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Select a work")
+                .items(&displays)
+                .default(0)
+                .interact()
+                .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+            hits[selection].rjcode.clone()
+        };
+
+        manage_work(conn, &RJCode::new(rjcode)?, &tagger_config)?;
+    }
+    Ok(())
+}
+
+fn field_label(field: OverrideField) -> &'static str {
+    match field {
+        OverrideField::Title => "Title",
+        OverrideField::Circle => "Circle",
+        OverrideField::Cvs => "CVs",
+        OverrideField::Tags => "Tags",
+        OverrideField::ReleaseDate => "Release date",
+    }
+}
+
+fn is_overridden(overrides: &WorkOverrides, field: OverrideField) -> bool {
+    match field {
+        OverrideField::Title => overrides.title.is_some(),
+        OverrideField::Circle => overrides.circle.is_some(),
+        OverrideField::Cvs => overrides.cvs.is_some(),
+        OverrideField::Tags => overrides.tags.is_some(),
+        OverrideField::ReleaseDate => overrides.release_date.is_some(),
+    }
+}
+
+/// The value written to the files, as edited on one line
+fn current_value(metadata: &AudioMetadata, field: OverrideField) -> String {
+    match field {
+        OverrideField::Title => metadata.title.clone(),
+        OverrideField::Circle => metadata.album_artist.clone(),
+        OverrideField::Cvs => metadata.artists.join(LIST_SEPARATOR),
+        OverrideField::Tags => metadata.all_tags().join(LIST_SEPARATOR),
+        OverrideField::ReleaseDate => metadata.date.clone().unwrap_or_default(),
+    }
+}
+
+fn manage_work(conn: &Connection, rjcode: &RJCode, tagger_config: &TaggerConfig) -> Result<(), HvtError> {
+    loop {
+        let metadata = tagger::fetch_metadata_from_db(conn, rjcode, tagger_config)?;
+        let overrides = work_overrides::get_work_overrides(conn, rjcode)?;
+
+        println!("\n=== {} ===", rjcode);
+        for field in OverrideField::ALL {
+            let marker = if is_overridden(&overrides, field) { "  (override)" } else { "" };
+            println!("  {:<13} {}{}", format!("{}:", field_label(field)), current_value(&metadata, field), marker);
+        }
+        if let Some(group) = work_groups::get_work_group(conn, rjcode)? {
+            println!("  {:<13} {} (disc {}/{})", "Work group:", group.name, group.disc_number, group.total_discs);
+        }
+        println!();
+
+        let mut options: Vec<String> = OverrideField::ALL.iter()
+            .map(|field| format!("Override {}", field_label(*field).to_lowercase()))
+            .collect();
+        options.push("Work group (album split across folders)".to_string());
+        options.push("Clear an override".to_string());
+        options.push("Back".to_string());
+        let selection = Select::with_theme(&ColorfulTheme::default())
+            .with_prompt("Work Manager")
+            .items(&options)
+            .default(0)
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+        if selection == options.len() - 1 {
+            break;
+        }
+
+        if selection == options.len() - 3 {
+            manage_work_group(conn, rjcode, &metadata.title)?;
+            continue;
+        }
+
+        if selection == options.len() - 2 {
+            let overridden: Vec<OverrideField> = OverrideField::ALL.into_iter()
+                .filter(|field| is_overridden(&overrides, *field))
+                .collect();
+            if overridden.is_empty() {
+                println!("No override on this work.");
+                continue;
+            }
+            let displays: Vec<&str> = overridden.iter().map(|field| field_label(*field)).collect();
+            let chosen = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Clear which override (back to the DLSite value)?")
+                .items(&displays)
+                .default(0)
+                .interact()
+                .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+            work_overrides::clear_work_override(conn, rjcode, overridden[chosen])?;
+            println!("\n✓ {} override cleared", field_label(overridden[chosen]));
+            mark_for_retagging(conn, rjcode)?;
+            continue;
+        }
+
+        let field = OverrideField::ALL[selection];
+        let prompt = if field.is_list() {
+            format!("{} (separated by '{}')", field_label(field), LIST_SEPARATOR.trim())
+        } else if field == OverrideField::ReleaseDate {
+            "Release date (YYYY-MM-DD)".to_string()
+        } else {
+            field_label(field).to_string()
+        };
+        let value: String = Input::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
+            .with_initial_text(current_value(&metadata, field))
+            .allow_empty(true)
+            .interact_text()
+            .map_err(|e| HvtError::Parse(format!("Input error: {}", e)))?;
+
+        // An empty list is a valid override (no CV or no tag); an empty title, circle or date isn't
+        let values: Vec<String> = if field.is_list() {
+            value.split(LIST_SEPARATOR.trim()).map(str::trim).filter(|v| !v.is_empty()).map(String::from).collect()
+        } else if value.trim().is_empty() {
+            println!("{} cannot be empty (use \"Clear an override\" to go back to the DLSite value).", field_label(field));
+            continue;
+        } else {
+            vec![value.trim().to_string()]
+        };
+        if field == OverrideField::ReleaseDate && id3_handler::parse_release_date(&values[0]).is_none() {
+            println!("Invalid date '{}', expected YYYY-MM-DD.", values[0]);
+            continue;
+        }
+        if values.join(LIST_SEPARATOR) == current_value(&metadata, field) && is_overridden(&overrides, field) {
+            continue;
+        }
+
+        let confirm = Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Set {} of {} to '{}'?", field_label(field).to_lowercase(), rjcode, values.join(LIST_SEPARATOR)))
+            .default(true)
+            .interact()
+            .map_err(|e| HvtError::Parse(format!("Confirmation error: {}", e)))?;
+        if !confirm {
+            continue;
+        }
+        work_overrides::set_work_override(conn, rjcode, field, &values)?;
+        println!("\n✓ {} overridden", field_label(field));
+        mark_for_retagging(conn, rjcode)?;
+    }
+    Ok(())
+}
+
+/// Puts a work in a group (a new one, named after the work by default, or an existing one), or
+/// moves it to another disc, renames its group or takes it out. Every work of the groups
+/// involved is marked for re-tagging, as their album or disc numbers change.
+fn manage_work_group(conn: &Connection, rjcode: &RJCode, title: &str) -> Result<(), HvtError> {
+    let current = work_groups::get_work_group(conn, rjcode)?;
+    let groups: Vec<(i64, String, i64)> = work_groups::list_groups(conn)?
+        .into_iter()
+        .filter(|(id, _, _)| current.as_ref().map(|g| g.group_id) != Some(*id))
+        .collect();
+
+    let mut options = vec!["Create a new group".to_string()];
+    if !groups.is_empty() {
+        options.push("Join an existing group".to_string());
+    }
+    if current.is_some() {
+        options.push("Change the disc number".to_string());
+        options.push("Rename the group".to_string());
+        options.push("Leave the group".to_string());
+    }
+    options.push("Back".to_string());
+    let selection = Select::with_theme(&ColorfulTheme::default())
+        .with_prompt("Work group")
+        .items(&options)
+        .default(0)
+        .interact()
+        .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+
+    // Works whose album or disc numbers may change
+    let mut affected = match &current {
+        Some(group) => work_groups::get_group_works(conn, group.group_id)?,
+        None => vec![rjcode.to_string()],
+    };
+    match options[selection].as_str() {
+        "Create a new group" => {
+            let name = input_group_name(title)?;
+            if groups.iter().any(|(_, n, _)| *n == name) || current.as_ref().is_some_and(|g| g.name == name) {
+                println!("A group named '{}' already exists, join it instead.", name);
+                return Ok(());
+            }
+            let group_id = work_groups::create_group(conn, &name)?;
+            work_groups::add_work_to_group(conn, rjcode, group_id)?;
+            println!("\n✓ {} is disc 1 of '{}'", rjcode, name);
+        }
+        "Join an existing group" => {
+            let displays: Vec<String> = groups.iter()
+                .map(|(_, name, works)| format!("{} ({} disc(s))", name, works))
+                .collect();
+            let chosen = Select::with_theme(&ColorfulTheme::default())
+                .with_prompt("Join which group? (the work becomes its last disc)")
+                .items(&displays)
+                .default(0)
+                .interact()
+                .map_err(|e| HvtError::Parse(format!("Selection error: {}", e)))?;
+            let (group_id, name, _) = &groups[chosen];
+            work_groups::add_work_to_group(conn, rjcode, *group_id)?;
+            affected.extend(work_groups::get_group_works(conn, *group_id)?);
+            print_membership(conn, rjcode, name)?;
+        }
+        "Change the disc number" => {
+            let Some(group) = &current else { return Ok(()) };
+            let disc: u32 = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Disc number (1-{})", group.total_discs))
+                .with_initial_text(group.disc_number.to_string())
+                .validate_with(|n: &u32| if (1..=group.total_discs).contains(n) { Ok(()) } else { Err("out of range") })
+                .interact_text()
+                .map_err(|e| HvtError::Parse(format!("Input error: {}", e)))?;
+            if disc == group.disc_number {
+                return Ok(());
+            }
+            work_groups::set_disc_number(conn, rjcode, disc)?;
+            print_membership(conn, rjcode, &group.name)?;
+        }
+        "Rename the group" => {
+            let Some(group) = &current else { return Ok(()) };
+            let name = input_group_name(&group.name)?;
+            if name == group.name {
+                return Ok(());
+            }
+            if groups.iter().any(|(_, n, _)| *n == name) {
+                println!("A group named '{}' already exists.", name);
+                return Ok(());
+            }
+            work_groups::rename_group(conn, group.group_id, &name)?;
+            println!("\n✓ Group renamed to '{}'", name);
+        }
+        "Leave the group" => {
+            work_groups::remove_work_from_group(conn, rjcode)?;
+            println!("\n✓ {} left its group", rjcode);
+        }
+        _ => return Ok(()),
+    }
+
+    affected.sort();
+    affected.dedup();
+    for work in &affected {
+        mark_for_retagging(conn, &RJCode::new(work.clone())?)?;
+    }
+    Ok(())
+}
+
+fn input_group_name(initial: &str) -> Result<String, HvtError> {
+    let name: String = Input::with_theme(&ColorfulTheme::default())
+        .with_prompt("Group name (album of its works)")
+        .with_initial_text(initial)
+        .interact_text()
+        .map_err(|e| HvtError::Parse(format!("Input error: {}", e)))?;
+    Ok(name.trim().to_string())
+}
+
+fn print_membership(conn: &Connection, rjcode: &RJCode, name: &str) -> Result<(), HvtError> {
+    if let Some(WorkGroupMembership { disc_number, total_discs, .. }) = work_groups::get_work_group(conn, rjcode)? {
+        println!("\n✓ {} is disc {}/{} of '{}'", rjcode, disc_number, total_discs, name);
+    }
+    Ok(())
+}
+
+fn mark_for_retagging(conn: &Connection, rjcode: &RJCode) -> Result<(), HvtError> {
+    let files_marked = queries::mark_work_for_retagging(conn, rjcode)?;
+    if files_marked > 0 {
+        println!("✓ {} file(s) marked for re-tagging", files_marked);
+        println!("  Run --tag to apply the changes");
+    }
+    Ok(())
+}